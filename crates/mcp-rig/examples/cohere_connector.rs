@@ -2,9 +2,8 @@ use std::env;
 
 use mcp_rig::{
     completion::{Completion, Prompt},
-    providers::cohere::Client as CohereClient,
+    providers::cohere::{ChatConnector, ChatOptions, Client as CohereClient},
 };
-use serde_json::json;
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
@@ -15,9 +14,10 @@ async fn main() -> Result<(), anyhow::Error> {
     let klimadao_agent = cohere_client
         .agent("command-r")
         .temperature(0.0)
-        .additional_params(json!({
-            "connectors": [{"id":"web-search", "options":{"site": "https://docs.klimadao.finance"}}]
-        }))
+        .additional_params(ChatOptions {
+            connectors: vec![ChatConnector::web_search().site("https://docs.klimadao.finance")],
+            ..Default::default()
+        })
         .build();
 
     // Prompt the model and print the response
@@ -35,9 +35,10 @@ async fn main() -> Result<(), anyhow::Error> {
     let response = klimadao_agent
         .completion("Tell me about BCT tokens?", vec![])
         .await?
-        .additional_params(json!({
-            "connectors": [{"id":"web-search", "options":{"site": "https://docs.klimadao.finance"}}]
-        }))
+        .additional_params(ChatOptions {
+            connectors: vec![ChatConnector::web_search().site("https://docs.klimadao.finance")],
+            ..Default::default()
+        })
         .send()
         .await?;
 
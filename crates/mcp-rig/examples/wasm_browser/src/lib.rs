@@ -0,0 +1,46 @@
+//! Browser example: an OpenAI agent running client-side, compiled to wasm32-unknown-unknown.
+//!
+//! The API key is passed in by the page: never ship a real API key to untrusted users, use a
+//! proxy issuing short-lived keys instead (see `Client::from_url`).
+use mcp_rig::{
+    agent::Agent,
+    completion::{Chat, Message},
+    providers::openai,
+};
+use wasm_bindgen::prelude::*;
+
+/// Chat session with an agent, exposed to JavaScript.
+#[wasm_bindgen]
+pub struct ChatSession {
+    agent: Agent<openai::CompletionModel>,
+    history: Vec<Message>,
+}
+
+#[wasm_bindgen]
+impl ChatSession {
+    #[wasm_bindgen(constructor)]
+    pub fn new(api_key: &str, preamble: &str) -> ChatSession {
+        let agent = openai::Client::new(api_key)
+            .agent(openai::GPT_4O_MINI)
+            .preamble(preamble)
+            .build();
+
+        ChatSession {
+            agent,
+            history: vec![],
+        }
+    }
+
+    /// Send a message to the agent and return its response.
+    pub async fn send(&mut self, message: String) -> Result<String, JsError> {
+        let response = self
+            .agent
+            .chat(message.as_str(), self.history.clone())
+            .await
+            .map_err(|e| JsError::new(&e.to_string()))?;
+
+        self.history.push(Message::user(message));
+        self.history.push(Message::assistant(response.clone()));
+        Ok(response)
+    }
+}
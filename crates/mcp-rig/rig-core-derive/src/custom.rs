@@ -1,7 +1,7 @@
 use quote::ToTokens;
 use syn::{meta::ParseNestedMeta, ExprPath};
 
-use crate::EMBED;
+use crate::{weighted::WEIGHT, EMBED};
 
 const EMBED_WITH: &str = "embed_with";
 
@@ -56,11 +56,18 @@ impl CustomAttributeParser for syn::Attribute {
             return Ok(false);
         }
 
+        let mut embed_with = false;
+        let mut weight = false;
+
         self.parse_nested_meta(|meta| {
             // Parse the meta attribute as an expression. Need this to compile.
             meta.value()?.parse::<syn::Expr>()?;
 
             if meta.path.is_ident(EMBED_WITH) {
+                embed_with = true;
+                Ok(())
+            } else if meta.path.is_ident(WEIGHT) {
+                weight = true;
                 Ok(())
             } else {
                 let path = meta.path.to_token_stream().to_string().replace(' ', "");
@@ -71,7 +78,14 @@ impl CustomAttributeParser for syn::Attribute {
             }
         })?;
 
-        Ok(true)
+        if embed_with && weight {
+            return Err(syn::Error::new_spanned(
+                self,
+                format_args!("`{}` cannot be combined with `{}`", WEIGHT, EMBED_WITH),
+            ));
+        }
+
+        Ok(embed_with)
     }
 
     fn expand_tag(&self) -> syn::Result<syn::ExprPath> {
@@ -1,20 +1,31 @@
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::DataStruct;
+use syn::{DataStruct, LitStr};
 
 use crate::{
     basic::{add_struct_bounds, basic_embed_fields},
     custom::custom_embed_fields,
+    template::{struct_template, template_fields},
+    weighted::weighted_embed_fields,
 };
 
 pub(crate) fn expand_derive_embedding(input: &mut syn::DeriveInput) -> syn::Result<TokenStream> {
     let name = &input.ident;
+    let template = struct_template(&input.attrs)?;
     let data = &input.data;
     let generics = &mut input.generics;
 
     let target_stream = match data {
         syn::Data::Struct(data_struct) => {
-            let (basic_targets, basic_target_size) = data_struct.basic(generics);
+            let weighted_fields = weighted_embed_fields(data_struct)?;
+
+            let (basic_targets, basic_target_size) = match &template {
+                Some(template) => data_struct.templated(generics, template, &weighted_fields)?,
+                None if !weighted_fields.is_empty() => {
+                    data_struct.weighted(generics, &weighted_fields)
+                }
+                None => data_struct.basic(generics),
+            };
             let (custom_targets, custom_target_size) = data_struct.custom()?;
 
             // If there are no fields tagged with `#[embed]` or `#[embed(embed_with = "...")]`, return an empty TokenStream.
@@ -62,6 +73,43 @@ trait StructParser {
 
     // Handles fields tagged with `#[embed(embed_with = "...")]`
     fn custom(&self) -> syn::Result<(TokenStream, usize)>;
+
+    // Handles structs where at least one field is tagged with `#[embed(weight = N)]`
+    fn weighted(
+        &self,
+        generics: &mut syn::Generics,
+        weighted_fields: &[(&syn::Field, usize)],
+    ) -> (TokenStream, usize);
+
+    // Handles structs tagged with `#[embed(template = "...")]`
+    fn templated(
+        &self,
+        generics: &mut syn::Generics,
+        template: &LitStr,
+        weighted_fields: &[(&syn::Field, usize)],
+    ) -> syn::Result<(TokenStream, usize)>;
+}
+
+/// Weight of the field (ie. the N in `#[embed(weight = N)]`), 1 if the field has no weight.
+fn field_weight(field: &syn::Field, weighted_fields: &[(&syn::Field, usize)]) -> usize {
+    weighted_fields
+        .iter()
+        .find(|(weighted_field, _)| weighted_field.ident == field.ident)
+        .map(|(_, weight)| *weight)
+        .unwrap_or(1)
+}
+
+/// Expression that evaluates to the text of the field: the texts returned by the `Embed`
+/// implementation of the field joined by spaces, repeated `weight` times.
+fn field_text(field: &syn::Field, weight: usize) -> TokenStream {
+    let field_name = &field.ident;
+
+    quote! {
+        {
+            let text = mcp_rig::embeddings::to_texts(&self.#field_name)?.join(" ");
+            vec![text; #weight].join(" ")
+        }
+    }
 }
 
 impl StructParser for DataStruct {
@@ -107,4 +155,64 @@ impl StructParser for DataStruct {
             embed_targets.len(),
         ))
     }
+
+    fn weighted(
+        &self,
+        generics: &mut syn::Generics,
+        weighted_fields: &[(&syn::Field, usize)],
+    ) -> (TokenStream, usize) {
+        let basic_fields = basic_embed_fields(self).collect::<Vec<_>>();
+
+        let field_texts = self
+            .fields
+            .iter()
+            // Iterate over every field tagged with `#[embed]` or `#[embed(weight = N)]`, in declaration order
+            .filter(|field| {
+                basic_fields.iter().any(|basic| basic.ident == field.ident)
+                    || weighted_fields
+                        .iter()
+                        .any(|(weighted, _)| weighted.ident == field.ident)
+            })
+            .map(|field| {
+                add_struct_bounds(generics, &field.ty);
+                field_text(field, field_weight(field, weighted_fields))
+            })
+            .collect::<Vec<_>>();
+
+        (
+            quote! {
+                embedder.embed(vec![#(#field_texts),*].join("\n"))
+            },
+            field_texts.len(),
+        )
+    }
+
+    fn templated(
+        &self,
+        generics: &mut syn::Generics,
+        template: &LitStr,
+        weighted_fields: &[(&syn::Field, usize)],
+    ) -> syn::Result<(TokenStream, usize)> {
+        let args = template_fields(self, template)?
+            // Iterate over every field referenced in the template
+            .into_iter()
+            .map(|field| {
+                add_struct_bounds(generics, &field.ty);
+
+                let field_name = &field.ident;
+                let text = field_text(field, field_weight(field, weighted_fields));
+
+                quote! {
+                    #field_name = #text
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok((
+            quote! {
+                embedder.embed(format!(#template, #(#args),*))
+            },
+            1,
+        ))
+    }
 }
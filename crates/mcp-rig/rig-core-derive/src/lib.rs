@@ -1,13 +1,25 @@
 extern crate proc_macro;
 use proc_macro::TokenStream;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, DeriveInput, ItemFn};
 
 mod basic;
 mod custom;
 mod embed;
+mod template;
+mod tool;
+mod wasm_send;
+mod weighted;
 
 pub(crate) const EMBED: &str = "embed";
 
+/// Supported attributes:
+/// - `#[embed]` on a field: the field is embedded using its own `Embed` implementation.
+/// - `#[embed(embed_with = "...")]` on a field: the field is embedded using the given function.
+/// - `#[embed(weight = N)]` on a field: the text of the field is repeated `N` times. If any field
+///   is weighted, the `#[embed]` and weighted fields are combined into a single text (one line per field).
+/// - `#[embed(template = "...")]` on the struct: the fields referenced by the template placeholders
+///   (e.g.: `"Title: {title}\n{body}"`) are combined into a single text.
+///
 /// References:
 /// <https://doc.rust-lang.org/book/ch19-06-macros.html#how-to-write-a-custom-derive-macro>
 /// <https://doc.rust-lang.org/reference/procedural-macros.html>
@@ -19,3 +31,51 @@ pub fn derive_embedding_trait(item: TokenStream) -> TokenStream {
         .unwrap_or_else(syn::Error::into_compile_error)
         .into()
 }
+
+/// Makes the future of an async function `Send` on wasm32 targets (where futures holding
+/// browser resources, e.g.: `reqwest` requests, are not `Send`), so that it can implement the
+/// async traits of rig. Internal to rig: the expanded code refers to `crate::wasm_compat`.
+///
+/// Usage: `#[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]`
+#[proc_macro_attribute]
+pub fn wasm_send(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as ItemFn);
+
+    wasm_send::expand_wasm_send(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Turns an async function into a tool: a unit struct named after the function (in PascalCase)
+/// implementing `mcp_rig::tool::Tool`, with an arguments struct (`<Name>Args`) holding the
+/// parameters of the function.
+///
+/// - The doc comment of the function becomes the description of the tool, and the doc comments
+///   of the parameters the descriptions of the arguments (in the JSON schema of the arguments).
+/// - The parameters must be owned types implementing `serde::Deserialize` and
+///   `schemars::JsonSchema`, and the function must return a `Result<Output, Error>`.
+/// - `#[tool(name = "...")]` sets the name of the tool (the name of the function by default).
+///
+/// # Example
+/// ```rust,ignore
+/// /// Add x and y together
+/// #[tool]
+/// async fn add(
+///     /// The first number to add
+///     x: i32,
+///     /// The second number to add
+///     y: i32,
+/// ) -> Result<i32, MathError> {
+///     Ok(x + y)
+/// }
+///
+/// let agent = openai.agent("gpt-4o").tool(Add).build();
+/// ```
+#[proc_macro_attribute]
+pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as ItemFn);
+
+    tool::expand_tool(attr.into(), input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
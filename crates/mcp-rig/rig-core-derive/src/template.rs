@@ -0,0 +1,84 @@
+use std::collections::HashSet;
+
+use syn::{DataStruct, LitStr};
+
+use crate::EMBED;
+
+const TEMPLATE: &str = "template";
+
+/// Finds and returns the "..." part of the struct-level #[embed(template = "...")] attribute, if any.
+pub(crate) fn struct_template(attrs: &[syn::Attribute]) -> syn::Result<Option<LitStr>> {
+    let mut template = None;
+
+    for attribute in attrs.iter().filter(|attr| attr.path().is_ident(EMBED)) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident(TEMPLATE) {
+                template = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error(format_args!(
+                    "unknown embedding struct attribute, expected `{} = \"...\"`",
+                    TEMPLATE
+                )))
+            }
+        })?;
+    }
+
+    Ok(template)
+}
+
+/// Finds the fields referenced by the placeholders of the template (ie. `{field}` or `{field:?}`),
+/// in order of first appearance.
+/// Returns an error if a placeholder is positional or does not refer to a field of the struct.
+pub(crate) fn template_fields<'a>(
+    data_struct: &'a DataStruct,
+    template: &LitStr,
+) -> syn::Result<Vec<&'a syn::Field>> {
+    let mut names = Vec::new();
+    let mut seen = HashSet::new();
+
+    let value = template.value();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '{' => {
+                let name = chars
+                    .by_ref()
+                    .take_while(|c| *c != '}')
+                    .collect::<String>()
+                    .split(':')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string();
+
+                if seen.insert(name.clone()) {
+                    names.push(name);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    names
+        .into_iter()
+        .map(|name| {
+            data_struct
+                .fields
+                .iter()
+                .find(|field| field.ident.as_ref().is_some_and(|ident| *ident == name))
+                .ok_or_else(|| {
+                    syn::Error::new_spanned(
+                        template,
+                        format!(
+                            "template placeholder `{{{}}}` must be a field of the struct",
+                            name
+                        ),
+                    )
+                })
+        })
+        .collect()
+}
@@ -0,0 +1,187 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse::Parser, Attribute, Expr, ExprLit, FnArg, GenericArgument, ItemFn, Lit, LitStr, Pat,
+    PathArguments, ReturnType, Type,
+};
+
+const NAME: &str = "name";
+
+/// Finds the "..." part of the `#[tool(name = "...")]` attribute, if any.
+fn tool_name(attr: TokenStream) -> syn::Result<Option<LitStr>> {
+    let mut name = None;
+
+    syn::meta::parser(|meta| {
+        if meta.path.is_ident(NAME) {
+            name = Some(meta.value()?.parse::<LitStr>()?);
+            Ok(())
+        } else {
+            Err(meta.error(format_args!(
+                "unknown tool attribute, expected `{} = \"...\"`",
+                NAME
+            )))
+        }
+    })
+    .parse2(attr)?;
+
+    Ok(name)
+}
+
+/// Joins the lines of the doc comments of `attrs` (i.e.: their `#[doc = "..."]` attributes).
+fn doc_comment(attrs: &[Attribute]) -> String {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta.require_name_value().ok()?.value {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(doc), ..
+            }) => Some(doc.value().trim().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Splits the `Result<O, E>` return type of the function into `O` and `E`.
+fn result_types(output: &ReturnType) -> syn::Result<(Type, Type)> {
+    let error = || {
+        syn::Error::new_spanned(
+            output,
+            "`tool` functions should return a `Result<Output, Error>`",
+        )
+    };
+
+    let ReturnType::Type(_, ty) = output else {
+        return Err(error());
+    };
+    let Type::Path(path) = &**ty else {
+        return Err(error());
+    };
+    let segment = path.path.segments.last().ok_or_else(error)?;
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return Err(error());
+    };
+
+    let mut types = args.args.iter().filter_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty.clone()),
+        _ => None,
+    });
+    match (segment.ident == "Result", types.next(), types.next()) {
+        (true, Some(output), Some(error)) => Ok((output, error)),
+        _ => Err(error()),
+    }
+}
+
+/// Converts a snake_case function name to the PascalCase name of its tool struct.
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn expand_tool(attr: TokenStream, mut item: ItemFn) -> syn::Result<TokenStream> {
+    if item.sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            item.sig.fn_token,
+            "`tool` can only be applied to async functions",
+        ));
+    }
+    if !item.sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item.sig.generics,
+            "`tool` functions cannot be generic",
+        ));
+    }
+
+    let fn_name = item.sig.ident.clone();
+    let vis = item.vis.clone();
+    let name =
+        tool_name(attr)?.unwrap_or_else(|| LitStr::new(&fn_name.to_string(), fn_name.span()));
+    let description = doc_comment(&item.attrs);
+    let (output, error) = result_types(&item.sig.output)?;
+
+    let struct_name = format_ident!("{}", pascal_case(&fn_name.to_string()));
+    let args_name = format_ident!("{}Args", struct_name);
+
+    let mut fields = vec![];
+    let mut field_names = vec![];
+    for input in item.sig.inputs.iter_mut() {
+        let FnArg::Typed(input) = input else {
+            return Err(syn::Error::new_spanned(
+                input,
+                "`tool` functions cannot take `self`",
+            ));
+        };
+
+        // The doc comments of the parameters become the descriptions of the arguments, and
+        // are removed from the function (where they are not allowed)
+        let docs = input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"))
+            .cloned()
+            .collect::<Vec<_>>();
+        input.attrs.retain(|attr| !attr.path().is_ident("doc"));
+
+        let Pat::Ident(pat) = &*input.pat else {
+            return Err(syn::Error::new_spanned(
+                &input.pat,
+                "`tool` function parameters should be identifiers",
+            ));
+        };
+        let ident = &pat.ident;
+        let ty = &input.ty;
+        fields.push(quote! {
+            #(#docs)*
+            pub #ident: #ty
+        });
+        field_names.push(ident.clone());
+    }
+
+    let args_doc = format!("Arguments of the [{}] tool.", struct_name);
+    let struct_doc = format!("Tool calling [{}].", fn_name);
+
+    Ok(quote! {
+        #item
+
+        #[doc = #struct_doc]
+        #[derive(Clone, Copy, Debug, Default)]
+        #vis struct #struct_name;
+
+        #[doc = #args_doc]
+        #[derive(mcp_rig::__private::serde::Deserialize, mcp_rig::__private::schemars::JsonSchema)]
+        #[serde(crate = "mcp_rig::__private::serde")]
+        #[schemars(crate = "mcp_rig::__private::schemars")]
+        #vis struct #args_name {
+            #(#fields),*
+        }
+
+        impl mcp_rig::tool::Tool for #struct_name {
+            const NAME: &'static str = #name;
+
+            type Error = #error;
+            type Args = #args_name;
+            type Output = #output;
+
+            async fn definition(&self, _prompt: String) -> mcp_rig::completion::ToolDefinition {
+                mcp_rig::completion::ToolDefinition {
+                    name: Self::NAME.to_string(),
+                    description: #description.to_string(),
+                    parameters: mcp_rig::tool::parameters_schema::<#args_name>(),
+                }
+            }
+
+            async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+                mcp_rig::tool::SyncFuture::new(#fn_name(#(args.#field_names),*)).await
+            }
+        }
+    })
+}
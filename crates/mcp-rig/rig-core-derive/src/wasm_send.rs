@@ -0,0 +1,20 @@
+use proc_macro2::TokenStream;
+use quote::ToTokens;
+use syn::{parse_quote, ItemFn};
+
+/// Wraps the body of an async function in a future that is `Send` on wasm32 targets.
+pub(crate) fn expand_wasm_send(mut item: ItemFn) -> syn::Result<TokenStream> {
+    if item.sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            item.sig.fn_token,
+            "`wasm_send` can only be applied to async functions",
+        ));
+    }
+
+    let block = &item.block;
+    item.block = parse_quote!({
+        crate::wasm_compat::WasmSendFuture::new(async move #block).await
+    });
+
+    Ok(item.into_token_stream())
+}
@@ -0,0 +1,56 @@
+use syn::DataStruct;
+
+use crate::EMBED;
+
+pub(crate) const WEIGHT: &str = "weight";
+
+/// Finds and returns fields with #[embed(weight = N)] attribute tags only.
+/// Also returns the N part of the tag (ie. the weight of the field).
+pub(crate) fn weighted_embed_fields(
+    data_struct: &DataStruct,
+) -> syn::Result<Vec<(&syn::Field, usize)>> {
+    data_struct
+        .fields
+        .iter()
+        .filter_map(|field| {
+            field
+                .attrs
+                .iter()
+                .filter(|attribute| {
+                    attribute.path().is_ident(EMBED)
+                        && matches!(&attribute.meta, syn::Meta::List(meta) if !meta.tokens.is_empty())
+                })
+                .find_map(|attribute| match field_weight(attribute) {
+                    Ok(Some(weight)) => Some(Ok((field, weight))),
+                    Ok(None) => None,
+                    Err(e) => Some(Err(e)),
+                })
+        })
+        .collect()
+}
+
+/// Get the N part of the #[embed(weight = N)] attribute, if present.
+fn field_weight(attribute: &syn::Attribute) -> syn::Result<Option<usize>> {
+    let mut weight = None;
+
+    attribute.parse_nested_meta(|meta| {
+        let value = meta.value()?;
+        if meta.path.is_ident(WEIGHT) {
+            let lit = value.parse::<syn::LitInt>()?;
+            let n = lit.base10_parse::<usize>()?;
+            if n == 0 {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "expected weight to be a positive integer",
+                ));
+            }
+            weight = Some(n);
+        } else {
+            // Other tags are validated by their own parsers.
+            value.parse::<syn::Expr>()?;
+        }
+        Ok(())
+    })?;
+
+    Ok(weight)
+}
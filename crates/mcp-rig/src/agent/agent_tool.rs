@@ -0,0 +1,186 @@
+//! Agents used as the tools of other agents, for hierarchical setups (e.g.: a writer agent
+//! delegating to researcher and critic agents).
+//!
+//! An [AgentTool] prompts its agent with the arguments of the tool call, and returns the
+//! answer of the agent as the result of the call. The [Extensions] of the call are passed on
+//! to the sub-agent (and thus to its own tools).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::providers::openai;
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let researcher = openai.agent(openai::GPT_4O_MINI)
+//!     .preamble("You are a researcher. Answer with the relevant facts only.")
+//!     .mcp_tool(search, client)
+//!     .build();
+//!
+//! let writer = openai.agent(openai::GPT_4O)
+//!     .preamble("You are a writer. Research the facts before writing.")
+//!     .agent_tool("researcher", "Ask the researcher for facts about a topic", researcher)
+//!     .build();
+//! ```
+use std::marker::PhantomData;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::Agent;
+use crate::{
+    completion::{CompletionModel, PromptError, ToolDefinition},
+    tool::{parameters_schema, Extensions, SyncFuture, Tool},
+};
+
+/// Default arguments of an [AgentTool]: the prompt of the agent.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PromptArgs {
+    /// The task or question for the agent
+    pub prompt: String,
+}
+
+/// Tool prompting an agent (see the [module](self) docs), created with [AgentTool::new] (the
+/// arguments are a prompt) or [AgentTool::typed] (the arguments are typed).
+pub struct AgentTool<M: CompletionModel, A = PromptArgs> {
+    name: String,
+    description: String,
+    agent: Agent<M>,
+    /// Converts the arguments of the tool to the prompt of the agent
+    to_prompt: fn(A) -> String,
+    _args: PhantomData<fn(A)>,
+}
+
+impl<M: CompletionModel> AgentTool<M, PromptArgs> {
+    /// Tool named `name` prompting `agent` with the `prompt` argument of the tool call
+    pub fn new(name: &str, description: &str, agent: Agent<M>) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            agent,
+            to_prompt: |args| args.prompt,
+            _args: PhantomData,
+        }
+    }
+}
+
+impl<M: CompletionModel, A> AgentTool<M, A>
+where
+    A: JsonSchema + Serialize + for<'a> Deserialize<'a> + Send + Sync,
+{
+    /// Tool named `name` prompting `agent` with the arguments of the tool call, deserialized
+    /// into `A` (whose JSON schema is sent to the model) and passed to the agent as JSON.
+    pub fn typed(name: &str, description: &str, agent: Agent<M>) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            agent,
+            to_prompt: |args| serde_json::to_string_pretty(&args).unwrap_or_default(),
+            _args: PhantomData,
+        }
+    }
+}
+
+impl<M, A> Tool for AgentTool<M, A>
+where
+    M: CompletionModel,
+    A: JsonSchema + for<'a> Deserialize<'a> + Send + Sync,
+{
+    // Unused: the name of the tool is given at runtime
+    const NAME: &'static str = "agent_tool";
+
+    type Error = PromptError;
+    type Args = A;
+    type Output = String;
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            parameters: parameters_schema::<A>(),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        Tool::call_with_extensions(self, args, &Extensions::new()).await
+    }
+
+    async fn call_with_extensions(
+        &self,
+        args: Self::Args,
+        extensions: &Extensions,
+    ) -> Result<Self::Output, Self::Error> {
+        let prompt = (self.to_prompt)(args);
+        tracing::info!(target: "rig", "Prompting agent tool {}", self.name);
+        SyncFuture::new(self.agent.prompt_with_extensions(prompt, extensions)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        completion::Prompt,
+        providers::mock::{self, MockResponse},
+    };
+
+    #[derive(Deserialize, Serialize, JsonSchema)]
+    struct ReviewArgs {
+        /// The text to review
+        text: String,
+    }
+
+    #[tokio::test]
+    async fn test_agent_tool() {
+        let researcher = mock::CompletionModel::new()
+            .default_response(MockResponse::text("Flurbos are green aliens."));
+        let critic = mock::CompletionModel::new().default_response(MockResponse::text("Good."));
+        let writer = mock::CompletionModel::new().push(MockResponse::tool_call(
+            "researcher",
+            json!({"prompt": "What is a flurbo?"}),
+        ));
+
+        let agent = writer
+            .agent()
+            .agent_tool(
+                "researcher",
+                "Ask the researcher",
+                researcher.agent().build(),
+            )
+            .typed_agent_tool::<ReviewArgs, _>("critic", "Ask the critic", critic.agent().build())
+            .build();
+
+        assert_eq!(
+            agent.prompt("Write about flurbos").await.unwrap(),
+            r#""Flurbos are green aliens.""#
+        );
+        assert_eq!(
+            researcher.requests()[0].prompt_text().unwrap(),
+            "What is a flurbo?"
+        );
+
+        let tools = &writer.requests()[0].tools;
+        assert!(tools.contains(&"researcher".to_string()));
+        assert!(tools.contains(&"critic".to_string()));
+
+        let critic_tool = AgentTool::typed("critic", "Ask the critic", critic.agent().build());
+        let review = Tool::call(
+            &critic_tool,
+            ReviewArgs {
+                text: "Flurbos are green.".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(review, "Good.");
+        assert!(critic.requests()[0]
+            .prompt_text()
+            .unwrap()
+            .contains(r#""text": "Flurbos are green.""#));
+    }
+}
@@ -0,0 +1,478 @@
+//! Compression of the long prompts of an agent, to cut the cost of long-context agents.
+//!
+//! With a [PromptCompressor], the context documents and chat history of each request over the
+//! target number of tokens are shrunk, rather than sent in full (or dropped by the
+//! [ContextWindow](super::ContextWindow), which applies after the compression):
+//! - [CompressionMethod::Prune] (the default): LLMLingua-style token pruning of the texts,
+//!   dropping the stop words first, then the least informative words. The key entities (i.e.:
+//!   the capitalized words, numbers, identifiers, URLs and quotes) are always kept.
+//! - [CompressionMethod::Summarize]: the oldest messages of the history are replaced by their
+//!   summary, written by the model of the agent (which is asked to keep the key entities), and
+//!   the documents are pruned.
+//!
+//! The preamble, the prompt and the tool definitions are never compressed.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{agent::PromptCompressor, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .dynamic_context(20, index)
+//!     .prompt_compression(PromptCompressor::new(8_000))
+//!     .build();
+//!
+//! // Summarize the history, keeping the last 6 messages verbatim
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .prompt_compression(PromptCompressor::new(8_000).summarize().keep_recent(6))
+//!     .build();
+//! ```
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    completion::{CompletionError, CompletionModel, CompletionRequest, Message},
+    message::{AssistantContent, Text, ToolResultContent, UserContent},
+    OneOrMany,
+};
+
+/// Number of characters per token of the default token counter.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Preamble of the model when it summarizes the history.
+const SUMMARIZER_PREAMBLE: &str = "You summarize conversations. Keep every name, number, date, \
+    identifier and decision; drop the pleasantries and repetitions. Answer with the summary only.";
+
+/// Words dropped first by the token pruning.
+const STOP_WORDS: &[&str] = &[
+    "a",
+    "about",
+    "actually",
+    "all",
+    "also",
+    "am",
+    "an",
+    "and",
+    "any",
+    "are",
+    "as",
+    "at",
+    "basically",
+    "be",
+    "been",
+    "being",
+    "both",
+    "but",
+    "by",
+    "can",
+    "could",
+    "did",
+    "do",
+    "does",
+    "each",
+    "for",
+    "from",
+    "had",
+    "has",
+    "have",
+    "having",
+    "here",
+    "how",
+    "i",
+    "if",
+    "in",
+    "into",
+    "is",
+    "it",
+    "its",
+    "just",
+    "me",
+    "more",
+    "most",
+    "my",
+    "of",
+    "on",
+    "or",
+    "other",
+    "our",
+    "quite",
+    "really",
+    "so",
+    "some",
+    "such",
+    "than",
+    "that",
+    "the",
+    "their",
+    "them",
+    "then",
+    "there",
+    "these",
+    "they",
+    "this",
+    "those",
+    "to",
+    "very",
+    "was",
+    "we",
+    "were",
+    "what",
+    "when",
+    "which",
+    "while",
+    "who",
+    "will",
+    "with",
+    "would",
+    "you",
+    "your",
+];
+
+type TokenCounter = Arc<dyn Fn(&str) -> usize + Send + Sync>;
+
+/// How a [PromptCompressor] shrinks the requests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionMethod {
+    /// Token pruning of the documents and history
+    #[default]
+    Prune,
+    /// Summary of the oldest messages of the history by the model of the agent, and token
+    /// pruning of the documents
+    Summarize,
+}
+
+/// Outcome of the compression of a request.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Compression {
+    /// Estimated number of tokens of the request before the compression
+    pub before: u64,
+    /// Estimated number of tokens of the request after the compression
+    pub after: u64,
+}
+
+/// Compressor of the prompts of an agent (see the [module](self) docs).
+#[derive(Clone)]
+pub struct PromptCompressor {
+    /// Number of tokens the requests are compressed to (if possible)
+    target_tokens: u64,
+    method: CompressionMethod,
+    /// Number of the most recent messages of the history never summarized
+    keep_recent: usize,
+    count_tokens: TokenCounter,
+}
+
+impl PromptCompressor {
+    /// Compress the requests over `target_tokens` tokens
+    pub fn new(target_tokens: u64) -> Self {
+        Self {
+            target_tokens,
+            method: CompressionMethod::Prune,
+            keep_recent: 4,
+            count_tokens: Arc::new(|text| text.len().div_ceil(CHARS_PER_TOKEN)),
+        }
+    }
+
+    /// Summarize the oldest messages of the history (see [CompressionMethod::Summarize])
+    pub fn summarize(mut self) -> Self {
+        self.method = CompressionMethod::Summarize;
+        self
+    }
+
+    /// Never summarize the `messages` most recent messages of the history (4 by default)
+    pub fn keep_recent(mut self, messages: usize) -> Self {
+        self.keep_recent = messages;
+        self
+    }
+
+    /// Count the tokens of the texts with `count_tokens` (e.g.: the tokenizer of the model)
+    pub fn token_counter(
+        mut self,
+        count_tokens: impl Fn(&str) -> usize + Send + Sync + 'static,
+    ) -> Self {
+        self.count_tokens = Arc::new(count_tokens);
+        self
+    }
+
+    /// Estimated number of tokens of `request`
+    pub fn count(&self, request: &CompletionRequest) -> u64 {
+        request
+            .preamble
+            .as_deref()
+            .map(|preamble| self.tokens(preamble))
+            .unwrap_or_default()
+            + self.json_tokens(&request.prompt)
+            + self.json_tokens(&request.chat_history)
+            + self.json_tokens(&request.tools)
+            + request
+                .documents
+                .iter()
+                .map(|document| self.tokens(&document.text))
+                .sum::<u64>()
+    }
+
+    /// Compress `request` if it is over the target number of tokens, summarizing with `model`
+    /// (if summarizing)
+    pub async fn compress<M: CompletionModel>(
+        &self,
+        request: &mut CompletionRequest,
+        model: &M,
+    ) -> Result<Compression, CompletionError> {
+        let before = self.count(request);
+        if before <= self.target_tokens {
+            return Ok(Compression {
+                before,
+                after: before,
+            });
+        }
+
+        if self.method == CompressionMethod::Summarize {
+            self.summarize_history(request, model).await?;
+        }
+        let after = self.count(request);
+        if after > self.target_tokens {
+            self.prune(request, after);
+        }
+
+        let compression = Compression {
+            before,
+            after: self.count(request),
+        };
+        tracing::info!(target: "rig",
+            "Compressed the request from {} to {} tokens",
+            compression.before, compression.after
+        );
+        Ok(compression)
+    }
+
+    /// Replace the history but its most recent messages with their summary
+    async fn summarize_history<M: CompletionModel>(
+        &self,
+        request: &mut CompletionRequest,
+        model: &M,
+    ) -> Result<(), CompletionError> {
+        let mut split = request.chat_history.len().saturating_sub(self.keep_recent);
+        // Don't leave tool results without their tool call
+        while split < request.chat_history.len() && is_tool_result(&request.chat_history[split]) {
+            split += 1;
+        }
+        if split == 0 {
+            return Ok(());
+        }
+
+        let transcript = request.chat_history[..split]
+            .iter()
+            .map(|message| serde_json::to_string(message).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let response = model
+            .completion_request(Message::user(format!(
+                "Summarize this conversation:\n{transcript}"
+            )))
+            .preamble(SUMMARIZER_PREAMBLE.to_string())
+            .send()
+            .await?;
+        let summary = match response.choice.first() {
+            AssistantContent::Text(text) => text.text,
+            AssistantContent::ToolCall(_) => {
+                return Err(CompletionError::ResponseError(
+                    "The summarizer answered with a tool call".to_string(),
+                ))
+            }
+        };
+
+        request.chat_history.splice(
+            ..split,
+            [Message::user(format!(
+                "Summary of the earlier conversation: {summary}"
+            ))],
+        );
+        Ok(())
+    }
+
+    /// Prune the texts of the documents and history of `request` (of `tokens` tokens) by the
+    /// ratio bringing it to the target
+    fn prune(&self, request: &mut CompletionRequest, tokens: u64) {
+        let compressible = request
+            .documents
+            .iter()
+            .map(|document| self.tokens(&document.text))
+            .sum::<u64>()
+            + self.json_tokens(&request.chat_history);
+        let excess = tokens - self.target_tokens;
+        let ratio = 1.0 - (excess as f64 / compressible.max(1) as f64).min(1.0);
+
+        for document in request.documents.iter_mut() {
+            document.text = self.prune_text(&document.text, ratio);
+        }
+        for message in request.chat_history.iter_mut() {
+            match message {
+                Message::User { content } => {
+                    *content = content.clone().map(|content| match content {
+                        UserContent::Text(Text { text }) => {
+                            UserContent::text(self.prune_text(&text, ratio))
+                        }
+                        UserContent::ToolResult(mut result) => {
+                            result.content = result.content.map(|content| match content {
+                                ToolResultContent::Text(Text { text }) => {
+                                    ToolResultContent::text(self.prune_text(&text, ratio))
+                                }
+                                content => content,
+                            });
+                            UserContent::ToolResult(result)
+                        }
+                        content => content,
+                    })
+                }
+                Message::Assistant { content } => {
+                    *content = content.clone().map(|content| match content {
+                        AssistantContent::Text(Text { text }) => {
+                            AssistantContent::text(self.prune_text(&text, ratio))
+                        }
+                        content => content,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Prune `text` to about `ratio` of its tokens: the stop words are dropped first, then the
+    /// words which are not key entities
+    pub fn prune_text(&self, text: &str, ratio: f64) -> String {
+        let tokens = self.tokens(text);
+        let budget = (tokens as f64 * ratio).ceil() as u64;
+        if tokens <= budget {
+            return text.to_string();
+        }
+
+        let words = text
+            .split_whitespace()
+            .filter(|word| !is_stop_word(word))
+            .collect::<Vec<_>>();
+        let pruned = words.join(" ");
+        if self.tokens(&pruned) <= budget {
+            return pruned;
+        }
+
+        // Keep all the entities, and the other words (in order) while within the budget
+        let entities = words
+            .iter()
+            .filter(|word| is_entity(word))
+            .map(|word| self.tokens(word) + 1)
+            .sum::<u64>();
+        let mut remaining = budget.saturating_sub(entities);
+        words
+            .into_iter()
+            .filter(|word| {
+                if is_entity(word) {
+                    return true;
+                }
+                let cost = self.tokens(word) + 1;
+                let keep = cost <= remaining;
+                if keep {
+                    remaining -= cost;
+                }
+                keep
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn tokens(&self, text: &str) -> u64 {
+        (self.count_tokens)(text) as u64
+    }
+
+    fn json_tokens(&self, value: &impl Serialize) -> u64 {
+        self.tokens(&serde_json::to_string(value).unwrap_or_default())
+    }
+}
+
+/// Whether `word` is a key entity: a capitalized word, a number, an identifier, a URL or a quote
+fn is_entity(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| c.is_ascii_punctuation() && c != '"' && c != '\'');
+    word.chars().next().is_some_and(char::is_uppercase)
+        || word.chars().any(|c| c.is_ascii_digit())
+        || word.contains(['/', '@', '_', '"', '\''])
+        || word.trim_end_matches('.').contains('.')
+}
+
+fn is_stop_word(word: &str) -> bool {
+    let word = word
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase();
+    STOP_WORDS.contains(&word.as_str())
+}
+
+/// Whether `message` holds tool results
+fn is_tool_result(message: &Message) -> bool {
+    match message {
+        Message::User { content } => content
+            .iter()
+            .any(|content| matches!(content, UserContent::ToolResult(_))),
+        Message::Assistant { .. } => false,
+    }
+}
+
+impl std::fmt::Debug for PromptCompressor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PromptCompressor")
+            .field("target_tokens", &self.target_tokens)
+            .field("method", &self.method)
+            .field("keep_recent", &self.keep_recent)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::{Completion, Document},
+        providers::mock::{self, MockResponse},
+    };
+
+    #[tokio::test]
+    async fn test_compression() {
+        let compressor = PromptCompressor::new(0);
+        let pruned = compressor.prune_text(
+            "So the meeting with Alice is actually on 2024-05-03 at the office in Paris, and \
+             she would really like you to bring the report from https://example.com/q1 with you",
+            0.5,
+        );
+        assert_eq!(
+            pruned,
+            "meeting Alice 2024-05-03 Paris, she https://example.com/q1"
+        );
+
+        // The oldest messages are summarized by the model, the recent ones are kept
+        let model = mock::CompletionModel::new()
+            .default_response(MockResponse::text("Alice booked room 42 for Friday."));
+        let agent = model.agent().build();
+        let history = (0..6)
+            .map(|i| Message::user(format!("Message {i}: {}", "blah ".repeat(50))))
+            .collect::<Vec<_>>();
+        let mut request = agent
+            .completion("Which room?", history)
+            .await
+            .unwrap()
+            .documents(vec![Document {
+                id: "doc".to_string(),
+                text: "The rooms are booked on the intranet. ".repeat(20),
+                additional_props: Default::default(),
+            }])
+            .build();
+        let compressor = PromptCompressor::new(300).summarize().keep_recent(2);
+        let compression = compressor.compress(&mut request, &model).await.unwrap();
+
+        assert!(compression.after < compression.before);
+        assert_eq!(request.chat_history.len(), 3);
+        assert_eq!(
+            request.chat_history[0],
+            Message::user("Summary of the earlier conversation: Alice booked room 42 for Friday.")
+        );
+        let summarized = model.requests()[0].prompt_text().unwrap();
+        assert!(summarized.contains("Message 3"));
+        assert!(!summarized.contains("Message 4"));
+    }
+}
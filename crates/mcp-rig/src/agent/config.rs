@@ -0,0 +1,498 @@
+//! Declarative configuration of agents.
+//!
+//! An [AgentConfig] describes an agent (model, preamble, tools, MCP servers, context...) with
+//! serializable data, so that agents can be defined in files instead of in code. The model,
+//! tools, MCP servers and vector indexes are referenced by name, and resolved from an
+//! [AgentRegistry] with [Agent::from_config]. Conversely, [Agent::to_config] returns the
+//! config of an agent.
+//!
+//! Since building an agent from its config is cheap, agents can be hot-reloaded by building
+//! them again when their config file changes.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     agent::{Agent, AgentConfig, AgentRegistry},
+//!     providers::openai,
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//! let registry = AgentRegistry::new()
+//!     .model("gpt-4o", openai.completion_model("gpt-4o"))
+//!     .tool(Adder)
+//!     .index("docs", docs_index);
+//!
+//! // agent.json:
+//! // {
+//! //     "model": "gpt-4o",
+//! //     "preamble": "You are a calculator.",
+//! //     "tools": ["add"],
+//! //     "dynamic_context": [{ "index": "docs", "sample": 2 }]
+//! // }
+//! let config = AgentConfig::load("agent.json")?;
+//! let agent = Agent::from_config(&config, &registry)?;
+//!
+//! // Save the config of the agent
+//! agent.to_config().save("agent.json")?;
+//! ```
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Agent, AgentBuilder};
+use crate::{
+    completion::{CompletionModel, ToolDefinition},
+    tool::{
+        ApprovalPolicy, ApprovalRequest, Approver, Extensions, ToolAnnotations, ToolDyn, ToolError,
+    },
+    vector_store::{VectorStoreError, VectorStoreIndexDyn},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AgentConfigError {
+    #[error("Model {0} not found in the registry")]
+    ModelNotFound(String),
+
+    #[error("Tool {0} not found in the registry")]
+    ToolNotFound(String),
+
+    #[error("MCP server {0} not found in the registry")]
+    McpServerNotFound(String),
+
+    #[error("Vector index {0} not found in the registry")]
+    IndexNotFound(String),
+
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// Serializable configuration of an [Agent]. See the [module documentation](self).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentConfig {
+    /// Name of the model in the registry
+    pub model: String,
+    /// System prompt
+    #[serde(default)]
+    pub preamble: String,
+    /// Context documents always available to the agent
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+    /// Additional parameters to be passed to the model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub additional_params: Option<serde_json::Value>,
+    /// Names of the tools always available to the agent
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+    /// Names of the MCP servers whose tools are always available to the agent
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mcp_servers: Vec<String>,
+    /// Vector indexes the context documents of each prompt are sampled from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dynamic_context: Vec<DynamicContextConfig>,
+    /// Approval of the tool calls of the agent (see [ApprovalPolicy])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_approval: Option<ApprovalPolicy>,
+}
+
+/// Vector index of the dynamic context of an agent.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DynamicContextConfig {
+    /// Name of the index in the registry
+    pub index: String,
+    /// Number of documents sampled from the index on each prompt
+    pub sample: usize,
+}
+
+impl AgentConfig {
+    /// Load a config from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AgentConfigError> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Save the config to a JSON file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), AgentConfigError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Named references of an agent to the components of its registry, kept to rebuild its config.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ConfigNames {
+    pub model: Option<String>,
+    pub mcp_servers: Vec<String>,
+    /// Names of the tools of the MCP servers (which are not part of `tools`)
+    pub mcp_tools: Vec<String>,
+    pub dynamic_context: Vec<DynamicContextConfig>,
+}
+
+/// Tool shared by the agents built from a registry.
+struct SharedTool(Arc<dyn ToolDyn>);
+
+impl ToolDyn for SharedTool {
+    fn name(&self) -> String {
+        self.0.name()
+    }
+
+    fn definition(
+        &self,
+        prompt: String,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = ToolDefinition> + Send + Sync + '_>>
+    {
+        self.0.definition(prompt)
+    }
+
+    fn call(
+        &self,
+        args: String,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<String, ToolError>> + Send + Sync + '_>,
+    > {
+        self.0.call(args)
+    }
+
+    fn call_with_extensions<'a>(
+        &'a self,
+        args: String,
+        extensions: &'a Extensions,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<String, ToolError>> + Send + Sync + 'a>,
+    > {
+        self.0.call_with_extensions(args, extensions)
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        self.0.annotations()
+    }
+
+    fn ready(
+        &self,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<(), ToolError>> + Send + Sync + '_>,
+    > {
+        self.0.ready()
+    }
+}
+
+/// Vector index shared by the agents built from a registry.
+struct SharedIndex(Arc<dyn VectorStoreIndexDyn>);
+
+impl VectorStoreIndexDyn for SharedIndex {
+    fn top_n<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+    ) -> futures::future::BoxFuture<
+        'a,
+        Result<Vec<(f64, String, serde_json::Value)>, VectorStoreError>,
+    > {
+        self.0.top_n(query, n)
+    }
+
+    fn top_n_ids<'a>(
+        &'a self,
+        query: &'a str,
+        n: usize,
+    ) -> futures::future::BoxFuture<'a, Result<Vec<(f64, String)>, VectorStoreError>> {
+        self.0.top_n_ids(query, n)
+    }
+}
+
+/// Models, tools, MCP servers and vector indexes that agent configs refer to by name.
+pub struct AgentRegistry<M: CompletionModel> {
+    models: HashMap<String, M>,
+    tools: HashMap<String, Arc<dyn ToolDyn>>,
+    mcp_servers: HashMap<String, Vec<Arc<dyn ToolDyn>>>,
+    indexes: HashMap<String, Arc<dyn VectorStoreIndexDyn>>,
+    /// Approver of the tool calls of the agents with [Approval::Ask](crate::tool::Approval::Ask)
+    /// rules
+    approver: Option<Approver>,
+}
+
+impl<M: CompletionModel> Default for AgentRegistry<M> {
+    fn default() -> Self {
+        Self {
+            models: HashMap::new(),
+            tools: HashMap::new(),
+            mcp_servers: HashMap::new(),
+            indexes: HashMap::new(),
+            approver: None,
+        }
+    }
+}
+
+impl<M: CompletionModel> AgentRegistry<M> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a model under `name`.
+    pub fn model(mut self, name: &str, model: M) -> Self {
+        self.models.insert(name.to_string(), model);
+        self
+    }
+
+    /// Register a tool under its name.
+    pub fn tool(mut self, tool: impl ToolDyn + 'static) -> Self {
+        self.tools.insert(tool.name(), Arc::new(tool));
+        self
+    }
+
+    /// Register the tools of an MCP server under `name`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn mcp_server<T: mcp_core::transport::Transport>(
+        mut self,
+        name: &str,
+        tools: Vec<mcp_core::types::Tool>,
+        client: Arc<mcp_core::client::Client<T>>,
+    ) -> Self {
+        let tools = tools
+            .into_iter()
+            .map(|tool| {
+                Arc::new(crate::tool::McpTool::from_mcp_server(tool, client.clone()))
+                    as Arc<dyn ToolDyn>
+            })
+            .collect();
+        self.mcp_servers.insert(name.to_string(), tools);
+        self
+    }
+
+    /// Register the tools of an MCP server under `name`, with at most `max_in_flight` calls
+    /// in flight to the server at once (across all the agents built from the registry).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn mcp_server_with_limit<T: mcp_core::transport::Transport>(
+        mut self,
+        name: &str,
+        tools: Vec<mcp_core::types::Tool>,
+        client: Arc<mcp_core::client::Client<T>>,
+        max_in_flight: usize,
+    ) -> Self {
+        let limit = crate::tool::McpCallLimit::new(max_in_flight);
+        let tools = tools
+            .into_iter()
+            .map(|tool| {
+                Arc::new(
+                    crate::tool::McpTool::from_mcp_server(tool, client.clone())
+                        .with_limit(limit.clone()),
+                ) as Arc<dyn ToolDyn>
+            })
+            .collect();
+        self.mcp_servers.insert(name.to_string(), tools);
+        self
+    }
+
+    /// Register a vector index under `name`.
+    pub fn index(mut self, name: &str, index: impl VectorStoreIndexDyn + 'static) -> Self {
+        self.indexes.insert(name.to_string(), Arc::new(index));
+        self
+    }
+
+    /// Ask `approver` whether the tool calls of the agents may proceed, for the calls the
+    /// [ApprovalPolicy] of their config asks about.
+    pub fn tool_approver<F, Fut>(mut self, approver: F) -> Self
+    where
+        F: Fn(ApprovalRequest) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = bool> + Send + 'static,
+    {
+        self.approver = Some(Arc::new(move |request| Box::pin(approver(request))));
+        self
+    }
+
+    /// Tool named `name`, either registered as a tool or as a tool of an MCP server.
+    fn find_tool(&self, name: &str) -> Option<&Arc<dyn ToolDyn>> {
+        self.tools.get(name).or_else(|| {
+            self.mcp_servers
+                .values()
+                .flatten()
+                .find(|tool| tool.name() == name)
+        })
+    }
+}
+
+impl<M: CompletionModel> AgentBuilder<M> {
+    /// Add a static tool shared with the registry.
+    fn add_tool(&mut self, tool: SharedTool) {
+        self.static_tools.push(tool.name());
+        self.tools.add_tool(tool);
+    }
+}
+
+impl<M: CompletionModel> Agent<M> {
+    /// Build an agent from its config, resolving the model, tools, MCP servers and vector
+    /// indexes it refers to from `registry`.
+    pub fn from_config(
+        config: &AgentConfig,
+        registry: &AgentRegistry<M>,
+    ) -> Result<Self, AgentConfigError> {
+        let model = registry
+            .models
+            .get(&config.model)
+            .ok_or_else(|| AgentConfigError::ModelNotFound(config.model.clone()))?;
+
+        let mut builder = AgentBuilder::new(model.clone())
+            .model_name(&config.model)
+            .preamble(&config.preamble);
+        builder.temperature = config.temperature;
+        builder.max_tokens = config.max_tokens;
+        builder.additional_params = config.additional_params.clone();
+        for doc in &config.context {
+            builder = builder.context(doc);
+        }
+
+        for name in &config.tools {
+            let tool = registry
+                .find_tool(name)
+                .ok_or_else(|| AgentConfigError::ToolNotFound(name.clone()))?;
+            builder.add_tool(SharedTool(tool.clone()));
+        }
+
+        for name in &config.mcp_servers {
+            let tools = registry
+                .mcp_servers
+                .get(name)
+                .ok_or_else(|| AgentConfigError::McpServerNotFound(name.clone()))?;
+            for tool in tools {
+                builder.config_names.mcp_tools.push(tool.name());
+                builder.add_tool(SharedTool(tool.clone()));
+            }
+            builder.config_names.mcp_servers.push(name.clone());
+        }
+
+        for context in &config.dynamic_context {
+            let index = registry
+                .indexes
+                .get(&context.index)
+                .ok_or_else(|| AgentConfigError::IndexNotFound(context.index.clone()))?;
+            builder = builder.dynamic_context(context.sample, SharedIndex(index.clone()));
+            builder.config_names.dynamic_context.push(context.clone());
+        }
+
+        if let Some(policy) = &config.tool_approval {
+            let policy = match &registry.approver {
+                Some(approver) => policy.clone().with_approver(approver.clone()),
+                None => policy.clone(),
+            };
+            builder = builder.tool_approval(policy);
+        }
+
+        Ok(builder.build())
+    }
+
+    /// Config of the agent. The model, MCP servers and vector indexes of the agent are only
+    /// part of the config if they are named, i.e.: if the agent was built with
+    /// [Agent::from_config] (or the model with [AgentBuilder::model_name]).
+    pub fn to_config(&self) -> AgentConfig {
+        AgentConfig {
+            model: self.config_names.model.clone().unwrap_or_default(),
+            preamble: self.preamble.clone(),
+            context: self
+                .static_context
+                .iter()
+                .map(|doc| doc.text.clone())
+                .collect(),
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            additional_params: self.additional_params.clone(),
+            tools: self
+                .static_tools
+                .iter()
+                .filter(|name| !self.config_names.mcp_tools.contains(name))
+                .cloned()
+                .collect(),
+            mcp_servers: self.config_names.mcp_servers.clone(),
+            dynamic_context: self.config_names.dynamic_context.clone(),
+            tool_approval: self.tools.approval_policy().cloned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        completion::Prompt,
+        providers::mock::{self, MockResponse},
+        tool::Tool,
+    };
+
+    #[derive(Deserialize)]
+    struct OperationArgs {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Math error")]
+    struct MathError;
+
+    struct Adder;
+
+    impl Tool for Adder {
+        const NAME: &'static str = "add";
+        type Error = MathError;
+        type Args = OperationArgs;
+        type Output = i32;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: "add".to_string(),
+                description: "Add x and y together".to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "x": { "type": "number" },
+                        "y": { "type": "number" }
+                    }
+                }),
+            }
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            Ok(args.x + args.y)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_agent_config() {
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::tool_call("add", json!({ "x": 1, "y": 2 })));
+        let registry = AgentRegistry::new()
+            .model("mock", model.clone())
+            .tool(Adder);
+
+        let config: AgentConfig = serde_json::from_value(json!({
+            "model": "mock",
+            "preamble": "You are a calculator.",
+            "temperature": 0.2,
+            "tools": ["add"],
+        }))
+        .unwrap();
+        let agent = Agent::from_config(&config, &registry).unwrap();
+        assert_eq!(agent.prompt("1 + 2?").await.unwrap(), "3");
+
+        let request = &model.requests()[0];
+        assert_eq!(request.preamble.as_deref(), Some("You are a calculator."));
+        assert_eq!(request.tools, vec!["add".to_string()]);
+
+        assert_eq!(agent.to_config(), config);
+
+        let config = AgentConfig {
+            tools: vec!["subtract".to_string()],
+            ..config
+        };
+        assert!(matches!(
+            Agent::from_config(&config, &registry),
+            Err(AgentConfigError::ToolNotFound(name)) if name == "subtract"
+        ));
+    }
+}
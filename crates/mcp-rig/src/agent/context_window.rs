@@ -0,0 +1,327 @@
+//! Fitting of the completion requests of an agent into the context window of its model.
+//!
+//! With a [ContextWindow], the preamble, context documents, chat history and tool definitions
+//! of each request are fitted into the context length of the model (minus the tokens reserved
+//! for the answer), instead of letting the provider fail with a context length error. When the
+//! request is too long, the content of the lowest [Priority] is dropped first: the oldest
+//! messages of the history, the last (i.e.: least relevant) documents, and the last tool
+//! definitions. The prompt itself is never dropped.
+//!
+//! The dropped content is reported in the logs and, for the prompts with a trace (see
+//! [Agent::prompt_with_trace](super::Agent::prompt_with_trace)), as a
+//! [TraceStep::ContextTrimmed](super::TraceStep::ContextTrimmed) step.
+//!
+//! The tokens are estimated at 4 characters per token by default (see
+//! [ContextWindow::token_counter] to use the tokenizer of the model).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{agent::{ContextWindow, Priority}, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .dynamic_context(10, index)
+//!     // Context length of the model from the built-in catalog
+//!     .context_window(ContextWindow::for_model(openai::GPT_4O).unwrap())
+//!     .build();
+//!
+//! // Keep the history rather than the documents
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .context_window(
+//!         ContextWindow::new(32_000)
+//!             .reserve_output(2_000)
+//!             .priorities([Priority::Preamble, Priority::History, Priority::Tools, Priority::Context]),
+//!     )
+//!     .build();
+//! ```
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    completion::{CompletionRequest, Message},
+    message::UserContent,
+    models,
+};
+
+/// Number of characters per token of the default token counter.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Tokens reserved for the answer, if the request has no maximum number of tokens.
+const DEFAULT_OUTPUT_RESERVE: u64 = 1024;
+
+/// Parts of a completion request, by decreasing priority (see [ContextWindow::priorities]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Preamble,
+    Tools,
+    /// Context documents (static and dynamic)
+    Context,
+    History,
+}
+
+/// Content dropped from a completion request to fit it into the context window.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Trimmed {
+    Preamble,
+    /// Context document (by id)
+    Document {
+        id: String,
+    },
+    /// Message of the history, by index in the original history
+    Message {
+        index: usize,
+    },
+    /// Tool definition (by name)
+    Tool {
+        name: String,
+    },
+}
+
+type TokenCounter = Arc<dyn Fn(&str) -> usize + Send + Sync>;
+
+/// Context window of the model of an agent (see the [module](self) docs).
+#[derive(Clone)]
+pub struct ContextWindow {
+    /// Context length of the model, in tokens
+    context_length: u64,
+    /// Tokens reserved for the answer (the maximum number of tokens of the request if unset)
+    output_reserve: Option<u64>,
+    /// Parts of the requests, by decreasing priority
+    priorities: [Priority; 4],
+    count_tokens: TokenCounter,
+}
+
+impl ContextWindow {
+    /// Context window of `context_length` tokens
+    pub fn new(context_length: u64) -> Self {
+        Self {
+            context_length,
+            output_reserve: None,
+            priorities: [
+                Priority::Preamble,
+                Priority::Tools,
+                Priority::Context,
+                Priority::History,
+            ],
+            count_tokens: Arc::new(|text| text.len().div_ceil(CHARS_PER_TOKEN)),
+        }
+    }
+
+    /// Context window of the model `model`, if its context length is in the built-in catalog
+    /// (see [models::context_length])
+    pub fn for_model(model: &str) -> Option<Self> {
+        models::context_length(model).map(Self::new)
+    }
+
+    /// Reserve `tokens` for the answer (by default, the maximum number of tokens of the
+    /// request, or 1024 tokens if unset)
+    pub fn reserve_output(mut self, tokens: u64) -> Self {
+        self.output_reserve = Some(tokens);
+        self
+    }
+
+    /// Set the priorities of the parts of the requests, from the highest (dropped last) to the
+    /// lowest (dropped first). By default: preamble, tools, context, history.
+    pub fn priorities(mut self, priorities: [Priority; 4]) -> Self {
+        self.priorities = priorities;
+        self
+    }
+
+    /// Count the tokens of the texts with `count_tokens` (e.g.: the tokenizer of the model)
+    pub fn token_counter(
+        mut self,
+        count_tokens: impl Fn(&str) -> usize + Send + Sync + 'static,
+    ) -> Self {
+        self.count_tokens = Arc::new(count_tokens);
+        self
+    }
+
+    /// Estimated number of tokens of `request`
+    pub fn count(&self, request: &CompletionRequest) -> u64 {
+        let preamble = request
+            .preamble
+            .as_deref()
+            .map(|preamble| self.tokens(preamble))
+            .unwrap_or_default();
+        preamble
+            + self.message_tokens(&request.prompt)
+            + request
+                .chat_history
+                .iter()
+                .map(|message| self.message_tokens(message))
+                .sum::<u64>()
+            + request
+                .documents
+                .iter()
+                .map(|document| self.tokens(&document.text))
+                .sum::<u64>()
+            + request
+                .tools
+                .iter()
+                .map(|tool| self.json_tokens(tool))
+                .sum::<u64>()
+    }
+
+    /// Drop the lowest priority content of `request` until it fits into the context window.
+    /// Returns the dropped content (empty if the request already fits). If the prompt alone
+    /// doesn't fit, everything else is dropped.
+    pub fn fit(&self, request: &mut CompletionRequest) -> Vec<Trimmed> {
+        let reserve = self
+            .output_reserve
+            .or(request.max_tokens)
+            .unwrap_or(DEFAULT_OUTPUT_RESERVE);
+        let budget = self.context_length.saturating_sub(reserve);
+        let mut tokens = self.count(request);
+        let mut trimmed = vec![];
+        // Index (in the original history) of the oldest message left
+        let mut first_message = 0;
+
+        for priority in self.priorities.iter().rev() {
+            while tokens > budget {
+                let dropped = match priority {
+                    Priority::Preamble => request.preamble.take().map(|preamble| {
+                        tokens -= self.tokens(&preamble);
+                        vec![Trimmed::Preamble]
+                    }),
+                    Priority::Tools => request.tools.pop().map(|tool| {
+                        tokens -= self.json_tokens(&tool);
+                        vec![Trimmed::Tool { name: tool.name }]
+                    }),
+                    Priority::Context => request.documents.pop().map(|document| {
+                        tokens -= self.tokens(&document.text);
+                        vec![Trimmed::Document { id: document.id }]
+                    }),
+                    Priority::History => {
+                        let mut dropped = vec![];
+                        // Don't leave tool results without their tool call
+                        while !request.chat_history.is_empty()
+                            && (dropped.is_empty() || is_tool_result(&request.chat_history[0]))
+                        {
+                            let message = request.chat_history.remove(0);
+                            tokens -= self.message_tokens(&message);
+                            dropped.push(Trimmed::Message {
+                                index: first_message,
+                            });
+                            first_message += 1;
+                        }
+                        (!dropped.is_empty()).then_some(dropped)
+                    }
+                };
+                match dropped {
+                    Some(dropped) => trimmed.extend(dropped),
+                    None => break,
+                }
+            }
+        }
+
+        if !trimmed.is_empty() {
+            tracing::warn!(target: "rig",
+                "Dropped {} items from the request to fit the context window: {:?}",
+                trimmed.len(), trimmed
+            );
+        }
+        trimmed
+    }
+
+    fn tokens(&self, text: &str) -> u64 {
+        (self.count_tokens)(text) as u64
+    }
+
+    fn json_tokens(&self, value: &impl Serialize) -> u64 {
+        self.tokens(&serde_json::to_string(value).unwrap_or_default())
+    }
+
+    fn message_tokens(&self, message: &Message) -> u64 {
+        self.json_tokens(message)
+    }
+}
+
+/// Whether `message` holds tool results
+fn is_tool_result(message: &Message) -> bool {
+    match message {
+        Message::User { content } => content
+            .iter()
+            .any(|content| matches!(content, UserContent::ToolResult(_))),
+        Message::Assistant { .. } => false,
+    }
+}
+
+impl std::fmt::Debug for ContextWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ContextWindow")
+            .field("context_length", &self.context_length)
+            .field("output_reserve", &self.output_reserve)
+            .field("priorities", &self.priorities)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::completion::{Document, ToolDefinition};
+
+    fn sample_request() -> CompletionRequest {
+        CompletionRequest {
+            prompt: Message::user("prompt"),
+            preamble: Some("preamble".to_string()),
+            chat_history: vec![Message::user("old"), Message::assistant("older")],
+            documents: vec![Document {
+                id: "doc".to_string(),
+                text: "text".to_string(),
+                additional_props: HashMap::new(),
+            }],
+            tools: vec![ToolDefinition {
+                name: "tool".to_string(),
+                description: "description".to_string(),
+                parameters: json!({}),
+            }],
+            temperature: None,
+            max_tokens: Some(0),
+            additional_params: None,
+            tool_choice: None,
+        }
+    }
+
+    #[test]
+    fn test_fit() {
+        // One token per item
+        let window = ContextWindow::new(4).token_counter(|_| 1);
+
+        let mut request = sample_request();
+        assert_eq!(window.count(&request), 6);
+        assert_eq!(
+            window.fit(&mut request),
+            vec![Trimmed::Message { index: 0 }, Trimmed::Message { index: 1 }]
+        );
+        assert!(request.chat_history.is_empty());
+        assert_eq!(request.documents.len(), 1);
+
+        let window = window.priorities([
+            Priority::History,
+            Priority::Preamble,
+            Priority::Tools,
+            Priority::Context,
+        ]);
+        let mut request = sample_request();
+        assert_eq!(
+            window.fit(&mut request),
+            vec![
+                Trimmed::Document {
+                    id: "doc".to_string()
+                },
+                Trimmed::Tool {
+                    name: "tool".to_string()
+                }
+            ]
+        );
+        assert!(window.fit(&mut request).is_empty());
+    }
+}
@@ -0,0 +1,229 @@
+//! Deduplication of the documents retrieved by an agent, so that the top-n retrieval from
+//! overlapping sources (e.g.: several indexes of the same docs, or the chunks of mirrored pages)
+//! doesn't waste the context window.
+//!
+//! With a [DocumentDedup], before the retrieved documents are added to a request:
+//! - the boilerplate (i.e.: the lines repeated across documents, such as headers, footers and
+//!   disclaimers) is only kept in the first document it appears in,
+//! - the near-duplicates (i.e.: the documents whose embedding is at least `threshold` similar to
+//!   that of a document ranked before them) are dropped.
+//!
+//! The documents are ranked in the order they were retrieved in, the first retrieved being kept.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{agent::DocumentDedup, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//! let embedding_model = openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .dynamic_context(5, docs_index)
+//!     .dynamic_context(5, wiki_index)
+//!     .dedup_context(DocumentDedup::new(embedding_model, 0.95))
+//!     .build();
+//! ```
+use std::collections::{HashMap, HashSet};
+
+use futures::future::BoxFuture;
+
+use crate::{
+    completion::Document,
+    embeddings::{distance::VectorDistance, Embedding, EmbeddingError, EmbeddingModel},
+};
+
+/// Minimum number of characters of a line for it to be collapsed as boilerplate (shorter lines,
+/// e.g.: the brackets of the JSON documents, are always kept).
+const BOILERPLATE_MIN_CHARS: usize = 20;
+
+type EmbedFn = Box<
+    dyn Fn(Vec<String>) -> BoxFuture<'static, Result<Vec<Embedding>, EmbeddingError>> + Send + Sync,
+>;
+
+/// Deduplication of the retrieved documents (see the [module](self) docs).
+pub struct DocumentDedup {
+    /// Cosine similarity from which two documents are duplicates
+    threshold: f64,
+    /// Whether the lines repeated across documents are collapsed
+    collapse_boilerplate: bool,
+    /// Embeds texts with the embedding model of the deduplication
+    embed: EmbedFn,
+}
+
+impl DocumentDedup {
+    /// Drop the documents at least `threshold` similar (i.e.: the cosine similarity of their
+    /// embeddings by `embedding_model`) to a document ranked before them
+    pub fn new(embedding_model: impl EmbeddingModel + 'static, threshold: f64) -> Self {
+        Self {
+            threshold,
+            collapse_boilerplate: true,
+            embed: Box::new(move |texts| {
+                let embedding_model = embedding_model.clone();
+                Box::pin(async move { embedding_model.embed_texts(texts).await })
+            }),
+        }
+    }
+
+    /// Keep the lines repeated across documents (collapsed by default)
+    pub fn keep_boilerplate(mut self) -> Self {
+        self.collapse_boilerplate = false;
+        self
+    }
+
+    /// Collapse the boilerplate of `documents` and drop their near-duplicates, keeping their
+    /// order
+    pub async fn dedup(&self, documents: Vec<Document>) -> Result<Vec<Document>, EmbeddingError> {
+        let documents = if self.collapse_boilerplate {
+            collapse_boilerplate(documents)
+        } else {
+            documents
+        };
+        if documents.len() < 2 {
+            return Ok(documents);
+        }
+
+        let embeddings =
+            (self.embed)(documents.iter().map(|doc| doc.text.clone()).collect()).await?;
+        if embeddings.len() != documents.len() {
+            return Err(EmbeddingError::ResponseError(
+                "Missing embeddings of the documents".into(),
+            ));
+        }
+
+        let mut kept: Vec<(Document, Embedding)> = vec![];
+        let mut dropped = vec![];
+        for (document, embedding) in documents.into_iter().zip(embeddings) {
+            let duplicate = kept
+                .iter()
+                .any(|(_, other)| embedding.cosine_similarity(other, false) >= self.threshold);
+            if duplicate {
+                dropped.push(document.id);
+            } else {
+                kept.push((document, embedding));
+            }
+        }
+        if !dropped.is_empty() {
+            tracing::debug!(target: "rig", "Dropped duplicate documents: {:?}", dropped);
+        }
+
+        Ok(kept.into_iter().map(|(document, _)| document).collect())
+    }
+}
+
+/// Only keep the lines repeated across `documents` in the first document they appear in, and
+/// drop the documents left empty
+fn collapse_boilerplate(documents: Vec<Document>) -> Vec<Document> {
+    let is_candidate = |line: &str| line.trim().chars().count() >= BOILERPLATE_MIN_CHARS;
+
+    // Number of the documents each line appears in
+    let mut counts = HashMap::<&str, usize>::new();
+    for document in &documents {
+        let lines = document
+            .text
+            .lines()
+            .map(str::trim)
+            .filter(|line| is_candidate(line))
+            .collect::<HashSet<_>>();
+        for line in lines {
+            *counts.entry(line).or_default() += 1;
+        }
+    }
+    let boilerplate = counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(line, _)| line.to_string())
+        .collect::<HashSet<_>>();
+    if boilerplate.is_empty() {
+        return documents;
+    }
+
+    let mut seen = HashSet::new();
+    documents
+        .into_iter()
+        .filter_map(|mut document| {
+            let mut in_document = HashSet::new();
+            let text = document
+                .text
+                .lines()
+                .filter(|line| {
+                    let line = line.trim();
+                    !boilerplate.contains(line)
+                        || in_document.contains(line)
+                        || (!seen.contains(line) && in_document.insert(line.to_string()))
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            seen.extend(in_document);
+
+            if text.trim().is_empty() {
+                tracing::debug!(target: "rig", "Dropped boilerplate document: {}", document.id);
+                return None;
+            }
+            document.text = text;
+            Some(document)
+        })
+        .collect()
+}
+
+impl std::fmt::Debug for DocumentDedup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DocumentDedup")
+            .field("threshold", &self.threshold)
+            .field("collapse_boilerplate", &self.collapse_boilerplate)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock;
+
+    fn document(id: &str, text: &str) -> Document {
+        Document {
+            id: id.to_string(),
+            text: text.to_string(),
+            additional_props: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dedup() {
+        let footer = "Copyright 2024 Example Corp. All rights reserved.";
+        let paris = format!("Paris is the capital of France.\n{footer}");
+        let model = mock::EmbeddingModel::new(2)
+            .embedding(&paris, vec![1.0, 0.0])
+            .embedding("The capital of France is Paris.", vec![0.99, 0.05])
+            .embedding("Berlin is the capital of Germany.", vec![0.0, 1.0]);
+        let dedup = DocumentDedup::new(model.clone(), 0.95);
+
+        let documents = dedup
+            .dedup(vec![
+                document("wiki", &paris),
+                document(
+                    "mirror",
+                    &format!("The capital of France is Paris.\n{footer}"),
+                ),
+                document(
+                    "berlin",
+                    &format!("Berlin is the capital of Germany.\n{footer}"),
+                ),
+                document("legal", footer),
+            ])
+            .await
+            .unwrap();
+
+        // The footer is only kept in the first document, the near-duplicate is dropped
+        assert_eq!(
+            documents
+                .iter()
+                .map(|doc| (doc.id.as_str(), doc.text.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("wiki", paris.as_str()),
+                ("berlin", "Berlin is the capital of Germany."),
+            ]
+        );
+        assert_eq!(model.requests()[0].len(), 3);
+    }
+}
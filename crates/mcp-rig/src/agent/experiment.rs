@@ -0,0 +1,303 @@
+//! A/B experiments on the preambles and models of agents.
+//!
+//! An [Experiment] holds two or more variants of an agent (e.g.: with different preambles or
+//! models), and assigns each session (i.e.: conversation) to one of them. The assignment is
+//! deterministic: a session always gets the same variant (by default, from a hash of the
+//! session ID, in proportion to the weights of the variants), so its conversation is
+//! consistent.
+//!
+//! Each response is tagged with its variant, and so is the token usage of its requests in the
+//! installed [UsageLedger](crate::usage::UsageLedger), to compare the variants downstream
+//! (e.g.: with [GroupBy::Variant](crate::usage::GroupBy::Variant)).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     agent::Experiment,
+//!     providers::openai,
+//!     usage::{GroupBy, Pricing, UsageLedger},
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//! let ledger = UsageLedger::new(Pricing::new().model("gpt-4o", 2.5, 10.0));
+//! ledger.clone().install();
+//!
+//! let experiment = Experiment::new(
+//!     "support-preamble",
+//!     "control",
+//!     openai.agent(openai::GPT_4O).preamble("You are a support agent.").build(),
+//! )
+//! .variant(
+//!     "friendly",
+//!     openai.agent(openai::GPT_4O).preamble("You are a friendly support agent.").build(),
+//! );
+//!
+//! let response = experiment.prompt("session-42", "How do I reset my password?").await?;
+//! println!("{} answered: {}", response.variant, response.response);
+//!
+//! let report = ledger
+//!     .query()
+//!     .experiment("support-preamble")
+//!     .group_by(GroupBy::Variant)
+//!     .report();
+//! ```
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::completion::{Chat, Message, PromptError};
+
+type Respond = Arc<
+    dyn Fn(Message, Vec<Message>) -> BoxFuture<'static, Result<String, PromptError>> + Send + Sync,
+>;
+
+type Assignment = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Response of an [Experiment], tagged with its variant.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ExperimentResponse {
+    pub experiment: String,
+    /// Name of the variant the session is assigned to
+    pub variant: String,
+    pub response: String,
+}
+
+struct Variant {
+    name: String,
+    weight: u32,
+    respond: Respond,
+}
+
+/// A/B experiment on the variants of an agent (see the [module](self) docs).
+#[derive(Clone)]
+pub struct Experiment {
+    name: String,
+    variants: Vec<Arc<Variant>>,
+    /// Assignment of the sessions to the variants (by hash of the session ID if unset)
+    assignment: Option<Assignment>,
+}
+
+impl Experiment {
+    /// Experiment `name`, with its control variant `control` (i.e.: the current agent)
+    pub fn new(name: &str, control: &str, agent: impl Chat + 'static) -> Self {
+        Self {
+            name: name.to_string(),
+            variants: vec![],
+            assignment: None,
+        }
+        .weighted_variant(control, 1, agent)
+    }
+
+    /// Add the variant `name` to the experiment, with a weight of 1
+    pub fn variant(self, name: &str, agent: impl Chat + 'static) -> Self {
+        self.weighted_variant(name, 1, agent)
+    }
+
+    /// Add the variant `name` to the experiment. The sessions are assigned to the variants in
+    /// proportion to their weights (e.g.: a variant of weight 1 alongside a control of weight 9
+    /// gets 10% of the sessions).
+    pub fn weighted_variant(mut self, name: &str, weight: u32, agent: impl Chat + 'static) -> Self {
+        let agent = Arc::new(agent);
+        let respond: Respond = Arc::new(move |prompt, chat_history| {
+            let agent = agent.clone();
+            Box::pin(async move { agent.chat(prompt, chat_history).await })
+        });
+        self.variants.push(Arc::new(Variant {
+            name: name.to_string(),
+            weight,
+            respond,
+        }));
+        self
+    }
+
+    /// Assign the sessions with `assign`, which returns the name of the variant of a session
+    /// ID (e.g.: from a feature flag service). It must be deterministic. The sessions assigned
+    /// to an unknown variant get the control variant.
+    pub fn assign_with(mut self, assign: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        self.assignment = Some(Arc::new(assign));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Name of the variant the session `session_id` is assigned to
+    pub fn assign(&self, session_id: &str) -> &str {
+        self.assigned_variant(session_id).name.as_str()
+    }
+
+    fn assigned_variant(&self, session_id: &str) -> &Variant {
+        let control = &self.variants[0];
+
+        if let Some(assign) = &self.assignment {
+            let name = assign(session_id);
+            return match self.variants.iter().find(|variant| variant.name == name) {
+                Some(variant) => variant,
+                None => {
+                    tracing::warn!(target: "rig",
+                        "Unknown variant {} of experiment {}, using {}", name, self.name, control.name
+                    );
+                    control
+                }
+            };
+        }
+
+        let total = self
+            .variants
+            .iter()
+            .map(|variant| variant.weight as u64)
+            .sum::<u64>();
+        if total == 0 {
+            return control;
+        }
+        let mut bucket = fnv1a(&format!("{}/{}", self.name, session_id)) % total;
+        self.variants
+            .iter()
+            .find(|variant| {
+                let found = bucket < variant.weight as u64;
+                bucket = bucket.saturating_sub(variant.weight as u64);
+                found
+            })
+            .unwrap_or(control)
+    }
+
+    /// Prompt the variant the session `session_id` is assigned to
+    pub async fn prompt(
+        &self,
+        session_id: &str,
+        prompt: impl Into<Message> + Send,
+    ) -> Result<ExperimentResponse, PromptError> {
+        self.chat(session_id, prompt, vec![]).await
+    }
+
+    /// Chat with the variant the session `session_id` is assigned to. The token usage of its
+    /// requests is attributed to the variant (and to the session, unless the agent of the
+    /// variant has its own session ID).
+    pub async fn chat(
+        &self,
+        session_id: &str,
+        prompt: impl Into<Message> + Send,
+        chat_history: Vec<Message>,
+    ) -> Result<ExperimentResponse, PromptError> {
+        let variant = self.assigned_variant(session_id);
+        tracing::info!(target: "rig",
+            "Session {} assigned to variant {} of experiment {}", session_id, variant.name, self.name
+        );
+
+        let response = crate::usage::experiment_scoped(
+            &self.name,
+            &variant.name,
+            session_id,
+            (variant.respond)(prompt.into(), chat_history),
+        )
+        .await?;
+
+        Ok(ExperimentResponse {
+            experiment: self.name.clone(),
+            variant: variant.name.clone(),
+            response,
+        })
+    }
+}
+
+/// 64-bit FNV-1a hash of `text`, stable across platforms and releases (unlike the hashers of
+/// the standard library)
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl std::fmt::Debug for Experiment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Experiment")
+            .field("name", &self.name)
+            .field(
+                "variants",
+                &self
+                    .variants
+                    .iter()
+                    .map(|variant| (&variant.name, variant.weight))
+                    .collect::<Vec<_>>(),
+            )
+            .field("assignment", &self.assignment.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        providers::mock::{self, MockResponse},
+        usage::{GroupBy, Pricing, UsageLedger},
+    };
+
+    /// Variant recording its usage in its own ledger (rather than in the installed one)
+    struct Recorder(UsageLedger, &'static str);
+
+    impl Chat for Recorder {
+        async fn chat(
+            &self,
+            _prompt: impl Into<Message> + Send,
+            _chat_history: Vec<Message>,
+        ) -> Result<String, PromptError> {
+            self.0.record("mock", 100, 10);
+            Ok(self.1.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_experiment() {
+        let ledger = UsageLedger::new(Pricing::new());
+        let experiment = Experiment::new("preamble", "control", Recorder(ledger.clone(), "A"))
+            .variant("treatment", Recorder(ledger.clone(), "B"));
+
+        // Deterministic, and both variants get sessions
+        let sessions = (0..20).map(|i| format!("session-{i}")).collect::<Vec<_>>();
+        let variants = sessions
+            .iter()
+            .map(|session| experiment.assign(session))
+            .collect::<Vec<_>>();
+        assert!(variants.contains(&"control") && variants.contains(&"treatment"));
+        assert!(sessions
+            .iter()
+            .zip(&variants)
+            .all(|(session, variant)| experiment.assign(session) == *variant));
+
+        for session in &sessions[..4] {
+            let response = experiment.prompt(session, "Hello").await.unwrap();
+            assert_eq!(response.variant, experiment.assign(session));
+            let expected = if response.variant == "control" {
+                "A"
+            } else {
+                "B"
+            };
+            assert_eq!(response.response, expected);
+        }
+
+        let records = ledger.query().experiment("preamble").records().to_vec();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].session_id.as_deref(), Some("session-0"));
+        assert_eq!(records[0].variant.as_deref(), Some(variants[0]));
+        let report = ledger.query().group_by(GroupBy::Variant).report();
+        assert_eq!(report.rows.iter().map(|row| row.requests).sum::<u64>(), 4);
+
+        let experiment = experiment.assign_with(|_| "treatment".to_string());
+        assert_eq!(experiment.assign("session-0"), "treatment");
+    }
+
+    #[tokio::test]
+    async fn test_experiment_agents() {
+        let model = mock::CompletionModel::new().default_response(MockResponse::text("Hi!"));
+        let experiment = Experiment::new("model", "control", model.agent().build())
+            .weighted_variant("disabled", 0, model.agent().preamble("Be brief.").build());
+
+        assert!((0..20).all(|i| experiment.assign(&format!("session-{i}")) == "control"));
+        let response = experiment.prompt("session-1", "Hello").await.unwrap();
+        assert_eq!(response.variant, "control");
+        assert_eq!(response.response, "Hi!");
+    }
+}
@@ -0,0 +1,303 @@
+//! Language handling for multilingual deployments: the language of each prompt is detected, and
+//! the agent is instructed to answer in the language of the user (or in a forced language), with
+//! the variant of its preamble written in that language (if any).
+//!
+//! With a [LanguagePolicy], before each prompt:
+//! - the language of the prompt is detected (see [detect_language], or
+//!   [LanguagePolicy::detector] for a custom detector), falling back to the
+//!   [fallback](LanguagePolicy::fallback) language (if any),
+//! - the preamble of the agent is replaced by its variant in the language of the answer (see
+//!   [LanguagePolicy::preamble]), if any,
+//! - the agent is instructed to answer in that language.
+//!
+//! The languages are identified by their ISO 639-1 codes (e.g.: `en`, `fr`).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{agent::LanguagePolicy, providers::openai};
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("You are the support agent of Acme.")
+//!     .language_policy(
+//!         LanguagePolicy::user_language()
+//!             .preamble("fr", "Tu es l'agent de support d'Acme.")
+//!             .fallback("en"),
+//!     )
+//!     .build();
+//!
+//! // Answered in French, with the French preamble
+//! agent.prompt("Bonjour, comment changer mon mot de passe ?").await?;
+//! ```
+use std::{collections::HashMap, sync::Arc};
+
+/// Most common words of the languages detected by [detect_language], by language.
+const COMMON_WORDS: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &[
+            "the", "is", "are", "and", "you", "what", "how", "hello", "please", "i", "my", "to",
+            "of", "in", "it", "can", "thanks", "with", "for", "this",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "est", "et", "je", "vous", "tu", "quoi", "comment", "bonjour",
+            "merci", "un", "une", "des", "du", "de", "pour", "que", "pas", "mon", "avec", "ce",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "la", "los", "las", "es", "y", "yo", "usted", "qué", "cómo", "hola", "gracias",
+            "un", "una", "por", "para", "que", "de", "mi", "con", "está",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "das", "ist", "und", "ich", "sie", "du", "was", "wie", "hallo", "danke",
+            "ein", "eine", "nicht", "mit", "mein", "für", "bitte", "zu",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "lo", "gli", "è", "e", "io", "che", "come", "ciao", "grazie", "un", "una", "per",
+            "non", "di", "mio", "con", "sono",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "os", "é", "eu", "você", "que", "como", "olá", "obrigado", "obrigada", "um",
+            "uma", "para", "não", "de", "meu", "com", "está",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "is", "en", "ik", "jij", "je", "wat", "hoe", "hallo", "dank", "een",
+            "niet", "van", "mijn", "met", "voor",
+        ],
+    ),
+];
+
+/// Names of the languages, as used in the instruction to answer in a language.
+const LANGUAGE_NAMES: &[(&str, &str)] = &[
+    ("ar", "Arabic"),
+    ("de", "German"),
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fr", "French"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("nl", "Dutch"),
+    ("pt", "Portuguese"),
+    ("ru", "Russian"),
+    ("zh", "Chinese"),
+];
+
+type DetectFn = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// Detect the language of `text` (as an ISO 639-1 code, e.g.: `fr`) from its script (Arabic,
+/// Chinese, Cyrillic, Japanese, Korean) or, for the texts in Latin script, from its most common
+/// words (Dutch, English, French, German, Italian, Portuguese, Spanish). Returns `None` if the
+/// language is not recognized.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let script = |range: std::ops::RangeInclusive<char>| text.chars().any(|c| range.contains(&c));
+    if script('\u{3040}'..='\u{30ff}') {
+        return Some("ja");
+    }
+    if script('\u{ac00}'..='\u{d7af}') {
+        return Some("ko");
+    }
+    if script('\u{4e00}'..='\u{9fff}') {
+        return Some("zh");
+    }
+    if script('\u{0400}'..='\u{04ff}') {
+        return Some("ru");
+    }
+    if script('\u{0600}'..='\u{06ff}') {
+        return Some("ar");
+    }
+
+    let words = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    let scores = COMMON_WORDS
+        .iter()
+        .map(|(language, common)| {
+            let score = words
+                .iter()
+                .filter(|word| common.contains(&word.as_str()))
+                .count();
+            (*language, score)
+        })
+        .collect::<Vec<_>>();
+    let best = scores.iter().map(|(_, score)| *score).max()?;
+    let mut best_languages = scores.iter().filter(|(_, score)| *score == best);
+    match (best_languages.next(), best_languages.next()) {
+        // Not recognized, or ambiguous
+        (Some((language, _)), None) if best > 0 => Some(*language),
+        _ => None,
+    }
+}
+
+/// Language policy of an agent (see the [module](self) docs).
+#[derive(Clone)]
+pub struct LanguagePolicy {
+    /// Language of the answers, whatever the language of the prompts
+    forced: Option<String>,
+    /// Language of the answers to the prompts whose language is not detected
+    fallback: Option<String>,
+    /// Variants of the preamble, by language
+    preambles: HashMap<String, String>,
+    detect: DetectFn,
+}
+
+impl LanguagePolicy {
+    /// Answer in the language of the prompts
+    pub fn user_language() -> Self {
+        Self {
+            forced: None,
+            fallback: None,
+            preambles: HashMap::new(),
+            detect: Arc::new(|text| detect_language(text).map(str::to_string)),
+        }
+    }
+
+    /// Answer in `language`, whatever the language of the prompts
+    pub fn forced(language: &str) -> Self {
+        Self {
+            forced: Some(language.to_string()),
+            ..Self::user_language()
+        }
+    }
+
+    /// Answer in `language` when the language of the prompt is not detected
+    pub fn fallback(mut self, language: &str) -> Self {
+        self.fallback = Some(language.to_string());
+        self
+    }
+
+    /// Use `preamble` (instead of the preamble of the agent) when answering in `language`
+    pub fn preamble(mut self, language: &str, preamble: &str) -> Self {
+        self.preambles
+            .insert(language.to_string(), preamble.to_string());
+        self
+    }
+
+    /// Detect the language of the prompts with `detect` (e.g.: a language identification
+    /// library), returning their ISO 639-1 code
+    pub fn detector(
+        mut self,
+        detect: impl Fn(&str) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.detect = Arc::new(detect);
+        self
+    }
+
+    /// Language of the answer to `prompt`, if any
+    pub fn language(&self, prompt: Option<&str>) -> Option<String> {
+        self.forced
+            .clone()
+            .or_else(|| prompt.and_then(|prompt| (self.detect)(prompt)))
+            .or_else(|| self.fallback.clone())
+    }
+
+    /// Preamble of the agent (of preamble `preamble`) when answering `prompt`
+    pub(crate) fn apply(&self, preamble: &str, prompt: Option<&str>) -> String {
+        let Some(language) = self.language(prompt) else {
+            return preamble.to_string();
+        };
+        tracing::debug!(target: "rig", "Answering in language {}", language);
+
+        let name = LANGUAGE_NAMES
+            .iter()
+            .find(|(code, _)| *code == language)
+            .map(|(_, name)| name.to_string())
+            .unwrap_or_else(|| language.clone());
+        let preamble = self
+            .preambles
+            .get(&language)
+            .map(String::as_str)
+            .unwrap_or(preamble);
+        if preamble.is_empty() {
+            format!("Answer in {name}.")
+        } else {
+            format!("{preamble}\n\nAnswer in {name}.")
+        }
+    }
+}
+
+impl std::fmt::Debug for LanguagePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LanguagePolicy")
+            .field("forced", &self.forced)
+            .field("fallback", &self.fallback)
+            .field("preambles", &self.preambles.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::Prompt,
+        providers::mock::{self, MockResponse},
+    };
+
+    #[tokio::test]
+    async fn test_language_policy() {
+        assert_eq!(
+            detect_language("Bonjour, comment changer mon mot de passe ?"),
+            Some("fr")
+        );
+        assert_eq!(detect_language("Wie ist das Wetter in Berlin?"), Some("de"));
+        assert_eq!(detect_language("Как дела?"), Some("ru"));
+        assert_eq!(detect_language("こんにちは"), Some("ja"));
+        assert_eq!(detect_language("42"), None);
+
+        let model = mock::CompletionModel::new().default_response(MockResponse::text("OK"));
+        let agent = model
+            .agent()
+            .preamble("You are the support agent of Acme.")
+            .language_policy(
+                LanguagePolicy::user_language()
+                    .preamble("fr", "Tu es l'agent de support d'Acme.")
+                    .fallback("en"),
+            )
+            .build();
+        agent
+            .prompt("Bonjour, comment changer mon mot de passe ?")
+            .await
+            .unwrap();
+        agent.prompt("¿Cómo estás?").await.unwrap();
+        agent.prompt("42").await.unwrap();
+
+        let preambles = model
+            .requests()
+            .into_iter()
+            .map(|request| request.preamble.unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            preambles,
+            vec![
+                "Tu es l'agent de support d'Acme.\n\nAnswer in French.",
+                "You are the support agent of Acme.\n\nAnswer in Spanish.",
+                "You are the support agent of Acme.\n\nAnswer in English.",
+            ]
+        );
+
+        let forced = LanguagePolicy::forced("de");
+        assert_eq!(
+            forced.apply("You are helpful.", Some("Bonjour !")),
+            "You are helpful.\n\nAnswer in German."
+        );
+    }
+}
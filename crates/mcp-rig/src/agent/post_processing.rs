@@ -0,0 +1,527 @@
+//! Post-processing of the answers of an agent into the exact format their consumers need (e.g.:
+//! plain text for a SMS gateway, the code of a code generator, JSON for an API, HTML for a web
+//! page), without regexes in the application.
+//!
+//! A [PostProcessor] is a pipeline of steps, applied in order to each answer of the agent:
+//! - [strip_markdown](PostProcessor::strip_markdown): the markdown formatting is removed,
+//! - [extract_code_block](PostProcessor::extract_code_block): the content of the first fenced
+//!   code block (of a language) is extracted,
+//! - [enforce_json](PostProcessor::enforce_json): the first JSON value of the answer (e.g.:
+//!   wrapped in a code block or in a sentence) is extracted, or the prompt fails,
+//! - [to_html](PostProcessor::to_html): the markdown is converted to HTML (the raw HTML of the
+//!   answer is escaped, and only the `http`, `https` and `mailto` links are kept),
+//! - [map](PostProcessor::map): a custom step.
+//!
+//! The answers of [prompt](crate::completion::Prompt::prompt) and
+//! [chat](crate::completion::Chat::chat) are post-processed, not the streamed answers.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{agent::PostProcessor, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("Answer with the JSON of the order.")
+//!     .post_processor(PostProcessor::new().enforce_json())
+//!     .build();
+//!
+//! // e.g.: `{"item":"pizza","quantity":2}`, even if the model answered
+//! // "Here is the order: ```json {...} ```"
+//! let order = agent.prompt("Two pizzas, please.").await?;
+//! ```
+use std::sync::{Arc, OnceLock};
+
+use regex::Regex;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PostProcessError {
+    /// The answer has no code block (of the expected language)
+    #[error("No code block in the answer")]
+    NoCodeBlock,
+
+    /// The answer has no valid JSON value
+    #[error("The answer is not valid JSON: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+
+    /// A custom step failed
+    #[error("{0}")]
+    Custom(String),
+}
+
+type StepFn = Arc<dyn Fn(String) -> Result<String, PostProcessError> + Send + Sync>;
+
+/// Pipeline of post-processing steps of the answers of an agent (see the [module](self) docs).
+#[derive(Clone, Default)]
+pub struct PostProcessor {
+    /// Steps, by name
+    steps: Vec<(String, StepFn)>,
+}
+
+impl PostProcessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn step(
+        mut self,
+        name: &str,
+        step: impl Fn(String) -> Result<String, PostProcessError> + Send + Sync + 'static,
+    ) -> Self {
+        self.steps.push((name.to_string(), Arc::new(step)));
+        self
+    }
+
+    /// Remove the markdown formatting of the answers (e.g.: `**bold**` becomes `bold`, and
+    /// `[docs](https://docs.rs)` becomes `docs (https://docs.rs)`)
+    pub fn strip_markdown(self) -> Self {
+        self.step("strip_markdown", |answer| Ok(strip_markdown(&answer)))
+    }
+
+    /// Extract the content of the first fenced code block of the answers, of `language` (e.g.:
+    /// `rust`) if any. Fails if there is none.
+    pub fn extract_code_block(self, language: Option<&str>) -> Self {
+        let language = language.map(str::to_lowercase);
+        self.step("extract_code_block", move |answer| {
+            blocks(&answer)
+                .into_iter()
+                .find_map(|block| match block {
+                    Block::Code {
+                        language: code_language,
+                        code,
+                    } if language.is_none()
+                        || code_language.as_deref().map(str::to_lowercase) == language =>
+                    {
+                        Some(code)
+                    }
+                    _ => None,
+                })
+                .ok_or(PostProcessError::NoCodeBlock)
+        })
+    }
+
+    /// Extract the first JSON object or array of the answers (serialized compactly). Fails if
+    /// there is none.
+    pub fn enforce_json(self) -> Self {
+        self.step("enforce_json", |answer| {
+            Ok(serde_json::to_string(&extract_json(&answer)?)?)
+        })
+    }
+
+    /// Convert the markdown of the answers to HTML
+    pub fn to_html(self) -> Self {
+        self.step("to_html", |answer| Ok(markdown_to_html(&answer)))
+    }
+
+    /// Apply the custom step `name` to the answers (e.g.: truncate them, or add a signature)
+    pub fn map(
+        self,
+        name: &str,
+        step: impl Fn(String) -> Result<String, PostProcessError> + Send + Sync + 'static,
+    ) -> Self {
+        self.step(name, step)
+    }
+
+    /// Apply the steps of the post-processor to `answer`, in order
+    pub fn apply(&self, answer: String) -> Result<String, PostProcessError> {
+        self.steps.iter().try_fold(answer, |answer, (name, step)| {
+            tracing::debug!(target: "rig", "Post-processing the answer: {}", name);
+            step(answer)
+        })
+    }
+}
+
+impl std::fmt::Debug for PostProcessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostProcessor")
+            .field(
+                "steps",
+                &self.steps.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Block of a markdown text
+#[derive(Debug)]
+enum Block {
+    Code {
+        language: Option<String>,
+        code: String,
+    },
+    Heading {
+        level: usize,
+        text: String,
+    },
+    List {
+        ordered: bool,
+        items: Vec<String>,
+    },
+    Quote(String),
+    Rule,
+    Paragraph(String),
+}
+
+/// Level and text of the heading `line`, if it is one
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = &line[level..];
+    ((1..=6).contains(&level) && (text.is_empty() || text.starts_with(' ')))
+        .then(|| (level, text.trim()))
+}
+
+/// Whether the list item `line` is ordered, and its text, if it is one
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    if let Some(text) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+    {
+        return Some((false, text.trim()));
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let text = &line[digits..];
+    (digits > 0)
+        .then(|| text.strip_prefix(". ").or_else(|| text.strip_prefix(") ")))
+        .flatten()
+        .map(|text| (true, text.trim()))
+}
+
+/// Whether `line` is a horizontal rule (e.g.: `---`)
+fn is_rule(line: &str) -> bool {
+    let chars = line
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<Vec<_>>();
+    chars.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|rule| chars.iter().all(|c| c == rule))
+}
+
+/// Whether `line` starts a block other than a paragraph
+fn starts_block(line: &str) -> bool {
+    line.starts_with("```")
+        || line.starts_with('>')
+        || heading(line).is_some()
+        || is_rule(line)
+        || list_item(line).is_some()
+}
+
+/// Blocks of the `markdown` text
+fn blocks(markdown: &str) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut lines = markdown.lines().peekable();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(info) = line.strip_prefix("```") {
+            let language = Some(info.trim())
+                .filter(|language| !language.is_empty())
+                .map(str::to_string);
+            let mut code = vec![];
+            for line in lines.by_ref() {
+                if line.trim_start().starts_with("```") {
+                    break;
+                }
+                code.push(line);
+            }
+            blocks.push(Block::Code {
+                language,
+                code: code.join("\n"),
+            });
+        } else if let Some((level, text)) = heading(line) {
+            blocks.push(Block::Heading {
+                level,
+                text: text.trim_end_matches('#').trim().to_string(),
+            });
+        } else if is_rule(line) {
+            blocks.push(Block::Rule);
+        } else if let Some((ordered, text)) = list_item(line) {
+            let mut items = vec![text.to_string()];
+            while let Some((true, text)) = lines
+                .peek()
+                .and_then(|line| list_item(line.trim()))
+                .map(|(item_ordered, text)| (item_ordered == ordered, text))
+            {
+                items.push(text.to_string());
+                lines.next();
+            }
+            blocks.push(Block::List { ordered, items });
+        } else if let Some(text) = line.strip_prefix('>') {
+            let mut quote = vec![text.trim()];
+            while let Some(text) = lines.peek().and_then(|line| line.trim().strip_prefix('>')) {
+                quote.push(text.trim());
+                lines.next();
+            }
+            blocks.push(Block::Quote(quote.join("\n")));
+        } else {
+            let mut paragraph = vec![line];
+            while let Some(line) = lines
+                .peek()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !starts_block(line))
+            {
+                paragraph.push(line);
+                lines.next();
+            }
+            blocks.push(Block::Paragraph(paragraph.join("\n")));
+        }
+    }
+    blocks
+}
+
+/// Patterns of the inline formatting (outside of the code spans)
+struct Inline {
+    image: Regex,
+    link: Regex,
+    bold: Regex,
+    italic: Regex,
+}
+
+fn inline() -> &'static Inline {
+    static INLINE: OnceLock<Inline> = OnceLock::new();
+    INLINE.get_or_init(|| {
+        let regex = |pattern| Regex::new(pattern).expect("Markdown pattern should be valid");
+        Inline {
+            image: regex(r"!\[([^\]]*)\]\(([^)\s]+)[^)]*\)"),
+            link: regex(r"\[([^\]]+)\]\(([^)\s]+)[^)]*\)"),
+            bold: regex(r"\*\*(\S(?:.*?\S)?)\*\*|__(\S(?:.*?\S)?)__"),
+            italic: regex(r"\*(\S(?:[^*]*?\S)?)\*|\b_(\S(?:[^_]*?\S)?)_\b"),
+        }
+    })
+}
+
+/// `text` with its inline formatting rendered by `render_text` (for the text outside of the
+/// code spans) and `code` (for the code spans)
+fn render_inline(
+    text: &str,
+    render_text: impl Fn(&str) -> String,
+    code: fn(&str) -> String,
+) -> String {
+    let segments = text.split('`').collect::<Vec<_>>();
+    let mut rendered = String::new();
+    for (index, segment) in segments.iter().enumerate() {
+        match index % 2 {
+            // A code span, unless its backtick is not closed
+            1 if index + 1 < segments.len() => rendered.push_str(&code(segment)),
+            1 => rendered.push_str(&render_text(&format!("`{segment}"))),
+            _ => rendered.push_str(&render_text(segment)),
+        }
+    }
+    rendered
+}
+
+/// `text` without its inline formatting
+fn strip_inline(text: &str) -> String {
+    render_inline(
+        text,
+        |text| {
+            let inline = inline();
+            let text = inline.image.replace_all(text, "$1");
+            let text = inline.link.replace_all(&text, "$1 ($2)");
+            let text = inline.bold.replace_all(&text, "$1$2");
+            inline.italic.replace_all(&text, "$1$2").into_owned()
+        },
+        str::to_string,
+    )
+}
+
+/// `markdown` as plain text
+fn strip_markdown(markdown: &str) -> String {
+    blocks(markdown)
+        .into_iter()
+        .filter_map(|block| match block {
+            Block::Code { code, .. } => Some(code),
+            Block::Heading { text, .. } | Block::Quote(text) | Block::Paragraph(text) => {
+                Some(strip_inline(&text))
+            }
+            Block::List { ordered, items } => Some(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| match ordered {
+                        true => format!("{}. {}", index + 1, strip_inline(item)),
+                        false => format!("- {}", strip_inline(item)),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            Block::Rule => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// `text` escaped for HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Whether the link `url` is kept in the HTML (i.e.: not a `javascript:` or `data:` URL)
+fn is_safe_url(url: &str) -> bool {
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme.to_lowercase())
+        .filter(|scheme| !scheme.contains(['/', '?', '#']));
+    matches!(scheme.as_deref(), None | Some("http" | "https" | "mailto"))
+}
+
+/// Inline formatting of `text` as HTML
+fn inline_html(text: &str) -> String {
+    render_inline(
+        text,
+        |text| {
+            let inline = inline();
+            let text = escape_html(text);
+            let text = inline
+                .image
+                .replace_all(&text, |captures: &regex::Captures| {
+                    match is_safe_url(&captures[2]) {
+                        true => format!("<img src=\"{}\" alt=\"{}\">", &captures[2], &captures[1]),
+                        false => captures[1].to_string(),
+                    }
+                });
+            let text =
+                inline
+                    .link
+                    .replace_all(&text, |captures: &regex::Captures| {
+                        match is_safe_url(&captures[2]) {
+                            true => format!("<a href=\"{}\">{}</a>", &captures[2], &captures[1]),
+                            false => captures[1].to_string(),
+                        }
+                    });
+            let text = inline.bold.replace_all(&text, "<strong>$1$2</strong>");
+            inline
+                .italic
+                .replace_all(&text, "<em>$1$2</em>")
+                .into_owned()
+        },
+        |code| format!("<code>{}</code>", escape_html(code)),
+    )
+}
+
+/// `markdown` as HTML
+fn markdown_to_html(markdown: &str) -> String {
+    blocks(markdown)
+        .into_iter()
+        .map(|block| match block {
+            Block::Code { language, code } => match language {
+                Some(language) => format!(
+                    "<pre><code class=\"language-{}\">{}</code></pre>",
+                    escape_html(&language),
+                    escape_html(&code)
+                ),
+                None => format!("<pre><code>{}</code></pre>", escape_html(&code)),
+            },
+            Block::Heading { level, text } => {
+                format!("<h{level}>{}</h{level}>", inline_html(&text))
+            }
+            Block::List { ordered, items } => {
+                let tag = if ordered { "ol" } else { "ul" };
+                let items = items
+                    .iter()
+                    .map(|item| format!("<li>{}</li>", inline_html(item)))
+                    .collect::<String>();
+                format!("<{tag}>{items}</{tag}>")
+            }
+            Block::Quote(text) => {
+                format!("<blockquote><p>{}</p></blockquote>", inline_html(&text))
+            }
+            Block::Rule => "<hr>".to_string(),
+            Block::Paragraph(text) => format!("<p>{}</p>", inline_html(&text)),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// First JSON object or array of `text` (e.g.: wrapped in a code block or in a sentence)
+fn extract_json(text: &str) -> Result<serde_json::Value, serde_json::Error> {
+    let error = match serde_json::from_str(text.trim()) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+    text.match_indices(['{', '['])
+        .find_map(|(start, _)| {
+            serde_json::Deserializer::from_str(&text[start..])
+                .into_iter::<serde_json::Value>()
+                .next()
+                .and_then(Result::ok)
+        })
+        .ok_or(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::Prompt,
+        providers::mock::{self, MockResponse},
+    };
+
+    #[tokio::test]
+    async fn test_post_processor() {
+        let answer =
+            "# Order\n\nHere is **your** order, see [the menu](https://acme.com/menu):\n\n\
+                      ```json\n{\"item\": \"pizza\", \"quantity\": 2}\n```\n\n\
+                      - Pay `cash` <b>only</b>\n- [Click](javascript:steal)";
+
+        assert_eq!(
+            PostProcessor::new()
+                .strip_markdown()
+                .apply(answer.into())
+                .unwrap(),
+            "Order\n\nHere is your order, see the menu (https://acme.com/menu):\n\n\
+             {\"item\": \"pizza\", \"quantity\": 2}\n\n\
+             - Pay cash <b>only</b>\n- Click (javascript:steal)"
+        );
+        assert_eq!(
+            PostProcessor::new()
+                .extract_code_block(Some("json"))
+                .apply(answer.into())
+                .unwrap(),
+            "{\"item\": \"pizza\", \"quantity\": 2}"
+        );
+        assert!(matches!(
+            PostProcessor::new()
+                .extract_code_block(Some("rust"))
+                .apply(answer.into()),
+            Err(PostProcessError::NoCodeBlock)
+        ));
+        assert_eq!(
+            PostProcessor::new().to_html().apply(answer.into()).unwrap(),
+            "<h1>Order</h1>\n\
+             <p>Here is <strong>your</strong> order, see \
+             <a href=\"https://acme.com/menu\">the menu</a>:</p>\n\
+             <pre><code class=\"language-json\">{&quot;item&quot;: &quot;pizza&quot;, \
+             &quot;quantity&quot;: 2}</code></pre>\n\
+             <ul><li>Pay <code>cash</code> &lt;b&gt;only&lt;/b&gt;</li><li>Click</li></ul>"
+        );
+
+        // The steps are applied in order
+        let model = mock::CompletionModel::new().default_response(MockResponse::text(answer));
+        let agent = model
+            .agent()
+            .post_processor(
+                PostProcessor::new()
+                    .enforce_json()
+                    .map("uppercase", |answer| Ok(answer.to_uppercase())),
+            )
+            .build();
+        assert_eq!(
+            agent.prompt("Two pizzas, please.").await.unwrap(),
+            "{\"ITEM\":\"PIZZA\",\"QUANTITY\":2}"
+        );
+
+        let model = mock::CompletionModel::new().default_response(MockResponse::text("Sorry!"));
+        let agent = model
+            .agent()
+            .post_processor(PostProcessor::new().enforce_json())
+            .build();
+        assert!(agent.prompt("Two pizzas, please.").await.is_err());
+    }
+}
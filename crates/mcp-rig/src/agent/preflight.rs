@@ -0,0 +1,266 @@
+//! Preflight validation of agents, for services to fail fast at startup (e.g.: on an invalid
+//! API key or an unreachable MCP server) rather than on the first prompt of a user.
+//!
+//! [Agent::preflight] checks that each tool of the agent is ready to be called (for the MCP
+//! tools: that their server is reachable and still lists them). The checks of the provider are
+//! opted into on the [Preflight]:
+//! - [Preflight::credentials]: the [HealthCheck] of the provider client.
+//! - [Preflight::model]: the model is listed by the provider (see [ListModels]).
+//! - [Preflight::warm_up]: a 1-token completion with the model of the agent, which validates
+//!   the credentials and the model of any provider (and opens its connections).
+//!
+//! The checks run concurrently, and their outcomes are collected in a [ReadinessReport].
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::providers::openai;
+//!
+//! let openai = openai::Client::from_env();
+//! let agent = openai.agent(openai::GPT_4O).mcp_tool(tool, client).build();
+//!
+//! let report = agent
+//!     .preflight()
+//!     .credentials(&openai)
+//!     .model(&openai, openai::GPT_4O)
+//!     .run()
+//!     .await;
+//! if !report.is_ready() {
+//!     for check in report.failures() {
+//!         eprintln!("{} failed: {}", check.name, check.error.as_deref().unwrap_or_default());
+//!     }
+//!     std::process::exit(1);
+//! }
+//! ```
+use std::time::Duration;
+
+use futures::future::{self, BoxFuture};
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+use super::Agent;
+use crate::{completion::CompletionModel, health::HealthCheck, models::ListModels};
+
+/// Kind of a [PreflightCheck].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckKind {
+    /// Credentials of the provider client
+    Credentials,
+    /// Availability of the model
+    Model,
+    /// Readiness of a tool
+    Tool,
+    /// Check added with [Preflight::check]
+    Custom,
+}
+
+/// Outcome of a check of a [Preflight].
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PreflightCheck {
+    /// Name of the check (i.e.: the name of the tool for the tool checks)
+    pub name: String,
+    pub kind: CheckKind,
+    pub passed: bool,
+    /// Error of the check, if it failed
+    pub error: Option<String>,
+    pub latency: Duration,
+}
+
+/// Readiness of an agent, as checked by a [Preflight].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ReadinessReport {
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl ReadinessReport {
+    /// Whether all the checks passed
+    pub fn is_ready(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    /// Checks which failed
+    pub fn failures(&self) -> impl Iterator<Item = &PreflightCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+
+    /// Names of the tools of the agent
+    pub fn tools(&self) -> impl Iterator<Item = &str> {
+        self.checks
+            .iter()
+            .filter(|check| check.kind == CheckKind::Tool)
+            .map(|check| check.name.as_str())
+    }
+}
+
+type Check<'a> = BoxFuture<'a, Result<(), String>>;
+
+/// Preflight validation of an agent (see the [module](self) docs).
+pub struct Preflight<'a, M: CompletionModel> {
+    agent: &'a Agent<M>,
+    checks: Vec<(String, CheckKind, Check<'a>)>,
+}
+
+impl<'a, M: CompletionModel> Preflight<'a, M> {
+    /// Check the credentials of the provider client `provider` (e.g.: the client the model of
+    /// the agent was created from)
+    pub fn credentials(mut self, provider: &'a impl HealthCheck) -> Self {
+        let check = Box::pin(async move {
+            let health = provider.health().await;
+            if health.healthy {
+                Ok(())
+            } else {
+                Err(format!(
+                    "Provider unhealthy (authentication: {:?}): {}",
+                    health.auth,
+                    health.error.unwrap_or_default()
+                ))
+            }
+        });
+        self.checks
+            .push(("credentials".to_string(), CheckKind::Credentials, check));
+        self
+    }
+
+    /// Check that the model `model` is available from the provider client `provider`
+    pub fn model(mut self, provider: &'a impl ListModels, model: &str) -> Self {
+        let name = model.to_string();
+        let check = Box::pin(async move {
+            provider
+                .validate_model(&name)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        });
+        self.checks
+            .push((model.to_string(), CheckKind::Model, check));
+        self
+    }
+
+    /// Send a 1-token completion request to the model of the agent
+    pub fn warm_up(mut self) -> Self {
+        let agent = self.agent;
+        let check = Box::pin(async move {
+            agent
+                .model
+                .completion_request("ping")
+                .max_tokens(1)
+                .send()
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        });
+        self.checks
+            .push(("warm_up".to_string(), CheckKind::Model, check));
+        self
+    }
+
+    /// Add the check `name` (e.g.: that a database is reachable), failing with its error
+    pub fn check(
+        mut self,
+        name: &str,
+        check: impl std::future::Future<Output = Result<(), String>> + Send + 'a,
+    ) -> Self {
+        self.checks
+            .push((name.to_string(), CheckKind::Custom, Box::pin(check)));
+        self
+    }
+
+    /// Run the checks concurrently, and report their outcomes
+    pub async fn run(self) -> ReadinessReport {
+        let agent = self.agent;
+        let start = Instant::now();
+        let tools = async {
+            agent
+                .tools
+                .readiness()
+                .await
+                .into_iter()
+                .map(|(name, result)| PreflightCheck {
+                    name,
+                    kind: CheckKind::Tool,
+                    passed: result.is_ok(),
+                    error: result.err().map(|e| e.to_string()),
+                    latency: start.elapsed(),
+                })
+                .collect::<Vec<_>>()
+        };
+        let checks = future::join_all(self.checks.into_iter().map(
+            |(name, kind, check)| async move {
+                let start = Instant::now();
+                let result = check.await;
+                PreflightCheck {
+                    name,
+                    kind,
+                    passed: result.is_ok(),
+                    error: result.err(),
+                    latency: start.elapsed(),
+                }
+            },
+        ));
+        let (mut checks, tools) = future::join(checks, tools).await;
+        checks.extend(tools);
+
+        let report = ReadinessReport { checks };
+        if report.is_ready() {
+            tracing::info!(target: "rig", "Preflight passed ({} checks)", report.checks.len());
+        } else {
+            tracing::warn!(target: "rig",
+                "Preflight failed: {:?}",
+                report.failures().map(|check| &check.name).collect::<Vec<_>>()
+            );
+        }
+        report
+    }
+}
+
+impl<M: CompletionModel> Agent<M> {
+    /// Preflight validation of the agent (see [Preflight]). Only the tools are checked unless
+    /// the checks of the provider are added.
+    pub fn preflight(&self) -> Preflight<'_, M> {
+        Preflight {
+            agent: self,
+            checks: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        providers::mock::{self, MockResponse},
+        tool,
+    };
+
+    #[tokio::test]
+    async fn test_preflight() {
+        let model = mock::CompletionModel::new().default_response(MockResponse::text("pong"));
+        let agent = model
+            .agent()
+            .tool(tool::from_fn(
+                "echo",
+                "Echo the arguments",
+                json!({"type": "object"}),
+                |args: serde_json::Value| async move { Ok::<_, std::io::Error>(args) },
+            ))
+            .build();
+
+        let report = agent.preflight().warm_up().run().await;
+        assert!(report.is_ready());
+        assert_eq!(report.tools().collect::<Vec<_>>(), vec!["echo"]);
+        assert_eq!(model.requests()[0].max_tokens, Some(1));
+
+        let report = agent
+            .preflight()
+            .check("database", async { Err("Connection refused".to_string()) })
+            .run()
+            .await;
+        assert!(!report.is_ready());
+        let failures = report.failures().collect::<Vec<_>>();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].kind, CheckKind::Custom);
+        assert_eq!(failures[0].error.as_deref(), Some("Connection refused"));
+    }
+}
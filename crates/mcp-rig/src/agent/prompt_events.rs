@@ -0,0 +1,215 @@
+//! Execution of the prompts of an agent as a stream of events, e.g.: for a UI to render the
+//! progress of the agent (the documents it retrieved, the tools it is calling...) rather than
+//! waiting for its answer.
+//!
+//! [Agent::prompt_events] and [Agent::chat_events] run the prompt like
+//! [Agent::prompt](crate::completion::Prompt::prompt) (with the same tool calls, reflection,
+//! redaction and trace export), and stream its [PromptEvent]s as they happen. The stream ends
+//! with [PromptEvent::Completed], or with the error of the prompt.
+//!
+//! The model is not streamed: each of its responses is a single [PromptEvent::ModelDelta].
+//! The events carry the texts as the answer: re-hydrated by the
+//! [Redactor](crate::redaction::Redactor) of the agent (if any), and, for the answer of the
+//! model, post-processed by its [PostProcessor](super::PostProcessor) (if any).
+//!
+//! # Example
+//! ```rust
+//! use futures::StreamExt;
+//! use mcp_rig::{agent::PromptEvent, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//! let agent = openai.agent(openai::GPT_4O).mcp_tool(fetch, client).build();
+//!
+//! let mut events = agent.prompt_events("What's on the front page of HN?");
+//! while let Some(event) = events.next().await {
+//!     match event? {
+//!         PromptEvent::ToolCallStarted { name, .. } => println!("Calling {name}..."),
+//!         PromptEvent::ToolCallFinished { name, is_error, .. } => println!("{name} done ({is_error})"),
+//!         PromptEvent::Completed { answer } => println!("{answer}"),
+//!         _ => {}
+//!     }
+//! }
+//! ```
+use std::pin::Pin;
+
+use futures::{channel::mpsc, future, stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{Agent, PromptOptions};
+use crate::completion::{CompletionModel, Message, PromptError};
+
+/// Event of the execution of a prompt (see the [module](self) docs).
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PromptEvent {
+    /// Text generated by the model (i.e.: its answer, or its reasoning before a tool call)
+    ModelDelta { text: String },
+    /// Context documents and tool definitions of a completion request, once retrieved
+    RetrievalDone {
+        /// Ids of the documents
+        documents: Vec<String>,
+        /// Names of the tools
+        tools: Vec<String>,
+    },
+    /// Tool called by the model, before the call
+    ToolCallStarted {
+        id: String,
+        name: String,
+        arguments: Value,
+    },
+    /// Result of a tool call (`{"error": "..."}` if it failed)
+    ToolCallFinished {
+        id: String,
+        name: String,
+        result: String,
+        is_error: bool,
+    },
+    /// Final answer of the agent
+    Completed { answer: String },
+}
+
+/// Stream of the events of a prompt.
+pub type PromptEventStream<'a> =
+    Pin<Box<dyn Stream<Item = Result<PromptEvent, PromptError>> + Send + 'a>>;
+
+pub(crate) type PromptEventSender = mpsc::UnboundedSender<Result<PromptEvent, PromptError>>;
+
+impl<M: CompletionModel> Agent<M> {
+    /// Prompt the agent, and stream the events of the prompt (see the [module](self) docs)
+    pub fn prompt_events(&self, prompt: impl Into<Message>) -> PromptEventStream<'_> {
+        self.chat_events(prompt, vec![])
+    }
+
+    /// Chat with the agent, and stream the events of the prompt (see the [module](self) docs)
+    pub fn chat_events(
+        &self,
+        prompt: impl Into<Message>,
+        chat_history: Vec<Message>,
+    ) -> PromptEventStream<'_> {
+        let (sender, receiver) = mpsc::unbounded();
+        let options = PromptOptions::new().events(sender.clone());
+        let prompt = prompt.into();
+        let run = async move {
+            let result = self
+                .chat_traced(prompt, chat_history, &options, None)
+                .await
+                .map(|answer| PromptEvent::Completed { answer });
+            let _ = sender.unbounded_send(result);
+        };
+
+        // Drive the prompt while forwarding its events: the receiver ends once the prompt is
+        // done (and its senders dropped)
+        Box::pin(stream::select(
+            receiver,
+            stream::once(run).filter_map(|()| future::ready(None)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        agent::PostProcessor,
+        providers::mock::{self, MockResponse},
+        redaction::Redactor,
+        tool,
+    };
+
+    fn echo() -> impl tool::Tool {
+        tool::from_fn(
+            "echo",
+            "Echo the arguments",
+            json!({"type": "object"}),
+            |args: Value| async move { Ok::<_, std::io::Error>(args["text"].clone()) },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_prompt_events() {
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::tool_call("echo", json!({"text": "Hi"})))
+            .default_response(MockResponse::text("Hello!"));
+        let agent = model.agent().tool(echo()).build();
+
+        let events = agent
+            .prompt_events("Say hi")
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert!(matches!(
+            &events[..],
+            [
+                PromptEvent::RetrievalDone { tools, .. },
+                PromptEvent::ToolCallStarted { name, .. },
+                PromptEvent::ToolCallFinished { result, is_error: false, .. },
+                PromptEvent::Completed { answer },
+            ] if tools == &["echo"] && name == "echo" && result == "\"Hi\"" && answer == "\"Hi\""
+        ));
+
+        let events = agent
+            .prompt_events("Hello")
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            events[1..],
+            [
+                PromptEvent::ModelDelta {
+                    text: "Hello!".to_string()
+                },
+                PromptEvent::Completed {
+                    answer: "Hello!".to_string()
+                }
+            ]
+        );
+
+        // The events carry the re-hydrated and post-processed texts, as the answer
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::tool_call(
+                "echo",
+                json!({"text": "<EMAIL_1>"}),
+            ))
+            .default_response(MockResponse::text("**Mailed <EMAIL_1>**"));
+        let agent = model
+            .agent()
+            .tool(echo())
+            .redaction(Redactor::new())
+            .post_processor(PostProcessor::new().strip_markdown())
+            .build();
+        let events = agent
+            .prompt_events("Mail a@b.com")
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert!(matches!(
+            &events[1..],
+            [
+                PromptEvent::ToolCallStarted { arguments, .. },
+                PromptEvent::ToolCallFinished { result, .. },
+                PromptEvent::Completed { answer },
+            ] if arguments == &json!({"text": "a@b.com"})
+                && result == "\"a@b.com\""
+                && answer == "\"a@b.com\""
+        ));
+        let events = agent
+            .prompt_events("Mail a@b.com")
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            events[1..],
+            [
+                PromptEvent::ModelDelta {
+                    text: "Mailed a@b.com".to_string()
+                },
+                PromptEvent::Completed {
+                    answer: "Mailed a@b.com".to_string()
+                }
+            ]
+        );
+    }
+}
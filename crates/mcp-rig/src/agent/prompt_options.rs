@@ -0,0 +1,130 @@
+//! Per-prompt overrides of the parameters of an agent.
+//!
+//! [PromptOptions] override the temperature, maximum number of tokens, tool choice and
+//! additional parameters of an agent for a single prompt (see
+//! [Agent::prompt_with](super::Agent::prompt_with)), without rebuilding the agent (and thus
+//! re-registering its MCP tools).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{agent::PromptOptions, completion::ToolChoice, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//! let agent = openai.agent(openai::GPT_4O).temperature(0.9).mcp_tool(fetch, client).build();
+//!
+//! // Deterministic answer, fetching the page first
+//! let answer = agent
+//!     .prompt_with(
+//!         "Summarize https://example.com",
+//!         PromptOptions::new()
+//!             .temperature(0.0)
+//!             .tool_choice(ToolChoice::Tool("fetch".to_string())),
+//!     )
+//!     .await?;
+//! ```
+use super::prompt_events::{PromptEvent, PromptEventSender};
+use crate::{
+    completion::{CompletionModel, CompletionRequestBuilder, ToolChoice},
+    tool::{Extensions, ToolSnapshot},
+};
+
+/// Overrides of the parameters of an agent for a single prompt (see the [module](self) docs).
+/// The parameters which are not set are those of the agent.
+#[derive(Clone, Debug, Default)]
+pub struct PromptOptions {
+    temperature: Option<f64>,
+    max_tokens: Option<u64>,
+    tool_choice: Option<ToolChoice>,
+    /// Merged into the additional parameters of the agent
+    additional_params: Option<serde_json::Value>,
+    /// Request-scoped extensions passed to the tools (see [Extensions])
+    pub(crate) extensions: Extensions,
+    /// Sender of the events of the prompt (see [Agent::chat_events](super::Agent::chat_events))
+    events: Option<PromptEventSender>,
+    /// Snapshot of the tool registry of the agent, taken when the prompt started (see
+    /// [AgentBuilder::tool_registry](super::AgentBuilder::tool_registry))
+    pub(crate) tools: Option<ToolSnapshot>,
+}
+
+impl PromptOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u64) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    /// Set additional parameters, merged into those of the agent (overriding them on
+    /// conflict)
+    pub fn additional_params(mut self, params: impl Into<serde_json::Value>) -> Self {
+        self.additional_params = Some(params.into());
+        self
+    }
+
+    /// Pass the request-scoped `extensions` to the tools called by the agent (see
+    /// [Tool::call_with_extensions](crate::tool::Tool::call_with_extensions))
+    pub fn extensions(mut self, extensions: Extensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    pub(crate) fn events(mut self, events: PromptEventSender) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub(crate) fn tools(mut self, tools: ToolSnapshot) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    /// Overrides changing the answer of the model, e.g.: to key the answers of the
+    /// [SemanticCache](super::SemanticCache)
+    pub(crate) fn overrides(&self) -> serde_json::Value {
+        serde_json::json!({
+            "temperature": self.temperature,
+            "max_tokens": self.max_tokens,
+            "tool_choice": self.tool_choice,
+            "additional_params": self.additional_params,
+        })
+    }
+
+    /// Send `event` to the event stream of the prompt (if any)
+    pub(crate) fn emit(&self, event: PromptEvent) {
+        if let Some(events) = &self.events {
+            let _ = events.unbounded_send(Ok(event));
+        }
+    }
+
+    /// Override the parameters of the completion request `builder` (of the agent)
+    pub(crate) fn apply<M: CompletionModel>(
+        &self,
+        mut builder: CompletionRequestBuilder<M>,
+    ) -> CompletionRequestBuilder<M> {
+        if let Some(temperature) = self.temperature {
+            builder = builder.temperature(temperature);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            builder = builder.max_tokens(max_tokens);
+        }
+        if let Some(tool_choice) = &self.tool_choice {
+            builder = builder.tool_choice(tool_choice.clone());
+        }
+        if let Some(params) = &self.additional_params {
+            builder = builder.additional_params(params.clone());
+        }
+        builder
+    }
+}
@@ -0,0 +1,150 @@
+//! ReAct-style traces of the prompts of an agent: the thoughts of the model, the tools it
+//! called, their observations (i.e.: results), and the final answer, in order.
+//!
+//! A [PromptTrace] is returned alongside the response by
+//! [Agent::prompt_with_trace](super::Agent::prompt_with_trace) and
+//! [Agent::chat_with_trace](super::Agent::chat_with_trace), e.g.: to debug why an agent chose
+//! a tool. Unlike an [AgentTrace](crate::observability::AgentTrace), it is not exported to an
+//! observability platform, but can be serialized to JSON.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::providers::openai;
+//!
+//! let openai = openai::Client::from_env();
+//! let agent = openai.agent(openai::GPT_4O).mcp_tool(fetch, client).build();
+//!
+//! let (answer, trace) = agent.prompt_with_trace("What's on the front page of HN?").await?;
+//! for (name, arguments) in trace.tool_calls() {
+//!     println!("Called {name} with {arguments}");
+//! }
+//! println!("{}", trace.to_json_pretty());
+//! ```
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::Trimmed;
+use crate::{
+    completion::AssistantContent,
+    message::ToolCall,
+    tool::{error_result, ToolSetError},
+};
+
+/// Step of a [PromptTrace].
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TraceStep {
+    /// Text of the model accompanying a tool call (i.e.: its reasoning)
+    Thought { text: String },
+    /// Tool called by the model
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: Value,
+    },
+    /// Result of a tool call, as returned to the model (`{"error": "..."}` if it failed)
+    Observation {
+        id: String,
+        name: String,
+        result: String,
+        is_error: bool,
+    },
+    /// Content dropped from the request to fit it into the context window of the model (see
+    /// [ContextWindow](super::ContextWindow))
+    ContextTrimmed { dropped: Vec<Trimmed> },
+    /// Critique of the answer by the critic of the agent (see
+    /// [Reflection](super::Reflection)), and whether it accepted the answer
+    Critique { text: String, accepted: bool },
+    /// Final answer of the agent
+    Answer { text: String },
+}
+
+/// Trace of the steps of a prompt of an agent (see the [module](self) docs).
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct PromptTrace {
+    pub steps: Vec<TraceStep>,
+}
+
+impl PromptTrace {
+    /// Name and arguments of the tools called by the model, in order
+    pub fn tool_calls(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.steps.iter().filter_map(|step| match step {
+            TraceStep::ToolCall {
+                name, arguments, ..
+            } => Some((name.as_str(), arguments)),
+            _ => None,
+        })
+    }
+
+    /// Final answer of the agent (i.e.: its last answer, if it revised it), if it answered
+    pub fn answer(&self) -> Option<&str> {
+        self.steps.iter().rev().find_map(|step| match step {
+            TraceStep::Answer { text } => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Record the thoughts and the tool call of a response of the model calling `tool_call`
+    pub(crate) fn record_tool_call<'a>(
+        &mut self,
+        response: impl IntoIterator<Item = &'a AssistantContent>,
+        tool_call: &ToolCall,
+    ) {
+        for content in response {
+            if let AssistantContent::Text(text) = content {
+                if !text.text.trim().is_empty() {
+                    self.steps.push(TraceStep::Thought {
+                        text: text.text.clone(),
+                    });
+                }
+            }
+        }
+        self.steps.push(TraceStep::ToolCall {
+            id: tool_call.id.clone(),
+            name: tool_call.function.name.clone(),
+            arguments: tool_call.function.arguments.clone(),
+        });
+    }
+
+    pub(crate) fn record_observation(
+        &mut self,
+        tool_call: &ToolCall,
+        result: &Result<String, ToolSetError>,
+    ) {
+        let (result, is_error) = match result {
+            Ok(output) => (output.clone(), false),
+            Err(error) => (error_result(error), true),
+        };
+        self.steps.push(TraceStep::Observation {
+            id: tool_call.id.clone(),
+            name: tool_call.function.name.clone(),
+            result,
+            is_error,
+        });
+    }
+
+    pub(crate) fn record_trimmed(&mut self, dropped: Vec<Trimmed>) {
+        self.steps.push(TraceStep::ContextTrimmed { dropped });
+    }
+
+    pub(crate) fn record_critique(&mut self, text: &str, accepted: bool) {
+        self.steps.push(TraceStep::Critique {
+            text: text.to_string(),
+            accepted,
+        });
+    }
+
+    pub(crate) fn record_answer(&mut self, text: &str) {
+        self.steps.push(TraceStep::Answer {
+            text: text.to_string(),
+        });
+    }
+}
@@ -0,0 +1,129 @@
+//! Self-critique (i.e.: reflection) of the answers of an agent.
+//!
+//! With a [Reflection], the draft answer of an agent is reviewed by a critic (the model of the
+//! agent, or another agent), and revised by the agent according to the critique, up to
+//! `rounds` times or until the critique accepts the answer. By default, the critic is asked to
+//! answer [APPROVED] when the answer needs no change; other acceptance criteria are set with
+//! [Reflection::accept_if].
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{agent::Reflection, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! // Reviewed by the same model
+//! let agent = openai.agent(openai::GPT_4O).reflect(2).build();
+//!
+//! // Reviewed by another agent
+//! let critic = openai.agent(openai::GPT_4O_MINI).build();
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .reflection(
+//!         Reflection::new(2)
+//!             .critic(critic)
+//!             .criteria("The answer must cite its sources."),
+//!     )
+//!     .build();
+//! ```
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+
+use crate::completion::{Prompt, PromptError};
+
+/// Answer of the critic accepting the answer (with the default acceptance criteria).
+pub const APPROVED: &str = "APPROVED";
+
+/// Preamble of the critic when it is the model of the agent.
+pub(crate) const CRITIC_PREAMBLE: &str =
+    "You are a demanding reviewer. You check answers for errors, omissions and unclear parts.";
+
+type Critic = Arc<dyn Fn(String) -> BoxFuture<'static, Result<String, PromptError>> + Send + Sync>;
+
+type Acceptance = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Self-critique of the answers of an agent (see the [module](self) docs).
+#[derive(Clone)]
+pub struct Reflection {
+    /// Maximum number of critiques (and thus revisions) of an answer
+    pub(crate) rounds: usize,
+    /// Critic of the answers (the model of the agent if unset)
+    pub(crate) critic: Option<Critic>,
+    /// Additional criteria of the critic
+    criteria: Option<String>,
+    /// Whether a critique accepts the answer
+    accept: Acceptance,
+}
+
+impl Reflection {
+    /// Review (and revise) the answers of the agent up to `rounds` times
+    pub fn new(rounds: usize) -> Self {
+        Self {
+            rounds,
+            critic: None,
+            criteria: None,
+            accept: Arc::new(|critique| critique.trim().starts_with(APPROVED)),
+        }
+    }
+
+    /// Have `critic` (e.g.: an agent using another model) review the answers, instead of the
+    /// model of the agent
+    pub fn critic(mut self, critic: impl Prompt + 'static) -> Self {
+        let critic = Arc::new(critic);
+        self.critic = Some(Arc::new(move |prompt| {
+            let critic = critic.clone();
+            Box::pin(async move { critic.prompt(prompt).await })
+        }));
+        self
+    }
+
+    /// Add criteria the answers must meet (e.g.: "The answer must cite its sources.")
+    pub fn criteria(mut self, criteria: &str) -> Self {
+        self.criteria = Some(criteria.to_string());
+        self
+    }
+
+    /// Accept the answers whose critique satisfies `accept` (by default, the critiques
+    /// starting with [APPROVED])
+    pub fn accept_if(mut self, accept: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.accept = Arc::new(accept);
+        self
+    }
+
+    /// Whether `critique` accepts the answer
+    pub(crate) fn accepts(&self, critique: &str) -> bool {
+        (self.accept)(critique)
+    }
+
+    /// Prompt asking the critic to review the `answer` to `question`
+    pub(crate) fn critique_prompt(&self, question: &str, answer: &str) -> String {
+        let criteria = self
+            .criteria
+            .as_ref()
+            .map(|criteria| format!("\nCriteria: {criteria}\n"))
+            .unwrap_or_default();
+        format!(
+            "Review the following answer to the question.\n{criteria}\n\
+            <question>\n{question}\n</question>\n\n<answer>\n{answer}\n</answer>\n\n\
+            If the answer is correct and complete, reply with {APPROVED} only. Otherwise, list \
+            the problems of the answer and how to fix them."
+        )
+    }
+
+    /// Prompt asking the agent to revise its answer according to `critique`
+    pub(crate) fn revision_prompt(&self, critique: &str) -> String {
+        format!(
+            "A reviewer made the following critique of your answer:\n\n{critique}\n\n\
+            Revise your answer accordingly. Reply with the revised answer only."
+        )
+    }
+}
+
+impl std::fmt::Debug for Reflection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reflection")
+            .field("rounds", &self.rounds)
+            .field("criteria", &self.criteria)
+            .finish()
+    }
+}
@@ -0,0 +1,366 @@
+//! Semantic cache of the answers of an agent, so that the prompts similar to a prompt already
+//! answered (e.g.: the FAQ-style traffic of a support agent) are answered without prompting the
+//! model again.
+//!
+//! With a [SemanticCache], the prompts of the agent are embedded, and:
+//! - if the embedding of a prompt previously answered is at least `threshold` similar, its answer
+//!   is returned,
+//! - otherwise the agent is prompted, and its answer is cached.
+//!
+//! Only the prompts without chat history are cached, and only their successful answers without
+//! tool calls (the answer of a run calling a tool is its result, e.g.: the confirmation of a side
+//! effect which must not be replayed). With a [Redactor](crate::redaction::Redactor), the
+//! redacted prompts and answers are cached (the answers being re-hydrated with the values of the
+//! prompt they answer). The entries of the cache are scoped to the tenant of the agent (if any)
+//! and to the session of the prompt, unless shared by the sessions of the tenant (see
+//! [SemanticCache::shared]), and to the model, preamble and overrides (see
+//! [PromptOptions](super::PromptOptions)) of the prompt. They expire after the TTL of the cache
+//! (if any), the oldest ones being evicted over its maximum number of entries.
+//!
+//! Cloning a cache returns a handle to the same entries, so that it can be shared by agents.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use mcp_rig::{agent::SemanticCache, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//! let embedding_model = openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+//!
+//! // The answers are not personal, so they are shared by the sessions
+//! let cache = SemanticCache::new(embedding_model, 0.95)
+//!     .ttl(Duration::from_secs(3600))
+//!     .shared();
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("You are the support agent of Acme.")
+//!     .semantic_cache(cache)
+//!     .build();
+//!
+//! agent.prompt("How do I reset my password?").await?;
+//! // Answered from the cache
+//! agent.prompt("how can I reset my password").await?;
+//! ```
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+use web_time::Instant;
+
+use crate::{
+    embeddings::{distance::VectorDistance, Embedding, EmbeddingError, EmbeddingModel},
+    observability::TraceContext,
+};
+
+type EmbedFn =
+    Arc<dyn Fn(String) -> BoxFuture<'static, Result<Embedding, EmbeddingError>> + Send + Sync>;
+
+/// Cached answer of a prompt
+struct CacheEntry {
+    scope: String,
+    embedding: Embedding,
+    answer: String,
+    created: Instant,
+}
+
+/// Result of a lookup in a [SemanticCache]
+pub(crate) enum Lookup {
+    /// Answer of a similar prompt
+    Hit(String),
+    /// No similar prompt: the answer of the prompt is to be inserted in `scope`
+    Miss { scope: String, embedding: Embedding },
+}
+
+/// Semantic cache of the answers of agents (see the [module](self) docs).
+#[derive(Clone)]
+pub struct SemanticCache {
+    /// Cosine similarity from which two prompts have the same answer
+    threshold: f64,
+    ttl: Option<Duration>,
+    max_entries: Option<usize>,
+    /// Whether the entries are shared by the sessions of the tenant
+    shared: bool,
+    /// Embeds prompts with the embedding model of the cache
+    embed: EmbedFn,
+    entries: Arc<Mutex<Vec<CacheEntry>>>,
+}
+
+impl SemanticCache {
+    /// Return the cached answer of the prompts at least `threshold` similar (i.e.: the cosine
+    /// similarity of their embeddings by `embedding_model`) to a prompt previously answered
+    pub fn new(embedding_model: impl EmbeddingModel + 'static, threshold: f64) -> Self {
+        Self {
+            threshold,
+            ttl: None,
+            max_entries: None,
+            shared: false,
+            embed: Arc::new(move |prompt| {
+                let embedding_model = embedding_model.clone();
+                Box::pin(async move { embedding_model.embed_text(&prompt).await })
+            }),
+            entries: Default::default(),
+        }
+    }
+
+    /// Expire the answers `ttl` after they were cached
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Keep at most `max_entries` answers, evicting the oldest ones
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Share the answers cached in a session (i.e.: the `session_id` of the [TraceContext] of
+    /// the agent) with the other sessions of the tenant, e.g.: for the answers without personal
+    /// information. By default, the answers are only returned in their session, and the prompts
+    /// without session are not cached.
+    pub fn shared(mut self) -> Self {
+        self.shared = true;
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<CacheEntry>> {
+        self.entries.lock().expect("Semantic cache lock poisoned")
+    }
+
+    /// Number of the answers in the cache (including the expired ones not evicted yet)
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Remove all the answers from the cache
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Scope of the entries of the agent of `context` with the parameters `key` (e.g.: the hash
+    /// of its model and preamble), if its prompts can be cached
+    fn scope(&self, context: &TraceContext, key: &str) -> Option<String> {
+        let tenant = context.tenant_id.as_deref().unwrap_or_default();
+        if self.shared {
+            return Some(format!("{tenant}#{key}"));
+        }
+        let session = context.session_id.as_deref()?;
+        Some(format!("{tenant}/{session}#{key}"))
+    }
+
+    /// Look up the answer of `prompt` of the agent of `context` with the parameters `key`.
+    /// Returns `None` if the prompt can't be cached (e.g.: its embedding failed).
+    pub(crate) async fn lookup(
+        &self,
+        context: &TraceContext,
+        key: &str,
+        prompt: &str,
+    ) -> Option<Lookup> {
+        let scope = self.scope(context, key)?;
+        let embedding = match (self.embed)(prompt.to_string()).await {
+            Ok(embedding) => embedding,
+            Err(e) => {
+                tracing::warn!(target: "rig", "Failed to embed the prompt for the semantic cache: {}", e);
+                return None;
+            }
+        };
+
+        let mut entries = self.lock();
+        if let Some(ttl) = self.ttl {
+            entries.retain(|entry| entry.created.elapsed() < ttl);
+        }
+        let best = entries
+            .iter()
+            .filter(|entry| entry.scope == scope)
+            .map(|entry| (entry, embedding.cosine_similarity(&entry.embedding, false)))
+            .filter(|(_, similarity)| *similarity >= self.threshold)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+        Some(match best {
+            Some((entry, similarity)) => {
+                tracing::debug!(target: "rig", "Semantic cache hit (similarity {:.3})", similarity);
+                Lookup::Hit(entry.answer.clone())
+            }
+            None => Lookup::Miss { scope, embedding },
+        })
+    }
+
+    /// Cache `answer` of the prompt of the missed lookup `scope` and `embedding`
+    pub(crate) fn insert(&self, scope: String, embedding: Embedding, answer: &str) {
+        let mut entries = self.lock();
+        entries.push(CacheEntry {
+            scope,
+            embedding,
+            answer: answer.to_string(),
+            created: Instant::now(),
+        });
+        if let Some(max_entries) = self.max_entries {
+            let excess = entries.len().saturating_sub(max_entries);
+            entries.drain(..excess);
+        }
+    }
+}
+
+impl std::fmt::Debug for SemanticCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SemanticCache")
+            .field("threshold", &self.threshold)
+            .field("ttl", &self.ttl)
+            .field("max_entries", &self.max_entries)
+            .field("shared", &self.shared)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        agent::PromptOptions,
+        completion::{Chat, Message, Prompt},
+        providers::mock::{self, MockResponse},
+        redaction::Redactor,
+        tool,
+    };
+
+    #[tokio::test]
+    async fn test_semantic_cache() {
+        let embedding_model = mock::EmbeddingModel::new(2)
+            .embedding("How do I reset my password?", vec![1.0, 0.0])
+            .embedding("how can I reset my password", vec![0.98, 0.1])
+            .embedding("Where is my order?", vec![0.0, 1.0]);
+        let cache = SemanticCache::new(embedding_model, 0.95)
+            .max_entries(10)
+            .shared();
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::text("Click on 'Forgot password'."))
+            .push(MockResponse::text("It ships tomorrow."))
+            .default_response(MockResponse::text("Click on 'Forgot password' (session)."));
+
+        let agent = model.agent().semantic_cache(cache.clone()).build();
+        assert_eq!(
+            agent.prompt("How do I reset my password?").await.unwrap(),
+            "Click on 'Forgot password'."
+        );
+        // A similar prompt is answered from the cache, another one by the model
+        assert_eq!(
+            agent.prompt("how can I reset my password").await.unwrap(),
+            "Click on 'Forgot password'."
+        );
+        assert_eq!(
+            agent.prompt("Where is my order?").await.unwrap(),
+            "It ships tomorrow."
+        );
+        assert_eq!(model.requests().len(), 2);
+        assert_eq!(cache.len(), 2);
+
+        // The answers are scoped to the overrides of the parameters of the agent
+        agent
+            .prompt_with(
+                "How do I reset my password?",
+                &PromptOptions::new().temperature(0.0),
+            )
+            .await
+            .unwrap();
+        assert_eq!(model.requests().len(), 3);
+        assert_eq!(cache.len(), 3);
+
+        // The prompts with a chat history are not cached
+        agent
+            .chat("How do I reset my password?", vec![Message::user("Hi!")])
+            .await
+            .unwrap();
+        assert_eq!(model.requests().len(), 4);
+        assert_eq!(cache.len(), 3);
+
+        // By default, the answers are not shared with the other sessions, and the prompts
+        // without session are not cached
+        let session_cache = SemanticCache::new(mock::EmbeddingModel::new(2), 0.95);
+        let session_agent = |session: &str| {
+            model
+                .agent()
+                .trace_context(TraceContext::new("support").session_id(session))
+                .semantic_cache(session_cache.clone())
+                .build()
+        };
+        session_agent("a")
+            .prompt("How do I reset my password?")
+            .await
+            .unwrap();
+        session_agent("a")
+            .prompt("How do I reset my password?")
+            .await
+            .unwrap();
+        assert_eq!(model.requests().len(), 5);
+        session_agent("b")
+            .prompt("How do I reset my password?")
+            .await
+            .unwrap();
+        assert_eq!(model.requests().len(), 6);
+        let anonymous_agent = model.agent().semantic_cache(session_cache.clone()).build();
+        anonymous_agent.prompt("Where is my order?").await.unwrap();
+        assert_eq!(session_cache.len(), 2);
+
+        // With a redactor, the redacted prompts and answers are cached
+        let embedding_model = mock::EmbeddingModel::new(2);
+        let cache = SemanticCache::new(embedding_model.clone(), 0.95).shared();
+        let model = mock::CompletionModel::new()
+            .default_response(MockResponse::text("Write to <EMAIL_1>."));
+        let agent = model
+            .agent()
+            .redaction(Redactor::new())
+            .semantic_cache(cache)
+            .build();
+        assert_eq!(
+            agent.prompt("Who handles a@b.com?").await.unwrap(),
+            "Write to a@b.com."
+        );
+        assert_eq!(
+            agent.prompt("Who handles c@d.org?").await.unwrap(),
+            "Write to c@d.org."
+        );
+        assert_eq!(model.requests().len(), 1);
+        assert_eq!(
+            embedding_model.requests()[0],
+            vec!["Who handles <EMAIL_1>?".to_string()]
+        );
+
+        // The runs calling tools are not cached: their answer is the result of the tool, which
+        // is called again
+        let cache = SemanticCache::new(mock::EmbeddingModel::new(2), 0.95).shared();
+        let model = mock::CompletionModel::new()
+            .default_response(MockResponse::tool_call("send", json!({ "to": "a@b.com" })));
+        let sent = Arc::new(AtomicUsize::new(0));
+        let agent = model
+            .agent()
+            .tool(tool::from_fn(
+                "send",
+                "Send the email",
+                json!({ "type": "object" }),
+                {
+                    let sent = sent.clone();
+                    move |args: serde_json::Value| {
+                        sent.fetch_add(1, Ordering::SeqCst);
+                        async move { Ok::<_, std::io::Error>(format!("Sent to {}.", args["to"])) }
+                    }
+                },
+            ))
+            .semantic_cache(cache.clone())
+            .build();
+        agent.prompt("Send it to a@b.com").await.unwrap();
+        agent.prompt("Send it to a@b.com").await.unwrap();
+        assert_eq!(model.requests().len(), 2);
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+//! Client-side enforcement of stop sequences and output budgets.
+//!
+//! Not all providers support stop sequences, and some ignore the maximum number of tokens of
+//! the request. With [StopConditions], an agent cuts its answers (and its streamed answers) at
+//! the first stop sequence, or once they exceed a maximum number of characters or (estimated)
+//! tokens, whatever the provider returned, protecting the consumers of the answers from
+//! runaway generations.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{agent::StopConditions, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .stop_conditions(
+//!         StopConditions::new()
+//!             .stop_sequence("</answer>")
+//!             .max_tokens(500),
+//!     )
+//!     .build();
+//! ```
+use async_stream::stream;
+use futures::StreamExt;
+
+use crate::streaming::{StreamingChoice, StreamingResult};
+
+/// Number of characters per token, to estimate the number of tokens of an answer.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Stop sequences and output budget of the answers of an agent (see the [module](self) docs).
+#[derive(Clone, Debug, Default)]
+pub struct StopConditions {
+    stop_sequences: Vec<String>,
+    max_chars: Option<usize>,
+    max_tokens: Option<usize>,
+}
+
+impl StopConditions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cut the answers at the first occurrence of `sequence` (excluded)
+    pub fn stop_sequence(mut self, sequence: &str) -> Self {
+        if !sequence.is_empty() {
+            self.stop_sequences.push(sequence.to_string());
+        }
+        self
+    }
+
+    /// Cut the answers after `max_chars` characters
+    pub fn max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = Some(max_chars);
+        self
+    }
+
+    /// Cut the answers after about `max_tokens` tokens (estimated at 4 characters per token)
+    pub fn max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Maximum number of characters of the answers, if any
+    fn budget(&self) -> Option<usize> {
+        let tokens = self
+            .max_tokens
+            .map(|tokens| tokens.saturating_mul(CHARS_PER_TOKEN));
+        match (self.max_chars, tokens) {
+            (Some(chars), Some(tokens)) => Some(chars.min(tokens)),
+            (chars, tokens) => chars.or(tokens),
+        }
+    }
+
+    /// Cut `text` at its first stop sequence, or after the budget of characters. Returns the
+    /// (possibly unchanged) text, and whether it was cut.
+    pub fn apply<'a>(&self, text: &'a str) -> (&'a str, bool) {
+        let mut end = self
+            .stop_sequences
+            .iter()
+            .filter_map(|sequence| text.find(sequence.as_str()))
+            .min()
+            .unwrap_or(text.len());
+        if let Some(budget) = self.budget() {
+            if let Some((index, _)) = text.char_indices().nth(budget) {
+                end = end.min(index);
+            }
+        }
+        (&text[..end], end < text.len())
+    }
+
+    /// Enforce the stop conditions on the text chunks of `stream`: the stream ends at the
+    /// first stop sequence (even if split across chunks), or once the budget of characters is
+    /// exhausted. Tool call chunks are passed through.
+    pub fn guard_stream(&self, mut stream: StreamingResult) -> StreamingResult {
+        let conditions = self.clone();
+        // Length of the text held back, as it could be the start of a stop sequence
+        let holdback = conditions
+            .stop_sequences
+            .iter()
+            .map(|sequence| sequence.len() - 1)
+            .max()
+            .unwrap_or_default();
+
+        Box::pin(stream! {
+            // Text of the answer so far (sent or not), and length of the sent text
+            let mut text = String::new();
+            let mut sent = 0;
+
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(StreamingChoice::Message(chunk)) => {
+                        text.push_str(&chunk);
+                        let (allowed, stopped) = conditions.apply(&text);
+                        let end = if stopped {
+                            allowed.len()
+                        } else {
+                            floor_char_boundary(&text, text.len().saturating_sub(holdback))
+                        };
+                        if end > sent {
+                            yield Ok(StreamingChoice::Message(text[sent..end].to_string()));
+                            sent = end;
+                        }
+                        if stopped {
+                            tracing::info!(target: "rig", "Stopped the answer of the agent");
+                            return;
+                        }
+                    }
+                    other => {
+                        if text.len() > sent {
+                            yield Ok(StreamingChoice::Message(text[sent..].to_string()));
+                            sent = text.len();
+                        }
+                        yield other;
+                    }
+                }
+            }
+
+            if text.len() > sent {
+                yield Ok(StreamingChoice::Message(text[sent..].to_string()));
+            }
+        })
+    }
+}
+
+/// Largest char boundary of `text` not greater than `index`
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    (0..=index)
+        .rev()
+        .find(|&index| text.is_char_boundary(index))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+    use crate::completion::CompletionError;
+
+    #[test]
+    fn test_apply() {
+        let conditions = StopConditions::new().stop_sequence("STOP").max_chars(10);
+        assert_eq!(conditions.apply("Hello STOP world"), ("Hello ", true));
+        assert_eq!(conditions.apply("Hello world!"), ("Hello worl", true));
+        assert_eq!(conditions.apply("Hello"), ("Hello", false));
+
+        let conditions = StopConditions::new().max_tokens(1);
+        assert_eq!(conditions.apply("héllo"), ("héll", true));
+    }
+
+    #[tokio::test]
+    async fn test_guard_stream() {
+        let chunks = ["Hello ", "wor", "ld ST", "OP and more", "!"]
+            .map(|chunk| Ok::<_, CompletionError>(StreamingChoice::Message(chunk.to_string())));
+        let guarded = StopConditions::new()
+            .stop_sequence("STOP")
+            .guard_stream(Box::pin(stream::iter(chunks)));
+
+        let text = guarded
+            .map(|chunk| chunk.unwrap().to_string())
+            .collect::<Vec<_>>()
+            .await
+            .concat();
+        assert_eq!(text, "Hello world ");
+    }
+}
@@ -0,0 +1,216 @@
+//! Time-aware context: the current date and time, timezone and locale of the user are injected
+//! into the preamble of the agent with each prompt, since the models don't know what day it is
+//! (e.g.: "what's on today?", "book it for next Friday").
+//!
+//! With a [TimeContext], the preamble of the agent is rendered before each prompt:
+//! - its template variables are replaced: `{{date}}` (e.g.: `2024-05-04`), `{{time}}` (e.g.:
+//!   `01:30`), `{{weekday}}` (e.g.: `Saturday`), `{{datetime}}` (e.g.:
+//!   `Saturday 2024-05-04 01:30`), `{{timezone}}` (e.g.: `Europe/Paris, UTC+02:00`) and
+//!   `{{locale}}` (e.g.: `fr-FR`),
+//! - a line with the current date, time, timezone and locale is appended to it (unless
+//!   [TimeContext::variables_only] is set).
+//!
+//! The timezone is a fixed UTC offset (i.e.: the daylight saving time changes are not applied):
+//! it is to be set from the current offset of the user (e.g.: sent by their browser).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{agent::TimeContext, providers::openai};
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("You are a travel assistant. Today is {{weekday}} {{date}}.")
+//!     .time_context(TimeContext::new().timezone("Europe/Paris", 120).locale("fr-FR"))
+//!     .build();
+//! ```
+use std::{sync::Arc, time::Duration};
+
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::observability::utc;
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+type Clock = Arc<dyn Fn() -> SystemTime + Send + Sync>;
+
+/// Date, time, timezone and locale injected into the preamble of an agent (see the
+/// [module](self) docs).
+#[derive(Clone)]
+pub struct TimeContext {
+    /// Name of the timezone (e.g.: `Europe/Paris`), if any
+    timezone: Option<String>,
+    /// Offset of the timezone from UTC, in minutes
+    offset_minutes: i32,
+    locale: Option<String>,
+    /// Whether only the template variables are injected (no line appended to the preamble)
+    variables_only: bool,
+    clock: Clock,
+}
+
+impl Default for TimeContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeContext {
+    /// Inject the current date and time in UTC
+    pub fn new() -> Self {
+        Self {
+            timezone: None,
+            offset_minutes: 0,
+            locale: None,
+            variables_only: false,
+            clock: Arc::new(SystemTime::now),
+        }
+    }
+
+    /// Inject the date and time in the timezone `name` (e.g.: `Europe/Paris`), `offset_minutes`
+    /// from UTC (e.g.: `120` for UTC+02:00)
+    pub fn timezone(mut self, name: &str, offset_minutes: i32) -> Self {
+        self.timezone = Some(name.to_string());
+        self.offset_minutes = offset_minutes;
+        self
+    }
+
+    /// Inject the locale of the user (e.g.: `fr-FR`)
+    pub fn locale(mut self, locale: &str) -> Self {
+        self.locale = Some(locale.to_string());
+        self
+    }
+
+    /// Only replace the template variables of the preamble, without appending the date and time
+    /// to it
+    pub fn variables_only(mut self) -> Self {
+        self.variables_only = true;
+        self
+    }
+
+    /// Read the current time from `clock` (e.g.: a fixed time in tests)
+    pub fn clock(mut self, clock: impl Fn() -> SystemTime + Send + Sync + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Timezone, as injected (e.g.: `Europe/Paris, UTC+02:00`)
+    fn timezone_label(&self) -> String {
+        let sign = if self.offset_minutes < 0 { '-' } else { '+' };
+        let offset = self.offset_minutes.unsigned_abs();
+        let utc_offset = format!("UTC{sign}{:02}:{:02}", offset / 60, offset % 60);
+        match &self.timezone {
+            Some(name) => format!("{name}, {utc_offset}"),
+            None => utc_offset,
+        }
+    }
+
+    /// `preamble` with the current date and time injected
+    pub fn render(&self, preamble: &str) -> String {
+        let now = (self.clock)();
+        let offset = Duration::from_secs(self.offset_minutes.unsigned_abs() as u64 * 60);
+        let local = if self.offset_minutes < 0 {
+            now.checked_sub(offset).unwrap_or(UNIX_EPOCH)
+        } else {
+            now + offset
+        };
+
+        let (year, month, day, hour, minute, ..) = utc(local);
+        let days = local
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            / 86400;
+        // The Unix epoch was a Thursday
+        let weekday = WEEKDAYS[((days + 3) % 7) as usize];
+        let date = format!("{year:04}-{month:02}-{day:02}");
+        let time = format!("{hour:02}:{minute:02}");
+        let datetime = format!("{weekday} {date} {time}");
+        let timezone = self.timezone_label();
+        let locale = self.locale.clone().unwrap_or_default();
+
+        let mut rendered = [
+            ("{{datetime}}", &datetime),
+            ("{{date}}", &date),
+            ("{{time}}", &time),
+            ("{{weekday}}", &weekday.to_string()),
+            ("{{timezone}}", &timezone),
+            ("{{locale}}", &locale),
+        ]
+        .into_iter()
+        .fold(preamble.to_string(), |preamble, (variable, value)| {
+            preamble.replace(variable, value)
+        });
+
+        if !self.variables_only {
+            if !rendered.is_empty() {
+                rendered.push_str("\n\n");
+            }
+            rendered.push_str(&format!("Current date and time: {datetime} ({timezone})."));
+            if let Some(locale) = &self.locale {
+                rendered.push_str(&format!(" Locale of the user: {locale}."));
+            }
+        }
+        rendered
+    }
+}
+
+impl std::fmt::Debug for TimeContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimeContext")
+            .field("timezone", &self.timezone_label())
+            .field("locale", &self.locale)
+            .field("variables_only", &self.variables_only)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::Prompt,
+        providers::mock::{self, MockResponse},
+    };
+
+    #[tokio::test]
+    async fn test_time_context() {
+        // 2024-05-03T23:30:00Z, i.e.: Saturday 2024-05-04 01:30 in Paris
+        let now = UNIX_EPOCH + Duration::from_secs(1_714_779_000);
+        let model = mock::CompletionModel::new().default_response(MockResponse::text("Hi!"));
+        let agent = model
+            .agent()
+            .preamble("You are a travel assistant. Today is {{weekday}} {{date}}.")
+            .time_context(
+                TimeContext::new()
+                    .timezone("Europe/Paris", 120)
+                    .locale("fr-FR")
+                    .clock(move || now),
+            )
+            .build();
+
+        agent.prompt("What's on today?").await.unwrap();
+        assert_eq!(
+            model.requests()[0].preamble.as_deref(),
+            Some(
+                "You are a travel assistant. Today is Saturday 2024-05-04.\n\n\
+                 Current date and time: Saturday 2024-05-04 01:30 (Europe/Paris, UTC+02:00). \
+                 Locale of the user: fr-FR."
+            )
+        );
+
+        let context = TimeContext::new()
+            .timezone("America/New_York", -240)
+            .variables_only()
+            .clock(move || now);
+        assert_eq!(
+            context.render("{{datetime}} {{timezone}}"),
+            "Friday 2024-05-03 19:30 America/New_York, UTC-04:00"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Dynamic selection of the tools sent to the model, for agents with many tools (e.g.: the
+//! tools of several MCP servers).
+//!
+//! With a [ToolSelection], an agent only sends the definitions of the `top_k` tools most
+//! relevant to each prompt (i.e.: whose name and description are the closest to the prompt),
+//! plus the pinned tools which are always sent. The definitions of the tools are embedded on
+//! first use, and their embeddings are cached. With the [ToolStats] of the agent (see
+//! [ToolSelection::stats]), the flaky tools are down-ranked.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{agent::ToolSelection, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//! let embedding_model = openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .mcp_tool(create_issue, github_client.clone())
+//!     .mcp_tool(search_code, github_client)
+//!     .mcp_tool(send_message, slack_client)
+//!     .tool(Calculator)
+//!     .tool_selection(ToolSelection::new(embedding_model, 5).pin("calculator"))
+//!     .build();
+//! ```
+use std::{collections::HashMap, sync::RwLock};
+
+use futures::future::BoxFuture;
+
+use crate::{
+    completion::ToolDefinition,
+    embeddings::{distance::VectorDistance, Embedding, EmbeddingError, EmbeddingModel},
+    tool_stats::ToolStats,
+};
+
+type EmbedFn = Box<
+    dyn Fn(Vec<String>) -> BoxFuture<'static, Result<Vec<Embedding>, EmbeddingError>> + Send + Sync,
+>;
+
+/// Selection of the `top_k` tools most relevant to each prompt (see the [module](self) docs).
+pub struct ToolSelection {
+    /// Number of (unpinned) tools sent with each prompt
+    top_k: usize,
+    /// Tools always sent to the model (by name)
+    pinned: Vec<String>,
+    /// Embeds texts with the embedding model of the selection
+    embed: EmbedFn,
+    /// Embeddings of the tool definitions (by tool name), computed on first use
+    embeddings: RwLock<HashMap<String, Embedding>>,
+    /// Usage statistics of the tools, down-ranking the flaky tools
+    stats: Option<ToolStats>,
+}
+
+impl ToolSelection {
+    /// Select the `top_k` tools most relevant to each prompt, using `embedding_model`
+    pub fn new(embedding_model: impl EmbeddingModel + 'static, top_k: usize) -> Self {
+        Self {
+            top_k,
+            pinned: vec![],
+            embed: Box::new(move |texts| {
+                let embedding_model = embedding_model.clone();
+                Box::pin(async move { embedding_model.embed_texts(texts).await })
+            }),
+            embeddings: Default::default(),
+            stats: None,
+        }
+    }
+
+    /// Always send the tool `toolname` to the model (in addition to the `top_k` selected tools)
+    pub fn pin(mut self, toolname: &str) -> Self {
+        self.pinned.push(toolname.to_string());
+        self
+    }
+
+    /// Down-rank the flaky tools according to `stats` (e.g.: the stats of the agent): the
+    /// similarity of a tool to the prompt is lowered by its failure rate (see
+    /// [ToolStats::reliability])
+    pub fn stats(mut self, stats: ToolStats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Pinned tools (by name)
+    pub fn pinned(&self) -> &[String] {
+        &self.pinned
+    }
+
+    /// Select among `definitions` the pinned tools and the `top_k` tools most relevant to
+    /// `prompt`, the most relevant first.
+    pub async fn select(
+        &self,
+        prompt: &str,
+        definitions: Vec<ToolDefinition>,
+    ) -> Result<Vec<ToolDefinition>, EmbeddingError> {
+        let (pinned, candidates): (Vec<_>, Vec<_>) = definitions
+            .into_iter()
+            .partition(|definition| self.pinned.contains(&definition.name));
+        if candidates.len() <= self.top_k {
+            return Ok([pinned, candidates].concat());
+        }
+
+        // Embed the prompt, along with the definitions not embedded yet
+        let new_tools = {
+            let embeddings = self
+                .embeddings
+                .read()
+                .expect("Tool embeddings lock poisoned");
+            candidates
+                .iter()
+                .filter(|definition| !embeddings.contains_key(&definition.name))
+                .collect::<Vec<_>>()
+        };
+        let texts = new_tools
+            .iter()
+            .map(|definition| format!("{}: {}", definition.name, definition.description))
+            .chain([prompt.to_string()])
+            .collect();
+        let mut new_embeddings = (self.embed)(texts).await?;
+        let prompt_embedding = new_embeddings.pop().ok_or_else(|| {
+            EmbeddingError::ResponseError("Missing embedding of the prompt".into())
+        })?;
+
+        let embeddings = {
+            let mut embeddings = self
+                .embeddings
+                .write()
+                .expect("Tool embeddings lock poisoned");
+            for (definition, embedding) in new_tools.into_iter().zip(new_embeddings) {
+                embeddings.insert(definition.name.clone(), embedding);
+            }
+            embeddings.clone()
+        };
+
+        let mut scored = candidates
+            .into_iter()
+            .filter_map(|definition| {
+                let score = embeddings
+                    .get(&definition.name)?
+                    .cosine_similarity(&prompt_embedding, false);
+                let penalty = self
+                    .stats
+                    .as_ref()
+                    .map(|stats| 1.0 - stats.reliability(&definition.name))
+                    .unwrap_or_default();
+                Some((score - penalty, definition))
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        tracing::debug!(target: "rig",
+            "Selected tools: {:?}",
+            scored.iter().take(self.top_k).map(|(_, definition)| &definition.name).collect::<Vec<_>>()
+        );
+
+        Ok(pinned
+            .into_iter()
+            .chain(
+                scored
+                    .into_iter()
+                    .take(self.top_k)
+                    .map(|(_, definition)| definition),
+            )
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::providers::mock;
+
+    fn definition(name: &str, description: &str) -> ToolDefinition {
+        ToolDefinition {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters: json!({"type": "object"}),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_select() {
+        let model = mock::EmbeddingModel::new(2)
+            .embedding("weather: Get the weather", vec![1.0, 0.0])
+            .embedding("stocks: Get a stock price", vec![0.0, 1.0])
+            .embedding("clock: Get the time", vec![0.6, 0.8])
+            .embedding("Will it rain?", vec![0.9, 0.1]);
+        let selection = ToolSelection::new(model.clone(), 1).pin("clock");
+
+        let definitions = vec![
+            definition("stocks", "Get a stock price"),
+            definition("weather", "Get the weather"),
+            definition("clock", "Get the time"),
+        ];
+        let names = |definitions: Vec<ToolDefinition>| {
+            definitions
+                .into_iter()
+                .map(|definition| definition.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(
+                selection
+                    .select("Will it rain?", definitions.clone())
+                    .await
+                    .unwrap()
+            ),
+            vec!["clock", "weather"]
+        );
+
+        // The tool definitions are only embedded once
+        selection
+            .select("Will it rain?", definitions)
+            .await
+            .unwrap();
+        assert_eq!(model.requests()[1], vec!["Will it rain?"]);
+    }
+}
@@ -0,0 +1,288 @@
+//! Stable, versioned JSON format of the full runs of an agent: its config, the messages of the
+//! conversation, the steps of the run (tool calls, observations...) and its token usage.
+//!
+//! A [Transcript] is returned alongside the answer by [Agent::chat_with_transcript], and can be
+//! saved to and loaded from a file, e.g.: to share a reproducible bug report, or as the golden
+//! file of a regression test (see [Transcript::check_golden]). Transcripts saved by older
+//! versions of the crate keep loading: the format only changes with a new
+//! [TRANSCRIPT_VERSION].
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{agent::Transcript, providers::openai, usage::{Pricing, UsageLedger}};
+//!
+//! let ledger = UsageLedger::new(Pricing::new().model("gpt-4o", 2.5, 10.0));
+//! ledger.clone().install();
+//!
+//! let agent = openai.agent(openai::GPT_4O).model_name("gpt-4o").tool(Adder).build();
+//! let (answer, transcript) = agent.chat_with_transcript("What is 1 + 2?", vec![]).await?;
+//! let transcript = transcript.usage_from(&ledger.records());
+//! transcript.save("bug-report.json")?;
+//!
+//! // Later, or on another machine
+//! let transcript = Transcript::load("bug-report.json")?;
+//! for (name, arguments) in transcript.tool_calls() {
+//!     println!("Called {name} with {arguments}");
+//! }
+//! ```
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{Agent, AgentConfig, PromptTrace, TraceStep};
+use crate::{
+    completion::{CompletionModel, Message, PromptError},
+    usage::UsageRecord,
+};
+
+/// Version of the transcript format written by this version of the crate.
+pub const TRANSCRIPT_VERSION: u32 = 1;
+
+/// Environment variable (re)writing the golden files instead of checking them (see
+/// [Transcript::check_golden]), e.g.: `UPDATE_GOLDEN=1 cargo test`.
+const UPDATE_GOLDEN: &str = "UPDATE_GOLDEN";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TranscriptError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// The transcript was written by a newer version of the crate
+    #[error("Unsupported transcript version {0} (latest supported: {TRANSCRIPT_VERSION})")]
+    UnsupportedVersion(u32),
+
+    /// The transcript differs from its golden file
+    #[error("Transcript differs from the golden file {}", .0.display())]
+    GoldenMismatch(PathBuf),
+}
+
+/// Token usage of a run.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptUsage {
+    /// Number of requests to the models
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Cost of the priced requests
+    pub cost: f64,
+}
+
+/// Transcript of a run of an agent (see the [module](self) docs).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transcript {
+    /// Version of the format (see [TRANSCRIPT_VERSION])
+    pub version: u32,
+    /// Config of the agent
+    pub config: AgentConfig,
+    /// Messages of the conversation: the chat history, the prompt and the answer
+    pub messages: Vec<Message>,
+    /// Steps of the run (i.e.: the thoughts, tool calls and observations of the agent)
+    #[serde(default)]
+    pub steps: Vec<TraceStep>,
+    #[serde(default)]
+    pub usage: TranscriptUsage,
+    /// Free-form metadata (e.g.: the description of a bug, the version of the application)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Transcript {
+    pub fn new(config: AgentConfig) -> Self {
+        Self {
+            version: TRANSCRIPT_VERSION,
+            config,
+            messages: vec![],
+            steps: vec![],
+            usage: TranscriptUsage::default(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// Add `message` to the messages of the conversation
+    pub fn message(mut self, message: Message) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    /// Add the steps of `trace` to the steps of the run
+    pub fn trace(mut self, trace: PromptTrace) -> Self {
+        self.steps.extend(trace.steps);
+        self
+    }
+
+    /// Add the usage of `records` (e.g.: the records made during the run by a
+    /// [UsageLedger](crate::usage::UsageLedger)) to the usage of the run
+    pub fn usage_from(mut self, records: &[UsageRecord]) -> Self {
+        for record in records {
+            self.usage.requests += 1;
+            self.usage.input_tokens += record.input_tokens;
+            self.usage.output_tokens += record.output_tokens;
+            self.usage.cost += record.cost.unwrap_or_default();
+        }
+        self
+    }
+
+    /// Add the metadata `key` (e.g.: `issue`)
+    pub fn metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Name and arguments of the tools called during the run, in order
+    pub fn tool_calls(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.steps.iter().filter_map(|step| match step {
+            TraceStep::ToolCall {
+                name, arguments, ..
+            } => Some((name.as_str(), arguments)),
+            _ => None,
+        })
+    }
+
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Parse a transcript, checking that its version is supported.
+    pub fn from_json(json: &str) -> Result<Self, TranscriptError> {
+        let value = serde_json::from_str::<Value>(json)?;
+        let version = value["version"].as_u64().unwrap_or_default() as u32;
+        if version > TRANSCRIPT_VERSION {
+            return Err(TranscriptError::UnsupportedVersion(version));
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Load a transcript from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TranscriptError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Save the transcript to a JSON file, creating its directory if needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TranscriptError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Check the transcript against the golden file at `path`, for regression tests. The
+    /// golden file is written if it doesn't exist, or if the `UPDATE_GOLDEN` environment
+    /// variable is set (e.g.: after an intended change of the behavior of the agent).
+    pub fn check_golden(&self, path: impl AsRef<Path>) -> Result<(), TranscriptError> {
+        let path = path.as_ref();
+        if !path.exists() || std::env::var_os(UPDATE_GOLDEN).is_some() {
+            return self.save(path);
+        }
+        if Self::load(path)? != *self {
+            return Err(TranscriptError::GoldenMismatch(path.to_path_buf()));
+        }
+        Ok(())
+    }
+}
+
+impl<M: CompletionModel> Agent<M> {
+    /// Chat with the agent, and return its answer along with the [Transcript] of the run. The
+    /// usage of the transcript is left empty, as it is recorded by the installed
+    /// [UsageLedger](crate::usage::UsageLedger) (see [Transcript::usage_from]).
+    pub async fn chat_with_transcript(
+        &self,
+        prompt: impl Into<Message> + Send,
+        chat_history: Vec<Message>,
+    ) -> Result<(String, Transcript), PromptError> {
+        let prompt = prompt.into();
+        let (answer, trace) = self
+            .chat_with_trace(prompt.clone(), chat_history.clone())
+            .await?;
+
+        let mut transcript = Transcript::new(self.to_config()).trace(trace);
+        transcript.messages = chat_history;
+        let transcript = transcript
+            .message(prompt)
+            .message(Message::assistant(answer.clone()));
+        Ok((answer, transcript))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        providers::mock::{self, MockResponse},
+        tool,
+        usage::{Pricing, UsageLedger},
+    };
+
+    #[tokio::test]
+    async fn test_transcript() {
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::tool_call("add", json!({ "x": 1, "y": 2 })));
+        let agent = model
+            .agent()
+            .model_name("mock")
+            .preamble("You are a calculator.")
+            .tool(tool::from_fn(
+                "add",
+                "Add x and y together",
+                json!({ "type": "object" }),
+                |args: Value| async move {
+                    Ok::<_, std::io::Error>(
+                        args["x"].as_i64().unwrap_or_default()
+                            + args["y"].as_i64().unwrap_or_default(),
+                    )
+                },
+            ))
+            .build();
+        let (answer, transcript) = agent
+            .chat_with_transcript("What is 1 + 2?", vec![Message::user("Hi!")])
+            .await
+            .unwrap();
+        assert_eq!(answer, "3");
+
+        let ledger = UsageLedger::new(Pricing::new().model("mock", 1.0, 2.0));
+        ledger.record("mock", 1_000_000, 500_000);
+        let transcript = transcript
+            .usage_from(&ledger.records())
+            .metadata("issue", "42");
+        assert_eq!(transcript.version, TRANSCRIPT_VERSION);
+        assert_eq!(transcript.config.model, "mock");
+        assert_eq!(transcript.messages.len(), 3);
+        assert_eq!(
+            transcript.tool_calls().collect::<Vec<_>>(),
+            vec![("add", &json!({ "x": 1, "y": 2 }))]
+        );
+        assert_eq!(transcript.usage.cost, 2.0);
+
+        // Saved and loaded transcripts are the same, and golden files catch regressions
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("golden/transcript.json");
+        transcript.check_golden(&path).unwrap();
+        assert_eq!(Transcript::load(&path).unwrap(), transcript);
+        transcript.check_golden(&path).unwrap();
+        assert!(matches!(
+            transcript
+                .clone()
+                .metadata("issue", "43")
+                .check_golden(&path),
+            Err(TranscriptError::GoldenMismatch(_))
+        ));
+
+        // Transcripts of newer versions are rejected
+        let mut newer = serde_json::to_value(&transcript).unwrap();
+        newer["version"] = json!(TRANSCRIPT_VERSION + 1);
+        assert!(matches!(
+            Transcript::from_json(&newer.to_string()),
+            Err(TranscriptError::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,147 @@
+//! Audio generation (text-to-speech) with the models of the providers supporting it (e.g.:
+//! [crate::providers::hyperbolic]).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{audio_generation::AudioGenerationModel, providers::hyperbolic};
+//!
+//! let hyperbolic = hyperbolic::Client::from_env();
+//! let model = hyperbolic.audio_generation_model();
+//!
+//! let response = model
+//!     .audio_generation_request("Hello, I am a flurbo!")
+//!     .voice("EN-US")
+//!     .speed(1.2)
+//!     .send()
+//!     .await?;
+//! std::fs::write("flurbo.mp3", response.audio)?;
+//! ```
+use std::future::Future;
+
+use crate::http_client::HttpClientError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AudioGenerationError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error building the audio generation request
+    #[error("RequestError: {0}")]
+    RequestError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Error parsing the audio generation response
+    #[error("ResponseError: {0}")]
+    ResponseError(String),
+
+    /// Error returned by the audio generation model provider
+    #[error("ProviderError: {0}")]
+    ProviderError(String),
+}
+
+impl From<HttpClientError> for AudioGenerationError {
+    fn from(error: HttpClientError) -> Self {
+        match error {
+            HttpClientError::HttpError(error) => AudioGenerationError::HttpError(error),
+            error => AudioGenerationError::RequestError(error.into()),
+        }
+    }
+}
+
+/// Request to an audio generation model.
+#[derive(Clone, Debug)]
+pub struct AudioGenerationRequest {
+    /// Text to speak
+    pub text: String,
+    /// Voice (or speaker) of the audio, if the model supports several
+    pub voice: Option<String>,
+    /// Speed of the speech (1.0 being the normal speed)
+    pub speed: Option<f32>,
+    /// Additional provider-specific parameters (e.g.: language)
+    pub additional_params: Option<serde_json::Value>,
+}
+
+/// Response of an audio generation model.
+#[derive(Clone, Debug)]
+pub struct AudioGenerationResponse<T> {
+    /// The generated audio (e.g.: MP3 bytes)
+    pub audio: Vec<u8>,
+    /// The raw response of the provider
+    pub response: T,
+}
+
+/// Trait for the models generating audio (speech) from a text.
+pub trait AudioGenerationModel: Clone + Send + Sync {
+    /// The raw response type returned by the provider
+    type Response: Send + Sync;
+
+    /// Generate audio from `request`.
+    fn audio_generation(
+        &self,
+        request: AudioGenerationRequest,
+    ) -> impl Future<Output = Result<AudioGenerationResponse<Self::Response>, AudioGenerationError>> + Send;
+
+    /// Create an audio generation request builder with the given text.
+    fn audio_generation_request(&self, text: &str) -> AudioGenerationRequestBuilder<Self> {
+        AudioGenerationRequestBuilder::new(self.clone(), text)
+    }
+}
+
+/// Builder of an [AudioGenerationRequest].
+pub struct AudioGenerationRequestBuilder<M: AudioGenerationModel> {
+    model: M,
+    text: String,
+    voice: Option<String>,
+    speed: Option<f32>,
+    additional_params: Option<serde_json::Value>,
+}
+
+impl<M: AudioGenerationModel> AudioGenerationRequestBuilder<M> {
+    pub fn new(model: M, text: &str) -> Self {
+        Self {
+            model,
+            text: text.to_string(),
+            voice: None,
+            speed: None,
+            additional_params: None,
+        }
+    }
+
+    /// Set the voice (or speaker) of the audio.
+    pub fn voice(mut self, voice: &str) -> Self {
+        self.voice = Some(voice.to_string());
+        self
+    }
+
+    /// Set the speed of the speech (1.0 being the normal speed).
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.speed = Some(speed);
+        self
+    }
+
+    /// Set additional provider-specific parameters of the request.
+    pub fn additional_params(mut self, additional_params: serde_json::Value) -> Self {
+        self.additional_params = Some(additional_params);
+        self
+    }
+
+    /// Build the request.
+    pub fn build(self) -> AudioGenerationRequest {
+        AudioGenerationRequest {
+            text: self.text,
+            voice: self.voice,
+            speed: self.speed,
+            additional_params: self.additional_params,
+        }
+    }
+
+    /// Build the request and send it to the model.
+    pub async fn send(self) -> Result<AudioGenerationResponse<M::Response>, AudioGenerationError> {
+        let model = self.model.clone();
+        model.audio_generation(self.build()).await
+    }
+}
@@ -0,0 +1,648 @@
+//! Benchmarks of agent configurations, e.g.: to choose between the Cohere, OpenAI and Groq
+//! backends of an agent, or between two preambles.
+//!
+//! A [Bench] runs the same [EvalCase]s against several configurations of an agent (any
+//! [BenchAgent], e.g.: an [Agent] of any provider), and measures for each of them:
+//! - the latency of the answers (mean and percentiles),
+//! - the token usage and cost of the requests, recorded in a [UsageLedger] (see
+//!   [Bench::usage]),
+//! - the number of tool calls,
+//! - the scores of the answers by the [Scorer]s of the bench (see the [evals](crate::evals)
+//!   module),
+//!
+//! and emits a [BenchReport] comparing them (as a table with its `Display` implementation, or
+//! as CSV or JSON).
+//!
+//! The configurations are run one after the other (their cases possibly concurrently, see
+//! [Bench::concurrency]). The usage of the requests of the agents is attributed to the
+//! experiment named after the bench and to the variant named after the configuration (see
+//! [UsageRecord](crate::usage::UsageRecord)), so that the requests of the scorers (e.g.: of an
+//! `LlmJudge`) and the other requests of the process are not counted.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     bench::Bench,
+//!     evals::{scorers::LlmJudge, EvalCase},
+//!     providers::{cohere, groq, openai},
+//!     usage::{Pricing, UsageLedger},
+//! };
+//!
+//! let ledger = UsageLedger::new(
+//!     Pricing::new()
+//!         .model("gpt-4o", 2.5, 10.0)
+//!         .model("command-r", 0.15, 0.6)
+//!         .model("llama-3.3-70b-versatile", 0.59, 0.79),
+//! );
+//! ledger.clone().install();
+//!
+//! let preamble = "You are a support agent for Acme.";
+//! let report = Bench::new("support-backends")
+//!     .case(EvalCase::new("refund", "How do I get a refund?").criterion("Mentions the 30 days limit"))
+//!     .case(EvalCase::new("order", "Where is my order 1234?").criterion("Looks the order up"))
+//!     .scorer(LlmJudge::new(openai.completion_model(openai::GPT_4O)))
+//!     .agent("openai", openai.agent(openai::GPT_4O).preamble(preamble).build())
+//!     .agent("cohere", cohere.agent(cohere::COMMAND_R).preamble(preamble).build())
+//!     .agent("groq", groq.agent("llama-3.3-70b-versatile").preamble(preamble).build())
+//!     .repetitions(5)
+//!     .usage(ledger)
+//!     .run()
+//!     .await;
+//!
+//! println!("{report}");
+//! std::fs::write("bench.csv", report.to_csv())?;
+//! ```
+use std::{fmt, time::Duration};
+
+use futures::{future::BoxFuture, stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+use crate::{
+    agent::Agent,
+    completion::{CompletionModel, PromptError},
+    evals::{score_output, EvalCase, Score, Scorer, ScorerDyn},
+    usage::UsageLedger,
+};
+
+/// Answer of a [BenchAgent] to a prompt.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchAnswer {
+    pub output: String,
+    /// Names of the tools called to answer, in order
+    pub tool_calls: Vec<String>,
+}
+
+/// Trait for the agents benchmarked by a [Bench] (e.g.: an [Agent]).
+pub trait BenchAgent: Send + Sync {
+    /// Answer `prompt`
+    fn answer<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, Result<BenchAnswer, PromptError>>;
+}
+
+impl<M: CompletionModel> BenchAgent for Agent<M> {
+    fn answer<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, Result<BenchAnswer, PromptError>> {
+        Box::pin(async move {
+            let (output, trace) = self.prompt_with_trace(prompt).await?;
+            Ok(BenchAnswer {
+                output,
+                tool_calls: trace
+                    .tool_calls()
+                    .map(|(name, _)| name.to_string())
+                    .collect(),
+            })
+        })
+    }
+}
+
+/// Benchmark of agent configurations (see the [module](self) docs).
+pub struct Bench {
+    name: String,
+    cases: Vec<EvalCase>,
+    scorers: Vec<Box<dyn ScorerDyn>>,
+    /// Configurations, by name
+    agents: Vec<(String, Box<dyn BenchAgent>)>,
+    /// Number of runs of each case per configuration
+    repetitions: usize,
+    concurrency: usize,
+    ledger: Option<UsageLedger>,
+}
+
+impl Bench {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            cases: vec![],
+            scorers: vec![],
+            agents: vec![],
+            repetitions: 1,
+            concurrency: 1,
+            ledger: None,
+        }
+    }
+
+    /// Add a case to the bench.
+    pub fn case(mut self, case: EvalCase) -> Self {
+        self.cases.push(case);
+        self
+    }
+
+    /// Add multiple cases to the bench.
+    pub fn cases(mut self, cases: impl IntoIterator<Item = EvalCase>) -> Self {
+        self.cases.extend(cases);
+        self
+    }
+
+    /// Add a scorer of the answers.
+    pub fn scorer(mut self, scorer: impl Scorer + 'static) -> Self {
+        self.scorers.push(Box::new(scorer));
+        self
+    }
+
+    /// Add the configuration `name` (e.g.: `openai-gpt-4o`), answering with `agent`.
+    pub fn agent(mut self, name: &str, agent: impl BenchAgent + 'static) -> Self {
+        self.agents.push((name.to_string(), Box::new(agent)));
+        self
+    }
+
+    /// Run each case `repetitions` times per configuration (1 by default), for more reliable
+    /// latency percentiles.
+    pub fn repetitions(mut self, repetitions: usize) -> Self {
+        self.repetitions = repetitions.max(1);
+        self
+    }
+
+    /// Set the number of cases run concurrently (1 by default). The configurations are always
+    /// run one after the other.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Measure the token usage and cost of the configurations from the records of `ledger`
+    /// (which should be installed, see [UsageLedger::install]), excluding the requests of the
+    /// scorers.
+    pub fn usage(mut self, ledger: UsageLedger) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+
+    /// Run the cases against every configuration. Errors of the agents do not stop the run:
+    /// they are recorded in the results of the cases.
+    pub async fn run(&self) -> BenchReport {
+        let start = Instant::now();
+        let mut configurations = vec![];
+        for (name, agent) in &self.agents {
+            configurations.push(self.run_configuration(name, agent.as_ref()).await);
+        }
+
+        BenchReport {
+            bench: self.name.clone(),
+            scorers: self.scorers.iter().map(|scorer| scorer.name()).collect(),
+            configurations,
+            duration: start.elapsed(),
+        }
+    }
+
+    async fn run_configuration(&self, name: &str, agent: &dyn BenchAgent) -> ConfigurationReport {
+        tracing::info!(target: "rig", "Benchmarking configuration {name}");
+        let records_before = self
+            .ledger
+            .as_ref()
+            .map(|ledger| ledger.records().len())
+            .unwrap_or_default();
+
+        let start = Instant::now();
+        let runs = self
+            .cases
+            .iter()
+            .flat_map(|case| std::iter::repeat_n(case, self.repetitions));
+        let results = stream::iter(runs)
+            .map(|case| self.run_case(name, agent, case))
+            .buffered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await;
+        let duration = start.elapsed();
+
+        let usage = self.ledger.as_ref().map(|ledger| {
+            let mut usage = BenchUsage::default();
+            let records = ledger.records();
+            let records = records.iter().skip(records_before).filter(|record| {
+                record.experiment.as_deref() == Some(self.name.as_str())
+                    && record.variant.as_deref() == Some(name)
+            });
+            for record in records {
+                usage.requests += 1;
+                usage.input_tokens += record.input_tokens;
+                usage.output_tokens += record.output_tokens;
+                match record.cost {
+                    Some(cost) => usage.cost += cost,
+                    None => usage.unpriced_requests += 1,
+                }
+            }
+            usage
+        });
+
+        ConfigurationReport {
+            name: name.to_string(),
+            results,
+            usage,
+            duration,
+        }
+    }
+
+    async fn run_case(&self, name: &str, agent: &dyn BenchAgent, case: &EvalCase) -> BenchResult {
+        let start = Instant::now();
+        // Only the usage of the agent is attributed to the configuration, not that of the scorers
+        let session_id = format!("{}/{name}", self.name);
+        let answer = crate::usage::experiment_scoped(
+            &self.name,
+            name,
+            &session_id,
+            agent.answer(&case.input),
+        )
+        .await;
+        let duration = start.elapsed();
+
+        match answer {
+            Ok(answer) => BenchResult {
+                case: case.name.clone(),
+                scores: score_output(&self.scorers, case, &answer.output).await,
+                output: Some(answer.output),
+                error: None,
+                tool_calls: answer.tool_calls,
+                duration,
+            },
+            Err(e) => BenchResult {
+                case: case.name.clone(),
+                output: None,
+                error: Some(e.to_string()),
+                scores: vec![],
+                tool_calls: vec![],
+                duration,
+            },
+        }
+    }
+}
+
+/// Result of a run of a case against a configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchResult {
+    /// Name of the case
+    pub case: String,
+    /// Answer of the agent, if it answered
+    pub output: Option<String>,
+    /// Error of the agent, if it failed to answer
+    pub error: Option<String>,
+    pub scores: Vec<Score>,
+    /// Names of the tools called by the agent, in order
+    pub tool_calls: Vec<String>,
+    /// Time taken by the agent to answer
+    pub duration: Duration,
+}
+
+impl BenchResult {
+    /// Whether the agent answered and the answer passed every scorer.
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.scores.iter().all(|score| score.passed)
+    }
+}
+
+/// Token usage and cost of a configuration.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchUsage {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Total cost of the priced requests
+    pub cost: f64,
+    /// Number of requests made by models without a price (not included in the cost)
+    pub unpriced_requests: u64,
+}
+
+/// Measures of a configuration of a [Bench].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfigurationReport {
+    /// Name of the configuration
+    pub name: String,
+    /// Results of the runs, in the order of the cases
+    pub results: Vec<BenchResult>,
+    /// Usage of the configuration, if measured (see [Bench::usage])
+    pub usage: Option<BenchUsage>,
+    /// Total duration of the runs
+    pub duration: Duration,
+}
+
+impl ConfigurationReport {
+    /// Fraction of the runs that passed (1 if there were none).
+    pub fn pass_rate(&self) -> f64 {
+        if self.results.is_empty() {
+            return 1.0;
+        }
+        let passed = self.results.iter().filter(|result| result.passed()).count();
+        passed as f64 / self.results.len() as f64
+    }
+
+    /// Number of runs where the agent failed to answer.
+    pub fn errors(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| result.error.is_some())
+            .count()
+    }
+
+    /// Mean latency of the answers.
+    pub fn mean_latency(&self) -> Duration {
+        if self.results.is_empty() {
+            return Duration::ZERO;
+        }
+        self.results
+            .iter()
+            .map(|result| result.duration)
+            .sum::<Duration>()
+            / self.results.len() as u32
+    }
+
+    /// Latency under which `percentile`% of the answers were given (e.g.: 95 for the p95),
+    /// with the nearest-rank method.
+    pub fn latency_percentile(&self, percentile: f64) -> Duration {
+        latency_percentile(
+            self.results.iter().map(|result| result.duration).collect(),
+            percentile,
+        )
+    }
+
+    /// Total number of tool calls.
+    pub fn tool_calls(&self) -> usize {
+        self.results
+            .iter()
+            .map(|result| result.tool_calls.len())
+            .sum()
+    }
+
+    /// Mean number of tool calls per run.
+    pub fn mean_tool_calls(&self) -> f64 {
+        if self.results.is_empty() {
+            0.0
+        } else {
+            self.tool_calls() as f64 / self.results.len() as f64
+        }
+    }
+
+    /// Mean score given by the scorer named `scorer`, if it scored any run.
+    pub fn mean_score(&self, scorer: &str) -> Option<f64> {
+        let scores = self
+            .results
+            .iter()
+            .flat_map(|result| &result.scores)
+            .filter(|score| score.scorer == scorer)
+            .map(|score| score.value)
+            .collect::<Vec<_>>();
+
+        (!scores.is_empty()).then(|| scores.iter().sum::<f64>() / scores.len() as f64)
+    }
+}
+
+/// Report of the run of a [Bench], comparing its configurations.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchReport {
+    /// Name of the bench
+    pub bench: String,
+    /// Names of the scorers of the bench
+    pub scorers: Vec<String>,
+    /// Measures of the configurations, in the order of the bench
+    pub configurations: Vec<ConfigurationReport>,
+    /// Total duration of the run
+    pub duration: Duration,
+}
+
+impl BenchReport {
+    /// Measures of the configuration `name`, if any.
+    pub fn configuration(&self, name: &str) -> Option<&ConfigurationReport> {
+        self.configurations
+            .iter()
+            .find(|configuration| configuration.name == name)
+    }
+
+    /// Header and rows of the comparison table of the configurations.
+    fn table(&self) -> (Vec<String>, Vec<Vec<String>>) {
+        let header = [
+            "configuration",
+            "pass_rate",
+            "errors",
+            "mean_latency_ms",
+            "p50_latency_ms",
+            "p95_latency_ms",
+            "mean_tool_calls",
+            "input_tokens",
+            "output_tokens",
+            "cost",
+        ]
+        .into_iter()
+        .map(String::from)
+        .chain(self.scorers.clone())
+        .collect();
+
+        let rows = self
+            .configurations
+            .iter()
+            .map(|configuration| {
+                let usage = configuration.usage.as_ref();
+                let ms = |duration: Duration| duration.as_millis().to_string();
+                [
+                    configuration.name.clone(),
+                    format!("{:.3}", configuration.pass_rate()),
+                    configuration.errors().to_string(),
+                    ms(configuration.mean_latency()),
+                    ms(configuration.latency_percentile(50.0)),
+                    ms(configuration.latency_percentile(95.0)),
+                    format!("{:.2}", configuration.mean_tool_calls()),
+                    usage
+                        .map(|usage| usage.input_tokens.to_string())
+                        .unwrap_or_default(),
+                    usage
+                        .map(|usage| usage.output_tokens.to_string())
+                        .unwrap_or_default(),
+                    usage
+                        .map(|usage| format!("{:.6}", usage.cost))
+                        .unwrap_or_default(),
+                ]
+                .into_iter()
+                .chain(self.scorers.iter().map(|scorer| {
+                    configuration
+                        .mean_score(scorer)
+                        .map(|score| format!("{score:.3}"))
+                        .unwrap_or_default()
+                }))
+                .collect()
+            })
+            .collect();
+        (header, rows)
+    }
+
+    /// Export the comparison of the configurations as CSV, with one row per configuration.
+    pub fn to_csv(&self) -> String {
+        let (header, rows) = self.table();
+        let mut csv = header.join(",") + "\n";
+        for row in rows {
+            let line = row
+                .iter()
+                .map(|value| csv_field(value))
+                .collect::<Vec<_>>()
+                .join(",");
+            csv.push_str(&line);
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Export the report as JSON, including the results of every run.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Bench {}: {} configurations in {:.2?}",
+            self.bench,
+            self.configurations.len(),
+            self.duration
+        )?;
+
+        let (header, rows) = self.table();
+        let widths = header
+            .iter()
+            .enumerate()
+            .map(|(i, title)| {
+                rows.iter()
+                    .map(|row| row[i].len())
+                    .chain([title.len()])
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        for row in [header].iter().chain(&rows) {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .map(|(value, width)| format!("{value:<width$}"))
+                .collect::<Vec<_>>()
+                .join(" | ");
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Latency under which `percentile`% of `latencies` are, with the nearest-rank method (zero if
+/// there are none).
+pub(crate) fn latency_percentile(mut latencies: Vec<Duration>, percentile: f64) -> Duration {
+    latencies.sort();
+    let rank = (percentile.clamp(0.0, 100.0) / 100.0 * latencies.len() as f64).ceil() as usize;
+    latencies
+        .get(rank.saturating_sub(1))
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Quote a CSV field if needed.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        evals::{scorers::ExactMatch, EvalError},
+        providers::mock::{self, MockResponse},
+        tool,
+        usage::Pricing,
+    };
+
+    /// Agent answering `answer` after `latency`, and recording its usage in a ledger
+    struct Recorder {
+        ledger: UsageLedger,
+        answer: &'static str,
+        latency: Duration,
+    }
+
+    impl BenchAgent for Recorder {
+        fn answer<'a>(
+            &'a self,
+            _prompt: &'a str,
+        ) -> BoxFuture<'a, Result<BenchAnswer, PromptError>> {
+            Box::pin(async move {
+                futures_timer::Delay::new(self.latency).await;
+                self.ledger.record("small", 100, 10);
+                Ok(BenchAnswer {
+                    output: self.answer.to_string(),
+                    tool_calls: vec![],
+                })
+            })
+        }
+    }
+
+    /// Scorer passing every answer, and recording its usage in a ledger (e.g.: as an LLM judge)
+    struct Judge {
+        ledger: UsageLedger,
+    }
+
+    impl Scorer for Judge {
+        fn name(&self) -> String {
+            "judge".to_string()
+        }
+
+        async fn score(&self, _case: &EvalCase, _output: &str) -> Result<Score, EvalError> {
+            self.ledger.record("small", 1000, 100);
+            Ok(Score::binary("judge", true))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bench() {
+        let ledger = UsageLedger::new(Pricing::new().model("small", 1.0, 2.0));
+        let model = mock::CompletionModel::new()
+            .when_prompt_contains("capital", MockResponse::tool_call("lookup", json!({})))
+            .default_response(MockResponse::error("model overloaded"));
+        let lookup = tool::from_fn(
+            "lookup",
+            "Look up a fact",
+            json!({"type": "object"}),
+            |_args: serde_json::Value| async move { Ok::<_, std::io::Error>("Paris".to_string()) },
+        );
+
+        let report = Bench::new("capitals")
+            .case(EvalCase::new("france", "What is the capital of France?").expected("\"Paris\""))
+            .case(EvalCase::new("flurbo", "What is a flurbo?").expected("A currency"))
+            .scorer(ExactMatch::new())
+            .scorer(Judge {
+                ledger: ledger.clone(),
+            })
+            .agent("tools", model.agent().tool(lookup).build())
+            .agent(
+                "small",
+                Recorder {
+                    ledger: ledger.clone(),
+                    answer: "\"Paris\"",
+                    latency: Duration::from_millis(5),
+                },
+            )
+            .repetitions(2)
+            .concurrency(2)
+            .usage(ledger)
+            .run()
+            .await;
+
+        let tools = report.configuration("tools").unwrap();
+        assert_eq!(tools.results.len(), 4);
+        assert_eq!(tools.pass_rate(), 0.5);
+        assert_eq!(tools.errors(), 2);
+        assert_eq!(tools.tool_calls(), 2);
+        assert_eq!(tools.results[0].tool_calls, ["lookup"]);
+        assert_eq!(tools.usage, Some(BenchUsage::default()));
+
+        let small = report.configuration("small").unwrap();
+        assert_eq!(small.mean_score("exact_match"), Some(0.5));
+        assert!(small.latency_percentile(95.0) >= Duration::from_millis(5));
+        // The usage of the judge is not counted
+        let usage = small.usage.as_ref().unwrap();
+        assert_eq!((usage.requests, usage.input_tokens), (4, 400));
+        assert!((usage.cost - 0.00048).abs() < 1e-12);
+
+        let csv = report.to_csv();
+        assert!(csv.starts_with("configuration,pass_rate,errors,mean_latency_ms"));
+        assert!(csv.lines().nth(2).unwrap().starts_with("small,0.500,0,"));
+        assert!(report
+            .to_string()
+            .starts_with("Bench capitals: 2 configurations"));
+    }
+}
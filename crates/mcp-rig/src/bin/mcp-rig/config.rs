@@ -0,0 +1,121 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("TomlError: {0}")]
+    TomlError(#[from] toml::de::Error),
+
+    #[error("YamlError: {0}")]
+    YamlError(#[from] serde_yaml::Error),
+
+    #[error("Unsupported config file extension: {0} (expected .toml, .yaml or .yml)")]
+    UnsupportedFormat(String),
+}
+
+/// Provider of the completion (and embedding) models. The API key of the provider is read
+/// from its usual environment variable (e.g.: `OPENAI_API_KEY`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Openai,
+    Anthropic,
+    Cohere,
+    Gemini,
+    Deepseek,
+    Xai,
+}
+
+/// Configuration of the chat.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Config {
+    pub provider: Provider,
+    pub model: String,
+    #[serde(default)]
+    pub preamble: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+    /// Check at startup that the model exists (for the providers listing their models:
+    /// OpenAI and Cohere)
+    #[serde(default)]
+    pub validate_model: bool,
+    /// MCP servers whose tools are given to the agent
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServerConfig>,
+    /// Vector index of documents used as dynamic context of the agent
+    #[serde(default)]
+    pub vector_index: Option<VectorIndexConfig>,
+    /// Where the scoped secure values of the MCP servers are read from
+    #[serde(default)]
+    pub secrets: SecretsConfig,
+}
+
+/// MCP server, reached over WebSocket for the `ws://` and `wss://` URLs, and over SSE
+/// otherwise.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    pub url: String,
+    /// Name of the server, and scope of its secrets (see [SecretsConfig])
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Names of the secure values of the server (e.g.: API keys), read from the scope of the
+    /// server
+    #[serde(default)]
+    pub secrets: Vec<String>,
+    /// Secure values of the server, by name, read from the environment variable they map to
+    #[serde(default)]
+    pub secure_values: HashMap<String, String>,
+}
+
+/// Providers of the scoped secure values of the MCP servers: the JSON file mapping the scopes
+/// to their values (if any), then the environment variables `<PREFIX>_<SCOPE>_<NAME>` (e.g.:
+/// `TWITTER_API_KEY` for the secret `api_key` of the server `twitter`, without prefix).
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SecretsConfig {
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    #[serde(default)]
+    pub env_prefix: Option<String>,
+}
+
+/// In-memory vector index of local documents.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VectorIndexConfig {
+    /// Provider of the embedding model (defaults to the provider of the chat)
+    #[serde(default)]
+    pub provider: Option<Provider>,
+    /// Embedding model
+    pub model: String,
+    /// Glob patterns of the documents to index (e.g.: `docs/**/*.md`)
+    pub documents: Vec<String>,
+    /// Number of documents added to the context of each prompt
+    #[serde(default = "default_sample")]
+    pub sample: usize,
+}
+
+fn default_sample() -> usize {
+    3
+}
+
+impl Config {
+    /// Load the config from a TOML or YAML file, depending on its extension.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Ok(toml::from_str(&content)?),
+            Some("yaml" | "yml") => Ok(serde_yaml::from_str(&content)?),
+            extension => Err(ConfigError::UnsupportedFormat(
+                extension.unwrap_or_default().to_string(),
+            )),
+        }
+    }
+}
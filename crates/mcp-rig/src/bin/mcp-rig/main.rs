@@ -0,0 +1,268 @@
+//! `mcp-rig`: interactive chat with an agent described by a config file (requires the `cli`
+//! feature).
+//!
+//! ```sh
+//! cargo run --features cli --bin mcp-rig -- agent.toml
+//! ```
+//!
+//! The config file is a TOML (`.toml`) or YAML (`.yaml`, `.yml`) file describing the provider
+//! and model of the agent, its preamble, the MCP servers whose tools it can call and the
+//! documents of its vector index (if any). The API keys are read from the environment
+//! (e.g.: `OPENAI_API_KEY`, `ANTHROPIC_API_KEY`).
+//!
+//! ```toml
+//! provider = "openai"
+//! model = "gpt-4o"
+//! preamble = "You are a helpful assistant that can post on Twitter."
+//! temperature = 0.5
+//!
+//! # Secrets of the servers, read from `secrets.json` (e.g.: `{ "twitter": { "api_key": "..." } }`)
+//! # and then from the environment (e.g.: `TWITTER_API_KEY`)
+//! [secrets]
+//! file = "secrets.json"
+//!
+//! [[mcp_servers]]
+//! url = "https://twitter-mcp.fabelis.ai"
+//! name = "twitter"
+//! secrets = ["api_key", "api_secret"]
+//!
+//! # WebSocket servers are reached with their `ws://` or `wss://` URL
+//! [[mcp_servers]]
+//! url = "wss://mcp.example.com/ws"
+//!
+//! [vector_index]
+//! model = "text-embedding-3-small"
+//! documents = ["docs/**/*.md"]
+//! sample = 2
+//! ```
+//!
+//! With `--codegen <FILE>`, the typed bindings of the tools of the MCP servers are written to
+//! `FILE` (see `mcp_rig::codegen`) instead.
+//!
+//! Responses are streamed for the providers that support streaming (Anthropic), unless
+//! `--no-stream` is set. The tool calls of the agent are displayed along with their results.
+use std::{error::Error, path::PathBuf, sync::Arc};
+
+use clap::Parser;
+use mcp_core::{
+    client::{Client as McpClient, SecureValue},
+    transport::Transport,
+    types::{Implementation, Tool},
+};
+use mcp_rig::{
+    agent::{Agent, AgentBuilder},
+    codegen::Codegen,
+    completion::CompletionModel,
+    embeddings::{EmbeddingModel, EmbeddingsBuilder},
+    loaders::FileLoader,
+    mcp_transport::McpTransport,
+    models::ListModels,
+    providers::{anthropic, cohere, deepseek, gemini, openai, xai},
+    secrets::{self, EnvSecrets, FileSecrets, SecretsChain},
+    vector_store::in_memory_store::InMemoryVectorStore,
+};
+
+mod config;
+mod repl;
+
+use config::{Config, McpServerConfig, Provider, SecretsConfig, VectorIndexConfig};
+
+/// Chat with an agent described by a config file.
+#[derive(Parser)]
+#[command(name = "mcp-rig", version)]
+struct Args {
+    /// Path of the config file (.toml, .yaml or .yml)
+    config: PathBuf,
+
+    /// Print the responses once complete instead of streaming them
+    #[arg(long)]
+    no_stream: bool,
+
+    /// Write the typed bindings of the tools of the MCP servers to this file (see
+    /// `mcp_rig::codegen`), instead of chatting
+    #[arg(long)]
+    codegen: Option<PathBuf>,
+}
+
+type McpTools = Vec<(Tool, Arc<McpClient<McpTransport>>)>;
+
+/// Providers of the scoped secrets of the MCP servers.
+fn secrets_provider(config: &SecretsConfig) -> Result<SecretsChain, Box<dyn Error>> {
+    let mut chain = SecretsChain::new();
+    if let Some(file) = &config.file {
+        chain = chain.provider(FileSecrets::load(file)?);
+    }
+    let env = match &config.env_prefix {
+        Some(prefix) => EnvSecrets::new().prefix(prefix),
+        None => EnvSecrets::new(),
+    };
+    Ok(chain.provider(env))
+}
+
+/// Connect to the MCP servers and list their tools.
+async fn connect(
+    servers: &[McpServerConfig],
+    secrets: &SecretsChain,
+) -> Result<McpTools, Box<dyn Error>> {
+    let mut tools = vec![];
+    for server in servers {
+        let transport = McpTransport::from_url(&server.url);
+        transport.open().await?;
+
+        let builder = server
+            .secure_values
+            .iter()
+            .fold(McpClient::builder(transport), |builder, (name, var)| {
+                builder.with_secure_value(name, SecureValue::Env(var.clone()))
+            });
+        let scope = server.name.as_deref().unwrap_or(&server.url);
+        let client = secrets::with_scoped_secrets(builder, secrets, scope, &server.secrets)
+            .await?
+            .use_strict()
+            .build();
+        let client = Arc::new(client);
+        tokio::spawn({
+            let client = client.clone();
+            async move { client.start().await }
+        });
+
+        client
+            .initialize(Implementation {
+                name: "mcp-rig".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            })
+            .await?;
+        let server_tools = client.list_tools(None, None).await?.tools;
+        println!("Connected to {} ({} tools)", server.url, server_tools.len());
+
+        tools.extend(server_tools.into_iter().map(|tool| (tool, client.clone())));
+    }
+    Ok(tools)
+}
+
+/// Index the documents of the vector index with `model`, and add the index to the dynamic
+/// context of the agent.
+async fn dynamic_context<M: CompletionModel, E: EmbeddingModel + 'static>(
+    builder: AgentBuilder<M>,
+    model: E,
+    config: &VectorIndexConfig,
+) -> Result<AgentBuilder<M>, Box<dyn Error>> {
+    let mut documents = vec![];
+    for pattern in &config.documents {
+        documents.extend(FileLoader::with_glob(pattern)?.read().ignore_errors());
+    }
+    println!("Indexing {} documents...", documents.len());
+
+    let embeddings = EmbeddingsBuilder::new(model.clone())
+        .documents(documents)?
+        .build()
+        .await?;
+    let index = InMemoryVectorStore::from_documents(embeddings).index(model);
+
+    Ok(builder.dynamic_context(config.sample, index))
+}
+
+async fn build_agent<M: CompletionModel>(
+    mut builder: AgentBuilder<M>,
+    config: &Config,
+    tools: &McpTools,
+) -> Result<Agent<M>, Box<dyn Error>> {
+    if let Some(preamble) = &config.preamble {
+        builder = builder.preamble(preamble);
+    }
+    if let Some(temperature) = config.temperature {
+        builder = builder.temperature(temperature);
+    }
+    if let Some(max_tokens) = config.max_tokens {
+        builder = builder.max_tokens(max_tokens);
+    }
+    for (tool, client) in tools {
+        builder = builder.mcp_tool(tool.clone(), client.clone());
+    }
+
+    if let Some(index) = &config.vector_index {
+        builder = match index.provider.unwrap_or(config.provider) {
+            Provider::Openai => {
+                let model = openai::Client::from_env().embedding_model(&index.model);
+                dynamic_context(builder, model, index).await?
+            }
+            Provider::Cohere => {
+                let model =
+                    cohere::Client::from_env().embedding_model(&index.model, "search_document");
+                dynamic_context(builder, model, index).await?
+            }
+            Provider::Gemini => {
+                let model = gemini::Client::from_env().embedding_model(&index.model);
+                dynamic_context(builder, model, index).await?
+            }
+            Provider::Xai => {
+                let model = xai::Client::from_env().embedding_model(&index.model);
+                dynamic_context(builder, model, index).await?
+            }
+            provider => {
+                return Err(format!("{provider:?} does not provide embedding models").into())
+            }
+        };
+    }
+
+    Ok(builder.trace_exporter(repl::ToolCallPrinter).build())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let config = Config::load(&args.config)?;
+    let secrets = secrets_provider(&config.secrets)?;
+    let tools = connect(&config.mcp_servers, &secrets).await?;
+    if let Some(path) = &args.codegen {
+        let bindings = Codegen::new("McpTools")
+            .mcp_tools(tools.into_iter().map(|(tool, _)| tool))
+            .generate()?;
+        std::fs::write(path, bindings)?;
+        println!("Wrote the bindings of the tools to {}", path.display());
+        return Ok(());
+    }
+    let model = config.model.as_str();
+
+    match config.provider {
+        Provider::Openai => {
+            let client = openai::Client::from_env();
+            if config.validate_model {
+                client.validate_model(model).await?;
+            }
+            let builder = client.agent(model);
+            repl::chat(build_agent(builder, &config, &tools).await?).await
+        }
+        Provider::Anthropic => {
+            let builder = anthropic::Client::from_env().agent(model);
+            let agent = build_agent(builder, &config, &tools).await?;
+            if args.no_stream {
+                repl::chat(agent).await
+            } else {
+                repl::stream(agent).await
+            }
+        }
+        Provider::Cohere => {
+            let client = cohere::Client::from_env();
+            if config.validate_model {
+                client.validate_model(model).await?;
+            }
+            let builder = client.agent(model);
+            repl::chat(build_agent(builder, &config, &tools).await?).await
+        }
+        Provider::Gemini => {
+            let builder = gemini::Client::from_env().agent(model);
+            repl::chat(build_agent(builder, &config, &tools).await?).await
+        }
+        Provider::Deepseek => {
+            let builder = deepseek::Client::from_env().agent(model);
+            repl::chat(build_agent(builder, &config, &tools).await?).await
+        }
+        Provider::Xai => {
+            let builder = xai::Client::from_env().agent(model);
+            repl::chat(build_agent(builder, &config, &tools).await?).await
+        }
+    }
+
+    Ok(())
+}
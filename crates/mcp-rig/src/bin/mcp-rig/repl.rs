@@ -0,0 +1,139 @@
+use std::io::{self, Write};
+
+use futures::StreamExt;
+use mcp_rig::{
+    agent::Agent,
+    completion::{Chat, CompletionModel, Message},
+    observability::{AgentTrace, TraceExportError, TraceExporter},
+    streaming::{StreamingChat, StreamingChoice, StreamingCompletionModel},
+};
+
+/// Trace exporter printing the tool calls of the agent.
+pub struct ToolCallPrinter;
+
+impl TraceExporter for ToolCallPrinter {
+    async fn export(&self, trace: &AgentTrace) -> Result<(), TraceExportError> {
+        for tool_call in &trace.tool_calls {
+            print_tool_call(&tool_call.name, &tool_call.arguments);
+            match (&tool_call.output, &tool_call.error) {
+                (_, Some(error)) => println!("  error: {error}"),
+                (Some(output), _) => println!("  result: {output}"),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn print_tool_call(name: &str, arguments: &serde_json::Value) {
+    println!("[tool] {name}({arguments})");
+}
+
+/// Read the next prompt from stdin, or `None` when the user exits.
+fn read_prompt() -> Option<String> {
+    loop {
+        print!("> ");
+        // Flush stdout to ensure the prompt appears before input
+        io::stdout().flush().ok()?;
+
+        let mut input = String::new();
+        match io::stdin().read_line(&mut input) {
+            // End of input
+            Ok(0) => return None,
+            Ok(_) => match input.trim() {
+                "" => continue,
+                "exit" | "/exit" => return None,
+                input => return Some(input.to_string()),
+            },
+            Err(error) => println!("Error reading input: {}", error),
+        }
+    }
+}
+
+fn welcome() {
+    println!("Welcome to the chat! Type '/clear' to start over and 'exit' to quit.");
+}
+
+/// Chat with `agent`, printing its responses once complete.
+pub async fn chat<M: CompletionModel>(agent: Agent<M>) {
+    welcome();
+    let mut history = vec![];
+
+    while let Some(prompt) = read_prompt() {
+        if prompt == "/clear" {
+            history.clear();
+            continue;
+        }
+
+        let result = agent.chat(prompt.as_str(), history.clone()).await;
+        // The tool calls are printed by the trace exporter of the agent
+        agent.flush_traces().await;
+        match result {
+            Ok(response) => {
+                println!("{response}\n");
+                history.push(Message::user(prompt));
+                history.push(Message::assistant(response));
+            }
+            Err(e) => eprintln!("Error: {e}\n"),
+        }
+    }
+}
+
+/// Chat with `agent`, printing its responses as they are streamed.
+pub async fn stream<M: StreamingCompletionModel>(agent: Agent<M>) {
+    welcome();
+    let mut history = vec![];
+
+    while let Some(prompt) = read_prompt() {
+        if prompt == "/clear" {
+            history.clear();
+            continue;
+        }
+
+        let mut stream = match agent.stream_chat(&prompt, history.clone()).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Error: {e}\n");
+                continue;
+            }
+        };
+
+        let mut response = String::new();
+        let mut failed = false;
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(StreamingChoice::Message(text)) => {
+                    print!("{text}");
+                    io::stdout().flush().ok();
+                    response.push_str(&text);
+                }
+                Ok(StreamingChoice::ToolCall(name, _, arguments)) => {
+                    println!();
+                    print_tool_call(&name, &arguments);
+                    match agent.tools.call(&name, arguments.to_string()).await {
+                        Ok(result) => {
+                            println!("  result: {result}");
+                            response.push_str(&result);
+                        }
+                        Err(e) => {
+                            eprintln!("  error: {e}");
+                            failed = true;
+                            break;
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("\nError: {e}");
+                    failed = true;
+                    break;
+                }
+            }
+        }
+        println!("\n");
+
+        if !failed {
+            history.push(Message::user(prompt));
+            history.push(Message::assistant(response));
+        }
+    }
+}
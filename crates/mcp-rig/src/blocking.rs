@@ -0,0 +1,145 @@
+//! Blocking (i.e.: synchronous) wrappers of the async API (requires the `blocking` feature),
+//! for CLI tools and scripts that don't otherwise need an async runtime.
+//!
+//! The wrappers run the futures on a single-threaded Tokio runtime managed internally, so
+//! they must not be called from an async context (i.e.: from within another Tokio runtime).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     blocking::PromptBlocking,
+//!     embeddings::EmbeddingsBuilder,
+//!     providers::openai,
+//! };
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let openai = openai::Client::from_env();
+//!
+//!     let agent = openai.agent(openai::GPT_4O).build();
+//!     let response = agent.prompt_blocking("Who are you?")?;
+//!     println!("{response}");
+//!
+//!     let embeddings = EmbeddingsBuilder::new(openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL))
+//!         .document("Hello, world!")?
+//!         .build_blocking()?;
+//!     Ok(())
+//! }
+//! ```
+use std::{future::Future, sync::OnceLock};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::{
+    completion::{Chat, CompletionModel, Message, Prompt, PromptError},
+    embeddings::{Embed, Embedding, EmbeddingError, EmbeddingModel, EmbeddingsBuilder},
+    extractor::{ExtractionError, Extractor},
+    OneOrMany,
+};
+
+static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+
+/// Run `future` to completion on the internal runtime, blocking the current thread.
+///
+/// # Panics
+/// If called from an async context.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Failed to build the blocking runtime")
+        })
+        .block_on(future)
+}
+
+/// Blocking version of the [Prompt] trait.
+pub trait PromptBlocking {
+    /// Send a prompt and block until the response is received. See [Prompt::prompt].
+    fn prompt_blocking(&self, prompt: impl Into<Message> + Send) -> Result<String, PromptError>;
+}
+
+impl<T: Prompt> PromptBlocking for T {
+    fn prompt_blocking(&self, prompt: impl Into<Message> + Send) -> Result<String, PromptError> {
+        block_on(self.prompt(prompt))
+    }
+}
+
+/// Blocking version of the [Chat] trait.
+pub trait ChatBlocking {
+    /// Send a prompt with a chat history and block until the response is received.
+    /// See [Chat::chat].
+    fn chat_blocking(
+        &self,
+        prompt: impl Into<Message> + Send,
+        chat_history: Vec<Message>,
+    ) -> Result<String, PromptError>;
+}
+
+impl<T: Chat> ChatBlocking for T {
+    fn chat_blocking(
+        &self,
+        prompt: impl Into<Message> + Send,
+        chat_history: Vec<Message>,
+    ) -> Result<String, PromptError> {
+        block_on(self.chat(prompt, chat_history))
+    }
+}
+
+impl<M: EmbeddingModel, T: Embed + Send> EmbeddingsBuilder<M, T> {
+    /// Blocking version of [EmbeddingsBuilder::build].
+    pub fn build_blocking(self) -> Result<Vec<(T, OneOrMany<Embedding>)>, EmbeddingError> {
+        block_on(self.build())
+    }
+}
+
+impl<T, M> Extractor<M, T>
+where
+    T: JsonSchema + for<'a> Deserialize<'a> + Send + Sync,
+    M: CompletionModel + Sync,
+{
+    /// Blocking version of [Extractor::extract].
+    pub fn extract_blocking(&self, text: &str) -> Result<T, ExtractionError> {
+        block_on(self.extract(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::{self, MockResponse};
+
+    #[test]
+    fn test_prompt_blocking() {
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::text("Hi!"))
+            .push(MockResponse::text("Fine, thanks."));
+        let agent = model.agent().build();
+
+        assert_eq!(agent.prompt_blocking("Hello").unwrap(), "Hi!");
+        assert_eq!(
+            agent
+                .chat_blocking(
+                    "How are you?",
+                    vec![Message::user("Hello"), Message::assistant("Hi!")]
+                )
+                .unwrap(),
+            "Fine, thanks."
+        );
+        assert_eq!(model.requests()[1].chat_history.len(), 2);
+    }
+
+    #[test]
+    fn test_build_blocking() {
+        let model = mock::EmbeddingModel::new(2).embedding("flurbo", vec![1.0, 0.0]);
+
+        let embeddings = EmbeddingsBuilder::new(model)
+            .document("flurbo")
+            .unwrap()
+            .build_blocking()
+            .unwrap();
+        assert_eq!(embeddings.len(), 1);
+        assert_eq!(embeddings[0].1.first().vec, vec![1.0, 0.0]);
+    }
+}
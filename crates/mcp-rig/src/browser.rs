@@ -0,0 +1,502 @@
+//! Browser automation tools (`browser_navigate`, `browser_click`, `browser_extract_text` and
+//! `browser_screenshot`), for the agents browsing the web (e.g.: fill a form, or check what a
+//! page looks like).
+//!
+//! The tools drive any [BrowserController], so that the agents don't depend on the browser
+//! behind it:
+//! - [McpBrowser] drives the headless browser of an MCP browser server (i.e.: the Puppeteer MCP
+//!   server, `@modelcontextprotocol/server-puppeteer`), through its MCP client (or any
+//!   [McpToolCaller]),
+//! - other browsers (e.g.: a local Chrome through the DevTools protocol) implement
+//!   [BrowserController] directly.
+//!
+//! The screenshots are images for the vision models: the `browser_screenshot` tool returns the
+//! JSON of a [Screenshot], which [Screenshot::from_tool_output] reads back, and
+//! [Screenshot::message] turns into a prompt with the image content (see
+//! [UserContent::image]).
+//!
+//! # Example
+//! ```rust
+//! use std::sync::Arc;
+//! use mcp_rig::{browser::{BrowserTools, McpBrowser, Screenshot}, completion::Prompt, providers::openai};
+//!
+//! // MCP client of the Puppeteer MCP server, started and initialized...
+//! let browser = BrowserTools::new(McpBrowser::new(Arc::new(client)));
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("You browse the web for the user.")
+//!     .tool(browser.navigate_tool())
+//!     .tool(browser.click_tool())
+//!     .tool(browser.extract_text_tool())
+//!     .tool(browser.screenshot_tool())
+//!     .build();
+//! let output = agent.prompt("Take a screenshot of https://example.com").await?;
+//!
+//! let screenshot = Screenshot::from_tool_output(&output)?;
+//! let description = agent.prompt(screenshot.message("Describe the layout of this page")).await?;
+//! ```
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    completion::message::{
+        ContentFormat, Image, ImageMediaType, Message, ToolResultContent, UserContent,
+    },
+    mcp_servers::McpToolCaller,
+    one_or_many::OneOrMany,
+    tool::{self, OutputLimit, Tool, ToolAnnotations, ToolError},
+};
+
+/// Default maximum number of characters of the texts extracted from the pages.
+const DEFAULT_MAX_TEXT: usize = 20_000;
+
+/// Default size of the screenshots of [McpBrowser], in pixels.
+const DEFAULT_VIEWPORT: (u32, u32) = (1280, 800);
+
+#[derive(Debug, thiserror::Error)]
+pub enum BrowserError {
+    /// No element of the page matches the selector
+    #[error("Element not found: {0}")]
+    ElementNotFound(String),
+
+    /// The browser returned no image for a screenshot
+    #[error("No image in the screenshot")]
+    NoImage,
+
+    /// Error returned by the browser (e.g.: by the tools of the MCP browser server)
+    #[error("ToolError: {0}")]
+    ToolError(#[from] ToolError),
+
+    /// The output is not the JSON of a [Screenshot]
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// Screenshot of a page, as a base64 encoded image.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Screenshot {
+    /// Image, base64 encoded
+    pub data: String,
+    /// Media type of the image (e.g.: PNG), if known
+    pub media_type: Option<ImageMediaType>,
+}
+
+impl Screenshot {
+    /// Screenshot returned by the `browser_screenshot` tool (i.e.: its JSON output)
+    pub fn from_tool_output(output: &str) -> Result<Self, BrowserError> {
+        Ok(serde_json::from_str(output)?)
+    }
+
+    /// Image content of the screenshot
+    pub fn image(&self) -> Image {
+        Image {
+            data: self.data.clone(),
+            format: Some(ContentFormat::Base64),
+            media_type: self.media_type.clone(),
+            detail: None,
+        }
+    }
+
+    /// Screenshot as the content of a user message
+    pub fn user_content(&self) -> UserContent {
+        UserContent::Image(self.image())
+    }
+
+    /// Screenshot as the content of a tool result
+    pub fn tool_result_content(&self) -> ToolResultContent {
+        ToolResultContent::Image(self.image())
+    }
+
+    /// User message with `text` and the screenshot, for vision models
+    pub fn message(&self, text: impl Into<String>) -> Message {
+        Message::User {
+            content: OneOrMany::many(vec![UserContent::text(text), self.user_content()])
+                .expect("The message has two contents"),
+        }
+    }
+}
+
+/// Controller of a browser (see the [module](self) docs).
+pub trait BrowserController: Send + Sync {
+    /// Open the page `url`, returning a description of the result (e.g.: the new URL)
+    fn navigate<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String, BrowserError>>;
+
+    /// Click the element matching the CSS selector `selector`
+    fn click<'a>(&'a self, selector: &'a str) -> BoxFuture<'a, Result<String, BrowserError>>;
+
+    /// Visible text of the element matching the CSS selector `selector` (the whole page if
+    /// unset)
+    fn extract_text<'a>(
+        &'a self,
+        selector: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<String, BrowserError>>;
+
+    /// Screenshot of the visible part of the page
+    fn screenshot(&self) -> BoxFuture<'_, Result<Screenshot, BrowserError>>;
+}
+
+/// Headless browser of an MCP browser server (i.e.: the `puppeteer_*` tools of the Puppeteer MCP
+/// server), driven through its MCP client.
+pub struct McpBrowser<C> {
+    client: Arc<C>,
+    viewport: (u32, u32),
+}
+
+impl<C> Clone for McpBrowser<C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            viewport: self.viewport,
+        }
+    }
+}
+
+impl<C: McpToolCaller> McpBrowser<C> {
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            viewport: DEFAULT_VIEWPORT,
+        }
+    }
+
+    /// Take the screenshots at `width` x `height` pixels (1280 x 800 by default)
+    pub fn viewport(mut self, width: u32, height: u32) -> Self {
+        self.viewport = (width, height);
+        self
+    }
+
+    /// Result of the JavaScript expression `script` evaluated in the page
+    async fn evaluate(&self, script: &str) -> Result<Value, BrowserError> {
+        let output = self
+            .client
+            .call_tool("puppeteer_evaluate", json!({ "script": script }))
+            .await?;
+        // The server returns "Execution result:\n<JSON>\n\nConsole output:\n<logs>"
+        let result = output
+            .strip_prefix("Execution result:\n")
+            .and_then(|output| output.split("\n\nConsole output:").next())
+            .unwrap_or(&output);
+        Ok(serde_json::from_str(result).unwrap_or_else(|_| Value::String(result.to_string())))
+    }
+}
+
+impl<C: McpToolCaller> BrowserController for McpBrowser<C> {
+    fn navigate<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String, BrowserError>> {
+        Box::pin(async move {
+            Ok(self
+                .client
+                .call_tool("puppeteer_navigate", json!({ "url": url }))
+                .await?)
+        })
+    }
+
+    fn click<'a>(&'a self, selector: &'a str) -> BoxFuture<'a, Result<String, BrowserError>> {
+        Box::pin(async move {
+            Ok(self
+                .client
+                .call_tool("puppeteer_click", json!({ "selector": selector }))
+                .await?)
+        })
+    }
+
+    fn extract_text<'a>(
+        &'a self,
+        selector: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<String, BrowserError>> {
+        Box::pin(async move {
+            let script = match selector {
+                // The selector is a JSON string, i.e.: an escaped JavaScript string
+                Some(selector) => format!(
+                    "(() => {{ const element = document.querySelector({}); \
+                     return element ? element.innerText : null; }})()",
+                    Value::String(selector.to_string())
+                ),
+                None => "document.body.innerText".to_string(),
+            };
+            match self.evaluate(&script).await? {
+                Value::String(text) => Ok(text),
+                Value::Null => Err(BrowserError::ElementNotFound(
+                    selector.unwrap_or("body").to_string(),
+                )),
+                other => Ok(other.to_string()),
+            }
+        })
+    }
+
+    fn screenshot(&self) -> BoxFuture<'_, Result<Screenshot, BrowserError>> {
+        Box::pin(async move {
+            let (width, height) = self.viewport;
+            let contents = self
+                .client
+                .call_tool_content(
+                    "puppeteer_screenshot",
+                    json!({ "name": "screenshot", "width": width, "height": height }),
+                )
+                .await?;
+            contents
+                .into_iter()
+                .find_map(|content| match content {
+                    ToolResultContent::Image(image) => Some(Screenshot {
+                        data: image.data,
+                        media_type: image.media_type,
+                    }),
+                    _ => None,
+                })
+                .ok_or(BrowserError::NoImage)
+        })
+    }
+}
+
+/// Arguments of the `browser_navigate` tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct NavigateArgs {
+    /// URL of the page to open (e.g.: `https://example.com`)
+    pub url: String,
+}
+
+/// Arguments of the `browser_click` tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ClickArgs {
+    /// CSS selector of the element to click (e.g.: `button[type=submit]`)
+    pub selector: String,
+}
+
+/// Arguments of the `browser_extract_text` tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExtractTextArgs {
+    /// CSS selector of the element to read (the whole page if unset)
+    pub selector: Option<String>,
+}
+
+/// Arguments of the `browser_screenshot` tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ScreenshotArgs {}
+
+/// Browser automation tools (see the [module](self) docs).
+pub struct BrowserTools<B> {
+    browser: Arc<B>,
+    /// Maximum number of characters of the extracted texts
+    max_text: usize,
+}
+
+impl<B> Clone for BrowserTools<B> {
+    fn clone(&self) -> Self {
+        Self {
+            browser: self.browser.clone(),
+            max_text: self.max_text,
+        }
+    }
+}
+
+impl<B: BrowserController + 'static> BrowserTools<B> {
+    pub fn new(browser: B) -> Self {
+        Self {
+            browser: Arc::new(browser),
+            max_text: DEFAULT_MAX_TEXT,
+        }
+    }
+
+    /// Shorten the extracted texts to their first and last `max_chars / 2` characters (20,000
+    /// characters by default)
+    pub fn max_text(mut self, max_chars: usize) -> Self {
+        self.max_text = max_chars;
+        self
+    }
+
+    /// Tool `browser_navigate` opening a page, for agents
+    pub fn navigate_tool(&self) -> impl Tool + 'static {
+        let browser = self.browser.clone();
+        tool::from_typed_fn(
+            "browser_navigate",
+            "Open a web page in the browser",
+            move |args: NavigateArgs| {
+                let browser = browser.clone();
+                async move { browser.navigate(&args.url).await }
+            },
+        )
+        .with_annotations(ToolAnnotations {
+            read_only: Some(true),
+            open_world: Some(true),
+            ..Default::default()
+        })
+    }
+
+    /// Tool `browser_click` clicking an element of the page, for agents
+    pub fn click_tool(&self) -> impl Tool + 'static {
+        let browser = self.browser.clone();
+        tool::from_typed_fn(
+            "browser_click",
+            "Click an element of the current web page",
+            move |args: ClickArgs| {
+                let browser = browser.clone();
+                async move { browser.click(&args.selector).await }
+            },
+        )
+        // A click may submit a form (e.g.: a purchase), so it goes through the approval policy
+        .with_annotations(ToolAnnotations {
+            read_only: Some(false),
+            destructive: Some(true),
+            open_world: Some(true),
+            ..Default::default()
+        })
+    }
+
+    /// Tool `browser_extract_text` reading the text of the page, for agents
+    pub fn extract_text_tool(&self) -> impl Tool + 'static {
+        let browser = self.browser.clone();
+        let max_text = self.max_text;
+        tool::from_typed_fn(
+            "browser_extract_text",
+            "Read the visible text of the current web page, or of one of its elements",
+            move |args: ExtractTextArgs| {
+                let browser = browser.clone();
+                async move {
+                    let text = browser.extract_text(args.selector.as_deref()).await?;
+                    Ok::<_, BrowserError>(
+                        OutputLimit::head_tail(max_text)
+                            .apply("browser_extract_text", text)
+                            .await,
+                    )
+                }
+            },
+        )
+        .with_annotations(ToolAnnotations {
+            read_only: Some(true),
+            open_world: Some(true),
+            ..Default::default()
+        })
+    }
+
+    /// Tool `browser_screenshot` taking a screenshot of the page, for agents (see
+    /// [Screenshot::from_tool_output])
+    pub fn screenshot_tool(&self) -> impl Tool + 'static {
+        let browser = self.browser.clone();
+        tool::from_typed_fn(
+            "browser_screenshot",
+            "Take a screenshot of the current web page",
+            move |_: ScreenshotArgs| {
+                let browser = browser.clone();
+                async move { browser.screenshot().await }
+            },
+        )
+        .with_annotations(ToolAnnotations {
+            read_only: Some(true),
+            open_world: Some(true),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        completion::Prompt,
+        providers::mock::{self, MockResponse},
+    };
+
+    /// Puppeteer MCP server recording the tool calls
+    #[derive(Default)]
+    struct MockServer {
+        calls: Mutex<Vec<(String, Value)>>,
+    }
+
+    impl McpToolCaller for MockServer {
+        fn call_tool<'a>(
+            &'a self,
+            name: &'a str,
+            arguments: Value,
+        ) -> BoxFuture<'a, Result<String, ToolError>> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push((name.to_string(), arguments.clone()));
+            Box::pin(async move {
+                Ok(match name {
+                    "puppeteer_evaluate" if arguments["script"] == "document.body.innerText" => {
+                        "Execution result:\n\"Example Domain\"\n\nConsole output:\n".to_string()
+                    }
+                    "puppeteer_evaluate" => {
+                        "Execution result:\nnull\n\nConsole output:\n".to_string()
+                    }
+                    _ => format!("{name} done"),
+                })
+            })
+        }
+
+        fn call_tool_content<'a>(
+            &'a self,
+            name: &'a str,
+            _arguments: Value,
+        ) -> BoxFuture<'a, Result<Vec<ToolResultContent>, ToolError>> {
+            Box::pin(async move {
+                Ok(vec![
+                    ToolResultContent::text(format!("{name} taken")),
+                    ToolResultContent::image(
+                        "iVBORw0KGgo=",
+                        Some(ContentFormat::Base64),
+                        Some(ImageMediaType::PNG),
+                        None,
+                    ),
+                ])
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_browser() {
+        let server = Arc::new(MockServer::default());
+        let browser = McpBrowser::new(server.clone());
+
+        browser.navigate("https://example.com").await.unwrap();
+        browser.click("a#more").await.unwrap();
+        assert_eq!(browser.extract_text(None).await.unwrap(), "Example Domain");
+        assert!(matches!(
+            browser.extract_text(Some("p\"); alert(\"")).await,
+            Err(BrowserError::ElementNotFound(_))
+        ));
+        {
+            let calls = server.calls.lock().unwrap();
+            assert_eq!(calls[0].1, json!({"url": "https://example.com"}));
+            assert_eq!(calls[1].1, json!({"selector": "a#more"}));
+            // The selector is escaped in the script
+            assert!(calls[3].1["script"]
+                .as_str()
+                .unwrap()
+                .contains(r#"document.querySelector("p\"); alert(\"")"#));
+        }
+
+        // The screenshots of the tool are images for the vision models
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::tool_call("browser_screenshot", json!({})));
+        let tools = BrowserTools::new(browser);
+        let agent = model.agent().tool(tools.screenshot_tool()).build();
+        let output = agent.prompt("Take a screenshot").await.unwrap();
+        let screenshot = Screenshot::from_tool_output(&output).unwrap();
+        assert_eq!(
+            screenshot,
+            Screenshot {
+                data: "iVBORw0KGgo=".to_string(),
+                media_type: Some(ImageMediaType::PNG),
+            }
+        );
+        let Message::User { content } = screenshot.message("Describe the page") else {
+            panic!("Expected a user message");
+        };
+        assert_eq!(
+            content.iter().nth(1),
+            Some(&UserContent::image(
+                "iVBORw0KGgo=",
+                Some(ContentFormat::Base64),
+                Some(ImageMediaType::PNG),
+                None
+            ))
+        );
+    }
+}
@@ -0,0 +1,934 @@
+//! Deterministic calculation tools (`calculator` and `convert`), so that the agents compute the
+//! numeric answers instead of guessing them (e.g.: `17.5% of 2,340`, or `72 °F in °C`).
+//!
+//! - [evaluate] evaluates math expressions: `+ - * / % ^`, parentheses, the constants `pi` and
+//!   `e`, and the usual functions (e.g.: `sqrt`, `ln`, `sin`, `round`, `min`, `max`).
+//! - [convert_units] converts between the units of length, area, volume, mass, time, speed,
+//!   data and temperature (e.g.: `mi` to `km`, `°F` to `°C`, `GiB` to `MB`).
+//! - [Calculator::convert] also converts between currencies (e.g.: `USD` to `EUR`), with the
+//!   rates of a pluggable [ExchangeRates] provider:
+//!   - [FixedRates]: rates set in code (e.g.: in tests, or refreshed daily),
+//!   - [RatesCallback]: the rates returned by a callback, e.g.: fetched from an exchange rates
+//!     API.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{calculator::{Calculator, FixedRates}, providers::openai};
+//!
+//! let calculator = Calculator::new()
+//!     .exchange_rates(FixedRates::new("USD").with_rate("EUR", 0.92).with_rate("GBP", 0.79));
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("Use your tools for any calculation.")
+//!     .tool(calculator.evaluate_tool())
+//!     .tool(calculator.convert_tool())
+//!     .build();
+//! ```
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+use futures::future::BoxFuture;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::tool::{self, Tool, ToolAnnotations};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CalculatorError {
+    /// The expression can't be parsed (e.g.: unbalanced parentheses, unknown function)
+    #[error("Invalid expression: {0}")]
+    InvalidExpression(String),
+
+    /// The result is infinite or not a number (e.g.: a division by zero)
+    #[error("The result is not a finite number")]
+    NotFinite,
+
+    /// The unit is neither a known unit nor a currency with exchange rates
+    #[error("Unknown unit: {0}")]
+    UnknownUnit(String),
+
+    /// The units measure different quantities (e.g.: meters and seconds)
+    #[error("Incompatible units: {0} and {1}")]
+    IncompatibleUnits(String, String),
+
+    /// The exchange rates provider has no rate between the currencies
+    #[error("No exchange rate from {from} to {to}")]
+    RateNotFound { from: String, to: String },
+
+    /// Error returned by the exchange rates provider (e.g.: the API is unreachable)
+    #[error("RatesError: {0}")]
+    RatesError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+// ================================================================
+// Math expressions
+// ================================================================
+
+/// Maximum nesting depth of the math expressions (i.e.: of their parentheses, signs and
+/// powers), so that their parsing doesn't overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, CalculatorError> {
+    let chars = expression.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '0'..='9' | '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                // Scientific notation (e.g.: 1.5e-3)
+                if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                    let mut end = i + 1;
+                    if end < chars.len() && (chars[end] == '+' || chars[end] == '-') {
+                        end += 1;
+                    }
+                    if end < chars.len() && chars[end].is_ascii_digit() {
+                        i = end;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+                let number = chars[start..i].iter().collect::<String>();
+                tokens.push(Token::Number(number.parse().map_err(|_| {
+                    CalculatorError::InvalidExpression(format!("invalid number {number}"))
+                })?));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(
+                    chars[start..i].iter().collect::<String>().to_lowercase(),
+                ));
+            }
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                tokens.push(Token::Op('^'));
+                i += 2;
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' => {
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+            '×' => {
+                tokens.push(Token::Op('*'));
+                i += 1;
+            }
+            '÷' => {
+                tokens.push(Token::Op('/'));
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::Open);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            _ => {
+                return Err(CalculatorError::InvalidExpression(format!(
+                    "unexpected character {c}"
+                )))
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser (and evaluator) of the math expressions:
+/// - expression: term (("+" | "-") term)*
+/// - term: unary (("*" | "/" | "%") unary)*
+/// - unary: ("+" | "-") unary | power
+/// - power: atom ("^" unary)?
+/// - atom: number | constant | function "(" expression ("," expression)* ")" | "(" expression ")"
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Nesting depth of the unary being parsed
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), CalculatorError> {
+        match self.advance() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(CalculatorError::InvalidExpression(format!(
+                "expected {expected:?}, found {token:?}"
+            ))),
+            None => Err(CalculatorError::InvalidExpression(format!(
+                "expected {expected:?} at the end"
+            ))),
+        }
+    }
+
+    fn expression(&mut self) -> Result<f64, CalculatorError> {
+        let mut value = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.position += 1;
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<f64, CalculatorError> {
+        let mut value = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            self.position += 1;
+            let rhs = self.unary()?;
+            value = match op {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Ok(value)
+    }
+
+    /// Every nesting (i.e.: parentheses, signs and powers) goes through [Parser::unary], whose
+    /// depth is limited
+    fn unary(&mut self) -> Result<f64, CalculatorError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(CalculatorError::InvalidExpression(format!(
+                "nested deeper than {MAX_DEPTH} levels"
+            )));
+        }
+        self.depth += 1;
+        let value = self.signed();
+        self.depth -= 1;
+        value
+    }
+
+    fn signed(&mut self) -> Result<f64, CalculatorError> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.position += 1;
+                Ok(-self.unary()?)
+            }
+            Some(Token::Op('+')) => {
+                self.position += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<f64, CalculatorError> {
+        let base = self.atom()?;
+        if let Some(Token::Op('^')) = self.peek() {
+            self.position += 1;
+            // Right associative: 2^3^2 is 2^(3^2)
+            return Ok(base.powf(self.unary()?));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<f64, CalculatorError> {
+        match self.advance() {
+            Some(Token::Number(number)) => Ok(number),
+            Some(Token::Open) => {
+                let value = self.expression()?;
+                self.expect(Token::Close)?;
+                Ok(value)
+            }
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::Open) => {
+                self.position += 1;
+                let mut args = vec![self.expression()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.position += 1;
+                    args.push(self.expression()?);
+                }
+                self.expect(Token::Close)?;
+                function(&name, &args)
+            }
+            Some(Token::Ident(name)) => match name.as_str() {
+                "pi" | "π" => Ok(std::f64::consts::PI),
+                "e" => Ok(std::f64::consts::E),
+                "tau" => Ok(std::f64::consts::TAU),
+                _ => Err(CalculatorError::InvalidExpression(format!(
+                    "unknown constant {name}"
+                ))),
+            },
+            Some(token) => Err(CalculatorError::InvalidExpression(format!(
+                "unexpected {token:?}"
+            ))),
+            None => Err(CalculatorError::InvalidExpression(
+                "unexpected end of the expression".to_string(),
+            )),
+        }
+    }
+}
+
+/// Value of the function `name` for `args`
+fn function(name: &str, args: &[f64]) -> Result<f64, CalculatorError> {
+    let unary = |f: fn(f64) -> f64| match args {
+        [x] => Ok(f(*x)),
+        _ => Err(CalculatorError::InvalidExpression(format!(
+            "{name} takes 1 argument, got {}",
+            args.len()
+        ))),
+    };
+    match name {
+        "sqrt" => unary(f64::sqrt),
+        "cbrt" => unary(f64::cbrt),
+        "abs" => unary(f64::abs),
+        "exp" => unary(f64::exp),
+        "ln" => unary(f64::ln),
+        "log" | "log10" => unary(f64::log10),
+        "log2" => unary(f64::log2),
+        "sin" => unary(f64::sin),
+        "cos" => unary(f64::cos),
+        "tan" => unary(f64::tan),
+        "asin" => unary(f64::asin),
+        "acos" => unary(f64::acos),
+        "atan" => unary(f64::atan),
+        "floor" => unary(f64::floor),
+        "ceil" => unary(f64::ceil),
+        "round" => match args {
+            [x] => Ok(x.round()),
+            // Round to a number of decimals (e.g.: round(3.14159, 2))
+            [x, decimals] => {
+                let factor = 10f64.powf(decimals.trunc());
+                Ok((x * factor).round() / factor)
+            }
+            _ => Err(CalculatorError::InvalidExpression(
+                "round takes 1 or 2 arguments".to_string(),
+            )),
+        },
+        "min" | "max" if !args.is_empty() => {
+            let pick: fn(f64, f64) -> f64 = if name == "min" { f64::min } else { f64::max };
+            Ok(args.iter().copied().reduce(pick).unwrap_or_default())
+        }
+        _ => Err(CalculatorError::InvalidExpression(format!(
+            "unknown function {name}"
+        ))),
+    }
+}
+
+/// Value of the math expression `expression` (e.g.: `2 * (3 + 4) ^ 2`, `sqrt(2) / 2`)
+pub fn evaluate(expression: &str) -> Result<f64, CalculatorError> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+        depth: 0,
+    };
+    let value = parser.expression()?;
+    if let Some(token) = parser.peek() {
+        return Err(CalculatorError::InvalidExpression(format!(
+            "unexpected {token:?}"
+        )));
+    }
+    if !value.is_finite() {
+        return Err(CalculatorError::NotFinite);
+    }
+    Ok(value)
+}
+
+/// `value` rounded to 10 decimals, without the trailing zeros (e.g.: `0.3` for `0.1 + 0.2`)
+pub fn format_number(value: f64) -> String {
+    if value != 0.0 && (value.abs() >= 1e15 || value.abs() < 1e-6) {
+        return format!("{value:e}");
+    }
+    let formatted = format!("{value:.10}");
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    match formatted {
+        "-0" => "0".to_string(),
+        formatted => formatted.to_string(),
+    }
+}
+
+// ================================================================
+// Units
+// ================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Dimension {
+    Length,
+    Area,
+    Volume,
+    Mass,
+    Time,
+    Speed,
+    Data,
+    Temperature,
+}
+
+/// Units: their names, dimension, and value in the base unit of the dimension (i.e.: meter,
+/// square meter, liter, kilogram, second, meter per second, byte). The temperatures are
+/// converted separately.
+const UNITS: &[(&[&str], Dimension, f64)] = &[
+    (
+        &["m", "meter", "meters", "metre", "metres"],
+        Dimension::Length,
+        1.0,
+    ),
+    (
+        &["km", "kilometer", "kilometers", "kilometre", "kilometres"],
+        Dimension::Length,
+        1e3,
+    ),
+    (
+        &[
+            "cm",
+            "centimeter",
+            "centimeters",
+            "centimetre",
+            "centimetres",
+        ],
+        Dimension::Length,
+        1e-2,
+    ),
+    (
+        &[
+            "mm",
+            "millimeter",
+            "millimeters",
+            "millimetre",
+            "millimetres",
+        ],
+        Dimension::Length,
+        1e-3,
+    ),
+    (
+        &["µm", "um", "micrometer", "micrometers"],
+        Dimension::Length,
+        1e-6,
+    ),
+    (&["nm", "nanometer", "nanometers"], Dimension::Length, 1e-9),
+    (&["mi", "mile", "miles"], Dimension::Length, 1609.344),
+    (&["yd", "yard", "yards"], Dimension::Length, 0.9144),
+    (&["ft", "foot", "feet"], Dimension::Length, 0.3048),
+    (&["in", "inch", "inches"], Dimension::Length, 0.0254),
+    (
+        &["nmi", "nautical mile", "nautical miles"],
+        Dimension::Length,
+        1852.0,
+    ),
+    (
+        &["m2", "m²", "square meter", "square meters"],
+        Dimension::Area,
+        1.0,
+    ),
+    (
+        &["km2", "km²", "square kilometer", "square kilometers"],
+        Dimension::Area,
+        1e6,
+    ),
+    (
+        &["cm2", "cm²", "square centimeter", "square centimeters"],
+        Dimension::Area,
+        1e-4,
+    ),
+    (&["ha", "hectare", "hectares"], Dimension::Area, 1e4),
+    (&["acre", "acres"], Dimension::Area, 4046.856_422_4),
+    (
+        &["ft2", "ft²", "sqft", "square foot", "square feet"],
+        Dimension::Area,
+        0.092_903_04,
+    ),
+    (
+        &["mi2", "mi²", "square mile", "square miles"],
+        Dimension::Area,
+        2_589_988.110_336,
+    ),
+    (
+        &["l", "liter", "liters", "litre", "litres"],
+        Dimension::Volume,
+        1.0,
+    ),
+    (
+        &[
+            "ml",
+            "milliliter",
+            "milliliters",
+            "millilitre",
+            "millilitres",
+        ],
+        Dimension::Volume,
+        1e-3,
+    ),
+    (
+        &["m3", "m³", "cubic meter", "cubic meters"],
+        Dimension::Volume,
+        1e3,
+    ),
+    (
+        &["gal", "gallon", "gallons"],
+        Dimension::Volume,
+        3.785_411_784,
+    ),
+    (&["qt", "quart", "quarts"], Dimension::Volume, 0.946_352_946),
+    (&["pt", "pint", "pints"], Dimension::Volume, 0.473_176_473),
+    (&["cup", "cups"], Dimension::Volume, 0.236_588_236_5),
+    (
+        &["fl oz", "floz", "fluid ounce", "fluid ounces"],
+        Dimension::Volume,
+        0.029_573_529_562_5,
+    ),
+    (
+        &["tbsp", "tablespoon", "tablespoons"],
+        Dimension::Volume,
+        0.014_786_764_781_25,
+    ),
+    (
+        &["tsp", "teaspoon", "teaspoons"],
+        Dimension::Volume,
+        0.004_928_921_593_75,
+    ),
+    (&["kg", "kilogram", "kilograms"], Dimension::Mass, 1.0),
+    (&["g", "gram", "grams"], Dimension::Mass, 1e-3),
+    (&["mg", "milligram", "milligrams"], Dimension::Mass, 1e-6),
+    (
+        &["t", "tonne", "tonnes", "metric ton", "metric tons"],
+        Dimension::Mass,
+        1e3,
+    ),
+    (
+        &["lb", "lbs", "pound", "pounds"],
+        Dimension::Mass,
+        0.453_592_37,
+    ),
+    (
+        &["oz", "ounce", "ounces"],
+        Dimension::Mass,
+        0.028_349_523_125,
+    ),
+    (&["st", "stone", "stones"], Dimension::Mass, 6.350_293_18),
+    (&["s", "sec", "second", "seconds"], Dimension::Time, 1.0),
+    (
+        &["ms", "millisecond", "milliseconds"],
+        Dimension::Time,
+        1e-3,
+    ),
+    (&["min", "minute", "minutes"], Dimension::Time, 60.0),
+    (&["h", "hr", "hour", "hours"], Dimension::Time, 3600.0),
+    (&["d", "day", "days"], Dimension::Time, 86_400.0),
+    (&["week", "weeks"], Dimension::Time, 604_800.0),
+    (&["yr", "year", "years"], Dimension::Time, 31_557_600.0),
+    (&["m/s", "meters per second"], Dimension::Speed, 1.0),
+    (
+        &["km/h", "kph", "kmh", "kilometers per hour"],
+        Dimension::Speed,
+        1.0 / 3.6,
+    ),
+    (&["mph", "miles per hour"], Dimension::Speed, 0.447_04),
+    (&["kn", "knot", "knots"], Dimension::Speed, 1852.0 / 3600.0),
+    (&["ft/s", "feet per second"], Dimension::Speed, 0.3048),
+    (&["B", "byte", "bytes"], Dimension::Data, 1.0),
+    (&["bit", "bits"], Dimension::Data, 0.125),
+    (&["kB", "KB", "kilobyte", "kilobytes"], Dimension::Data, 1e3),
+    (&["MB", "megabyte", "megabytes"], Dimension::Data, 1e6),
+    (&["GB", "gigabyte", "gigabytes"], Dimension::Data, 1e9),
+    (&["TB", "terabyte", "terabytes"], Dimension::Data, 1e12),
+    (&["KiB", "kibibyte", "kibibytes"], Dimension::Data, 1024.0),
+    (
+        &["MiB", "mebibyte", "mebibytes"],
+        Dimension::Data,
+        1_048_576.0,
+    ),
+    (
+        &["GiB", "gibibyte", "gibibytes"],
+        Dimension::Data,
+        1_073_741_824.0,
+    ),
+    (
+        &["TiB", "tebibyte", "tebibytes"],
+        Dimension::Data,
+        1_099_511_627_776.0,
+    ),
+    (&["°C", "C", "celsius"], Dimension::Temperature, 0.0),
+    (&["°F", "F", "fahrenheit"], Dimension::Temperature, 0.0),
+    (&["K", "kelvin", "kelvins"], Dimension::Temperature, 0.0),
+];
+
+/// Unit named `name` (the names are case sensitive first, e.g.: `mB` isn't `MB`, then case
+/// insensitive), with the canonical name of the unit
+fn unit(name: &str) -> Option<(&'static str, Dimension, f64)> {
+    let name = name.trim();
+    UNITS
+        .iter()
+        .find(|(names, _, _)| names.contains(&name))
+        .or_else(|| {
+            UNITS
+                .iter()
+                .find(|(names, _, _)| names.iter().any(|unit| unit.eq_ignore_ascii_case(name)))
+        })
+        .map(|(names, dimension, factor)| (names[0], *dimension, *factor))
+}
+
+/// `value` in the temperature unit `unit` (e.g.: `°C`), in kelvins
+fn to_kelvin(unit: &str, value: f64) -> f64 {
+    match unit {
+        "°C" => value + 273.15,
+        "°F" => (value - 32.0) * 5.0 / 9.0 + 273.15,
+        _ => value,
+    }
+}
+
+/// `kelvins` in the temperature unit `unit` (e.g.: `°C`)
+fn from_kelvin(unit: &str, kelvins: f64) -> f64 {
+    match unit {
+        "°C" => kelvins - 273.15,
+        "°F" => (kelvins - 273.15) * 9.0 / 5.0 + 32.0,
+        _ => kelvins,
+    }
+}
+
+/// `value` converted from the unit `from` to the unit `to` (e.g.: `mi` to `km`, `°F` to `°C`)
+pub fn convert_units(value: f64, from: &str, to: &str) -> Result<f64, CalculatorError> {
+    let (from_unit, from_dimension, from_factor) =
+        unit(from).ok_or_else(|| CalculatorError::UnknownUnit(from.to_string()))?;
+    let (to_unit, to_dimension, to_factor) =
+        unit(to).ok_or_else(|| CalculatorError::UnknownUnit(to.to_string()))?;
+    if from_dimension != to_dimension {
+        return Err(CalculatorError::IncompatibleUnits(
+            from.to_string(),
+            to.to_string(),
+        ));
+    }
+    Ok(match from_dimension {
+        Dimension::Temperature => from_kelvin(to_unit, to_kelvin(from_unit, value)),
+        _ => value * from_factor / to_factor,
+    })
+}
+
+// ================================================================
+// Currencies
+// ================================================================
+
+/// Trait for the providers of the exchange rates between currencies.
+pub trait ExchangeRates: Send + Sync {
+    /// Amount of the currency `to` worth one unit of the currency `from` (e.g.: `0.92` from `USD`
+    /// to `EUR`), or `None` if the provider doesn't have the rate. The currencies are ISO 4217
+    /// codes in uppercase.
+    fn rate(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> impl Future<Output = Result<Option<f64>, CalculatorError>> + Send;
+}
+
+/// Dyn-compatible version of the [ExchangeRates] trait.
+trait ExchangeRatesDyn: Send + Sync {
+    fn rate<'a>(
+        &'a self,
+        from: &'a str,
+        to: &'a str,
+    ) -> BoxFuture<'a, Result<Option<f64>, CalculatorError>>;
+}
+
+impl<T: ExchangeRates> ExchangeRatesDyn for T {
+    fn rate<'a>(
+        &'a self,
+        from: &'a str,
+        to: &'a str,
+    ) -> BoxFuture<'a, Result<Option<f64>, CalculatorError>> {
+        Box::pin(ExchangeRates::rate(self, from, to))
+    }
+}
+
+/// Exchange rates set in code, as the amounts of the currencies worth one unit of a base
+/// currency.
+#[derive(Clone, Debug)]
+pub struct FixedRates {
+    base: String,
+    rates: HashMap<String, f64>,
+}
+
+impl FixedRates {
+    /// Rates relative to the currency `base` (e.g.: `USD`)
+    pub fn new(base: &str) -> Self {
+        Self {
+            base: base.to_uppercase(),
+            rates: HashMap::new(),
+        }
+    }
+
+    /// Set the amount of the currency `currency` worth one unit of the base currency (e.g.:
+    /// `0.92` for `EUR` with `USD` as base)
+    pub fn with_rate(mut self, currency: &str, rate: f64) -> Self {
+        self.rates.insert(currency.to_uppercase(), rate);
+        self
+    }
+
+    /// Amount of `currency` worth one unit of the base currency
+    fn base_rate(&self, currency: &str) -> Option<f64> {
+        if currency == self.base {
+            Some(1.0)
+        } else {
+            self.rates.get(currency).copied()
+        }
+    }
+}
+
+impl ExchangeRates for FixedRates {
+    async fn rate(&self, from: &str, to: &str) -> Result<Option<f64>, CalculatorError> {
+        Ok(self
+            .base_rate(from)
+            .zip(self.base_rate(to))
+            .map(|(from, to)| to / from))
+    }
+}
+
+/// Exchange rates returned by a callback (e.g.: fetching them from an exchange rates API),
+/// called with the currencies `from` and `to`.
+pub struct RatesCallback<F> {
+    callback: F,
+}
+
+impl<F, Fut> RatesCallback<F>
+where
+    F: Fn(String, String) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Option<f64>, CalculatorError>> + Send,
+{
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F, Fut> ExchangeRates for RatesCallback<F>
+where
+    F: Fn(String, String) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Option<f64>, CalculatorError>> + Send,
+{
+    async fn rate(&self, from: &str, to: &str) -> Result<Option<f64>, CalculatorError> {
+        (self.callback)(from.to_string(), to.to_string()).await
+    }
+}
+
+/// Whether `unit` looks like an ISO 4217 currency code (e.g.: `usd`)
+fn is_currency(unit: &str) -> bool {
+    unit.len() == 3 && unit.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+// ================================================================
+// Tools
+// ================================================================
+
+/// Arguments of the `calculator` tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EvaluateArgs {
+    /// Math expression (e.g.: `2 * (3 + 4) ^ 2`, `sqrt(2) / 2`, `round(2340 * 17.5 / 100, 2)`)
+    pub expression: String,
+}
+
+/// Arguments of the `convert` tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ConvertArgs {
+    /// Value to convert
+    pub value: f64,
+    /// Unit or currency of the value (e.g.: `mi`, `°F`, `GiB`, `USD`)
+    pub from: String,
+    /// Unit or currency to convert the value to (e.g.: `km`, `°C`, `MB`, `EUR`)
+    pub to: String,
+}
+
+/// Deterministic calculation tools (see the [module](self) docs).
+#[derive(Clone, Default)]
+pub struct Calculator {
+    rates: Option<Arc<dyn ExchangeRatesDyn>>,
+}
+
+impl Calculator {
+    /// Calculator converting units, but no currencies (until given exchange rates with
+    /// [Calculator::exchange_rates])
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert between currencies with the rates of `rates`
+    pub fn exchange_rates(mut self, rates: impl ExchangeRates + 'static) -> Self {
+        self.rates = Some(Arc::new(rates));
+        self
+    }
+
+    /// `value` converted from the unit or currency `from` to `to`
+    pub async fn convert(&self, value: f64, from: &str, to: &str) -> Result<f64, CalculatorError> {
+        let rates = match &self.rates {
+            Some(rates)
+                if unit(from).is_none()
+                    && unit(to).is_none()
+                    && is_currency(from)
+                    && is_currency(to) =>
+            {
+                rates
+            }
+            _ => return convert_units(value, from, to),
+        };
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        let rate = if from == to {
+            1.0
+        } else {
+            rates
+                .rate(&from, &to)
+                .await?
+                .ok_or(CalculatorError::RateNotFound { from, to })?
+        };
+        Ok(value * rate)
+    }
+
+    /// Tool `calculator` evaluating math expressions, for agents
+    pub fn evaluate_tool(&self) -> impl Tool + 'static {
+        tool::from_typed_fn(
+            "calculator",
+            "Evaluate a math expression exactly. Use it for any arithmetic instead of computing \
+             the result yourself.",
+            |args: EvaluateArgs| {
+                let result = evaluate(&args.expression).map(format_number);
+                async move { result }
+            },
+        )
+        .with_annotations(ToolAnnotations {
+            read_only: Some(true),
+            idempotent: Some(true),
+            open_world: Some(false),
+            ..Default::default()
+        })
+    }
+
+    /// Tool `convert` converting between units and currencies, for agents
+    pub fn convert_tool(&self) -> impl Tool + 'static {
+        let calculator = self.clone();
+        let description = if self.rates.is_some() {
+            "Convert a value between units (length, area, volume, mass, time, speed, data, \
+             temperature) or currencies (ISO 4217 codes, e.g.: USD)"
+        } else {
+            "Convert a value between units (length, area, volume, mass, time, speed, data, \
+             temperature)"
+        };
+        tool::from_typed_fn("convert", description, move |args: ConvertArgs| {
+            let calculator = calculator.clone();
+            async move {
+                let result = calculator.convert(args.value, &args.from, &args.to).await?;
+                Ok::<_, CalculatorError>(format!(
+                    "{} {} = {} {}",
+                    format_number(args.value),
+                    args.from,
+                    format_number(result),
+                    args.to
+                ))
+            }
+        })
+        .with_annotations(ToolAnnotations {
+            read_only: Some(true),
+            open_world: Some(self.rates.is_some()),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        completion::Prompt,
+        providers::mock::{self, MockResponse},
+    };
+
+    #[tokio::test]
+    async fn test_calculator() {
+        let eval = |expression| format_number(evaluate(expression).unwrap());
+        assert_eq!(eval("2 + 3 * 4"), "14");
+        assert_eq!(eval("2 * (3 + 4) ^ 2"), "98");
+        assert_eq!(eval("-2 ^ 2"), "-4");
+        assert_eq!(eval("2 ^ 3 ^ 2"), "512");
+        assert_eq!(eval("0.1 + 0.2"), "0.3");
+        assert_eq!(eval("round(2340 * 17.5 / 100, 2)"), "409.5");
+        assert_eq!(eval("max(1, sqrt(16), 2) % 3"), "1");
+        assert_eq!(eval("1.5e3 / 4"), "375");
+        assert_eq!(eval("cos(pi)"), "-1");
+        assert!(matches!(evaluate("1 / 0"), Err(CalculatorError::NotFinite)));
+        assert!(matches!(
+            evaluate("(1 + 2"),
+            Err(CalculatorError::InvalidExpression(_))
+        ));
+        assert!(matches!(
+            evaluate("system(1)"),
+            Err(CalculatorError::InvalidExpression(_))
+        ));
+        // The deeply nested expressions are rejected, rather than overflowing the stack
+        let nested = format!("{}1{}", "(".repeat(60), ")".repeat(60));
+        assert_eq!(evaluate(&nested).unwrap(), 1.0);
+        for expression in [
+            format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000)),
+            format!("{}1", "-".repeat(10_000)),
+            format!("2{}", "^2".repeat(10_000)),
+        ] {
+            assert!(matches!(
+                evaluate(&expression),
+                Err(CalculatorError::InvalidExpression(_))
+            ));
+        }
+
+        let convert = |value, from, to| format_number(convert_units(value, from, to).unwrap());
+        assert_eq!(convert(1.0, "mi", "km"), "1.609344");
+        assert_eq!(convert(212.0, "°F", "celsius"), "100");
+        assert_eq!(convert(1.0, "GiB", "MB"), "1073.741824");
+        assert_eq!(convert(2.0, "hours", "min"), "120");
+        assert!(matches!(
+            convert_units(1.0, "kg", "m"),
+            Err(CalculatorError::IncompatibleUnits(_, _))
+        ));
+
+        let calculator = Calculator::new().exchange_rates(
+            FixedRates::new("USD")
+                .with_rate("EUR", 0.8)
+                .with_rate("GBP", 0.5),
+        );
+        assert_eq!(
+            format_number(calculator.convert(10.0, "eur", "GBP").await.unwrap()),
+            "6.25"
+        );
+        assert!(matches!(
+            calculator.convert(10.0, "USD", "JPY").await,
+            Err(CalculatorError::RateNotFound { .. })
+        ));
+        // Without rates, the currencies are unknown units
+        assert!(matches!(
+            Calculator::new().convert(10.0, "USD", "EUR").await,
+            Err(CalculatorError::UnknownUnit(_))
+        ));
+
+        let model = mock::CompletionModel::new().push(MockResponse::tool_call(
+            "convert",
+            json!({"value": 100, "from": "USD", "to": "EUR"}),
+        ));
+        let agent = model.agent().tool(calculator.convert_tool()).build();
+        assert_eq!(
+            agent.prompt("How much is $100 in euros?").await.unwrap(),
+            "\"100 USD = 80 EUR\""
+        );
+    }
+}
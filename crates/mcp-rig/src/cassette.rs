@@ -0,0 +1,417 @@
+//! VCR-style recording and replaying of the HTTP interactions of provider clients, so that
+//! integration tests can run offline and deterministically.
+//!
+//! A [Cassette] is a JSON file containing the recorded interactions (request and response)
+//! of a client. In [CassetteMode::Record] mode, the requests are sent to the provider and the
+//! interactions are appended to the cassette file. In [CassetteMode::Replay] mode, no request is
+//! sent: the response of the matching recorded interaction is returned instead.
+//!
+//! Before being recorded, the interactions go through the same redaction rules as the
+//! [payload logger](crate::payload_log) (authentication headers, API keys, etc.), so that
+//! cassettes can be committed safely. Additional rules can be added with [Cassette::rule].
+//!
+//! Record/replay is supported by the OpenAI and Cohere clients (for non-streaming requests),
+//! see `openai::Client::with_cassette` and `cohere::Client::with_cassette`.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{cassette::Cassette, completion::Prompt, providers::openai};
+//!
+//! #[tokio::test]
+//! async fn test_agent() {
+//!     // Replays `tests/cassettes/agent.json` if it exists, otherwise records it
+//!     // (which requires the `OPENAI_API_KEY` environment variable to be set)
+//!     let cassette = Cassette::auto("tests/cassettes/agent.json").unwrap();
+//!     let client = openai::Client::new(&std::env::var("OPENAI_API_KEY").unwrap_or_default())
+//!         .with_cassette(cassette);
+//!
+//!     let agent = client.agent("gpt-4o").build();
+//!     let response = agent.prompt("What is a flurbo?").await.unwrap();
+//!     assert!(response.contains("flurbo"));
+//! }
+//! ```
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    completion::canonical,
+    http_client::{HttpBackend, HttpClientError},
+    payload_log::{self, RedactionRule, SendLogged},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CassetteError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// Mode of a [Cassette].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CassetteMode {
+    /// Send the requests to the provider and record the interactions
+    Record,
+    /// Replay the recorded interactions without sending any request
+    Replay,
+}
+
+/// Recorded request.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    pub body: Value,
+}
+
+/// Recorded response.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RecordedResponse {
+    pub status: u16,
+    /// JSON body of the response, or its raw text if it is not valid JSON
+    pub body: Value,
+}
+
+/// Recorded HTTP interaction.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    /// Whether each interaction was already replayed
+    replayed: Vec<bool>,
+}
+
+/// Cassette of recorded HTTP interactions. Cloning a cassette returns a handle to the same
+/// cassette. See the [module documentation](self).
+#[derive(Clone)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    state: Arc<Mutex<CassetteState>>,
+    rules: Arc<Vec<Box<dyn RedactionRule>>>,
+}
+
+impl Cassette {
+    /// Create a cassette recording the interactions to the file at `path`.
+    /// The previous content of the file (if any) is replaced.
+    pub fn record(path: impl AsRef<Path>) -> Self {
+        Self::new(path.as_ref(), CassetteMode::Record, vec![])
+    }
+
+    /// Load the cassette at `path` to replay its interactions.
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self, CassetteError> {
+        let file: CassetteFile = serde_json::from_slice(&std::fs::read(path.as_ref())?)?;
+        Ok(Self::new(
+            path.as_ref(),
+            CassetteMode::Replay,
+            file.interactions,
+        ))
+    }
+
+    /// Replay the cassette at `path` if it exists, otherwise record it.
+    pub fn auto(path: impl AsRef<Path>) -> Result<Self, CassetteError> {
+        if path.as_ref().exists() {
+            Self::replay(path)
+        } else {
+            Ok(Self::record(path))
+        }
+    }
+
+    fn new(path: &Path, mode: CassetteMode, interactions: Vec<Interaction>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            mode,
+            state: Arc::new(Mutex::new(CassetteState {
+                replayed: vec![false; interactions.len()],
+                interactions,
+            })),
+            rules: Arc::new(payload_log::default_rules()),
+        }
+    }
+
+    /// Add a scrubbing rule, applied to the interactions (as JSON) before they are recorded
+    /// and to the requests before they are matched against the recorded ones.
+    /// Rules must be added before the cassette is attached to a client.
+    pub fn rule(mut self, rule: impl RedactionRule + 'static) -> Self {
+        Arc::get_mut(&mut self.rules)
+            .expect("Scrubbing rules should be added before the cassette is shared")
+            .push(Box::new(rule));
+        self
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// Interactions recorded (or loaded) so far.
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.state().interactions.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, CassetteState> {
+        self.state.lock().expect("Cassette lock poisoned")
+    }
+
+    /// Apply the scrubbing rules to `interaction`.
+    fn scrub(&self, interaction: Interaction) -> Interaction {
+        let mut json =
+            serde_json::to_value(&interaction).expect("Interaction should be serializable");
+        self.rules.iter().for_each(|rule| rule.redact(&mut json));
+        serde_json::from_value(json).unwrap_or(interaction)
+    }
+
+    /// Apply the scrubbing rules to `request`, so that it can be compared to the
+    /// recorded (i.e.: scrubbed) requests.
+    fn scrub_request(&self, request: &RecordedRequest) -> RecordedRequest {
+        let mut json = serde_json::json!({ "request": request });
+        self.rules.iter().for_each(|rule| rule.redact(&mut json));
+        serde_json::from_value(json["request"].take()).unwrap_or_else(|_| request.clone())
+    }
+
+    /// Response of the first interaction (not replayed yet) matching `request`: same method,
+    /// URL and body if possible (compared in their canonical form, see
+    /// [canonical](crate::completion::canonical)), otherwise same method and URL.
+    fn find(&self, request: &RecordedRequest) -> Option<RecordedResponse> {
+        let request = self.scrub_request(request);
+        let body = canonical::to_string(&canonical::strip_volatile(request.body.clone()));
+        let mut state = self.state();
+
+        let index = state
+            .interactions
+            .iter()
+            .zip(&state.replayed)
+            .position(|(interaction, replayed)| {
+                !replayed
+                    && interaction.request.method == request.method
+                    && interaction.request.url == request.url
+                    && canonical::to_string(&canonical::strip_volatile(
+                        interaction.request.body.clone(),
+                    )) == body
+            })
+            .or_else(|| {
+                state.interactions.iter().zip(&state.replayed).position(
+                    |(interaction, replayed)| {
+                        !replayed
+                            && interaction.request.method == request.method
+                            && interaction.request.url == request.url
+                    },
+                )
+            })?;
+
+        state.replayed[index] = true;
+        Some(state.interactions[index].response.clone())
+    }
+
+    /// Record an interaction and save the cassette.
+    fn push(&self, interaction: Interaction) {
+        let interaction = self.scrub(interaction);
+        let mut state = self.state();
+        state.interactions.push(interaction);
+        state.replayed.push(false);
+
+        if let Err(e) = self.save(&state.interactions) {
+            tracing::warn!(target: "rig",
+                "Failed to save cassette {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    fn save(&self, interactions: &[Interaction]) -> Result<(), CassetteError> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = CassetteFile {
+            interactions: interactions.to_vec(),
+        };
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&file)?)?;
+        Ok(())
+    }
+}
+
+/// Extension trait used by the providers to send requests through a [Cassette] (if any).
+pub(crate) trait SendRecorded {
+    fn send_recorded(
+        self,
+        source: &'static str,
+        cassette: Option<&Cassette>,
+        http: &HttpBackend,
+    ) -> impl Future<Output = Result<reqwest::Response, HttpClientError>>;
+}
+
+impl SendRecorded for reqwest::RequestBuilder {
+    /// Fails with [HttpClientError::NoRecordedInteraction] in replay mode if the cassette has
+    /// no interaction matching the request.
+    async fn send_recorded(
+        self,
+        source: &'static str,
+        cassette: Option<&Cassette>,
+        http: &HttpBackend,
+    ) -> Result<reqwest::Response, HttpClientError> {
+        let Some(cassette) = cassette else {
+            return self.send_logged(source, http).await;
+        };
+
+        let (client, request) = self.build_split();
+        let request = request?;
+        let recorded_request = RecordedRequest {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(payload_log::body_to_value)
+                .unwrap_or_default(),
+        };
+
+        match cassette.mode {
+            CassetteMode::Replay => {
+                let Some(response) = cassette.find(&recorded_request) else {
+                    return Err(HttpClientError::NoRecordedInteraction(format!(
+                        "cassette {} has no interaction matching {} {} with body {}",
+                        cassette.path.display(),
+                        recorded_request.method,
+                        recorded_request.url,
+                        recorded_request.body
+                    )));
+                };
+
+                let body = match response.body {
+                    Value::String(text) => text.into_bytes(),
+                    body => serde_json::to_vec(&body).unwrap_or_default(),
+                };
+                let mut headers = reqwest::header::HeaderMap::new();
+                headers.insert(
+                    reqwest::header::CONTENT_TYPE,
+                    reqwest::header::HeaderValue::from_static("application/json"),
+                );
+
+                Ok(payload_log::rebuild_response(
+                    reqwest::StatusCode::from_u16(response.status)
+                        .unwrap_or(reqwest::StatusCode::OK),
+                    reqwest::Version::HTTP_11,
+                    headers,
+                    body,
+                ))
+            }
+            CassetteMode::Record => {
+                let response = http.execute(&client, request).await?;
+
+                let status = response.status();
+                let version = response.version();
+                let headers = response.headers().clone();
+                let bytes = response.bytes().await?;
+
+                cassette.push(Interaction {
+                    request: recorded_request,
+                    response: RecordedResponse {
+                        status: status.as_u16(),
+                        body: payload_log::body_to_value(&bytes),
+                    },
+                });
+
+                Ok(payload_log::rebuild_response(
+                    status, version, headers, bytes,
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::payload_log::{RedactPath, REDACTED};
+
+    fn interaction(content: &str, answer: &str) -> Interaction {
+        Interaction {
+            request: RecordedRequest {
+                method: "POST".to_string(),
+                url: "https://api.openai.com/v1/chat/completions".to_string(),
+                body: json!({"messages": [{"role": "user", "content": content}]}),
+            },
+            response: RecordedResponse {
+                status: 200,
+                body: json!({"answer": answer}),
+            },
+        }
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("cassettes/test.json");
+
+        let cassette = Cassette::record(&path).rule(RedactPath::new("request.body.user"));
+        let mut recorded = interaction("Hello", "Hi!");
+        recorded.request.body["user"] = json!("user-42");
+        cassette.push(recorded);
+        cassette.push(interaction("Bye", "Goodbye!"));
+        let mut recorded = interaction("Embed", "[0.1, 0.2]");
+        recorded.request.url = "https://example.com/v1beta/embed?key=AIzaSecret".to_string();
+        cassette.push(recorded);
+
+        let cassette = Cassette::auto(&path).unwrap();
+        assert_eq!(cassette.mode(), CassetteMode::Replay);
+
+        let interactions = cassette.interactions();
+        assert_eq!(interactions.len(), 3);
+        assert_eq!(interactions[0].request.body["user"], REDACTED);
+        assert!(!interactions[2].request.url.contains("AIzaSecret"));
+
+        // Requests are scrubbed before being matched
+        let mut hello = interaction("Hello", "").request;
+        hello.body["user"] = json!("user-7");
+        assert_eq!(cassette.scrub_request(&hello).body["user"], REDACTED);
+
+        // Requests are matched on their body, regardless of the order
+        let bye = cassette.find(&interaction("Bye", "").request).unwrap();
+        assert_eq!(bye.body["answer"], "Goodbye!");
+
+        // Falls back to the first interaction not replayed yet with the same URL
+        let other = cassette.find(&interaction("Other", "").request).unwrap();
+        assert_eq!(other.body["answer"], "Hi!");
+
+        assert_eq!(cassette.find(&interaction("Bye", "").request), None);
+    }
+
+    #[tokio::test]
+    async fn test_replay_without_matching_interaction() {
+        let cassette = Cassette::new(
+            Path::new("cassettes/test.json"),
+            CassetteMode::Replay,
+            vec![interaction("Hello", "Hi!")],
+        );
+
+        let request = reqwest::Client::new()
+            .post("https://api.openai.com/v1/embeddings")
+            .json(&json!({"input": "Hello"}));
+        let error = request
+            .send_recorded("test", Some(&cassette), &HttpBackend::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, HttpClientError::NoRecordedInteraction(_)));
+        assert!(error
+            .to_string()
+            .contains("POST https://api.openai.com/v1/embeddings"));
+    }
+}
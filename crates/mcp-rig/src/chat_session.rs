@@ -0,0 +1,246 @@
+//! Chat sessions with branching: a [ChatSession] keeps the history of a conversation with a
+//! [Chat] implementation (e.g.: an agent), and can be forked at any turn into a new branch,
+//! e.g.: to regenerate an answer or explore alternate continuations, without changing the
+//! original session.
+//!
+//! The histories of the branches are copy-on-write: the messages they have in common are
+//! shared, and a fork only costs a pointer copy.
+//!
+//! By default, the full history is sent to the chatbot with each prompt. With
+//! [Memory](crate::memory::Memory) strategies (see [ChatSession::memory]), e.g.: a
+//! [HybridMemory](crate::memory::HybridMemory) or an
+//! [EntityMemory](crate::memory::EntityMemory), the context recalled by the memories is sent
+//! instead.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{chat_session::ChatSession, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//! let agent = openai.agent(openai::GPT_4O).build();
+//!
+//! let mut session = ChatSession::new(agent);
+//! session.chat("Suggest a name for my cat.").await?;
+//! session.chat("Something shorter?").await?;
+//!
+//! // Regenerate the last answer in a new branch
+//! let mut branch = session.fork();
+//! let other_answer = branch.regenerate().await?;
+//!
+//! // Continue the conversation from its first turn in another branch
+//! let mut branch = session.fork_at(2);
+//! branch.chat("Something longer?").await?;
+//! ```
+use std::sync::Arc;
+
+use crate::{
+    completion::{Chat, CompletionError, Message, PromptError},
+    memory::Memory,
+};
+
+/// Message of a conversation, linked to the previous message (shared by the branches).
+struct Node {
+    message: Message,
+    previous: Option<Arc<Node>>,
+    /// Number of messages of the conversation, up to this message (included)
+    len: usize,
+}
+
+/// Chat session with branching (see the [module](self) docs). Cloning a session is the same as
+/// [forking](ChatSession::fork) it.
+pub struct ChatSession<C: Chat> {
+    chatbot: Arc<C>,
+    /// Memory strategies recalling the context of the prompts, in order (the full history if
+    /// none)
+    memories: Vec<Arc<dyn Memory>>,
+    /// Last message of the conversation
+    last: Option<Arc<Node>>,
+}
+
+impl<C: Chat> Clone for ChatSession<C> {
+    fn clone(&self) -> Self {
+        Self {
+            chatbot: self.chatbot.clone(),
+            memories: self.memories.clone(),
+            last: self.last.clone(),
+        }
+    }
+}
+
+impl<C: Chat> ChatSession<C> {
+    /// Start a new conversation with `chatbot`
+    pub fn new(chatbot: C) -> Self {
+        Self {
+            chatbot: Arc::new(chatbot),
+            memories: vec![],
+            last: None,
+        }
+    }
+
+    /// Send the context recalled by `memory` to the chatbot with each prompt, instead of the
+    /// full history (shared by the branches of the session). With several memories, each one
+    /// recalls its context from the context recalled by the previous one.
+    pub fn memory(mut self, memory: impl Memory + 'static) -> Self {
+        self.memories.push(Arc::new(memory));
+        self
+    }
+
+    /// Continue the conversation `history` with `chatbot`
+    pub fn from_history(chatbot: C, history: Vec<Message>) -> Self {
+        let mut session = Self::new(chatbot);
+        history
+            .into_iter()
+            .for_each(|message| session.push(message));
+        session
+    }
+
+    /// Number of messages of the conversation
+    pub fn len(&self) -> usize {
+        self.last.as_ref().map(|node| node.len).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.last.is_none()
+    }
+
+    /// Messages of the conversation, in order
+    pub fn history(&self) -> Vec<Message> {
+        let mut history = Vec::with_capacity(self.len());
+        let mut node = self.last.as_deref();
+        while let Some(current) = node {
+            history.push(current.message.clone());
+            node = current.previous.as_deref();
+        }
+        history.reverse();
+        history
+    }
+
+    /// Append `message` to the conversation (e.g.: to edit it before prompting the chatbot)
+    pub fn push(&mut self, message: Message) {
+        self.last = Some(Arc::new(Node {
+            message,
+            len: self.len() + 1,
+            previous: self.last.take(),
+        }));
+    }
+
+    /// Send `prompt` to the chatbot with the history of the conversation, and append both the
+    /// prompt and the answer to the conversation. On error, the conversation is unchanged.
+    pub async fn chat(&mut self, prompt: impl Into<Message> + Send) -> Result<String, PromptError> {
+        let prompt = prompt.into();
+        let mut context = self.history();
+        for memory in &self.memories {
+            context = memory
+                .recall(&prompt, context)
+                .await
+                .map_err(|e| CompletionError::RequestError(Box::new(e)))?;
+        }
+        let answer = self.chatbot.chat(prompt.clone(), context).await?;
+        self.push(prompt);
+        self.push(Message::assistant(answer.clone()));
+
+        if !self.memories.is_empty() {
+            let history = self.history();
+            for memory in &self.memories {
+                if let Err(e) = memory.record(&history).await {
+                    tracing::warn!(target: "rig", "Failed to record the turn in memory: {}", e);
+                }
+            }
+        }
+        Ok(answer)
+    }
+
+    /// New branch of the conversation, from its current turn
+    pub fn fork(&self) -> Self {
+        self.clone()
+    }
+
+    /// New branch of the conversation, from its first `len` messages (e.g.: `len() - 2` to
+    /// remove the last prompt and answer)
+    pub fn fork_at(&self, len: usize) -> Self {
+        let mut last = self.last.clone();
+        while let Some(node) = last.as_ref().filter(|node| node.len > len) {
+            last = node.previous.clone();
+        }
+        Self {
+            chatbot: self.chatbot.clone(),
+            memories: self.memories.clone(),
+            last,
+        }
+    }
+
+    /// Replace the last answer of the conversation with a new answer of the chatbot to the
+    /// last prompt, and return it. Other branches sharing the answer are unchanged. Returns
+    /// `Ok(None)` if the conversation doesn't end with a prompt and its answer.
+    pub async fn regenerate(&mut self) -> Result<Option<String>, PromptError> {
+        let Some((prompt, len)) = self.last_turn() else {
+            return Ok(None);
+        };
+
+        let mut branch = self.fork_at(len);
+        let answer = branch.chat(prompt).await?;
+        *self = branch;
+        Ok(Some(answer))
+    }
+
+    /// Last prompt of the conversation, if it ends with a prompt and its answer, and the
+    /// number of messages before it
+    fn last_turn(&self) -> Option<(Message, usize)> {
+        let answer = self.last.as_ref()?;
+        let prompt = answer.previous.as_ref()?;
+        match (&prompt.message, &answer.message) {
+            (Message::User { .. }, Message::Assistant { .. }) => {
+                Some((prompt.message.clone(), prompt.len - 1))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Drop for Node {
+    // Drop the unshared messages iteratively (instead of recursively), so that dropping a long
+    // conversation doesn't overflow the stack
+    fn drop(&mut self) {
+        let mut previous = self.previous.take();
+        while let Some(node) = previous {
+            match Arc::try_unwrap(node) {
+                Ok(mut node) => previous = node.previous.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::{self, MockResponse};
+
+    #[tokio::test]
+    async fn test_fork() {
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::text("Tom"))
+            .push(MockResponse::text("Al"))
+            .push(MockResponse::text("Bo"))
+            .push(MockResponse::text("Maximilian"));
+        let mut session = ChatSession::new(model.agent().build());
+
+        session.chat("A name for my cat?").await.unwrap();
+        session.chat("Shorter?").await.unwrap();
+        assert_eq!(session.len(), 4);
+
+        let mut branch = session.fork();
+        assert_eq!(branch.regenerate().await.unwrap().unwrap(), "Bo");
+        assert_eq!(branch.history()[3], Message::assistant("Bo"));
+        assert_eq!(session.history()[3], Message::assistant("Al"));
+
+        let mut branch = session.fork_at(2);
+        branch.chat("Longer?").await.unwrap();
+        assert_eq!(branch.len(), 4);
+        assert_eq!(branch.history()[2], Message::user("Longer?"));
+        assert_eq!(session.history()[2], Message::user("Shorter?"));
+
+        // The regenerated answer was prompted with the history before the last prompt
+        assert_eq!(model.requests()[2].chat_history.len(), 2);
+    }
+}
@@ -0,0 +1,893 @@
+//! Built-in tools of the code-editing agents, confined to a set of root directories:
+//! - `read_file`: read a file, or a range of its lines (numbered, for the model to reference),
+//! - `search_files`: search a regex in the files under the roots (or a directory),
+//! - `apply_patch`: apply a unified diff to the files under the roots (e.g.: as written by the
+//!   model in a ```` ```diff ```` code block).
+//!
+//! The roots are the MCP roots of the client (see [Roots::from_mcp] and [Roots::to_mcp]): the
+//! paths outside of them (e.g.: `../../etc/passwd`, or a symbolic link out of a root) are
+//! rejected. The `apply_patch` tool is annotated as destructive, so that it goes through the
+//! [ApprovalPolicy](crate::tool::ApprovalPolicy) of the agent (e.g.: ask the user before
+//! writing), while the read-only tools don't.
+//!
+//! A patch is applied to all of its files or to none: its hunks are matched against the files
+//! (tolerating wrong line numbers and counts, which the models often get wrong) before any file
+//! is written.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     code_tools::{CodeTools, Roots},
+//!     providers::openai,
+//!     tool::{Approval, ApprovalPolicy},
+//! };
+//!
+//! let tools = CodeTools::new(Roots::new().root("./my-project")?);
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("You are a coding assistant. Edit the files with unified diffs.")
+//!     .tool(tools.read_tool())
+//!     .tool(tools.search_tool())
+//!     .tool(tools.apply_patch_tool())
+//!     .tool_approval(
+//!         ApprovalPolicy::new()
+//!             .destructive(Approval::Ask)
+//!             .approver(|request| async move { confirm(&request.args).await }),
+//!     )
+//!     .build();
+//! ```
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::tool::{self, Tool, ToolAnnotations};
+
+/// Maximum number of matches returned by a search.
+const MAX_MATCHES: usize = 100;
+
+/// Maximum size of the files searched, in bytes.
+const MAX_SEARCHED_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CodeToolError {
+    /// The path is not under the roots of the tools
+    #[error("Path outside of the roots: {0}")]
+    OutsideRoots(String),
+
+    /// The tools have no roots
+    #[error("No roots configured")]
+    NoRoots,
+
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Invalid regex: {0}")]
+    RegexError(#[from] regex::Error),
+
+    #[error("Invalid glob pattern: {0}")]
+    PatternError(#[from] glob::PatternError),
+
+    /// The patch is not a valid unified diff
+    #[error("Invalid patch: {0}")]
+    InvalidPatch(String),
+
+    /// A hunk of the patch doesn't match the content of its file
+    #[error("Hunk {hunk} of the patch doesn't match the content of {path}")]
+    PatchConflict { path: String, hunk: usize },
+}
+
+/// Root directories the code tools are confined to (e.g.: the MCP roots of the client).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Roots {
+    /// Canonical paths of the roots, with their names (if any)
+    roots: Vec<(PathBuf, Option<String>)>,
+}
+
+impl Roots {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the directory `path` to the roots
+    pub fn root(mut self, path: impl AsRef<Path>) -> Result<Self, CodeToolError> {
+        self.roots.push((path.as_ref().canonicalize()?, None));
+        Ok(self)
+    }
+
+    /// Add the directory `path` to the roots, named `name` (e.g.: in the MCP roots)
+    pub fn named_root(mut self, name: &str, path: impl AsRef<Path>) -> Result<Self, CodeToolError> {
+        self.roots
+            .push((path.as_ref().canonicalize()?, Some(name.to_string())));
+        Ok(self)
+    }
+
+    /// Roots of the result of an MCP `roots/list` request (e.g.:
+    /// `{"roots": [{"uri": "file:///home/user/project", "name": "project"}]}`). The roots which
+    /// are not `file://` URIs of existing directories are ignored.
+    pub fn from_mcp(result: &serde_json::Value) -> Self {
+        let roots = result
+            .get("roots")
+            .and_then(|roots| roots.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|root| {
+                let path = percent_decode(root.get("uri")?.as_str()?.strip_prefix("file://")?);
+                let name = root
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .map(str::to_string);
+                Some((Path::new(&path).canonicalize().ok()?, name))
+            })
+            .collect();
+        Self { roots }
+    }
+
+    /// Result of an MCP `roots/list` request listing the roots (i.e.: to expose them to the MCP
+    /// servers)
+    pub fn to_mcp(&self) -> serde_json::Value {
+        let roots = self
+            .roots
+            .iter()
+            .map(|(path, name)| {
+                let mut root = json!({ "uri": format!("file://{}", path.display()) });
+                if let Some(name) = name {
+                    root["name"] = json!(name);
+                }
+                root
+            })
+            .collect::<Vec<_>>();
+        json!({ "roots": roots })
+    }
+
+    /// Paths of the roots
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.roots.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// Resolve `path` (absolute, or relative to the first root where it exists), checking that
+    /// it is under a root, even through symbolic links
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, CodeToolError> {
+        let path = Path::new(path);
+        let absolute = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            let mut candidates = self.paths().map(|root| root.join(path));
+            let first = candidates.next().ok_or(CodeToolError::NoRoots)?;
+            std::iter::once(first.clone())
+                .chain(candidates)
+                .find(|candidate| candidate.exists())
+                .unwrap_or(first)
+        };
+
+        let resolved = canonicalize_existing(&normalize(&absolute));
+        if self.paths().any(|root| resolved.starts_with(root)) {
+            Ok(resolved)
+        } else {
+            Err(CodeToolError::OutsideRoots(path.display().to_string()))
+        }
+    }
+
+    /// `path` relative to its root, as shown to the model
+    fn display(&self, path: &Path) -> String {
+        self.paths()
+            .find_map(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+}
+
+/// `path` with its `.` and `..` components resolved (lexically)
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// `path` with its longest existing ancestor canonicalized (i.e.: its symbolic links resolved)
+fn canonicalize_existing(path: &Path) -> PathBuf {
+    let mut missing = vec![];
+    let mut existing = path;
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return missing
+                .into_iter()
+                .rev()
+                .fold(canonical, |path, component| path.join(component));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+/// `text` with its percent-encoded bytes (e.g.: `%20`) decoded
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Arguments of the `read_file` tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReadFileArgs {
+    /// Path of the file, relative to the project root
+    pub path: String,
+    /// First line to read (1-based, from the first line if unset)
+    pub start_line: Option<usize>,
+    /// Last line to read (included, up to the last line if unset)
+    pub end_line: Option<usize>,
+}
+
+/// Arguments of the `search_files` tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SearchFilesArgs {
+    /// Regular expression to search
+    pub pattern: String,
+    /// Directory to search in, relative to the project root (the whole project if unset)
+    pub path: Option<String>,
+    /// Glob pattern of the paths of the files to search (e.g.: `src/**/*.rs`)
+    pub glob: Option<String>,
+}
+
+/// Arguments of the `apply_patch` tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ApplyPatchArgs {
+    /// Unified diff of the changes (with `---`/`+++` file headers and `@@` hunks), with paths
+    /// relative to the project root
+    pub patch: String,
+}
+
+/// Code-editing tools confined to [Roots] (see the [module](self) docs).
+#[derive(Clone, Debug)]
+pub struct CodeTools {
+    roots: Roots,
+}
+
+impl CodeTools {
+    pub fn new(roots: Roots) -> Self {
+        Self { roots }
+    }
+
+    /// Lines `start_line` to `end_line` (1-based, included) of the file `path`, prefixed with
+    /// their number (e.g.: `12 | fn main() {`)
+    pub fn read(
+        &self,
+        path: &str,
+        start_line: Option<usize>,
+        end_line: Option<usize>,
+    ) -> Result<String, CodeToolError> {
+        let content = std::fs::read_to_string(self.roots.resolve(path)?)?;
+        let lines = content.lines().collect::<Vec<_>>();
+        let start = start_line.unwrap_or(1).max(1);
+        let end = end_line.unwrap_or(lines.len()).min(lines.len());
+        let width = end.to_string().len();
+        Ok((start..=end)
+            .map(|number| format!("{number:>width$} | {}", lines[number - 1]))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    /// Lines matching the regex `pattern` in the files under the directory `path` (all the
+    /// roots if unset) whose path matches `glob` (if any), as `path:line: text`. The hidden files
+    /// and directories (e.g.: `.git`) and the files which are not UTF-8 are skipped.
+    pub fn search(
+        &self,
+        pattern: &str,
+        path: Option<&str>,
+        glob: Option<&str>,
+    ) -> Result<String, CodeToolError> {
+        let regex = regex::Regex::new(pattern)?;
+        let glob = glob.map(glob::Pattern::new).transpose()?;
+        let directories = match path {
+            Some(path) => vec![self.roots.resolve(path)?],
+            None => self.roots.paths().map(Path::to_path_buf).collect(),
+        };
+
+        let mut matches = vec![];
+        let mut pending = directories;
+        while let Some(path) = pending.pop() {
+            if matches.len() >= MAX_MATCHES {
+                break;
+            }
+            // The symbolic links are not followed, as they could lead out of the roots
+            let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+                continue;
+            };
+            if metadata.is_symlink() {
+                continue;
+            }
+            if metadata.is_dir() {
+                let mut entries = std::fs::read_dir(&path)?
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| {
+                        !path
+                            .file_name()
+                            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+                    })
+                    .collect::<Vec<_>>();
+                // Searched in order, as popped from the end
+                entries.sort_by(|a, b| b.cmp(a));
+                pending.extend(entries);
+                continue;
+            }
+
+            let display = self.roots.display(&path);
+            if metadata.len() > MAX_SEARCHED_FILE_SIZE
+                || glob.as_ref().is_some_and(|glob| !glob.matches(&display))
+            {
+                continue;
+            }
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            matches.extend(
+                content
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, line)| regex.is_match(line))
+                    .map(|(index, line)| format!("{display}:{}: {}", index + 1, line.trim())),
+            );
+        }
+
+        if matches.is_empty() {
+            return Ok("No matches".to_string());
+        }
+        let truncated = matches.len() > MAX_MATCHES;
+        matches.truncate(MAX_MATCHES);
+        if truncated {
+            matches.push(format!("(only the first {MAX_MATCHES} matches are listed)"));
+        }
+        Ok(matches.join("\n"))
+    }
+
+    /// Apply the unified diff `patch` (possibly in a code block) to the files under the roots,
+    /// returning a summary of the changes (e.g.: `src/main.rs: +3 -1`). Nothing is written
+    /// unless all the hunks of the patch apply.
+    pub fn apply_patch(&self, patch: &str) -> Result<String, CodeToolError> {
+        let file_patches = parse_patch(&unfence(patch))?;
+        if file_patches.is_empty() {
+            return Err(CodeToolError::InvalidPatch(
+                "No file headers (`---`/`+++`) in the patch".to_string(),
+            ));
+        }
+
+        // New contents of the files (None when deleted), computed before writing any of them
+        let mut contents: HashMap<PathBuf, Option<String>> = HashMap::new();
+        let mut summary = vec![];
+        for file_patch in &file_patches {
+            let path = self.roots.resolve(&file_patch.path)?;
+            let original = match contents.get(&path) {
+                Some(content) => content.clone().unwrap_or_default(),
+                None if file_patch.is_creation && path.exists() => {
+                    return Err(CodeToolError::InvalidPatch(format!(
+                        "{} already exists",
+                        file_patch.path
+                    )));
+                }
+                None if file_patch.is_creation => String::new(),
+                None => std::fs::read_to_string(&path)?,
+            };
+            let patched = file_patch.apply(&original)?;
+            summary.push(format!(
+                "{}: {}",
+                self.roots.display(&path),
+                file_patch.summary()
+            ));
+            contents.insert(path, (!file_patch.is_deletion).then_some(patched));
+        }
+
+        for (path, content) in contents {
+            match content {
+                Some(content) => {
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::fs::write(&path, content)?;
+                }
+                None => std::fs::remove_file(&path)?,
+            }
+        }
+        tracing::info!(target: "rig", "Applied patch: {}", summary.join(", "));
+        Ok(summary.join("\n"))
+    }
+
+    /// Tool `read_file` reading a file or a range of its lines, for agents
+    pub fn read_tool(&self) -> impl Tool + 'static {
+        let tools = self.clone();
+        tool::from_typed_fn(
+            "read_file",
+            "Read a file of the project, or a range of its lines. The lines are prefixed with \
+             their number.",
+            move |args: ReadFileArgs| {
+                let result = tools.read(&args.path, args.start_line, args.end_line);
+                async move { result }
+            },
+        )
+        .with_annotations(read_only())
+    }
+
+    /// Tool `search_files` searching a regex in the files, for agents
+    pub fn search_tool(&self) -> impl Tool + 'static {
+        let tools = self.clone();
+        tool::from_typed_fn(
+            "search_files",
+            "Search a regular expression in the files of the project. Returns the matching \
+             lines as `path:line: text`.",
+            move |args: SearchFilesArgs| {
+                let result =
+                    tools.search(&args.pattern, args.path.as_deref(), args.glob.as_deref());
+                async move { result }
+            },
+        )
+        .with_annotations(read_only())
+    }
+
+    /// Tool `apply_patch` applying a unified diff to the files, for agents. The tool is
+    /// annotated as destructive (see the [module](self) docs).
+    pub fn apply_patch_tool(&self) -> impl Tool + 'static {
+        let tools = self.clone();
+        tool::from_typed_fn(
+            "apply_patch",
+            "Edit the files of the project with a unified diff (`--- a/path`, `+++ b/path` and \
+             `@@ -l,n +l,n @@` hunks with 3 lines of context). Use `/dev/null` as the old path \
+             to create a file, and as the new path to delete it.",
+            move |args: ApplyPatchArgs| {
+                let result = tools.apply_patch(&args.patch);
+                async move { result }
+            },
+        )
+        .with_annotations(ToolAnnotations {
+            read_only: Some(false),
+            destructive: Some(true),
+            idempotent: Some(false),
+            open_world: Some(false),
+            ..Default::default()
+        })
+    }
+}
+
+/// Annotations of the read-only tools
+fn read_only() -> ToolAnnotations {
+    ToolAnnotations {
+        read_only: Some(true),
+        open_world: Some(false),
+        ..Default::default()
+    }
+}
+
+/// Content of the first code block of `text` (e.g.: ```` ```diff ````), or `text` if it has
+/// none
+fn unfence(text: &str) -> String {
+    let mut lines = text.lines().skip_while(|line| !line.starts_with("```"));
+    if lines.next().is_none() {
+        return text.to_string();
+    }
+    let mut content = lines
+        .take_while(|line| !line.starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    content.push('\n');
+    content
+}
+
+/// Line of a hunk
+#[derive(Debug, PartialEq)]
+enum HunkLine {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+impl HunkLine {
+    /// Line of the body of a hunk, or None for the other lines (e.g.: `\ No newline at end of
+    /// file`)
+    fn parse(line: &str) -> Option<Self> {
+        match line.chars().next() {
+            Some('+') => Some(Self::Added(line[1..].to_string())),
+            Some('-') => Some(Self::Removed(line[1..].to_string())),
+            Some(' ') => Some(Self::Context(line[1..].to_string())),
+            // The models often drop the space of the empty context lines
+            None => Some(Self::Context(String::new())),
+            _ => None,
+        }
+    }
+
+    /// Numbers of lines of the line in the original and patched files
+    fn counts(&self) -> (usize, usize) {
+        match self {
+            Self::Context(_) => (1, 1),
+            Self::Removed(_) => (1, 0),
+            Self::Added(_) => (0, 1),
+        }
+    }
+}
+
+/// Hunk of a unified diff
+#[derive(Debug, Default)]
+struct Hunk {
+    /// First line of the hunk in the original file (1-based, 0 for an empty file)
+    old_start: usize,
+    lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    /// Lines of the hunk in the original file
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(text) | HunkLine::Removed(text) => Some(text.as_str()),
+                HunkLine::Added(_) => None,
+            })
+            .collect()
+    }
+
+    /// Lines of the hunk in the patched file
+    fn new_lines(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(text) | HunkLine::Added(text) => Some(text.clone()),
+                HunkLine::Removed(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Changes of a file in a unified diff
+#[derive(Debug)]
+struct FilePatch {
+    path: String,
+    is_creation: bool,
+    is_deletion: bool,
+    hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// `original` with the hunks applied. A hunk is applied where its original lines are,
+    /// starting from its line number then moving away from it (the line numbers written by
+    /// the models are often wrong).
+    fn apply(&self, original: &str) -> Result<String, CodeToolError> {
+        let mut lines = original.lines().map(str::to_string).collect::<Vec<_>>();
+        // Offset of the line numbers of the next hunks, from the hunks applied
+        let mut offset = 0isize;
+        // Lines of the patched file before which no hunk can apply anymore
+        let mut floor = 0;
+        for (index, hunk) in self.hunks.iter().enumerate() {
+            let old = hunk.old_lines();
+            // The hunks without original lines are inserted after their line (e.g.: after the
+            // 5th line for `@@ -5,0 +6,2 @@`)
+            let start = if old.is_empty() {
+                hunk.old_start
+            } else {
+                hunk.old_start.saturating_sub(1)
+            };
+            let expected = (start as isize + offset).max(0) as usize;
+            let position = (floor..=lines.len())
+                .filter(|position| {
+                    lines
+                        .get(*position..*position + old.len())
+                        .is_some_and(|window| {
+                            window
+                                .iter()
+                                .map(|line| line.trim_end())
+                                .eq(old.iter().map(|line| line.trim_end()))
+                        })
+                })
+                .min_by_key(|position| position.abs_diff(expected))
+                .ok_or_else(|| CodeToolError::PatchConflict {
+                    path: self.path.clone(),
+                    hunk: index + 1,
+                })?;
+
+            let new = hunk.new_lines();
+            offset += new.len() as isize - old.len() as isize;
+            floor = position + new.len();
+            lines.splice(position..position + old.len(), new);
+        }
+
+        let mut patched = lines.join("\n");
+        if !patched.is_empty() && (original.is_empty() || original.ends_with('\n')) {
+            patched.push('\n');
+        }
+        Ok(patched)
+    }
+
+    /// Number of the added and removed lines (e.g.: `+3 -1`)
+    fn summary(&self) -> String {
+        let count = |predicate: fn(&HunkLine) -> bool| {
+            self.hunks
+                .iter()
+                .flat_map(|hunk| &hunk.lines)
+                .filter(|line| predicate(line))
+                .count()
+        };
+        let added = count(|line| matches!(line, HunkLine::Added(_)));
+        let removed = count(|line| matches!(line, HunkLine::Removed(_)));
+        match (self.is_creation, self.is_deletion) {
+            (true, _) => format!("created (+{added})"),
+            (_, true) => "deleted".to_string(),
+            _ => format!("+{added} -{removed}"),
+        }
+    }
+}
+
+/// Path of the `---`/`+++` header `header` (without its `a/` or `b/` prefix and its
+/// timestamp), or None for `/dev/null`
+fn header_path(header: &str) -> Option<String> {
+    let path = header.split('\t').next().unwrap_or_default().trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+/// Numbers of lines of the hunk in the original and new files, from the `range` of its header
+/// (e.g.: `-12,3 +12,4 @@`), if written
+fn hunk_counts(range: &str) -> Option<(usize, usize)> {
+    // The count is omitted for a single line (e.g.: `-12 +12 @@`)
+    let count = |range: &str| match range.split_once(',') {
+        Some((_, count)) => count.parse().ok(),
+        None => range.parse::<usize>().ok().map(|_| 1),
+    };
+    let mut ranges = range.split_whitespace();
+    let old = count(ranges.next()?.strip_prefix('-')?)?;
+    let new = count(ranges.next()?.strip_prefix('+')?)?;
+    Some((old, new))
+}
+
+/// Whether the numbers of lines `counts` of a hunk match its body `lines`, i.e.: the hunk ends
+/// right after them (at the end of the patch, at the next hunk or file, or at a line out of the
+/// diff such as the end of its code block)
+fn counts_match<'a>(lines: impl Iterator<Item = &'a str>, counts: (usize, usize)) -> bool {
+    let mut lines = lines.filter(|line| !line.starts_with('\\'));
+    let (mut old, mut new) = counts;
+    while old > 0 || new > 0 {
+        let Some((line_old, line_new)) = lines
+            .next()
+            .and_then(HunkLine::parse)
+            .map(|line| line.counts())
+        else {
+            return false;
+        };
+        let (Some(left_old), Some(left_new)) =
+            (old.checked_sub(line_old), new.checked_sub(line_new))
+        else {
+            return false;
+        };
+        (old, new) = (left_old, left_new);
+    }
+    match (lines.next(), lines.next()) {
+        (Some(line), next) if line.starts_with("--- ") => {
+            next.is_some_and(|next| next.starts_with("+++ "))
+        }
+        (Some(line), _) => !matches!(line.chars().next(), Some('+' | '-' | ' ')),
+        (None, _) => true,
+    }
+}
+
+/// Changes of the files of the unified diff `patch`
+fn parse_patch(patch: &str) -> Result<Vec<FilePatch>, CodeToolError> {
+    let invalid = |message: &str| CodeToolError::InvalidPatch(message.to_string());
+    let mut file_patches: Vec<FilePatch> = vec![];
+    let mut lines = patch.lines().peekable();
+    while let Some(line) = lines.next() {
+        if let Some(old) = line.strip_prefix("--- ") {
+            let new = lines
+                .next()
+                .and_then(|line| line.strip_prefix("+++ "))
+                .ok_or_else(|| invalid("`---` header without `+++` header"))?;
+            let (old, new) = (header_path(old), header_path(new));
+            file_patches.push(FilePatch {
+                path: new
+                    .clone()
+                    .or(old.clone())
+                    .ok_or_else(|| invalid("`/dev/null` as both paths"))?,
+                is_creation: old.is_none(),
+                is_deletion: new.is_none(),
+                hunks: vec![],
+            });
+        } else if let Some(range) = line.strip_prefix("@@ ") {
+            let file_patch = file_patches
+                .last_mut()
+                .ok_or_else(|| invalid("Hunk without file headers"))?;
+            let old_start = range
+                .trim_start_matches('-')
+                .split([',', ' '])
+                .next()
+                .and_then(|start| start.parse().ok())
+                .unwrap_or_default();
+            let mut hunk = Hunk {
+                old_start,
+                lines: vec![],
+            };
+            // Lines of the hunk left to read in the original and new files, according to its
+            // header (e.g.: `@@ -12,3 +12,4 @@`), if they match its body (the models often get
+            // them wrong)
+            let mut remaining =
+                hunk_counts(range).filter(|counts| counts_match(lines.clone(), *counts));
+            while let Some(&line) = lines.peek() {
+                // Until the hunk is complete, the lines starting with `--- ` are removed lines
+                // (e.g.: a removed `-- comment`) rather than the headers of the next file
+                let complete = matches!(remaining, None | Some((0, 0)));
+                let file_headers = line.starts_with("--- ")
+                    && lines
+                        .clone()
+                        .nth(1)
+                        .is_some_and(|next| next.starts_with("+++ "));
+                if line.starts_with("@@ ") || (complete && file_headers) {
+                    break;
+                }
+                lines.next();
+                let Some(hunk_line) = HunkLine::parse(line) else {
+                    continue;
+                };
+                if let Some((old, new)) = remaining.as_mut() {
+                    let (line_old, line_new) = hunk_line.counts();
+                    *old = old.saturating_sub(line_old);
+                    *new = new.saturating_sub(line_new);
+                }
+                hunk.lines.push(hunk_line);
+            }
+            // Trailing empty lines are not context
+            while hunk.lines.last() == Some(&HunkLine::Context(String::new())) {
+                hunk.lines.pop();
+            }
+            file_patch.hunks.push(hunk);
+        }
+    }
+    Ok(file_patches)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        completion::Prompt,
+        providers::mock::{self, MockResponse},
+        tool::{Approval, ApprovalPolicy},
+    };
+
+    #[tokio::test]
+    async fn test_code_tools() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("src/main.rs"),
+            "fn main() {\n    let name = \"world\";\n\n    println!(\"Hello, {name}!\");\n}\n",
+        )
+        .unwrap();
+        let roots = Roots::from_mcp(&json!({
+            "roots": [{"uri": format!("file://{}", dir.path().display()), "name": "project"}]
+        }));
+        let tools = CodeTools::new(roots);
+
+        assert_eq!(
+            tools.read("src/main.rs", Some(2), Some(3)).unwrap(),
+            "2 |     let name = \"world\";\n3 | "
+        );
+        assert_eq!(
+            tools.search("println", None, Some("src/*.rs")).unwrap(),
+            "src/main.rs:4: println!(\"Hello, {name}!\");"
+        );
+        assert!(matches!(
+            tools.read("../outside.txt", None, None),
+            Err(CodeToolError::OutsideRoots(_))
+        ));
+
+        // Wrong line numbers, a code fence and a dropped space of an empty context line
+        let patch = "Here is the change:\n```diff\n--- a/src/main.rs\n+++ b/src/main.rs\n\
+                     @@ -10,3 +10,3 @@\n-    let name = \"world\";\n+    let name = \"Rust\";\n\n\
+                     \x20    println!(\"Hello, {name}!\");\n```";
+        assert_eq!(tools.apply_patch(patch).unwrap(), "src/main.rs: +1 -1");
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("src/main.rs")).unwrap(),
+            "fn main() {\n    let name = \"Rust\";\n\n    println!(\"Hello, {name}!\");\n}\n"
+        );
+        // Nothing is written when a hunk doesn't apply
+        let patch = "--- /dev/null\n+++ b/README.md\n@@ -0,0 +1 @@\n+# Hello\n\
+                     --- a/src/main.rs\n+++ b/src/main.rs\n@@ -1 +1 @@\n-fn start() {\n+fn run() {\n";
+        assert!(matches!(
+            tools.apply_patch(patch),
+            Err(CodeToolError::PatchConflict { hunk: 1, .. })
+        ));
+        assert!(!dir.path().join("README.md").exists());
+
+        // The removed lines starting with `--` (e.g.: SQL comments) are not file headers
+        std::fs::write(
+            dir.path().join("schema.sql"),
+            "-- Users\nCREATE TABLE users (id INTEGER);\n",
+        )
+        .unwrap();
+        let patch = "--- a/schema.sql\n+++ b/schema.sql\n@@ -1,2 +1,2 @@\n--- Users\n\
+                     +-- Accounts\n CREATE TABLE users (id INTEGER);\n";
+        assert_eq!(tools.apply_patch(patch).unwrap(), "schema.sql: +1 -1");
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("schema.sql")).unwrap(),
+            "-- Accounts\nCREATE TABLE users (id INTEGER);\n"
+        );
+        // Wrong line counts don't hide the headers of the next file
+        let patch = "--- a/schema.sql\n+++ b/schema.sql\n@@ -1,9 +1,9 @@\n--- Accounts\n\
+                     +-- Customers\n CREATE TABLE users (id INTEGER);\n--- a/src/main.rs\n\
+                     +++ b/src/main.rs\n@@ -2 +2 @@\n-    let name = \"Rust\";\n\
+                     +    let name = \"world\";\n";
+        assert_eq!(
+            tools.apply_patch(patch).unwrap(),
+            "schema.sql: +1 -1\nsrc/main.rs: +1 -1"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("schema.sql")).unwrap(),
+            "-- Customers\nCREATE TABLE users (id INTEGER);\n"
+        );
+        // The hunks without original lines are inserted after their line
+        let patch = "--- a/schema.sql\n+++ b/schema.sql\n@@ -2,0 +3 @@\n\
+                     +CREATE TABLE orders (id INTEGER);\n";
+        assert_eq!(tools.apply_patch(patch).unwrap(), "schema.sql: +1 -0");
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("schema.sql")).unwrap(),
+            "-- Customers\nCREATE TABLE users (id INTEGER);\nCREATE TABLE orders (id INTEGER);\n"
+        );
+
+        // The patches are submitted to the approval policy of the agent
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::tool_call(
+                "apply_patch",
+                json!({"patch": "--- /dev/null\n+++ b/README.md\n@@ -0,0 +1 @@\n+# Hello\n"}),
+            ))
+            .default_response(MockResponse::text("Done"));
+        let agent = model
+            .agent()
+            .tool(tools.read_tool())
+            .tool(tools.apply_patch_tool())
+            .tool_approval(
+                ApprovalPolicy::new()
+                    .read_only(Approval::Allow)
+                    .destructive(Approval::Deny),
+            )
+            .build();
+        assert!(agent.prompt("Add a README").await.is_err());
+        assert!(!dir.path().join("README.md").exists());
+    }
+}
@@ -0,0 +1,378 @@
+//! Generation of typed Rust bindings of the tools of an MCP server (as listed by `tools/list`),
+//! so that the integrations calling many tools get compile-time checked arguments rather than
+//! `json!` blobs.
+//!
+//! For each tool, [Codegen] generates:
+//! - a struct of its arguments (e.g.: `PostArgs` for the tool `Post`), with a field per property
+//!   of its input schema (the properties not required being `Option`s), and a struct per nested
+//!   object,
+//! - a method calling it on the generated client (e.g.: `post`), which wraps any
+//!   [McpToolCaller](crate::mcp_servers::McpToolCaller) (e.g.: the MCP client of the server).
+//!
+//! The JSON schema types are mapped to `String`, `i64`, `f64`, `bool`, `Vec<T>` and structs,
+//! and the other schemas (e.g.: `anyOf`, `$ref`) to `serde_json::Value`. The generated code
+//! depends on `mcp_rig`, `serde` and `serde_json`.
+//!
+//! The bindings are generated at runtime (e.g.: from a build script, or with the `--codegen`
+//! option of the `mcp-rig` CLI), from the tools listed by the server.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::codegen::Codegen;
+//!
+//! // In build.rs
+//! let tools = client.list_tools(None, None).await?.tools;
+//! let bindings = Codegen::new("TwitterTools").mcp_tools(tools).generate()?;
+//! std::fs::write(format!("{}/twitter.rs", std::env::var("OUT_DIR")?), bindings)?;
+//!
+//! // In the crate
+//! include!(concat!(env!("OUT_DIR"), "/twitter.rs"));
+//!
+//! let twitter = TwitterTools::new(client);
+//! twitter.post(&PostArgs { tweet: "Hello!".to_string(), ..Default::default() }).await?;
+//! ```
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::completion::ToolDefinition;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CodegenError {
+    /// Two tools or structs map to the same Rust name (e.g.: the tools `get-user` and `get_user`)
+    #[error("Duplicate generated name: {0}")]
+    DuplicateName(String),
+}
+
+/// Generator of the typed bindings of the tools of an MCP server (see the [module](self) docs).
+#[derive(Clone, Debug)]
+pub struct Codegen {
+    /// Name of the generated client
+    client: String,
+    tools: Vec<ToolDefinition>,
+}
+
+impl Codegen {
+    /// Generate the client `client` (e.g.: `TwitterTools`)
+    pub fn new(client: &str) -> Self {
+        Self {
+            client: client.to_string(),
+            tools: vec![],
+        }
+    }
+
+    /// Generate the bindings of the tool `definition`
+    pub fn tool_definition(mut self, definition: ToolDefinition) -> Self {
+        self.tools.push(definition);
+        self
+    }
+
+    /// Generate the bindings of the tools of an MCP server
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn mcp_tools(mut self, tools: impl IntoIterator<Item = mcp_core::types::Tool>) -> Self {
+        self.tools
+            .extend(tools.into_iter().map(|tool| ToolDefinition {
+                name: tool.name,
+                description: tool.description.unwrap_or_default(),
+                parameters: serde_json::to_value(&tool.input_schema).unwrap_or_default(),
+            }));
+        self
+    }
+
+    /// Rust source of the bindings
+    pub fn generate(&self) -> Result<String, CodegenError> {
+        let mut structs = Structs::default();
+        let mut methods = vec![];
+        // The constructor of the client
+        let mut method_names = HashSet::from(["new".to_string()]);
+        for tool in &self.tools {
+            let method = identifier(&tool.name);
+            if !method_names.insert(method.clone()) {
+                return Err(CodegenError::DuplicateName(method));
+            }
+            let args = structs.object(
+                &format!("{}Args", pascal_case(&tool.name)),
+                &format!("Arguments of the tool `{}`.", tool.name),
+                &tool.parameters,
+            )?;
+            methods.push(format!(
+                "{doc}    pub async fn {method}(&self, args: &{args}) -> Result<String, ToolError> {{\n        \
+                 let arguments = serde_json::to_value(args)?;\n        \
+                 self.client.call_tool({name:?}, arguments).await\n    }}\n",
+                doc = doc_comment(&tool.description, "    "),
+                name = tool.name,
+            ));
+        }
+
+        let mut source = String::from(
+            "// Generated by `mcp_rig::codegen` from the tools of an MCP server, do not edit.\n\
+             use mcp_rig::{mcp_servers::McpToolCaller, tool::ToolError};\n\
+             use serde::{Deserialize, Serialize};\n",
+        );
+        for definition in &structs.definitions {
+            source.push('\n');
+            source.push_str(definition);
+        }
+        source.push_str(&format!(
+            "\n/// Typed client of the tools of the MCP server.\n\
+             pub struct {client}<C> {{\n    client: std::sync::Arc<C>,\n}}\n\n\
+             impl<C: McpToolCaller> {client}<C> {{\n    \
+             pub fn new(client: std::sync::Arc<C>) -> Self {{\n        Self {{ client }}\n    }}\n",
+            client = self.client,
+        ));
+        for method in methods {
+            source.push('\n');
+            source.push_str(&method);
+        }
+        source.push_str("}\n");
+        Ok(source)
+    }
+}
+
+/// Structs generated for the objects of the input schemas
+#[derive(Default)]
+struct Structs {
+    names: HashSet<String>,
+    definitions: Vec<String>,
+}
+
+impl Structs {
+    /// Generate the struct `name` of the object `schema`, and return its name
+    fn object(&mut self, name: &str, doc: &str, schema: &Value) -> Result<String, CodegenError> {
+        if !self.names.insert(name.to_string()) {
+            return Err(CodegenError::DuplicateName(name.to_string()));
+        }
+        // Reserve the position of the struct before its nested structs
+        let index = self.definitions.len();
+        self.definitions.push(String::new());
+
+        let required = schema["required"]
+            .as_array()
+            .map(|required| required.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_else(HashSet::new);
+        let mut fields = String::new();
+        let mut field_names = HashSet::new();
+        if let Some(properties) = schema["properties"].as_object() {
+            // Sorted for the output to be stable
+            let mut properties = properties.iter().collect::<Vec<_>>();
+            properties.sort_by_key(|(property, _)| *property);
+            for (property, property_schema) in properties {
+                let field = identifier(property);
+                if !field_names.insert(field.clone()) {
+                    return Err(CodegenError::DuplicateName(format!("{name}::{field}")));
+                }
+                let nested = format!("{name}{}", pascal_case(property));
+                let (rust_type, nullable) = self.rust_type(&nested, property_schema)?;
+
+                let description = property_schema["description"].as_str().unwrap_or_default();
+                fields.push_str(&doc_comment(description, "    "));
+                let mut attributes = vec![];
+                if field.trim_start_matches("r#") != property {
+                    attributes.push(format!("rename = {property:?}"));
+                }
+                let rust_type = if nullable || !required.contains(property.as_str()) {
+                    attributes.push("skip_serializing_if = \"Option::is_none\"".to_string());
+                    format!("Option<{rust_type}>")
+                } else {
+                    rust_type
+                };
+                if !attributes.is_empty() {
+                    fields.push_str(&format!("    #[serde({})]\n", attributes.join(", ")));
+                }
+                fields.push_str(&format!("    pub {field}: {rust_type},\n"));
+            }
+        }
+
+        self.definitions[index] = format!(
+            "{}#[derive(Clone, Debug, Default, Deserialize, Serialize)]\npub struct {name} {{\n{fields}}}\n",
+            doc_comment(doc, ""),
+        );
+        Ok(name.to_string())
+    }
+
+    /// Rust type of `schema` (generating the struct `name` if it is an object), and whether it
+    /// is nullable
+    fn rust_type(&mut self, name: &str, schema: &Value) -> Result<(String, bool), CodegenError> {
+        let (schema_type, nullable) = match &schema["type"] {
+            Value::String(schema_type) => (Some(schema_type.as_str()), false),
+            // e.g.: `["string", "null"]`
+            Value::Array(types) => {
+                let types = types.iter().filter_map(Value::as_str).collect::<Vec<_>>();
+                let non_null = types
+                    .iter()
+                    .filter(|schema_type| **schema_type != "null")
+                    .collect::<Vec<_>>();
+                match non_null[..] {
+                    [schema_type] => (Some(*schema_type), non_null.len() < types.len()),
+                    _ => (None, false),
+                }
+            }
+            _ => (None, false),
+        };
+
+        let rust_type = match schema_type {
+            Some("string") => "String".to_string(),
+            Some("integer") => "i64".to_string(),
+            Some("number") => "f64".to_string(),
+            Some("boolean") => "bool".to_string(),
+            Some("array") => {
+                let (item_type, _) = self.rust_type(&format!("{name}Item"), &schema["items"])?;
+                format!("Vec<{item_type}>")
+            }
+            Some("object") if schema["properties"].is_object() => {
+                let description = schema["description"].as_str().unwrap_or_default();
+                self.object(name, description, schema)?
+            }
+            _ => "serde_json::Value".to_string(),
+        };
+        Ok((rust_type, nullable))
+    }
+}
+
+/// Doc comment of `text`, indented by `indent` (empty if `text` is empty)
+fn doc_comment(text: &str, indent: &str) -> String {
+    text.trim()
+        .lines()
+        .map(|line| format!("{indent}/// {}\n", line.trim_end()).replace("/// \n", "///\n"))
+        .collect()
+}
+
+/// Lowercase ASCII words of `name` (e.g.: `["get", "user", "id"]` for `getUser-ID`)
+fn words(name: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut after_lowercase = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            after_lowercase = false;
+            continue;
+        }
+        if c.is_ascii_uppercase() && after_lowercase {
+            words.push(std::mem::take(&mut word));
+        }
+        after_lowercase = c.is_ascii_lowercase() || c.is_ascii_digit();
+        word.push(c.to_ascii_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// `name` in snake case, as a valid identifier (e.g.: `get_user` for `getUser`)
+fn snake_case(name: &str) -> String {
+    let name = words(name).join("_");
+    match name.chars().next() {
+        None => "unnamed".to_string(),
+        Some(c) if c.is_ascii_digit() => format!("_{name}"),
+        _ => name,
+    }
+}
+
+/// `name` in Pascal case, as a valid identifier (e.g.: `GetUser` for `get_user`)
+fn pascal_case(name: &str) -> String {
+    let name = words(name)
+        .into_iter()
+        .map(|word| word[..1].to_ascii_uppercase() + &word[1..])
+        .collect::<String>();
+    match name.chars().next() {
+        None => "Unnamed".to_string(),
+        Some(c) if c.is_ascii_digit() => format!("_{name}"),
+        _ => name,
+    }
+}
+
+/// `name` in snake case, as a field or method name (e.g.: `r#type` for `type`)
+fn identifier(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern",
+        "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use",
+        "where", "while", "abstract", "become", "box", "do", "final", "gen", "macro", "override",
+        "priv", "try", "typeof", "unsized", "virtual", "yield",
+    ];
+    let name = snake_case(name);
+    match name.as_str() {
+        // Not allowed as raw identifiers
+        "self" | "super" | "crate" => format!("{name}_"),
+        keyword if KEYWORDS.contains(&keyword) => format!("r#{name}"),
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_codegen() {
+        let send = ToolDefinition {
+            name: "send-message".to_string(),
+            description: "Send a message to a channel".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "channelId": {"type": "string", "description": "ID of the channel"},
+                    "type": {"type": ["string", "null"]},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "embed": {
+                        "type": "object",
+                        "properties": {"title": {"type": "string"}, "color": {"type": "integer"}},
+                        "required": ["title"]
+                    }
+                },
+                "required": ["channelId", "type"]
+            }),
+        };
+        let source = Codegen::new("DiscordTools")
+            .tool_definition(send.clone())
+            .generate()
+            .unwrap();
+
+        assert!(source.contains(
+            "/// Arguments of the tool `send-message`.\n\
+             #[derive(Clone, Debug, Default, Deserialize, Serialize)]\n\
+             pub struct SendMessageArgs {\n    \
+             /// ID of the channel\n    \
+             #[serde(rename = \"channelId\")]\n    \
+             pub channel_id: String,\n"
+        ));
+        assert!(source.contains(
+            "    #[serde(skip_serializing_if = \"Option::is_none\")]\n    \
+             pub embed: Option<SendMessageArgsEmbed>,\n"
+        ));
+        assert!(source.contains("pub tags: Option<Vec<String>>,\n"));
+        assert!(source.contains("pub r#type: Option<String>,\n"));
+        assert!(source.contains(
+            "pub struct SendMessageArgsEmbed {\n    \
+             #[serde(skip_serializing_if = \"Option::is_none\")]\n    \
+             pub color: Option<i64>,\n    \
+             pub title: String,\n}\n"
+        ));
+        assert!(source.contains(
+            "impl<C: McpToolCaller> DiscordTools<C> {\n    \
+             pub fn new(client: std::sync::Arc<C>) -> Self {\n        Self { client }\n    }\n\n    \
+             /// Send a message to a channel\n    \
+             pub async fn send_message(&self, args: &SendMessageArgs) -> Result<String, ToolError> {\n        \
+             let arguments = serde_json::to_value(args)?;\n        \
+             self.client.call_tool(\"send-message\", arguments).await\n    }\n}\n"
+        ));
+
+        let duplicate = ToolDefinition {
+            name: "send_message".to_string(),
+            ..send.clone()
+        };
+        assert!(matches!(
+            Codegen::new("DiscordTools")
+                .tool_definition(send)
+                .tool_definition(duplicate)
+                .generate(),
+            Err(CodegenError::DuplicateName(name)) if name == "send_message"
+        ));
+    }
+}
@@ -0,0 +1,255 @@
+//! Deterministic JSON canonicalization, so that semantically identical requests serialize (and
+//! hash) identically across runs, e.g.: as the key of a cache, to match a recorded request on
+//! replay (see [Cassette](crate::cassette::Cassette)) or to deduplicate requests.
+//!
+//! The canonical form of a JSON value (see [to_string]):
+//! - sorts the keys of the objects,
+//! - writes the integral floats as integers (i.e.: `1.0` as `1`, and `-0.0` as `0`),
+//! - has no whitespace.
+//!
+//! The canonical form of a [CompletionRequest] (see [CompletionRequest::canonical_json])
+//! additionally strips its volatile fields:
+//! - the [VOLATILE_PARAMS] of its additional parameters (e.g.: the end-user id),
+//! - the tool call ids, generated by the providers, which are renumbered in order of
+//!   appearance (i.e.: `call_0`, `call_1`...) with their results still matched to them,
+//! - the order of its tools.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::completion::canonical;
+//! use serde_json::json;
+//!
+//! assert_eq!(
+//!     canonical::to_string(&json!({"b": 1.0, "a": [true, null]})),
+//!     r#"{"a":[true,null],"b":1}"#
+//! );
+//!
+//! let key = request.canonical_hash();
+//! ```
+use std::collections::HashMap;
+
+use serde_json::{json, Number, Value};
+use sha2::{Digest, Sha256};
+
+use super::{AssistantContent, CompletionRequest, Message};
+use crate::message::UserContent;
+
+/// Additional parameters which don't change the completion of a request (e.g.: the id of the
+/// end-user, or metadata), stripped from its canonical form.
+pub const VOLATILE_PARAMS: &[&str] = &[
+    "user",
+    "metadata",
+    "stream",
+    "stream_options",
+    "request_id",
+    "idempotency_key",
+];
+
+/// Canonical serialization of `value` (see the [module](self) docs).
+pub fn to_string(value: &Value) -> String {
+    let mut canonical = String::new();
+    write(value, &mut canonical);
+    canonical
+}
+
+/// Hex-encoded SHA-256 hash of the canonical serialization of `value`.
+pub fn hash(value: &Value) -> String {
+    Sha256::digest(to_string(value).as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// `value` without its [VOLATILE_PARAMS] (if it is an object)
+pub fn strip_volatile(mut value: Value) -> Value {
+    if let Value::Object(object) = &mut value {
+        VOLATILE_PARAMS.iter().for_each(|param| {
+            object.remove(*param);
+        });
+    }
+    value
+}
+
+fn write(value: &Value, out: &mut String) {
+    match value {
+        Value::Number(number) => out.push_str(&normalize(number).to_string()),
+        Value::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(object) => {
+            let mut entries = object.iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            out.push('{');
+            for (index, (key, value)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write(value, out);
+            }
+            out.push('}');
+        }
+        // Null, booleans and strings have a single serialization
+        value => out.push_str(&value.to_string()),
+    }
+}
+
+/// `number` as an integer if it is an integral float (exactly representable as such)
+fn normalize(number: &Number) -> Number {
+    match number.as_f64() {
+        Some(float) if number.is_f64() && float.fract() == 0.0 && float.abs() < 2f64.powi(53) => {
+            Number::from(float as i64)
+        }
+        _ => number.clone(),
+    }
+}
+
+/// Renumber the tool call ids of `messages` in order of appearance
+fn renumber_tool_calls(messages: Vec<Message>) -> Vec<Message> {
+    let mut ids = HashMap::new();
+    let mut renumber = |id: String| {
+        let next = ids.len();
+        ids.entry(id)
+            .or_insert_with(|| format!("call_{next}"))
+            .clone()
+    };
+    messages
+        .into_iter()
+        .map(|message| match message {
+            Message::User { content } => Message::User {
+                content: content.map(|content| match content {
+                    UserContent::ToolResult(mut result) => {
+                        result.id = renumber(result.id);
+                        UserContent::ToolResult(result)
+                    }
+                    content => content,
+                }),
+            },
+            Message::Assistant { content } => Message::Assistant {
+                content: content.map(|content| match content {
+                    AssistantContent::ToolCall(mut call) => {
+                        call.id = renumber(call.id);
+                        AssistantContent::ToolCall(call)
+                    }
+                    content => content,
+                }),
+            },
+        })
+        .collect()
+}
+
+impl CompletionRequest {
+    /// Canonical form of the request, without its volatile fields (see the [module](self)
+    /// docs)
+    pub fn canonical_value(&self) -> Value {
+        let mut messages = self.chat_history.clone();
+        messages.push(self.prompt.clone());
+        let mut messages = renumber_tool_calls(messages);
+        let prompt = messages.pop();
+
+        let mut tools = self.tools.clone();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+
+        json!({
+            "preamble": self.preamble,
+            "chat_history": messages,
+            "prompt": prompt,
+            "documents": self.documents,
+            "tools": tools,
+            "temperature": self.temperature,
+            "max_tokens": self.max_tokens,
+            "tool_choice": self.tool_choice,
+            "additional_params": self.additional_params.clone().map(strip_volatile),
+        })
+    }
+
+    /// Canonical serialization of the request (see [CompletionRequest::canonical_value])
+    pub fn canonical_json(&self) -> String {
+        to_string(&self.canonical_value())
+    }
+
+    /// Hex-encoded SHA-256 hash of the canonical serialization of the request, e.g.: as a cache
+    /// key
+    pub fn canonical_hash(&self) -> String {
+        hash(&self.canonical_value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::{ToolChoice, ToolDefinition},
+        message::ToolResultContent,
+        OneOrMany,
+    };
+
+    fn request(call_id: &str, tools: &[&str], params: Value) -> CompletionRequest {
+        CompletionRequest {
+            prompt: Message::User {
+                content: OneOrMany::one(UserContent::tool_result(
+                    call_id,
+                    OneOrMany::one(ToolResultContent::text("Sunny")),
+                )),
+            },
+            preamble: Some("You are a weather assistant".to_string()),
+            chat_history: vec![
+                Message::user("What's the weather in Paris?"),
+                Message::Assistant {
+                    content: OneOrMany::one(AssistantContent::tool_call(
+                        call_id,
+                        "get_weather",
+                        json!({"city": "Paris"}),
+                    )),
+                },
+            ],
+            documents: vec![],
+            tools: tools
+                .iter()
+                .map(|name| ToolDefinition {
+                    name: name.to_string(),
+                    description: String::new(),
+                    parameters: json!({"type": "object"}),
+                })
+                .collect(),
+            temperature: Some(1.0),
+            max_tokens: None,
+            additional_params: Some(params),
+            tool_choice: Some(ToolChoice::Auto),
+        }
+    }
+
+    #[test]
+    fn test_canonical_hash() {
+        assert_eq!(
+            to_string(&json!({"b": {"y": -0.0, "x": 2.5}, "a": [1.0, "1.0"]})),
+            r#"{"a":[1,"1.0"],"b":{"x":2.5,"y":0}}"#
+        );
+
+        let request_a = request(
+            "call_abc",
+            &["get_weather", "get_time"],
+            json!({"top_p": 1.0, "user": "user-1", "seed": 7}),
+        );
+        let request_b = request(
+            "call_xyz",
+            &["get_time", "get_weather"],
+            json!({"seed": 7, "top_p": 1, "metadata": {"trace": "t-2"}}),
+        );
+        assert_eq!(request_a.canonical_json(), request_b.canonical_json());
+        assert_eq!(request_a.canonical_hash(), request_b.canonical_hash());
+        assert!(request_a.canonical_json().contains(r#""id":"call_0""#));
+
+        let mut request_c = request_a.clone();
+        request_c.temperature = Some(0.5);
+        assert_ne!(request_a.canonical_hash(), request_c.canonical_hash());
+    }
+}
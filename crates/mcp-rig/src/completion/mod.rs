@@ -1,4 +1,6 @@
+pub mod canonical;
 pub mod message;
+pub mod race;
 pub mod request;
 
 pub use message::{AssistantContent, Message, MessageError};
@@ -0,0 +1,244 @@
+//! Racing of completion models: the same request is sent to two or more models (e.g.: of
+//! different providers), and the first acceptable response wins. The requests of the other
+//! models are cancelled (i.e.: dropped) as soon as there is a winner.
+//!
+//! A [Race] is itself a [CompletionModel], and can thus be the model of an agent. It is
+//! useful when the latency of a provider is flaky: with [Race::hedge_after], the requests to
+//! the backup models are only sent if the first models haven't answered after a delay (i.e.:
+//! hedged requests), to save their cost in the common case.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use mcp_rig::{
+//!     agent::AgentBuilder,
+//!     completion::{race::Race, AssistantContent},
+//!     providers::{anthropic, openai},
+//! };
+//!
+//! let race = Race::new()
+//!     .model("openai", openai.completion_model(openai::GPT_4O))
+//!     .model("anthropic", anthropic.completion_model(anthropic::CLAUDE_3_5_SONNET))
+//!     .hedge_after(Duration::from_secs(2))
+//!     // Reject the empty answers
+//!     .accept_if(|response| match response.choice.first() {
+//!         AssistantContent::Text(text) => !text.text.trim().is_empty(),
+//!         AssistantContent::ToolCall(_) => true,
+//!     });
+//!
+//! let agent = AgentBuilder::new(race).preamble("You are a helpful assistant.").build();
+//! ```
+use std::{any::Any, sync::Arc, time::Duration};
+
+use futures::{future::BoxFuture, stream::FuturesUnordered, StreamExt};
+use futures_timer::Delay;
+
+use super::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse};
+
+type Complete = Arc<
+    dyn Fn(
+            CompletionRequest,
+        ) -> BoxFuture<'static, Result<CompletionResponse<RaceResponse>, CompletionError>>
+        + Send
+        + Sync,
+>;
+
+type Acceptance = Arc<dyn Fn(&CompletionResponse<RaceResponse>) -> bool + Send + Sync>;
+
+/// Raw response of the winner of a [Race].
+pub struct RaceResponse {
+    /// Name of the model which won the race
+    pub winner: String,
+    raw_response: Box<dyn Any + Send + Sync>,
+}
+
+impl RaceResponse {
+    /// Raw response of the winner, if it is a response of type `T` (i.e.: the
+    /// [CompletionModel::Response] of the winner)
+    pub fn raw_response<T: 'static>(&self) -> Option<&T> {
+        self.raw_response.downcast_ref()
+    }
+}
+
+impl std::fmt::Debug for RaceResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RaceResponse")
+            .field("winner", &self.winner)
+            .finish()
+    }
+}
+
+struct Entrant {
+    name: String,
+    complete: Complete,
+    supports_vision: bool,
+}
+
+/// Race of completion models (see the [module](self) docs).
+#[derive(Clone)]
+pub struct Race {
+    entrants: Vec<Arc<Entrant>>,
+    /// Delay between the requests to the successive models (all sent at once if unset)
+    hedge_delay: Option<Duration>,
+    accept: Acceptance,
+}
+
+impl Default for Race {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Race {
+    /// Race without models, accepting any successful response
+    pub fn new() -> Self {
+        Self {
+            entrants: vec![],
+            hedge_delay: None,
+            accept: Arc::new(|_| true),
+        }
+    }
+
+    /// Add the model `model` (named `name`, e.g.: in the logs) to the race
+    pub fn model<M: CompletionModel + 'static>(mut self, name: &str, model: M) -> Self
+    where
+        M::Response: 'static,
+    {
+        let winner = name.to_string();
+        let supports_vision = model.supports_vision();
+        let complete: Complete = Arc::new(move |request| {
+            let model = model.clone();
+            let winner = winner.clone();
+            Box::pin(async move {
+                let response = model.completion(request).await?;
+                Ok(CompletionResponse {
+                    choice: response.choice,
+                    raw_response: RaceResponse {
+                        winner,
+                        raw_response: Box::new(response.raw_response),
+                    },
+                })
+            })
+        });
+        self.entrants.push(Arc::new(Entrant {
+            name: name.to_string(),
+            complete,
+            supports_vision,
+        }));
+        self
+    }
+
+    /// Send the request to the `n`-th model only if no model answered acceptably after
+    /// `n * delay` (i.e.: hedged requests), instead of sending it to all models at once
+    pub fn hedge_after(mut self, delay: Duration) -> Self {
+        self.hedge_delay = Some(delay);
+        self
+    }
+
+    /// Only accept the responses satisfying `accept` (by default, any successful response).
+    /// The race fails if no model answers acceptably.
+    pub fn accept_if(
+        mut self,
+        accept: impl Fn(&CompletionResponse<RaceResponse>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.accept = Arc::new(accept);
+        self
+    }
+}
+
+impl CompletionModel for Race {
+    type Response = RaceResponse;
+
+    fn supports_vision(&self) -> bool {
+        !self.entrants.is_empty() && self.entrants.iter().all(|entrant| entrant.supports_vision)
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<RaceResponse>, CompletionError> {
+        let mut race = self
+            .entrants
+            .iter()
+            .enumerate()
+            .map(|(index, entrant)| {
+                let entrant = entrant.clone();
+                let request = request.clone();
+                let delay = self.hedge_delay.map(|delay| delay * index as u32);
+                async move {
+                    if let Some(delay) = delay.filter(|delay| !delay.is_zero()) {
+                        Delay::new(delay).await;
+                    }
+                    (entrant.name.clone(), (entrant.complete)(request).await)
+                }
+            })
+            .collect::<FuturesUnordered<_>>();
+
+        let mut failures = vec![];
+        while let Some((name, result)) = race.next().await {
+            match result {
+                Ok(response) if (self.accept)(&response) => {
+                    tracing::info!(target: "rig", "Model {} won the race", name);
+                    // The requests to the other models are cancelled when `race` is dropped
+                    return Ok(response);
+                }
+                Ok(_) => {
+                    tracing::warn!(target: "rig", "Response of model {} rejected in the race", name);
+                    failures.push(format!("{name}: response rejected"));
+                }
+                Err(error) => {
+                    tracing::warn!(target: "rig", "Model {} failed in the race: {}", name, error);
+                    failures.push(format!("{name}: {error}"));
+                }
+            }
+        }
+
+        Err(CompletionError::ResponseError(format!(
+            "No acceptable response in the race: {}",
+            failures.join("; ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::Prompt,
+        providers::mock::{self, MockResponse},
+    };
+
+    #[tokio::test]
+    async fn test_race() {
+        let slow = mock::CompletionModel::new()
+            .latency(Duration::from_millis(100))
+            .default_response(MockResponse::text("Slow answer"));
+        let fast = mock::CompletionModel::new()
+            .latency(Duration::from_millis(10))
+            .default_response(MockResponse::text("Fast answer"));
+        let failing = mock::CompletionModel::new().default_response(MockResponse::error("Down"));
+
+        let race = Race::new()
+            .model("slow", slow.clone())
+            .model("failing", failing)
+            .model("fast", fast.clone());
+        let agent = crate::agent::AgentBuilder::new(race.clone()).build();
+        assert_eq!(agent.prompt("Hello").await.unwrap(), "Fast answer");
+
+        let race = race.accept_if(|response| {
+            response.raw_response.winner != "fast"
+                && response.raw_response.raw_response::<()>().is_some()
+        });
+        let response = race.completion_request("Hello").send().await.unwrap();
+        assert_eq!(response.raw_response.winner, "slow");
+
+        // The backup models are not called if the first model answers in time
+        let race = Race::new()
+            .model("fast", fast.clone())
+            .model("slow", slow.clone())
+            .hedge_after(Duration::from_millis(50));
+        race.completion_request("Hello").send().await.unwrap();
+        assert_eq!(slow.requests().len(), 2);
+        assert_eq!(fast.requests().len(), 3);
+    }
+}
@@ -99,6 +99,10 @@ pub enum CompletionError {
     /// Error returned by the completion model provider
     #[error("ProviderError: {0}")]
     ProviderError(String),
+
+    /// The tenant of the agent rejected the request (e.g.: it is rate limited)
+    #[error("TenantError: {0}")]
+    TenantError(#[from] crate::tenant::TenantError),
 }
 
 #[derive(Debug, Error)]
@@ -228,6 +232,13 @@ pub trait CompletionModel: Clone + Send + Sync {
     ) -> impl std::future::Future<Output = Result<CompletionResponse<Self::Response>, CompletionError>>
            + Send;
 
+    /// Whether the model accepts image inputs (see [UserContent::Image](crate::message::UserContent::Image)).
+    /// Used to fail early with a clear error instead of letting the provider reject (or silently
+    /// drop) the images. Defaults to `false`.
+    fn supports_vision(&self) -> bool {
+        false
+    }
+
     /// Generates a completion request builder for the given `prompt`.
     fn completion_request(&self, prompt: impl Into<Message>) -> CompletionRequestBuilder<Self> {
         CompletionRequestBuilder::new(self.clone(), prompt)
@@ -235,6 +246,7 @@ pub trait CompletionModel: Clone + Send + Sync {
 }
 
 /// Struct representing a general completion request that can be sent to a completion model provider.
+#[derive(Clone)]
 pub struct CompletionRequest {
     /// The prompt to be sent to the completion model provider
     pub prompt: Message,
@@ -252,6 +264,46 @@ pub struct CompletionRequest {
     pub max_tokens: Option<u64>,
     /// Additional provider-specific parameters to be sent to the completion model provider
     pub additional_params: Option<serde_json::Value>,
+    /// Which tools the model may call (the provider's default, i.e.: [ToolChoice::Auto], if
+    /// unset). Ignored by the providers without tool choice.
+    pub tool_choice: Option<ToolChoice>,
+}
+
+/// Which tools a model may (or must) call.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// The model chooses whether to call tools
+    Auto,
+    /// The model doesn't call tools
+    None,
+    /// The model calls at least one tool
+    Required,
+    /// The model calls the given tool
+    Tool(String),
+}
+
+impl ToolChoice {
+    /// Tool choice in the format of OpenAI (and the OpenAI-compatible providers)
+    pub fn to_openai(&self) -> serde_json::Value {
+        match self {
+            ToolChoice::Auto => "auto".into(),
+            ToolChoice::None => "none".into(),
+            ToolChoice::Required => "required".into(),
+            ToolChoice::Tool(name) => serde_json::json!({
+                "type": "function",
+                "function": { "name": name }
+            }),
+        }
+    }
+
+    /// `tool_choice` in the format of OpenAI, `"auto"` if unset
+    pub(crate) fn openai_or_auto(tool_choice: &Option<ToolChoice>) -> serde_json::Value {
+        tool_choice
+            .as_ref()
+            .map(ToolChoice::to_openai)
+            .unwrap_or_else(|| "auto".into())
+    }
 }
 
 impl CompletionRequest {
@@ -329,6 +381,7 @@ pub struct CompletionRequestBuilder<M: CompletionModel> {
     temperature: Option<f64>,
     max_tokens: Option<u64>,
     additional_params: Option<serde_json::Value>,
+    tool_choice: Option<ToolChoice>,
 }
 
 impl<M: CompletionModel> CompletionRequestBuilder<M> {
@@ -343,6 +396,7 @@ impl<M: CompletionModel> CompletionRequestBuilder<M> {
             temperature: None,
             max_tokens: None,
             additional_params: None,
+            tool_choice: None,
         }
     }
 
@@ -396,10 +450,15 @@ impl<M: CompletionModel> CompletionRequestBuilder<M> {
     /// Cohere's completion models accept a `connectors` parameter that can be used to
     /// specify the data connectors used by Cohere when executing the completion
     /// (see `examples/cohere_connectors.rs`).
-    pub fn additional_params(mut self, additional_params: serde_json::Value) -> Self {
+    pub fn additional_params(mut self, additional_params: impl Into<serde_json::Value>) -> Self {
+        let additional_params = additional_params.into();
         match self.additional_params {
             Some(params) => {
-                self.additional_params = Some(json_utils::merge(params, additional_params));
+                self.additional_params = Some(json_utils::deep_merge(
+                    params,
+                    additional_params,
+                    json_utils::ArrayStrategy::Replace,
+                ));
             }
             None => {
                 self.additional_params = Some(additional_params);
@@ -444,6 +503,18 @@ impl<M: CompletionModel> CompletionRequestBuilder<M> {
         self
     }
 
+    /// Sets which tools the model may call.
+    pub fn tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    /// Sets which tools the model may call.
+    pub fn tool_choice_opt(mut self, tool_choice: Option<ToolChoice>) -> Self {
+        self.tool_choice = tool_choice;
+        self
+    }
+
     /// Builds the completion request.
     pub fn build(self) -> CompletionRequest {
         CompletionRequest {
@@ -455,6 +526,7 @@ impl<M: CompletionModel> CompletionRequestBuilder<M> {
             temperature: self.temperature,
             max_tokens: self.max_tokens,
             additional_params: self.additional_params,
+            tool_choice: self.tool_choice,
         }
     }
 
@@ -535,6 +607,7 @@ mod tests {
             temperature: None,
             max_tokens: None,
             additional_params: None,
+            tool_choice: None,
         };
 
         let expected = Message::User {
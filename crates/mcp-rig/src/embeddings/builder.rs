@@ -1,18 +1,43 @@
 //! The module defines the [EmbeddingsBuilder] struct which accumulates objects to be embedded
 //! and batch generates the embeddings for each object when built.
 //! Only types that implement the [Embed] trait can be added to the [EmbeddingsBuilder].
+//!
+//! Texts are split into batches of at most [EmbeddingModel::MAX_DOCUMENTS] texts (or a smaller,
+//! user-defined batch size) which are sent concurrently to the model provider. Progress can be
+//! monitored for large corpora by registering a callback with [EmbeddingsBuilder::on_progress].
 
-use std::{cmp::max, collections::HashMap};
+use std::{cmp::max, collections::HashMap, sync::Arc};
 
 use futures::{stream, StreamExt};
 
 use crate::{
     embeddings::{
-        embed::TextEmbedder, Embed, EmbedError, Embedding, EmbeddingError, EmbeddingModel,
+        checkpoint::{Checkpoint, CheckpointStore},
+        embed::TextEmbedder,
+        Embed, EmbedError, Embedding, EmbeddingError, EmbeddingModel,
     },
     OneOrMany,
 };
 
+/// Progress report emitted by the [EmbeddingsBuilder] after each completed batch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EmbeddingProgress {
+    /// Number of documents for which all texts have been embedded
+    pub documents_done: usize,
+    /// Total number of documents to embed
+    pub documents_total: usize,
+    /// Number of texts embedded so far
+    pub texts_done: usize,
+    /// Total number of texts to embed
+    pub texts_total: usize,
+    /// Number of tokens billed so far, as reported by the model provider (0 if the
+    /// provider does not report usage)
+    pub tokens_billed: usize,
+}
+
+type ProgressCallback = Arc<dyn Fn(EmbeddingProgress) + Send + Sync>;
+type DocumentId<T> = Box<dyn Fn(&T) -> String + Send + Sync>;
+
 /// Builder for creating embeddings from one or more documents of type `T`.
 /// Note: `T` can be any type that implements the [Embed] trait.
 ///
@@ -50,6 +75,10 @@ use crate::{
 pub struct EmbeddingsBuilder<M: EmbeddingModel, T: Embed> {
     model: M,
     documents: Vec<(T, Vec<String>)>,
+    batch_size: usize,
+    concurrency: usize,
+    on_progress: Option<ProgressCallback>,
+    checkpoint: Option<(Box<dyn CheckpointStore>, DocumentId<T>)>,
 }
 
 impl<M: EmbeddingModel, T: Embed> EmbeddingsBuilder<M, T> {
@@ -58,9 +87,48 @@ impl<M: EmbeddingModel, T: Embed> EmbeddingsBuilder<M, T> {
         Self {
             model,
             documents: vec![],
+            batch_size: M::MAX_DOCUMENTS,
+            concurrency: max(1, 1024 / M::MAX_DOCUMENTS),
+            on_progress: None,
+            checkpoint: None,
         }
     }
 
+    /// Set the maximum number of texts sent to the model provider in a single request.
+    /// The batch size is capped at [EmbeddingModel::MAX_DOCUMENTS].
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, M::MAX_DOCUMENTS);
+        self
+    }
+
+    /// Set the maximum number of concurrent requests sent to the model provider.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = max(1, concurrency);
+        self
+    }
+
+    /// Register a callback that is called with an [EmbeddingProgress] report every time
+    /// a batch of texts has been embedded.
+    pub fn on_progress(mut self, f: impl Fn(EmbeddingProgress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(f));
+        self
+    }
+
+    /// Persist the progress of the embedding job in the given checkpoint `store`. Documents are
+    /// identified by the ID returned by `id`.
+    ///
+    /// When building, documents already present in the stored checkpoint (with the same ID and
+    /// the same texts) are not re-embedded, which allows an interrupted job to be resumed.
+    /// Documents sharing the same ID and texts are only embedded once.
+    pub fn checkpoint(
+        mut self,
+        store: impl CheckpointStore + 'static,
+        id: impl Fn(&T) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.checkpoint = Some((Box::new(store), Box::new(id)));
+        self
+    }
+
     /// Add a document to be embedded to the builder. `document` must implement the [Embed] trait.
     pub fn document(mut self, document: T) -> Result<Self, EmbedError> {
         let mut embedder = TextEmbedder::default();
@@ -86,47 +154,138 @@ impl<M: EmbeddingModel, T: Embed + Send> EmbeddingsBuilder<M, T> {
     /// Generate embeddings for all documents in the builder.
     /// Returns a vector of tuples, where the first element is the document and the second element is the embeddings (either one embedding or many).
     pub async fn build(self) -> Result<Vec<(T, OneOrMany<Embedding>)>, EmbeddingError> {
-        use stream::TryStreamExt;
+        let checkpoint = match &self.checkpoint {
+            Some((store, _)) => store.load()?.unwrap_or_default(),
+            None => Checkpoint::default(),
+        };
 
         // Store the documents and their texts in a HashMap for easy access.
         let mut docs = HashMap::new();
         let mut texts = Vec::new();
+        // Number of texts left to embed for each document.
+        let mut remaining = HashMap::new();
+        // Embeddings of each document, keyed by document index.
+        let mut embeddings: HashMap<_, OneOrMany<Embedding>> = HashMap::new();
+        // Checkpoint key of each document (only used when checkpointing).
+        let mut keys = HashMap::new();
+        // Documents sharing the key of a previous document (index -> index of the first document).
+        let mut duplicates = HashMap::new();
+        let mut first_with_key = HashMap::new();
 
         // Iterate over all documents in the builder and insert their docs and texts into the lookup stores.
         for (i, (doc, doc_texts)) in self.documents.into_iter().enumerate() {
+            if let Some((_, id_fn)) = &self.checkpoint {
+                let key = Checkpoint::key(&id_fn(&doc), &doc_texts);
+
+                if let Some(first) = first_with_key.get(&key) {
+                    duplicates.insert(i, *first);
+                    docs.insert(i, doc);
+                    continue;
+                }
+                first_with_key.insert(key.clone(), i);
+
+                if let Some(restored) = checkpoint.documents.get(&key) {
+                    if restored.len() == doc_texts.len() {
+                        if let Ok(restored) = OneOrMany::many(restored.clone()) {
+                            embeddings.insert(i, restored);
+                            docs.insert(i, doc);
+                            remaining.insert(i, 0);
+                            continue;
+                        }
+                    }
+                }
+                keys.insert(i, key);
+            }
+
             docs.insert(i, doc);
+            remaining.insert(i, doc_texts.len());
             texts.push((i, doc_texts));
         }
 
+        if !embeddings.is_empty() {
+            tracing::info!(target: "rig",
+                "Resuming embedding job: {} document(s) restored from checkpoint",
+                embeddings.len()
+            );
+        }
+
+        let mut progress = EmbeddingProgress {
+            documents_done: embeddings.len() + duplicates.len(),
+            documents_total: docs.len(),
+            texts_total: remaining.values().sum(),
+            ..Default::default()
+        };
+
         // Compute the embeddings.
-        let mut embeddings = stream::iter(texts.into_iter())
+        let mut batches = stream::iter(texts.into_iter())
             // Merge the texts of each document into a single list of texts.
             .flat_map(|(i, texts)| stream::iter(texts.into_iter().map(move |text| (i, text))))
             // Chunk them into batches. Each batch size is at most the embedding API limit per request.
-            .chunks(M::MAX_DOCUMENTS)
+            .chunks(self.batch_size)
             // Generate the embeddings for each batch.
             .map(|text| async {
                 let (ids, docs): (Vec<_>, Vec<_>) = text.into_iter().unzip();
 
-                let embeddings = self.model.embed_texts(docs).await?;
-                Ok::<_, EmbeddingError>(ids.into_iter().zip(embeddings).collect::<Vec<_>>())
+                let (embeddings, usage) = self.model.embed_texts_with_usage(docs).await?;
+                Ok::<_, EmbeddingError>((
+                    ids.into_iter().zip(embeddings).collect::<Vec<_>>(),
+                    usage,
+                ))
             })
-            // Parallelize the embeddings generation over 10 concurrent requests
-            .buffer_unordered(max(1, 1024 / M::MAX_DOCUMENTS))
-            // Collect the embeddings into a HashMap.
-            .try_fold(
-                HashMap::new(),
-                |mut acc: HashMap<_, OneOrMany<Embedding>>, embeddings| async move {
-                    embeddings.into_iter().for_each(|(i, embedding)| {
-                        acc.entry(i)
-                            .and_modify(|embeddings| embeddings.push(embedding.clone()))
-                            .or_insert(OneOrMany::one(embedding.clone()));
-                    });
-
-                    Ok(acc)
-                },
-            )
-            .await?;
+            // Parallelize the embeddings generation over `concurrency` concurrent requests
+            .buffer_unordered(self.concurrency);
+
+        // Collect the embeddings into a HashMap.
+        while let Some(batch) = batches.next().await {
+            let (batch, usage) = batch?;
+
+            progress.texts_done += batch.len();
+            progress.tokens_billed += usage.map(|usage| usage.total_tokens).unwrap_or(0);
+
+            let mut completed = vec![];
+            batch.into_iter().for_each(|(i, embedding)| {
+                embeddings
+                    .entry(i)
+                    .and_modify(|embeddings| embeddings.push(embedding.clone()))
+                    .or_insert(OneOrMany::one(embedding));
+
+                if let Some(count) = remaining.get_mut(&i) {
+                    *count -= 1;
+                    if *count == 0 {
+                        completed.push(i);
+                    }
+                }
+            });
+
+            progress.documents_done += completed.len();
+
+            // Save the newly completed documents to the checkpoint
+            if let Some((store, _)) = &self.checkpoint {
+                let documents = completed
+                    .into_iter()
+                    .filter_map(|i| {
+                        let embeddings = embeddings.get(&i)?.iter().cloned().collect();
+                        Some((keys.get(&i)?.clone(), embeddings))
+                    })
+                    .collect::<HashMap<_, _>>();
+                if !documents.is_empty() {
+                    store.append(&documents)?;
+                }
+            }
+
+            if let Some(on_progress) = &self.on_progress {
+                on_progress(progress);
+            }
+        }
+
+        // Documents sharing an ID with a previous document reuse its embeddings
+        for (i, first) in duplicates {
+            let first_embeddings = embeddings
+                .get(&first)
+                .cloned()
+                .expect("Document should be present");
+            embeddings.insert(i, first_embeddings);
+        }
 
         // Merge the embeddings with their respective documents
         Ok(docs
@@ -384,4 +543,102 @@ mod tests {
             second_definition.1.rest()[0].document, "A fictional creature found in the distant, swampy marshlands of the planet Glibbo in the Andromeda galaxy.".to_string()
         )
     }
+
+    #[tokio::test]
+    async fn test_build_reports_progress() {
+        use std::sync::{Arc, Mutex};
+
+        let reports = Arc::new(Mutex::new(vec![]));
+        let reports_clone = reports.clone();
+
+        let fake_model = Model;
+        let result = EmbeddingsBuilder::new(fake_model)
+            .documents(definitions_multiple_text())
+            .unwrap()
+            .documents(definitions_multiple_text_2())
+            .unwrap()
+            .batch_size(1)
+            .concurrency(1)
+            .on_progress(move |progress| reports_clone.lock().unwrap().push(progress))
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 4);
+
+        let reports = reports.lock().unwrap();
+        // 6 texts in total, sent one at a time
+        assert_eq!(reports.len(), 6);
+
+        let last = reports.last().unwrap();
+        assert_eq!(last.texts_done, 6);
+        assert_eq!(last.texts_total, 6);
+        assert_eq!(last.documents_done, 4);
+        assert_eq!(last.documents_total, 4);
+        assert_eq!(last.tokens_billed, 0);
+
+        // With a single concurrent request, the first document is done after its 2 texts
+        assert_eq!(reports[0].documents_done, 0);
+        assert_eq!(reports[1].documents_done, 1);
+    }
+
+    #[tokio::test]
+    async fn test_build_resumes_from_checkpoint() {
+        use crate::embeddings::checkpoint::{Checkpoint, CheckpointStore, InMemoryCheckpointStore};
+        use std::collections::HashMap;
+
+        let store = InMemoryCheckpointStore::default();
+        let mut definitions = definitions_multiple_text();
+        let mut checkpoint = HashMap::new();
+        checkpoint.insert(
+            Checkpoint::key("doc0", &definitions[0].definitions),
+            vec![
+                Embedding {
+                    document: "restored 1".to_string(),
+                    vec: vec![9.0],
+                },
+                Embedding {
+                    document: "restored 2".to_string(),
+                    vec: vec![9.0],
+                },
+            ],
+        );
+        // doc1 changed since it was checkpointed, so it is embedded again
+        checkpoint.insert(
+            Checkpoint::key("doc1", &["Outdated definition".to_string()]),
+            vec![Embedding {
+                document: "Outdated definition".to_string(),
+                vec: vec![9.0],
+            }],
+        );
+        store.append(&checkpoint).unwrap();
+
+        // Duplicate of doc1, should only be embedded once
+        definitions.push(definitions[1].clone());
+
+        let mut result = EmbeddingsBuilder::new(Model)
+            .documents(definitions)
+            .unwrap()
+            .checkpoint(store.clone(), |doc: &WordDefinition| doc.id.clone())
+            .build()
+            .await
+            .unwrap();
+
+        result.sort_by(|(fake_definition_1, _), (fake_definition_2, _)| {
+            fake_definition_1.id.cmp(&fake_definition_2.id)
+        });
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0].0.id, "doc0");
+        assert_eq!(result[0].1.first().vec, vec![9.0]);
+        assert_eq!(result[1].0.id, "doc1");
+        assert_eq!(result[1].1.len(), 2);
+        assert_ne!(result[1].1.first().vec, vec![9.0]);
+        assert_eq!(result[2].0.id, "doc1");
+        assert_eq!(result[2].1.len(), 2);
+
+        let saved = store.checkpoint().unwrap();
+        assert!(saved.contains("doc0", &result[0].0.definitions));
+        assert!(saved.contains("doc1", &result[1].0.definitions));
+    }
 }
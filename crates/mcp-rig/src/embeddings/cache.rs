@@ -0,0 +1,510 @@
+//! The module defines the [EmbeddingCache] trait, which represents a key-value store for
+//! previously computed embedding vectors, and the [CachedEmbeddingModel] struct, which wraps any
+//! [EmbeddingModel] and only forwards texts that are not already in the cache to the provider.
+//!
+//! Cache keys are derived from a SHA-256 hash of the embedded text (optionally prefixed by a
+//! namespace, e.g.: the model name), so re-running an [EmbeddingsBuilder](crate::embeddings::EmbeddingsBuilder)
+//! over a mostly-unchanged corpus only bills the provider for the new or modified documents.
+//!
+//! The following backends are provided:
+//! - [InMemoryEmbeddingCache]: bounded in-memory LRU cache
+//! - [DiskEmbeddingCache]: one JSON file per entry in a local directory (not available on wasm
+//!   targets)
+//! - [RedisEmbeddingCache]: Redis-backed cache (requires the `redis` feature)
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     embeddings::{cache::{CachedEmbeddingModel, InMemoryEmbeddingCache}, EmbeddingModel},
+//!     providers::openai,
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//! let model = openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+//!
+//! let cached_model = CachedEmbeddingModel::new(model, InMemoryEmbeddingCache::new(10_000))
+//!     .namespace(openai::TEXT_EMBEDDING_3_SMALL);
+//!
+//! // The second call is served from the cache.
+//! let embedding = cached_model.embed_text("Hello, world!").await?;
+//! let embedding = cached_model.embed_text("Hello, world!").await?;
+//! ```
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::{
+    future::Future,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+
+use super::{embedding::EmbeddingUsage, Embedding, EmbeddingError, EmbeddingModel};
+
+/// Trait for key-value stores that can hold embedding vectors.
+pub trait EmbeddingCache: Send + Sync {
+    /// Get the embedding vector stored under `key`, if any.
+    fn get(
+        &self,
+        key: &str,
+    ) -> impl Future<Output = Result<Option<Vec<f64>>, EmbeddingError>> + Send;
+
+    /// Store the embedding vector `vec` under `key`.
+    fn put(
+        &self,
+        key: &str,
+        vec: Vec<f64>,
+    ) -> impl Future<Output = Result<(), EmbeddingError>> + Send;
+}
+
+/// Compute the cache key of `text` in the given `namespace`.
+pub fn cache_key(namespace: &str, text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(namespace.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(text.as_bytes());
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+// ================================================================
+// Cached embedding model
+// ================================================================
+
+/// Wrapper around an [EmbeddingModel] that looks up texts in an [EmbeddingCache] before
+/// sending them to the model provider.
+pub struct CachedEmbeddingModel<M: EmbeddingModel, C: EmbeddingCache> {
+    model: M,
+    cache: Arc<C>,
+    namespace: String,
+}
+
+impl<M: EmbeddingModel, C: EmbeddingCache> Clone for CachedEmbeddingModel<M, C> {
+    fn clone(&self) -> Self {
+        Self {
+            model: self.model.clone(),
+            cache: self.cache.clone(),
+            namespace: self.namespace.clone(),
+        }
+    }
+}
+
+impl<M: EmbeddingModel, C: EmbeddingCache> CachedEmbeddingModel<M, C> {
+    /// Create a new cached embedding model from an embedding model and a cache backend.
+    pub fn new(model: M, cache: C) -> Self {
+        Self {
+            model,
+            cache: Arc::new(cache),
+            namespace: String::new(),
+        }
+    }
+
+    /// Set the namespace used to compute cache keys. Embeddings from different models
+    /// sharing a same cache backend should use different namespaces (e.g.: the model name).
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.namespace = namespace.to_string();
+        self
+    }
+
+    /// Get a reference to the underlying cache backend.
+    pub fn cache(&self) -> &C {
+        &self.cache
+    }
+
+    /// Get a reference to the underlying embedding model.
+    pub fn model(&self) -> &M {
+        &self.model
+    }
+}
+
+impl<M: EmbeddingModel, C: EmbeddingCache> EmbeddingModel for CachedEmbeddingModel<M, C> {
+    const MAX_DOCUMENTS: usize = M::MAX_DOCUMENTS;
+
+    fn ndims(&self) -> usize {
+        self.model.ndims()
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        let (embeddings, _) = self.embed_texts_with_usage(texts).await?;
+        Ok(embeddings)
+    }
+
+    /// The usage is the one reported by the model for the texts missing from the cache (if any).
+    async fn embed_texts_with_usage(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<(Vec<Embedding>, Option<EmbeddingUsage>), EmbeddingError> {
+        let texts = texts.into_iter().collect::<Vec<_>>();
+        let keys = texts
+            .iter()
+            .map(|text| cache_key(&self.namespace, text))
+            .collect::<Vec<_>>();
+
+        let mut embeddings = Vec::with_capacity(texts.len());
+        let mut misses = Vec::new();
+
+        for (i, (text, key)) in texts.iter().zip(keys.iter()).enumerate() {
+            match self.cache.get(key).await? {
+                Some(vec) => embeddings.push(Some(Embedding {
+                    document: text.clone(),
+                    vec,
+                })),
+                None => {
+                    embeddings.push(None);
+                    misses.push(i);
+                }
+            }
+        }
+
+        tracing::debug!(target: "rig",
+            "Embedding cache: {} hit(s), {} miss(es)",
+            texts.len() - misses.len(),
+            misses.len()
+        );
+
+        let mut usage = None;
+        if !misses.is_empty() {
+            let (computed, computed_usage) = self
+                .model
+                .embed_texts_with_usage(
+                    misses.iter().map(|i| texts[*i].clone()).collect::<Vec<_>>(),
+                )
+                .await?;
+            usage = computed_usage;
+
+            if computed.len() != misses.len() {
+                return Err(EmbeddingError::ResponseError(format!(
+                    "Expected {} embeddings, got {}",
+                    misses.len(),
+                    computed.len()
+                )));
+            }
+
+            for (i, embedding) in misses.into_iter().zip(computed) {
+                self.cache.put(&keys[i], embedding.vec.clone()).await?;
+                embeddings[i] = Some(embedding);
+            }
+        }
+
+        let embeddings = embeddings
+            .into_iter()
+            .map(|embedding| embedding.expect("All embeddings should be computed"))
+            .collect();
+        Ok((embeddings, usage))
+    }
+}
+
+// ================================================================
+// In-memory LRU backend
+// ================================================================
+
+/// Bounded in-memory [EmbeddingCache]. When full, the least recently used entry is evicted.
+pub struct InMemoryEmbeddingCache {
+    capacity: usize,
+    entries: Mutex<LruCache<String, Vec<f64>>>,
+}
+
+impl InMemoryEmbeddingCache {
+    /// Create a new in-memory cache holding at most `capacity` embeddings.
+    pub fn new(capacity: usize) -> Self {
+        let bound = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            capacity,
+            entries: Mutex::new(LruCache::new(bound)),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<String, Vec<f64>>> {
+        self.entries.lock().expect("Cache lock poisoned")
+    }
+
+    /// Number of embeddings currently in the cache.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl EmbeddingCache for InMemoryEmbeddingCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<f64>>, EmbeddingError> {
+        Ok(self.lock().get(key).cloned())
+    }
+
+    async fn put(&self, key: &str, vec: Vec<f64>) -> Result<(), EmbeddingError> {
+        if self.capacity > 0 {
+            self.lock().put(key.to_string(), vec);
+        }
+        Ok(())
+    }
+}
+
+// ================================================================
+// Disk backend
+// ================================================================
+
+/// [EmbeddingCache] that persists each embedding as a JSON file in a local directory. The files
+/// are read and written on the blocking threads of the tokio runtime.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct DiskEmbeddingCache {
+    dir: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DiskEmbeddingCache {
+    /// Create a new disk cache in `dir`. The directory is created if it does not exist.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, EmbeddingError> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(|e| EmbeddingError::CacheError(e.into()))?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+}
+
+/// Run the file operation `f` on a blocking thread.
+#[cfg(not(target_arch = "wasm32"))]
+async fn blocking_fs<T: Send + 'static>(
+    f: impl FnOnce() -> std::io::Result<T> + Send + 'static,
+) -> Result<T, EmbeddingError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| EmbeddingError::CacheError(e.into()))?
+        .map_err(|e| EmbeddingError::CacheError(e.into()))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EmbeddingCache for DiskEmbeddingCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<f64>>, EmbeddingError> {
+        let path = self.path(key);
+        let bytes = blocking_fs(move || match std::fs::read(path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        })
+        .await?;
+        Ok(bytes
+            .map(|bytes| serde_json::from_slice(&bytes))
+            .transpose()?)
+    }
+
+    async fn put(&self, key: &str, vec: Vec<f64>) -> Result<(), EmbeddingError> {
+        let path = self.path(key);
+        let bytes = serde_json::to_vec(&vec)?;
+        blocking_fs(move || std::fs::write(path, bytes)).await
+    }
+}
+
+// ================================================================
+// Redis backend
+// ================================================================
+
+/// [EmbeddingCache] backed by a Redis server. Requires the `redis` feature.
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+pub struct RedisEmbeddingCache {
+    connection: redis::aio::MultiplexedConnection,
+    prefix: String,
+    ttl: Option<u64>,
+}
+
+#[cfg(feature = "redis")]
+impl RedisEmbeddingCache {
+    /// Connect to the Redis server at `url` (e.g.: `redis://127.0.0.1/`).
+    pub async fn connect(url: &str) -> Result<Self, EmbeddingError> {
+        let client = redis::Client::open(url).map_err(|e| EmbeddingError::CacheError(e.into()))?;
+        let connection = client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| EmbeddingError::CacheError(e.into()))?;
+
+        Ok(Self {
+            connection,
+            prefix: "rig:embedding:".to_string(),
+            ttl: None,
+        })
+    }
+
+    /// Set the prefix prepended to every Redis key (defaults to `rig:embedding:`).
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Set the expiration (in seconds) of cached entries.
+    pub fn ttl(mut self, seconds: u64) -> Self {
+        self.ttl = Some(seconds);
+        self
+    }
+}
+
+#[cfg(feature = "redis")]
+impl EmbeddingCache for RedisEmbeddingCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<f64>>, EmbeddingError> {
+        use redis::AsyncCommands;
+
+        let mut connection = self.connection.clone();
+        let value: Option<String> = connection
+            .get(format!("{}{}", self.prefix, key))
+            .await
+            .map_err(|e| EmbeddingError::CacheError(e.into()))?;
+
+        Ok(value.map(|v| serde_json::from_str(&v)).transpose()?)
+    }
+
+    async fn put(&self, key: &str, vec: Vec<f64>) -> Result<(), EmbeddingError> {
+        use redis::AsyncCommands;
+
+        let mut connection = self.connection.clone();
+        let key = format!("{}{}", self.prefix, key);
+        let value = serde_json::to_string(&vec)?;
+
+        match self.ttl {
+            Some(ttl) => connection.set_ex::<_, _, ()>(key, value, ttl).await,
+            None => connection.set::<_, _, ()>(key, value).await,
+        }
+        .map_err(|e| EmbeddingError::CacheError(e.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct CountingModel {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl EmbeddingModel for CountingModel {
+        const MAX_DOCUMENTS: usize = 5;
+
+        fn ndims(&self) -> usize {
+            1
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|text| {
+                    self.calls.fetch_add(1, Ordering::SeqCst);
+                    Embedding {
+                        vec: vec![text.len() as f64],
+                        document: text,
+                    }
+                })
+                .collect())
+        }
+
+        async fn embed_texts_with_usage(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<(Vec<Embedding>, Option<EmbeddingUsage>), EmbeddingError> {
+            let embeddings = self.embed_texts(texts).await?;
+            let total_tokens = embeddings.iter().map(|e| e.document.len()).sum();
+            Ok((embeddings, Some(EmbeddingUsage { total_tokens })))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_model_only_embeds_misses() {
+        let model = CountingModel::default();
+        let calls = model.calls.clone();
+        let cached = CachedEmbeddingModel::new(model, InMemoryEmbeddingCache::new(10));
+
+        let first = cached
+            .embed_texts(vec!["a".to_string(), "bb".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let second = cached
+            .embed_texts(vec!["bb".to_string(), "ccc".to_string(), "a".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        assert_eq!(first[0].vec, vec![1.0]);
+        assert_eq!(second[0].document, "bb".to_string());
+        assert_eq!(second[0].vec, vec![2.0]);
+        assert_eq!(second[1].vec, vec![3.0]);
+        assert_eq!(second[2].vec, vec![1.0]);
+    }
+
+    #[tokio::test]
+    async fn test_cached_model_forwards_usage_of_misses() {
+        let cached =
+            CachedEmbeddingModel::new(CountingModel::default(), InMemoryEmbeddingCache::new(10));
+
+        let (_, usage) = cached
+            .embed_texts_with_usage(vec!["a".to_string(), "bb".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(usage, Some(EmbeddingUsage { total_tokens: 3 }));
+
+        let (_, usage) = cached
+            .embed_texts_with_usage(vec!["bb".to_string(), "ccc".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(usage, Some(EmbeddingUsage { total_tokens: 3 }));
+
+        let (_, usage) = cached
+            .embed_texts_with_usage(vec!["a".to_string(), "ccc".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(usage, None);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_cache_evicts_least_recently_used() {
+        let cache = InMemoryEmbeddingCache::new(2);
+
+        cache.put("a", vec![1.0]).await.unwrap();
+        cache.put("b", vec![2.0]).await.unwrap();
+        // Touch "a" so that "b" becomes the least recently used entry
+        assert!(cache.get("a").await.unwrap().is_some());
+        cache.put("c", vec![3.0]).await.unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("a").await.unwrap(), Some(vec![1.0]));
+        assert_eq!(cache.get("b").await.unwrap(), None);
+        assert_eq!(cache.get("c").await.unwrap(), Some(vec![3.0]));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_disk_cache_roundtrip() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let cache = DiskEmbeddingCache::new(dir.path()).unwrap();
+
+        let key = cache_key("model", "hello");
+        assert_eq!(cache.get(&key).await.unwrap(), None);
+
+        cache.put(&key, vec![0.5, 0.25]).await.unwrap();
+        assert_eq!(cache.get(&key).await.unwrap(), Some(vec![0.5, 0.25]));
+    }
+
+    #[test]
+    fn test_cache_key_depends_on_namespace() {
+        assert_eq!(cache_key("a", "text"), cache_key("a", "text"));
+        assert_ne!(cache_key("a", "text"), cache_key("b", "text"));
+        assert_eq!(cache_key("a", "text").len(), 64);
+    }
+}
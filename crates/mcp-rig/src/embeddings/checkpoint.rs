@@ -0,0 +1,224 @@
+//! The module defines the [CheckpointStore] trait and the [Checkpoint] struct, which are used by
+//! the [EmbeddingsBuilder](crate::embeddings::EmbeddingsBuilder) to persist the embeddings of
+//! already processed documents while a (potentially very long) embedding job is running.
+//!
+//! If the job is interrupted, running it again with the same checkpoint store will skip every
+//! document already present in the checkpoint (with the same ID and the same texts) and only
+//! embed the remaining ones. The documents whose texts changed since they were checkpointed are
+//! embedded again.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     embeddings::{checkpoint::FileCheckpointStore, EmbeddingsBuilder},
+//!     providers::openai,
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//! let model = openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL);
+//!
+//! let embeddings = EmbeddingsBuilder::new(model)
+//!     .documents(documents)?
+//!     .checkpoint(FileCheckpointStore::new("embeddings.checkpoint.jsonl"), |doc: &Doc| doc.id.clone())
+//!     .build()
+//!     .await?;
+//! ```
+use std::{
+    collections::HashMap,
+    io::{Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Embedding, EmbeddingError};
+
+/// State of an embedding job: the embeddings of every document that has been completely
+/// embedded so far, keyed by the ID and the hash of the texts of the document (see
+/// [Checkpoint::key]).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Checkpoint {
+    pub documents: HashMap<String, Vec<Embedding>>,
+}
+
+impl Checkpoint {
+    /// Key of the document with the given ID and texts.
+    pub fn key(id: &str, texts: &[String]) -> String {
+        format!(
+            "{id}#{}",
+            crate::completion::canonical::hash(&serde_json::json!(texts))
+        )
+    }
+
+    /// Check if the document with the given ID and texts has already been embedded.
+    pub fn contains(&self, id: &str, texts: &[String]) -> bool {
+        self.documents.contains_key(&Self::key(id, texts))
+    }
+}
+
+/// Trait for stores that can persist a [Checkpoint].
+pub trait CheckpointStore: Send + Sync {
+    /// Load the last saved checkpoint, if any.
+    fn load(&self) -> Result<Option<Checkpoint>, EmbeddingError>;
+
+    /// Add the embeddings of newly completed `documents` (keyed as in [Checkpoint::documents])
+    /// to the saved checkpoint.
+    fn append(&self, documents: &HashMap<String, Vec<Embedding>>) -> Result<(), EmbeddingError>;
+}
+
+/// Line of the file of a [FileCheckpointStore].
+#[derive(Deserialize, Serialize)]
+struct CheckpointLine {
+    key: String,
+    embeddings: Vec<Embedding>,
+}
+
+/// [CheckpointStore] that persists the checkpoint as a JSON lines file, with one line per
+/// completed document. The lines are only ever appended, so that saving the progress of a job
+/// doesn't rewrite the whole checkpoint. A line cut by an interrupted job is ignored.
+pub struct FileCheckpointStore {
+    path: PathBuf,
+}
+
+impl FileCheckpointStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn load(&self) -> Result<Option<Checkpoint>, EmbeddingError> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(EmbeddingError::CacheError(e.into())),
+        };
+
+        let mut checkpoint = Checkpoint::default();
+        let lines = text.lines().filter(|line| !line.trim().is_empty());
+        for line in lines {
+            match serde_json::from_str::<CheckpointLine>(line) {
+                Ok(line) => {
+                    checkpoint.documents.insert(line.key, line.embeddings);
+                }
+                Err(e) => tracing::warn!(target: "rig", "Skipping invalid checkpoint line: {}", e),
+            }
+        }
+        Ok(Some(checkpoint))
+    }
+
+    fn append(&self, documents: &HashMap<String, Vec<Embedding>>) -> Result<(), EmbeddingError> {
+        let mut lines = vec![];
+        for (key, embeddings) in documents {
+            serde_json::to_writer(
+                &mut lines,
+                &CheckpointLine {
+                    key: key.clone(),
+                    embeddings: embeddings.clone(),
+                },
+            )?;
+            lines.push(b'\n');
+        }
+
+        std::fs::OpenOptions::new()
+            .read(true)
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| {
+                // A line cut by an interrupted job is ended first
+                let len = file.metadata()?.len();
+                if len > 0 {
+                    let mut last = [0u8];
+                    file.seek(SeekFrom::Start(len - 1))?;
+                    file.read_exact(&mut last)?;
+                    if last[0] != b'\n' {
+                        file.write_all(b"\n")?;
+                    }
+                }
+                file.write_all(&lines)
+            })
+            .map_err(|e| EmbeddingError::CacheError(e.into()))
+    }
+}
+
+/// In-memory [CheckpointStore]. Mostly useful for testing, or to share a checkpoint
+/// between multiple builders within the same process.
+#[derive(Clone, Default)]
+pub struct InMemoryCheckpointStore {
+    checkpoint: Arc<Mutex<Option<Checkpoint>>>,
+}
+
+impl InMemoryCheckpointStore {
+    /// Get a copy of the last saved checkpoint.
+    pub fn checkpoint(&self) -> Option<Checkpoint> {
+        self.checkpoint
+            .lock()
+            .expect("Checkpoint lock poisoned")
+            .clone()
+    }
+}
+
+impl CheckpointStore for InMemoryCheckpointStore {
+    fn load(&self) -> Result<Option<Checkpoint>, EmbeddingError> {
+        Ok(self.checkpoint())
+    }
+
+    fn append(&self, documents: &HashMap<String, Vec<Embedding>>) -> Result<(), EmbeddingError> {
+        self.checkpoint
+            .lock()
+            .expect("Checkpoint lock poisoned")
+            .get_or_insert_with(Checkpoint::default)
+            .documents
+            .extend(documents.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_checkpoint_store_roundtrip() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("checkpoint.jsonl");
+        let store = FileCheckpointStore::new(&path);
+
+        assert!(store.load().unwrap().is_none());
+
+        let texts = vec!["hello".to_string()];
+        let embedding = |vec: Vec<f64>| Embedding {
+            document: "hello".to_string(),
+            vec,
+        };
+        store
+            .append(&HashMap::from([(
+                Checkpoint::key("doc0", &texts),
+                vec![embedding(vec![0.1, 0.2])],
+            )]))
+            .unwrap();
+        // Line cut by an interrupted job
+        std::fs::write(
+            &path,
+            std::fs::read_to_string(&path).unwrap() + r#"{"key":"doc1#"#,
+        )
+        .unwrap();
+        store
+            .append(&HashMap::from([(
+                Checkpoint::key("doc2", &texts),
+                vec![embedding(vec![0.3, 0.4])],
+            )]))
+            .unwrap();
+
+        let loaded = store.load().unwrap().unwrap();
+        assert_eq!(loaded.documents.len(), 2);
+        assert!(loaded.contains("doc0", &texts));
+        assert!(!loaded.contains("doc0", &["goodbye".to_string()]));
+        assert_eq!(
+            loaded.documents[&Checkpoint::key("doc2", &texts)][0].vec,
+            vec![0.3, 0.4]
+        );
+    }
+}
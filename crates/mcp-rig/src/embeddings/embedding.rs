@@ -8,6 +8,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use super::matryoshka::MatryoshkaEmbeddingModel;
+
 #[derive(Debug, thiserror::Error)]
 pub enum EmbeddingError {
     /// Http error (e.g.: connection error, timeout, etc.)
@@ -29,6 +31,10 @@ pub enum EmbeddingError {
     /// Error returned by the embedding model provider
     #[error("ProviderError: {0}")]
     ProviderError(String),
+
+    /// Error reading from or writing to an embedding cache or checkpoint store
+    #[error("CacheError: {0}")]
+    CacheError(Box<dyn std::error::Error + Send + Sync + 'static>),
 }
 
 /// Trait for embedding models that can generate embeddings for documents.
@@ -45,6 +51,25 @@ pub trait EmbeddingModel: Clone + Sync + Send {
         texts: impl IntoIterator<Item = String> + Send,
     ) -> impl std::future::Future<Output = Result<Vec<Embedding>, EmbeddingError>> + Send;
 
+    /// Embed multiple text documents in a single request and return the token usage
+    /// reported by the provider (if any) along with the embeddings.
+    /// The default implementation calls [EmbeddingModel::embed_texts] and reports no usage.
+    fn embed_texts_with_usage(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> impl std::future::Future<
+        Output = Result<(Vec<Embedding>, Option<EmbeddingUsage>), EmbeddingError>,
+    > + Send {
+        async { Ok((self.embed_texts(texts).await?, None)) }
+    }
+
+    /// Truncate the embeddings generated by this model to `ndims` dimensions (and re-normalize them).
+    /// Only meaningful for models trained with matryoshka representation learning.
+    /// See [MatryoshkaEmbeddingModel] for more details.
+    fn with_output_dims(self, ndims: usize) -> MatryoshkaEmbeddingModel<Self> {
+        MatryoshkaEmbeddingModel::new(self, ndims)
+    }
+
     /// Embed a single text document.
     fn embed_text(
         &self,
@@ -60,6 +85,13 @@ pub trait EmbeddingModel: Clone + Sync + Send {
     }
 }
 
+/// Token usage reported by an embedding model provider for a single request.
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct EmbeddingUsage {
+    /// Number of tokens billed for the request
+    pub total_tokens: usize,
+}
+
 /// Struct that holds a single document and its embedding.
 #[derive(Clone, Default, Deserialize, Serialize, Debug)]
 pub struct Embedding {
@@ -76,3 +108,16 @@ impl PartialEq for Embedding {
 }
 
 impl Eq for Embedding {}
+
+impl Embedding {
+    /// Truncate the embedding vector to its first `ndims` dimensions and re-normalize it
+    /// to unit length. Does nothing to the length if the vector is already shorter than `ndims`.
+    pub fn truncate(&mut self, ndims: usize) {
+        self.vec.truncate(ndims);
+
+        let magnitude = self.vec.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if magnitude > 0.0 {
+            self.vec.iter_mut().for_each(|x| *x /= magnitude);
+        }
+    }
+}
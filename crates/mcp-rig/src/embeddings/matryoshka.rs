@@ -0,0 +1,135 @@
+//! The module defines the [MatryoshkaEmbeddingModel] struct, which wraps any [EmbeddingModel]
+//! producing matryoshka-style embeddings (e.g.: OpenAI `text-embedding-3-*`, Nomic `nomic-embed-text-v1.5`)
+//! and truncates the returned vectors to a smaller number of dimensions client-side.
+//!
+//! Matryoshka embeddings are trained so that their first `n` dimensions are a valid (if less
+//! precise) embedding on their own. Truncated vectors are re-normalized to unit length so that
+//! cosine similarity and dot product remain equivalent.
+//!
+//! Providers that support truncation natively (e.g.: the OpenAI `dimensions` parameter) expose
+//! their own `with_output_dims` method, which should be preferred since it reduces the size
+//! of the response.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     embeddings::{matryoshka::MatryoshkaEmbeddingModel, EmbeddingModel},
+//!     providers::openai,
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//! let model = MatryoshkaEmbeddingModel::new(
+//!     openai.embedding_model(openai::TEXT_EMBEDDING_3_LARGE),
+//!     256,
+//! );
+//!
+//! assert_eq!(model.ndims(), 256);
+//! ```
+use super::{Embedding, EmbeddingError, EmbeddingModel, EmbeddingUsage};
+
+/// [EmbeddingModel] that truncates the embeddings of the wrapped model to `ndims` dimensions
+/// and re-normalizes them.
+#[derive(Clone)]
+pub struct MatryoshkaEmbeddingModel<M: EmbeddingModel> {
+    model: M,
+    ndims: usize,
+}
+
+impl<M: EmbeddingModel> MatryoshkaEmbeddingModel<M> {
+    pub fn new(model: M, ndims: usize) -> Self {
+        Self { model, ndims }
+    }
+
+    /// Get a reference to the wrapped model.
+    pub fn inner(&self) -> &M {
+        &self.model
+    }
+
+    fn truncate(&self, embeddings: Vec<Embedding>) -> Result<Vec<Embedding>, EmbeddingError> {
+        embeddings
+            .into_iter()
+            .map(|mut embedding| {
+                if embedding.vec.len() < self.ndims {
+                    return Err(EmbeddingError::ResponseError(format!(
+                        "Cannot truncate embedding of {} dimensions to {} dimensions",
+                        embedding.vec.len(),
+                        self.ndims
+                    )));
+                }
+                embedding.truncate(self.ndims);
+                Ok(embedding)
+            })
+            .collect()
+    }
+}
+
+impl<M: EmbeddingModel> EmbeddingModel for MatryoshkaEmbeddingModel<M> {
+    const MAX_DOCUMENTS: usize = M::MAX_DOCUMENTS;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
+
+    async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<Embedding>, EmbeddingError> {
+        self.truncate(self.model.embed_texts(texts).await?)
+    }
+
+    async fn embed_texts_with_usage(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<(Vec<Embedding>, Option<EmbeddingUsage>), EmbeddingError> {
+        let (embeddings, usage) = self.model.embed_texts_with_usage(texts).await?;
+        Ok((self.truncate(embeddings)?, usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct FixedModel;
+
+    impl EmbeddingModel for FixedModel {
+        const MAX_DOCUMENTS: usize = 5;
+
+        fn ndims(&self) -> usize {
+            4
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|document| Embedding {
+                    document,
+                    vec: vec![3.0, 4.0, 1.0, 1.0],
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_truncate_and_normalize() {
+        let model = FixedModel.with_output_dims(2);
+        assert_eq!(model.ndims(), 2);
+
+        let embedding = model.embed_text("hello").await.unwrap();
+        assert_eq!(embedding.vec, vec![0.6, 0.8]);
+    }
+
+    #[tokio::test]
+    async fn test_truncate_too_many_dims() {
+        let model = FixedModel.with_output_dims(8);
+
+        assert!(matches!(
+            model.embed_text("hello").await,
+            Err(EmbeddingError::ResponseError(_))
+        ));
+    }
+}
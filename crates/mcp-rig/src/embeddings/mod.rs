@@ -4,12 +4,21 @@
 //! and document similarity.
 
 pub mod builder;
+pub mod cache;
+pub mod checkpoint;
 pub mod embed;
 pub mod embedding;
+pub mod matryoshka;
+pub mod quantization;
+pub mod sparse;
 pub mod tool;
 
 pub mod distance;
-pub use builder::EmbeddingsBuilder;
+pub use builder::{EmbeddingProgress, EmbeddingsBuilder};
+pub use cache::{CachedEmbeddingModel, EmbeddingCache};
 pub use embed::{to_texts, Embed, EmbedError, TextEmbedder};
-pub use embedding::{Embedding, EmbeddingError, EmbeddingModel};
+pub use embedding::{Embedding, EmbeddingError, EmbeddingModel, EmbeddingUsage};
+pub use matryoshka::MatryoshkaEmbeddingModel;
+pub use quantization::{EmbeddingVector, QuantizedEmbedding};
+pub use sparse::{HybridEmbedding, SparseEmbedding, SparseEmbeddingModel};
 pub use tool::ToolSchema;
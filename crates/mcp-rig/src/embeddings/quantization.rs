@@ -0,0 +1,295 @@
+//! The module defines compact representations of embedding vectors.
+//!
+//! Providers return embeddings as `f32` values at best, so storing them as `Vec<f64>` (see
+//! [Embedding]) doubles their memory footprint. The [QuantizedEmbedding] struct stores the
+//! vector using any type that implements the [Element] trait:
+//! - `f32`: lossless w.r.t. what providers return, half the size of `f64`
+//! - [f16]: half precision float, a quarter of the size of `f64`
+//! - `i8`: symmetric scalar quantization (one scale factor per vector), an eighth of the size of `f64`
+//!
+//! The module also defines the [EmbeddingVector] trait, which abstracts over the different
+//! embedding representations that can be stored in an [InMemoryVectorStore](crate::vector_store::in_memory_store::InMemoryVectorStore).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::embeddings::{quantization::QuantizedEmbedding, Embedding};
+//!
+//! let embedding = Embedding {
+//!     document: "Hello, world!".to_string(),
+//!     vec: vec![0.1, -0.5, 0.9],
+//! };
+//!
+//! let quantized: QuantizedEmbedding<i8> = embedding.quantize();
+//! assert_eq!(quantized.vec, vec![14, -71, 127]);
+//! ```
+pub use half::f16;
+use serde::{Deserialize, Serialize};
+
+use super::{distance::VectorDistance, Embedding};
+
+/// Trait for the element types that can be used to store an embedding vector.
+pub trait Element: Copy + Send + Sync + Serialize + for<'a> Deserialize<'a> + 'static {
+    /// Convert a vector of `f64` values to this element type.
+    /// Returns the converted values and the scale factor needed to recover the original values.
+    fn quantize(vec: &[f64]) -> (Vec<Self>, f32);
+
+    /// Recover the (approximate) original value given the scale factor of the vector.
+    fn dequantize(self, scale: f32) -> f32;
+}
+
+impl Element for f32 {
+    fn quantize(vec: &[f64]) -> (Vec<Self>, f32) {
+        (vec.iter().map(|x| *x as f32).collect(), 1.0)
+    }
+
+    fn dequantize(self, _scale: f32) -> f32 {
+        self
+    }
+}
+
+impl Element for f16 {
+    fn quantize(vec: &[f64]) -> (Vec<Self>, f32) {
+        (vec.iter().map(|x| f16::from_f64(*x)).collect(), 1.0)
+    }
+
+    fn dequantize(self, _scale: f32) -> f32 {
+        self.to_f32()
+    }
+}
+
+impl Element for i8 {
+    fn quantize(vec: &[f64]) -> (Vec<Self>, f32) {
+        let max = vec.iter().fold(0.0_f64, |max, x| max.max(x.abs()));
+        let scale = if max == 0.0 { 1.0 } else { max / 127.0 };
+
+        (
+            vec.iter()
+                .map(|x| (x / scale).round().clamp(-127.0, 127.0) as i8)
+                .collect(),
+            scale as f32,
+        )
+    }
+
+    fn dequantize(self, scale: f32) -> f32 {
+        self as f32 * scale
+    }
+}
+
+/// Struct that holds a single document and its embedding stored as a vector of `E`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct QuantizedEmbedding<E: Element> {
+    /// The document that was embedded. Used for debugging.
+    pub document: String,
+    /// The (possibly quantized) embedding vector
+    pub vec: Vec<E>,
+    /// Scale factor used to recover the original values (1.0 for float types)
+    pub scale: f32,
+}
+
+impl<E: Element> QuantizedEmbedding<E> {
+    /// Iterate over the (dequantized) values of the embedding vector.
+    pub fn values(&self) -> impl Iterator<Item = f32> + '_ {
+        self.vec.iter().map(|x| x.dequantize(self.scale))
+    }
+
+    /// Convert back to an [Embedding] (i.e.: a vector of `f64`).
+    pub fn to_embedding(&self) -> Embedding {
+        Embedding {
+            document: self.document.clone(),
+            vec: self.values().map(|x| x as f64).collect(),
+        }
+    }
+
+    /// Size in bytes of the embedding vector.
+    pub fn vec_size(&self) -> usize {
+        self.vec.len() * std::mem::size_of::<E>()
+    }
+}
+
+impl<E: Element> From<&Embedding> for QuantizedEmbedding<E> {
+    fn from(embedding: &Embedding) -> Self {
+        let (vec, scale) = E::quantize(&embedding.vec);
+        Self {
+            document: embedding.document.clone(),
+            vec,
+            scale,
+        }
+    }
+}
+
+impl<E: Element> From<Embedding> for QuantizedEmbedding<E> {
+    fn from(embedding: Embedding) -> Self {
+        (&embedding).into()
+    }
+}
+
+impl<E: Element> PartialEq for QuantizedEmbedding<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.document == other.document
+    }
+}
+
+impl<E: Element> Eq for QuantizedEmbedding<E> {}
+
+impl Embedding {
+    /// Convert the embedding to a compact representation using `E` as element type.
+    pub fn quantize<E: Element>(&self) -> QuantizedEmbedding<E> {
+        self.into()
+    }
+}
+
+impl<E: Element> VectorDistance for QuantizedEmbedding<E> {
+    fn dot_product(&self, other: &Self) -> f64 {
+        self.values()
+            .zip(other.values())
+            .map(|(x, y)| x as f64 * y as f64)
+            .sum()
+    }
+
+    fn cosine_similarity(&self, other: &Self, normalized: bool) -> f64 {
+        let dot_product = self.dot_product(other);
+
+        if normalized {
+            dot_product
+        } else {
+            let magnitude1: f64 = self
+                .values()
+                .map(|x| (x as f64).powi(2))
+                .sum::<f64>()
+                .sqrt();
+            let magnitude2: f64 = other
+                .values()
+                .map(|x| (x as f64).powi(2))
+                .sum::<f64>()
+                .sqrt();
+
+            dot_product / (magnitude1 * magnitude2)
+        }
+    }
+
+    fn angular_distance(&self, other: &Self, normalized: bool) -> f64 {
+        let cosine_sim = self.cosine_similarity(other, normalized);
+        cosine_sim.acos() / std::f64::consts::PI
+    }
+
+    fn euclidean_distance(&self, other: &Self) -> f64 {
+        self.values()
+            .zip(other.values())
+            .map(|(x, y)| (x as f64 - y as f64).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    fn manhattan_distance(&self, other: &Self) -> f64 {
+        self.values()
+            .zip(other.values())
+            .map(|(x, y)| (x as f64 - y as f64).abs())
+            .sum()
+    }
+
+    fn chebyshev_distance(&self, other: &Self) -> f64 {
+        self.values()
+            .zip(other.values())
+            .map(|(x, y)| (x as f64 - y as f64).abs())
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Trait for embedding representations that can be stored in a vector store and compared
+/// against a query [Embedding].
+pub trait EmbeddingVector: Clone + Send + Sync {
+    /// The document that was embedded.
+    fn document(&self) -> &str;
+
+    /// Cosine similarity between this embedding and the `query` embedding.
+    fn similarity(&self, query: &Embedding) -> f64;
+}
+
+impl EmbeddingVector for Embedding {
+    fn document(&self) -> &str {
+        &self.document
+    }
+
+    fn similarity(&self, query: &Embedding) -> f64 {
+        self.cosine_similarity(query, false)
+    }
+}
+
+impl<E: Element> EmbeddingVector for QuantizedEmbedding<E> {
+    fn document(&self) -> &str {
+        &self.document
+    }
+
+    fn similarity(&self, query: &Embedding) -> f64 {
+        let (dot_product, magnitude1, magnitude2) =
+            self.values()
+                .zip(query.vec.iter())
+                .fold((0.0, 0.0, 0.0), |(dot, m1, m2), (x, y)| {
+                    let x = x as f64;
+                    (dot + x * y, m1 + x * x, m2 + y * y)
+                });
+
+        dot_product / (magnitude1.sqrt() * magnitude2.sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embedding() -> Embedding {
+        Embedding {
+            document: "test".to_string(),
+            vec: vec![0.1, -0.5, 0.9],
+        }
+    }
+
+    #[test]
+    fn test_quantize_f32() {
+        let quantized: QuantizedEmbedding<f32> = embedding().quantize();
+
+        assert_eq!(quantized.vec, vec![0.1, -0.5, 0.9]);
+        assert_eq!(quantized.vec_size(), 12);
+    }
+
+    #[test]
+    fn test_quantize_i8() {
+        let quantized: QuantizedEmbedding<i8> = embedding().quantize();
+
+        assert_eq!(quantized.vec, vec![14, -71, 127]);
+        assert_eq!(quantized.vec_size(), 3);
+
+        let restored = quantized.to_embedding();
+        restored
+            .vec
+            .iter()
+            .zip(embedding().vec.iter())
+            .for_each(|(x, y)| assert!((x - y).abs() < 0.01));
+    }
+
+    #[test]
+    fn test_quantize_f16() {
+        let quantized: QuantizedEmbedding<f16> = embedding().quantize();
+
+        assert_eq!(quantized.vec_size(), 6);
+        quantized
+            .values()
+            .zip(embedding().vec.iter())
+            .for_each(|(x, y)| assert!((x as f64 - y).abs() < 0.001));
+    }
+
+    #[test]
+    fn test_similarity_matches_full_precision() {
+        let query = Embedding {
+            document: "query".to_string(),
+            vec: vec![0.0, 0.1, 0.6],
+        };
+
+        let full = embedding().similarity(&query);
+        let f32_sim = embedding().quantize::<f32>().similarity(&query);
+        let i8_sim = embedding().quantize::<i8>().similarity(&query);
+
+        assert!((full - f32_sim).abs() < 1e-6);
+        assert!((full - i8_sim).abs() < 1e-2);
+    }
+}
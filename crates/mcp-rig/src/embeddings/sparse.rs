@@ -0,0 +1,250 @@
+//! The module defines the [SparseEmbeddingModel] trait, which represents a model that generates
+//! sparse (i.e.: lexical, SPLADE-style) embeddings, and the [SparseEmbedding] struct.
+//!
+//! Sparse embeddings map a document to weights over the model's vocabulary, which makes them
+//! much better than dense embeddings at matching rare keywords (product codes, names, etc.).
+//! Combined with a dense embedding (see [HybridEmbedding] and [HybridEmbeddingModel]), they
+//! can be indexed by hybrid retrieval backends (e.g.: Qdrant, or the
+//! [InMemoryHybridIndex](crate::vector_store::in_memory_store::InMemoryHybridIndex)) which
+//! implement the [HybridVectorStoreIndex](crate::vector_store::hybrid::HybridVectorStoreIndex) trait.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     embeddings::sparse::HybridEmbeddingModel,
+//!     providers::{local, openai},
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//! let local = local::Client::new();
+//!
+//! let model = HybridEmbeddingModel::new(
+//!     openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL),
+//!     local.sparse_embedding_model(local::SPLADE_PP_EN_V1)?,
+//! );
+//!
+//! let embeddings = model.embed_texts(vec!["Hello, world!".to_string()]).await?;
+//! ```
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Embedding, EmbeddingError, EmbeddingModel};
+
+/// Trait for embedding models that can generate sparse embeddings for documents.
+pub trait SparseEmbeddingModel: Clone + Sync + Send {
+    /// The maximum number of documents that can be embedded in a single request.
+    const MAX_DOCUMENTS: usize;
+
+    /// Embed multiple text documents in a single request
+    fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> impl std::future::Future<Output = Result<Vec<SparseEmbedding>, EmbeddingError>> + Send;
+
+    /// Embed a single text document.
+    fn embed_text(
+        &self,
+        text: &str,
+    ) -> impl std::future::Future<Output = Result<SparseEmbedding, EmbeddingError>> + Send {
+        async {
+            Ok(self
+                .embed_texts(vec![text.to_string()])
+                .await?
+                .pop()
+                .expect("There should be at least one embedding"))
+        }
+    }
+}
+
+/// Struct that holds a single document and its sparse embedding.
+/// `indices` and `values` have the same length: `values[i]` is the weight of
+/// the dimension `indices[i]`. Dimensions that are not listed have a weight of zero.
+#[derive(Clone, Default, Deserialize, Serialize, Debug)]
+pub struct SparseEmbedding {
+    /// The document that was embedded. Used for debugging.
+    pub document: String,
+    /// The indices of the non-zero dimensions
+    pub indices: Vec<u32>,
+    /// The weights of the non-zero dimensions
+    pub values: Vec<f32>,
+}
+
+impl SparseEmbedding {
+    /// Number of non-zero dimensions.
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Dot product between two sparse embeddings.
+    pub fn dot_product(&self, other: &Self) -> f64 {
+        let weights = self
+            .indices
+            .iter()
+            .zip(self.values.iter())
+            .collect::<HashMap<_, _>>();
+
+        other
+            .indices
+            .iter()
+            .zip(other.values.iter())
+            .filter_map(|(index, value)| {
+                weights
+                    .get(index)
+                    .map(|weight| **weight as f64 * *value as f64)
+            })
+            .sum()
+    }
+}
+
+impl PartialEq for SparseEmbedding {
+    fn eq(&self, other: &Self) -> bool {
+        self.document == other.document
+    }
+}
+
+impl Eq for SparseEmbedding {}
+
+/// Struct that holds a dense and a sparse embedding of the same document.
+#[derive(Clone, Default, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct HybridEmbedding {
+    pub dense: Embedding,
+    pub sparse: SparseEmbedding,
+}
+
+/// Model that generates [HybridEmbedding]s by combining a dense [EmbeddingModel]
+/// with a [SparseEmbeddingModel].
+#[derive(Clone)]
+pub struct HybridEmbeddingModel<D: EmbeddingModel, S: SparseEmbeddingModel> {
+    pub dense: D,
+    pub sparse: S,
+}
+
+impl<D: EmbeddingModel, S: SparseEmbeddingModel> HybridEmbeddingModel<D, S> {
+    pub fn new(dense: D, sparse: S) -> Self {
+        Self { dense, sparse }
+    }
+
+    /// Number of dimensions of the dense embeddings.
+    pub fn ndims(&self) -> usize {
+        self.dense.ndims()
+    }
+
+    /// Embed multiple text documents with both models concurrently.
+    pub async fn embed_texts(
+        &self,
+        texts: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<HybridEmbedding>, EmbeddingError> {
+        let texts = texts.into_iter().collect::<Vec<_>>();
+
+        let (dense, sparse) = futures::try_join!(
+            self.dense.embed_texts(texts.clone()),
+            self.sparse.embed_texts(texts.clone())
+        )?;
+
+        if dense.len() != texts.len() || sparse.len() != texts.len() {
+            return Err(EmbeddingError::ResponseError(
+                "Response data length does not match input length".into(),
+            ));
+        }
+
+        Ok(dense
+            .into_iter()
+            .zip(sparse)
+            .map(|(dense, sparse)| HybridEmbedding { dense, sparse })
+            .collect())
+    }
+
+    /// Embed a single text document with both models concurrently.
+    pub async fn embed_text(&self, text: &str) -> Result<HybridEmbedding, EmbeddingError> {
+        Ok(self
+            .embed_texts(vec![text.to_string()])
+            .await?
+            .pop()
+            .expect("There should be at least one embedding"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Dense;
+
+    impl EmbeddingModel for Dense {
+        const MAX_DOCUMENTS: usize = 5;
+
+        fn ndims(&self) -> usize {
+            2
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|document| Embedding {
+                    document,
+                    vec: vec![1.0, 0.0],
+                })
+                .collect())
+        }
+    }
+
+    #[derive(Clone)]
+    struct Sparse;
+
+    impl SparseEmbeddingModel for Sparse {
+        const MAX_DOCUMENTS: usize = 5;
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<SparseEmbedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|document| SparseEmbedding {
+                    indices: vec![document.len() as u32],
+                    values: vec![1.0],
+                    document,
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_sparse_dot_product() {
+        let a = SparseEmbedding {
+            document: "a".to_string(),
+            indices: vec![1, 5, 9],
+            values: vec![0.5, 2.0, 1.0],
+        };
+        let b = SparseEmbedding {
+            document: "b".to_string(),
+            indices: vec![9, 2, 5],
+            values: vec![3.0, 7.0, 0.5],
+        };
+
+        assert_eq!(a.dot_product(&b), 4.0);
+        assert_eq!(b.dot_product(&a), 4.0);
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_embedding_model() {
+        let model = HybridEmbeddingModel::new(Dense, Sparse);
+
+        let embedding = model.embed_text("hello").await.unwrap();
+
+        assert_eq!(model.ndims(), 2);
+        assert_eq!(embedding.dense.document, "hello");
+        assert_eq!(embedding.dense.vec, vec![1.0, 0.0]);
+        assert_eq!(embedding.sparse.document, "hello");
+        assert_eq!(embedding.sparse.indices, vec![5]);
+    }
+}
@@ -0,0 +1,468 @@
+//! This module provides an evaluation harness for agents, e.g.: to regression-test changes to
+//! their preamble or prompts.
+//!
+//! An [EvalSuite] is a list of [EvalCase]s (an input, and optionally an expected output and
+//! criteria the output should meet) and a list of [Scorer]s. Running the suite prompts the agent
+//! with the input of each case and scores its output with every scorer, producing an
+//! [EvalReport].
+//!
+//! The following scorers are provided (see the [scorers] module):
+//! - [ExactMatch](scorers::ExactMatch): the output is the expected output,
+//! - [RegexMatch](scorers::RegexMatch): the output matches a regular expression,
+//! - [EmbeddingSimilarity](scorers::EmbeddingSimilarity): the output is semantically close to
+//!   the expected output,
+//! - [LlmJudge](scorers::LlmJudge): a model grades the output against the expected output
+//!   and the criteria of the case.
+//!
+//! Reports can be saved (they are serializable) and compared to a baseline with
+//! [EvalReport::regressions].
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     evals::{scorers::{LlmJudge, RegexMatch}, EvalCase, EvalSuite},
+//!     providers::openai,
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//! let agent = openai
+//!     .agent(openai::GPT_4O)
+//!     .preamble("You are a support agent for Acme. Always answer politely.")
+//!     .build();
+//!
+//! let report = EvalSuite::new("support")
+//!     .case(
+//!         EvalCase::new("refund", "How do I get a refund?")
+//!             .expected("Refunds can be requested from the Orders page within 30 days.")
+//!             .criterion("Mentions the 30 days limit"),
+//!     )
+//!     .case(EvalCase::new("greeting", "Hi!").criterion("Greets the user"))
+//!     .scorer(RegexMatch::new(r"(?i)hello|hi|thanks")?)
+//!     .scorer(LlmJudge::new(openai.completion_model(openai::GPT_4O)).threshold(0.7))
+//!     .run(&agent)
+//!     .await;
+//!
+//! println!("{report}");
+//! assert!(report.pass_rate() >= 0.9);
+//! ```
+use std::{fmt, future::Future, time::Duration};
+
+use futures::{future::BoxFuture, stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+use crate::{
+    completion::{Prompt, PromptError},
+    embeddings::EmbeddingError,
+    extractor::ExtractionError,
+};
+
+pub mod scorers;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EvalError {
+    /// The scorer requires an expected output but the case has none
+    #[error("Case {0} has no expected output")]
+    MissingExpected(String),
+
+    #[error("EmbeddingError: {0}")]
+    EmbeddingError(#[from] EmbeddingError),
+
+    #[error("ExtractionError: {0}")]
+    ExtractionError(#[from] ExtractionError),
+
+    #[error("PromptError: {0}")]
+    PromptError(#[from] PromptError),
+}
+
+/// Test case of an [EvalSuite].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EvalCase {
+    /// Name of the case, used to identify it in reports
+    pub name: String,
+    /// Prompt sent to the agent
+    pub input: String,
+    /// Expected output (reference answer) of the agent, if any
+    pub expected: Option<String>,
+    /// Criteria the output of the agent should meet (used by the LLM judge)
+    pub criteria: Vec<String>,
+}
+
+impl EvalCase {
+    pub fn new(name: &str, input: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            input: input.to_string(),
+            expected: None,
+            criteria: vec![],
+        }
+    }
+
+    /// Set the expected output of the case.
+    pub fn expected(mut self, expected: &str) -> Self {
+        self.expected = Some(expected.to_string());
+        self
+    }
+
+    /// Add a criterion the output should meet.
+    pub fn criterion(mut self, criterion: &str) -> Self {
+        self.criteria.push(criterion.to_string());
+        self
+    }
+
+    /// Expected output of the case, or an error if it has none.
+    pub(crate) fn expected_or_err(&self) -> Result<&str, EvalError> {
+        self.expected
+            .as_deref()
+            .ok_or_else(|| EvalError::MissingExpected(self.name.clone()))
+    }
+}
+
+/// Score given by a [Scorer] to the output of a case.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Score {
+    /// Name of the scorer
+    pub scorer: String,
+    /// Score, between 0 and 1
+    pub value: f64,
+    /// Whether the output passed the scorer's threshold
+    pub passed: bool,
+    /// Explanation of the score (e.g.: reasoning of the LLM judge, scorer error)
+    pub reason: Option<String>,
+}
+
+impl Score {
+    /// Binary score: 1 if `passed`, 0 otherwise.
+    pub fn binary(scorer: &str, passed: bool) -> Self {
+        Self {
+            scorer: scorer.to_string(),
+            value: if passed { 1.0 } else { 0.0 },
+            passed,
+            reason: None,
+        }
+    }
+
+    /// Score of `value`, passing if it is at least `threshold`.
+    pub fn threshold(scorer: &str, value: f64, threshold: f64) -> Self {
+        Self {
+            scorer: scorer.to_string(),
+            value,
+            passed: value >= threshold,
+            reason: None,
+        }
+    }
+
+    pub fn reason(mut self, reason: &str) -> Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+}
+
+/// Trait for scorers of the outputs of eval cases.
+pub trait Scorer: Send + Sync {
+    /// Name of the scorer, used to identify its scores in reports
+    fn name(&self) -> String;
+
+    /// Score the `output` of the agent for `case`.
+    fn score(
+        &self,
+        case: &EvalCase,
+        output: &str,
+    ) -> impl Future<Output = Result<Score, EvalError>> + Send;
+}
+
+/// Wrapper trait to allow for dynamic dispatch of scorers
+pub(crate) trait ScorerDyn: Send + Sync {
+    fn name(&self) -> String;
+
+    fn score<'a>(
+        &'a self,
+        case: &'a EvalCase,
+        output: &'a str,
+    ) -> BoxFuture<'a, Result<Score, EvalError>>;
+}
+
+impl<T: Scorer> ScorerDyn for T {
+    fn name(&self) -> String {
+        <Self as Scorer>::name(self)
+    }
+
+    fn score<'a>(
+        &'a self,
+        case: &'a EvalCase,
+        output: &'a str,
+    ) -> BoxFuture<'a, Result<Score, EvalError>> {
+        Box::pin(<Self as Scorer>::score(self, case, output))
+    }
+}
+
+/// Suite of eval cases, scored by a list of scorers. See the [module documentation](self).
+pub struct EvalSuite {
+    name: String,
+    cases: Vec<EvalCase>,
+    scorers: Vec<Box<dyn ScorerDyn>>,
+    concurrency: usize,
+}
+
+impl EvalSuite {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            cases: vec![],
+            scorers: vec![],
+            concurrency: 1,
+        }
+    }
+
+    /// Add a case to the suite.
+    pub fn case(mut self, case: EvalCase) -> Self {
+        self.cases.push(case);
+        self
+    }
+
+    /// Add multiple cases to the suite.
+    pub fn cases(mut self, cases: impl IntoIterator<Item = EvalCase>) -> Self {
+        self.cases.extend(cases);
+        self
+    }
+
+    /// Add a scorer to the suite.
+    pub fn scorer(mut self, scorer: impl Scorer + 'static) -> Self {
+        self.scorers.push(Box::new(scorer));
+        self
+    }
+
+    /// Set the number of cases run concurrently (1 by default).
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Run every case of the suite against `agent` and score the outputs.
+    /// Errors (of the agent or of the scorers) do not stop the run: they are recorded in the
+    /// results of the cases, which then fail.
+    pub async fn run(&self, agent: &impl Prompt) -> EvalReport {
+        let start = Instant::now();
+        let results = stream::iter(&self.cases)
+            .map(|case| self.run_case(agent, case))
+            .buffered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await;
+
+        EvalReport {
+            suite: self.name.clone(),
+            results,
+            duration: start.elapsed(),
+        }
+    }
+
+    async fn run_case(&self, agent: &impl Prompt, case: &EvalCase) -> CaseResult {
+        let start = Instant::now();
+        let output = agent.prompt(case.input.as_str()).await;
+        let duration = start.elapsed();
+
+        let (output, error, scores) = match output {
+            Ok(output) => {
+                let scores = score_output(&self.scorers, case, &output).await;
+                (Some(output), None, scores)
+            }
+            Err(e) => (None, Some(e.to_string()), vec![]),
+        };
+
+        CaseResult {
+            case: case.name.clone(),
+            input: case.input.clone(),
+            output,
+            error,
+            scores,
+            duration,
+        }
+    }
+}
+
+/// Scores of the `output` of the agent for `case` by every scorer of `scorers`. The errors of
+/// the scorers are failing scores.
+pub(crate) async fn score_output(
+    scorers: &[Box<dyn ScorerDyn>],
+    case: &EvalCase,
+    output: &str,
+) -> Vec<Score> {
+    futures::future::join_all(scorers.iter().map(|scorer| async {
+        scorer.score(case, output).await.unwrap_or_else(|e| {
+            Score::binary(&scorer.name(), false).reason(&format!("Scorer error: {e}"))
+        })
+    }))
+    .await
+}
+
+/// Result of an eval case.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CaseResult {
+    /// Name of the case
+    pub case: String,
+    pub input: String,
+    /// Output of the agent, if it answered
+    pub output: Option<String>,
+    /// Error of the agent, if it failed to answer
+    pub error: Option<String>,
+    pub scores: Vec<Score>,
+    /// Time taken by the agent to answer
+    pub duration: Duration,
+}
+
+impl CaseResult {
+    /// Whether the agent answered and the output passed every scorer.
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.scores.iter().all(|score| score.passed)
+    }
+
+    /// Score given by the scorer named `scorer`, if any.
+    pub fn score(&self, scorer: &str) -> Option<&Score> {
+        self.scores.iter().find(|score| score.scorer == scorer)
+    }
+}
+
+/// Report of the run of an [EvalSuite].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EvalReport {
+    /// Name of the suite
+    pub suite: String,
+    /// Results of the cases, in the order of the suite
+    pub results: Vec<CaseResult>,
+    /// Total duration of the run
+    pub duration: Duration,
+}
+
+impl EvalReport {
+    /// Number of cases that passed.
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|result| result.passed()).count()
+    }
+
+    /// Results of the cases that failed.
+    pub fn failures(&self) -> Vec<&CaseResult> {
+        self.results
+            .iter()
+            .filter(|result| !result.passed())
+            .collect()
+    }
+
+    /// Fraction of the cases that passed (1 if the suite is empty).
+    pub fn pass_rate(&self) -> f64 {
+        if self.results.is_empty() {
+            1.0
+        } else {
+            self.passed() as f64 / self.results.len() as f64
+        }
+    }
+
+    /// Mean score given by the scorer named `scorer`, if it scored any case.
+    pub fn mean_score(&self, scorer: &str) -> Option<f64> {
+        let scores = self
+            .results
+            .iter()
+            .filter_map(|result| result.score(scorer))
+            .map(|score| score.value)
+            .collect::<Vec<_>>();
+
+        (!scores.is_empty()).then(|| scores.iter().sum::<f64>() / scores.len() as f64)
+    }
+
+    /// Names of the cases that passed in `baseline` (e.g.: the report of the previous version
+    /// of the preamble) but fail in this report.
+    pub fn regressions<'a>(&'a self, baseline: &EvalReport) -> Vec<&'a str> {
+        self.results
+            .iter()
+            .filter(|result| !result.passed())
+            .filter(|result| {
+                baseline
+                    .results
+                    .iter()
+                    .any(|base| base.case == result.case && base.passed())
+            })
+            .map(|result| result.case.as_str())
+            .collect()
+    }
+}
+
+impl fmt::Display for EvalReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Suite {}: {}/{} passed ({:.1}%) in {:.2?}",
+            self.suite,
+            self.passed(),
+            self.results.len(),
+            self.pass_rate() * 100.0,
+            self.duration
+        )?;
+
+        for result in &self.results {
+            let status = if result.passed() { "PASS" } else { "FAIL" };
+            write!(f, "  [{status}] {}", result.case)?;
+            if let Some(error) = &result.error {
+                write!(f, " (error: {error})")?;
+            }
+            writeln!(f)?;
+
+            for score in &result.scores {
+                write!(f, "    {}: {:.2}", score.scorer, score.value)?;
+                if let Some(reason) = &score.reason {
+                    write!(f, " - {reason}")?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scorers::ExactMatch, *};
+    use crate::providers::mock::{self, MockResponse};
+
+    #[tokio::test]
+    async fn test_eval_suite() {
+        let model = mock::CompletionModel::new()
+            .when_prompt_contains("capital", MockResponse::text("Paris"))
+            .when_prompt_contains("boom", MockResponse::error("model overloaded"))
+            .default_response(MockResponse::text("I don't know"));
+        let agent = model.agent().build();
+
+        let suite = EvalSuite::new("geography")
+            .case(EvalCase::new("capital", "What is the capital of France?").expected("paris"))
+            .case(EvalCase::new("unknown", "What is a flurbo?").expected("A currency"))
+            .case(EvalCase::new("no-expected", "Hello"))
+            .case(EvalCase::new("error", "boom").expected("Paris"))
+            .scorer(ExactMatch::new().case_insensitive())
+            .concurrency(2);
+
+        let report = suite.run(&agent).await;
+        assert_eq!(report.results.len(), 4);
+        assert_eq!(report.passed(), 1);
+        assert_eq!(report.pass_rate(), 0.25);
+        assert_eq!(report.mean_score("exact_match"), Some(1.0 / 3.0));
+
+        let results = &report.results;
+        assert_eq!(results[0].case, "capital");
+        assert!(results[0].passed());
+        assert_eq!(results[1].output.as_deref(), Some("I don't know"));
+        assert!(!results[1].passed());
+        assert!(results[2].scores[0]
+            .reason
+            .as_deref()
+            .unwrap()
+            .contains("no expected output"));
+        assert!(results[3].error.is_some());
+        assert!(results[3].scores.is_empty());
+
+        let mut baseline = report.clone();
+        baseline.results[1].scores[0].passed = true;
+        assert_eq!(report.regressions(&baseline), ["unknown"]);
+
+        let display = report.to_string();
+        assert!(display.starts_with("Suite geography: 1/4 passed (25.0%)"));
+        assert!(display.contains("[FAIL] error (error: "));
+    }
+}
@@ -0,0 +1,282 @@
+//! Scorers of the outputs of eval cases. See the [evals](super) module.
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{EvalCase, EvalError, Score, Scorer};
+use crate::{
+    completion::CompletionModel,
+    embeddings::{distance::VectorDistance, EmbeddingModel},
+    extractor::{Extractor, ExtractorBuilder},
+};
+
+/// Scorer checking that the output is the expected output of the case
+/// (after trimming surrounding whitespace).
+#[derive(Clone, Debug, Default)]
+pub struct ExactMatch {
+    case_insensitive: bool,
+}
+
+impl ExactMatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ignore the case when comparing the output to the expected output.
+    pub fn case_insensitive(mut self) -> Self {
+        self.case_insensitive = true;
+        self
+    }
+}
+
+impl Scorer for ExactMatch {
+    fn name(&self) -> String {
+        "exact_match".to_string()
+    }
+
+    async fn score(&self, case: &EvalCase, output: &str) -> Result<Score, EvalError> {
+        let (expected, output) = (case.expected_or_err()?.trim(), output.trim());
+        let passed = if self.case_insensitive {
+            expected.to_lowercase() == output.to_lowercase()
+        } else {
+            expected == output
+        };
+
+        Ok(Score::binary(&self.name(), passed))
+    }
+}
+
+/// Scorer checking that the output matches a regular expression.
+#[derive(Clone, Debug)]
+pub struct RegexMatch {
+    /// Regex to match, or `None` to use the expected output of each case as regex
+    regex: Option<Regex>,
+}
+
+impl RegexMatch {
+    /// Create a scorer matching the outputs against `pattern`.
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            regex: Some(Regex::new(pattern)?),
+        })
+    }
+
+    /// Create a scorer matching the output of each case against its expected output,
+    /// interpreted as a regex.
+    pub fn expected() -> Self {
+        Self { regex: None }
+    }
+}
+
+impl Scorer for RegexMatch {
+    fn name(&self) -> String {
+        "regex_match".to_string()
+    }
+
+    async fn score(&self, case: &EvalCase, output: &str) -> Result<Score, EvalError> {
+        let passed = match &self.regex {
+            Some(regex) => regex.is_match(output),
+            None => match Regex::new(case.expected_or_err()?) {
+                Ok(regex) => regex.is_match(output),
+                Err(e) => {
+                    return Ok(
+                        Score::binary(&self.name(), false).reason(&format!("Invalid regex: {e}"))
+                    )
+                }
+            },
+        };
+
+        Ok(Score::binary(&self.name(), passed))
+    }
+}
+
+/// Scorer computing the cosine similarity between the embeddings of the output and of the
+/// expected output of the case. The output passes if the similarity is at least the threshold
+/// (0.8 by default).
+#[derive(Clone)]
+pub struct EmbeddingSimilarity<M: EmbeddingModel> {
+    model: M,
+    threshold: f64,
+}
+
+impl<M: EmbeddingModel> EmbeddingSimilarity<M> {
+    pub fn new(model: M) -> Self {
+        Self {
+            model,
+            threshold: 0.8,
+        }
+    }
+
+    /// Set the minimum similarity for the output to pass.
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+}
+
+impl<M: EmbeddingModel> Scorer for EmbeddingSimilarity<M> {
+    fn name(&self) -> String {
+        "embedding_similarity".to_string()
+    }
+
+    async fn score(&self, case: &EvalCase, output: &str) -> Result<Score, EvalError> {
+        let expected = case.expected_or_err()?;
+        let embeddings = self
+            .model
+            .embed_texts([output.to_string(), expected.to_string()])
+            .await?;
+
+        let similarity = match &embeddings[..] {
+            [output, expected] => output.cosine_similarity(expected, false),
+            _ => 0.0,
+        };
+
+        Ok(Score::threshold(&self.name(), similarity, self.threshold))
+    }
+}
+
+/// Verdict of the LLM judge
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Verdict {
+    /// Grade of the answer, between 0 (completely wrong) and 1 (perfect)
+    pub score: f64,
+    /// Short explanation of the grade
+    pub reasoning: String,
+}
+
+const JUDGE_PREAMBLE: &str = "\
+    You are an impartial judge grading the answer of an AI assistant to a question.\n\
+    Grade the answer between 0 (completely wrong or unhelpful) and 1 (perfect), taking into \
+    account the reference answer and the criteria (if any), and briefly explain your grade.\
+";
+
+/// Scorer asking a model to grade the output against the expected output and the criteria
+/// of the case. The output passes if its grade is at least the threshold (0.7 by default).
+pub struct LlmJudge<M: CompletionModel> {
+    extractor: Extractor<M, Verdict>,
+    threshold: f64,
+}
+
+impl<M: CompletionModel> LlmJudge<M> {
+    /// Create a judge using `model`.
+    pub fn new(model: M) -> Self {
+        Self::with_instructions(model, "")
+    }
+
+    /// Create a judge using `model` with additional grading instructions
+    /// (e.g.: "Penalize answers longer than 3 sentences").
+    pub fn with_instructions(model: M, instructions: &str) -> Self {
+        let mut builder = ExtractorBuilder::new(model).preamble(JUDGE_PREAMBLE);
+        if !instructions.is_empty() {
+            builder = builder.preamble(instructions);
+        }
+
+        Self {
+            extractor: builder.build(),
+            threshold: 0.7,
+        }
+    }
+
+    /// Set the minimum grade for the output to pass.
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    fn prompt(case: &EvalCase, output: &str) -> String {
+        let mut prompt = format!("Question:\n{}\n\n", case.input);
+        if let Some(expected) = &case.expected {
+            prompt.push_str(&format!("Reference answer:\n{expected}\n\n"));
+        }
+        if !case.criteria.is_empty() {
+            prompt.push_str("Criteria:\n");
+            case.criteria
+                .iter()
+                .for_each(|criterion| prompt.push_str(&format!("- {criterion}\n")));
+            prompt.push('\n');
+        }
+        prompt.push_str(&format!("Answer to grade:\n{output}"));
+        prompt
+    }
+}
+
+impl<M: CompletionModel> Scorer for LlmJudge<M> {
+    fn name(&self) -> String {
+        "llm_judge".to_string()
+    }
+
+    async fn score(&self, case: &EvalCase, output: &str) -> Result<Score, EvalError> {
+        let verdict = self.extractor.extract(&Self::prompt(case, output)).await?;
+        let score = verdict.score.clamp(0.0, 1.0);
+
+        Ok(Score::threshold(&self.name(), score, self.threshold).reason(&verdict.reasoning))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::providers::mock::{self, MockResponse};
+
+    #[tokio::test]
+    async fn test_scorers() {
+        let case = EvalCase::new("capital", "What is the capital of France?").expected("Paris");
+
+        let score = ExactMatch::new().score(&case, " Paris\n").await.unwrap();
+        assert!(score.passed);
+        let score = ExactMatch::new().score(&case, "paris").await.unwrap();
+        assert!(!score.passed);
+
+        let score = RegexMatch::new(r"(?i)\bparis\b")
+            .unwrap()
+            .score(&case, "It is paris.")
+            .await
+            .unwrap();
+        assert!(score.passed);
+        let score = RegexMatch::expected().score(&case, "Lyon").await.unwrap();
+        assert!(!score.passed);
+
+        let model = mock::EmbeddingModel::new(2)
+            .embedding("Paris", vec![1.0, 0.0])
+            .embedding("It is Paris", vec![0.8, 0.6]);
+        let score = EmbeddingSimilarity::new(model)
+            .threshold(0.9)
+            .score(&case, "It is Paris")
+            .await
+            .unwrap();
+        assert!((score.value - 0.8).abs() < 1e-9);
+        assert!(!score.passed);
+
+        assert!(matches!(
+            ExactMatch::new()
+                .score(&EvalCase::new("greeting", "Hi!"), "Hello")
+                .await,
+            Err(EvalError::MissingExpected(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_llm_judge() {
+        let model = mock::CompletionModel::new().push(MockResponse::tool_call(
+            "submit",
+            json!({"score": 0.9, "reasoning": "Correct, but terse"}),
+        ));
+
+        let case = EvalCase::new("capital", "What is the capital of France?")
+            .expected("Paris")
+            .criterion("Answers in one word");
+        let score = LlmJudge::new(model.clone())
+            .score(&case, "Paris")
+            .await
+            .unwrap();
+        assert!(score.passed);
+        assert_eq!(score.value, 0.9);
+        assert_eq!(score.reason.as_deref(), Some("Correct, but terse"));
+
+        let prompt = model.requests()[0].prompt_text().unwrap();
+        assert!(prompt.contains("Reference answer:\nParis"));
+        assert!(prompt.contains("- Answers in one word"));
+    }
+}
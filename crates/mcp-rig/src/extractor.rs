@@ -27,17 +27,86 @@
 //!     .await
 //!     .expect("Failed to extract data from text");
 //! ```
+//!
+//! # Retries
+//! If the data submitted by the model cannot be deserialized into the target structure, or if
+//! it is rejected by one of the extractor's validators, the error can be fed back to the model so
+//! that it can correct its submission:
+//! ```
+//! let extractor = openai.extractor::<Person>(openai::GPT_4O)
+//!     .validate(|person: &Person| match person.age {
+//!         Some(age) if age > 150 => Err(format!("Invalid age: {age}")),
+//!         _ => Ok(()),
+//!     })
+//!     .retries(2)
+//!     .build();
+//! ```
+//!
+//! # Confidence scoring
+//! The model can also be asked to rate its confidence in the extracted data, e.g.: to route
+//! uncertain extractions to human review:
+//! ```
+//! let extractor = openai.extractor::<Person>(openai::GPT_4O)
+//!     .with_confidence()
+//!     .build();
+//!
+//! let extraction = extractor.extract_with_confidence("John Doe is a doctor.").await?;
+//! if !extraction.is_confident(0.8) {
+//!     println!("Needs review: {:?}", extraction.low_confidence_fields(0.8));
+//! }
+//! ```
+//!
+//! # Schema customization
+//! The JSON schema generated for the target structure can be adjusted before it is sent to the
+//! model, e.g.: to add field descriptions or to strip constructs the provider does not support
+//! (see the [schema] module):
+//! ```
+//! use mcp_rig::extractor::schema;
+//!
+//! let extractor = gemini.extractor::<Person>(gemini::completion::GEMINI_1_5_FLASH)
+//!     .field_description("age", "Age of the person in years")
+//!     .rename_field("profession", "job_title")
+//!     .map_schema(|schema| {
+//!         schema::inline_refs(schema);
+//!         schema::remove_keys(schema, &["$schema", "title", "additionalProperties"]);
+//!     })
+//!     .build();
+//! ```
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
 
-use std::marker::PhantomData;
+use async_stream::stream;
+use futures::{
+    future::{self, BoxFuture},
+    stream, Stream, StreamExt,
+};
+use futures_timer::Delay;
 
 use schemars::{schema_for, JsonSchema};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use web_time::Instant;
 
 use crate::{
     agent::{Agent, AgentBuilder},
-    completion::{CompletionModel, Prompt, PromptError, ToolDefinition},
-    tool::Tool,
+    completion::{
+        Chat, Completion, CompletionError, CompletionModel, Message, PromptError, ToolDefinition,
+    },
+    json_utils,
+    message::{Image, UserContent},
+    streaming::{StreamingChoice, StreamingCompletionModel},
+    tool::{Tool, ToolError, ToolSetError},
+    OneOrMany,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -48,13 +117,89 @@ pub enum ExtractionError {
     #[error("Failed to deserialize the extracted data: {0}")]
     DeserializationError(#[from] serde_json::Error),
 
+    #[error("Extracted data failed validation: {0}")]
+    ValidationError(String),
+
+    #[error("Unsupported input: {0}")]
+    UnsupportedInput(String),
+
+    #[error("Confidence scoring is not enabled on this extractor")]
+    ConfidenceNotEnabled,
+
     #[error("PromptError: {0}")]
     PromptError(#[from] PromptError),
 }
 
+impl ExtractionError {
+    /// Whether the error is caused by invalid data submitted by the model (and can therefore
+    /// be fixed by feeding the error back to the model).
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ExtractionError::NoData
+                | ExtractionError::DeserializationError(_)
+                | ExtractionError::ValidationError(_)
+        )
+    }
+}
+
+/// Structured data extracted along with the model's confidence in it
+/// (see [Extractor::extract_with_confidence]).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Extraction<T> {
+    /// The extracted data
+    pub value: T,
+    /// Overall confidence of the model in the extracted data, between 0 and 1
+    pub confidence: f64,
+    /// Confidence of the model in each (top-level) field of the extracted data, between 0 and 1.
+    /// Fields for which the model did not report a confidence are missing.
+    pub field_confidence: HashMap<String, f64>,
+}
+
+impl<T> Extraction<T> {
+    /// Whether the overall confidence and the confidence of every field are at least `threshold`.
+    pub fn is_confident(&self, threshold: f64) -> bool {
+        self.confidence >= threshold && self.low_confidence_fields(threshold).is_empty()
+    }
+
+    /// Names of the fields whose confidence is below `threshold`, sorted alphabetically.
+    pub fn low_confidence_fields(&self, threshold: f64) -> Vec<&str> {
+        let mut fields = self
+            .field_confidence
+            .iter()
+            .filter(|(_, confidence)| **confidence < threshold)
+            .map(|(field, _)| field.as_str())
+            .collect::<Vec<_>>();
+        fields.sort();
+        fields
+    }
+}
+
+/// Submission of the `submit` tool when confidence scoring is enabled
+#[derive(Deserialize)]
+struct ConfidenceSubmission {
+    data: serde_json::Value,
+    confidence: f64,
+    #[serde(default)]
+    field_confidence: HashMap<String, f64>,
+}
+
+/// Function used to validate the extracted data.
+/// Resolves to an error message describing the problem if the data is invalid.
+pub type Validator<T> =
+    Box<dyn for<'a> Fn(&'a T) -> BoxFuture<'a, Result<(), String>> + Send + Sync>;
+
 /// Extractor for structured data from text
 pub struct Extractor<M: CompletionModel, T: JsonSchema + for<'a> Deserialize<'a> + Send + Sync> {
     agent: Agent<M>,
+    model: M,
+    /// JSON schema of `T`, generated once and shared by all extractions
+    schema: serde_json::Value,
+    /// Fields renamed in the schema, as a map from the name sent to the model to the field name
+    renames: HashMap<String, String>,
+    validators: Vec<Validator<T>>,
+    retries: usize,
+    confidence: bool,
     _t: PhantomData<T>,
 }
 
@@ -62,14 +207,380 @@ impl<T: JsonSchema + for<'a> Deserialize<'a> + Send + Sync, M: CompletionModel>
 where
     M: Sync,
 {
+    /// Extract structured data from `text`.
+    ///
+    /// If the submitted data is invalid, the error is sent back to the model and the
+    /// extraction is retried up to the number of retries configured on the extractor.
     pub async fn extract(&self, text: &str) -> Result<T, ExtractionError> {
-        let summary = self.agent.prompt(text).await?;
+        self.extract_message(Message::user(text)).await
+    }
+
+    /// Extract structured data from an image (e.g.: a receipt, a screenshot or a scanned form).
+    /// Returns [ExtractionError::UnsupportedInput] if the model does not accept image inputs.
+    pub async fn extract_image(&self, image: Image) -> Result<T, ExtractionError> {
+        self.extract_message(Message::User {
+            content: OneOrMany::many(vec![
+                UserContent::text("Extract the structured data from the following image."),
+                UserContent::Image(image),
+            ])
+            .expect("Content should not be empty"),
+        })
+        .await
+    }
+
+    /// Extract structured data from an arbitrary user message (e.g.: text and images).
+    /// Returns [ExtractionError::UnsupportedInput] if the message contains images and the
+    /// model does not accept image inputs.
+    pub async fn extract_message(
+        &self,
+        message: impl Into<Message> + Send,
+    ) -> Result<T, ExtractionError> {
+        Ok(self.run(message.into()).await?.value)
+    }
+
+    /// Extract structured data from `text` along with the model's confidence in it.
+    /// Returns [ExtractionError::ConfidenceNotEnabled] if the extractor was not built
+    /// with [ExtractorBuilder::with_confidence].
+    pub async fn extract_with_confidence(
+        &self,
+        text: &str,
+    ) -> Result<Extraction<T>, ExtractionError> {
+        if !self.confidence {
+            return Err(ExtractionError::ConfidenceNotEnabled);
+        }
+        self.run(Message::user(text)).await
+    }
+
+    async fn run(&self, mut prompt: Message) -> Result<Extraction<T>, ExtractionError> {
+        if has_images(&prompt) && !self.model.supports_vision() {
+            return Err(ExtractionError::UnsupportedInput(
+                "the completion model does not accept image inputs".to_string(),
+            ));
+        }
+
+        let mut chat_history = vec![];
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+
+            let (submission, result) =
+                match self.agent.chat(prompt.clone(), chat_history.clone()).await {
+                    Ok(submission) => {
+                        let result = self.parse(&submission).await;
+                        (Some(submission), result)
+                    }
+                    // The model called the `submit` tool with arguments that do not match the schema
+                    Err(PromptError::ToolError(ToolSetError::ToolCallError(
+                        ToolError::JsonError(e),
+                    ))) => (None, Err(ExtractionError::DeserializationError(e))),
+                    Err(e) => return Err(e.into()),
+                };
+
+            match result {
+                Ok(data) => {
+                    tracing::info!(target: "rig", "Extraction attempt {} succeeded", attempt);
+                    return Ok(data);
+                }
+                Err(e) if e.is_retryable() && attempt <= self.retries => {
+                    tracing::warn!(target: "rig",
+                        "Extraction attempt {}/{} failed: {}",
+                        attempt,
+                        self.retries + 1,
+                        e
+                    );
+
+                    chat_history.push(prompt);
+                    if let Some(submission) = submission {
+                        chat_history.push(Message::assistant(submission));
+                    }
+                    prompt = Message::user(format!(
+                        "The data you submitted is invalid: {e}\n\
+                        Fix the problem and call the `submit` function again with the corrected data."
+                    ));
+                }
+                Err(e) => {
+                    tracing::warn!(target: "rig",
+                        "Extraction attempt {}/{} failed: {}",
+                        attempt,
+                        self.retries + 1,
+                        e
+                    );
+                    return Err(e);
+                }
+            }
+        }
+    }
 
-        if summary.is_empty() {
+    async fn parse(&self, submission: &str) -> Result<Extraction<T>, ExtractionError> {
+        if submission.is_empty() {
             return Err(ExtractionError::NoData);
         }
 
-        Ok(serde_json::from_str(&summary)?)
+        let submission = if self.confidence {
+            serde_json::from_str(submission)?
+        } else {
+            ConfidenceSubmission {
+                data: serde_json::from_str(submission)?,
+                confidence: 1.0,
+                field_confidence: HashMap::new(),
+            }
+        };
+
+        let value = serde_json::from_value(restore_field_names(&self.renames, submission.data))?;
+
+        for validator in &self.validators {
+            validator(&value)
+                .await
+                .map_err(ExtractionError::ValidationError)?;
+        }
+
+        let field_confidence = submission
+            .field_confidence
+            .into_iter()
+            .map(|(field, confidence)| match self.renames.get(&field) {
+                Some(original) => (original.clone(), confidence),
+                None => (field, confidence),
+            })
+            .collect();
+
+        Ok(Extraction {
+            value,
+            confidence: submission.confidence,
+            field_confidence,
+        })
+    }
+}
+
+/// Maximum number of times a single extraction of a batch is retried after being rate limited
+const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+
+/// Initial delay after which a rate limited batch resumes (doubled on each consecutive retry)
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Aggregate statistics of a batch extraction (see [Extractor::extract_batch_with_stats])
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BatchExtractionStats {
+    /// Number of inputs in the batch
+    pub inputs: usize,
+    /// Number of successful extractions
+    pub succeeded: usize,
+    /// Number of failed extractions
+    pub failed: usize,
+    /// Number of extractions retried because the provider rate limited the requests
+    pub rate_limited: usize,
+    /// Total duration of the batch
+    pub elapsed: Duration,
+}
+
+impl<T: JsonSchema + for<'a> Deserialize<'a> + Send + Sync, M: CompletionModel> Extractor<M, T>
+where
+    M: Sync,
+{
+    /// Extract structured data from multiple texts, running at most `concurrency` extractions
+    /// at once. The results are returned in the same order as the inputs.
+    ///
+    /// When an extraction is rate limited by the provider, the whole batch pauses with an
+    /// exponential backoff before the extraction is retried.
+    pub async fn extract_batch(
+        &self,
+        inputs: impl IntoIterator<Item = impl Into<String>>,
+        concurrency: usize,
+    ) -> Vec<Result<T, ExtractionError>> {
+        self.extract_batch_with_stats(inputs, concurrency).await.0
+    }
+
+    /// Same as [Extractor::extract_batch] but also returns aggregate statistics of the batch.
+    pub async fn extract_batch_with_stats(
+        &self,
+        inputs: impl IntoIterator<Item = impl Into<String>>,
+        concurrency: usize,
+    ) -> (Vec<Result<T, ExtractionError>>, BatchExtractionStats) {
+        let start = Instant::now();
+        let inputs = inputs.into_iter().map(Into::into).collect::<Vec<String>>();
+        let paused_until = &Mutex::new(None::<Instant>);
+        let rate_limited = &AtomicUsize::new(0);
+
+        let results = stream::iter(inputs.iter())
+            .map(|input| async move {
+                let mut retries = 0;
+                loop {
+                    // Wait if the batch has been paused by a rate limited extraction
+                    let pause = paused_until
+                        .lock()
+                        .expect("Rate limit lock poisoned")
+                        .map(|until| until.saturating_duration_since(Instant::now()));
+                    if let Some(pause) = pause.filter(|pause| !pause.is_zero()) {
+                        Delay::new(pause).await;
+                    }
+
+                    match self.extract(input).await {
+                        Err(e) if is_rate_limited(&e) && retries < MAX_RATE_LIMIT_RETRIES => {
+                            let backoff = RATE_LIMIT_BACKOFF * 2u32.pow(retries);
+                            retries += 1;
+                            rate_limited.fetch_add(1, Ordering::Relaxed);
+
+                            tracing::warn!(target: "rig",
+                                "Extraction rate limited, pausing batch for {:?}: {}",
+                                backoff,
+                                e
+                            );
+
+                            let mut paused_until =
+                                paused_until.lock().expect("Rate limit lock poisoned");
+                            let until = Instant::now() + backoff;
+                            *paused_until = Some(paused_until.map_or(until, |u| u.max(until)));
+                        }
+                        result => return result,
+                    }
+                }
+            })
+            .buffered(concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+
+        let succeeded = results.iter().filter(|result| result.is_ok()).count();
+        let stats = BatchExtractionStats {
+            inputs: results.len(),
+            succeeded,
+            failed: results.len() - succeeded,
+            rate_limited: rate_limited.load(Ordering::Relaxed),
+            elapsed: start.elapsed(),
+        };
+
+        tracing::info!(target: "rig",
+            "Batch extraction done: {}/{} succeeded ({} rate limited) in {:?}",
+            stats.succeeded,
+            stats.inputs,
+            stats.rate_limited,
+            stats.elapsed
+        );
+
+        (results, stats)
+    }
+}
+
+/// Rename the fields of a submission that were renamed in the schema back to their original names.
+fn restore_field_names(
+    renames: &HashMap<String, String>,
+    mut value: serde_json::Value,
+) -> serde_json::Value {
+    if let serde_json::Value::Object(map) = &mut value {
+        for (name, field) in renames {
+            if let Some(field_value) = map.remove(name) {
+                map.insert(field.clone(), field_value);
+            }
+        }
+    }
+    value
+}
+
+/// Whether the message contains image content.
+fn has_images(message: &Message) -> bool {
+    match message {
+        Message::User { content } => content
+            .iter()
+            .any(|content| matches!(content, UserContent::Image(_))),
+        Message::Assistant { .. } => false,
+    }
+}
+
+/// Whether the error was caused by the provider rate limiting the requests.
+fn is_rate_limited(error: &ExtractionError) -> bool {
+    match error {
+        ExtractionError::PromptError(PromptError::CompletionError(CompletionError::HttpError(
+            e,
+        ))) => e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS),
+        ExtractionError::PromptError(PromptError::CompletionError(
+            CompletionError::ProviderError(message),
+        )) => {
+            let message = message.to_lowercase();
+            ["rate limit", "rate_limit", "too many requests", "429"]
+                .iter()
+                .any(|pattern| message.contains(pattern))
+        }
+        _ => false,
+    }
+}
+
+/// Stream of progressively-filled partial values returned by [Extractor::extract_stream]
+pub type PartialExtractionStream<T> = Pin<Box<dyn Stream<Item = Result<T, ExtractionError>>>>;
+
+impl<T, M> Extractor<M, T>
+where
+    T: JsonSchema + for<'a> Deserialize<'a> + Send + Sync + 'static,
+    M: StreamingCompletionModel + Sync,
+{
+    /// Extract structured data from `text` using a streaming completion.
+    ///
+    /// Instead of calling the `submit` function, the model is asked to answer with the JSON
+    /// object directly. Each time a new piece of the object has been received, the stream yields
+    /// the data extracted so far, so the fields of `T` should be `Option`s (or have defaults)
+    /// for partial values to deserialize. The last item of the stream is the complete value.
+    ///
+    /// Note: validators and retries are not applied to streaming extractions.
+    pub async fn extract_stream(
+        &self,
+        text: &str,
+    ) -> Result<PartialExtractionStream<T>, ExtractionError> {
+        let mut request = self
+            .agent
+            .completion(text, vec![])
+            .await
+            .map_err(PromptError::from)?
+            .build();
+
+        request.tools.clear();
+        request.preamble = Some(format!(
+            "{}\n=============== OUTPUT FORMAT ===============\n\
+            The `submit` function is NOT available. Instead, answer ONLY with the JSON object \
+            you would have submitted (no markdown, no explanations), following this JSON schema:\n{}",
+            request.preamble.unwrap_or_default(),
+            self.schema
+        ));
+
+        let mut stream = self
+            .model
+            .stream(request)
+            .await
+            .map_err(PromptError::from)?;
+        let renames = self.renames.clone();
+
+        Ok(Box::pin(stream! {
+            let mut buffer = String::new();
+            let mut last_value = None;
+
+            while let Some(chunk) = stream.next().await {
+                let value = match chunk {
+                    Ok(StreamingChoice::Message(text)) => {
+                        buffer.push_str(&text);
+                        match json_utils::parse_partial(&buffer) {
+                            Some(value) => value,
+                            None => continue,
+                        }
+                    }
+                    Ok(StreamingChoice::ToolCall(_, _, params)) => params,
+                    Err(e) => {
+                        yield Err(ExtractionError::from(PromptError::from(e)));
+                        return;
+                    }
+                };
+
+                if last_value.as_ref() == Some(&value) {
+                    continue;
+                }
+
+                // Partial values may not deserialize yet (e.g.: missing required field)
+                if let Ok(data) = serde_json::from_value::<T>(restore_field_names(&renames, value.clone())) {
+                    last_value = Some(value);
+                    yield Ok(data);
+                }
+            }
+
+            if last_value.is_none() {
+                yield Err(ExtractionError::NoData);
+            }
+        }))
     }
 }
 
@@ -79,6 +590,12 @@ pub struct ExtractorBuilder<
     M: CompletionModel,
 > {
     agent_builder: AgentBuilder<M>,
+    model: M,
+    schema: serde_json::Value,
+    renames: HashMap<String, String>,
+    validators: Vec<Validator<T>>,
+    retries: usize,
+    confidence: bool,
     _t: PhantomData<T>,
 }
 
@@ -87,14 +604,19 @@ impl<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync, M: Compl
 {
     pub fn new(model: M) -> Self {
         Self {
-            agent_builder: AgentBuilder::new(model)
+            agent_builder: AgentBuilder::new(model.clone())
                 .preamble("\
                     You are an AI assistant whose purpose is to extract structured data from the provided text.\n\
                     You will have access to a `submit` function that defines the structure of the data to extract from the provided text.\n\
                     Use the `submit` function to submit the structured data.\n\
                     Be sure to fill out every field and ALWAYS CALL THE `submit` function, event with default values!!!.
-                ")
-                .tool(SubmitTool::<T> {_t: PhantomData}),
+                "),
+            model,
+            schema: json!(schema_for!(T)),
+            renames: HashMap::new(),
+            validators: vec![],
+            retries: 0,
+            confidence: false,
             _t: PhantomData,
         }
     }
@@ -113,36 +635,161 @@ impl<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync, M: Compl
         self
     }
 
+    /// Add a validator for the extracted data (e.g.: to check date ranges or cross-field consistency).
+    /// If the validator returns an error, the extraction fails (or is retried, see [ExtractorBuilder::retries]).
+    pub fn validate(
+        mut self,
+        validator: impl Fn(&T) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        let validator: Validator<T> =
+            Box::new(move |data| Box::pin(future::ready(validator(data))));
+        self.validators.push(validator);
+        self
+    }
+
+    /// Same as [ExtractorBuilder::validate] but with an async validator
+    /// (e.g.: to check the extracted data against a database).
+    pub fn validate_async<Fut>(
+        mut self,
+        validator: impl Fn(&T) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let validator: Validator<T> = Box::new(move |data| Box::pin(validator(data)));
+        self.validators.push(validator);
+        self
+    }
+
+    /// Set the number of times the extraction is retried when the submitted data cannot be
+    /// deserialized or fails validation. The error is fed back to the model on each retry.
+    /// Defaults to 0 (no retries).
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Override the JSON schema sent to the model. The schema must still describe data that
+    /// deserializes into `T`.
+    pub fn schema(mut self, schema: serde_json::Value) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Post-process the JSON schema sent to the model (e.g.: to strip constructs that the
+    /// provider does not support, see the [schema] module).
+    pub fn map_schema(mut self, f: impl FnOnce(&mut serde_json::Value)) -> Self {
+        f(&mut self.schema);
+        self
+    }
+
+    /// Set the description of a (top-level) field of the schema.
+    pub fn field_description(mut self, field: &str, description: &str) -> Self {
+        if let Some(property) = schema::property_mut(&mut self.schema, field) {
+            property.insert("description".to_string(), json!(description));
+        }
+        self
+    }
+
+    /// Set the format of a (top-level) field of the schema (e.g.: "date", "email" or "uri").
+    pub fn field_format(mut self, field: &str, format: &str) -> Self {
+        if let Some(property) = schema::property_mut(&mut self.schema, field) {
+            property.insert("format".to_string(), json!(format));
+        }
+        self
+    }
+
+    /// Rename a (top-level) field of the schema. The model submits the field under the new
+    /// name, which is mapped back to the original field name before deserialization.
+    pub fn rename_field(mut self, field: &str, name: &str) -> Self {
+        if let Some(properties) = self
+            .schema
+            .get_mut("properties")
+            .and_then(serde_json::Value::as_object_mut)
+        {
+            if let Some(property) = properties.remove(field) {
+                properties.insert(name.to_string(), property);
+            }
+        }
+        if let Some(required) = self
+            .schema
+            .get_mut("required")
+            .and_then(serde_json::Value::as_array_mut)
+        {
+            required
+                .iter_mut()
+                .filter(|required| **required == field)
+                .for_each(|required| *required = json!(name));
+        }
+        self.renames.insert(name.to_string(), field.to_string());
+        self
+    }
+
+    /// Ask the model to rate its confidence in the extracted data, overall and per field.
+    /// The scores are self-reported by the model (token logprobs are not exposed by the
+    /// completion models), so they are best used to rank extractions rather than as probabilities.
+    /// Required by [Extractor::extract_with_confidence].
+    pub fn with_confidence(mut self) -> Self {
+        self.confidence = true;
+        self
+    }
+
     /// Build the Extractor
     pub fn build(self) -> Extractor<M, T> {
+        let (agent_builder, tool_schema) = if self.confidence {
+            (
+                self.agent_builder.append_preamble(
+                    "\n=============== CONFIDENCE ===============\n\
+                    Submit the extracted data as `data`, along with your `confidence` in it as a \
+                    number between 0 (pure guess) and 1 (explicitly stated in the text), both overall \
+                    and for each field of the data in `field_confidence`.",
+                ),
+                schema::with_confidence(self.schema.clone()),
+            )
+        } else {
+            (self.agent_builder, self.schema.clone())
+        };
+
         Extractor {
-            agent: self.agent_builder.build(),
+            agent: agent_builder
+                .tool(SubmitTool {
+                    schema: tool_schema,
+                })
+                .build(),
+            model: self.model,
+            schema: self.schema,
+            renames: self.renames,
+            validators: self.validators,
+            retries: self.retries,
+            confidence: self.confidence,
             _t: PhantomData,
         }
     }
 }
 
 #[derive(Deserialize, Serialize)]
-struct SubmitTool<T: JsonSchema + for<'a> Deserialize<'a> + Send + Sync> {
-    _t: PhantomData<T>,
+struct SubmitTool {
+    schema: serde_json::Value,
 }
 
 #[derive(Debug, thiserror::Error)]
 #[error("SubmitError")]
 struct SubmitError;
 
-impl<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync> Tool for SubmitTool<T> {
+impl Tool for SubmitTool {
     const NAME: &'static str = "submit";
     type Error = SubmitError;
-    type Args = T;
-    type Output = T;
+    // The submission is deserialized into the target structure by the extractor, once the
+    // renamed fields have been restored
+    type Args = serde_json::Value;
+    type Output = serde_json::Value;
 
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: Self::NAME.to_string(),
             description: "Submit the structured data you extracted from the provided text."
                 .to_string(),
-            parameters: json!(schema_for!(T)),
+            parameters: self.schema.clone(),
         }
     }
 
@@ -150,3 +797,507 @@ impl<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync> Tool for
         Ok(data)
     }
 }
+
+/// Helpers to adapt the JSON schema of an extractor to the JSON schema features supported by
+/// a provider (see [ExtractorBuilder::map_schema]).
+pub mod schema {
+    use serde_json::{json, Map, Value};
+
+    /// Get the schema of a top-level property of an object schema.
+    pub(crate) fn property_mut<'a>(
+        schema: &'a mut Value,
+        field: &str,
+    ) -> Option<&'a mut Map<String, Value>> {
+        schema
+            .get_mut("properties")?
+            .get_mut(field)?
+            .as_object_mut()
+    }
+
+    /// Wrap the schema of the extracted data into the schema of a submission with confidence
+    /// scores (see [ExtractorBuilder::with_confidence](super::ExtractorBuilder::with_confidence)).
+    pub(crate) fn with_confidence(mut schema: Value) -> Value {
+        let confidence = json!({"type": "number", "minimum": 0.0, "maximum": 1.0});
+
+        // Definitions must stay at the root for `$ref`s to resolve
+        let definitions = schema
+            .as_object_mut()
+            .and_then(|map| map.remove("definitions"));
+        if let Some(map) = schema.as_object_mut() {
+            map.remove("$schema");
+        }
+
+        let mut wrapped = json!({
+            "type": "object",
+            "properties": {
+                "data": schema,
+                "confidence": confidence.clone(),
+                "field_confidence": {
+                    "type": "object",
+                    "additionalProperties": confidence,
+                },
+            },
+            "required": ["data", "confidence", "field_confidence"],
+        });
+        if let Some(definitions) = definitions {
+            wrapped["definitions"] = definitions;
+        }
+        wrapped
+    }
+
+    /// Recursively remove the given keys (e.g.: `"$schema"`, `"title"`, `"format"`) from the schema.
+    pub fn remove_keys(schema: &mut Value, keys: &[&str]) {
+        match schema {
+            Value::Object(map) => {
+                keys.iter().for_each(|key| {
+                    map.remove(*key);
+                });
+                // Do not treat property names as schema keywords
+                map.iter_mut()
+                    .for_each(|(key, value)| match (key.as_str(), value) {
+                        ("properties", Value::Object(properties)) => properties
+                            .values_mut()
+                            .for_each(|property| remove_keys(property, keys)),
+                        (_, value) => remove_keys(value, keys),
+                    });
+            }
+            Value::Array(values) => values.iter_mut().for_each(|value| remove_keys(value, keys)),
+            _ => {}
+        }
+    }
+
+    /// Replace the `$ref`s of the schema with the definitions they point to and remove the
+    /// `definitions` (for providers that do not support references).
+    /// Recursive definitions are left as is.
+    pub fn inline_refs(schema: &mut Value) {
+        let Some(definitions) = schema.as_object_mut().and_then(|map| {
+            map.remove("definitions")
+                .or_else(|| map.remove("$defs"))
+                .and_then(|definitions| match definitions {
+                    Value::Object(definitions) => Some(definitions),
+                    _ => None,
+                })
+        }) else {
+            return;
+        };
+
+        resolve_refs(schema, &definitions, &mut vec![]);
+    }
+
+    fn resolve_refs(schema: &mut Value, definitions: &Map<String, Value>, stack: &mut Vec<String>) {
+        match schema {
+            Value::Object(map) => {
+                let name = map
+                    .get("$ref")
+                    .and_then(Value::as_str)
+                    .and_then(|reference| reference.rsplit('/').next())
+                    .map(str::to_string);
+
+                match name {
+                    Some(name) if !stack.contains(&name) => {
+                        if let Some(Value::Object(definition)) = definitions.get(&name) {
+                            map.remove("$ref");
+                            definition.iter().for_each(|(key, value)| {
+                                map.entry(key.clone()).or_insert_with(|| value.clone());
+                            });
+
+                            stack.push(name);
+                            map.values_mut()
+                                .for_each(|value| resolve_refs(value, definitions, stack));
+                            stack.pop();
+                        }
+                    }
+                    Some(_) => {}
+                    None => map
+                        .values_mut()
+                        .for_each(|value| resolve_refs(value, definitions, stack)),
+                }
+            }
+            Value::Array(values) => values
+                .iter_mut()
+                .for_each(|value| resolve_refs(value, definitions, stack)),
+            _ => {}
+        }
+    }
+
+    /// Adapt the schema to OpenAI's strict structured outputs: every object disallows additional
+    /// properties and lists all of its properties as required (optional fields must be nullable).
+    pub fn openai_strict(schema: &mut Value) {
+        match schema {
+            Value::Object(map) => {
+                if let Some(Value::Object(properties)) = map.get("properties") {
+                    let required = properties.keys().cloned().map(Value::String).collect();
+                    map.insert("required".to_string(), Value::Array(required));
+                    map.insert("additionalProperties".to_string(), Value::Bool(false));
+                }
+                map.values_mut().for_each(openai_strict);
+            }
+            Value::Array(values) => values.iter_mut().for_each(openai_strict),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::{
+        completion::{AssistantContent, CompletionRequest, CompletionResponse},
+        message::ImageMediaType,
+    };
+
+    /// Model that calls the `submit` tool with the given arguments, in order.
+    #[derive(Clone)]
+    struct MockModel {
+        submissions: Arc<Mutex<VecDeque<serde_json::Value>>>,
+        requests: Arc<Mutex<Vec<CompletionRequest>>>,
+    }
+
+    impl MockModel {
+        fn new(submissions: Vec<serde_json::Value>) -> Self {
+            Self {
+                submissions: Arc::new(Mutex::new(submissions.into())),
+                requests: Arc::new(Mutex::new(vec![])),
+            }
+        }
+    }
+
+    impl CompletionModel for MockModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            self.requests.lock().unwrap().push(request);
+            let arguments = self.submissions.lock().unwrap().pop_front().unwrap();
+
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::tool_call("call_0", "submit", arguments)),
+                raw_response: (),
+            })
+        }
+    }
+
+    #[derive(Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+    struct Person {
+        name: String,
+        age: u8,
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_deserialization_error() {
+        let model = MockModel::new(vec![
+            json!({"name": "John"}),
+            json!({"name": "John", "age": 30}),
+        ]);
+
+        let extractor = ExtractorBuilder::<Person, _>::new(model.clone())
+            .retries(1)
+            .build();
+
+        let person = extractor.extract("John is 30").await.unwrap();
+        assert_eq!(
+            person,
+            Person {
+                name: "John".to_string(),
+                age: 30
+            }
+        );
+
+        let requests = model.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].chat_history.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_validation_error_after_retries() {
+        let model = MockModel::new(vec![
+            json!({"name": "John", "age": 200}),
+            json!({"name": "John", "age": 200}),
+        ]);
+
+        let extractor = ExtractorBuilder::<Person, _>::new(model.clone())
+            .validate(|person: &Person| {
+                if person.age > 150 {
+                    Err(format!("Invalid age: {}", person.age))
+                } else {
+                    Ok(())
+                }
+            })
+            .retries(1)
+            .build();
+
+        let result = extractor.extract("John is 30").await;
+        assert!(matches!(result, Err(ExtractionError::ValidationError(_))));
+
+        let requests = model.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].chat_history.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_async_validation() {
+        let model = MockModel::new(vec![
+            json!({"name": "John", "age": 30}),
+            json!({"name": "Johnny", "age": 30}),
+        ]);
+
+        let extractor = ExtractorBuilder::<Person, _>::new(model.clone())
+            .validate_async(|person: &Person| {
+                let name = person.name.clone();
+                async move {
+                    if name.len() < 5 {
+                        Err(format!("Name too short: {name}"))
+                    } else {
+                        Ok(())
+                    }
+                }
+            })
+            .retries(1)
+            .build();
+
+        let person = extractor.extract("Johnny is 30").await.unwrap();
+        assert_eq!(person.name, "Johnny");
+    }
+
+    /// Model that streams the given text chunks.
+    #[derive(Clone)]
+    struct MockStreamingModel {
+        chunks: Vec<&'static str>,
+    }
+
+    impl CompletionModel for MockStreamingModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            unimplemented!()
+        }
+    }
+
+    impl StreamingCompletionModel for MockStreamingModel {
+        async fn stream(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<crate::streaming::StreamingResult, CompletionError> {
+            assert!(request.tools.is_empty());
+
+            Ok(Box::pin(futures::stream::iter(
+                self.chunks
+                    .clone()
+                    .into_iter()
+                    .map(|chunk| Ok(StreamingChoice::Message(chunk.to_string()))),
+            )))
+        }
+    }
+
+    #[derive(Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+    struct PartialPerson {
+        name: Option<String>,
+        age: Option<u8>,
+    }
+
+    #[tokio::test]
+    async fn test_extract_stream() {
+        let model = MockStreamingModel {
+            chunks: vec!["{\"name\": \"Jo", "hn\", \"ag", "e\": 30}"],
+        };
+
+        let extractor = ExtractorBuilder::<PartialPerson, _>::new(model).build();
+
+        let partials = extractor
+            .extract_stream("John is 30")
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(
+            partials,
+            vec![
+                PartialPerson {
+                    name: Some("Jo".to_string()),
+                    age: None
+                },
+                PartialPerson {
+                    name: Some("John".to_string()),
+                    age: None
+                },
+                PartialPerson {
+                    name: Some("John".to_string()),
+                    age: Some(30)
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extract_batch() {
+        let model = MockModel::new(vec![
+            json!({"name": "John", "age": 30}),
+            json!({"name": "Jane"}),
+        ]);
+
+        let extractor = ExtractorBuilder::<Person, _>::new(model).build();
+
+        let (results, stats) = extractor
+            .extract_batch_with_stats(vec!["John is 30", "Jane is 25"], 1)
+            .await;
+
+        assert_eq!(
+            results[0].as_ref().unwrap(),
+            &Person {
+                name: "John".to_string(),
+                age: 30
+            }
+        );
+        assert!(matches!(
+            results[1],
+            Err(ExtractionError::DeserializationError(_))
+        ));
+        assert_eq!(stats.inputs, 2);
+        assert_eq!(stats.succeeded, 1);
+        assert_eq!(stats.failed, 1);
+        assert_eq!(stats.rate_limited, 0);
+    }
+
+    #[tokio::test]
+    async fn test_rename_field() {
+        let model = MockModel::new(vec![json!({"full_name": "John", "age": 30})]);
+
+        let extractor = ExtractorBuilder::<Person, _>::new(model.clone())
+            .rename_field("name", "full_name")
+            .field_description("age", "Age in years")
+            .build();
+
+        let person = extractor.extract("John is 30").await.unwrap();
+        assert_eq!(person.name, "John");
+
+        let requests = model.requests.lock().unwrap();
+        let parameters = &requests[0].tools[0].parameters;
+        assert!(parameters["properties"].get("name").is_none());
+        assert!(parameters["properties"].get("full_name").is_some());
+        assert_eq!(
+            parameters["properties"]["age"]["description"],
+            "Age in years"
+        );
+        assert!(parameters["required"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("full_name")));
+    }
+
+    #[tokio::test]
+    async fn test_extract_with_confidence() {
+        let model = MockModel::new(vec![json!({
+            "data": {"full_name": "John", "age": 30},
+            "confidence": 0.9,
+            "field_confidence": {"full_name": 0.95, "age": 0.4}
+        })]);
+
+        let extractor = ExtractorBuilder::<Person, _>::new(model.clone())
+            .rename_field("name", "full_name")
+            .with_confidence()
+            .build();
+
+        let extraction = extractor
+            .extract_with_confidence("John is 30")
+            .await
+            .unwrap();
+        assert_eq!(extraction.value.name, "John");
+        assert_eq!(extraction.confidence, 0.9);
+        assert_eq!(extraction.field_confidence["name"], 0.95);
+        assert!(!extraction.is_confident(0.8));
+        assert_eq!(extraction.low_confidence_fields(0.8), vec!["age"]);
+
+        let requests = model.requests.lock().unwrap();
+        let parameters = &requests[0].tools[0].parameters;
+        assert!(parameters["properties"]["data"]["properties"]
+            .get("full_name")
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_confidence_not_enabled() {
+        let model = MockModel::new(vec![]);
+
+        let extractor = ExtractorBuilder::<Person, _>::new(model).build();
+
+        assert!(matches!(
+            extractor.extract_with_confidence("John is 30").await,
+            Err(ExtractionError::ConfidenceNotEnabled)
+        ));
+    }
+
+    #[test]
+    fn test_schema_helpers() {
+        let mut schema = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "Order",
+            "type": "object",
+            "properties": {
+                "title": {"type": "string"},
+                "customer": {"$ref": "#/definitions/Customer"}
+            },
+            "definitions": {
+                "Customer": {
+                    "title": "Customer",
+                    "type": "object",
+                    "properties": {"name": {"type": "string"}}
+                }
+            }
+        });
+
+        schema::inline_refs(&mut schema);
+        schema::remove_keys(&mut schema, &["$schema", "title"]);
+
+        assert_eq!(
+            schema,
+            json!({
+                "type": "object",
+                "properties": {
+                    "title": {"type": "string"},
+                    "customer": {
+                        "type": "object",
+                        "properties": {"name": {"type": "string"}}
+                    }
+                }
+            })
+        );
+
+        schema::openai_strict(&mut schema);
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(
+            schema["properties"]["customer"]["required"],
+            json!(["name"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extract_image_unsupported() {
+        let model = MockModel::new(vec![json!({"name": "John", "age": 30})]);
+
+        let extractor = ExtractorBuilder::<Person, _>::new(model.clone()).build();
+
+        let result = extractor
+            .extract_image(Image {
+                data: "aGVsbG8=".to_string(),
+                format: None,
+                media_type: Some(ImageMediaType::PNG),
+                detail: None,
+            })
+            .await;
+
+        assert!(matches!(result, Err(ExtractionError::UnsupportedInput(_))));
+        assert!(model.requests.lock().unwrap().is_empty());
+    }
+}
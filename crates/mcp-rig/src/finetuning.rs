@@ -0,0 +1,339 @@
+//! Fine-tuning jobs, e.g.: to train a model on the records collected from live agents with a
+//! [DatasetCollector](crate::observability::dataset::DatasetCollector).
+//!
+//! The [FineTuning] trait is implemented by the clients of the providers exposing a
+//! fine-tuning API (e.g.: [crate::providers::openai::Client], [crate::providers::cohere::Client]).
+//! Once a job succeeded, [FineTuning::fine_tuned_model] returns the completion model of the
+//! resulting model, to be used like any other model (e.g.: in an agent).
+//!
+//! The records of a dataset are converted to the training format of a provider with
+//! [write_training_jsonl], then uploaded to the provider (e.g.: with
+//! [upload_file](crate::providers::openai::Client::upload_file) for OpenAI, or
+//! [upload_dataset](crate::providers::cohere::Client::upload_dataset) for Cohere).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     agent::AgentBuilder,
+//!     finetuning::{write_training_jsonl, FineTuneRequest, FineTuning, TrainingFormat},
+//!     providers::openai::{self, FilePurpose},
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let mut training_data = vec![];
+//! write_training_jsonl(&collector.records(), TrainingFormat::OpenAi, &mut training_data)?;
+//! let file = openai
+//!     .upload_file("training.jsonl", training_data, FilePurpose::FineTune)
+//!     .await?;
+//!
+//! let job = openai
+//!     .create_fine_tune_job(FineTuneRequest::new(openai::GPT_4O_MINI, &file.id).suffix("support"))
+//!     .await?;
+//! let job = openai
+//!     .wait_for_fine_tune_job(&job.id, std::time::Duration::from_secs(30))
+//!     .await?;
+//!
+//! let agent = AgentBuilder::new(openai.fine_tuned_model(&job)?).build();
+//! ```
+use std::{future::Future, io::Write, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    completion::CompletionModel,
+    http_client::HttpClientError,
+    message::{AssistantContent, Message, Text, UserContent},
+    observability::dataset::DatasetRecord,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum FineTuneError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// Error returned by the provider
+    #[error("ProviderError: {0}")]
+    ProviderError(String),
+
+    /// The job has no fine-tuned model (yet)
+    #[error("ModelNotReady: job {0} has no fine-tuned model")]
+    ModelNotReady(String),
+}
+
+impl From<HttpClientError> for FineTuneError {
+    fn from(error: HttpClientError) -> Self {
+        match error {
+            HttpClientError::HttpError(error) => FineTuneError::HttpError(error),
+            error => FineTuneError::ProviderError(error.to_string()),
+        }
+    }
+}
+
+/// Request creating a fine-tuning job.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FineTuneRequest {
+    /// Model to fine-tune
+    pub base_model: String,
+    /// ID of the training data, as uploaded to the provider (file ID for OpenAI, dataset ID
+    /// for Cohere)
+    pub training_file: String,
+    /// ID of the validation data, if any
+    pub validation_file: Option<String>,
+    /// Suffix of the name of the fine-tuned model
+    pub suffix: Option<String>,
+    /// Number of training epochs (the provider picks it when unset)
+    pub epochs: Option<u64>,
+}
+
+impl FineTuneRequest {
+    pub fn new(base_model: &str, training_file: &str) -> Self {
+        Self {
+            base_model: base_model.to_string(),
+            training_file: training_file.to_string(),
+            validation_file: None,
+            suffix: None,
+            epochs: None,
+        }
+    }
+
+    pub fn validation_file(mut self, validation_file: &str) -> Self {
+        self.validation_file = Some(validation_file.to_string());
+        self
+    }
+
+    pub fn suffix(mut self, suffix: &str) -> Self {
+        self.suffix = Some(suffix.to_string());
+        self
+    }
+
+    pub fn epochs(mut self, epochs: u64) -> Self {
+        self.epochs = Some(epochs);
+        self
+    }
+}
+
+/// Status of a fine-tuning job.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FineTuneStatus {
+    /// The job (or its data) is waiting to be processed
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl FineTuneStatus {
+    /// Whether the job is over (i.e.: its status won't change anymore)
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            FineTuneStatus::Succeeded | FineTuneStatus::Failed | FineTuneStatus::Cancelled
+        )
+    }
+}
+
+/// A fine-tuning job of a provider.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FineTuneJob {
+    pub id: String,
+    pub base_model: String,
+    pub status: FineTuneStatus,
+    /// ID of the resulting model, once the job succeeded
+    pub fine_tuned_model: Option<String>,
+    /// Creation time of the job (unix timestamp, in seconds)
+    pub created_at: Option<u64>,
+    /// Reason of the failure of the job, if any
+    pub error: Option<String>,
+}
+
+/// Trait for the provider clients able to fine-tune models.
+pub trait FineTuning: Send + Sync {
+    /// Completion model of the fine-tuned models
+    type Model: CompletionModel;
+
+    /// Create (i.e.: start) a fine-tuning job.
+    fn create_fine_tune_job(
+        &self,
+        request: FineTuneRequest,
+    ) -> impl Future<Output = Result<FineTuneJob, FineTuneError>> + Send;
+
+    /// Get the fine-tuning job `job_id` (e.g.: to monitor its status).
+    fn fine_tune_job(
+        &self,
+        job_id: &str,
+    ) -> impl Future<Output = Result<FineTuneJob, FineTuneError>> + Send;
+
+    /// List the fine-tuning jobs.
+    fn list_fine_tune_jobs(
+        &self,
+    ) -> impl Future<Output = Result<Vec<FineTuneJob>, FineTuneError>> + Send;
+
+    /// Cancel the fine-tuning job `job_id`.
+    fn cancel_fine_tune_job(
+        &self,
+        job_id: &str,
+    ) -> impl Future<Output = Result<(), FineTuneError>> + Send;
+
+    /// Completion model of the model fine-tuned by `job`, or [FineTuneError::ModelNotReady] if
+    /// the job didn't succeed (yet).
+    fn fine_tuned_model(&self, job: &FineTuneJob) -> Result<Self::Model, FineTuneError>;
+
+    /// Poll the fine-tuning job `job_id` every `poll_interval` until it is over, and return it.
+    fn wait_for_fine_tune_job(
+        &self,
+        job_id: &str,
+        poll_interval: Duration,
+    ) -> impl Future<Output = Result<FineTuneJob, FineTuneError>> + Send {
+        async move {
+            loop {
+                let job = self.fine_tune_job(job_id).await?;
+                if job.status.is_terminal() {
+                    return Ok(job);
+                }
+                tracing::debug!(target: "rig",
+                    "Fine-tuning job {} is {:?}, polling again in {:?}",
+                    job_id, job.status, poll_interval
+                );
+                futures_timer::Delay::new(poll_interval).await;
+            }
+        }
+    }
+}
+
+/// Training data format of a provider.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrainingFormat {
+    /// `{"messages": [{"role": "system" | "user" | "assistant", "content": "..."}]}` (OpenAI,
+    /// and the providers with OpenAI-compatible fine-tuning, e.g.: Mistral)
+    OpenAi,
+    /// `{"messages": [{"role": "System" | "User" | "Chatbot", "content": "..."}]}`
+    Cohere,
+}
+
+impl TrainingFormat {
+    fn role(&self, message: &Message) -> &'static str {
+        match (self, message) {
+            (TrainingFormat::OpenAi, Message::User { .. }) => "user",
+            (TrainingFormat::OpenAi, Message::Assistant { .. }) => "assistant",
+            (TrainingFormat::Cohere, Message::User { .. }) => "User",
+            (TrainingFormat::Cohere, Message::Assistant { .. }) => "Chatbot",
+        }
+    }
+
+    fn system_role(&self) -> &'static str {
+        match self {
+            TrainingFormat::OpenAi => "system",
+            TrainingFormat::Cohere => "System",
+        }
+    }
+}
+
+/// Text of a message (tool calls, tool results and media are not part of the training data).
+fn message_text(message: &Message) -> String {
+    let texts: Vec<&str> = match message {
+        Message::User { content } => content
+            .iter()
+            .filter_map(|content| match content {
+                UserContent::Text(Text { text }) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect(),
+        Message::Assistant { content } => content
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(Text { text }) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect(),
+    };
+    texts.join("\n")
+}
+
+/// Write `records` to `writer` in the training `format`, one conversation per line (preamble,
+/// context, prompt and response). Records with a feedback score of 0 or less (e.g.: a thumbs
+/// down) are skipped.
+pub fn write_training_jsonl(
+    records: &[DatasetRecord],
+    format: TrainingFormat,
+    mut writer: impl Write,
+) -> Result<(), FineTuneError> {
+    for record in records {
+        if record
+            .feedback
+            .as_ref()
+            .and_then(|feedback| feedback.score)
+            .is_some_and(|score| score <= 0.0)
+        {
+            continue;
+        }
+
+        let mut messages = vec![];
+        if let Some(preamble) = &record.preamble {
+            messages.push(json!({ "role": format.system_role(), "content": preamble }));
+        }
+        for message in record.context.iter().chain([&record.prompt]) {
+            let text = message_text(message);
+            if !text.is_empty() {
+                messages.push(json!({ "role": format.role(message), "content": text }));
+            }
+        }
+        messages.push(json!({
+            "role": format.role(&Message::assistant("")),
+            "content": record.response
+        }));
+
+        serde_json::to_writer(&mut writer, &json!({ "messages": messages }))?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::observability::dataset::Feedback;
+
+    #[test]
+    fn test_write_training_jsonl() {
+        let records = vec![
+            DatasetRecord::new("What is a flurbo?", "A green alien.")
+                .preamble("You are a dictionary.")
+                .context(vec![Message::user("Hi!"), Message::assistant("Hello!")]),
+            DatasetRecord {
+                feedback: Some(Feedback::new().score(0.0)),
+                ..DatasetRecord::new("What is a glarb?", "No idea.")
+            },
+        ];
+
+        let mut output = vec![];
+        write_training_jsonl(&records, TrainingFormat::Cohere, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(output.lines().count(), 1);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(output.trim()).unwrap(),
+            json!({
+                "messages": [
+                    {"role": "System", "content": "You are a dictionary."},
+                    {"role": "User", "content": "Hi!"},
+                    {"role": "Chatbot", "content": "Hello!"},
+                    {"role": "User", "content": "What is a flurbo?"},
+                    {"role": "Chatbot", "content": "A green alien."}
+                ]
+            })
+        );
+    }
+}
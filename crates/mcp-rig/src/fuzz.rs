@@ -0,0 +1,704 @@
+//! Property-based fuzzing of the conversions of the generic [Message]s into the messages of the
+//! providers, to catch the conversion bugs (e.g.: dropped tool results, lost roles) before the
+//! models do.
+//!
+//! A [MessageGenerator] generates random but well-formed conversations (i.e.: whose tool results
+//! follow their tool calls) from a seed, with the content types supported by a provider (see
+//! [MessageGenerator::images], [MessageGenerator::audio], etc.). The properties check a
+//! conversation against a provider:
+//! - [openai_round_trip] and [anthropic_round_trip]: the conversation converted into provider
+//!   messages and back is the same, except for the fields the provider doesn't carry,
+//! - [cohere_preserved]: Cohere has no conversion back, so the roles, texts, tool calls and tool
+//!   results of the conversation are checked in the Cohere messages.
+//!
+//! [check] runs a property on many generated conversations, and reports the shortest failing
+//! conversation with the seed reproducing it.
+//!
+//! This module is only available with the `test-support` feature (e.g.: in the
+//! `[dev-dependencies]` of a crate adding a provider).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::fuzz::{self, MessageGenerator};
+//!
+//! #[test]
+//! fn test_openai_conversions() {
+//!     fuzz::check(
+//!         |seed| MessageGenerator::new(seed).images(true).audio(true),
+//!         500,
+//!         fuzz::openai_round_trip,
+//!     )
+//!     .unwrap();
+//! }
+//! ```
+use std::{collections::HashMap, fmt};
+
+use serde_json::json;
+
+use crate::{
+    completion::message::{
+        AssistantContent, Audio, AudioMediaType, ContentFormat, Document, DocumentMediaType, Image,
+        ImageDetail, ImageMediaType, Message, MessageError, Text, ToolCall, ToolFunction,
+        ToolResult, ToolResultContent, UserContent,
+    },
+    one_or_many::OneOrMany,
+    providers::{anthropic, cohere, openai},
+};
+
+/// Default number of turns of the generated conversations.
+const DEFAULT_TURNS: usize = 4;
+
+/// Fragments of the generated texts, with the characters most likely to break a conversion.
+const FRAGMENTS: &[&str] = &[
+    "Hello",
+    "What's the weather in Paris?",
+    "",
+    " ",
+    "line one\nline two",
+    "tab\tseparated",
+    "\"quoted\"",
+    "back\\slash",
+    "{\"result\": 42}",
+    "[1, 2, 3]",
+    "null",
+    "<b>html</b>",
+    "émojis 🦀🚀",
+    "日本語のテキスト",
+    "مرحبا",
+    "0",
+    "true",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum PropertyError {
+    #[error("MessageError: {0}")]
+    MessageError(#[from] MessageError),
+
+    /// The converted conversation differs from the original one
+    #[error("Mismatch: {0}")]
+    Mismatch(String),
+}
+
+/// Failure of a property (see [check]).
+#[derive(Debug)]
+pub struct PropertyFailure {
+    /// Seed of the generator of the failing conversation
+    pub seed: u64,
+    /// Shortest failing prefix of the generated conversation
+    pub conversation: Vec<Message>,
+    pub error: PropertyError,
+}
+
+impl fmt::Display for PropertyFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Property failed for seed {}: {}\nConversation: {}",
+            self.seed,
+            self.error,
+            serde_json::to_string_pretty(&self.conversation).unwrap_or_default()
+        )
+    }
+}
+
+impl std::error::Error for PropertyFailure {}
+
+/// Small deterministic pseudo-random generator (xorshift64*), so that a seed always reproduces
+/// the same conversation.
+#[derive(Clone, Debug)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // The state of xorshift must not be zero
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Random number in `0..n`
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+
+    fn pick<T: Clone>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())].clone()
+    }
+}
+
+/// Generator of random conversations (see the [module](self) docs). By default, the messages
+/// only contain texts, tool calls and text tool results.
+#[derive(Clone, Debug)]
+pub struct MessageGenerator {
+    rng: Rng,
+    /// Next id of the generated tool calls
+    next_call: usize,
+    images: bool,
+    audio: bool,
+    documents: bool,
+    tool_result_images: bool,
+    /// Maximum number of contents of a message
+    max_contents: usize,
+}
+
+impl MessageGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            next_call: 0,
+            images: false,
+            audio: false,
+            documents: false,
+            tool_result_images: false,
+            max_contents: 3,
+        }
+    }
+
+    /// Add images to the user messages
+    pub fn images(mut self, images: bool) -> Self {
+        self.images = images;
+        self
+    }
+
+    /// Add audio to the user messages
+    pub fn audio(mut self, audio: bool) -> Self {
+        self.audio = audio;
+        self
+    }
+
+    /// Add (PDF) documents to the user messages
+    pub fn documents(mut self, documents: bool) -> Self {
+        self.documents = documents;
+        self
+    }
+
+    /// Add images to the tool results
+    pub fn tool_result_images(mut self, tool_result_images: bool) -> Self {
+        self.tool_result_images = tool_result_images;
+        self
+    }
+
+    /// Generate messages with at most `max_contents` contents (3 by default)
+    pub fn max_contents(mut self, max_contents: usize) -> Self {
+        self.max_contents = max_contents.max(1);
+        self
+    }
+
+    /// Random text
+    pub fn text(&mut self) -> String {
+        (0..1 + self.rng.below(3))
+            .map(|_| self.rng.pick(FRAGMENTS))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Random JSON value, nested at most `depth` times
+    fn value(&mut self, depth: usize) -> serde_json::Value {
+        match self.rng.below(if depth == 0 { 4 } else { 6 }) {
+            0 => serde_json::Value::Null,
+            1 => json!(self.rng.chance(0.5)),
+            2 => json!(self.rng.below(2000) as i64 - 1000),
+            3 => json!(self.text()),
+            4 => (0..self.rng.below(3))
+                .map(|_| self.value(depth - 1))
+                .collect(),
+            _ => self.arguments(depth - 1),
+        }
+    }
+
+    /// Random JSON object, as the arguments of a tool call
+    fn arguments(&mut self, depth: usize) -> serde_json::Value {
+        (0..self.rng.below(4))
+            .map(|i| (format!("arg_{i}"), self.value(depth)))
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+
+    /// Random base64-like data
+    fn data(&mut self) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        (0..4 + self.rng.below(60))
+            .map(|_| ALPHABET[self.rng.below(ALPHABET.len())] as char)
+            .collect()
+    }
+
+    fn image(&mut self) -> Image {
+        Image {
+            data: self.data(),
+            format: Some(ContentFormat::Base64),
+            media_type: Some(self.rng.pick(&[
+                ImageMediaType::JPEG,
+                ImageMediaType::PNG,
+                ImageMediaType::GIF,
+                ImageMediaType::WEBP,
+            ])),
+            detail: Some(
+                self.rng
+                    .pick(&[ImageDetail::Low, ImageDetail::High, ImageDetail::Auto]),
+            ),
+        }
+    }
+
+    /// Random user content, other than a tool result
+    fn user_content(&mut self) -> UserContent {
+        let mut kinds = vec![0];
+        kinds.extend(self.images.then_some(1));
+        kinds.extend(self.audio.then_some(2));
+        kinds.extend(self.documents.then_some(3));
+        match self.rng.pick(&kinds) {
+            1 => UserContent::Image(self.image()),
+            2 => UserContent::Audio(Audio {
+                data: self.data(),
+                format: Some(ContentFormat::Base64),
+                media_type: Some(self.rng.pick(&[AudioMediaType::WAV, AudioMediaType::MP3])),
+            }),
+            3 => UserContent::Document(Document {
+                data: self.data(),
+                format: Some(ContentFormat::Base64),
+                media_type: Some(DocumentMediaType::PDF),
+            }),
+            _ => UserContent::text(self.text()),
+        }
+    }
+
+    /// Random user message, without tool results
+    pub fn user_message(&mut self) -> Message {
+        let count = 1 + self.rng.below(self.max_contents);
+        Message::User {
+            content: OneOrMany::many((0..count).map(|_| self.user_content()).collect::<Vec<_>>())
+                .expect("There is at least one content"),
+        }
+    }
+
+    /// Random assistant message, with texts and tool calls
+    pub fn assistant_message(&mut self) -> Message {
+        let count = 1 + self.rng.below(self.max_contents);
+        let content = (0..count)
+            .map(|_| {
+                if self.rng.chance(0.5) {
+                    AssistantContent::text(self.text())
+                } else {
+                    self.next_call += 1;
+                    AssistantContent::ToolCall(ToolCall {
+                        id: format!("call_{}_{}", self.next_call, self.rng.below(1_000_000)),
+                        function: ToolFunction {
+                            name: self
+                                .rng
+                                .pick(&["search", "get_weather", "add", "send_email"])
+                                .to_string(),
+                            arguments: self.arguments(2),
+                        },
+                    })
+                }
+            })
+            .collect::<Vec<_>>();
+        Message::Assistant {
+            content: OneOrMany::many(content).expect("There is at least one content"),
+        }
+    }
+
+    /// User message with the results of the tool calls of `assistant`, if it called any, and
+    /// sometimes other content (e.g.: a follow-up question)
+    fn tool_results_message(&mut self, assistant: &Message) -> Option<Message> {
+        let Message::Assistant { content } = assistant else {
+            return None;
+        };
+        let mut results = content
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::ToolCall(call) => Some(call.id.clone()),
+                AssistantContent::Text(_) => None,
+            })
+            .map(|id| {
+                let count = 1 + self.rng.below(self.max_contents);
+                let content = (0..count)
+                    .map(|_| {
+                        if self.tool_result_images && self.rng.chance(0.3) {
+                            ToolResultContent::Image(Image {
+                                detail: None,
+                                ..self.image()
+                            })
+                        } else {
+                            ToolResultContent::text(self.text())
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                UserContent::tool_result(
+                    id,
+                    OneOrMany::many(content).expect("There is at least one content"),
+                )
+            })
+            .collect::<Vec<_>>();
+        if results.is_empty() {
+            return None;
+        }
+        if self.rng.chance(0.3) {
+            results.push(self.user_content());
+        }
+        Some(Message::User {
+            content: OneOrMany::many(results).expect("There is at least one tool result"),
+        })
+    }
+
+    /// Random conversation of `turns` turns (i.e.: a user message, an assistant message, then
+    /// the results of its tool calls and the final answer if it called tools)
+    pub fn conversation(&mut self, turns: usize) -> Vec<Message> {
+        let mut messages = vec![];
+        for _ in 0..turns {
+            messages.push(self.user_message());
+            let assistant = self.assistant_message();
+            let results = self.tool_results_message(&assistant);
+            messages.push(assistant);
+            if let Some(results) = results {
+                messages.push(results);
+                messages.push(Message::assistant(self.text()));
+            }
+        }
+        messages
+    }
+}
+
+/// Check `property` on the conversations of `cases` generators (created from the seeds
+/// `0..cases` by `generator`), returning the first failure with its shortest failing prefix
+pub fn check(
+    generator: impl Fn(u64) -> MessageGenerator,
+    cases: u64,
+    property: impl Fn(&[Message]) -> Result<(), PropertyError>,
+) -> Result<(), PropertyFailure> {
+    for seed in 0..cases {
+        let conversation = generator(seed).conversation(DEFAULT_TURNS);
+        if let Err(error) = property(&conversation) {
+            // Shrink the conversation to its shortest failing prefix
+            let (length, error) = (1..conversation.len())
+                .find_map(|length| {
+                    property(&conversation[..length])
+                        .err()
+                        .map(|error| (length, error))
+                })
+                .unwrap_or((conversation.len(), error));
+            return Err(PropertyFailure {
+                seed,
+                conversation: conversation[..length].to_vec(),
+                error,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn mismatch(what: &str, expected: &impl fmt::Debug, actual: &impl fmt::Debug) -> PropertyError {
+    PropertyError::Mismatch(format!("{what}: expected {expected:?}, got {actual:?}"))
+}
+
+/// Check that `conversation` converted into OpenAI messages and back is the same, except that:
+/// - the tool results are sent in their own messages, before the other content of their user
+///   message,
+/// - the texts of an assistant message are sent before its tool calls,
+/// - the media types of the images and the documents (sent as text) are lost.
+pub fn openai_round_trip(conversation: &[Message]) -> Result<(), PropertyError> {
+    let mut expected = vec![];
+    for message in conversation {
+        match message.clone() {
+            Message::User { content } => {
+                let (results, other): (Vec<_>, Vec<_>) = content
+                    .into_iter()
+                    .partition(|content| matches!(content, UserContent::ToolResult(_)));
+                expected.extend(results.into_iter().map(|result| Message::User {
+                    content: OneOrMany::one(result),
+                }));
+                let other = other
+                    .into_iter()
+                    .map(|content| match content {
+                        UserContent::Image(image) => UserContent::Image(Image {
+                            media_type: None,
+                            ..image
+                        }),
+                        UserContent::Document(Document { data, .. }) => UserContent::text(data),
+                        content => content,
+                    })
+                    .collect::<Vec<_>>();
+                if let Ok(content) = OneOrMany::many(other) {
+                    expected.push(Message::User { content });
+                }
+            }
+            Message::Assistant { content } => {
+                let (texts, calls): (Vec<_>, Vec<_>) = content
+                    .into_iter()
+                    .partition(|content| matches!(content, AssistantContent::Text(_)));
+                expected.push(Message::Assistant {
+                    content: OneOrMany::many(texts.into_iter().chain(calls))
+                        .expect("There is at least one content"),
+                });
+            }
+        }
+    }
+
+    let mut actual = vec![];
+    for message in conversation {
+        let messages: Vec<openai::Message> = message.clone().try_into()?;
+        for message in messages {
+            actual.push(Message::try_from(message)?);
+        }
+    }
+    compare(&expected, &actual)
+}
+
+/// Check that `conversation` converted into Anthropic messages and back is the same, except for
+/// the details of the images
+pub fn anthropic_round_trip(conversation: &[Message]) -> Result<(), PropertyError> {
+    let clear_detail = |image: Image| Image {
+        detail: None,
+        ..image
+    };
+    let expected = conversation
+        .iter()
+        .map(|message| match message.clone() {
+            Message::User { content } => Message::User {
+                content: content.map(|content| match content {
+                    UserContent::Image(image) => UserContent::Image(clear_detail(image)),
+                    UserContent::ToolResult(ToolResult { id, content }) => {
+                        UserContent::ToolResult(ToolResult {
+                            id,
+                            content: content.map(|content| match content {
+                                ToolResultContent::Image(image) => {
+                                    ToolResultContent::Image(clear_detail(image))
+                                }
+                                content => content,
+                            }),
+                        })
+                    }
+                    content => content,
+                }),
+            },
+            message => message,
+        })
+        .collect::<Vec<_>>();
+
+    let actual = conversation
+        .iter()
+        .map(|message| {
+            let message: anthropic::completion::Message = message.clone().try_into()?;
+            Ok(Message::try_from(message)?)
+        })
+        .collect::<Result<Vec<_>, PropertyError>>()?;
+    compare(&expected, &actual)
+}
+
+fn compare(expected: &[Message], actual: &[Message]) -> Result<(), PropertyError> {
+    if expected.len() != actual.len() {
+        return Err(mismatch(
+            "Number of messages",
+            &expected.len(),
+            &actual.len(),
+        ));
+    }
+    for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+        if expected != actual {
+            return Err(mismatch(&format!("Message {i}"), expected, actual));
+        }
+    }
+    Ok(())
+}
+
+/// Check that `conversation` converted into Cohere messages keeps its roles, texts, tool calls
+/// and tool results (matched to their tool calls), given that Cohere:
+/// - sends each text of a user message in its own message, followed by its tool results,
+/// - joins the texts of an assistant message.
+pub fn cohere_preserved(conversation: &[Message]) -> Result<(), PropertyError> {
+    let mut converter = cohere::MessageConverter::default();
+    let mut calls = HashMap::new();
+
+    for (i, message) in conversation.iter().enumerate() {
+        let converted = converter.convert(message.clone())?;
+        let what = |field: &str| format!("Message {i} ({field})");
+        match message {
+            Message::User { content } => {
+                let texts = content
+                    .iter()
+                    .filter_map(|content| match content {
+                        UserContent::Text(Text { text }) => Some(text.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let results = content
+                    .iter()
+                    .filter_map(|content| match content {
+                        UserContent::ToolResult(result) => Some(result.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut converted = converted.into_iter();
+                let user_texts = converted
+                    .by_ref()
+                    .take(texts.len())
+                    .map(|message| match message {
+                        cohere::Message::User { message, .. } => Ok(message),
+                        _ => Err(PropertyError::Mismatch(what("role of a text"))),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if user_texts != texts {
+                    return Err(mismatch(&what("texts"), &texts, &user_texts));
+                }
+
+                let tool_results = match converted.next() {
+                    Some(cohere::Message::Tool { tool_results }) => tool_results,
+                    Some(_) => {
+                        return Err(PropertyError::Mismatch(what("role of the tool results")))
+                    }
+                    None => vec![],
+                };
+                if tool_results.len() != results.len() {
+                    return Err(mismatch(
+                        &what("number of tool results"),
+                        &results.len(),
+                        &tool_results.len(),
+                    ));
+                }
+                for (result, tool_result) in results.iter().zip(&tool_results) {
+                    let call: &ToolFunction = calls.get(&result.id).ok_or_else(|| {
+                        PropertyError::Mismatch(format!("Unknown tool call {}", result.id))
+                    })?;
+                    if (&tool_result.call.name, &tool_result.call.parameters)
+                        != (&call.name, &call.arguments)
+                    {
+                        return Err(mismatch(
+                            &what("call of a tool result"),
+                            call,
+                            &tool_result.call,
+                        ));
+                    }
+                    let outputs = result
+                        .content
+                        .iter()
+                        .map(|content| match content {
+                            ToolResultContent::Text(Text { text }) => {
+                                match serde_json::from_str(&text) {
+                                    Ok(output @ serde_json::Value::Object(_)) => output,
+                                    _ => json!({ "result": text }),
+                                }
+                            }
+                            ToolResultContent::Image(_) => serde_json::Value::Null,
+                        })
+                        .collect::<Vec<_>>();
+                    if outputs != tool_result.outputs {
+                        return Err(mismatch(
+                            &what("outputs of a tool result"),
+                            &outputs,
+                            &tool_result.outputs,
+                        ));
+                    }
+                }
+                if let Some(message) = converted.next() {
+                    return Err(PropertyError::Mismatch(format!(
+                        "{}: unexpected {}",
+                        what("extra message"),
+                        serde_json::to_string(&message).unwrap_or_default()
+                    )));
+                }
+            }
+            Message::Assistant { content } => {
+                let mut texts = vec![];
+                let mut function_calls = vec![];
+                for content in content.iter() {
+                    match content {
+                        AssistantContent::Text(Text { text }) => texts.push(text.clone()),
+                        AssistantContent::ToolCall(ToolCall { id, function }) => {
+                            calls.insert(id.clone(), function.clone());
+                            function_calls
+                                .push((function.name.clone(), function.arguments.clone()));
+                        }
+                    }
+                }
+                let [cohere::Message::Chatbot {
+                    message,
+                    tool_calls,
+                }] = converted.as_slice()
+                else {
+                    return Err(PropertyError::Mismatch(what(
+                        "expected a single chatbot message",
+                    )));
+                };
+                let text = texts.join("\n");
+                if *message != text {
+                    return Err(mismatch(&what("text"), &text, message));
+                }
+                let tool_calls = tool_calls
+                    .iter()
+                    .map(|call| (call.name.clone(), call.parameters.clone()))
+                    .collect::<Vec<_>>();
+                if tool_calls != function_calls {
+                    return Err(mismatch(&what("tool calls"), &function_calls, &tool_calls));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        // The same seed generates the same conversation
+        let conversation = MessageGenerator::new(7).images(true).conversation(3);
+        assert_eq!(
+            conversation,
+            MessageGenerator::new(7).images(true).conversation(3)
+        );
+        assert!(conversation.len() >= 6);
+
+        check(
+            |seed| {
+                MessageGenerator::new(seed)
+                    .images(true)
+                    .audio(true)
+                    .documents(true)
+            },
+            300,
+            openai_round_trip,
+        )
+        .unwrap();
+        check(
+            |seed| {
+                MessageGenerator::new(seed)
+                    .images(true)
+                    .documents(true)
+                    .tool_result_images(true)
+            },
+            300,
+            anthropic_round_trip,
+        )
+        .unwrap();
+        check(MessageGenerator::new, 300, cohere_preserved).unwrap();
+
+        // A dropped tool result is reported with the shortest failing conversation
+        let has_tool_result = |message: &Message| match message {
+            Message::User { content } => content
+                .iter()
+                .any(|content| matches!(content, UserContent::ToolResult(_))),
+            Message::Assistant { .. } => false,
+        };
+        let failure = check(MessageGenerator::new, 10, |conversation| {
+            if conversation.iter().any(has_tool_result) {
+                Err(PropertyError::Mismatch("Dropped tool result".to_string()))
+            } else {
+                Ok(())
+            }
+        })
+        .unwrap_err();
+        assert!(has_tool_result(failure.conversation.last().unwrap()));
+        assert!(failure.to_string().contains("Dropped tool result"));
+    }
+}
@@ -0,0 +1,155 @@
+//! Health checks of the provider clients, e.g.: for orchestrators to verify the connectivity to
+//! a provider (and the validity of its API key) before routing traffic to it.
+//!
+//! The [HealthCheck] trait is implemented by the provider clients, with a cheap authenticated
+//! call to the provider (e.g.: listing its models, or a 1-token completion).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{health::HealthCheck, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let health = openai.health().await;
+//! if !health.healthy {
+//!     println!("OpenAI is unavailable ({:?}): {:?}", health.auth, health.error);
+//! }
+//! println!("OpenAI latency: {:?}", health.latency);
+//! ```
+use std::{future::Future, time::Duration};
+
+use web_time::Instant;
+
+use crate::http_client::HttpBackend;
+
+/// Status of the authentication of a client with its provider.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthStatus {
+    /// The provider accepted the credentials of the client
+    Valid,
+    /// The provider rejected the credentials of the client (401 or 403 status)
+    Invalid,
+    /// The provider could not be reached, or failed before checking the credentials
+    Unknown,
+}
+
+/// Result of a health check of a provider client.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Health {
+    /// Whether the provider is reachable and accepted the request
+    pub healthy: bool,
+    /// Status of the authentication of the client
+    pub auth: AuthStatus,
+    /// HTTP status of the response (if any)
+    pub status: Option<u16>,
+    /// Time taken by the call to the provider
+    pub latency: Duration,
+    /// Error of the call (if any): the connection error, or the body of the error response
+    pub error: Option<String>,
+}
+
+/// Trait for the provider clients able to check their connectivity to the provider.
+pub trait HealthCheck: Send + Sync {
+    /// Perform a cheap authenticated call to the provider and return its outcome.
+    fn health(&self) -> impl Future<Output = Health> + Send;
+}
+
+/// Send `request` through `http` and return the health of the provider from its response.
+pub(crate) async fn check(http: &HttpBackend, request: reqwest::RequestBuilder) -> Health {
+    let start = Instant::now();
+    let response = http.send(request).await;
+    let latency = start.elapsed();
+
+    let response = match response {
+        Ok(response) => response,
+        Err(error) => {
+            return Health {
+                healthy: false,
+                auth: AuthStatus::Unknown,
+                status: None,
+                latency,
+                error: Some(error.to_string()),
+            }
+        }
+    };
+
+    let status = response.status();
+    let auth = if status.is_success() {
+        AuthStatus::Valid
+    } else if status == reqwest::StatusCode::UNAUTHORIZED
+        || status == reqwest::StatusCode::FORBIDDEN
+    {
+        AuthStatus::Invalid
+    } else {
+        AuthStatus::Unknown
+    };
+    let error = if status.is_success() {
+        None
+    } else {
+        Some(response.text().await.unwrap_or_else(|e| e.to_string()))
+    };
+
+    Health {
+        healthy: status.is_success(),
+        auth,
+        status: Some(status.as_u16()),
+        latency,
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::http_client::{HttpClient, HttpClientError};
+
+    struct FakeClient(u16);
+
+    impl HttpClient for FakeClient {
+        async fn send(
+            &self,
+            _request: http::Request<Bytes>,
+        ) -> Result<http::Response<Bytes>, HttpClientError> {
+            Ok(http::Response::builder()
+                .status(self.0)
+                .body(Bytes::from_static(b"{\"error\":\"Invalid API key\"}"))?)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check() {
+        let request = || reqwest::Client::new().get("https://example.com/models");
+
+        let health = check(
+            &HttpBackend::default().with_client(FakeClient(200)),
+            request(),
+        )
+        .await;
+        assert!(health.healthy);
+        assert_eq!(health.auth, AuthStatus::Valid);
+        assert_eq!(health.error, None);
+
+        let health = check(
+            &HttpBackend::default().with_client(FakeClient(401)),
+            request(),
+        )
+        .await;
+        assert!(!health.healthy);
+        assert_eq!(health.auth, AuthStatus::Invalid);
+        assert_eq!(health.status, Some(401));
+        assert_eq!(
+            health.error.as_deref(),
+            Some("{\"error\":\"Invalid API key\"}")
+        );
+
+        let health = check(
+            &HttpBackend::default().with_client(FakeClient(503)),
+            request(),
+        )
+        .await;
+        assert!(!health.healthy);
+        assert_eq!(health.auth, AuthStatus::Unknown);
+    }
+}
@@ -0,0 +1,677 @@
+//! Pluggable HTTP backend of the provider clients.
+//!
+//! By default, the provider clients (e.g.: [crate::providers::openai::Client]) send their
+//! requests with [reqwest]. The [HttpClient] trait allows replacing it with any other client
+//! (e.g.: `hyper`, a client going through a corporate proxy stack, or a fake returning canned
+//! responses in unit tests), using the `with_http_client` method of the provider clients.
+//! To only configure the reqwest client (e.g.: proxy, custom root certificates, mTLS), pass a
+//! pre-built [reqwest::Client] to the `with_reqwest_client` method of the provider clients.
+//!
+//! The requests and responses are exchanged as [http::Request] and [http::Response] with a
+//! [Bytes] body, so that the trait doesn't depend on any HTTP library or async runtime.
+//! The default headers of the provider client (e.g.: the `Authorization` header of the OpenAI
+//! client) are added to the requests before they are passed to the custom client.
+//!
+//! The JSON requests and responses of a provider client can also be modified in flight with
+//! [Transform] plugins, added with the `with_transform` method of the provider clients (e.g.:
+//! to add the headers of an enterprise gateway with [GatewayHeaders], or strip the fields a
+//! proxy doesn't support with [StripFields]), rather than through the additional parameters of
+//! the requests. The transforms apply in the order they were added, to the requests after the
+//! default headers and API key of the client are set, and to the non-streaming JSON responses.
+//!
+//! Note: since the body of the responses is returned in full, the streaming responses (e.g.:
+//! [crate::providers::anthropic] streaming completions) are only received once complete when
+//! using a custom client.
+//!
+//! # Example
+//! ```rust
+//! use bytes::Bytes;
+//! use mcp_rig::{
+//!     http_client::{HttpClient, HttpClientError},
+//!     providers::openai,
+//! };
+//!
+//! struct FakeClient;
+//!
+//! impl HttpClient for FakeClient {
+//!     async fn send(
+//!         &self,
+//!         request: http::Request<Bytes>,
+//!     ) -> Result<http::Response<Bytes>, HttpClientError> {
+//!         println!("{} {}", request.method(), request.uri());
+//!         Ok(http::Response::new(Bytes::from_static(b"{...}")))
+//!     }
+//! }
+//!
+//! let openai = openai::Client::new("sk-...").with_http_client(FakeClient);
+//! ```
+use std::{future::Future, sync::Arc};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+
+use crate::{
+    completion::CompletionError,
+    embeddings::EmbeddingError,
+    key_provider::{KeyError, KeyPlacement, KeyProvider, KeyProviderDyn},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpClientError {
+    /// Error of the reqwest client (e.g.: connection error, timeout, etc.)
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// The request cannot be sent through a custom client (e.g.: streaming body)
+    #[error("InvalidRequest: {0}")]
+    InvalidRequest(String),
+
+    /// Error returned by a custom client
+    #[error("ClientError: {0}")]
+    ClientError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// The key provider of the client failed to provide an API key
+    #[error("KeyError: {0}")]
+    KeyError(KeyError),
+
+    /// The [cassette](crate::cassette::Cassette) being replayed has no interaction matching
+    /// the request
+    #[error("NoRecordedInteraction: {0}")]
+    NoRecordedInteraction(String),
+}
+
+impl From<http::Error> for HttpClientError {
+    fn from(error: http::Error) -> Self {
+        HttpClientError::InvalidRequest(error.to_string())
+    }
+}
+
+impl From<HttpClientError> for CompletionError {
+    fn from(error: HttpClientError) -> Self {
+        match error {
+            HttpClientError::HttpError(error) => CompletionError::HttpError(error),
+            error => CompletionError::RequestError(error.into()),
+        }
+    }
+}
+
+impl From<HttpClientError> for EmbeddingError {
+    fn from(error: HttpClientError) -> Self {
+        match error {
+            HttpClientError::HttpError(error) => EmbeddingError::HttpError(error),
+            error => EmbeddingError::ProviderError(error.to_string()),
+        }
+    }
+}
+
+/// Trait for the HTTP clients used by the providers to send their requests.
+pub trait HttpClient: Send + Sync {
+    /// Send `request` and return the response (with its full body).
+    fn send(
+        &self,
+        request: http::Request<Bytes>,
+    ) -> impl Future<Output = Result<http::Response<Bytes>, HttpClientError>> + Send;
+
+    /// Send `request` and return the response, with its body cut after `max_bytes` bytes, and
+    /// whether it was cut. By default, the full body is received before it is cut: the clients
+    /// able to stop reading the body (e.g.: [reqwest::Client]) override this method.
+    fn send_limited(
+        &self,
+        request: http::Request<Bytes>,
+        max_bytes: usize,
+    ) -> impl Future<Output = Result<(http::Response<Bytes>, bool), HttpClientError>> + Send {
+        async move {
+            let (parts, body) = self.send(request).await?.into_parts();
+            let truncated = body.len() > max_bytes;
+            let body = body.slice(..body.len().min(max_bytes));
+            Ok((http::Response::from_parts(parts, body), truncated))
+        }
+    }
+}
+
+impl HttpClient for reqwest::Client {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn send(
+        &self,
+        request: http::Request<Bytes>,
+    ) -> Result<http::Response<Bytes>, HttpClientError> {
+        let response = self.execute(request.try_into()?).await?;
+
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers().clone();
+        }
+        Ok(builder.body(response.bytes().await?)?)
+    }
+
+    /// Stop reading the body of the response after `max_bytes` bytes
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn send_limited(
+        &self,
+        request: http::Request<Bytes>,
+        max_bytes: usize,
+    ) -> Result<(http::Response<Bytes>, bool), HttpClientError> {
+        let mut response = self.execute(request.try_into()?).await?;
+
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers().clone();
+        }
+        let mut body = Vec::new();
+        let mut truncated = false;
+        while let Some(chunk) = response.chunk().await? {
+            let remaining = max_bytes - body.len();
+            if chunk.len() > remaining {
+                body.extend_from_slice(&chunk[..remaining]);
+                truncated = true;
+                break;
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok((builder.body(Bytes::from(body))?, truncated))
+    }
+}
+
+/// Dyn-compatible version of the [HttpClient] trait.
+pub(crate) trait HttpClientDyn: Send + Sync {
+    fn send(
+        &self,
+        request: http::Request<Bytes>,
+    ) -> BoxFuture<'_, Result<http::Response<Bytes>, HttpClientError>>;
+
+    fn send_limited(
+        &self,
+        request: http::Request<Bytes>,
+        max_bytes: usize,
+    ) -> BoxFuture<'_, Result<(http::Response<Bytes>, bool), HttpClientError>>;
+}
+
+impl<T: HttpClient> HttpClientDyn for T {
+    fn send(
+        &self,
+        request: http::Request<Bytes>,
+    ) -> BoxFuture<'_, Result<http::Response<Bytes>, HttpClientError>> {
+        Box::pin(HttpClient::send(self, request))
+    }
+
+    fn send_limited(
+        &self,
+        request: http::Request<Bytes>,
+        max_bytes: usize,
+    ) -> BoxFuture<'_, Result<(http::Response<Bytes>, bool), HttpClientError>> {
+        Box::pin(HttpClient::send_limited(self, request, max_bytes))
+    }
+}
+
+/// Plugin modifying the JSON requests and responses of a provider client (see the
+/// [module](self) docs). The requests whose body is not JSON (e.g.: file uploads) are passed
+/// with a `null` body, whose changes are ignored.
+pub trait Transform: Send + Sync {
+    /// Modify the outgoing `request` (e.g.: its URL, headers or body)
+    fn request(&self, request: &mut http::Request<Value>) {
+        let _ = request;
+    }
+
+    /// Modify the incoming `response` (e.g.: its status or body)
+    fn response(&self, response: &mut http::Response<Value>) {
+        let _ = response;
+    }
+}
+
+/// Transform adding headers to the requests (e.g.: the routing or authentication headers of an
+/// enterprise gateway), overriding those of the client.
+#[derive(Clone, Debug, Default)]
+pub struct GatewayHeaders {
+    headers: HeaderMap,
+}
+
+impl GatewayHeaders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the header `name` with `value`
+    ///
+    /// # Panics
+    /// If the header name or value is invalid.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(
+            reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .expect("Header name should parse"),
+            value.parse().expect("Header value should parse"),
+        );
+        self
+    }
+}
+
+impl Transform for GatewayHeaders {
+    fn request(&self, request: &mut http::Request<Value>) {
+        for (name, value) in &self.headers {
+            request.headers_mut().insert(name, value.clone());
+        }
+    }
+}
+
+/// Transform removing fields from the JSON body of the requests (e.g.: the parameters a proxy
+/// doesn't support), by their JSON pointer (e.g.: `/stream_options`).
+#[derive(Clone, Debug, Default)]
+pub struct StripFields {
+    pointers: Vec<String>,
+}
+
+impl StripFields {
+    pub fn new(pointers: &[&str]) -> Self {
+        Self {
+            pointers: pointers.iter().map(|pointer| pointer.to_string()).collect(),
+        }
+    }
+}
+
+impl Transform for StripFields {
+    fn request(&self, request: &mut http::Request<Value>) {
+        for pointer in &self.pointers {
+            let Some((parent, field)) = pointer.rsplit_once('/') else {
+                continue;
+            };
+            match request.body_mut().pointer_mut(parent) {
+                Some(Value::Object(object)) => {
+                    object.remove(field);
+                }
+                Some(Value::Array(items)) => {
+                    if let Ok(index) = field.parse::<usize>() {
+                        if index < items.len() {
+                            items.remove(index);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// HTTP backend of a provider client: its reqwest client (the default), or a custom
+/// [HttpClient] set with `with_http_client`.
+#[derive(Clone, Default)]
+pub(crate) struct HttpBackend {
+    /// Default headers of the provider client, added to the requests missing them
+    headers: HeaderMap,
+    custom: Option<Arc<dyn HttpClientDyn>>,
+    keys: Option<(Arc<dyn KeyProviderDyn>, KeyPlacement)>,
+    transforms: Vec<Arc<dyn Transform>>,
+}
+
+impl HttpBackend {
+    /// Create a backend adding `headers` to the requests. They should be the default headers
+    /// of the reqwest client of the provider.
+    pub(crate) fn new(headers: HeaderMap) -> Self {
+        Self {
+            headers,
+            custom: None,
+            keys: None,
+            transforms: vec![],
+        }
+    }
+
+    /// Add the header `name` with `value` to the requests.
+    ///
+    /// # Panics
+    /// If the header name or value is invalid.
+    pub(crate) fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(
+            reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .expect("Header name should parse"),
+            value.parse().expect("Header value should parse"),
+        );
+        self
+    }
+
+    /// Send the requests through `client` instead of the reqwest client.
+    pub(crate) fn with_client(mut self, client: impl HttpClient + 'static) -> Self {
+        self.custom = Some(Arc::new(client));
+        self
+    }
+
+    /// Set the API key of each request (at `placement`) with the key from `keys`.
+    pub(crate) fn with_keys(
+        mut self,
+        keys: impl KeyProvider + 'static,
+        placement: KeyPlacement,
+    ) -> Self {
+        self.keys = Some((Arc::new(keys), placement));
+        self
+    }
+
+    /// Modify the JSON requests and responses with `transform`, after the previous transforms.
+    pub(crate) fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.transforms.push(Arc::new(transform));
+        self
+    }
+
+    /// Send `request` through the backend.
+    pub(crate) async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, HttpClientError> {
+        let (client, request) = request.build_split();
+        self.execute(&client, request?).await
+    }
+
+    /// Execute `request` (built by `client`) through the backend. With a key provider, the
+    /// request is retried with the next key on a 401 or 429 response.
+    pub(crate) async fn execute(
+        &self,
+        client: &reqwest::Client,
+        mut request: reqwest::Request,
+    ) -> Result<reqwest::Response, HttpClientError> {
+        let Some((keys, placement)) = &self.keys else {
+            return self.execute_once(client, request).await;
+        };
+
+        let attempts = keys.attempts().max(1);
+        let mut attempt = 1;
+        loop {
+            // Requests with a streaming body cannot be cloned (nor retried)
+            let retry = if attempt < attempts {
+                request.try_clone()
+            } else {
+                None
+            };
+
+            let key = keys.key().await.map_err(HttpClientError::KeyError)?;
+            placement.apply(&mut request, &key)?;
+            let response = self.execute_once(client, request).await?;
+
+            let status = response.status();
+            if status != reqwest::StatusCode::UNAUTHORIZED
+                && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+            {
+                return Ok(response);
+            }
+            keys.report_failure(&key, status.as_u16());
+
+            let Some(next) = retry else {
+                return Ok(response);
+            };
+            tracing::warn!(target: "rig",
+                "Request failed with status {} (attempt {}/{}), retrying with the next API key",
+                status,
+                attempt,
+                attempts
+            );
+            request = next;
+            attempt += 1;
+        }
+    }
+
+    async fn execute_once(
+        &self,
+        client: &reqwest::Client,
+        mut request: reqwest::Request,
+    ) -> Result<reqwest::Response, HttpClientError> {
+        // The client may not have the default headers of the provider (e.g.: custom client, or
+        // reqwest client set with `with_reqwest_client`)
+        for (name, value) in &self.headers {
+            if !request.headers().contains_key(name) {
+                request.headers_mut().insert(name, value.clone());
+            }
+        }
+
+        if self.transforms.is_empty() {
+            return self.send_once(client, request).await;
+        }
+        self.transform_request(&mut request)?;
+        let response = self.send_once(client, request).await?;
+        self.transform_response(response).await
+    }
+
+    /// Apply the transforms to `request`
+    fn transform_request(&self, request: &mut reqwest::Request) -> Result<(), HttpClientError> {
+        let json = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .and_then(|bytes| serde_json::from_slice::<Value>(bytes).ok());
+        let mut builder = http::Request::builder()
+            .method(request.method().clone())
+            .uri(request.url().as_str());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = request.headers().clone();
+        }
+        let mut transformed = builder.body(json.clone().unwrap_or_default())?;
+        self.transforms
+            .iter()
+            .for_each(|transform| transform.request(&mut transformed));
+
+        let (parts, body) = transformed.into_parts();
+        *request.method_mut() = parts.method;
+        *request.url_mut() = reqwest::Url::parse(&parts.uri.to_string())
+            .map_err(|e| HttpClientError::InvalidRequest(e.to_string()))?;
+        *request.headers_mut() = parts.headers;
+        if json.is_some() {
+            *request.body_mut() = Some(serde_json::to_vec(&body).unwrap_or_default().into());
+        }
+        Ok(())
+    }
+
+    /// Apply the transforms to `response`, unless it is streamed or not JSON
+    async fn transform_response(
+        &self,
+        response: reqwest::Response,
+    ) -> Result<reqwest::Response, HttpClientError> {
+        let streamed = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("event-stream"));
+        if streamed {
+            return Ok(response);
+        }
+
+        let status = response.status();
+        let version = response.version();
+        let headers = response.headers().clone();
+        let bytes = response.bytes().await?;
+        let Ok(json) = serde_json::from_slice::<Value>(&bytes) else {
+            return Ok(crate::payload_log::rebuild_response(
+                status, version, headers, bytes,
+            ));
+        };
+
+        let mut builder = http::Response::builder().status(status).version(version);
+        if let Some(response_headers) = builder.headers_mut() {
+            *response_headers = headers;
+        }
+        let mut transformed = builder.body(json)?;
+        self.transforms
+            .iter()
+            .for_each(|transform| transform.response(&mut transformed));
+
+        let (parts, body) = transformed.into_parts();
+        Ok(crate::payload_log::rebuild_response(
+            parts.status,
+            parts.version,
+            parts.headers,
+            serde_json::to_vec(&body).unwrap_or_default(),
+        ))
+    }
+
+    async fn send_once(
+        &self,
+        client: &reqwest::Client,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, HttpClientError> {
+        let Some(custom) = &self.custom else {
+            return Ok(client.execute(request).await?);
+        };
+
+        let body = match request.body() {
+            Some(body) => Bytes::copy_from_slice(body.as_bytes().ok_or_else(|| {
+                HttpClientError::InvalidRequest("Streaming bodies are not supported".into())
+            })?),
+            None => Bytes::new(),
+        };
+        let mut builder = http::Request::builder()
+            .method(request.method().clone())
+            .uri(request.url().as_str());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = request.headers().clone();
+        }
+
+        let response = custom.send(builder.body(body)?).await?;
+        let (parts, body) = response.into_parts();
+        Ok(crate::payload_log::rebuild_response(
+            parts.status,
+            parts.version,
+            parts.headers,
+            body,
+        ))
+    }
+}
+
+/// Build a `multipart/form-data` body with the text `fields` followed by the file `filename`,
+/// and return its content type and bytes. The body is built by hand (instead of using
+/// reqwest's multipart forms) so that it can be sent through any HTTP backend.
+pub(crate) fn multipart_body(
+    fields: &[(&str, &str)],
+    file_field: &str,
+    filename: &str,
+    content: Vec<u8>,
+) -> (String, Vec<u8>) {
+    let boundary = format!(
+        "mcp-rig-{}",
+        web_time::SystemTime::now()
+            .duration_since(web_time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+    );
+
+    let mut body = String::new();
+    for (name, value) in fields {
+        body.push_str(&format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+        ));
+    }
+    body.push_str(&format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"{file_field}\"; filename=\"{}\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n",
+        filename.replace('"', "\\\"")
+    ));
+
+    let mut body = body.into_bytes();
+    body.extend(content);
+    body.extend(format!("\r\n--{boundary}--\r\n").into_bytes());
+    (format!("multipart/form-data; boundary={boundary}"), body)
+}
+
+impl std::fmt::Debug for HttpBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpBackend")
+            .field("custom", &self.custom.is_some())
+            .field("keys", &self.keys.is_some())
+            .field("transforms", &self.transforms.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct FakeClient {
+        requests: Arc<Mutex<Vec<http::Request<Bytes>>>>,
+    }
+
+    impl HttpClient for FakeClient {
+        async fn send(
+            &self,
+            request: http::Request<Bytes>,
+        ) -> Result<http::Response<Bytes>, HttpClientError> {
+            self.requests.lock().unwrap().push(request);
+            Ok(http::Response::builder()
+                .status(201)
+                .body(Bytes::from_static(b"{\"ok\":true}"))?)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_client() {
+        let fake = FakeClient::default();
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", "Bearer sk-123".parse().unwrap());
+        let backend = HttpBackend::new(headers).with_client(fake.clone());
+
+        let response = backend
+            .send(
+                reqwest::Client::new()
+                    .post("https://example.com/v1/chat")
+                    .header("X-Custom", "1")
+                    .body("{\"model\":\"m\"}"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 201);
+        assert_eq!(
+            response.json::<serde_json::Value>().await.unwrap(),
+            serde_json::json!({"ok": true})
+        );
+
+        let requests = fake.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method(), http::Method::POST);
+        assert_eq!(requests[0].uri(), "https://example.com/v1/chat");
+        assert_eq!(requests[0].headers()["Authorization"], "Bearer sk-123");
+        assert_eq!(requests[0].headers()["X-Custom"], "1");
+        assert_eq!(requests[0].body().as_ref(), b"{\"model\":\"m\"}");
+    }
+
+    struct Unwrap;
+
+    impl Transform for Unwrap {
+        fn response(&self, response: &mut http::Response<Value>) {
+            *response.body_mut() = response.body()["ok"].clone();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transforms() {
+        let fake = FakeClient::default();
+        let backend = HttpBackend::new(HeaderMap::new())
+            .with_client(fake.clone())
+            .with_transform(GatewayHeaders::new().header("X-Gateway-Route", "llm"))
+            .with_transform(StripFields::new(&["/stream_options", "/messages/1"]))
+            .with_transform(Unwrap);
+
+        let response = backend
+            .send(
+                reqwest::Client::new()
+                    .post("https://example.com/v1/chat")
+                    .json(&serde_json::json!({
+                        "model": "m",
+                        "messages": ["Hello", "Bye"],
+                        "stream_options": {"include_usage": true}
+                    })),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            response.json::<Value>().await.unwrap(),
+            serde_json::json!(true)
+        );
+
+        let requests = fake.requests.lock().unwrap();
+        assert_eq!(requests[0].headers()["X-Gateway-Route"], "llm");
+        assert_eq!(
+            serde_json::from_slice::<Value>(requests[0].body()).unwrap(),
+            serde_json::json!({"model": "m", "messages": ["Hello"]})
+        );
+    }
+}
@@ -0,0 +1,504 @@
+//! Built-in `http_request` tool, for agents to call the REST APIs for which there is no MCP
+//! server, under policy controls:
+//! - only the URLs of the allowed domains are requested (see [HttpTool::allow_domain]), and the
+//!   redirects are not followed (the model sees them, and may request their location if it is
+//!   allowed),
+//! - only `GET` requests are sent, unless `POST` requests are allowed (see
+//!   [HttpTool::allow_post]),
+//! - the credentials of the APIs are injected into the headers of their requests from a
+//!   [SecretsProvider] (see [HttpTool::secret_header]): the model never sees them,
+//! - the bodies of the responses are cut after a maximum number of bytes (see
+//!   [HttpTool::max_response_bytes]), the rest of the body not being read,
+//! - the requests fail after a timeout (see [HttpTool::timeout]).
+//!
+//! The tool is not available on wasm targets, whose `fetch` follows the redirects.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{http_tool::HttpTool, providers::openai, secrets::EnvSecrets};
+//!
+//! // The token is read from the environment variable `API_GITHUB_COM_TOKEN`
+//! let http = HttpTool::new()
+//!     .allow_domain("api.github.com")
+//!     .secrets(EnvSecrets::new())
+//!     .secret_header("api.github.com", "Authorization", "Bearer {token}");
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("You answer questions about the GitHub repositories.")
+//!     .tool(http.tool())
+//!     .build();
+//! ```
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use futures::future::{self, Either};
+use futures_timer::Delay;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::{
+    http_client::{HttpClient, HttpClientDyn, HttpClientError},
+    secrets::{SecretsError, SecretsProvider, SecretsProviderDyn},
+    tool::{self, Tool, ToolAnnotations},
+};
+
+/// Default maximum number of bytes of the bodies of the responses returned to the model.
+const DEFAULT_MAX_RESPONSE_BYTES: usize = 100_000;
+
+/// Default timeout of the requests.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpToolError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+
+    /// The domain of the URL is not allowed
+    #[error("Domain not allowed: {0}")]
+    DomainNotAllowed(String),
+
+    /// The method is not allowed (e.g.: `POST` without [HttpTool::allow_post])
+    #[error("Method not allowed: {0}")]
+    MethodNotAllowed(String),
+
+    #[error("Invalid header: {0}")]
+    InvalidHeader(String),
+
+    /// The response was not received before the timeout
+    #[error("Request timed out after {0:?}")]
+    Timeout(Duration),
+
+    /// A secret of the headers is missing, or the secrets provider failed
+    #[error("SecretsError: {0}")]
+    SecretsError(#[from] SecretsError),
+
+    #[error("HttpClientError: {0}")]
+    HttpClientError(#[from] HttpClientError),
+}
+
+impl From<http::Error> for HttpToolError {
+    fn from(error: http::Error) -> Self {
+        HttpToolError::InvalidHeader(error.to_string())
+    }
+}
+
+/// Header of the requests to a domain, whose value is rendered from secrets
+#[derive(Clone, Debug)]
+struct SecretHeader {
+    domain: String,
+    name: String,
+    /// Value of the header, with the names of the secrets in braces (e.g.: `Bearer {token}`)
+    template: String,
+}
+
+/// Arguments of the `http_request` tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct HttpRequestArgs {
+    /// HTTP method: `GET` (the default) or `POST`
+    pub method: Option<String>,
+    /// URL of the request, including its query string
+    pub url: String,
+    /// Additional headers of the request (e.g.: `{"Accept": "application/json"}`)
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Body of a `POST` request: sent as JSON, unless it is a string (sent as is)
+    pub body: Option<serde_json::Value>,
+}
+
+/// HTTP request tool with policy controls (see the [module](self) docs).
+#[derive(Clone)]
+pub struct HttpTool {
+    client: Arc<dyn HttpClientDyn>,
+    /// Allowed domains (e.g.: `api.github.com`, or `*.example.com` for its subdomains)
+    domains: Vec<String>,
+    allow_post: bool,
+    secrets: Option<Arc<dyn SecretsProviderDyn>>,
+    secret_headers: Vec<SecretHeader>,
+    max_response_bytes: usize,
+    timeout: Duration,
+}
+
+impl Default for HttpTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpTool {
+    /// Tool sending its requests with a reqwest client not following the redirects, allowed
+    /// to request no domain (until some are allowed with [HttpTool::allow_domain])
+    ///
+    /// # Panics
+    /// If the reqwest client cannot be built (e.g.: its TLS backend fails to initialize).
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("Reqwest client should build");
+
+        Self {
+            client: Arc::new(client),
+            domains: vec![],
+            allow_post: false,
+            secrets: None,
+            secret_headers: vec![],
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Send the requests with a reqwest client built from `builder` (e.g.: going through a
+    /// proxy). The client doesn't follow the redirects, whatever the policy of `builder`.
+    pub fn client_builder(
+        mut self,
+        builder: reqwest::ClientBuilder,
+    ) -> Result<Self, HttpToolError> {
+        let client = builder
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(HttpClientError::from)?;
+        self.client = Arc::new(client);
+        Ok(self)
+    }
+
+    /// Send the requests with `client`, which must not follow the redirects
+    #[cfg(test)]
+    fn http_client(mut self, client: impl HttpClient + 'static) -> Self {
+        self.client = Arc::new(client);
+        self
+    }
+
+    /// Allow the requests to `domain` (e.g.: `api.github.com`), or to its subdomains if it starts
+    /// with `*.` (e.g.: `*.example.com`)
+    pub fn allow_domain(mut self, domain: &str) -> Self {
+        self.domains.push(domain.to_lowercase());
+        self
+    }
+
+    /// Allow the `POST` requests (the tool is then annotated as destructive, for the
+    /// [ApprovalPolicy](crate::tool::ApprovalPolicy) of the agent)
+    pub fn allow_post(mut self) -> Self {
+        self.allow_post = true;
+        self
+    }
+
+    /// Read the secrets of the headers (see [HttpTool::secret_header]) from `secrets`
+    pub fn secrets(mut self, secrets: impl SecretsProvider + 'static) -> Self {
+        self.secrets = Some(Arc::new(secrets));
+        self
+    }
+
+    /// Add the header `name` to the requests to `domain` (e.g.: `api.github.com`, or
+    /// `*.example.com` for its subdomains) if it is allowed, with the value `template` whose
+    /// secrets in braces (e.g.: `Bearer {token}`) are read from the scope `domain` of the secrets
+    /// provider
+    pub fn secret_header(mut self, domain: &str, name: &str, template: &str) -> Self {
+        self.secret_headers.push(SecretHeader {
+            domain: domain.to_lowercase(),
+            name: name.to_string(),
+            template: template.to_string(),
+        });
+        self
+    }
+
+    /// Cut the bodies of the responses after `max_bytes` bytes (100 kB by default)
+    pub fn max_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_response_bytes = max_bytes;
+        self
+    }
+
+    /// Fail the requests whose response is not received after `timeout` (30 seconds by
+    /// default)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Whether the requests to `host` are allowed
+    fn is_allowed(&self, host: &str) -> bool {
+        self.domains
+            .iter()
+            .any(|domain| domain_matches(domain, host))
+    }
+
+    /// `template` with its secrets (of the scope `scope`) in braces replaced by their value
+    async fn render_secrets(&self, scope: &str, template: &str) -> Result<String, HttpToolError> {
+        let mut rendered = String::new();
+        let mut rest = template;
+        while let Some((before, after)) = rest.split_once('{') {
+            let Some((name, after)) = after.split_once('}') else {
+                break;
+            };
+            let secret = match &self.secrets {
+                Some(secrets) => secrets.secret(scope, name).await?,
+                None => None,
+            };
+            let secret = secret.ok_or_else(|| SecretsError::NotFound {
+                scope: scope.to_string(),
+                name: name.to_string(),
+            })?;
+            rendered.push_str(before);
+            rendered.push_str(&secret);
+            rest = after;
+        }
+        rendered.push_str(rest);
+        Ok(rendered)
+    }
+
+    /// Send the request `method` to `url` with `headers` and `body` (if allowed), returning the
+    /// status, content type and body (cut after the maximum number of bytes) of the response
+    pub async fn request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &HashMap<String, String>,
+        body: Option<serde_json::Value>,
+    ) -> Result<String, HttpToolError> {
+        let method = method.to_uppercase();
+        match method.as_str() {
+            "GET" => {}
+            "POST" if self.allow_post => {}
+            _ => return Err(HttpToolError::MethodNotAllowed(method)),
+        }
+        let parsed =
+            reqwest::Url::parse(url).map_err(|e| HttpToolError::InvalidUrl(e.to_string()))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(HttpToolError::InvalidUrl(url.to_string()));
+        }
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| HttpToolError::InvalidUrl(url.to_string()))?
+            .to_lowercase();
+        if !self.is_allowed(&host) {
+            return Err(HttpToolError::DomainNotAllowed(host));
+        }
+
+        // The URL sent is the one checked
+        let mut request = http::Request::builder()
+            .method(method.as_str())
+            .uri(parsed.as_str());
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let body = match body {
+            Some(serde_json::Value::String(text)) => Bytes::from(text),
+            Some(json) => {
+                request = request.header("Content-Type", "application/json");
+                Bytes::from(json.to_string())
+            }
+            None => Bytes::new(),
+        };
+        let mut request = request.body(body)?;
+        // The secret headers override those of the model
+        for header in self
+            .secret_headers
+            .iter()
+            .filter(|header| domain_matches(&header.domain, &host))
+        {
+            let value = self
+                .render_secrets(&header.domain, &header.template)
+                .await?;
+            request.headers_mut().insert(
+                http::header::HeaderName::from_bytes(header.name.as_bytes())
+                    .map_err(|e| HttpToolError::InvalidHeader(e.to_string()))?,
+                value
+                    .parse()
+                    .map_err(|_| HttpToolError::InvalidHeader(header.name.clone()))?,
+            );
+        }
+
+        tracing::info!(target: "rig", "Sending tool request {} {}", method, parsed);
+        let send = self.client.send_limited(request, self.max_response_bytes);
+        let (response, truncated) = match future::select(send, Delay::new(self.timeout)).await {
+            Either::Left((response, _)) => response?,
+            Either::Right(_) => return Err(HttpToolError::Timeout(self.timeout)),
+        };
+        let content_type = response
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("unknown")
+            .to_string();
+        let location = response
+            .headers()
+            .get(http::header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .map(|location| format!("Location: {location}\n"))
+            .unwrap_or_default();
+        let mut text = String::from_utf8_lossy(response.body()).into_owned();
+        if truncated {
+            text.push_str(&format!(
+                "\n[... truncated after {} bytes ...]",
+                self.max_response_bytes
+            ));
+        }
+        Ok(format!(
+            "Status: {}\nContent-Type: {content_type}\n{location}\n{text}",
+            response.status()
+        ))
+    }
+
+    /// Tool `http_request` sending HTTP requests to the allowed domains, for agents
+    pub fn tool(&self) -> impl Tool + 'static {
+        let http = self.clone();
+        let methods = if self.allow_post {
+            "GET or POST"
+        } else {
+            "GET"
+        };
+        let description = format!(
+            "Send a {methods} HTTP request to a REST API. The allowed domains are: {}.",
+            self.domains.join(", ")
+        );
+        tool::from_typed_fn(
+            "http_request",
+            &description,
+            move |args: HttpRequestArgs| {
+                let http = http.clone();
+                async move {
+                    let method = args.method.as_deref().unwrap_or("GET");
+                    http.request(method, &args.url, &args.headers, args.body)
+                        .await
+                }
+            },
+        )
+        .with_annotations(ToolAnnotations {
+            read_only: Some(!self.allow_post),
+            destructive: Some(self.allow_post),
+            open_world: Some(true),
+            ..Default::default()
+        })
+    }
+}
+
+/// Whether `host` is `domain`, or one of its subdomains if it starts with `*.` (e.g.:
+/// `*.example.com`)
+fn domain_matches(domain: &str, host: &str) -> bool {
+    match domain.strip_prefix("*.") {
+        Some(parent) => host
+            .strip_suffix(parent)
+            .is_some_and(|subdomain| subdomain.ends_with('.')),
+        None => host == domain,
+    }
+}
+
+impl std::fmt::Debug for HttpTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpTool")
+            .field("domains", &self.domains)
+            .field("allow_post", &self.allow_post)
+            .field("secret_headers", &self.secret_headers)
+            .field("max_response_bytes", &self.max_response_bytes)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::secrets::InMemorySecrets;
+
+    /// Client recording the requests, and answering with a canned body after `delay`
+    #[derive(Clone, Default)]
+    struct FakeClient {
+        requests: Arc<Mutex<Vec<http::Request<Bytes>>>>,
+        delay: Duration,
+    }
+
+    impl HttpClient for FakeClient {
+        async fn send(
+            &self,
+            request: http::Request<Bytes>,
+        ) -> Result<http::Response<Bytes>, HttpClientError> {
+            self.requests.lock().unwrap().push(request);
+            Delay::new(self.delay).await;
+            Ok(http::Response::builder()
+                .header("Content-Type", "application/json")
+                .body(Bytes::from_static(br#"{"stars": 42, "forks": 7}"#))?)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_tool() {
+        let client = FakeClient::default();
+        let http = HttpTool::new()
+            .http_client(client.clone())
+            .allow_domain("api.github.com")
+            .allow_domain("*.example.com")
+            .secrets(
+                InMemorySecrets::new()
+                    .with_secret("api.github.com", "token", "ghp_secret")
+                    .with_secret("api.example.com", "key", "example_secret"),
+            )
+            .secret_header("api.github.com", "Authorization", "Bearer {token}")
+            .secret_header("api.example.com", "X-Api-Key", "{key}")
+            .max_response_bytes(12);
+
+        let headers = HashMap::from([("Authorization".to_string(), "Bearer stolen".to_string())]);
+        let output = http
+            .request(
+                "get",
+                "https://API.github.com/repos/fabelis/../fabelis/mcp-rig",
+                &headers,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            output,
+            "Status: 200 OK\nContent-Type: application/json\n\n{\"stars\": 42\n\
+             [... truncated after 12 bytes ...]"
+        );
+        // The URL sent is the normalized one
+        assert_eq!(
+            client.requests.lock().unwrap()[0].uri(),
+            "https://api.github.com/repos/fabelis/mcp-rig"
+        );
+        assert_eq!(
+            client.requests.lock().unwrap()[0].headers()["Authorization"],
+            "Bearer ghp_secret"
+        );
+
+        // The secret headers are only sent to their domain, not to the other allowed subdomains
+        let no_headers = HashMap::new();
+        for url in ["https://api.example.com/", "https://docs.example.com/"] {
+            assert!(http.request("GET", url, &no_headers, None).await.is_ok());
+        }
+        let requests = client.requests.lock().unwrap();
+        assert_eq!(requests[1].headers()["X-Api-Key"], "example_secret");
+        assert!(!requests[2].headers().contains_key("X-Api-Key"));
+        drop(requests);
+        assert!(matches!(
+            http.request("GET", "https://example.com.evil.io/", &no_headers, None)
+                .await,
+            Err(HttpToolError::DomainNotAllowed(_))
+        ));
+        assert!(matches!(
+            http.request("GET", "file:///etc/passwd", &no_headers, None)
+                .await,
+            Err(HttpToolError::InvalidUrl(_))
+        ));
+        assert!(matches!(
+            http.request("POST", "https://api.github.com/gists", &no_headers, None)
+                .await,
+            Err(HttpToolError::MethodNotAllowed(_))
+        ));
+        assert_eq!(client.requests.lock().unwrap().len(), 3);
+
+        // The requests fail after the timeout
+        let slow = HttpTool::new()
+            .http_client(FakeClient {
+                delay: Duration::from_secs(5),
+                ..Default::default()
+            })
+            .allow_domain("api.github.com")
+            .timeout(Duration::from_millis(50));
+        assert!(matches!(
+            slow.request("GET", "https://api.github.com/", &no_headers, None)
+                .await,
+            Err(HttpToolError::Timeout(_))
+        ));
+    }
+}
@@ -0,0 +1,148 @@
+//! Image generation with the models of the providers supporting it (e.g.:
+//! [crate::providers::hyperbolic] SDXL and Flux models).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{image_generation::ImageGenerationModel, providers::hyperbolic};
+//!
+//! let hyperbolic = hyperbolic::Client::from_env();
+//! let model = hyperbolic.image_generation_model(hyperbolic::FLUX_1_DEV);
+//!
+//! let response = model
+//!     .image_generation_request("A flurbo on the beach, watercolor")
+//!     .width(1024)
+//!     .height(1024)
+//!     .send()
+//!     .await?;
+//! std::fs::write("flurbo.png", response.image)?;
+//! ```
+use std::future::Future;
+
+use crate::http_client::HttpClientError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImageGenerationError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error building the image generation request
+    #[error("RequestError: {0}")]
+    RequestError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    /// Error parsing the image generation response
+    #[error("ResponseError: {0}")]
+    ResponseError(String),
+
+    /// Error returned by the image generation model provider
+    #[error("ProviderError: {0}")]
+    ProviderError(String),
+}
+
+impl From<HttpClientError> for ImageGenerationError {
+    fn from(error: HttpClientError) -> Self {
+        match error {
+            HttpClientError::HttpError(error) => ImageGenerationError::HttpError(error),
+            error => ImageGenerationError::RequestError(error.into()),
+        }
+    }
+}
+
+/// Request to an image generation model.
+#[derive(Clone, Debug)]
+pub struct ImageGenerationRequest {
+    /// Description of the image to generate
+    pub prompt: String,
+    /// Width of the image, in pixels
+    pub width: u32,
+    /// Height of the image, in pixels
+    pub height: u32,
+    /// Additional provider-specific parameters (e.g.: number of steps, seed)
+    pub additional_params: Option<serde_json::Value>,
+}
+
+/// Response of an image generation model.
+#[derive(Clone, Debug)]
+pub struct ImageGenerationResponse<T> {
+    /// The generated image (e.g.: PNG bytes)
+    pub image: Vec<u8>,
+    /// The raw response of the provider
+    pub response: T,
+}
+
+/// Trait for the models generating images from a text prompt.
+pub trait ImageGenerationModel: Clone + Send + Sync {
+    /// The raw response type returned by the provider
+    type Response: Send + Sync;
+
+    /// Generate an image from `request`.
+    fn image_generation(
+        &self,
+        request: ImageGenerationRequest,
+    ) -> impl Future<Output = Result<ImageGenerationResponse<Self::Response>, ImageGenerationError>> + Send;
+
+    /// Create an image generation request builder with the given prompt.
+    fn image_generation_request(&self, prompt: &str) -> ImageGenerationRequestBuilder<Self> {
+        ImageGenerationRequestBuilder::new(self.clone(), prompt)
+    }
+}
+
+/// Builder of an [ImageGenerationRequest].
+pub struct ImageGenerationRequestBuilder<M: ImageGenerationModel> {
+    model: M,
+    prompt: String,
+    width: u32,
+    height: u32,
+    additional_params: Option<serde_json::Value>,
+}
+
+impl<M: ImageGenerationModel> ImageGenerationRequestBuilder<M> {
+    /// Create a builder of a 1024x1024 image generation request.
+    pub fn new(model: M, prompt: &str) -> Self {
+        Self {
+            model,
+            prompt: prompt.to_string(),
+            width: 1024,
+            height: 1024,
+            additional_params: None,
+        }
+    }
+
+    /// Set the width of the image, in pixels.
+    pub fn width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Set the height of the image, in pixels.
+    pub fn height(mut self, height: u32) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Set additional provider-specific parameters of the request.
+    pub fn additional_params(mut self, additional_params: serde_json::Value) -> Self {
+        self.additional_params = Some(additional_params);
+        self
+    }
+
+    /// Build the request.
+    pub fn build(self) -> ImageGenerationRequest {
+        ImageGenerationRequest {
+            prompt: self.prompt,
+            width: self.width,
+            height: self.height,
+            additional_params: self.additional_params,
+        }
+    }
+
+    /// Build the request and send it to the model.
+    pub async fn send(self) -> Result<ImageGenerationResponse<M::Response>, ImageGenerationError> {
+        let model = self.model.clone();
+        model.image_generation(self.build()).await
+    }
+}
@@ -25,6 +25,130 @@ pub fn merge_inplace(a: &mut serde_json::Value, b: serde_json::Value) {
     }
 }
 
+/// How [deep_merge] merges two arrays at the same path.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArrayStrategy {
+    /// The array of the second value replaces the first one
+    #[default]
+    Replace,
+    /// The items of the second array are appended to the first one
+    Append,
+    /// The items of the second array are appended to the first one, unless already in it
+    Union,
+}
+
+/// Recursively merge `b` into `a`: the objects are merged key by key, the arrays according to
+/// `arrays`, and any other value of `b` replaces that of `a`, except `null` which leaves it
+/// unchanged. E.g.: `{"options": {"a": 1}}` merged with `{"options": {"b": 2}}` gives
+/// `{"options": {"a": 1, "b": 2}}` (while [merge] gives `{"options": {"b": 2}}`).
+pub fn deep_merge(
+    mut a: serde_json::Value,
+    b: serde_json::Value,
+    arrays: ArrayStrategy,
+) -> serde_json::Value {
+    deep_merge_inplace(&mut a, b, arrays);
+    a
+}
+
+/// In-place version of [deep_merge].
+pub fn deep_merge_inplace(a: &mut serde_json::Value, b: serde_json::Value, arrays: ArrayStrategy) {
+    match (a, b) {
+        (_, serde_json::Value::Null) => (),
+        (serde_json::Value::Object(a_map), serde_json::Value::Object(b_map)) => {
+            for (key, value) in b_map {
+                match a_map.get_mut(&key) {
+                    Some(a_value) => deep_merge_inplace(a_value, value, arrays),
+                    None => {
+                        a_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (serde_json::Value::Array(a_items), serde_json::Value::Array(b_items)) => match arrays {
+            ArrayStrategy::Replace => *a_items = b_items,
+            ArrayStrategy::Append => a_items.extend(b_items),
+            ArrayStrategy::Union => b_items.into_iter().for_each(|item| {
+                if !a_items.contains(&item) {
+                    a_items.push(item);
+                }
+            }),
+        },
+        (a, b) => *a = b,
+    }
+}
+
+/// Parse a (possibly incomplete) JSON object or array, e.g.: the prefix of a JSON document
+/// being streamed by a model. Unterminated strings, objects and arrays are closed, and trailing
+/// incomplete tokens (e.g.: a key without a value) are dropped.
+/// Any text before the first `{` or `[` and after the end of the document is ignored.
+///
+/// Returns `None` if no JSON object or array has started yet or if the prefix cannot be repaired.
+pub fn parse_partial(text: &str) -> Option<serde_json::Value> {
+    let start = text.find(['{', '['])?;
+    let text = &text[start..];
+
+    // Stack of closing delimiters of the currently open objects and arrays
+    let mut closers = vec![];
+    // Last position at which the document can be cut (and the closers at that position)
+    let mut cut = (0, vec![]);
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut end = text.len();
+
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => {
+                closers.push(if c == '{' { '}' } else { ']' });
+                cut = (i + 1, closers.clone());
+            }
+            '}' | ']' => {
+                closers.pop();
+                if closers.is_empty() {
+                    end = i + 1;
+                    break;
+                }
+            }
+            ',' => cut = (i, closers.clone()),
+            _ => (),
+        }
+    }
+
+    let text = &text[..end];
+    if closers.is_empty() {
+        return serde_json::from_str(text).ok();
+    }
+
+    // Try to close the document as is (e.g.: unterminated string value or number)
+    let mut candidate = text.to_string();
+    if in_string {
+        if escaped {
+            candidate.pop();
+        }
+        candidate.push('"');
+    }
+    candidate.extend(closers.iter().rev());
+    if let Ok(value) = serde_json::from_str(&candidate) {
+        return Some(value);
+    }
+
+    // Otherwise, drop the last incomplete member or element
+    let (position, closers) = cut;
+    let mut candidate = text[..position].to_string();
+    candidate.extend(closers.iter().rev());
+    serde_json::from_str(&candidate).ok()
+}
+
 /// This module is helpful in cases where raw json objects are serialized and deserialized as
 ///  strings such as `"{\"key\": \"value\"}"`. This might seem odd but it's actually how some
 ///  some providers such as OpenAI return function arguments (for some reason).
@@ -169,6 +293,71 @@ mod tests {
         assert_eq!(a, expected);
     }
 
+    #[test]
+    fn test_deep_merge() {
+        let a = serde_json::json!({
+            "model": "command-r",
+            "options": {"safety": {"mode": "strict"}, "stop": ["\n"]},
+            "tags": ["a", "b"]
+        });
+        let b = serde_json::json!({
+            "options": {"safety": {"level": 2}, "stop": ["END", "\n"]},
+            "tags": ["b", "c"],
+            "model": null
+        });
+
+        assert_eq!(
+            deep_merge(a.clone(), b.clone(), ArrayStrategy::Replace),
+            serde_json::json!({
+                "model": "command-r",
+                "options": {"safety": {"mode": "strict", "level": 2}, "stop": ["END", "\n"]},
+                "tags": ["b", "c"]
+            })
+        );
+        assert_eq!(
+            deep_merge(a.clone(), b.clone(), ArrayStrategy::Append)["tags"],
+            serde_json::json!(["a", "b", "b", "c"])
+        );
+        let union = deep_merge(a, b, ArrayStrategy::Union);
+        assert_eq!(union["tags"], serde_json::json!(["a", "b", "c"]));
+        assert_eq!(union["options"]["stop"], serde_json::json!(["\n", "END"]));
+
+        // Scalars and mismatched types are replaced
+        assert_eq!(
+            deep_merge(
+                serde_json::json!({"n": [1], "s": "x"}),
+                serde_json::json!({"n": {"k": 1}, "s": 2}),
+                ArrayStrategy::Union
+            ),
+            serde_json::json!({"n": {"k": 1}, "s": 2})
+        );
+    }
+
+    #[test]
+    fn test_parse_partial() {
+        assert_eq!(
+            parse_partial("Sure! ```json\n{\"na"),
+            Some(serde_json::json!({}))
+        );
+        assert_eq!(
+            parse_partial(r#"{"name": "Jo"#),
+            Some(serde_json::json!({"name": "Jo"}))
+        );
+        assert_eq!(
+            parse_partial(r#"{"name": "John", "age"#),
+            Some(serde_json::json!({"name": "John"}))
+        );
+        assert_eq!(
+            parse_partial(r#"{"name": "John", "tags": ["a", "b"#),
+            Some(serde_json::json!({"name": "John", "tags": ["a", "b"]}))
+        );
+        assert_eq!(
+            parse_partial(r#"{"name": "John", "age": 30}```"#),
+            Some(serde_json::json!({"name": "John", "age": 30}))
+        );
+        assert_eq!(parse_partial("no json here"), None);
+    }
+
     #[test]
     fn test_stringified_json_serialize() {
         let dummy = Dummy {
@@ -0,0 +1,299 @@
+//! API keys of the provider clients, for key rotation and load balancing.
+//!
+//! By default, the provider clients use the API key given at construction for all their
+//! requests. With the `with_key_provider` method of the provider clients, the key of each
+//! request is instead obtained from a [KeyProvider]:
+//! - [StaticKey]: always the same key.
+//! - [KeyPool]: the keys of a pool in turn (round-robin), e.g.: to spread the load of a
+//!   high-throughput deployment across several keys.
+//! - [KeyCallback]: the key returned by a callback, e.g.: to fetch the (rotated) key from a vault.
+//!
+//! When a request fails with a `401 Unauthorized` or `429 Too Many Requests` status, the key is
+//! reported to the provider with [KeyProvider::report_failure], and the request is retried with
+//! the next key (up to [KeyProvider::attempts] times).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{key_provider::KeyPool, providers::openai};
+//!
+//! let openai = openai::Client::new("sk-1")
+//!     .with_key_provider(KeyPool::new(["sk-1", "sk-2", "sk-3"]));
+//! ```
+use std::{
+    future::Future,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use futures::future::BoxFuture;
+
+use crate::http_client::HttpClientError;
+
+/// Error returned by a [KeyProvider] that fails to provide a key.
+pub type KeyError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Trait for the providers of the API keys used by the provider clients.
+pub trait KeyProvider: Send + Sync {
+    /// Return the key to use for the next request.
+    fn key(&self) -> impl Future<Output = Result<String, KeyError>> + Send;
+
+    /// Report that `key` was rejected with the HTTP status `status` (401 or 429).
+    fn report_failure(&self, key: &str, status: u16) {
+        let _ = (key, status);
+    }
+
+    /// Maximum number of attempts of a request, each with a new key.
+    fn attempts(&self) -> usize {
+        1
+    }
+}
+
+/// Dyn-compatible version of the [KeyProvider] trait.
+pub(crate) trait KeyProviderDyn: Send + Sync {
+    fn key(&self) -> BoxFuture<'_, Result<String, KeyError>>;
+
+    fn report_failure(&self, key: &str, status: u16);
+
+    fn attempts(&self) -> usize;
+}
+
+impl<T: KeyProvider> KeyProviderDyn for T {
+    fn key(&self) -> BoxFuture<'_, Result<String, KeyError>> {
+        Box::pin(KeyProvider::key(self))
+    }
+
+    fn report_failure(&self, key: &str, status: u16) {
+        KeyProvider::report_failure(self, key, status)
+    }
+
+    fn attempts(&self) -> usize {
+        KeyProvider::attempts(self)
+    }
+}
+
+/// Key provider always returning the same key.
+#[derive(Clone, Debug)]
+pub struct StaticKey(String);
+
+impl StaticKey {
+    pub fn new(key: &str) -> Self {
+        Self(key.to_string())
+    }
+}
+
+impl KeyProvider for StaticKey {
+    async fn key(&self) -> Result<String, KeyError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Key provider returning the keys of a pool in turn (round-robin). A failed request is retried
+/// with each of the other keys of the pool.
+#[derive(Debug)]
+pub struct KeyPool {
+    keys: Vec<String>,
+    next: AtomicUsize,
+}
+
+impl KeyPool {
+    /// Create a pool with the given keys.
+    ///
+    /// # Panics
+    /// If `keys` is empty.
+    pub fn new(keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let keys = keys.into_iter().map(Into::into).collect::<Vec<_>>();
+        assert!(!keys.is_empty(), "Key pool should not be empty");
+        Self {
+            keys,
+            next: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl KeyProvider for KeyPool {
+    async fn key(&self) -> Result<String, KeyError> {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.keys.len();
+        Ok(self.keys[index].clone())
+    }
+
+    fn attempts(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+/// Key provider returning the key returned by a callback (e.g.: fetched from a vault), called
+/// for each request. By default, a failed request is retried once with a new key.
+pub struct KeyCallback<F> {
+    callback: F,
+    attempts: usize,
+}
+
+impl<F, Fut> KeyCallback<F>
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<String, KeyError>> + Send,
+{
+    pub fn new(callback: F) -> Self {
+        Self {
+            callback,
+            attempts: 2,
+        }
+    }
+
+    /// Set the maximum number of attempts of a request, each with a new key.
+    pub fn attempts(mut self, attempts: usize) -> Self {
+        self.attempts = attempts;
+        self
+    }
+}
+
+impl<F, Fut> KeyProvider for KeyCallback<F>
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<String, KeyError>> + Send,
+{
+    async fn key(&self) -> Result<String, KeyError> {
+        (self.callback)().await
+    }
+
+    fn attempts(&self) -> usize {
+        self.attempts
+    }
+}
+
+/// Where a provider expects its API key in the requests.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum KeyPlacement {
+    /// `Authorization: Bearer <key>` header
+    Bearer,
+    /// Header with the given (lowercase) name
+    Header(&'static str),
+    /// Query parameter with the given name
+    Query(&'static str),
+}
+
+impl KeyPlacement {
+    /// Set `key` on `request`, replacing the previous one (if any).
+    pub(crate) fn apply(
+        &self,
+        request: &mut reqwest::Request,
+        key: &str,
+    ) -> Result<(), HttpClientError> {
+        let (name, value) = match *self {
+            KeyPlacement::Bearer => (reqwest::header::AUTHORIZATION, format!("Bearer {key}")),
+            KeyPlacement::Header(name) => (
+                reqwest::header::HeaderName::from_static(name),
+                key.to_string(),
+            ),
+            KeyPlacement::Query(name) => {
+                let pairs = request
+                    .url()
+                    .query_pairs()
+                    .filter(|(pair, _)| **pair != *name)
+                    .map(|(pair, value)| (pair.into_owned(), value.into_owned()))
+                    .collect::<Vec<_>>();
+                request
+                    .url_mut()
+                    .query_pairs_mut()
+                    .clear()
+                    .extend_pairs(pairs)
+                    .append_pair(name, key);
+                return Ok(());
+            }
+        };
+
+        let mut value = reqwest::header::HeaderValue::from_str(&value)
+            .map_err(|_| HttpClientError::InvalidRequest("Invalid API key".into()))?;
+        value.set_sensitive(true);
+        request.headers_mut().insert(name, value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::http_client::{HttpBackend, HttpClient};
+
+    /// Fake client rate limiting the requests using the key `sk-1`
+    #[derive(Clone, Default)]
+    struct FakeClient {
+        keys: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl HttpClient for FakeClient {
+        async fn send(
+            &self,
+            request: http::Request<Bytes>,
+        ) -> Result<http::Response<Bytes>, HttpClientError> {
+            let key = request.headers()["Authorization"]
+                .to_str()
+                .unwrap()
+                .to_string();
+            self.keys.lock().unwrap().push(key.clone());
+            let status = if key == "Bearer sk-1" { 429 } else { 200 };
+            Ok(http::Response::builder()
+                .status(status)
+                .body(Bytes::new())?)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_key_pool() {
+        let pool = KeyPool::new(["sk-1", "sk-2"]);
+        assert_eq!(KeyProvider::key(&pool).await.unwrap(), "sk-1");
+        assert_eq!(KeyProvider::key(&pool).await.unwrap(), "sk-2");
+        assert_eq!(KeyProvider::key(&pool).await.unwrap(), "sk-1");
+        assert_eq!(KeyProvider::attempts(&pool), 2);
+    }
+
+    #[tokio::test]
+    async fn test_key_failover() {
+        let fake = FakeClient::default();
+        let backend = HttpBackend::default()
+            .with_keys(KeyPool::new(["sk-1", "sk-2"]), KeyPlacement::Bearer)
+            .with_client(fake.clone());
+        let request = || {
+            reqwest::Client::new()
+                .post("https://example.com")
+                .body("{}")
+        };
+
+        let response = backend.send(request()).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            *fake.keys.lock().unwrap(),
+            vec!["Bearer sk-1".to_string(), "Bearer sk-2".to_string()]
+        );
+
+        // With a single attempt, the rate limited response is returned
+        let backend = HttpBackend::default()
+            .with_keys(StaticKey::new("sk-1"), KeyPlacement::Bearer)
+            .with_client(fake.clone());
+        let response = backend.send(request()).await.unwrap();
+        assert_eq!(response.status(), 429);
+    }
+
+    #[test]
+    fn test_key_placement() {
+        let mut request = reqwest::Client::new()
+            .get("https://example.com/models?key=old&alt=sse")
+            .build()
+            .unwrap();
+        KeyPlacement::Query("key")
+            .apply(&mut request, "new")
+            .unwrap();
+        assert_eq!(
+            request.url().as_str(),
+            "https://example.com/models?alt=sse&key=new"
+        );
+
+        KeyPlacement::Header("x-api-key")
+            .apply(&mut request, "sk-1")
+            .unwrap();
+        assert_eq!(request.headers()["x-api-key"], "sk-1");
+    }
+}
@@ -79,20 +79,71 @@
 //!
 //! You can also implement your own vector store integration by defining types that
 //! implement the [VectorStoreIndex](crate::vector_store::VectorStoreIndex) trait.
+//!
+//! ## WebAssembly
+//! The provider clients and agents compile to `wasm32-unknown-unknown`, so agents can run in the
+//! browser (see the `examples/wasm_browser` crate). MCP tools, and the built-in local and HTTP
+//! tools, are not available on wasm targets.
 
 pub mod agent;
+pub mod audio_generation;
+pub mod bench;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod browser;
+pub mod calculator;
+pub mod cassette;
+pub mod chat_session;
 pub mod cli_chatbot;
+pub mod code_tools;
+pub mod codegen;
 pub mod completion;
 pub mod embeddings;
+pub mod evals;
 pub mod extractor;
+pub mod finetuning;
+#[cfg(any(test, feature = "test-support"))]
+pub mod fuzz;
+pub mod health;
+pub mod http_client;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod http_tool;
+pub mod image_generation;
 pub(crate) mod json_utils;
+pub mod key_provider;
+pub mod load_test;
 pub mod loaders;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod local_tools;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod mcp_servers;
+#[cfg(all(feature = "mcp-websocket", not(target_arch = "wasm32")))]
+pub mod mcp_transport;
+pub mod memory;
+pub mod models;
+pub mod observability;
 pub mod one_or_many;
+pub mod payload_log;
 pub mod pipeline;
 pub mod providers;
+#[cfg(feature = "realtime")]
+pub mod realtime;
+pub mod redaction;
+pub mod scheduler;
+pub mod secrets;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod shutdown;
 pub mod streaming;
+pub mod telemetry;
+pub mod tenant;
 pub mod tool;
+pub mod tool_stats;
+pub mod usage;
 pub mod vector_store;
+#[cfg(target_arch = "wasm32")]
+pub(crate) mod wasm_compat;
 
 // Re-export commonly used types and traits
 pub use completion::message;
@@ -100,4 +151,11 @@ pub use embeddings::Embed;
 pub use one_or_many::{EmptyListError, OneOrMany};
 
 #[cfg(feature = "derive")]
-pub use rig_derive::Embed;
+pub use rig_derive::{tool, Embed};
+
+/// Dependencies of the code generated by the macros of `rig_derive` (not part of the public API).
+#[doc(hidden)]
+pub mod __private {
+    pub use schemars;
+    pub use serde;
+}
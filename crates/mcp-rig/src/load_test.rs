@@ -0,0 +1,550 @@
+//! Load tests of agents, to find their rate-limit and latency cliffs before a production
+//! rollout.
+//!
+//! A [LoadTest] drives a [LoadTarget] with simulated conversations:
+//! - the target is an [Agent], or an agent served over HTTP by an
+//!   `AgentServer` (see [ServerTarget]),
+//! - the turns of the users are scripted (see [SimulatedUser::scripted]), or generated by a
+//!   model playing the user (see [SimulatedUser::generated]).
+//!
+//! The load increases by stages (see [LoadTest::stages]): each stage runs a number of
+//! conversations concurrently, and the [LoadReport] gives the latency percentiles, throughput,
+//! error rate and number of rate-limited turns of each stage. [LoadReport::cliff] finds the
+//! first stage over a latency or error budget.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use mcp_rig::{load_test::{LoadTest, ServerTarget, SimulatedUser}, providers::openai};
+//!
+//! let report = LoadTest::new(ServerTarget::new("http://localhost:3000"))
+//!     .user(SimulatedUser::scripted(["Hi!", "How do I get a refund?", "Thanks"]))
+//!     .user(SimulatedUser::generated(
+//!         openai.agent(openai::GPT_4O_MINI).build(),
+//!         "You ordered a blue mug, received a red one, and want a replacement",
+//!         4,
+//!     ))
+//!     .stages([1, 5, 10, 25, 50])
+//!     .think_time(Duration::from_secs(2))
+//!     .run()
+//!     .await;
+//!
+//! println!("{report}");
+//! if let Some(stage) = report.cliff(Duration::from_secs(10), 0.05) {
+//!     println!("The agent breaks down at {} concurrent conversations", stage.concurrency);
+//! }
+//! ```
+use std::{fmt, sync::Arc, time::Duration};
+
+use futures::future::BoxFuture;
+use futures_timer::Delay;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use web_time::Instant;
+
+use crate::{
+    agent::Agent,
+    bench::latency_percentile,
+    completion::{Chat, CompletionError, CompletionModel, Message, Prompt, PromptError},
+    observability::new_id,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoadTestError {
+    #[error("PromptError: {0}")]
+    PromptError(#[from] PromptError),
+
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// The server responded with an error status
+    #[error("Server responded with status {status}: {body}")]
+    StatusError { status: u16, body: String },
+}
+
+impl LoadTestError {
+    /// Whether the error was caused by a rate limit (of the provider or of the server)
+    pub fn is_rate_limited(&self) -> bool {
+        let message = match self {
+            LoadTestError::StatusError { status, .. } => return *status == 429,
+            LoadTestError::HttpError(e) => {
+                return e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS)
+            }
+            LoadTestError::PromptError(PromptError::CompletionError(
+                CompletionError::HttpError(e),
+            )) => return e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS),
+            LoadTestError::PromptError(e) => e.to_string().to_lowercase(),
+        };
+        ["rate limit", "rate_limit", "too many requests", "429"]
+            .iter()
+            .any(|pattern| message.contains(pattern))
+    }
+}
+
+/// Trait for the targets of a [LoadTest] (e.g.: an [Agent]).
+pub trait LoadTarget: Send + Sync {
+    /// Answer the user `message` of the conversation `session_id`, whose previous messages are
+    /// `history`
+    fn respond<'a>(
+        &'a self,
+        session_id: &'a str,
+        message: &'a str,
+        history: &'a [Message],
+    ) -> BoxFuture<'a, Result<String, LoadTestError>>;
+}
+
+impl<M: CompletionModel> LoadTarget for Agent<M> {
+    fn respond<'a>(
+        &'a self,
+        _session_id: &'a str,
+        message: &'a str,
+        history: &'a [Message],
+    ) -> BoxFuture<'a, Result<String, LoadTestError>> {
+        Box::pin(async move { Ok(self.chat(message.to_string(), history.to_vec()).await?) })
+    }
+}
+
+/// Agent served over HTTP by an `AgentServer` (see the `serve` feature), which keeps the
+/// history of the conversations (i.e.: its `POST /chat` route, with the session ID of the
+/// conversation).
+#[derive(Clone, Debug)]
+pub struct ServerTarget {
+    client: reqwest::Client,
+    /// URL of the `POST /chat` route
+    url: String,
+}
+
+impl ServerTarget {
+    /// Server at `base_url` (e.g.: `http://localhost:3000`, or `https://example.com/assistant`
+    /// if the server is nested)
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: format!("{}/chat", base_url.trim_end_matches('/')),
+        }
+    }
+
+    /// Send the requests with `client` (e.g.: with authentication headers)
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+impl LoadTarget for ServerTarget {
+    fn respond<'a>(
+        &'a self,
+        session_id: &'a str,
+        message: &'a str,
+        _history: &'a [Message],
+    ) -> BoxFuture<'a, Result<String, LoadTestError>> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .post(&self.url)
+                .json(&json!({ "message": message, "session_id": session_id }))
+                .send()
+                .await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(LoadTestError::StatusError {
+                    status: status.as_u16(),
+                    body: response.text().await.unwrap_or_default(),
+                });
+            }
+            let body = response.json::<serde_json::Value>().await?;
+            Ok(body["response"].as_str().unwrap_or_default().to_string())
+        })
+    }
+}
+
+type Respond = Arc<dyn Fn(String) -> BoxFuture<'static, Result<String, PromptError>> + Send + Sync>;
+
+#[derive(Clone)]
+enum Turns {
+    Scripted(Vec<String>),
+    Generated {
+        respond: Respond,
+        goal: String,
+        turns: usize,
+    },
+}
+
+/// Simulated user of a [LoadTest], writing the user turns of its conversations.
+#[derive(Clone)]
+pub struct SimulatedUser {
+    turns: Turns,
+}
+
+impl SimulatedUser {
+    /// User sending `turns`, in order
+    pub fn scripted(turns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            turns: Turns::Scripted(turns.into_iter().map(Into::into).collect()),
+        }
+    }
+
+    /// User played by `model` (e.g.: an agent of a small model), writing `turns` messages to
+    /// reach `goal` (e.g.: `You want a refund for your order 1234`)
+    pub fn generated(model: impl Prompt + 'static, goal: &str, turns: usize) -> Self {
+        let model = Arc::new(model);
+        Self {
+            turns: Turns::Generated {
+                respond: Arc::new(move |prompt| {
+                    let model = model.clone();
+                    Box::pin(async move { model.prompt(prompt).await })
+                }),
+                goal: goal.to_string(),
+                turns,
+            },
+        }
+    }
+
+    /// Message of the user for the turn `turn` of a conversation whose previous turns (user and
+    /// assistant messages) are `previous`, or `None` if the conversation is over
+    async fn message(
+        &self,
+        turn: usize,
+        previous: &[(String, String)],
+    ) -> Option<Result<String, PromptError>> {
+        match &self.turns {
+            Turns::Scripted(turns) => turns.get(turn).cloned().map(Ok),
+            Turns::Generated { turns, .. } if turn >= *turns => None,
+            Turns::Generated { respond, goal, .. } => {
+                let transcript = previous
+                    .iter()
+                    .map(|(user, assistant)| format!("User: {user}\nAssistant: {assistant}"))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let prompt = format!(
+                    "You are simulating a user talking to an assistant. Your goal: {goal}.\n\n\
+                     Conversation so far:\n{}\n\n\
+                     Write only the next message of the user, without any prefix.",
+                    if transcript.is_empty() {
+                        "(none)"
+                    } else {
+                        &transcript
+                    }
+                );
+                Some(respond(prompt).await)
+            }
+        }
+    }
+}
+
+/// Result of a turn of a simulated conversation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TurnResult {
+    /// Index of the conversation in its stage
+    pub conversation: usize,
+    /// Index of the turn in its conversation
+    pub turn: usize,
+    /// Time taken by the target to answer
+    pub latency: Duration,
+    /// Error of the target, if it failed to answer
+    pub error: Option<String>,
+    /// Whether the error was caused by a rate limit
+    pub rate_limited: bool,
+}
+
+/// Measures of a stage of a [LoadTest].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StageReport {
+    /// Number of concurrent conversations
+    pub concurrency: usize,
+    /// Results of the turns answered (or failed) by the target
+    pub turns: Vec<TurnResult>,
+    /// Number of user turns which couldn't be generated (see [SimulatedUser::generated])
+    pub user_errors: usize,
+    /// Duration of the stage
+    pub duration: Duration,
+}
+
+impl StageReport {
+    /// Number of turns the target failed to answer.
+    pub fn errors(&self) -> usize {
+        self.turns
+            .iter()
+            .filter(|turn| turn.error.is_some())
+            .count()
+    }
+
+    /// Number of turns failed because of a rate limit.
+    pub fn rate_limited(&self) -> usize {
+        self.turns.iter().filter(|turn| turn.rate_limited).count()
+    }
+
+    /// Fraction of the turns the target failed to answer (0 if there were none).
+    pub fn error_rate(&self) -> f64 {
+        if self.turns.is_empty() {
+            0.0
+        } else {
+            self.errors() as f64 / self.turns.len() as f64
+        }
+    }
+
+    /// Latency under which `percentile`% of the turns were answered (e.g.: 95 for the p95), with
+    /// the nearest-rank method. The failed turns count, as they are latencies seen by users.
+    pub fn latency_percentile(&self, percentile: f64) -> Duration {
+        latency_percentile(
+            self.turns.iter().map(|turn| turn.latency).collect(),
+            percentile,
+        )
+    }
+
+    /// Number of turns answered per second.
+    pub fn throughput(&self) -> f64 {
+        let answered = self.turns.len() - self.errors();
+        match self.duration.as_secs_f64() {
+            seconds if seconds > 0.0 => answered as f64 / seconds,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Report of a [LoadTest], with the measures of its stages.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LoadReport {
+    /// Measures of the stages, by increasing load
+    pub stages: Vec<StageReport>,
+}
+
+impl LoadReport {
+    /// First stage whose p95 latency is over `max_p95`, or whose error rate is over
+    /// `max_error_rate` (e.g.: 0.05), if any
+    pub fn cliff(&self, max_p95: Duration, max_error_rate: f64) -> Option<&StageReport> {
+        self.stages.iter().find(|stage| {
+            stage.latency_percentile(95.0) > max_p95 || stage.error_rate() > max_error_rate
+        })
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "concurrency | turns | errors | rate_limited | p50_ms | p95_ms | p99_ms | turns_per_s"
+        )?;
+        for stage in &self.stages {
+            writeln!(
+                f,
+                "{:>11} | {:>5} | {:>6} | {:>12} | {:>6} | {:>6} | {:>6} | {:>11.2}",
+                stage.concurrency,
+                stage.turns.len(),
+                stage.errors(),
+                stage.rate_limited(),
+                stage.latency_percentile(50.0).as_millis(),
+                stage.latency_percentile(95.0).as_millis(),
+                stage.latency_percentile(99.0).as_millis(),
+                stage.throughput()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Load test of a target with simulated conversations (see the [module](self) docs).
+pub struct LoadTest {
+    target: Box<dyn LoadTarget>,
+    users: Vec<SimulatedUser>,
+    /// Number of concurrent conversations of each stage
+    stages: Vec<usize>,
+    /// Pause of the users between two turns
+    think_time: Duration,
+}
+
+impl LoadTest {
+    pub fn new(target: impl LoadTarget + 'static) -> Self {
+        Self {
+            target: Box::new(target),
+            users: vec![],
+            stages: vec![1],
+            think_time: Duration::ZERO,
+        }
+    }
+
+    /// Add a simulated user. The conversations of a stage are assigned to the users in turn.
+    pub fn user(mut self, user: SimulatedUser) -> Self {
+        self.users.push(user);
+        self
+    }
+
+    /// Run one stage per number of concurrent conversations of `stages`, in order (a single
+    /// conversation by default)
+    pub fn stages(mut self, stages: impl IntoIterator<Item = usize>) -> Self {
+        self.stages = stages.into_iter().collect();
+        self
+    }
+
+    /// Pause the users for `think_time` between two turns (no pause by default)
+    pub fn think_time(mut self, think_time: Duration) -> Self {
+        self.think_time = think_time;
+        self
+    }
+
+    /// Run the stages, one after the other.
+    pub async fn run(&self) -> LoadReport {
+        let mut stages = vec![];
+        for concurrency in &self.stages {
+            tracing::info!(target: "rig", "Load test stage of {concurrency} conversations");
+            stages.push(self.run_stage(*concurrency).await);
+        }
+        LoadReport { stages }
+    }
+
+    async fn run_stage(&self, concurrency: usize) -> StageReport {
+        let start = Instant::now();
+        let conversations = futures::future::join_all(
+            (0..concurrency)
+                .filter_map(|i| {
+                    let user = self.users.get(i % self.users.len().max(1))?;
+                    Some(self.run_conversation(i, user))
+                })
+                .collect::<Vec<_>>(),
+        )
+        .await;
+
+        let mut turns = vec![];
+        let mut user_errors = 0;
+        for (conversation_turns, conversation_user_errors) in conversations {
+            turns.extend(conversation_turns);
+            user_errors += conversation_user_errors;
+        }
+        StageReport {
+            concurrency,
+            turns,
+            user_errors,
+            duration: start.elapsed(),
+        }
+    }
+
+    /// Run the conversation `conversation` of `user`, returning the results of its turns and
+    /// the number of user turns which couldn't be generated
+    async fn run_conversation(
+        &self,
+        conversation: usize,
+        user: &SimulatedUser,
+    ) -> (Vec<TurnResult>, usize) {
+        let session_id = new_id();
+        let mut history = vec![];
+        let mut previous = vec![];
+        let mut results = vec![];
+
+        for turn in 0.. {
+            let message = match user.message(turn, &previous).await {
+                Some(Ok(message)) => message,
+                Some(Err(e)) => {
+                    tracing::warn!(target: "rig", "Failed to generate a user turn: {e}");
+                    return (results, 1);
+                }
+                None => break,
+            };
+            if turn > 0 && !self.think_time.is_zero() {
+                Delay::new(self.think_time).await;
+            }
+
+            let start = Instant::now();
+            let response = self.target.respond(&session_id, &message, &history).await;
+            let latency = start.elapsed();
+            match response {
+                Ok(response) => {
+                    results.push(TurnResult {
+                        conversation,
+                        turn,
+                        latency,
+                        error: None,
+                        rate_limited: false,
+                    });
+                    history.push(Message::user(message.clone()));
+                    history.push(Message::assistant(response.clone()));
+                    previous.push((message, response));
+                }
+                Err(e) => {
+                    results.push(TurnResult {
+                        conversation,
+                        turn,
+                        latency,
+                        error: Some(e.to_string()),
+                        rate_limited: e.is_rate_limited(),
+                    });
+                    // The conversation can't go on without the answer
+                    break;
+                }
+            }
+        }
+        (results, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::providers::mock::{self, MockResponse};
+
+    /// Target rate-limited over 2 concurrent requests
+    struct LimitedTarget {
+        in_flight: AtomicUsize,
+    }
+
+    impl LoadTarget for LimitedTarget {
+        fn respond<'a>(
+            &'a self,
+            _session_id: &'a str,
+            message: &'a str,
+            _history: &'a [Message],
+        ) -> BoxFuture<'a, Result<String, LoadTestError>> {
+            Box::pin(async move {
+                let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                Delay::new(Duration::from_millis(20)).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                if in_flight > 2 {
+                    return Err(LoadTestError::StatusError {
+                        status: 429,
+                        body: "Too many requests".to_string(),
+                    });
+                }
+                Ok(format!("Echo: {message}"))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_test() {
+        // Agent target, with a user played by a model
+        let agent = mock::CompletionModel::new()
+            .default_response(MockResponse::text("Sure, what is your order number?"))
+            .agent()
+            .build();
+        let user = mock::CompletionModel::new()
+            .default_response(MockResponse::text("I want a refund"))
+            .agent()
+            .build();
+        let report = LoadTest::new(agent)
+            .user(SimulatedUser::generated(user, "Get a refund", 2))
+            .stages([1, 3])
+            .run()
+            .await;
+        assert_eq!(report.stages.len(), 2);
+        assert_eq!(report.stages[1].turns.len(), 6);
+        assert_eq!(report.stages[1].errors(), 0);
+
+        // Rate-limited target, with a scripted user
+        let report = LoadTest::new(LimitedTarget {
+            in_flight: AtomicUsize::new(0),
+        })
+        .user(SimulatedUser::scripted(["Hi!", "Bye!"]))
+        .stages([1, 2, 4])
+        .run()
+        .await;
+        assert_eq!(report.stages[1].turns.len(), 4);
+        assert_eq!(report.stages[2].rate_limited(), 4);
+        // The conversations stop at their first failed turn
+        assert_eq!(report.stages[2].turns.len(), 4);
+
+        let cliff = report.cliff(Duration::from_secs(10), 0.05).unwrap();
+        assert_eq!(cliff.concurrency, 4);
+        assert!(report.to_string().starts_with("concurrency | turns"));
+    }
+}
@@ -0,0 +1,485 @@
+//! Sandboxed local tools (`run_command`, `read_file` and `write_file`), for the basic local
+//! automations which don't deserve a separate MCP server (e.g.: run the tests of a project and
+//! fix the failing ones). The `read_file` tool is the one of
+//! [CodeTools](crate::code_tools::CodeTools::read_tool), so that both sets of tools can be used
+//! by an agent.
+//!
+//! The tools are confined by explicit allowlists:
+//! - the files are read and written under the [Roots] of the tools only (see
+//!   [code_tools](crate::code_tools)),
+//! - only the allowed commands are run (see [LocalTools::command]), without a shell (i.e.: the
+//!   arguments are passed as is, so that `; rm -rf ~` is just an argument), from a directory
+//!   under the roots,
+//! - the commands only get the allowed environment variables (see [LocalTools::env_var]), not
+//!   the secrets of the process,
+//! - the commands are killed after a timeout (see [LocalTools::timeout]), along with the
+//!   processes they started on Unix (e.g.: a background process holding their outputs), and
+//!   their outputs are shortened to a maximum number of characters (see
+//!   [LocalTools::max_output]).
+//!
+//! The `run_command` and `write_file` tools are annotated as destructive, so that they go
+//! through the [ApprovalPolicy](crate::tool::ApprovalPolicy) of the agent.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use mcp_rig::{code_tools::Roots, local_tools::LocalTools, providers::openai};
+//!
+//! let tools = LocalTools::new(Roots::new().root("./my-project")?)
+//!     .command("cargo")
+//!     .timeout(Duration::from_secs(120));
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .preamble("You maintain a Rust project. Run `cargo test` and fix the failing tests.")
+//!     .tool(tools.run_command_tool())
+//!     .tool(tools.read_file_tool())
+//!     .tool(tools.write_file_tool())
+//!     .build();
+//! ```
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use futures::{
+    channel::oneshot,
+    future::{self, Either},
+};
+use futures_timer::Delay;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::{
+    code_tools::{CodeToolError, CodeTools, Roots},
+    tool::{self, OutputLimit, Tool, ToolAnnotations},
+};
+
+/// Default timeout of the commands.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default maximum number of characters of the outputs of the commands.
+const DEFAULT_MAX_OUTPUT: usize = 10_000;
+
+/// Maximum number of bytes of the standard output (and error) of a command kept in memory.
+const MAX_CAPTURED_BYTES: u64 = 1024 * 1024;
+
+/// Environment variables passed to the commands by default.
+const DEFAULT_ENV_VARS: &[&str] = &["PATH", "HOME", "LANG", "TMPDIR", "SYSTEMROOT", "TEMP"];
+
+/// Interval between the checks of whether a command exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum LocalToolError {
+    /// The command is not in the allowlist of the tools
+    #[error("Command not allowed: {0}")]
+    CommandNotAllowed(String),
+
+    /// The command didn't exit before the timeout, and was killed
+    #[error("Command timed out after {0:?}")]
+    Timeout(Duration),
+
+    /// The path is invalid or outside of the roots of the tools
+    #[error("CodeToolError: {0}")]
+    CodeToolError(#[from] CodeToolError),
+
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Arguments of the `run_command` tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RunCommandArgs {
+    /// Command to run (e.g.: `cargo`)
+    pub command: String,
+    /// Arguments of the command (e.g.: `["test", "--lib"]`), passed as is (not through a shell)
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Directory to run the command from, relative to the project root (the project root if
+    /// unset)
+    pub cwd: Option<String>,
+}
+
+/// Arguments of the `write_file` tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WriteFileArgs {
+    /// Path of the file, relative to the project root (created if it doesn't exist)
+    pub path: String,
+    /// New content of the file
+    pub content: String,
+}
+
+/// Sandboxed local tools (see the [module](self) docs).
+#[derive(Clone, Debug)]
+pub struct LocalTools {
+    roots: Roots,
+    /// Allowed commands
+    commands: Vec<String>,
+    /// Environment variables passed to the commands
+    env_vars: Vec<String>,
+    timeout: Duration,
+    /// Maximum number of characters of the outputs of the commands
+    max_output: usize,
+}
+
+impl LocalTools {
+    /// Tools reading and writing the files under `roots`, allowed to run no command (until
+    /// some are allowed with [LocalTools::command])
+    pub fn new(roots: Roots) -> Self {
+        Self {
+            roots,
+            commands: vec![],
+            env_vars: DEFAULT_ENV_VARS
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            timeout: DEFAULT_TIMEOUT,
+            max_output: DEFAULT_MAX_OUTPUT,
+        }
+    }
+
+    /// Allow the command `command`: a program of the `PATH` (e.g.: `cargo`), or the path of a
+    /// program (e.g.: `./scripts/test.sh`, relative to the first root). The paths are resolved
+    /// before they are matched, so that the model can run the program from another directory
+    /// (e.g.: `./test.sh` from `scripts`), but not another program of the same name.
+    pub fn command(mut self, command: &str) -> Self {
+        self.commands.push(command.to_string());
+        self
+    }
+
+    /// Pass the environment variable `name` of the process to the commands (e.g.:
+    /// `CARGO_HOME`). By default, only `PATH`, `HOME`, `LANG` and the temporary directory are.
+    pub fn env_var(mut self, name: &str) -> Self {
+        self.env_vars.push(name.to_string());
+        self
+    }
+
+    /// Kill the commands running for longer than `timeout` (30 seconds by default)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Shorten the outputs of the commands to their first and last `max_chars / 2` characters
+    /// (10,000 characters by default)
+    pub fn max_output(mut self, max_chars: usize) -> Self {
+        self.max_output = max_chars;
+        self
+    }
+
+    async fn limit_output(&self, toolname: &str, output: String) -> String {
+        OutputLimit::head_tail(self.max_output)
+            .apply(toolname, output)
+            .await
+    }
+
+    /// Program of the allowed command `command` run from `cwd`. The commands with a path are
+    /// matched by their resolved path, the others by name.
+    fn allowed_program(&self, command: &str, cwd: &Path) -> Result<PathBuf, LocalToolError> {
+        let not_allowed = || LocalToolError::CommandNotAllowed(command.to_string());
+        if !has_path(command) {
+            if !self.commands.iter().any(|allowed| allowed == command) {
+                return Err(not_allowed());
+            }
+            return Ok(PathBuf::from(command));
+        }
+
+        let program = cwd
+            .join(command)
+            .canonicalize()
+            .map_err(|_| not_allowed())?;
+        let root = self.roots.paths().next();
+        let allowed = self
+            .commands
+            .iter()
+            .filter(|allowed| has_path(allowed))
+            .filter_map(|allowed| {
+                let path = Path::new(allowed);
+                let path = if path.is_absolute() {
+                    path.to_path_buf()
+                } else {
+                    root?.join(path)
+                };
+                path.canonicalize().ok()
+            })
+            .any(|allowed| allowed == program);
+        if !allowed {
+            return Err(not_allowed());
+        }
+        Ok(program)
+    }
+
+    /// Run the allowed command `command` with `args` from the directory `cwd` (the first root if
+    /// unset), returning its exit code and outputs
+    pub async fn run_command(
+        &self,
+        command: &str,
+        args: &[String],
+        cwd: Option<&str>,
+    ) -> Result<String, LocalToolError> {
+        let cwd = self.roots.resolve(cwd.unwrap_or("."))?;
+        let program = self.allowed_program(command, &cwd)?;
+        tracing::info!(target: "rig", "Running command {} {:?} in {}", command, args, cwd.display());
+
+        let env = self
+            .env_vars
+            .iter()
+            .filter_map(|name| Some((name, std::env::var_os(name)?)));
+        let mut command_builder = Command::new(program);
+        command_builder
+            .args(args)
+            .current_dir(cwd)
+            .env_clear()
+            .envs(env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // The command leads its own process group, so that the processes it starts are killed
+        // along with it
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command_builder, 0);
+        let mut child = command_builder.spawn()?;
+        let stdout = capture(child.stdout.take());
+        let stderr = capture(child.stderr.take());
+
+        let start = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if start.elapsed() >= self.timeout {
+                tracing::warn!(target: "rig", "Command {} timed out, killing it", command);
+                kill(&mut child)?;
+                child.wait()?;
+                return Err(LocalToolError::Timeout(self.timeout));
+            }
+            Delay::new(POLL_INTERVAL).await;
+        };
+
+        // The outputs are closed once all the processes holding them exited (e.g.: a background
+        // process started by the command), which may not happen before the timeout
+        let outputs = future::join(stdout, stderr);
+        let remaining = self.timeout.saturating_sub(start.elapsed());
+        let (stdout, stderr) = match future::select(outputs, Delay::new(remaining)).await {
+            Either::Left(((stdout, stderr), _)) => {
+                (stdout.unwrap_or_default(), stderr.unwrap_or_default())
+            }
+            Either::Right(_) => {
+                tracing::warn!(target: "rig",
+                    "The outputs of command {} are still open, killing its processes", command
+                );
+                kill(&mut child)?;
+                return Err(LocalToolError::Timeout(self.timeout));
+            }
+        };
+
+        let exit_code = status
+            .code()
+            .map(|code| code.to_string())
+            .unwrap_or_else(|| "none (killed by a signal)".to_string());
+        let output = format!(
+            "Exit code: {exit_code}\nStdout:\n{}\nStderr:\n{}",
+            String::from_utf8_lossy(&stdout),
+            String::from_utf8_lossy(&stderr)
+        );
+        Ok(self.limit_output("run_command", output).await)
+    }
+
+    /// Write `content` to the file `path` (creating it and its directories if needed)
+    pub fn write_file(&self, path: &str, content: &str) -> Result<String, LocalToolError> {
+        let resolved = self.roots.resolve(path)?;
+        if let Some(parent) = resolved.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&resolved, content)?;
+        tracing::info!(target: "rig", "Wrote {} bytes to {}", content.len(), resolved.display());
+        Ok(format!("Wrote {} bytes to {path}", content.len()))
+    }
+
+    /// Tool `run_command` running an allowed command, for agents
+    pub fn run_command_tool(&self) -> impl Tool + 'static {
+        let tools = self.clone();
+        let description = format!(
+            "Run a command of the project (without a shell). The allowed commands are: {}.",
+            self.commands.join(", ")
+        );
+        tool::from_typed_fn("run_command", &description, move |args: RunCommandArgs| {
+            let tools = tools.clone();
+            async move {
+                tools
+                    .run_command(&args.command, &args.args, args.cwd.as_deref())
+                    .await
+            }
+        })
+        .with_annotations(destructive())
+    }
+
+    /// Tool `read_file` reading a file or a range of its lines, for agents (i.e.: the
+    /// [CodeTools::read_tool] of the roots)
+    pub fn read_file_tool(&self) -> impl Tool + 'static {
+        CodeTools::new(self.roots.clone()).read_tool()
+    }
+
+    /// Tool `write_file` writing a file, for agents
+    pub fn write_file_tool(&self) -> impl Tool + 'static {
+        let tools = self.clone();
+        tool::from_typed_fn(
+            "write_file",
+            "Write a file of the project, replacing its content",
+            move |args: WriteFileArgs| {
+                let result = tools.write_file(&args.path, &args.content);
+                async move { result }
+            },
+        )
+        .with_annotations(ToolAnnotations {
+            idempotent: Some(true),
+            ..destructive()
+        })
+    }
+}
+
+/// Annotations of the tools modifying the files
+fn destructive() -> ToolAnnotations {
+    ToolAnnotations {
+        read_only: Some(false),
+        destructive: Some(true),
+        open_world: Some(false),
+        ..Default::default()
+    }
+}
+
+/// Whether `command` is the path of a program (e.g.: `./test.sh`), rather than its name
+fn has_path(command: &str) -> bool {
+    command.contains('/') || command.contains(std::path::MAIN_SEPARATOR)
+}
+
+/// Kill the command `child` and, on Unix, the processes of its process group (i.e.: the
+/// processes it started, unless they left the group)
+fn kill(child: &mut Child) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        // SAFETY: `kill` has no memory safety requirements
+        if unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) } == -1 {
+            let error = std::io::Error::last_os_error();
+            // The group is gone once all its processes exited
+            if error.raw_os_error() != Some(libc::ESRCH) {
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    child.kill()
+}
+
+/// Read `output` (the standard output or error of a command) in a thread, keeping its first
+/// bytes, received once the output is closed
+fn capture(output: Option<impl Read + Send + 'static>) -> oneshot::Receiver<Vec<u8>> {
+    let (sender, receiver) = oneshot::channel();
+    std::thread::spawn(move || {
+        let mut captured = vec![];
+        if let Some(mut output) = output {
+            let _ = (&mut output)
+                .take(MAX_CAPTURED_BYTES)
+                .read_to_end(&mut captured);
+            // Drain the rest, for the command not to block on a full pipe
+            let _ = std::io::copy(&mut output, &mut std::io::sink());
+        }
+        let _ = sender.send(captured);
+    });
+    receiver
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_tools() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let tools = LocalTools::new(Roots::new().root(dir.path()).unwrap())
+            .command("echo")
+            .command("env")
+            .command("sleep")
+            .command("sh")
+            .command("./scripts/test.sh")
+            .timeout(Duration::from_millis(200))
+            .max_output(100);
+
+        let output = tools
+            .run_command("echo", &["hello; rm -rf ~".to_string()], None)
+            .await
+            .unwrap();
+        assert_eq!(
+            output,
+            "Exit code: 0\nStdout:\nhello; rm -rf ~\n\nStderr:\n"
+        );
+        assert!(matches!(
+            tools.run_command("rm", &["-rf".to_string()], None).await,
+            Err(LocalToolError::CommandNotAllowed(_))
+        ));
+        assert!(matches!(
+            tools.run_command("sleep", &["5".to_string()], None).await,
+            Err(LocalToolError::Timeout(_))
+        ));
+        // The background processes holding the outputs are killed after the timeout too
+        let start = Instant::now();
+        let args = ["-c".to_string(), "sleep 5 & echo started".to_string()];
+        assert!(matches!(
+            tools.run_command("sh", &args, None).await,
+            Err(LocalToolError::Timeout(_))
+        ));
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // The commands only get the allowed environment variables
+        let output = tools
+            .clone()
+            .max_output(DEFAULT_MAX_OUTPUT)
+            .run_command("env", &[], None)
+            .await
+            .unwrap();
+        assert!(output
+            .lines()
+            .skip(2)
+            .take_while(|line| *line != "Stderr:")
+            .filter_map(|line| line.split_once('='))
+            .all(|(name, _)| DEFAULT_ENV_VARS.contains(&name)));
+
+        // The commands with a path are matched by their resolved path
+        tools.write_file("scripts/test.sh", "echo ok").unwrap();
+        tools.write_file("other/test.sh", "rm -rf ~").unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        assert_eq!(
+            tools
+                .allowed_program("./test.sh", &root.join("scripts"))
+                .unwrap(),
+            root.join("scripts/test.sh")
+        );
+        assert!(matches!(
+            tools.allowed_program("./test.sh", &root.join("other")),
+            Err(LocalToolError::CommandNotAllowed(_))
+        ));
+
+        tools
+            .write_file("notes/todo.txt", &"x".repeat(300))
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("notes/todo.txt")).unwrap(),
+            "x".repeat(300)
+        );
+        assert!(matches!(
+            tools.write_file("../escape.txt", "x"),
+            Err(LocalToolError::CodeToolError(CodeToolError::OutsideRoots(
+                _
+            )))
+        ));
+        assert!(matches!(
+            tools.run_command("echo", &[], Some("..")).await,
+            Err(LocalToolError::CodeToolError(CodeToolError::OutsideRoots(
+                _
+            )))
+        ));
+    }
+}
@@ -0,0 +1,309 @@
+//! Typed clients of the fabelis Twitter and Discord MCP servers, used by the examples of the
+//! crate, so that their tools are called with typed arguments rather than `json!` blobs.
+//!
+//! [TwitterMcp] and [DiscordMcp] wrap the MCP client of their server (or any [McpToolCaller]):
+//! - their methods call the tools of the server directly (e.g.: [TwitterMcp::post]),
+//! - their `*_tool` methods return the tools preconfigured for agents (e.g.:
+//!   [TwitterMcp::post_tool]), whose arguments only have the fields the model should fill.
+//!
+//! The credentials of the servers are secure values of the MCP client (see
+//! [credentials_from_env]): the helpers leave them empty in the arguments, for the client to
+//! fill them in.
+//!
+//! # Example
+//! ```rust
+//! use std::sync::Arc;
+//! use mcp_core::{client::Client, transport::{ClientSseTransport, Transport}};
+//! use mcp_rig::{mcp_servers::{self, TwitterMcp}, providers::openai};
+//!
+//! let transport = ClientSseTransport::builder(mcp_servers::TWITTER_SERVER_URL.to_string()).build();
+//! transport.open().await?;
+//! let builder = Client::builder(transport).use_strict();
+//! let client = Arc::new(mcp_servers::credentials_from_env(builder, mcp_servers::TWITTER_SECURE_VALUES).build());
+//! // Start and initialize the client...
+//!
+//! let twitter = TwitterMcp::new(client);
+//! twitter.post("Hello from mcp-rig!").await?;
+//!
+//! let agent = openai.agent(openai::GPT_4O).tool(twitter.post_tool()).build();
+//! agent.prompt("Tweet a haiku about Rust").await?;
+//! ```
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    completion::message::ToolResultContent,
+    tool::{self, Tool, ToolError},
+};
+
+/// URL of the fabelis Twitter MCP server.
+pub const TWITTER_SERVER_URL: &str = "https://twitter-mcp.fabelis.ai";
+
+/// URL of the fabelis Discord MCP server.
+pub const DISCORD_SERVER_URL: &str = "https://discord-mcp.fabelis.ai";
+
+/// Secure values of the Twitter MCP server.
+pub const TWITTER_SECURE_VALUES: &[&str] = &[
+    "twitter_api_key",
+    "twitter_api_secret",
+    "twitter_access_token",
+    "twitter_access_token_secret",
+];
+
+/// Secure values of the Discord MCP server.
+pub const DISCORD_SECURE_VALUES: &[&str] = &["discord_token"];
+
+/// Caller of the tools of an MCP server (e.g.: its MCP client).
+pub trait McpToolCaller: Send + Sync {
+    /// Call the tool `name` with `arguments`, returning the texts of its result
+    fn call_tool<'a>(
+        &'a self,
+        name: &'a str,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<String, ToolError>>;
+
+    /// Call the tool `name` with `arguments`, returning the contents of its result (e.g.: the
+    /// images of the screenshot tools). Only the texts by default.
+    fn call_tool_content<'a>(
+        &'a self,
+        name: &'a str,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<Vec<ToolResultContent>, ToolError>> {
+        Box::pin(async move {
+            Ok(vec![ToolResultContent::text(
+                self.call_tool(name, arguments).await?,
+            )])
+        })
+    }
+}
+
+impl<T: mcp_core::transport::Transport> McpToolCaller for mcp_core::client::Client<T> {
+    fn call_tool<'a>(
+        &'a self,
+        name: &'a str,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(tool::call_mcp_tool(self, name, arguments))
+    }
+
+    fn call_tool_content<'a>(
+        &'a self,
+        name: &'a str,
+        arguments: Value,
+    ) -> BoxFuture<'a, Result<Vec<ToolResultContent>, ToolError>> {
+        Box::pin(tool::call_mcp_tool_content(self, name, arguments))
+    }
+}
+
+/// Give the secure values `names` to the MCP client built by `builder`, read from the
+/// environment variables of the same name in uppercase (e.g.: `TWITTER_API_KEY` for
+/// `twitter_api_key`).
+pub fn credentials_from_env<T: mcp_core::transport::Transport>(
+    builder: mcp_core::client::ClientBuilder<T>,
+    names: &[&str],
+) -> mcp_core::client::ClientBuilder<T> {
+    names.iter().fold(builder, |builder, name| {
+        builder.with_secure_value(
+            *name,
+            mcp_core::client::SecureValue::Env(name.to_uppercase()),
+        )
+    })
+}
+
+/// `arguments` with the secure values `secure_values` left empty, for the MCP client to fill
+fn with_secure_values(mut arguments: Map<String, Value>, secure_values: &[&str]) -> Value {
+    for name in secure_values {
+        arguments.insert(name.to_string(), Value::String(String::new()));
+    }
+    Value::Object(arguments)
+}
+
+/// Arguments of [TwitterMcp::post_tool].
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TweetArgs {
+    /// Text of the tweet (at most 280 characters)
+    pub text: String,
+}
+
+/// Typed client of the Twitter MCP server (see the [module](self) docs).
+pub struct TwitterMcp<C> {
+    client: Arc<C>,
+}
+
+impl<C> Clone for TwitterMcp<C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+        }
+    }
+}
+
+impl<C: McpToolCaller + 'static> TwitterMcp<C> {
+    /// Name of the tool posting a tweet
+    pub const POST: &'static str = "Post";
+
+    pub fn new(client: Arc<C>) -> Self {
+        Self { client }
+    }
+
+    /// Post the tweet `text`
+    pub async fn post(&self, text: &str) -> Result<String, ToolError> {
+        let mut arguments = Map::new();
+        arguments.insert("tweet".to_string(), text.into());
+        self.client
+            .call_tool(
+                Self::POST,
+                with_secure_values(arguments, TWITTER_SECURE_VALUES),
+            )
+            .await
+    }
+
+    /// Tool `twitter_post` posting a tweet, for agents
+    pub fn post_tool(&self) -> impl Tool + 'static {
+        let twitter = self.clone();
+        tool::from_typed_fn(
+            "twitter_post",
+            "Post a tweet on Twitter",
+            move |args: TweetArgs| {
+                let twitter = twitter.clone();
+                async move { twitter.post(&args.text).await }
+            },
+        )
+    }
+}
+
+/// Arguments of [DiscordMcp::send_tool].
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DiscordMessageArgs {
+    /// ID of the Discord channel
+    pub channel_id: String,
+    /// Content of the message
+    pub message: String,
+}
+
+/// Typed client of the Discord MCP server (see the [module](self) docs).
+pub struct DiscordMcp<C> {
+    client: Arc<C>,
+}
+
+impl<C> Clone for DiscordMcp<C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+        }
+    }
+}
+
+impl<C: McpToolCaller + 'static> DiscordMcp<C> {
+    /// Name of the tool sending a message
+    pub const SEND: &'static str = "Send";
+
+    pub fn new(client: Arc<C>) -> Self {
+        Self { client }
+    }
+
+    /// Send `message` to the channel `channel_id`
+    pub async fn send(&self, channel_id: &str, message: &str) -> Result<String, ToolError> {
+        let mut arguments = Map::new();
+        arguments.insert("channel_id".to_string(), channel_id.into());
+        arguments.insert("message".to_string(), message.into());
+        self.client
+            .call_tool(
+                Self::SEND,
+                with_secure_values(arguments, DISCORD_SECURE_VALUES),
+            )
+            .await
+    }
+
+    /// Tool `discord_send` sending a message to a Discord channel, for agents
+    pub fn send_tool(&self) -> impl Tool + 'static {
+        let discord = self.clone();
+        tool::from_typed_fn(
+            "discord_send",
+            "Send a message to a Discord channel",
+            move |args: DiscordMessageArgs| {
+                let discord = discord.clone();
+                async move { discord.send(&args.channel_id, &args.message).await }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        completion::Prompt,
+        providers::mock::{self, MockResponse},
+    };
+
+    /// Caller recording the tool calls
+    #[derive(Default)]
+    struct MockCaller {
+        calls: Mutex<Vec<(String, Value)>>,
+    }
+
+    impl McpToolCaller for MockCaller {
+        fn call_tool<'a>(
+            &'a self,
+            name: &'a str,
+            arguments: Value,
+        ) -> BoxFuture<'a, Result<String, ToolError>> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push((name.to_string(), arguments));
+            Box::pin(async move { Ok(format!("{name} done")) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mcp_servers() {
+        let caller = Arc::new(MockCaller::default());
+        let twitter = TwitterMcp::new(caller.clone());
+        let discord = DiscordMcp::new(caller.clone());
+
+        assert_eq!(twitter.post("hello").await.unwrap(), "Post done");
+        discord.send("1234", "hi").await.unwrap();
+        assert_eq!(
+            caller.calls.lock().unwrap()[..],
+            [
+                (
+                    "Post".to_string(),
+                    json!({
+                        "tweet": "hello",
+                        "twitter_api_key": "",
+                        "twitter_api_secret": "",
+                        "twitter_access_token": "",
+                        "twitter_access_token_secret": ""
+                    })
+                ),
+                (
+                    "Send".to_string(),
+                    json!({"channel_id": "1234", "message": "hi", "discord_token": ""})
+                ),
+            ]
+        );
+
+        // The preconfigured tools only ask the model for the fields it should fill
+        let model = mock::CompletionModel::new().push(MockResponse::tool_call(
+            "twitter_post",
+            json!({"text": "A haiku"}),
+        ));
+        let agent = model.agent().tool(twitter.post_tool()).build();
+        assert_eq!(
+            agent.prompt("Tweet a haiku").await.unwrap(),
+            "\"Post done\""
+        );
+        assert_eq!(caller.calls.lock().unwrap()[2].1["tweet"], "A haiku");
+        assert_eq!(model.requests()[0].tools, vec!["twitter_post"]);
+    }
+}
@@ -0,0 +1,627 @@
+//! WebSocket transport of the MCP clients (requires the `mcp-websocket` feature).
+//!
+//! Several community MCP servers only speak WebSocket. A [ClientWsTransport] connects an MCP
+//! client to such a server, and reconnects it (with the backoff of its [RetryPolicy]) when the
+//! connection drops: the messages sent while reconnecting are queued, and the `initialize`
+//! handshake of the client is replayed on the new connection, so that the tools of the server
+//! keep working. The requests waiting for a response when the connection dropped fail with an
+//! error: they are not replayed, as the server may have run them (e.g.: a tool call with side
+//! effects).
+//!
+//! [McpTransport] picks the transport of a server from its URL: WebSocket for the `ws://` and
+//! `wss://` URLs, SSE otherwise.
+//!
+//! # Example
+//! ```rust
+//! use std::{sync::Arc, time::Duration};
+//! use mcp_core::{client::Client, transport::Transport, types::Implementation};
+//! use mcp_rig::{mcp_transport::ClientWsTransport, pipeline::retry::RetryPolicy, providers::openai};
+//!
+//! let transport = ClientWsTransport::builder("wss://mcp.example.com/ws")
+//!     .header("Authorization", "Bearer secret")
+//!     .reconnect(RetryPolicy::new(10).backoff(Duration::from_secs(1), Duration::from_secs(30)))
+//!     .build();
+//! transport.open().await?;
+//!
+//! let client = Arc::new(Client::builder(transport).build());
+//! tokio::spawn({
+//!     let client = client.clone();
+//!     async move { client.start().await }
+//! });
+//! client.initialize(Implementation { name: "agent".into(), version: "1.0.0".into() }).await?;
+//!
+//! let tools = client.list_tools(None, None).await?.tools;
+//! let agent = tools
+//!     .into_iter()
+//!     .fold(openai.agent(openai::GPT_4O), |builder, tool| builder.mcp_tool(tool, client.clone()))
+//!     .build();
+//! ```
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use futures_timer::Delay;
+use mcp_core::transport::{ClientSseTransport, Message, Transport};
+use serde_json::{json, Value};
+use tokio::{
+    net::TcpStream,
+    sync::{mpsc, Mutex as AsyncMutex},
+    task::JoinHandle,
+};
+use tokio_tungstenite::{
+    tungstenite::{self, client::IntoClientRequest, http::HeaderValue},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::pipeline::retry::RetryPolicy;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Maximum time to wait for the response to the replayed `initialize` request.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// JSON-RPC error code of the requests failed by a dropped connection.
+const CONNECTION_LOST: i64 = -32000;
+
+/// Configuration of a [ClientWsTransport].
+#[derive(Clone, Debug)]
+struct WsConfig {
+    url: String,
+    headers: Vec<(String, String)>,
+    reconnect: RetryPolicy,
+}
+
+impl WsConfig {
+    async fn connect(&self) -> Result<Socket> {
+        let mut request = self.url.as_str().into_client_request()?;
+        for (name, value) in &self.headers {
+            request.headers_mut().insert(
+                tungstenite::http::HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        let (socket, _) = tokio_tungstenite::connect_async(request).await?;
+        Ok(socket)
+    }
+}
+
+/// Builder of a [ClientWsTransport].
+pub struct ClientWsTransportBuilder {
+    config: WsConfig,
+}
+
+impl ClientWsTransportBuilder {
+    /// Add the header `name` to the handshake request (e.g.: for authentication)
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.config
+            .headers
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Reconnect according to `policy` when the connection drops (by default, up to 3 times
+    /// with the default backoff of [RetryPolicy])
+    pub fn reconnect(mut self, policy: RetryPolicy) -> Self {
+        self.config.reconnect = policy;
+        self
+    }
+
+    pub fn build(self) -> ClientWsTransport {
+        ClientWsTransport {
+            config: Arc::new(self.config),
+            connection: Mutex::new(None),
+        }
+    }
+}
+
+/// Open connection of a [ClientWsTransport], managed by a background task.
+struct Connection {
+    outgoing: mpsc::UnboundedSender<String>,
+    incoming: Arc<AsyncMutex<mpsc::UnboundedReceiver<Result<String>>>>,
+    task: JoinHandle<()>,
+}
+
+/// WebSocket transport of an MCP client, reconnecting when the connection drops (see the
+/// [module](self) docs).
+pub struct ClientWsTransport {
+    config: Arc<WsConfig>,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl ClientWsTransport {
+    pub fn builder(url: &str) -> ClientWsTransportBuilder {
+        ClientWsTransportBuilder {
+            config: WsConfig {
+                url: url.to_string(),
+                headers: vec![],
+                reconnect: RetryPolicy::default(),
+            },
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Connection>> {
+        self.connection
+            .lock()
+            .expect("WebSocket transport lock poisoned")
+    }
+}
+
+#[async_trait]
+impl Transport for ClientWsTransport {
+    async fn send(&self, message: &Message) -> Result<()> {
+        let text = serde_json::to_string(message)?;
+        let outgoing = self
+            .lock()
+            .as_ref()
+            .map(|connection| connection.outgoing.clone())
+            .ok_or_else(|| anyhow!("WebSocket transport is not open"))?;
+        outgoing
+            .send(text)
+            .map_err(|_| anyhow!("WebSocket transport is closed"))
+    }
+
+    async fn receive(&self) -> Result<Message> {
+        let incoming = self
+            .lock()
+            .as_ref()
+            .map(|connection| connection.incoming.clone())
+            .ok_or_else(|| anyhow!("WebSocket transport is not open"))?;
+        let text = incoming
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or_else(|| anyhow!("WebSocket transport is closed"))??;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Connect to the server (failing if it is unreachable), and start the task managing the
+    /// connection
+    async fn open(&self) -> Result<()> {
+        let socket = self.config.connect().await?;
+        let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+        let (incoming_tx, incoming) = mpsc::unbounded_channel();
+        let task = tokio::spawn(run(self.config.clone(), socket, outgoing_rx, incoming_tx));
+
+        if let Some(previous) = self.lock().replace(Connection {
+            outgoing,
+            incoming: Arc::new(AsyncMutex::new(incoming)),
+            task,
+        }) {
+            previous.task.abort();
+        }
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<()> {
+        // Dropping the sender ends the task, which closes the socket
+        self.lock().take();
+        Ok(())
+    }
+}
+
+/// Forward the messages between the channels of the transport and the socket, reconnecting
+/// the socket when the connection drops
+async fn run(
+    config: Arc<WsConfig>,
+    mut socket: Socket,
+    mut outgoing: mpsc::UnboundedReceiver<String>,
+    incoming: mpsc::UnboundedSender<Result<String>>,
+) {
+    let mut session = Session::default();
+    // Message whose send failed, sent again once reconnected
+    let mut unsent = None;
+
+    loop {
+        // Message whose send failed (if any), if the connection dropped
+        let dropped = match unsent.take() {
+            Some(text) => send(&mut socket, &mut session, text).await.err().map(Some),
+            None => tokio::select! {
+                message = outgoing.recv() => {
+                    let Some(text) = message else {
+                        let _ = socket.close(None).await;
+                        return;
+                    };
+                    send(&mut socket, &mut session, text).await.err().map(Some)
+                }
+                frame = socket.next() => match frame {
+                    Some(Ok(tungstenite::Message::Text(text))) => {
+                        session.received(&text);
+                        if incoming.send(Ok(text)).is_err() {
+                            // The transport was dropped
+                            return;
+                        }
+                        None
+                    }
+                    Some(Ok(tungstenite::Message::Close(_))) | None => {
+                        tracing::warn!(target: "rig", "MCP WebSocket connection closed by the server");
+                        Some(None)
+                    }
+                    Some(Err(error)) => {
+                        tracing::warn!(target: "rig", "MCP WebSocket connection failed: {}", error);
+                        Some(None)
+                    }
+                    // Pings are answered by tungstenite
+                    Some(Ok(_)) => None,
+                },
+            },
+        };
+        let Some(failed) = dropped else {
+            continue;
+        };
+        unsent = failed;
+
+        // The requests sent on the dropped connection will not be answered
+        for response in session.fail_in_flight() {
+            if incoming.send(Ok(response)).is_err() {
+                return;
+            }
+        }
+        match reconnect(&config, &session, &incoming).await {
+            Ok(reconnected) => socket = reconnected,
+            Err(error) => {
+                let _ = incoming.send(Err(error));
+                return;
+            }
+        }
+    }
+}
+
+/// Send `text` to the server, returning it if the send failed
+async fn send(
+    socket: &mut Socket,
+    session: &mut Session,
+    text: String,
+) -> std::result::Result<(), String> {
+    match socket.send(tungstenite::Message::text(text.clone())).await {
+        Ok(()) => {
+            session.sent(&text);
+            Ok(())
+        }
+        Err(error) => {
+            tracing::warn!(target: "rig", "MCP WebSocket send failed: {}", error);
+            Err(text)
+        }
+    }
+}
+
+/// Reconnect to the server, and replay the handshake of the client (forwarding the other
+/// messages of the server to `incoming`)
+async fn reconnect(
+    config: &WsConfig,
+    session: &Session,
+    incoming: &mpsc::UnboundedSender<Result<String>>,
+) -> Result<Socket> {
+    let policy = &config.reconnect;
+    let mut last_error = anyhow!("No reconnection attempt");
+
+    for attempt in 0..policy.max_retries {
+        Delay::new(policy.delay(attempt)).await;
+        let result = async {
+            let mut socket = config.connect().await?;
+            let Some((id, initialize)) = session.initialize() else {
+                return Ok(socket);
+            };
+            socket.send(tungstenite::Message::text(initialize)).await?;
+            // The client is only initialized once the server answered the `initialize` request
+            tokio::select! {
+                response = initialize_response(&mut socket, id, incoming) => response?,
+                _ = Delay::new(HANDSHAKE_TIMEOUT) => {
+                    return Err(anyhow!("No response to the initialize request"));
+                }
+            }
+            if let Some(initialized) = session.initialized.clone() {
+                socket.send(tungstenite::Message::text(initialized)).await?;
+            }
+            Ok::<_, anyhow::Error>(socket)
+        }
+        .await;
+
+        match result {
+            Ok(socket) => {
+                tracing::info!(target: "rig",
+                    "Reconnected to MCP server {} (attempt {})", config.url, attempt + 1
+                );
+                return Ok(socket);
+            }
+            Err(error) => {
+                tracing::warn!(target: "rig",
+                    "Reconnection to MCP server {} failed (attempt {}): {}", config.url, attempt + 1, error
+                );
+                last_error = error;
+            }
+        }
+    }
+
+    Err(anyhow!(
+        "Connection to MCP server {} lost after {} reconnection attempts: {}",
+        config.url,
+        policy.max_retries,
+        last_error
+    ))
+}
+
+/// Wait for the response to the `initialize` request `id` (not forwarded to the client, which
+/// already got one), forwarding the other messages of the server to `incoming`
+async fn initialize_response(
+    socket: &mut Socket,
+    id: &Value,
+    incoming: &mpsc::UnboundedSender<Result<String>>,
+) -> Result<()> {
+    while let Some(frame) = socket.next().await {
+        let tungstenite::Message::Text(text) = frame? else {
+            continue;
+        };
+        match serde_json::from_str::<Value>(&text) {
+            Ok(message) if message.get("method").is_none() && message.get("id") == Some(id) => {
+                return match message.get("error") {
+                    Some(error) => Err(anyhow!("Initialize request failed: {}", error)),
+                    None => Ok(()),
+                };
+            }
+            _ => {
+                let _ = incoming.send(Ok(text));
+            }
+        }
+    }
+    Err(anyhow!("Connection closed during the handshake"))
+}
+
+/// MCP session of the client: its `initialize` handshake, replayed when reconnecting so that the
+/// server initializes the new connection, and its requests waiting for a response.
+#[derive(Debug, Default)]
+struct Session {
+    /// ID and message of the `initialize` request of the client
+    initialize: Option<(Value, String)>,
+    /// Whether the server answered the `initialize` request
+    initialize_answered: bool,
+    initialized: Option<String>,
+    /// IDs of the requests of the client not answered yet
+    in_flight: Vec<Value>,
+}
+
+impl Session {
+    /// Track the message `text` sent by the client
+    fn sent(&mut self, text: &str) {
+        let Ok(message) = serde_json::from_str::<Value>(text) else {
+            return;
+        };
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return;
+        };
+        let id = message.get("id").cloned();
+        match method {
+            "initialize" => {
+                self.initialize = id.clone().map(|id| (id, text.to_string()));
+                self.initialize_answered = false;
+            }
+            "notifications/initialized" => self.initialized = Some(text.to_string()),
+            _ => {}
+        }
+        self.in_flight.extend(id);
+    }
+
+    /// Track the message `text` received from the server
+    fn received(&mut self, text: &str) {
+        let Ok(message) = serde_json::from_str::<Value>(text) else {
+            return;
+        };
+        let Some(id) = message
+            .get("id")
+            .filter(|_| message.get("method").is_none())
+        else {
+            return;
+        };
+        self.in_flight.retain(|pending| pending != id);
+        if self
+            .initialize
+            .as_ref()
+            .is_some_and(|(initialize, _)| initialize == id)
+        {
+            self.initialize_answered = true;
+        }
+    }
+
+    /// ID and message of the `initialize` request to replay on a new connection, if the
+    /// server answered it
+    fn initialize(&self) -> Option<(&Value, String)> {
+        self.initialize
+            .as_ref()
+            .filter(|_| self.initialize_answered)
+            .map(|(id, text)| (id, text.clone()))
+    }
+
+    /// Error responses of the requests in flight, which won't be answered (the server may have
+    /// run them, e.g.: a tool call with side effects, so they are not replayed)
+    fn fail_in_flight(&mut self) -> Vec<String> {
+        self.in_flight
+            .drain(..)
+            .map(|id| {
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {
+                        "code": CONNECTION_LOST,
+                        "message": "Connection to the MCP server lost",
+                    },
+                })
+                .to_string()
+            })
+            .collect()
+    }
+}
+
+/// Transport of an MCP client, picked from the URL of the server.
+pub enum McpTransport {
+    Sse(ClientSseTransport),
+    WebSocket(ClientWsTransport),
+}
+
+impl McpTransport {
+    /// WebSocket transport for the `ws://` and `wss://` URLs, SSE transport otherwise
+    pub fn from_url(url: &str) -> Self {
+        if url.starts_with("ws://") || url.starts_with("wss://") {
+            McpTransport::WebSocket(ClientWsTransport::builder(url).build())
+        } else {
+            McpTransport::Sse(ClientSseTransport::builder(url.to_string()).build())
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for McpTransport {
+    async fn send(&self, message: &Message) -> Result<()> {
+        match self {
+            McpTransport::Sse(transport) => transport.send(message).await,
+            McpTransport::WebSocket(transport) => transport.send(message).await,
+        }
+    }
+
+    async fn receive(&self) -> Result<Message> {
+        match self {
+            McpTransport::Sse(transport) => transport.receive().await,
+            McpTransport::WebSocket(transport) => transport.receive().await,
+        }
+    }
+
+    async fn open(&self) -> Result<()> {
+        match self {
+            McpTransport::Sse(transport) => transport.open().await,
+            McpTransport::WebSocket(transport) => transport.open().await,
+        }
+    }
+
+    async fn close(&self) -> Result<()> {
+        match self {
+            McpTransport::Sse(transport) => transport.close().await,
+            McpTransport::WebSocket(transport) => transport.close().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session() {
+        let mut session = Session::default();
+        let initialize = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+        let initialized = r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#;
+        session.sent(initialize);
+        // The `initialize` request is only replayed once answered
+        assert!(session.initialize().is_none());
+        session.received(r#"{"jsonrpc":"2.0","id":1,"result":{}}"#);
+        session.sent(initialized);
+        session.sent(r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{}}"#);
+        session.sent(r#"{"jsonrpc":"2.0","id":3,"method":"tools/list","params":{}}"#);
+        session.received(r#"{"jsonrpc":"2.0","id":3,"result":{}}"#);
+
+        assert_eq!(
+            session.initialize(),
+            Some((&json!(1), initialize.to_string()))
+        );
+        assert_eq!(session.initialized.as_deref(), Some(initialized));
+        // The requests not answered fail
+        let failed = session
+            .fail_in_flight()
+            .iter()
+            .map(|text| serde_json::from_str::<Value>(text).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0]["id"], 2);
+        assert_eq!(failed[0]["error"]["code"], CONNECTION_LOST);
+        assert!(session.fail_in_flight().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reconnect() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut received = vec![];
+            for connection in 0..2 {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+                while let Some(Ok(tungstenite::Message::Text(text))) = socket.next().await {
+                    let message = serde_json::from_str::<Value>(&text).unwrap();
+                    let method = message["method"].as_str().unwrap_or_default().to_string();
+                    received.push(method.clone());
+                    match method.as_str() {
+                        // The first connection drops during the tool call
+                        "tools/call" if connection == 0 => break,
+                        "notifications/initialized" => {}
+                        _ => {
+                            let response =
+                                json!({"jsonrpc": "2.0", "id": message["id"], "result": {}});
+                            socket
+                                .send(tungstenite::Message::text(response.to_string()))
+                                .await
+                                .unwrap();
+                        }
+                    }
+                    if method == "tools/list" {
+                        break;
+                    }
+                }
+            }
+            received
+        });
+
+        let transport = ClientWsTransport::builder(&url)
+            .reconnect(
+                RetryPolicy::new(3).backoff(Duration::from_millis(10), Duration::from_millis(10)),
+            )
+            .build();
+        transport.open().await.unwrap();
+        let send = |message: Value| {
+            let message = serde_json::from_value::<Message>(message).unwrap();
+            let transport = &transport;
+            async move { transport.send(&message).await.unwrap() }
+        };
+        let response = || {
+            let transport = &transport;
+            async move { serde_json::to_value(transport.receive().await.unwrap()).unwrap() }
+        };
+
+        send(json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}})).await;
+        assert_eq!(response().await["id"], 1);
+        send(json!({"jsonrpc": "2.0", "method": "notifications/initialized"})).await;
+        send(json!({"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {}})).await;
+        // The tool call fails with the connection, rather than waiting forever
+        let failed = response().await;
+        assert_eq!(failed["id"], 2);
+        assert!(failed.get("error").is_some());
+
+        // The handshake is replayed on the new connection, before the next requests
+        send(json!({"jsonrpc": "2.0", "id": 3, "method": "tools/list", "params": {}})).await;
+        assert_eq!(response().await["id"], 3);
+        assert_eq!(
+            server.await.unwrap(),
+            vec![
+                "initialize",
+                "notifications/initialized",
+                "tools/call",
+                "initialize",
+                "notifications/initialized",
+                "tools/list"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_url() {
+        assert!(matches!(
+            McpTransport::from_url("wss://mcp.example.com/ws"),
+            McpTransport::WebSocket(_)
+        ));
+        assert!(matches!(
+            McpTransport::from_url("https://mcp.example.com"),
+            McpTransport::Sse(_)
+        ));
+    }
+}
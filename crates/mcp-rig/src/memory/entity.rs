@@ -0,0 +1,250 @@
+//! Entity memory: an [extractor](crate::extractor) maintains a structured profile of the user
+//! (i.e.: their name, preferences, ongoing tasks, and the entities they mentioned) from the turns
+//! of their conversations, and the profile is recalled at the start of their later sessions.
+//!
+//! The profiles of the users are kept in the [EntityMemory], shared by its clones: the memory of
+//! a user (see [EntityMemory::for_user]) is attached to each of their sessions. The profiles can
+//! be persisted with [EntityMemory::profiles] and [EntityMemory::load_profiles].
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{chat_session::ChatSession, memory::EntityMemory, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//! let memory = EntityMemory::new(openai.completion_model(openai::GPT_4O_MINI));
+//!
+//! let mut session = ChatSession::new(openai.agent(openai::GPT_4O).build())
+//!     .memory(memory.for_user("alice"));
+//! session.chat("I'm Alice, I'm vegetarian.").await?;
+//!
+//! // Later, in another session of the same user
+//! let mut session = ChatSession::new(openai.agent(openai::GPT_4O).build())
+//!     .memory(memory.for_user("alice"));
+//! session.chat("Suggest a recipe for tonight.").await?;
+//! ```
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use futures::future::BoxFuture;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{transcript, Memory, MemoryError};
+use crate::{
+    completion::{CompletionModel, Message},
+    extractor::{ExtractionError, ExtractorBuilder},
+};
+
+/// Preamble of the extractor when it updates the profile of the user.
+const EXTRACTOR_PREAMBLE: &str = "You maintain the profile of a user from their conversations. \
+    Keep the known facts unless the user contradicts them, add the new ones, and drop the tasks \
+    the user completed or abandoned. Only record what the user said about themselves.";
+
+type ExtractFn =
+    Arc<dyn Fn(String) -> BoxFuture<'static, Result<UserProfile, ExtractionError>> + Send + Sync>;
+
+/// Entity mentioned by a user (e.g.: a relative, a pet, a place or a project).
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct Entity {
+    pub name: String,
+    /// Kind of the entity (e.g.: person, pet, place, project)
+    pub kind: String,
+    /// Facts about the entity (e.g.: "3 years old")
+    #[serde(default)]
+    pub facts: Vec<String>,
+}
+
+/// Profile of a user, as maintained by an [EntityMemory].
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct UserProfile {
+    /// Name of the user, if known
+    pub name: Option<String>,
+    /// Preferences of the user (e.g.: "vegetarian", "short answers")
+    #[serde(default)]
+    pub preferences: Vec<String>,
+    /// Ongoing tasks of the user (e.g.: "planning a trip to Japan in May")
+    #[serde(default)]
+    pub tasks: Vec<String>,
+    /// Entities mentioned by the user
+    #[serde(default)]
+    pub entities: Vec<Entity>,
+}
+
+impl UserProfile {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Profile as the context of a session
+    fn context(&self) -> String {
+        let mut lines =
+            vec!["What you know about the user from previous conversations:".to_string()];
+        if let Some(name) = &self.name {
+            lines.push(format!("- Name: {name}"));
+        }
+        if !self.preferences.is_empty() {
+            lines.push(format!("- Preferences: {}", self.preferences.join("; ")));
+        }
+        if !self.tasks.is_empty() {
+            lines.push(format!("- Ongoing tasks: {}", self.tasks.join("; ")));
+        }
+        for entity in &self.entities {
+            lines.push(format!(
+                "- {} ({}): {}",
+                entity.name,
+                entity.kind,
+                entity.facts.join("; ")
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Entity memory of the users of chat sessions (see the [module](self) docs).
+#[derive(Clone)]
+pub struct EntityMemory {
+    extract: ExtractFn,
+    /// User whose profile is recalled and updated
+    user_id: String,
+    profiles: Arc<Mutex<HashMap<String, UserProfile>>>,
+}
+
+impl EntityMemory {
+    /// Maintain the profiles of the users with an extractor of `model`
+    pub fn new<M: CompletionModel + 'static>(model: M) -> Self {
+        let extractor = Arc::new(
+            ExtractorBuilder::<UserProfile, M>::new(model)
+                .preamble(EXTRACTOR_PREAMBLE)
+                .build(),
+        );
+        Self {
+            extract: Arc::new(move |text| {
+                let extractor = extractor.clone();
+                Box::pin(async move { extractor.extract(&text).await })
+            }),
+            user_id: String::new(),
+            profiles: Default::default(),
+        }
+    }
+
+    /// Memory of the user `user_id`, sharing the profiles of this memory
+    pub fn for_user(&self, user_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            ..self.clone()
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, UserProfile>> {
+        self.profiles.lock().expect("Entity memory lock poisoned")
+    }
+
+    /// Profile of the user of the memory
+    pub fn profile(&self) -> UserProfile {
+        self.lock().get(&self.user_id).cloned().unwrap_or_default()
+    }
+
+    /// Profiles of all the users, by user id (e.g.: to persist them)
+    pub fn profiles(&self) -> HashMap<String, UserProfile> {
+        self.lock().clone()
+    }
+
+    /// Load the `profiles` of users (e.g.: persisted by another process), replacing their
+    /// current profiles
+    pub fn load_profiles(&self, profiles: HashMap<String, UserProfile>) {
+        self.lock().extend(profiles);
+    }
+}
+
+impl Memory for EntityMemory {
+    fn recall<'a>(
+        &'a self,
+        _prompt: &'a Message,
+        mut history: Vec<Message>,
+    ) -> BoxFuture<'a, Result<Vec<Message>, MemoryError>> {
+        Box::pin(async move {
+            let profile = self.profile();
+            if !profile.is_empty() {
+                history.insert(0, Message::user(profile.context()));
+            }
+            Ok(history)
+        })
+    }
+
+    fn record<'a>(&'a self, history: &'a [Message]) -> BoxFuture<'a, Result<(), MemoryError>> {
+        Box::pin(async move {
+            let turn = &history[history.len().saturating_sub(2)..];
+            let current = serde_json::to_string(&self.profile()).unwrap_or_default();
+            let profile = (self.extract)(format!(
+                "Current profile of the user:\n{current}\n\nLast turn of the conversation:\n{}",
+                transcript(turn)
+            ))
+            .await?;
+            self.lock().insert(self.user_id.clone(), profile);
+            Ok(())
+        })
+    }
+}
+
+impl std::fmt::Debug for EntityMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EntityMemory")
+            .field("user_id", &self.user_id)
+            .field("profiles", &self.lock().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        chat_session::ChatSession,
+        providers::mock::{self, MockResponse},
+    };
+
+    #[tokio::test]
+    async fn test_entity_memory() {
+        let model = mock::CompletionModel::new().push(MockResponse::tool_call(
+            "submit",
+            json!({
+                "name": "Alice",
+                "preferences": ["vegetarian"],
+                "tasks": [],
+                "entities": [{"name": "Tom", "kind": "cat", "facts": ["3 years old"]}]
+            }),
+        ));
+        let memory = EntityMemory::new(model.clone());
+        let chatbot = mock::CompletionModel::new().default_response(MockResponse::text("Noted!"));
+
+        let mut session =
+            ChatSession::new(chatbot.agent().build()).memory(memory.for_user("alice"));
+        session
+            .chat("I'm Alice, I'm vegetarian and my cat Tom is 3.")
+            .await
+            .unwrap();
+        let extraction = model.requests()[0].prompt_text().unwrap();
+        assert!(extraction
+            .contains("User: I'm Alice, I'm vegetarian and my cat Tom is 3.\nAssistant: Noted!"));
+
+        // The profile is recalled in the later sessions of the user only
+        let mut session =
+            ChatSession::new(chatbot.agent().build()).memory(memory.for_user("alice"));
+        session.chat("Suggest a recipe.").await.unwrap();
+        assert_eq!(
+            chatbot.requests()[1].chat_history,
+            vec![Message::user(
+                "What you know about the user from previous conversations:\n\
+                 - Name: Alice\n\
+                 - Preferences: vegetarian\n\
+                 - Tom (cat): 3 years old"
+            )]
+        );
+        assert!(memory.for_user("bob").profile().is_empty());
+        assert_eq!(memory.profiles().len(), 1);
+    }
+}
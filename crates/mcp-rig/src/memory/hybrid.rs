@@ -0,0 +1,332 @@
+//! Sliding window + summary memory: the most recent messages are kept verbatim, and the older
+//! ones are replaced by a summary, updated by a model as the messages slide out of the window.
+//!
+//! Optionally (see [HybridMemory::archive]), the messages out of the window are also archived
+//! with their embeddings, and the archived messages most similar to the prompt are recalled along
+//! with the summary (e.g.: a detail the summary dropped).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{chat_session::ChatSession, memory::HybridMemory, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//! let memory = HybridMemory::new(openai.completion_model(openai::GPT_4O_MINI), 10)
+//!     .archive(openai.embedding_model(openai::TEXT_EMBEDDING_3_SMALL), 3);
+//!
+//! let mut session = ChatSession::new(openai.agent(openai::GPT_4O).build()).memory(memory);
+//! session.chat("My cat is called Tom.").await?;
+//! ```
+use std::sync::Mutex;
+
+use futures::future::BoxFuture;
+
+use super::{fingerprint, is_tool_result, transcript, Memory, MemoryError};
+use crate::{
+    completion::{CompletionError, CompletionModel, Message},
+    embeddings::{distance::VectorDistance, Embedding, EmbeddingError, EmbeddingModel},
+    message::AssistantContent,
+};
+
+/// Preamble of the model when it summarizes the conversation.
+const SUMMARIZER_PREAMBLE: &str = "You summarize conversations. Keep every name, number, date, \
+    identifier, preference and decision; drop the pleasantries and repetitions. Answer with the \
+    summary only.";
+
+type SummarizeFn =
+    Box<dyn Fn(String) -> BoxFuture<'static, Result<String, CompletionError>> + Send + Sync>;
+
+type EmbedFn = Box<
+    dyn Fn(Vec<String>) -> BoxFuture<'static, Result<Vec<Embedding>, EmbeddingError>> + Send + Sync,
+>;
+
+/// Summary of the first messages of a conversation
+struct Summary {
+    /// Fingerprint of the summarized messages
+    fingerprint: u64,
+    /// Number of the summarized messages
+    len: usize,
+    text: String,
+}
+
+/// Message archived with its embedding
+struct Archived {
+    /// Position of the message in the conversation
+    index: usize,
+    /// Fingerprint of the message
+    fingerprint: u64,
+    text: String,
+    embedding: Embedding,
+}
+
+/// Archive of the messages out of the window
+struct Archive {
+    embed: EmbedFn,
+    /// Number of the archived messages recalled with each prompt
+    sample: usize,
+}
+
+/// Sliding window + summary memory strategy (see the [module](self) docs).
+pub struct HybridMemory {
+    /// Number of the most recent messages kept verbatim
+    window: usize,
+    summarize: SummarizeFn,
+    archive: Option<Archive>,
+    /// Summaries of the branches of the conversation (the most recent first)
+    summaries: Mutex<Vec<Summary>>,
+    archived: Mutex<Vec<Archived>>,
+}
+
+/// Maximum number of summaries kept (i.e.: of branches of a conversation summarized)
+const MAX_SUMMARIES: usize = 8;
+
+impl HybridMemory {
+    /// Keep the `window` most recent messages verbatim, and summarize the older ones with
+    /// `model`
+    pub fn new(model: impl CompletionModel + 'static, window: usize) -> Self {
+        Self {
+            window,
+            summarize: Box::new(move |prompt| {
+                let model = model.clone();
+                Box::pin(async move {
+                    let response = model
+                        .completion_request(Message::user(prompt))
+                        .preamble(SUMMARIZER_PREAMBLE.to_string())
+                        .send()
+                        .await?;
+                    match response.choice.first() {
+                        AssistantContent::Text(text) => Ok(text.text),
+                        AssistantContent::ToolCall(_) => Err(CompletionError::ResponseError(
+                            "The summarizer answered with a tool call".to_string(),
+                        )),
+                    }
+                })
+            }),
+            archive: None,
+            summaries: Default::default(),
+            archived: Default::default(),
+        }
+    }
+
+    /// Archive the messages out of the window with their embeddings by `embedding_model`, and
+    /// recall the `sample` archived messages most similar to each prompt
+    pub fn archive(
+        mut self,
+        embedding_model: impl EmbeddingModel + 'static,
+        sample: usize,
+    ) -> Self {
+        self.archive = Some(Archive {
+            embed: Box::new(move |texts| {
+                let embedding_model = embedding_model.clone();
+                Box::pin(async move { embedding_model.embed_texts(texts).await })
+            }),
+            sample,
+        });
+        self
+    }
+
+    /// Summary of `messages`, updating the summary of their longest summarized prefix
+    async fn summary(&self, messages: &[Message]) -> Result<String, MemoryError> {
+        let previous = self
+            .summaries
+            .lock()
+            .expect("Memory lock poisoned")
+            .iter()
+            .filter(|summary| {
+                summary.len <= messages.len()
+                    && summary.fingerprint == fingerprint(&messages[..summary.len])
+            })
+            .max_by_key(|summary| summary.len)
+            .map(|summary| (summary.len, summary.text.clone()));
+
+        let text = match previous {
+            Some((len, text)) if len == messages.len() => return Ok(text),
+            Some((len, text)) => {
+                (self.summarize)(format!(
+                    "Update the summary of this conversation with its new messages.\n\
+                     Summary:\n{text}\n\nNew messages:\n{}",
+                    transcript(&messages[len..])
+                ))
+                .await?
+            }
+            None => {
+                (self.summarize)(format!(
+                    "Summarize this conversation:\n{}",
+                    transcript(messages)
+                ))
+                .await?
+            }
+        };
+
+        let mut summaries = self.summaries.lock().expect("Memory lock poisoned");
+        summaries.insert(
+            0,
+            Summary {
+                fingerprint: fingerprint(messages),
+                len: messages.len(),
+                text: text.clone(),
+            },
+        );
+        summaries.truncate(MAX_SUMMARIES);
+        Ok(text)
+    }
+
+    /// Archive `messages` (the messages out of the window), and return the texts of the `sample`
+    /// archived messages most similar to `prompt`, in the order of the conversation
+    async fn recall_archived(
+        &self,
+        archive: &Archive,
+        prompt: &Message,
+        messages: &[Message],
+    ) -> Result<Vec<String>, MemoryError> {
+        let fingerprints = messages
+            .iter()
+            .map(|message| fingerprint(std::slice::from_ref(message)))
+            .collect::<Vec<_>>();
+        let is_archived =
+            |archived: &Archived| fingerprints.get(archived.index) == Some(&archived.fingerprint);
+
+        let missing = {
+            let archived = self.archived.lock().expect("Memory lock poisoned");
+            (0..messages.len())
+                .filter(|index| {
+                    !archived
+                        .iter()
+                        .any(|archived| archived.index == *index && is_archived(archived))
+                })
+                .collect::<Vec<_>>()
+        };
+        let Some(prompt) = prompt.rag_text() else {
+            return Ok(vec![]);
+        };
+
+        // Embed the prompt along with the messages not archived yet
+        let texts = missing
+            .iter()
+            .map(|index| transcript(std::slice::from_ref(&messages[*index])))
+            .collect::<Vec<_>>();
+        let mut embeddings = (archive.embed)([vec![prompt], texts.clone()].concat()).await?;
+        if embeddings.len() != texts.len() + 1 {
+            return Err(
+                EmbeddingError::ResponseError("Missing embeddings of the messages".into()).into(),
+            );
+        }
+        let prompt_embedding = embeddings.remove(0);
+
+        let mut archived = self.archived.lock().expect("Memory lock poisoned");
+        archived.extend(missing.into_iter().zip(texts).zip(embeddings).map(
+            |((index, text), embedding)| Archived {
+                index,
+                fingerprint: fingerprints[index],
+                text,
+                embedding,
+            },
+        ));
+
+        let mut recalled = archived
+            .iter()
+            .filter(|archived| is_archived(archived))
+            .map(|archived| {
+                let similarity = prompt_embedding.cosine_similarity(&archived.embedding, false);
+                (similarity, archived.index, archived.text.clone())
+            })
+            .collect::<Vec<_>>();
+        recalled.sort_by(|(a, ..), (b, ..)| b.total_cmp(a));
+        recalled.truncate(archive.sample);
+        recalled.sort_by_key(|(_, index, _)| *index);
+        Ok(recalled.into_iter().map(|(_, _, text)| text).collect())
+    }
+}
+
+impl Memory for HybridMemory {
+    fn recall<'a>(
+        &'a self,
+        prompt: &'a Message,
+        mut history: Vec<Message>,
+    ) -> BoxFuture<'a, Result<Vec<Message>, MemoryError>> {
+        Box::pin(async move {
+            let mut split = history.len().saturating_sub(self.window);
+            // Don't leave tool results without their tool call
+            while split < history.len() && is_tool_result(&history[split]) {
+                split += 1;
+            }
+            if split == 0 {
+                return Ok(history);
+            }
+
+            let mut context = format!(
+                "Summary of the earlier conversation: {}",
+                self.summary(&history[..split]).await?
+            );
+            if let Some(archive) = &self.archive {
+                let recalled = self
+                    .recall_archived(archive, prompt, &history[..split])
+                    .await?;
+                if !recalled.is_empty() {
+                    context.push_str("\n\nRelevant earlier messages:\n");
+                    context.push_str(&recalled.join("\n"));
+                }
+            }
+
+            history.splice(..split, [Message::user(context)]);
+            Ok(history)
+        })
+    }
+}
+
+impl std::fmt::Debug for HybridMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HybridMemory")
+            .field("window", &self.window)
+            .field(
+                "archive_sample",
+                &self.archive.as_ref().map(|archive| archive.sample),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chat_session::ChatSession,
+        providers::mock::{self, MockResponse},
+    };
+
+    #[tokio::test]
+    async fn test_hybrid_memory() {
+        let summarizer = mock::CompletionModel::new()
+            .push(MockResponse::text("The user has a cat."))
+            .push(MockResponse::text("The user has a cat and lives in Paris."));
+        let embedding_model = mock::EmbeddingModel::new(2)
+            .embedding("What is my cat's name?", vec![1.0, 0.0])
+            .embedding("User: My cat is called Tom.", vec![1.0, 0.0])
+            .embedding("Assistant: Nice!", vec![0.0, 1.0]);
+        let memory = HybridMemory::new(summarizer.clone(), 2).archive(embedding_model, 1);
+
+        let chatbot = mock::CompletionModel::new().default_response(MockResponse::text("Nice!"));
+        let mut session = ChatSession::new(chatbot.agent().build()).memory(memory);
+        session.chat("My cat is called Tom.").await.unwrap();
+        session.chat("I live in Paris.").await.unwrap();
+        session.chat("What is my cat's name?").await.unwrap();
+
+        // The first turn slid out of the window: it is summarized, and recalled from the archive
+        let request = &chatbot.requests()[2];
+        assert_eq!(request.chat_history.len(), 3);
+        assert_eq!(
+            request.chat_history[0],
+            Message::user(
+                "Summary of the earlier conversation: The user has a cat.\n\n\
+                 Relevant earlier messages:\nUser: My cat is called Tom."
+            )
+        );
+        assert_eq!(request.chat_history[1], Message::user("I live in Paris."));
+
+        // The summary is updated with the messages sliding out of the window only
+        session.chat("Thanks!").await.unwrap();
+        let update = summarizer.requests()[1].prompt_text().unwrap();
+        assert!(update.contains("The user has a cat.") && update.contains("I live in Paris."));
+        assert!(!update.contains("Tom"));
+        assert_eq!(session.len(), 8);
+    }
+}
@@ -0,0 +1,122 @@
+//! Memory strategies of the [chat sessions](crate::chat_session::ChatSession), i.e.: how the
+//! history of a conversation is turned into the context sent to the chatbot with each prompt.
+//!
+//! By default, a session sends its full history. With a [Memory] (see
+//! [ChatSession::memory](crate::chat_session::ChatSession::memory)), the session sends the
+//! context [recalled](Memory::recall) by the memory instead, and lets it
+//! [record](Memory::record) each turn of the conversation. The built-in strategies are:
+//! - [HybridMemory]: a sliding window of the most recent messages, kept verbatim, along with a
+//!   summary of the older messages and the archived messages most relevant to the prompt,
+//! - [EntityMemory]: a profile of the user (e.g.: their name, preferences and ongoing tasks),
+//!   extracted from their conversations and recalled in their later sessions.
+//!
+//! The memory strategies are shared by the branches of a session (see
+//! [ChatSession::fork](crate::chat_session::ChatSession::fork)): their state is keyed by the
+//! messages it was computed from, so that the branches don't see each other's turns.
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use futures::future::BoxFuture;
+
+use crate::{
+    completion::{CompletionError, Message},
+    embeddings::EmbeddingError,
+    extractor::ExtractionError,
+    message::{AssistantContent, UserContent},
+};
+
+mod entity;
+mod hybrid;
+
+pub use entity::{Entity, EntityMemory, UserProfile};
+pub use hybrid::HybridMemory;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MemoryError {
+    /// The model of the memory (e.g.: its summarizer) failed
+    #[error("CompletionError: {0}")]
+    CompletionError(#[from] CompletionError),
+
+    /// The embedding model of the memory failed
+    #[error("EmbeddingError: {0}")]
+    EmbeddingError(#[from] EmbeddingError),
+
+    /// The extractor of the memory failed
+    #[error("ExtractionError: {0}")]
+    ExtractionError(#[from] ExtractionError),
+}
+
+/// Memory strategy of a chat session (see the [module](self) docs).
+pub trait Memory: Send + Sync {
+    /// Context sent to the chatbot with `prompt` (as its chat history), from the `history` of
+    /// the conversation
+    fn recall<'a>(
+        &'a self,
+        prompt: &'a Message,
+        history: Vec<Message>,
+    ) -> BoxFuture<'a, Result<Vec<Message>, MemoryError>>;
+
+    /// Record the last turn of the conversation `history` (i.e.: its last prompt and answer),
+    /// once the chatbot answered
+    fn record<'a>(&'a self, history: &'a [Message]) -> BoxFuture<'a, Result<(), MemoryError>> {
+        let _ = history;
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Transcript of `messages` for the models of the memories, a line per message (e.g.:
+/// `User: Hello!`)
+pub(crate) fn transcript(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(|message| {
+            let (role, texts) = match message {
+                Message::User { content } => (
+                    "User",
+                    content
+                        .iter()
+                        .map(|content| match content {
+                            UserContent::Text(text) => text.text.clone(),
+                            content => serde_json::to_string(content).unwrap_or_default(),
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+                Message::Assistant { content } => (
+                    "Assistant",
+                    content
+                        .iter()
+                        .map(|content| match content {
+                            AssistantContent::Text(text) => text.text.clone(),
+                            content => serde_json::to_string(content).unwrap_or_default(),
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+            };
+            format!("{role}: {}", texts.join(" "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Fingerprint of `messages`, identifying them across the branches of a session
+pub(crate) fn fingerprint(messages: &[Message]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for message in messages {
+        serde_json::to_string(message)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Whether `message` holds tool results (which can't be separated from their tool call)
+pub(crate) fn is_tool_result(message: &Message) -> bool {
+    match message {
+        Message::User { content } => content
+            .iter()
+            .any(|content| matches!(content, UserContent::ToolResult(_))),
+        Message::Assistant { .. } => false,
+    }
+}
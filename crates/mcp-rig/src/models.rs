@@ -0,0 +1,145 @@
+//! Listing of the models available from a provider, e.g.: to check at startup that the
+//! configured model ids exist (instead of failing on the first request).
+//!
+//! The [ListModels] trait is implemented by the clients of the providers exposing the list of
+//! their models (e.g.: [crate::providers::openai::Client], [crate::providers::cohere::Client]).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{models::ListModels, providers::openai};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! for model in openai.list_models().await? {
+//!     println!("{}", model.id);
+//! }
+//!
+//! // Fails with `ModelListError::ModelNotFound` if the model doesn't exist
+//! openai.validate_model(openai::GPT_4O).await?;
+//! ```
+//!
+//! The context length of the well-known models is also available offline, from a built-in
+//! catalog (see [context_length]).
+use std::future::Future;
+
+use serde::{Deserialize, Serialize};
+
+use crate::http_client::HttpClientError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ModelListError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error returned by the provider
+    #[error("ProviderError: {0}")]
+    ProviderError(String),
+
+    /// The model is not available from the provider
+    #[error("ModelNotFound: {0}")]
+    ModelNotFound(String),
+}
+
+impl From<HttpClientError> for ModelListError {
+    fn from(error: HttpClientError) -> Self {
+        match error {
+            HttpClientError::HttpError(error) => ModelListError::HttpError(error),
+            error => ModelListError::ProviderError(error.to_string()),
+        }
+    }
+}
+
+/// Metadata of a model available from a provider. The fields other than `id` are only set if
+/// the provider returns them.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct ModelInfo {
+    /// Id of the model, as passed to the `completion_model` or `embedding_model` methods of
+    /// the provider client
+    pub id: String,
+    /// Owner of the model (e.g.: `openai`, or the organization of a fine-tuned model)
+    pub owned_by: Option<String>,
+    /// Creation time of the model (unix timestamp, in seconds)
+    pub created: Option<u64>,
+    /// Maximum number of tokens of the context of the model
+    pub context_length: Option<u64>,
+    /// Endpoints supporting the model (e.g.: `chat`, `embed`)
+    pub endpoints: Vec<String>,
+}
+
+/// Trait for the provider clients able to list their models.
+pub trait ListModels: Send + Sync {
+    /// List the models available from the provider.
+    fn list_models(&self) -> impl Future<Output = Result<Vec<ModelInfo>, ModelListError>> + Send;
+
+    /// Check that the model `model` is available from the provider and return its metadata.
+    fn validate_model(
+        &self,
+        model: &str,
+    ) -> impl Future<Output = Result<ModelInfo, ModelListError>> + Send {
+        async move {
+            self.list_models()
+                .await?
+                .into_iter()
+                .find(|info| info.id == model)
+                .ok_or_else(|| ModelListError::ModelNotFound(model.to_string()))
+        }
+    }
+}
+
+/// Context lengths (in tokens) of well-known models, by model name prefix.
+const CONTEXT_LENGTHS: &[(&str, u64)] = &[
+    ("gpt-4o", 128_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4.5", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+    ("claude-", 200_000),
+    ("gemini-1.5-pro", 2_097_152),
+    ("gemini-1.5-flash", 1_048_576),
+    ("gemini-2.0", 1_048_576),
+    ("gemini-2.5", 1_048_576),
+    ("command-r", 128_000),
+    ("command-a", 256_000),
+    ("deepseek-chat", 65_536),
+    ("deepseek-reasoner", 65_536),
+    ("grok-", 131_072),
+    ("mistral-large", 131_072),
+    ("moonshot-v1-8k", 8_192),
+    ("moonshot-v1-32k", 32_768),
+    ("moonshot-v1-128k", 131_072),
+    ("llama-3", 131_072),
+];
+
+/// Context length (in tokens) of the well-known model `model`, from the built-in catalog.
+///
+/// A model is found by the longest model name it starts with (e.g.: `gpt-4o-2024-08-06` has
+/// the context length of `gpt-4o`).
+pub fn context_length(model: &str) -> Option<u64> {
+    CONTEXT_LENGTHS
+        .iter()
+        .filter(|(name, _)| model.starts_with(name))
+        .max_by_key(|(name, _)| name.len())
+        .map(|(_, length)| *length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_length() {
+        assert_eq!(context_length("gpt-4o-mini"), Some(128_000));
+        assert_eq!(context_length("gpt-4-0613"), Some(8_192));
+        assert_eq!(context_length("claude-3-5-sonnet-latest"), Some(200_000));
+        assert_eq!(context_length("unknown-model"), None);
+    }
+}
@@ -0,0 +1,431 @@
+//! Collection of (prompt, context, response, feedback) records from live agents, to build
+//! fine-tuning or evaluation datasets.
+//!
+//! A [DatasetCollector] is a [TraceExporter]: attached to an agent with
+//! [AgentBuilder::trace_exporter](crate::agent::AgentBuilder::trace_exporter), it turns each
+//! successful prompt of the agent into a [DatasetRecord]. Records can also be added manually
+//! with [DatasetCollector::record]. Feedback (e.g.: a thumbs up from the user) can be attached
+//! to a record afterwards with [DatasetCollector::feedback].
+//!
+//! Only a fraction of the prompts can be collected with [DatasetCollector::sample_rate], and
+//! the records go through the redaction rules of the collector (e.g.:
+//! [RedactPii](crate::payload_log::RedactPii)) before being stored.
+//!
+//! The records are written in the JSONL format with [DatasetCollector::write_jsonl], or in the
+//! Parquet format with `DatasetCollector::write_parquet` (requires the `parquet` feature).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     completion::Prompt,
+//!     observability::dataset::{DatasetCollector, Feedback},
+//!     payload_log::RedactPii,
+//!     providers::openai,
+//! };
+//!
+//! // Collect 10% of the prompts, without the email addresses and phone numbers of the users
+//! let collector = DatasetCollector::new()
+//!     .sample_rate(0.1)
+//!     .rule(RedactPii::new());
+//!
+//! let agent = openai
+//!     .agent("gpt-4o")
+//!     .trace_exporter(collector.clone())
+//!     .build();
+//!
+//! let response = agent.prompt("What is a flurbo?").await?;
+//! agent.flush_traces().await;
+//! if let Some(id) = collector.last_record_id() {
+//!     collector.feedback(&id, Feedback::new().score(1.0).label("helpful"));
+//! }
+//!
+//! collector.write_jsonl(std::fs::File::create("dataset.jsonl")?)?;
+//! ```
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use super::{new_id, AgentTrace, TraceExportError, TraceExporter};
+use crate::{completion::Message, payload_log::RedactionRule};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DatasetError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[cfg(feature = "parquet")]
+    #[error("ParquetError: {0}")]
+    ParquetError(#[from] parquet::errors::ParquetError),
+
+    #[cfg(feature = "parquet")]
+    #[error("ArrowError: {0}")]
+    ArrowError(#[from] arrow_schema::ArrowError),
+}
+
+/// Feedback on the response of a record.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Feedback {
+    /// Score of the response (e.g.: 1 for a thumbs up, 0 for a thumbs down)
+    pub score: Option<f64>,
+    /// Label of the response (e.g.: "helpful", "hallucination")
+    pub label: Option<String>,
+    /// Free-form comment on the response
+    pub comment: Option<String>,
+}
+
+impl Feedback {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn score(mut self, score: f64) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.comment = Some(comment.to_string());
+        self
+    }
+}
+
+/// Record of a dataset: a prompt, its context, the response and the feedback on the response.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DatasetRecord {
+    /// ID of the record (ID of the agent trace, for the records collected from agents)
+    pub id: String,
+    /// Time the record was collected, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// System prompt of the agent, if any
+    pub preamble: Option<String>,
+    /// Previous messages of the conversation
+    pub context: Vec<Message>,
+    pub prompt: Message,
+    pub response: String,
+    pub feedback: Option<Feedback>,
+    /// Additional metadata (e.g.: name of the agent, session ID)
+    pub metadata: Map<String, Value>,
+}
+
+impl DatasetRecord {
+    pub fn new(prompt: impl Into<Message>, response: &str) -> Self {
+        Self {
+            id: new_id(),
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or_default(),
+            preamble: None,
+            context: vec![],
+            prompt: prompt.into(),
+            response: response.to_string(),
+            feedback: None,
+            metadata: Map::new(),
+        }
+    }
+
+    pub fn preamble(mut self, preamble: &str) -> Self {
+        self.preamble = Some(preamble.to_string());
+        self
+    }
+
+    pub fn context(mut self, context: Vec<Message>) -> Self {
+        self.context = context;
+        self
+    }
+
+    pub fn metadata(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.metadata.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Record of a successful agent trace, or `None` if the agent failed.
+    fn from_trace(trace: &AgentTrace) -> Option<Self> {
+        let response = trace.output.as_ref()?;
+        let context = &trace.context;
+
+        let mut record = Self::new(trace.prompt.clone(), response)
+            .context(trace.chat_history.clone())
+            .metadata("name", context.name.as_str());
+        record.id = trace.id.clone();
+        if !trace.preamble.is_empty() {
+            record = record.preamble(&trace.preamble);
+        }
+        if let Some(session_id) = &context.session_id {
+            record = record.metadata("session_id", session_id.as_str());
+        }
+        if let Some(user_id) = &context.user_id {
+            record = record.metadata("user_id", user_id.as_str());
+        }
+        if !context.tags.is_empty() {
+            record = record.metadata("tags", context.tags.clone());
+        }
+        record.metadata.extend(context.metadata.clone());
+
+        Some(record)
+    }
+}
+
+/// Collector of dataset records. Cloning a collector returns a handle to the same records.
+/// See the [module documentation](self).
+#[derive(Clone)]
+pub struct DatasetCollector {
+    records: Arc<Mutex<Vec<DatasetRecord>>>,
+    sample_rate: f64,
+    rules: Arc<Vec<Box<dyn RedactionRule>>>,
+}
+
+impl Default for DatasetCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DatasetCollector {
+    /// Create a collector keeping every record, without redaction.
+    pub fn new() -> Self {
+        Self {
+            records: Arc::new(Mutex::new(vec![])),
+            sample_rate: 1.0,
+            rules: Arc::new(vec![]),
+        }
+    }
+
+    /// Set the fraction (between 0 and 1) of the records to keep.
+    pub fn sample_rate(mut self, sample_rate: f64) -> Self {
+        self.sample_rate = sample_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Add a redaction rule, applied to the records (as JSON) before they are stored.
+    /// Rules must be added before the collector is attached to an agent.
+    pub fn rule(mut self, rule: impl RedactionRule + 'static) -> Self {
+        Arc::get_mut(&mut self.rules)
+            .expect("Redaction rules should be added before the collector is shared")
+            .push(Box::new(rule));
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<DatasetRecord>> {
+        self.records.lock().expect("Dataset lock poisoned")
+    }
+
+    /// Add a record to the dataset (if it is sampled). Returns the ID of the record if it was kept.
+    pub fn record(&self, record: DatasetRecord) -> Option<String> {
+        if !sampled(self.sample_rate) {
+            return None;
+        }
+
+        let id = record.id.clone();
+        let mut json = serde_json::to_value(&record).ok()?;
+        self.rules.iter().for_each(|rule| rule.redact(&mut json));
+        // A rule may have made the record invalid (e.g.: redacted a message object)
+        let mut record = match serde_json::from_value::<DatasetRecord>(json) {
+            Ok(record) => record,
+            Err(e) => {
+                tracing::warn!(target: "rig", "Dropping dataset record {}: {}", id, e);
+                return None;
+            }
+        };
+        // The ID is kept as is, so that feedback can be attached to the record
+        record.id = id.clone();
+
+        self.lock().push(record);
+        Some(id)
+    }
+
+    /// Attach `feedback` to the record with the given ID.
+    /// Returns false if there is no such record (e.g.: it was not sampled).
+    pub fn feedback(&self, id: &str, feedback: Feedback) -> bool {
+        match self.lock().iter_mut().find(|record| record.id == id) {
+            Some(record) => {
+                record.feedback = Some(feedback);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// ID of the last record added to the dataset, if any.
+    pub fn last_record_id(&self) -> Option<String> {
+        self.lock().last().map(|record| record.id.clone())
+    }
+
+    /// Records collected so far.
+    pub fn records(&self) -> Vec<DatasetRecord> {
+        self.lock().clone()
+    }
+
+    /// Remove and return the records collected so far (e.g.: after writing them).
+    pub fn drain(&self) -> Vec<DatasetRecord> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    /// Write the records to `writer`, one JSON object per line.
+    pub fn write_jsonl(&self, mut writer: impl Write) -> Result<(), DatasetError> {
+        for record in self.records() {
+            serde_json::to_writer(&mut writer, &record)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the records to a Parquet file at `path`. The messages, the feedback and the
+    /// metadata are stored as JSON strings.
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet(&self, path: impl AsRef<std::path::Path>) -> Result<(), DatasetError> {
+        use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray, UInt64Array};
+        use arrow_schema::{DataType, Field, Schema};
+        use parquet::arrow::ArrowWriter;
+
+        let records = self.records();
+        let strings = |value: fn(&DatasetRecord) -> Option<String>| -> ArrayRef {
+            Arc::new(records.iter().map(value).collect::<StringArray>())
+        };
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Utf8, false),
+            Field::new("timestamp_ms", DataType::UInt64, false),
+            Field::new("preamble", DataType::Utf8, true),
+            Field::new("context", DataType::Utf8, false),
+            Field::new("prompt", DataType::Utf8, false),
+            Field::new("response", DataType::Utf8, false),
+            Field::new("feedback_score", DataType::Float64, true),
+            Field::new("feedback_label", DataType::Utf8, true),
+            Field::new("feedback_comment", DataType::Utf8, true),
+            Field::new("metadata", DataType::Utf8, false),
+        ]));
+
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                strings(|record| Some(record.id.clone())),
+                Arc::new(UInt64Array::from_iter_values(
+                    records.iter().map(|record| record.timestamp_ms),
+                )),
+                strings(|record| record.preamble.clone()),
+                strings(|record| serde_json::to_string(&record.context).ok()),
+                strings(|record| serde_json::to_string(&record.prompt).ok()),
+                strings(|record| Some(record.response.clone())),
+                Arc::new(
+                    records
+                        .iter()
+                        .map(|record| record.feedback.as_ref().and_then(|f| f.score))
+                        .collect::<Float64Array>(),
+                ),
+                strings(|record| record.feedback.as_ref().and_then(|f| f.label.clone())),
+                strings(|record| record.feedback.as_ref().and_then(|f| f.comment.clone())),
+                strings(|record| serde_json::to_string(&record.metadata).ok()),
+            ],
+        )?;
+
+        let mut writer = ArrowWriter::try_new(std::fs::File::create(path)?, schema, None)?;
+        writer.write(&batch)?;
+        writer.close()?;
+        Ok(())
+    }
+}
+
+/// Whether a record should be kept, given the sample rate.
+fn sampled(sample_rate: f64) -> bool {
+    if sample_rate >= 1.0 {
+        return true;
+    }
+    // `RandomState` is seeded with random keys
+    let random = RandomState::new().build_hasher().finish();
+    (random as f64 / u64::MAX as f64) < sample_rate
+}
+
+impl TraceExporter for DatasetCollector {
+    async fn export(&self, trace: &AgentTrace) -> Result<(), TraceExportError> {
+        if let Some(record) = DatasetRecord::from_trace(trace) {
+            self.record(record);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::Prompt,
+        observability::TraceContext,
+        payload_log::{RedactPii, REDACTED},
+        providers::mock::{self, MockResponse},
+    };
+
+    #[tokio::test]
+    async fn test_dataset_collector() {
+        let collector = DatasetCollector::new().rule(RedactPii::new());
+
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::text("Hi!"))
+            .push(MockResponse::error("Rate limited"));
+        let agent = model
+            .agent()
+            .preamble("Be nice")
+            .trace_exporter(collector.clone())
+            .trace_context(TraceContext::new("greeter").session_id("session-1"))
+            .build();
+
+        agent.prompt("Hello, I am jane@example.com").await.unwrap();
+        // Failed prompts are not collected
+        assert!(agent.prompt("Hello").await.is_err());
+        agent.flush_traces().await;
+
+        let records = collector.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].preamble.as_deref(), Some("Be nice"));
+        assert_eq!(records[0].response, "Hi!");
+        assert_eq!(records[0].metadata["name"], "greeter");
+        assert_eq!(records[0].metadata["session_id"], "session-1");
+        assert_eq!(
+            records[0].prompt,
+            Message::user(format!("Hello, I am {REDACTED}"))
+        );
+
+        let id = collector.last_record_id().unwrap();
+        assert!(collector.feedback(&id, Feedback::new().score(1.0).label("helpful")));
+        assert!(!collector.feedback("unknown", Feedback::new().score(0.0)));
+
+        let mut output = vec![];
+        collector.write_jsonl(&mut output).unwrap();
+        let lines = String::from_utf8(output).unwrap();
+        let record: Value = serde_json::from_str(lines.lines().next().unwrap()).unwrap();
+        assert_eq!(record["id"], id.as_str());
+        assert_eq!(record["feedback"]["label"], "helpful");
+
+        assert_eq!(collector.drain().len(), 1);
+        assert!(collector.records().is_empty());
+    }
+
+    #[test]
+    fn test_sample_rate() {
+        let collector = DatasetCollector::new().sample_rate(0.0);
+        assert_eq!(collector.record(DatasetRecord::new("Hello", "Hi!")), None);
+
+        let collector = DatasetCollector::new().sample_rate(0.5);
+        (0..1000).for_each(|_| {
+            collector.record(DatasetRecord::new("Hello", "Hi!"));
+        });
+        let kept = collector.records().len();
+        assert!(kept > 350 && kept < 650, "{kept} records kept");
+    }
+}
@@ -0,0 +1,206 @@
+//! Langfuse trace exporter. Each [AgentTrace] is exported as a Langfuse trace containing a
+//! generation (the completion request) and one span per tool call.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::observability::langfuse::Langfuse;
+//!
+//! // Self-hosted instance
+//! let langfuse = Langfuse::from_url("pk-lf-...", "sk-lf-...", "https://langfuse.example.com");
+//!
+//! let agent = openai.agent("gpt-4o").trace_exporter(langfuse).build();
+//! ```
+
+use serde_json::{json, Value};
+use web_time::SystemTime;
+
+use super::{new_id, rfc3339, AgentTrace, TraceExportError, TraceExporter};
+
+/// URL of Langfuse Cloud (EU region)
+pub const LANGFUSE_CLOUD_URL: &str = "https://cloud.langfuse.com";
+
+/// Exporter of agent traces to Langfuse, via its ingestion API.
+#[derive(Clone)]
+pub struct Langfuse {
+    base_url: String,
+    public_key: String,
+    secret_key: String,
+    http_client: reqwest::Client,
+}
+
+impl Langfuse {
+    /// Create a Langfuse Cloud exporter with the given project keys.
+    pub fn new(public_key: &str, secret_key: &str) -> Self {
+        Self::from_url(public_key, secret_key, LANGFUSE_CLOUD_URL)
+    }
+
+    /// Create an exporter to the Langfuse instance at `base_url`.
+    pub fn from_url(public_key: &str, secret_key: &str, base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            public_key: public_key.to_string(),
+            secret_key: secret_key.to_string(),
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    /// Create an exporter from the `LANGFUSE_PUBLIC_KEY`, `LANGFUSE_SECRET_KEY` and
+    /// (optional) `LANGFUSE_HOST` environment variables.
+    /// Panics if the keys are not set.
+    pub fn from_env() -> Self {
+        let public_key = std::env::var("LANGFUSE_PUBLIC_KEY").expect("LANGFUSE_PUBLIC_KEY not set");
+        let secret_key = std::env::var("LANGFUSE_SECRET_KEY").expect("LANGFUSE_SECRET_KEY not set");
+        let base_url =
+            std::env::var("LANGFUSE_HOST").unwrap_or_else(|_| LANGFUSE_CLOUD_URL.to_string());
+        Self::from_url(&public_key, &secret_key, &base_url)
+    }
+}
+
+/// Ingestion event of type `event_type`.
+fn event(event_type: &str, body: Value) -> Value {
+    json!({
+        "id": new_id(),
+        "timestamp": rfc3339(SystemTime::now()),
+        "type": event_type,
+        "body": body,
+    })
+}
+
+/// Ingestion events of a trace: the trace itself, its generation and its tool calls.
+pub(crate) fn events(trace: &AgentTrace) -> Vec<Value> {
+    let context = &trace.context;
+    let mut metadata = context.metadata.clone();
+    if let Some(tenant_id) = &context.tenant_id {
+        metadata.insert("tenant_id".into(), tenant_id.clone().into());
+    }
+    let mut events = vec![event(
+        "trace-create",
+        json!({
+            "id": trace.id,
+            "timestamp": rfc3339(trace.start_time),
+            "name": context.name,
+            "sessionId": context.session_id,
+            "userId": context.user_id,
+            "tags": context.tags,
+            "metadata": metadata,
+            "input": trace.messages(),
+            "output": trace.output.as_ref().or(trace.error.as_ref()),
+        }),
+    )];
+
+    if let Some(generation) = &trace.generation {
+        events.push(event(
+            "generation-create",
+            json!({
+                "id": generation.id,
+                "traceId": trace.id,
+                "name": "completion",
+                "startTime": rfc3339(generation.start_time),
+                "endTime": rfc3339(generation.end_time),
+                "input": trace.messages(),
+                "output": generation.response,
+            }),
+        ));
+    }
+
+    events.extend(trace.tool_calls.iter().map(|tool_call| {
+        event(
+            "span-create",
+            json!({
+                "id": tool_call.id,
+                "traceId": trace.id,
+                "parentObservationId": trace.generation.as_ref().map(|generation| &generation.id),
+                "name": tool_call.name,
+                "startTime": rfc3339(tool_call.start_time),
+                "endTime": rfc3339(tool_call.end_time),
+                "input": tool_call.arguments,
+                "output": tool_call.output,
+                "level": if tool_call.error.is_some() { "ERROR" } else { "DEFAULT" },
+                "statusMessage": tool_call.error,
+            }),
+        )
+    }));
+
+    events
+}
+
+impl TraceExporter for Langfuse {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn export(&self, trace: &AgentTrace) -> Result<(), TraceExportError> {
+        self.export_batch(std::slice::from_ref(trace)).await
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn export_batch(&self, traces: &[AgentTrace]) -> Result<(), TraceExportError> {
+        let events = traces.iter().flat_map(events).collect::<Vec<_>>();
+        let response = self
+            .http_client
+            .post(format!("{}/api/public/ingestion", self.base_url))
+            .basic_auth(&self.public_key, Some(&self.secret_key))
+            .json(&json!({ "batch": events }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(TraceExportError::ExporterError(response.text().await?));
+        }
+
+        // The ingestion API reports the events it rejected in the `errors` field
+        let body = response.json::<Value>().await?;
+        match body.get("errors").and_then(Value::as_array) {
+            Some(errors) if !errors.is_empty() => Err(TraceExportError::ExporterError(
+                serde_json::to_string(errors).unwrap_or_default(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::{AssistantContent, Message},
+        observability::TraceContext,
+        tool::ToolSetError,
+    };
+
+    #[test]
+    fn test_events() {
+        let mut trace = AgentTrace::new(
+            &TraceContext::new("calculator").user_id("user-1"),
+            "You are a calculator",
+            &Message::user("What is 1 + 2?"),
+            &[],
+        );
+        let tool_call = AssistantContent::tool_call("call_0", "add", json!({"x": 1, "y": 2}));
+        trace.record_generation(tool_call.clone(), SystemTime::now());
+        if let AssistantContent::ToolCall(tool_call) = &tool_call {
+            trace.record_tool_call(
+                tool_call,
+                &Err(ToolSetError::ToolNotFoundError("add".to_string())),
+                SystemTime::now(),
+            );
+        }
+        trace.finish::<String>(&Ok("3".to_string()));
+
+        let events = events(&trace);
+        let types = events
+            .iter()
+            .map(|event| event["type"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(types, ["trace-create", "generation-create", "span-create"]);
+
+        assert_eq!(events[0]["body"]["name"], "calculator");
+        assert_eq!(events[0]["body"]["userId"], "user-1");
+        assert_eq!(events[0]["body"]["sessionId"], Value::Null);
+        assert_eq!(events[0]["body"]["output"], "3");
+        assert_eq!(events[1]["body"]["traceId"], trace.id);
+        assert_eq!(events[2]["body"]["name"], "add");
+        assert_eq!(events[2]["body"]["level"], "ERROR");
+        assert_eq!(
+            events[2]["body"]["parentObservationId"],
+            events[1]["body"]["id"]
+        );
+    }
+}
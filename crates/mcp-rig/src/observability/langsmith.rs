@@ -0,0 +1,234 @@
+//! LangSmith trace exporter. Each [AgentTrace] is exported as a `chain` run containing an `llm`
+//! run (the completion request) and one `tool` run per tool call. The session and user IDs
+//! are set in the metadata of the runs, so that the traces of a session are grouped
+//! in a LangSmith thread.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::observability::langsmith::LangSmith;
+//!
+//! let langsmith = LangSmith::new("lsv2_pt_...").project("support-agent");
+//!
+//! let agent = openai.agent("gpt-4o").trace_exporter(langsmith).build();
+//! ```
+
+use serde_json::{json, Value};
+use web_time::SystemTime;
+
+use super::{compact_timestamp, rfc3339, AgentTrace, TraceExportError, TraceExporter};
+
+/// URL of the LangSmith API
+pub const LANGSMITH_API_URL: &str = "https://api.smith.langchain.com";
+
+/// Exporter of agent traces to LangSmith, via its runs API.
+#[derive(Clone)]
+pub struct LangSmith {
+    base_url: String,
+    api_key: String,
+    project: String,
+    http_client: reqwest::Client,
+}
+
+impl LangSmith {
+    /// Create a LangSmith exporter with the given API key. The traces are exported
+    /// to the `default` project.
+    pub fn new(api_key: &str) -> Self {
+        Self::from_url(api_key, LANGSMITH_API_URL)
+    }
+
+    /// Create an exporter to the LangSmith API at `base_url` (e.g.: self-hosted instance).
+    pub fn from_url(api_key: &str, base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            project: "default".to_string(),
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    /// Create an exporter from the `LANGSMITH_API_KEY`, (optional) `LANGSMITH_ENDPOINT` and
+    /// (optional) `LANGSMITH_PROJECT` environment variables.
+    /// Panics if the API key is not set.
+    pub fn from_env() -> Self {
+        let api_key = std::env::var("LANGSMITH_API_KEY").expect("LANGSMITH_API_KEY not set");
+        let base_url =
+            std::env::var("LANGSMITH_ENDPOINT").unwrap_or_else(|_| LANGSMITH_API_URL.to_string());
+        let exporter = Self::from_url(&api_key, &base_url);
+
+        match std::env::var("LANGSMITH_PROJECT") {
+            Ok(project) => exporter.project(&project),
+            Err(_) => exporter,
+        }
+    }
+
+    /// Set the project the traces are exported to.
+    pub fn project(mut self, project: &str) -> Self {
+        self.project = project.to_string();
+        self
+    }
+
+    /// Runs of a trace: the root `chain` run, its `llm` run and its `tool` runs.
+    pub(crate) fn runs(&self, trace: &AgentTrace) -> Vec<Value> {
+        let context = &trace.context;
+
+        let mut metadata = context.metadata.clone();
+        if let Some(session_id) = &context.session_id {
+            metadata.insert("session_id".into(), session_id.clone().into());
+        }
+        if let Some(user_id) = &context.user_id {
+            metadata.insert("user_id".into(), user_id.clone().into());
+        }
+        if let Some(tenant_id) = &context.tenant_id {
+            metadata.insert("tenant_id".into(), tenant_id.clone().into());
+        }
+
+        // Runs are ordered in a trace by their "dotted order": the start time and ID of the
+        // run, prefixed by the dotted order of its parent
+        let root_order = dotted_order(trace.start_time, &trace.id);
+        let run = |id: &str,
+                   parent: Option<&str>,
+                   name: &str,
+                   run_type: &str,
+                   start_time: SystemTime,
+                   end_time: SystemTime| {
+            json!({
+                "id": id,
+                "trace_id": trace.id,
+                "parent_run_id": parent,
+                "dotted_order": match parent {
+                    Some(_) => format!("{root_order}.{}", dotted_order(start_time, id)),
+                    None => root_order.clone(),
+                },
+                "name": name,
+                "run_type": run_type,
+                "start_time": rfc3339(start_time),
+                "end_time": rfc3339(end_time),
+                "session_name": self.project,
+                "tags": context.tags,
+                "extra": { "metadata": metadata },
+            })
+        };
+
+        let mut root = run(
+            &trace.id,
+            None,
+            &context.name,
+            "chain",
+            trace.start_time,
+            trace.end_time,
+        );
+        root["inputs"] = json!({ "messages": trace.messages() });
+        root["outputs"] = json!({ "output": trace.output });
+        root["error"] = json!(trace.error);
+
+        let mut runs = vec![root];
+
+        if let Some(generation) = &trace.generation {
+            let mut llm = run(
+                &generation.id,
+                Some(&trace.id),
+                "completion",
+                "llm",
+                generation.start_time,
+                generation.end_time,
+            );
+            llm["inputs"] = json!({ "messages": trace.messages() });
+            llm["outputs"] = json!({ "response": generation.response });
+            runs.push(llm);
+        }
+
+        runs.extend(trace.tool_calls.iter().map(|tool_call| {
+            let mut tool = run(
+                &tool_call.id,
+                Some(&trace.id),
+                &tool_call.name,
+                "tool",
+                tool_call.start_time,
+                tool_call.end_time,
+            );
+            tool["inputs"] = json!({ "arguments": tool_call.arguments });
+            tool["outputs"] = json!({ "output": tool_call.output });
+            tool["error"] = json!(tool_call.error);
+            tool
+        }));
+
+        runs
+    }
+}
+
+fn dotted_order(start_time: SystemTime, id: &str) -> String {
+    format!("{}{id}", compact_timestamp(start_time))
+}
+
+impl TraceExporter for LangSmith {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn export(&self, trace: &AgentTrace) -> Result<(), TraceExportError> {
+        self.export_batch(std::slice::from_ref(trace)).await
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn export_batch(&self, traces: &[AgentTrace]) -> Result<(), TraceExportError> {
+        let runs = traces
+            .iter()
+            .flat_map(|trace| self.runs(trace))
+            .collect::<Vec<_>>();
+        let response = self
+            .http_client
+            .post(format!("{}/runs/batch", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .json(&json!({ "post": runs }))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(TraceExportError::ExporterError(response.text().await?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::{AssistantContent, Message},
+        observability::TraceContext,
+    };
+
+    #[test]
+    fn test_runs() {
+        let mut trace = AgentTrace::new(
+            &TraceContext::new("assistant")
+                .session_id("session-1")
+                .tag("prod"),
+            "",
+            &Message::user("Hello"),
+            &[],
+        );
+        trace.record_generation(AssistantContent::text("Hi!"), SystemTime::now());
+        trace.finish::<String>(&Ok("Hi!".to_string()));
+
+        let runs = LangSmith::new("key").project("my-project").runs(&trace);
+        assert_eq!(runs.len(), 2);
+
+        let (root, llm) = (&runs[0], &runs[1]);
+        assert_eq!(root["run_type"], "chain");
+        assert_eq!(root["name"], "assistant");
+        assert_eq!(root["parent_run_id"], Value::Null);
+        assert_eq!(root["session_name"], "my-project");
+        assert_eq!(root["tags"], json!(["prod"]));
+        assert_eq!(root["extra"]["metadata"]["session_id"], "session-1");
+        assert_eq!(root["outputs"]["output"], "Hi!");
+        assert_eq!(root["inputs"]["messages"].as_array().unwrap().len(), 1);
+
+        assert_eq!(llm["run_type"], "llm");
+        assert_eq!(llm["parent_run_id"], root["id"]);
+        assert_eq!(llm["trace_id"], root["id"]);
+
+        let root_order = root["dotted_order"].as_str().unwrap();
+        let llm_order = llm["dotted_order"].as_str().unwrap();
+        assert!(root_order.ends_with(trace.id.as_str()));
+        assert!(llm_order.starts_with(&format!("{root_order}.")));
+    }
+}
@@ -0,0 +1,668 @@
+//! This module provides the [TraceExporter] trait and the exporters that ship the traces of
+//! agents (prompt, completion, tool calls) to LLM observability platforms, so that they can be
+//! inspected and collected into evaluation datasets:
+//! - [langfuse::Langfuse]: exports to [Langfuse](https://langfuse.com) via its ingestion API,
+//! - [langsmith::LangSmith]: exports to [LangSmith](https://smith.langchain.com) via its runs API,
+//! - [dataset::DatasetCollector]: collects the prompts and responses into a dataset (JSONL or
+//!   Parquet), e.g.: for fine-tuning.
+//!
+//! An exporter is attached to an agent with [AgentBuilder::trace_exporter], along with a
+//! [TraceContext] (trace name, session and user IDs, tags) set with [AgentBuilder::trace_context].
+//! Each prompt of the agent then produces an [AgentTrace], which is queued once the agent has
+//! answered. The queued traces are exported in batches (see [TraceExporter::export_batch]) by a
+//! background task, so exports never delay the responses of the agent. The queue is bounded
+//! (see [AgentBuilder::trace_queue_capacity]): when it is full, the traces are dropped. Export
+//! failures and dropped traces are logged and do not affect the response of the agent. The
+//! queued traces can be awaited with [Agent::flush_traces] (e.g.: before the application exits).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     completion::Prompt,
+//!     observability::{langfuse::Langfuse, TraceContext},
+//!     providers::openai,
+//! };
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let agent = openai
+//!     .agent("gpt-4o")
+//!     .preamble("You are a helpful assistant.")
+//!     // Requires the `LANGFUSE_PUBLIC_KEY` and `LANGFUSE_SECRET_KEY` environment variables
+//!     .trace_exporter(Langfuse::from_env())
+//!     .trace_context(
+//!         TraceContext::new("support-agent")
+//!             .session_id("conversation-42")
+//!             .user_id("user-7"),
+//!     )
+//!     .build();
+//!
+//! let response = agent.prompt("What is a flurbo?").await?;
+//! ```
+//!
+//! [AgentBuilder::trace_exporter]: crate::agent::AgentBuilder::trace_exporter
+//! [AgentBuilder::trace_context]: crate::agent::AgentBuilder::trace_context
+//! [AgentBuilder::trace_queue_capacity]: crate::agent::AgentBuilder::trace_queue_capacity
+//! [Agent::flush_traces]: crate::agent::Agent::flush_traces
+use std::{
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    future::BoxFuture,
+    SinkExt, StreamExt,
+};
+use serde_json::{json, Value};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    completion::{AssistantContent, Message},
+    message::ToolCall,
+    tool::ToolSetError,
+};
+
+pub mod dataset;
+pub mod langfuse;
+pub mod langsmith;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TraceExportError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Error returned by the observability platform
+    #[error("ExporterError: {0}")]
+    ExporterError(String),
+}
+
+/// Default maximum number of traces of an agent waiting to be exported.
+pub const DEFAULT_TRACE_QUEUE_CAPACITY: usize = 1024;
+
+/// Maximum number of traces exported in one batch.
+pub const MAX_TRACE_BATCH: usize = 64;
+
+/// Trait for exporters of agent traces.
+pub trait TraceExporter: Send + Sync {
+    /// Export a trace to the observability platform.
+    fn export(
+        &self,
+        trace: &AgentTrace,
+    ) -> impl Future<Output = Result<(), TraceExportError>> + Send;
+
+    /// Export a batch of traces to the observability platform. By default, the traces are
+    /// exported one by one: exporters able to send several traces at once override this method.
+    fn export_batch(
+        &self,
+        traces: &[AgentTrace],
+    ) -> impl Future<Output = Result<(), TraceExportError>> + Send {
+        async move {
+            for trace in traces {
+                self.export(trace).await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Wrapper trait to allow for dynamic dispatch of trace exporters
+pub(crate) trait TraceExporterDyn: Send + Sync {
+    fn export_batch_boxed<'a>(
+        &'a self,
+        traces: &'a [AgentTrace],
+    ) -> BoxFuture<'a, Result<(), TraceExportError>>;
+}
+
+impl<T: TraceExporter> TraceExporterDyn for T {
+    fn export_batch_boxed<'a>(
+        &'a self,
+        traces: &'a [AgentTrace],
+    ) -> BoxFuture<'a, Result<(), TraceExportError>> {
+        Box::pin(self.export_batch(traces))
+    }
+}
+
+enum Queued {
+    Trace(AgentTrace),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Bounded queue of the traces of an agent, exported in batches by a background task.
+pub(crate) struct TraceQueue {
+    exporter: Arc<dyn TraceExporterDyn>,
+    capacity: usize,
+    /// Sender to the background task, spawned on the first trace
+    sender: Mutex<Option<mpsc::Sender<Queued>>>,
+}
+
+impl TraceQueue {
+    pub(crate) fn new(exporter: Arc<dyn TraceExporterDyn>, capacity: usize) -> Self {
+        Self {
+            exporter,
+            capacity,
+            sender: Mutex::new(None),
+        }
+    }
+
+    /// Lock the sender to the background task, spawning the task if it is not running (e.g.:
+    /// the runtime it was spawned on was shut down).
+    fn sender(&self) -> std::sync::MutexGuard<'_, Option<mpsc::Sender<Queued>>> {
+        let mut sender = self.sender.lock().expect("Trace queue lock poisoned");
+        if sender.as_ref().map_or(true, |sender| sender.is_closed()) {
+            let (tx, rx) = mpsc::channel(self.capacity);
+            spawn(export_batches(self.exporter.clone(), rx));
+            *sender = Some(tx);
+        }
+        sender
+    }
+
+    /// Queue `trace` to be exported, or drop it if the queue is full.
+    pub(crate) fn push(&self, trace: AgentTrace) {
+        if let Some(sender) = self.sender().as_mut() {
+            if let Err(e) = sender.try_send(Queued::Trace(trace)) {
+                if e.is_full() {
+                    tracing::warn!(target: "rig", "Trace queue is full, dropping agent trace");
+                }
+            }
+        }
+    }
+
+    /// Wait for the queued traces to be exported.
+    pub(crate) async fn flush(&self) {
+        let sender = self.sender().clone();
+        let Some(mut sender) = sender else {
+            return;
+        };
+
+        let (tx, rx) = oneshot::channel();
+        if sender.send(Queued::Flush(tx)).await.is_ok() {
+            let _ = rx.await;
+        }
+    }
+}
+
+/// Export the traces received from `receiver`, in batches of the traces queued meanwhile.
+async fn export_batches(exporter: Arc<dyn TraceExporterDyn>, receiver: mpsc::Receiver<Queued>) {
+    let mut chunks = receiver.ready_chunks(MAX_TRACE_BATCH);
+    while let Some(chunk) = chunks.next().await {
+        let mut traces = vec![];
+        let mut flushes = vec![];
+        for queued in chunk {
+            match queued {
+                Queued::Trace(trace) => traces.push(trace),
+                Queued::Flush(flush) => flushes.push(flush),
+            }
+        }
+
+        if !traces.is_empty() {
+            if let Err(e) = exporter.export_batch_boxed(&traces).await {
+                tracing::warn!(target: "rig", "Failed to export agent traces: {}", e);
+            }
+        }
+        for flush in flushes {
+            let _ = flush.send(());
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn(task: impl Future<Output = ()> + Send + 'static) {
+    tokio::spawn(task);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn spawn(task: impl Future<Output = ()> + 'static) {
+    wasm_bindgen_futures::spawn_local(task);
+}
+
+/// Context attached to the traces of an agent.
+#[derive(Clone, Debug)]
+pub struct TraceContext {
+    /// Name of the traces (e.g.: the name of the agent)
+    pub name: String,
+    /// ID of the session (i.e.: conversation) the traces belong to
+    pub session_id: Option<String>,
+    /// ID of the user of the agent
+    pub user_id: Option<String>,
+    /// ID of the tenant the agent runs for (see [Tenant](crate::tenant::Tenant))
+    pub tenant_id: Option<String>,
+    /// Tags of the traces
+    pub tags: Vec<String>,
+    /// Additional metadata of the traces
+    pub metadata: serde_json::Map<String, Value>,
+}
+
+impl Default for TraceContext {
+    fn default() -> Self {
+        Self::new("agent")
+    }
+}
+
+impl TraceContext {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            session_id: None,
+            user_id: None,
+            tenant_id: None,
+            tags: vec![],
+            metadata: serde_json::Map::new(),
+        }
+    }
+
+    /// Set the ID of the session the traces belong to
+    pub fn session_id(mut self, session_id: &str) -> Self {
+        self.session_id = Some(session_id.to_string());
+        self
+    }
+
+    /// Set the ID of the user of the agent
+    pub fn user_id(mut self, user_id: &str) -> Self {
+        self.user_id = Some(user_id.to_string());
+        self
+    }
+
+    /// Set the ID of the tenant the agent runs for
+    pub fn tenant_id(mut self, tenant_id: &str) -> Self {
+        self.tenant_id = Some(tenant_id.to_string());
+        self
+    }
+
+    /// Add a tag to the traces
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Add a metadata entry to the traces
+    pub fn metadata(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.metadata.insert(key.to_string(), value.into());
+        self
+    }
+}
+
+/// Trace of a prompt of an agent.
+#[derive(Clone, Debug)]
+pub struct AgentTrace {
+    /// ID of the trace (UUID v4)
+    pub id: String,
+    /// Context of the agent
+    pub context: TraceContext,
+    /// System prompt of the agent
+    pub preamble: String,
+    /// Prompt of the user
+    pub prompt: Message,
+    /// Previous messages of the conversation
+    pub chat_history: Vec<Message>,
+    /// Completion request made by the agent, if it was sent
+    pub generation: Option<GenerationTrace>,
+    /// Tool calls made by the agent
+    pub tool_calls: Vec<ToolCallTrace>,
+    /// Response of the agent, if it succeeded
+    pub output: Option<String>,
+    /// Error of the agent, if it failed
+    pub error: Option<String>,
+    pub start_time: SystemTime,
+    pub end_time: SystemTime,
+}
+
+/// Trace of a completion request made by an agent.
+#[derive(Clone, Debug)]
+pub struct GenerationTrace {
+    /// ID of the generation (UUID v4)
+    pub id: String,
+    /// Response of the model
+    pub response: AssistantContent,
+    pub start_time: SystemTime,
+    pub end_time: SystemTime,
+}
+
+/// Trace of a tool call made by an agent.
+#[derive(Clone, Debug)]
+pub struct ToolCallTrace {
+    /// ID of the tool call (UUID v4)
+    pub id: String,
+    /// Name of the tool
+    pub name: String,
+    /// Arguments of the tool call
+    pub arguments: Value,
+    /// Output of the tool, if it succeeded
+    pub output: Option<String>,
+    /// Error of the tool, if it failed
+    pub error: Option<String>,
+    pub start_time: SystemTime,
+    pub end_time: SystemTime,
+}
+
+impl AgentTrace {
+    pub(crate) fn new(
+        context: &TraceContext,
+        preamble: &str,
+        prompt: &Message,
+        chat_history: &[Message],
+    ) -> Self {
+        let now = SystemTime::now();
+        Self {
+            id: new_id(),
+            context: context.clone(),
+            preamble: preamble.to_string(),
+            prompt: prompt.clone(),
+            chat_history: chat_history.to_vec(),
+            generation: None,
+            tool_calls: vec![],
+            output: None,
+            error: None,
+            start_time: now,
+            end_time: now,
+        }
+    }
+
+    pub(crate) fn record_generation(&mut self, response: AssistantContent, start_time: SystemTime) {
+        self.generation = Some(GenerationTrace {
+            id: new_id(),
+            response,
+            start_time,
+            end_time: SystemTime::now(),
+        });
+    }
+
+    pub(crate) fn record_tool_call(
+        &mut self,
+        tool_call: &ToolCall,
+        result: &Result<String, ToolSetError>,
+        start_time: SystemTime,
+    ) {
+        self.tool_calls.push(ToolCallTrace {
+            id: new_id(),
+            name: tool_call.function.name.clone(),
+            arguments: tool_call.function.arguments.clone(),
+            output: result.as_ref().ok().cloned(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            start_time,
+            end_time: SystemTime::now(),
+        });
+    }
+
+    pub(crate) fn finish<E: std::fmt::Display>(&mut self, result: &Result<String, E>) {
+        match result {
+            Ok(output) => self.output = Some(output.clone()),
+            Err(e) => self.error = Some(e.to_string()),
+        }
+        self.end_time = SystemTime::now();
+    }
+
+    /// Messages sent to the model: the preamble (as a system message), the chat history
+    /// and the prompt.
+    pub fn messages(&self) -> Vec<Value> {
+        let preamble = (!self.preamble.is_empty())
+            .then(|| json!({"role": "system", "content": self.preamble}));
+
+        preamble
+            .into_iter()
+            .chain(
+                self.chat_history
+                    .iter()
+                    .chain(std::iter::once(&self.prompt))
+                    .map(|message| serde_json::to_value(message).unwrap_or_default()),
+            )
+            .collect()
+    }
+}
+
+/// Generate a random UUID v4.
+pub(crate) fn new_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    // `RandomState` is seeded with random keys
+    let random = |seed: u64| {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(seed);
+        hasher.write_u64(count);
+        hasher.finish()
+    };
+
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&random(nanos).to_be_bytes());
+    bytes[8..].copy_from_slice(&random(!nanos).to_be_bytes());
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex = bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// UTC date and time of `time`: (year, month, day, hour, minute, second, microsecond).
+pub(crate) fn utc(time: SystemTime) -> (i64, u64, u64, u64, u64, u64, u32) {
+    let duration = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = duration.as_secs();
+
+    // Conversion of the number of days since the epoch to a civil date
+    // (see http://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u64;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u64;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (
+        year,
+        month,
+        day,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        duration.subsec_micros(),
+    )
+}
+
+/// Format `time` as a RFC 3339 timestamp (e.g.: `2024-06-10T14:03:27.123456Z`).
+pub(crate) fn rfc3339(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second, micros) = utc(time);
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}.{micros:06}Z")
+}
+
+/// Format `time` as a compact timestamp (e.g.: `20240610T140327123456Z`).
+pub(crate) fn compact_timestamp(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second, micros) = utc(time);
+    format!("{year:04}{month:02}{day:02}T{hour:02}{minute:02}{second:02}{micros:06}Z")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{
+        agent::AgentBuilder,
+        completion::{
+            Chat, CompletionError, CompletionModel, CompletionRequest, CompletionResponse,
+        },
+        one_or_many::OneOrMany,
+        tool::Tool,
+    };
+
+    /// Exporter that keeps the exported traces in memory
+    #[derive(Clone, Default)]
+    pub(crate) struct MockExporter {
+        pub traces: Arc<Mutex<Vec<AgentTrace>>>,
+    }
+
+    impl TraceExporter for MockExporter {
+        async fn export(&self, trace: &AgentTrace) -> Result<(), TraceExportError> {
+            self.traces.lock().unwrap().push(trace.clone());
+            Ok(())
+        }
+    }
+
+    /// Model that always calls the `add` tool
+    #[derive(Clone)]
+    struct MockModel;
+
+    impl CompletionModel for MockModel {
+        type Response = ();
+
+        async fn completion(
+            &self,
+            _request: CompletionRequest,
+        ) -> Result<CompletionResponse<()>, CompletionError> {
+            Ok(CompletionResponse {
+                choice: OneOrMany::one(AssistantContent::tool_call(
+                    "call_0",
+                    "add",
+                    json!({"x": 1, "y": 2}),
+                )),
+                raw_response: (),
+            })
+        }
+    }
+
+    #[derive(serde::Deserialize)]
+    struct AddArgs {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Math error")]
+    struct MathError;
+
+    struct Adder;
+
+    impl Tool for Adder {
+        const NAME: &'static str = "add";
+
+        type Error = MathError;
+        type Args = AddArgs;
+        type Output = i32;
+
+        async fn definition(&self, _prompt: String) -> crate::completion::ToolDefinition {
+            crate::completion::ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Add x and y together".to_string(),
+                parameters: json!({}),
+            }
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            Ok(args.x + args.y)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_agent_trace() {
+        let exporter = MockExporter::default();
+        let agent = AgentBuilder::new(MockModel)
+            .preamble("You are a calculator")
+            .tool(Adder)
+            .trace_exporter(exporter.clone())
+            .trace_context(TraceContext::new("calculator").session_id("session-1"))
+            .build();
+
+        let response = agent
+            .chat("What is 1 + 2?", vec![Message::user("Hello")])
+            .await
+            .unwrap();
+        assert_eq!(response, "3");
+
+        agent.flush_traces().await;
+        let traces = exporter.traces.lock().unwrap();
+        assert_eq!(traces.len(), 1);
+
+        let trace = &traces[0];
+        assert_eq!(trace.context.name, "calculator");
+        assert_eq!(trace.context.session_id.as_deref(), Some("session-1"));
+        assert_eq!(trace.output.as_deref(), Some("3"));
+        assert!(trace.generation.is_some());
+        assert_eq!(trace.tool_calls.len(), 1);
+        assert_eq!(trace.tool_calls[0].name, "add");
+        assert_eq!(trace.tool_calls[0].output.as_deref(), Some("3"));
+        assert_eq!(trace.messages().len(), 3);
+        assert_eq!(trace.messages()[0]["role"], "system");
+    }
+
+    #[test]
+    fn test_new_id() {
+        let id = new_id();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        assert_ne!(id, new_id());
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let time = UNIX_EPOCH + Duration::from_micros(1_718_028_207_123_456);
+        assert_eq!(rfc3339(time), "2024-06-10T14:03:27.123456Z");
+        assert_eq!(compact_timestamp(time), "20240610T140327123456Z");
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000000Z");
+    }
+
+    /// Exporter that keeps the sizes of the exported batches
+    #[derive(Clone, Default)]
+    struct BatchExporter {
+        batches: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl TraceExporter for BatchExporter {
+        async fn export(&self, _trace: &AgentTrace) -> Result<(), TraceExportError> {
+            unreachable!("Traces are exported in batches")
+        }
+
+        async fn export_batch(&self, traces: &[AgentTrace]) -> Result<(), TraceExportError> {
+            self.batches.lock().unwrap().push(traces.len());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trace_queue() {
+        let trace = || AgentTrace::new(&TraceContext::default(), "", &Message::user("Hi"), &[]);
+
+        // The traces queued meanwhile are exported in one batch
+        let exporter = BatchExporter::default();
+        let queue = TraceQueue::new(Arc::new(exporter.clone()), 10);
+        (0..3).for_each(|_| queue.push(trace()));
+        queue.flush().await;
+        assert_eq!(*exporter.batches.lock().unwrap(), vec![3]);
+
+        // The traces are dropped while the queue is full
+        let exporter = BatchExporter::default();
+        let queue = TraceQueue::new(Arc::new(exporter.clone()), 0);
+        (0..3).for_each(|_| queue.push(trace()));
+        queue.flush().await;
+        assert_eq!(*exporter.batches.lock().unwrap(), vec![1]);
+
+        queue.push(trace());
+        queue.flush().await;
+        assert_eq!(*exporter.batches.lock().unwrap(), vec![1, 1]);
+    }
+}
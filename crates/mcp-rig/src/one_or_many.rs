@@ -126,6 +126,14 @@ impl<T: Clone> OneOrMany<T> {
         })
     }
 
+    /// Apply a function to every item in the list, returning a new [OneOrMany].
+    pub fn map<U, F: FnMut(T) -> U>(self, mut f: F) -> OneOrMany<U> {
+        OneOrMany {
+            first: f(self.first),
+            rest: self.rest.into_iter().map(f).collect(),
+        }
+    }
+
     pub fn iter(&self) -> Iter<T> {
         Iter {
             first: Some(&self.first),
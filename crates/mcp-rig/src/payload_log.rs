@@ -0,0 +1,681 @@
+//! Opt-in logging of the raw payloads exchanged with model providers and MCP servers, for
+//! debugging the behavior of agents in production.
+//!
+//! Once a [PayloadLogger] is installed, every (non-streaming) completion and embedding request
+//! sent to a provider, the corresponding response, and every MCP tool call and result is written
+//! as a JSON line to the logger's writer (e.g.: a file).
+//!
+//! Before being written, each entry goes through the redaction rules of the logger. By default,
+//! the [RedactHeaders] and [RedactSecrets] rules are applied, which strip the authentication
+//! headers, the secret fields and query parameters (e.g.: `api_key`, `password`) and the values
+//! that look like credentials (e.g.: `Bearer ...`, `sk-...`, including the query parameters such
+//! as `?key=AIza...`).
+//! Additional rules can be added with [PayloadLogger::redact_path] (e.g.: to strip the content
+//! of the messages) or [PayloadLogger::rule] (e.g.: [RedactPii], to strip the email addresses
+//! and phone numbers of the users).
+//!
+//! Note: the headers set as default headers of a provider's HTTP client (e.g.: the
+//! `Authorization` header of the OpenAI client) are not part of the logged requests.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::payload_log::PayloadLogger;
+//!
+//! PayloadLogger::to_file("payloads.jsonl")?
+//!     .redact_path("body.messages.*.content")
+//!     .install();
+//!
+//! // All requests, responses and MCP tool calls are now logged to `payloads.jsonl`
+//! let response = agent.prompt("What is a flurbo?").await?;
+//! ```
+//!
+//! Each line is a JSON object with a `kind` field (`request`, `response`, `tool_call` or
+//! `tool_result`), a `source` field (the name of the provider, or `mcp`) and a `timestamp_ms`
+//! field (milliseconds since the Unix epoch), e.g.:
+//! ```json
+//! {"kind":"request","source":"openai","method":"POST","url":"https://api.openai.com/v1/chat/completions","headers":{},"body":{"model":"gpt-4o","messages":[...]},"timestamp_ms":1718000000000}
+//! {"kind":"response","source":"openai","url":"https://api.openai.com/v1/chat/completions","status":200,"duration_ms":812,"body":{"choices":[...]},"timestamp_ms":1718000000812}
+//! ```
+use std::{
+    future::Future,
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use serde_json::{json, Value};
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::http_client::{HttpBackend, HttpClientError};
+
+/// Placeholder of the redacted values.
+pub const REDACTED: &str = "[REDACTED]";
+
+static LOGGER: RwLock<Option<Arc<PayloadLogger>>> = RwLock::new(None);
+
+/// Trait for the redaction rules applied to the log entries before they are written.
+pub trait RedactionRule: Send + Sync {
+    /// Redact the sensitive values of `entry` in place.
+    fn redact(&self, entry: &mut Value);
+}
+
+impl<F> RedactionRule for F
+where
+    F: Fn(&mut Value) + Send + Sync,
+{
+    fn redact(&self, entry: &mut Value) {
+        self(entry)
+    }
+}
+
+/// Rule that redacts the values of the given headers (case-insensitive) of the logged requests.
+pub struct RedactHeaders {
+    headers: Vec<String>,
+}
+
+impl Default for RedactHeaders {
+    fn default() -> Self {
+        Self::new([
+            "authorization",
+            "proxy-authorization",
+            "api-key",
+            "x-api-key",
+            "x-goog-api-key",
+            "cookie",
+            "set-cookie",
+        ])
+    }
+}
+
+impl RedactHeaders {
+    pub fn new(headers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            headers: headers
+                .into_iter()
+                .map(|header| header.into().to_lowercase())
+                .collect(),
+        }
+    }
+}
+
+impl RedactionRule for RedactHeaders {
+    fn redact(&self, entry: &mut Value) {
+        if let Some(headers) = entry.get_mut("headers").and_then(Value::as_object_mut) {
+            headers
+                .iter_mut()
+                .filter(|(name, _)| self.headers.contains(&name.to_lowercase()))
+                .for_each(|(_, value)| *value = REDACTED.into());
+        }
+    }
+}
+
+/// Rule that redacts secure values anywhere in the log entries:
+/// - the values of the object fields and URL query parameters with a secret name
+///   (e.g.: `api_key`, `password`, `client_secret`),
+/// - the strings and URL query parameters that start with a credential prefix
+///   (e.g.: `Bearer `, `sk-`).
+///
+/// The secret names are matched exactly (case-insensitive), so that generic fields such as `key`
+/// or `token` are not redacted unless their value looks like a credential.
+pub struct RedactSecrets {
+    keys: Vec<String>,
+    prefixes: Vec<String>,
+}
+
+impl Default for RedactSecrets {
+    fn default() -> Self {
+        Self {
+            keys: [
+                "api_key",
+                "apikey",
+                "api-key",
+                "access_token",
+                "refresh_token",
+                "id_token",
+                "client_secret",
+                "secret_key",
+                "private_key",
+                "secret",
+                "password",
+                "authorization",
+            ]
+            .map(String::from)
+            .to_vec(),
+            prefixes: [
+                "Bearer ",
+                "Basic ",
+                "sk-",
+                "xai-",
+                "AIza",
+                "ghp_",
+                "github_pat_",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+impl RedactSecrets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also redact the fields and query parameters with the given name (case-insensitive).
+    pub fn key(mut self, key: &str) -> Self {
+        self.keys.push(key.to_lowercase());
+        self
+    }
+
+    /// Also redact the strings starting with the given prefix.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefixes.push(prefix.to_string());
+        self
+    }
+
+    fn is_secret_key(&self, key: &str) -> bool {
+        self.keys.contains(&key.to_lowercase())
+    }
+
+    fn is_secret_value(&self, value: &str) -> bool {
+        self.prefixes.iter().any(|prefix| value.starts_with(prefix))
+    }
+
+    fn redact_value(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => object.iter_mut().for_each(|(key, value)| {
+                if self.is_secret_key(key) && !value.is_null() {
+                    *value = REDACTED.into();
+                } else {
+                    self.redact_value(value);
+                }
+            }),
+            Value::Array(values) => values.iter_mut().for_each(|value| self.redact_value(value)),
+            Value::String(string) => {
+                if self.is_secret_value(string) {
+                    *value = REDACTED.into();
+                } else if let Some(url) = self.redact_url(string) {
+                    *string = url;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Redact the query parameters of `url` with a secret name or value. Returns None if `url`
+    /// is not a URL or has no secret query parameter.
+    fn redact_url(&self, url: &str) -> Option<String> {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return None;
+        }
+        let mut url = reqwest::Url::parse(url).ok()?;
+        let is_secret =
+            |key: &str, value: &str| self.is_secret_key(key) || self.is_secret_value(value);
+        if !url
+            .query_pairs()
+            .any(|(key, value)| is_secret(&key, &value))
+        {
+            return None;
+        }
+
+        let pairs = url
+            .query_pairs()
+            .map(|(key, value)| {
+                let value = if is_secret(&key, &value) {
+                    REDACTED.to_string()
+                } else {
+                    value.into_owned()
+                };
+                (key.into_owned(), value)
+            })
+            .collect::<Vec<_>>();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+
+        Some(url.to_string())
+    }
+}
+
+impl RedactionRule for RedactSecrets {
+    fn redact(&self, entry: &mut Value) {
+        self.redact_value(entry)
+    }
+}
+
+/// Rule that redacts the value at a JSON path of the log entries. The path is a dot-separated
+/// list of object keys and array indices, where `*` matches every key or index
+/// (e.g.: `body.messages.*.content`).
+pub struct RedactPath {
+    path: Vec<String>,
+}
+
+impl RedactPath {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.split('.').map(String::from).collect(),
+        }
+    }
+
+    fn redact_at(value: &mut Value, path: &[String]) {
+        let Some((segment, rest)) = path.split_first() else {
+            *value = REDACTED.into();
+            return;
+        };
+
+        let children: Vec<&mut Value> = match (value, segment.as_str()) {
+            (Value::Object(object), "*") => object.values_mut().collect(),
+            (Value::Array(values), "*") => values.iter_mut().collect(),
+            (Value::Object(object), key) => object.get_mut(key).into_iter().collect(),
+            (Value::Array(values), index) => index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| values.get_mut(index))
+                .into_iter()
+                .collect(),
+            _ => vec![],
+        };
+
+        children
+            .into_iter()
+            .for_each(|child| Self::redact_at(child, rest));
+    }
+}
+
+impl RedactionRule for RedactPath {
+    fn redact(&self, entry: &mut Value) {
+        Self::redact_at(entry, &self.path)
+    }
+}
+
+/// Kinds and patterns of the personally identifiable information redacted by [RedactPii] and the
+/// [Redactor](crate::redaction::Redactor), in order.
+pub(crate) const PII_PATTERNS: &[(&str, &str)] = &[
+    ("EMAIL", r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}"),
+    // Payment card numbers
+    ("CARD", r"\b(?:\d[ -]?){12,18}\d\b"),
+    // e.g.: +1 555-123-4567, (555) 123 4567
+    (
+        "PHONE",
+        r"(?:\+\d{1,3}[\s.-]?)?\(?\d{3}\)?[\s.-]\d{3}[\s.-]\d{4}\b",
+    ),
+    // IPv4 addresses
+    ("IP", r"\b(?:\d{1,3}\.){3}\d{1,3}\b"),
+];
+
+/// Rule that redacts personally identifiable information in every string of the log entries:
+/// email addresses, phone numbers, payment card numbers and IP addresses by default.
+/// The matched substrings (not the whole strings) are replaced.
+pub struct RedactPii {
+    patterns: Vec<regex::Regex>,
+}
+
+impl Default for RedactPii {
+    fn default() -> Self {
+        Self {
+            patterns: PII_PATTERNS
+                .iter()
+                .map(|(_, pattern)| {
+                    regex::Regex::new(pattern).expect("PII pattern should be valid")
+                })
+                .collect(),
+        }
+    }
+}
+
+impl RedactPii {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also redact the substrings matching the regex `pattern` (e.g.: customer IDs).
+    pub fn pattern(mut self, pattern: &str) -> Result<Self, regex::Error> {
+        self.patterns.push(regex::Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    fn redact_value(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => object
+                .values_mut()
+                .for_each(|value| self.redact_value(value)),
+            Value::Array(values) => values.iter_mut().for_each(|value| self.redact_value(value)),
+            Value::String(string) => {
+                for pattern in &self.patterns {
+                    if pattern.is_match(string) {
+                        *string = pattern.replace_all(string, REDACTED).into_owned();
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl RedactionRule for RedactPii {
+    fn redact(&self, entry: &mut Value) {
+        self.redact_value(entry)
+    }
+}
+
+/// Default redaction rules: [RedactHeaders] and [RedactSecrets].
+pub(crate) fn default_rules() -> Vec<Box<dyn RedactionRule>> {
+    vec![
+        Box::new(RedactHeaders::default()),
+        Box::new(RedactSecrets::default()),
+    ]
+}
+
+/// Logger of the payloads exchanged with model providers and MCP servers, in the JSONL format.
+/// See the [module documentation](self).
+pub struct PayloadLogger {
+    writer: Mutex<Box<dyn Write + Send>>,
+    rules: Vec<Box<dyn RedactionRule>>,
+}
+
+impl PayloadLogger {
+    /// Create a logger writing to `writer`, with the default redaction rules
+    /// ([RedactHeaders] and [RedactSecrets]).
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+            rules: default_rules(),
+        }
+    }
+
+    /// Create a logger appending to the file at `path`, with the default redaction rules.
+    pub fn to_file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self::new(std::io::LineWriter::new(file)))
+    }
+
+    /// Remove the redaction rules of the logger, including the default ones.
+    pub fn without_redaction(mut self) -> Self {
+        self.rules.clear();
+        self
+    }
+
+    /// Add a redaction rule to the logger.
+    pub fn rule(mut self, rule: impl RedactionRule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Redact the value at the given JSON path of the log entries (see [RedactPath]).
+    pub fn redact_path(self, path: &str) -> Self {
+        self.rule(RedactPath::new(path))
+    }
+
+    /// Install the logger, replacing the previously installed one (if any).
+    pub fn install(self) {
+        *LOGGER.write().expect("Payload logger lock poisoned") = Some(Arc::new(self));
+    }
+
+    /// Uninstall the current logger (if any), which stops the logging of payloads.
+    pub fn uninstall() {
+        *LOGGER.write().expect("Payload logger lock poisoned") = None;
+    }
+
+    /// Flush the writer of the logger (e.g.: on shutdown).
+    pub fn flush(&self) {
+        let mut writer = self.writer.lock().expect("Payload logger lock poisoned");
+        if let Err(e) = writer.flush() {
+            tracing::warn!(target: "rig", "Failed to flush payload log: {}", e);
+        }
+    }
+
+    /// Redact `entry` and write it as a JSON line.
+    pub fn log(&self, mut entry: Value) {
+        if let Some(object) = entry.as_object_mut() {
+            object.insert("timestamp_ms".into(), timestamp_ms().into());
+        }
+        self.rules.iter().for_each(|rule| rule.redact(&mut entry));
+
+        let mut writer = self.writer.lock().expect("Payload logger lock poisoned");
+        if let Err(e) = writeln!(writer, "{entry}") {
+            tracing::warn!(target: "rig", "Failed to write payload log entry: {}", e);
+        }
+    }
+}
+
+fn timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Currently installed logger, if any.
+pub(crate) fn logger() -> Option<Arc<PayloadLogger>> {
+    LOGGER.read().expect("Payload logger lock poisoned").clone()
+}
+
+/// Parse `bytes` as JSON, falling back to a string if they are not valid JSON.
+pub(crate) fn body_to_value(bytes: &[u8]) -> Value {
+    serde_json::from_slice(bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(bytes).into_owned()))
+}
+
+/// Extension trait used by the providers to send requests whose payloads are logged
+/// when a [PayloadLogger] is installed.
+pub(crate) trait SendLogged {
+    fn send_logged(
+        self,
+        source: &'static str,
+        http: &HttpBackend,
+    ) -> impl Future<Output = Result<reqwest::Response, HttpClientError>>;
+}
+
+impl SendLogged for reqwest::RequestBuilder {
+    async fn send_logged(
+        self,
+        source: &'static str,
+        http: &HttpBackend,
+    ) -> Result<reqwest::Response, HttpClientError> {
+        let Some(logger) = logger() else {
+            return http.send(self).await;
+        };
+
+        let (client, request) = self.build_split();
+        let request = request?;
+        let url = request.url().to_string();
+
+        logger.log(json!({
+            "kind": "request",
+            "source": source,
+            "method": request.method().as_str(),
+            "url": url,
+            "headers": request
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into())
+                })
+                .collect::<serde_json::Map<String, Value>>(),
+            "body": request.body().and_then(|body| body.as_bytes()).map(body_to_value),
+        }));
+
+        let start = Instant::now();
+        let response = http.execute(&client, request).await?;
+
+        // The body of the response is consumed to be logged, so the response is rebuilt
+        let status = response.status();
+        let version = response.version();
+        let headers = response.headers().clone();
+        let bytes = response.bytes().await?;
+
+        logger.log(json!({
+            "kind": "response",
+            "source": source,
+            "url": url,
+            "status": status.as_u16(),
+            "duration_ms": start.elapsed().as_millis() as u64,
+            "body": body_to_value(&bytes),
+        }));
+
+        Ok(rebuild_response(status, version, headers, bytes))
+    }
+}
+
+/// Build a response from its parts (e.g.: after its body was consumed).
+pub(crate) fn rebuild_response(
+    status: reqwest::StatusCode,
+    version: reqwest::Version,
+    headers: reqwest::header::HeaderMap,
+    body: impl Into<bytes::Bytes>,
+) -> reqwest::Response {
+    let mut response = http::Response::new(body.into());
+    *response.status_mut() = status;
+    *response.version_mut() = version;
+    *response.headers_mut() = headers;
+    response.into()
+}
+
+/// Log a call to the MCP tool `tool` with the given arguments.
+pub(crate) fn log_tool_call(tool: &str, args: &Value) {
+    if let Some(logger) = logger() {
+        logger.log(json!({
+            "kind": "tool_call",
+            "source": "mcp",
+            "tool": tool,
+            "arguments": args,
+        }));
+    }
+}
+
+/// Log the result of a call to the MCP tool `tool`.
+pub(crate) fn log_tool_result<E: std::fmt::Display>(tool: &str, result: &Result<String, E>) {
+    if let Some(logger) = logger() {
+        logger.log(match result {
+            Ok(output) => json!({
+                "kind": "tool_result",
+                "source": "mcp",
+                "tool": tool,
+                "result": body_to_value(output.as_bytes()),
+            }),
+            Err(e) => json!({
+                "kind": "tool_result",
+                "source": "mcp",
+                "tool": tool,
+                "error": e.to_string(),
+            }),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writer to a shared buffer
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_default_redaction() {
+        let buffer = Buffer::default();
+        let logger = PayloadLogger::new(buffer.clone());
+
+        logger.log(json!({
+            "kind": "request",
+            "source": "gemini",
+            "url": "https://example.com/v1/models?key=AIzaSecret&alt=json&secret_key=secret",
+            "headers": {
+                "Authorization": "Bearer secret",
+                "Content-Type": "application/json",
+            },
+            "body": {
+                "api_key": "secret",
+                "token": "sk-proj-secret",
+                "max_tokens": 100,
+                "metadata": {"key": "user-42", "private_key": "secret"},
+                "messages": [{"role": "user", "content": "Hello"}],
+            },
+        }));
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(output.ends_with('\n'));
+        assert!(!output.contains("secret"));
+
+        let entry: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(entry["headers"]["Authorization"], REDACTED);
+        assert_eq!(entry["headers"]["Content-Type"], "application/json");
+        assert_eq!(
+            entry["url"],
+            "https://example.com/v1/models?key=%5BREDACTED%5D&alt=json&secret_key=%5BREDACTED%5D"
+        );
+        assert_eq!(entry["body"]["api_key"], REDACTED);
+        assert_eq!(entry["body"]["token"], REDACTED);
+        assert_eq!(entry["body"]["max_tokens"], 100);
+        // Only the specific secret names are redacted
+        assert_eq!(entry["body"]["metadata"]["key"], "user-42");
+        assert_eq!(entry["body"]["metadata"]["private_key"], REDACTED);
+        assert_eq!(entry["body"]["messages"][0]["content"], "Hello");
+        assert!(entry["timestamp_ms"].is_u64());
+    }
+
+    #[test]
+    fn test_redact_path() {
+        let mut entry = json!({
+            "body": {
+                "messages": [
+                    {"role": "system", "content": "You are a helpful assistant"},
+                    {"role": "user", "content": "My SSN is 123-45-6789"},
+                ],
+            },
+        });
+
+        RedactPath::new("body.messages.*.content").redact(&mut entry);
+        RedactPath::new("body.messages.0.role").redact(&mut entry);
+        // Paths that do not exist are ignored
+        RedactPath::new("body.tools.*.name").redact(&mut entry);
+
+        assert_eq!(
+            entry,
+            json!({
+                "body": {
+                    "messages": [
+                        {"role": REDACTED, "content": REDACTED},
+                        {"role": "user", "content": REDACTED},
+                    ],
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_redact_pii() {
+        let mut entry = json!({
+            "content": "Contact john.doe@example.com or +1 555-123-4567 from 192.168.0.1",
+            "card": "My card is 4111 1111 1111 1111",
+            "date": "2024-01-01",
+            "user": "ACME-12345",
+        });
+
+        RedactPii::new()
+            .pattern(r"ACME-\d+")
+            .unwrap()
+            .redact(&mut entry);
+
+        assert_eq!(
+            entry,
+            json!({
+                "content": "Contact [REDACTED] or [REDACTED] from [REDACTED]",
+                "card": "My card is [REDACTED]",
+                "date": "2024-01-01",
+                "user": REDACTED,
+            })
+        );
+    }
+}
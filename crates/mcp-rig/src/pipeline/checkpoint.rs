@@ -0,0 +1,318 @@
+//! This module defines the [StageStore] trait and the [Checkpointed] and [TryCheckpointed]
+//! adapters, which persist the output of a pipeline stage once it completes. When a long
+//! pipeline (e.g.: crawl → embed → extract over thousands of documents) is interrupted, running
+//! it again with the same store skips every stage whose output has already been saved and resumes
+//! from the first stage that did not complete.
+//!
+//! Checkpointing is best-effort: if the store cannot be read or written, a warning is logged
+//! and the stage is executed normally.
+//!
+//! Note: the saved outputs are identified by the name of the stage only, so a store should not be
+//! shared between runs of the same pipeline on different inputs (e.g.: use one directory per job).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::pipeline::{self, checkpoint::FileStageStore, Op, TryOp};
+//!
+//! let store = FileStageStore::new("./checkpoints/job-42");
+//!
+//! let pipeline = pipeline::new()
+//!     // Infallible stage
+//!     .chain(crawl.checkpoint("crawl", store.clone()))
+//!     // Fallible stage: only saved if it succeeds
+//!     .chain(embed.checkpoint_ok("embed", store.clone()));
+//!
+//! // If the process crashes during the `embed` stage, the next run skips the `crawl` stage
+//! let embeddings = pipeline.call(urls).await?;
+//! ```
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+
+use super::{op::Op, try_op::TryOp};
+
+/// Characters of the stage names that are percent-encoded in the file names of a
+/// [FileStageStore]. The encoding is reversible, so that two stages never share a file.
+const STAGE_FILE_NAME: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_');
+
+#[derive(Debug, thiserror::Error)]
+pub enum StageStoreError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// Trait for stores that persist the outputs of completed pipeline stages.
+pub trait StageStore: Send + Sync {
+    /// Load the saved output of the given stage, if any.
+    fn load(&self, stage: &str) -> Result<Option<serde_json::Value>, StageStoreError>;
+
+    /// Save the output of the given stage, replacing the previously saved one.
+    fn save(&self, stage: &str, output: &serde_json::Value) -> Result<(), StageStoreError>;
+
+    /// Remove every saved output (e.g.: once the pipeline has completed).
+    fn clear(&self) -> Result<(), StageStoreError>;
+}
+
+/// [StageStore] that persists the output of each stage as a JSON file in a directory, named
+/// after the percent-encoded stage name (e.g.: `crawl%2Fpages.json`). Files are written
+/// atomically (i.e.: written to a temporary file and then renamed) so that an interrupted
+/// pipeline never leaves a corrupted output behind.
+#[derive(Clone)]
+pub struct FileStageStore {
+    dir: PathBuf,
+}
+
+impl FileStageStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, stage: &str) -> PathBuf {
+        let name = utf8_percent_encode(stage, STAGE_FILE_NAME);
+        self.dir.join(format!("{name}.json"))
+    }
+}
+
+impl StageStore for FileStageStore {
+    fn load(&self, stage: &str) -> Result<Option<serde_json::Value>, StageStoreError> {
+        match std::fs::read(self.path(stage)) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, stage: &str, output: &serde_json::Value) -> Result<(), StageStoreError> {
+        let path = self.path(stage);
+        let tmp_path = path.with_extension("tmp");
+
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(&tmp_path, serde_json::to_vec(output)?)?;
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), StageStoreError> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// In-memory [StageStore]. Mostly useful for testing.
+#[derive(Clone, Default)]
+pub struct InMemoryStageStore {
+    outputs: Arc<Mutex<HashMap<String, serde_json::Value>>>,
+}
+
+impl InMemoryStageStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StageStore for InMemoryStageStore {
+    fn load(&self, stage: &str) -> Result<Option<serde_json::Value>, StageStoreError> {
+        Ok(self
+            .outputs
+            .lock()
+            .expect("Stage store lock poisoned")
+            .get(stage)
+            .cloned())
+    }
+
+    fn save(&self, stage: &str, output: &serde_json::Value) -> Result<(), StageStoreError> {
+        self.outputs
+            .lock()
+            .expect("Stage store lock poisoned")
+            .insert(stage.to_string(), output.clone());
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), StageStoreError> {
+        self.outputs
+            .lock()
+            .expect("Stage store lock poisoned")
+            .clear();
+        Ok(())
+    }
+}
+
+/// Load the saved output of `stage`, logging (and ignoring) any error.
+fn load_checkpoint<S: StageStore, T: for<'a> Deserialize<'a>>(store: &S, stage: &str) -> Option<T> {
+    match store.load(stage).and_then(|output| {
+        output
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(StageStoreError::from)
+    }) {
+        Ok(output) => output,
+        Err(e) => {
+            tracing::warn!(target: "rig", "Failed to load checkpoint of stage `{}`: {}", stage, e);
+            None
+        }
+    }
+}
+
+/// Save the output of `stage`, logging (and ignoring) any error.
+fn save_checkpoint<S: StageStore, T: Serialize>(store: &S, stage: &str, output: &T) {
+    if let Err(e) = serde_json::to_value(output)
+        .map_err(StageStoreError::from)
+        .and_then(|output| store.save(stage, &output))
+    {
+        tracing::warn!(target: "rig", "Failed to save checkpoint of stage `{}`: {}", stage, e);
+    }
+}
+
+/// Adapter that saves the output of an op in a [StageStore] and skips the op when its
+/// output has already been saved. See [Op::checkpoint].
+pub struct Checkpointed<T, S> {
+    op: T,
+    stage: String,
+    store: S,
+}
+
+impl<T, S> Checkpointed<T, S> {
+    pub fn new(op: T, stage: &str, store: S) -> Self {
+        Self {
+            op,
+            stage: stage.to_string(),
+            store,
+        }
+    }
+}
+
+impl<T, S> Op for Checkpointed<T, S>
+where
+    T: Op,
+    T::Output: Serialize + for<'a> Deserialize<'a>,
+    S: StageStore,
+{
+    type Input = T::Input;
+    type Output = T::Output;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        if let Some(output) = load_checkpoint(&self.store, &self.stage) {
+            tracing::info!(target: "rig", "Resuming after completed stage `{}`", self.stage);
+            return output;
+        }
+
+        let output = self.op.call(input).await;
+        save_checkpoint(&self.store, &self.stage, &output);
+        output
+    }
+}
+
+/// Same as [Checkpointed] but for fallible ops: only successful outputs are saved, so a stage
+/// that failed is executed again on the next run. See [TryOp::checkpoint_ok].
+pub struct TryCheckpointed<T, S> {
+    op: T,
+    stage: String,
+    store: S,
+}
+
+impl<T, S> TryCheckpointed<T, S> {
+    pub fn new(op: T, stage: &str, store: S) -> Self {
+        Self {
+            op,
+            stage: stage.to_string(),
+            store,
+        }
+    }
+}
+
+impl<T, S> Op for TryCheckpointed<T, S>
+where
+    T: TryOp,
+    T::Output: Serialize + for<'a> Deserialize<'a>,
+    S: StageStore,
+{
+    type Input = T::Input;
+    type Output = Result<T::Output, T::Error>;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        if let Some(output) = load_checkpoint(&self.store, &self.stage) {
+            tracing::info!(target: "rig", "Resuming after completed stage `{}`", self.stage);
+            return Ok(output);
+        }
+
+        let output = self.op.try_call(input).await?;
+        save_checkpoint(&self.store, &self.stage, &output);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::pipeline::{self, map};
+
+    #[tokio::test]
+    async fn test_resume_from_checkpoint() {
+        let store = InMemoryStageStore::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let make_pipeline = |fail: bool| {
+            let calls = calls.clone();
+            pipeline::new()
+                .chain(
+                    map(move |x: i32| {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        x + 1
+                    })
+                    .checkpoint("first", store.clone()),
+                )
+                .chain(
+                    map(move |x: i32| {
+                        if fail {
+                            Err("crash".to_string())
+                        } else {
+                            Ok(x * 10)
+                        }
+                    })
+                    .checkpoint_ok("second", store.clone()),
+                )
+        };
+
+        // First run: the first stage completes, the second one fails
+        assert_eq!(make_pipeline(true).call(1).await, Err("crash".to_string()));
+        assert_eq!(store.load("first").unwrap(), Some(serde_json::json!(2)));
+        assert_eq!(store.load("second").unwrap(), None);
+
+        // Second run: the first stage is skipped
+        assert_eq!(make_pipeline(false).call(1).await, Ok(20));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_file_stage_store() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let store = FileStageStore::new(dir.path().join("job"));
+
+        let op = map(|x: i32| vec![x; 3]).checkpoint("crawl/pages", store.clone());
+        assert_eq!(op.call(1).await, vec![1, 1, 1]);
+        // The saved output is returned, regardless of the input
+        assert_eq!(op.call(2).await, vec![1, 1, 1]);
+
+        // Stages with similar names are saved to different files
+        let other = map(|x: i32| vec![x; 2]).checkpoint("crawl_pages", store.clone());
+        assert_eq!(other.call(3).await, vec![3, 3]);
+        assert_eq!(op.call(2).await, vec![1, 1, 1]);
+        assert!(dir.path().join("job/crawl%2Fpages.json").exists());
+        assert!(dir.path().join("job/crawl_pages.json").exists());
+
+        store.clear().unwrap();
+        assert_eq!(op.call(2).await, vec![2, 2, 2]);
+    }
+}
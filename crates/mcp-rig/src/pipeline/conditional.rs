@@ -1,3 +1,9 @@
+use std::{collections::HashMap, hash::Hash};
+
+use futures::future::BoxFuture;
+
+use super::Op;
+
 /// Creates an `Op` that conditionally dispatches to one of multiple sub-ops
 /// based on the variant of the input enum.
 ///
@@ -157,6 +163,185 @@ macro_rules! try_conditional {
     };
 }
 
+/// Op that dispatches the input to one of two sub-ops depending on a predicate.
+/// See [branch].
+pub struct Branch<P, Op1, Op2> {
+    predicate: P,
+    if_true: Op1,
+    if_false: Op2,
+}
+
+impl<P, Op1, Op2> Branch<P, Op1, Op2> {
+    pub fn new(predicate: P, if_true: Op1, if_false: Op2) -> Self {
+        Self {
+            predicate,
+            if_true,
+            if_false,
+        }
+    }
+}
+
+impl<P, Op1, Op2> Op for Branch<P, Op1, Op2>
+where
+    P: Fn(&Op1::Input) -> bool + Send + Sync,
+    Op1: Op,
+    Op2: Op<Input = Op1::Input, Output = Op1::Output>,
+{
+    type Input = Op1::Input;
+    type Output = Op1::Output;
+
+    #[inline]
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        if (self.predicate)(&input) {
+            self.if_true.call(input).await
+        } else {
+            self.if_false.call(input).await
+        }
+    }
+}
+
+/// Create an op that runs `if_true` on the input if `predicate` returns true, and
+/// `if_false` otherwise. Both sub-ops must have the same input and output types.
+///
+/// # Example
+/// ```rust
+/// use mcp_rig::pipeline::{self, conditional::branch, map, Op};
+///
+/// let pipeline = pipeline::new().chain(branch(
+///     |x: &i32| *x > 0,
+///     map(|x: i32| format!("{x} is positive")),
+///     map(|x: i32| format!("{x} is not positive")),
+/// ));
+///
+/// let result = pipeline.call(1).await;
+/// assert_eq!(result, "1 is positive");
+/// ```
+pub fn branch<P, Op1, Op2>(predicate: P, if_true: Op1, if_false: Op2) -> Branch<P, Op1, Op2>
+where
+    P: Fn(&Op1::Input) -> bool + Send + Sync,
+    Op1: Op,
+    Op2: Op<Input = Op1::Input, Output = Op1::Output>,
+{
+    Branch::new(predicate, if_true, if_false)
+}
+
+/// Object-safe version of [Op], used to store sub-pipelines of different types.
+trait DynOp<Input, Output>: Send + Sync {
+    fn call_boxed(&self, input: Input) -> BoxFuture<'_, Output>;
+}
+
+impl<T: Op> DynOp<T::Input, T::Output> for T {
+    fn call_boxed(&self, input: T::Input) -> BoxFuture<'_, T::Output> {
+        Box::pin(self.call(input))
+    }
+}
+
+/// Op that dispatches the input to the sub-op registered for the key computed by a router op.
+/// See [route_by].
+pub struct RouteBy<R: Op, Output> {
+    router: R,
+    routes: HashMap<R::Output, Box<dyn DynOp<R::Input, Output>>>,
+    default: Box<dyn DynOp<R::Input, Output>>,
+}
+
+impl<R, Output> RouteBy<R, Output>
+where
+    R: Op,
+    R::Input: Clone,
+    R::Output: Eq + Hash,
+    Output: Send + Sync,
+{
+    pub fn new(router: R, default: impl Op<Input = R::Input, Output = Output> + 'static) -> Self {
+        Self {
+            router,
+            routes: HashMap::new(),
+            default: Box::new(default),
+        }
+    }
+
+    /// Process the inputs for which the router returns `key` with `op`.
+    pub fn route(
+        mut self,
+        key: R::Output,
+        op: impl Op<Input = R::Input, Output = Output> + 'static,
+    ) -> Self {
+        self.routes.insert(key, Box::new(op));
+        self
+    }
+}
+
+impl<R, Output> Op for RouteBy<R, Output>
+where
+    R: Op,
+    R::Input: Clone,
+    R::Output: Eq + Hash,
+    Output: Send + Sync,
+{
+    type Input = R::Input;
+    type Output = Output;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let key = self.router.call(input.clone()).await;
+
+        self.routes
+            .get(&key)
+            .unwrap_or(&self.default)
+            .call_boxed(input)
+            .await
+    }
+}
+
+/// Create an op that runs the `router` op on the input (e.g.: a closure, or an intent classifier
+/// built from an agent or an extractor) and dispatches the input to the sub-pipeline registered
+/// for the returned key with [RouteBy::route]. Inputs whose key has no registered route are
+/// processed by `default`.
+///
+/// Unlike [conditional!](crate::conditional!), the routes are registered at runtime and
+/// the sub-pipelines can be of different types, as long as they have the same input and output.
+///
+/// # Example
+/// ```rust
+/// use mcp_rig::pipeline::{self, conditional::route_by, map, Op};
+///
+/// #[derive(PartialEq, Eq, Hash)]
+/// enum Intent {
+///     Greeting,
+///     Question,
+///     Other,
+/// }
+///
+/// let router = map(|text: String| {
+///     if text.starts_with("Hello") {
+///         Intent::Greeting
+///     } else if text.ends_with('?') {
+///         Intent::Question
+///     } else {
+///         Intent::Other
+///     }
+/// });
+///
+/// let pipeline = pipeline::new().chain(
+///     route_by(router, map(|_: String| "I don't understand".to_string()))
+///         .route(Intent::Greeting, map(|_: String| "Hi!".to_string()))
+///         .route(Intent::Question, map(|text: String| format!("Good question: {text}"))),
+/// );
+///
+/// let result = pipeline.call("Hello there".to_string()).await;
+/// assert_eq!(result, "Hi!");
+/// ```
+pub fn route_by<R, Output>(
+    router: R,
+    default: impl Op<Input = R::Input, Output = Output> + 'static,
+) -> RouteBy<R, Output>
+where
+    R: Op,
+    R::Input: Clone,
+    R::Output: Eq + Hash,
+    Output: Send + Sync,
+{
+    RouteBy::new(router, default)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::pipeline::*;
@@ -204,4 +389,25 @@ mod tests {
         let result2 = try_conditional.try_call(ExampleEnum::Variant2(3)).await;
         assert_eq!(result2, Ok(6));
     }
+
+    #[tokio::test]
+    async fn test_branch() {
+        let op = conditional::branch(|x: &i32| *x > 0, map(|x: i32| x * 2), map(|x: i32| -x));
+
+        assert_eq!(op.call(2).await, 4);
+        assert_eq!(op.call(-3).await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_route_by() {
+        let router = map(|x: i32| x % 3);
+
+        let op = conditional::route_by(router, map(|x: i32| format!("{x}: default")))
+            .route(0, map(|x: i32| format!("{x}: zero")))
+            .route(1, then(|x: i32| async move { format!("{x}: one") }));
+
+        assert_eq!(op.call(3).await, "3: zero");
+        assert_eq!(op.call(4).await, "4: one");
+        assert_eq!(op.call(5).await, "5: default");
+    }
 }
@@ -0,0 +1,242 @@
+//! This module defines the [MapReduce] and [TryMapReduce] ops, which apply an op to each item of
+//! a collection (e.g.: the chunks of a document) with bounded concurrency and then reduce the
+//! partial results with a second op.
+//!
+//! The [summarize] function builds the standard "summary of summaries" pipeline used to
+//! summarize documents that do not fit in the context window of a model.
+use crate::completion::{self, PromptError};
+
+use super::{
+    agent_ops::{self, Prompt},
+    op::{self, Op},
+    try_op::TryOp,
+};
+
+/// Op that applies the `map` op to each item of the input (at most `concurrency` items at
+/// a time) and then reduces the partial results with the `reduce` op. See [map_reduce].
+pub struct MapReduce<MapOp, ReduceOp> {
+    map: MapOp,
+    reduce: ReduceOp,
+    concurrency: usize,
+}
+
+impl<MapOp, ReduceOp> MapReduce<MapOp, ReduceOp> {
+    pub fn new(map: MapOp, reduce: ReduceOp, concurrency: usize) -> Self {
+        Self {
+            map,
+            reduce,
+            concurrency,
+        }
+    }
+}
+
+impl<MapOp, ReduceOp> Op for MapReduce<MapOp, ReduceOp>
+where
+    MapOp: Op,
+    ReduceOp: Op<Input = Vec<MapOp::Output>>,
+{
+    type Input = Vec<MapOp::Input>;
+    type Output = ReduceOp::Output;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let partials = self.map.batch_call(self.concurrency.max(1), input).await;
+        self.reduce.call(partials).await
+    }
+}
+
+/// Create an op that applies `map` to each item of the input, running at most `concurrency`
+/// of them at once, and then passes the partial results (in the same order as the input)
+/// to `reduce`.
+///
+/// # Example
+/// ```rust
+/// use mcp_rig::pipeline::{self, map, map_reduce::map_reduce, Op};
+///
+/// let pipeline = pipeline::new().chain(map_reduce(
+///     map(|chunk: String| chunk.split_whitespace().count()),
+///     map(|counts: Vec<usize>| counts.into_iter().sum::<usize>()),
+///     4,
+/// ));
+///
+/// let result = pipeline.call(vec!["a b".to_string(), "c d e".to_string()]).await;
+/// assert_eq!(result, 5);
+/// ```
+pub fn map_reduce<MapOp, ReduceOp>(
+    map: MapOp,
+    reduce: ReduceOp,
+    concurrency: usize,
+) -> MapReduce<MapOp, ReduceOp>
+where
+    MapOp: Op,
+    ReduceOp: Op<Input = Vec<MapOp::Output>>,
+{
+    MapReduce::new(map, reduce, concurrency)
+}
+
+/// Same as [MapReduce] but for fallible ops: the partial results are only reduced if the `map`
+/// op succeeds on every item. See [try_map_reduce].
+pub struct TryMapReduce<MapOp, ReduceOp> {
+    map: MapOp,
+    reduce: ReduceOp,
+    concurrency: usize,
+}
+
+impl<MapOp, ReduceOp> TryMapReduce<MapOp, ReduceOp> {
+    pub fn new(map: MapOp, reduce: ReduceOp, concurrency: usize) -> Self {
+        Self {
+            map,
+            reduce,
+            concurrency,
+        }
+    }
+}
+
+impl<MapOp, ReduceOp> Op for TryMapReduce<MapOp, ReduceOp>
+where
+    MapOp: TryOp,
+    ReduceOp: TryOp<Input = Vec<MapOp::Output>, Error = MapOp::Error>,
+{
+    type Input = Vec<MapOp::Input>;
+    type Output = Result<ReduceOp::Output, MapOp::Error>;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let partials = self
+            .map
+            .try_batch_call(self.concurrency.max(1), input)
+            .await?;
+        self.reduce.try_call(partials).await
+    }
+}
+
+/// Same as [map_reduce] but for fallible ops. Fails with the first error returned by
+/// the `map` op, or with the error of the `reduce` op.
+pub fn try_map_reduce<MapOp, ReduceOp>(
+    map: MapOp,
+    reduce: ReduceOp,
+    concurrency: usize,
+) -> TryMapReduce<MapOp, ReduceOp>
+where
+    MapOp: TryOp,
+    ReduceOp: TryOp<Input = Vec<MapOp::Output>, Error = MapOp::Error>,
+{
+    TryMapReduce::new(map, reduce, concurrency)
+}
+
+/// Create a "summary of summaries" op: each chunk of the input is summarized by prompting
+/// `map_agent` (at most `concurrency` chunks at once), then the partial summaries are
+/// combined by prompting `reduce_agent` with all of them.
+///
+/// The instructions (e.g.: "Summarize the following text") should be set in the preambles
+/// of the agents.
+///
+/// # Example
+/// ```rust
+/// use mcp_rig::pipeline::{self, map_reduce::summarize, Op};
+///
+/// let chunk_summarizer = openai_client.agent("gpt-4o-mini")
+///     .preamble("Summarize the following excerpt of a report in a few sentences.")
+///     .build();
+/// let summarizer = openai_client.agent("gpt-4o")
+///     .preamble("Combine the following summaries of the parts of a report into a single summary.")
+///     .build();
+///
+/// let pipeline = pipeline::new().chain(summarize(chunk_summarizer, summarizer, 8));
+///
+/// let summary = pipeline.call(chunks).await?;
+/// ```
+pub fn summarize<P1, P2>(
+    map_agent: P1,
+    reduce_agent: P2,
+    concurrency: usize,
+) -> TryMapReduce<
+    Prompt<P1, String>,
+    impl Op<Input = Vec<String>, Output = Result<String, PromptError>>,
+>
+where
+    P1: completion::Prompt,
+    P2: completion::Prompt,
+{
+    let reduce = op::map(|summaries: Vec<String>| {
+        summaries
+            .into_iter()
+            .enumerate()
+            .map(|(i, summary)| format!("Summary {}:\n{summary}", i + 1))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    })
+    .chain(agent_ops::prompt(reduce_agent));
+
+    try_map_reduce(agent_ops::prompt(map_agent), reduce, concurrency)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        message,
+        pipeline::{agent_ops::tests::MockModel, map},
+    };
+
+    #[tokio::test]
+    async fn test_map_reduce() {
+        let op = map_reduce(
+            map(|x: i32| x * 2),
+            map(|xs: Vec<i32>| xs.iter().map(|x| x.to_string()).collect::<Vec<_>>()),
+            2,
+        );
+
+        let result = op.call(vec![1, 2, 3]).await;
+        assert_eq!(result, vec!["2", "4", "6"]);
+    }
+
+    #[tokio::test]
+    async fn test_try_map_reduce_err() {
+        let op = try_map_reduce(
+            map(|x: i32| {
+                if x > 0 {
+                    Ok(x)
+                } else {
+                    Err(format!("{x} is not positive"))
+                }
+            }),
+            map(|xs: Vec<i32>| Ok::<_, String>(xs.into_iter().sum::<i32>())),
+            2,
+        );
+
+        assert_eq!(op.call(vec![1, 2, 3]).await, Ok(6));
+        assert_eq!(
+            op.call(vec![1, -2, 3]).await,
+            Err("-2 is not positive".to_string())
+        );
+    }
+
+    /// Agent that answers with the number of lines of the prompt
+    struct LineCounter;
+
+    impl completion::Prompt for LineCounter {
+        async fn prompt(&self, prompt: impl Into<message::Message>) -> Result<String, PromptError> {
+            match prompt.into() {
+                message::Message::User { content } => match content.first() {
+                    message::UserContent::Text(message::Text { text }) => {
+                        Ok(text.lines().count().to_string())
+                    }
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_summarize() {
+        let op = summarize(MockModel, LineCounter, 2);
+
+        // Each partial summary is a single line ("Mock response: ..."), plus one header
+        // line per summary and one blank line between summaries
+        let result = op
+            .call(vec!["chunk 1".to_string(), "chunk 2".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(result, "5");
+    }
+}
@@ -63,6 +63,19 @@
 //! assert_eq!(result, "Result: 2, 0");
 //! ```
 //!
+//! To merge the outputs of two concurrent ops with a custom function instead of collecting them
+//! into a tuple, use [join](parallel::join) (or [try_join](parallel::try_join) for fallible ops):
+//! ```rust
+//! use mcp_rig::pipeline::{self, parallel::join, map, Op};
+//!
+//! let pipeline = pipeline::new()
+//!     .chain(join(
+//!         map(|x| x + 1),
+//!         map(|x| x - 1),
+//!         |a, b| format!("Results: {a}, {b}"),
+//!     ));
+//! ```
+//!
 //! Notes:
 //! - The [chain](Op::chain) method is similar to the [map](Op::map) method but it allows
 //!   for chaining arbitrary operations, as long as they implement the [Op] trait.
@@ -88,8 +101,13 @@
 //! ```
 
 pub mod agent_ops;
+pub mod checkpoint;
+pub mod map_reduce;
 pub mod op;
+pub mod retry;
+pub mod trace;
 pub mod try_op;
+pub mod verification;
 #[macro_use]
 pub mod parallel;
 #[macro_use]
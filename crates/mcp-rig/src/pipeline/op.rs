@@ -167,6 +167,52 @@ pub trait Op: Send + Sync {
     {
         Sequential::new(self, Prompt::new(prompt))
     }
+
+    /// Instrument the current op with a `tracing` span named `pipeline_op` which records the
+    /// `name` of the op, the sizes of its input and output and its duration. The executions
+    /// can also be recorded in a [PipelineTracer](super::trace::PipelineTracer) to export the
+    /// executed DAG (see [Traced::tracer]).
+    ///
+    /// # Example
+    /// ```rust
+    /// use mcp_rig::pipeline::{self, Op};
+    ///
+    /// let pipeline = pipeline::new()
+    ///     .map(|name| format!("Find funny nicknames for the following name: {name}!"))
+    ///     .prompt(agent)
+    ///     .traced("nicknames");
+    /// ```
+    fn traced(self, name: &str) -> Traced<Self>
+    where
+        Self::Input: std::fmt::Debug,
+        Self::Output: std::fmt::Debug,
+        Self: Sized,
+    {
+        Traced::new(self, name)
+    }
+
+    /// Save the output of the current op in `store` under the name `stage`, and skip the op
+    /// (returning the saved output instead) if it has already been saved by a previous run.
+    /// See the [checkpoint](super::checkpoint) module.
+    ///
+    /// # Example
+    /// ```rust
+    /// use mcp_rig::pipeline::{self, checkpoint::FileStageStore, Op};
+    ///
+    /// let store = FileStageStore::new("./checkpoints/job-42");
+    ///
+    /// let pipeline = pipeline::new()
+    ///     .chain(crawl.checkpoint("crawl", store.clone()))
+    ///     .map(|pages| pages.len());
+    /// ```
+    fn checkpoint<S>(self, stage: &str, store: S) -> Checkpointed<Self, S>
+    where
+        S: StageStore,
+        Self::Output: serde::Serialize + for<'a> serde::Deserialize<'a>,
+        Self: Sized,
+    {
+        Checkpointed::new(self, stage, store)
+    }
 }
 
 impl<T: Op> Op for &T {
@@ -210,7 +256,11 @@ where
 
 use crate::{completion, vector_store};
 
-use super::agent_ops::{Lookup, Prompt};
+use super::{
+    agent_ops::{Lookup, Prompt},
+    checkpoint::{Checkpointed, StageStore},
+    trace::Traced,
+};
 
 // ================================================================
 // Core Op implementations
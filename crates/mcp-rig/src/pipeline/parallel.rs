@@ -44,6 +44,113 @@ where
     }
 }
 
+/// Op that runs two ops concurrently on the same input and merges their outputs with a function.
+/// See [join].
+pub struct Join<Op1, Op2, F> {
+    op1: Op1,
+    op2: Op2,
+    merger: F,
+}
+
+impl<Op1, Op2, F> Join<Op1, Op2, F> {
+    pub fn new(op1: Op1, op2: Op2, merger: F) -> Self {
+        Self { op1, op2, merger }
+    }
+}
+
+impl<Op1, Op2, F, Output> Op for Join<Op1, Op2, F>
+where
+    Op1: Op,
+    Op1::Input: Clone,
+    Op2: Op<Input = Op1::Input>,
+    F: Fn(Op1::Output, Op2::Output) -> Output + Send + Sync,
+    Output: Send + Sync,
+{
+    type Input = Op1::Input;
+    type Output = Output;
+
+    #[inline]
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let (output1, output2) = join!(self.op1.call(input.clone()), self.op2.call(input));
+        (self.merger)(output1, output2)
+    }
+}
+
+/// Run two ops concurrently on the same input and merge their outputs with `merger`
+/// (e.g.: to combine retrieved documents with a classification of the query).
+///
+/// To merge the outputs of more than two ops, use [parallel!](crate::parallel!) followed
+/// by [map](Op::map).
+///
+/// # Example
+/// ```rust
+/// use mcp_rig::pipeline::{self, parallel::join, map, Op};
+///
+/// let pipeline = pipeline::new().chain(join(
+///     map(|x: i32| x + 1),
+///     map(|x: i32| x * 3),
+///     |a, b| format!("{a} and {b}"),
+/// ));
+///
+/// let result = pipeline.call(1).await;
+/// assert_eq!(result, "2 and 3");
+/// ```
+pub fn join<Op1, Op2, F, Output>(op1: Op1, op2: Op2, merger: F) -> Join<Op1, Op2, F>
+where
+    Op1: Op,
+    Op1::Input: Clone,
+    Op2: Op<Input = Op1::Input>,
+    F: Fn(Op1::Output, Op2::Output) -> Output + Send + Sync,
+    Output: Send + Sync,
+{
+    Join::new(op1, op2, merger)
+}
+
+/// Same as [Join] but for fallible ops: the outputs are only merged if both ops succeed.
+/// See [try_join].
+pub struct TryJoin<Op1, Op2, F> {
+    op1: Op1,
+    op2: Op2,
+    merger: F,
+}
+
+impl<Op1, Op2, F> TryJoin<Op1, Op2, F> {
+    pub fn new(op1: Op1, op2: Op2, merger: F) -> Self {
+        Self { op1, op2, merger }
+    }
+}
+
+impl<Op1, Op2, F, Output> Op for TryJoin<Op1, Op2, F>
+where
+    Op1: TryOp,
+    Op1::Input: Clone,
+    Op2: TryOp<Input = Op1::Input, Error = Op1::Error>,
+    F: Fn(Op1::Output, Op2::Output) -> Output + Send + Sync,
+    Output: Send + Sync,
+{
+    type Input = Op1::Input;
+    type Output = Result<Output, Op1::Error>;
+
+    #[inline]
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let (output1, output2) =
+            try_join!(self.op1.try_call(input.clone()), self.op2.try_call(input))?;
+        Ok((self.merger)(output1, output2))
+    }
+}
+
+/// Same as [join] but for fallible ops. Fails with the error of the first op that fails.
+pub fn try_join<Op1, Op2, F, Output>(op1: Op1, op2: Op2, merger: F) -> TryJoin<Op1, Op2, F>
+where
+    Op1: TryOp,
+    Op1::Input: Clone,
+    Op2: TryOp<Input = Op1::Input, Error = Op1::Error>,
+    F: Fn(Op1::Output, Op2::Output) -> Output + Send + Sync,
+    Output: Send + Sync,
+{
+    TryJoin::new(op1, op2, merger)
+}
+
 // See https://doc.rust-lang.org/src/core/future/join.rs.html#48
 #[macro_export]
 macro_rules! parallel_internal {
@@ -379,6 +486,47 @@ mod tests {
         assert_eq!(result, (1, 2, "1 is the number!".to_string(), true));
     }
 
+    #[tokio::test]
+    async fn test_join() {
+        let pipeline = join(
+            map(|x: i32| x + 1),
+            map(|x: i32| format!("{} is the number!", x)),
+            |a, b| format!("{a}: {b}"),
+        );
+
+        let result = pipeline.call(1).await;
+        assert_eq!(result, "2: 1 is the number!");
+    }
+
+    #[tokio::test]
+    async fn test_join_parallel_macro() {
+        let pipeline = join(
+            parallel!(map(|x: i32| x + 1), map(|x: i32| x * 3)),
+            map(|x: i32| x - 1),
+            |(a, b), c| a + b + c,
+        );
+
+        let result = pipeline.call(1).await;
+        assert_eq!(result, 5);
+    }
+
+    #[tokio::test]
+    async fn test_try_join() {
+        let pipeline = try_join(
+            map(|x: i32| Ok::<_, String>(x + 1)),
+            map(|x: i32| Ok::<_, String>(x * 3)),
+            |a, b| a + b,
+        );
+        assert_eq!(pipeline.try_call(1).await, Ok(5));
+
+        let pipeline = try_join(
+            map(|x: i32| Ok::<_, String>(x + 1)),
+            map(|x: i32| Err::<i32, _>(format!("{} is the number!", x))),
+            |a, b| a + b,
+        );
+        assert_eq!(pipeline.call(1).await, Err("1 is the number!".to_string()));
+    }
+
     #[tokio::test]
     async fn test_try_parallel_chain_compile_check() {
         let chain = pipeline::new().chain(
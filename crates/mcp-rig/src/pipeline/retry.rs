@@ -0,0 +1,341 @@
+//! This module defines the [Retry] and [Timeout] adapters, which can wrap any fallible pipeline
+//! stage (completion, tool call, retrieval, etc.) to retry it with exponential backoff or to
+//! bound its duration. See [TryOp::with_retry] and [TryOp::with_timeout].
+//!
+//! Both adapters fail with typed errors ([RetryError] and [TimeoutError]) that carry the name of
+//! the stage that failed, so that errors of multi-stage pipelines can be traced back to their origin.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use mcp_rig::pipeline::{self, retry::RetryPolicy, Op, TryOp};
+//!
+//! let agent = openai_client.agent("gpt-4o").build();
+//!
+//! let pipeline = pipeline::new()
+//!     .prompt(agent)
+//!     .with_timeout(Duration::from_secs(30))
+//!     .with_retry(RetryPolicy::new(3))
+//!     .named("completion");
+//!
+//! let result = pipeline.call("What is a flurbo?".to_string()).await;
+//! ```
+use std::{pin::pin, time::Duration};
+
+use futures::future::{self, Either};
+use futures_timer::Delay;
+
+use super::{op::Op, try_op::TryOp};
+
+/// Policy of the [Retry] adapter: how many times a failing stage is retried and how long to
+/// wait between two attempts. The delay starts at `initial_backoff` and is multiplied by
+/// `multiplier` after each retry, up to `max_backoff`.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_retries: usize,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy that retries a failing stage up to `max_retries` times,
+    /// with the default backoff.
+    pub fn new(max_retries: usize) -> Self {
+        Self {
+            max_retries,
+            ..Default::default()
+        }
+    }
+
+    /// Set the delay before the first retry and the maximum delay between two attempts.
+    pub fn backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Set the factor by which the delay is multiplied after each retry.
+    ///
+    /// # Panics
+    /// Panics if `multiplier` is negative or not finite.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        assert!(
+            multiplier.is_finite() && multiplier >= 0.0,
+            "The retry multiplier must be finite and non-negative, got {multiplier}"
+        );
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Delay before the retry number `retry` (starting at 0), capped at `max_backoff`.
+    /// Falls back to `max_backoff` if the delay can't be computed (e.g.: `multiplier` was set to
+    /// a negative value through the public field).
+    pub fn delay(&self, retry: usize) -> Duration {
+        let exponent = i32::try_from(retry).unwrap_or(i32::MAX);
+        let delay = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        Duration::try_from_secs_f64(delay)
+            .map(|delay| delay.min(self.max_backoff))
+            .unwrap_or(self.max_backoff)
+    }
+}
+
+/// Error returned by a [Retry] stage which failed on every attempt.
+#[derive(Debug, thiserror::Error)]
+#[error("Stage `{stage}` failed after {attempts} attempt(s): {error}")]
+pub struct RetryError<E> {
+    /// Name of the stage (see [Retry::named])
+    pub stage: String,
+    /// Number of attempts made
+    pub attempts: usize,
+    /// Error of the last attempt
+    pub error: E,
+}
+
+/// Error returned by a [Timeout] stage.
+#[derive(Debug, thiserror::Error)]
+pub enum TimeoutError<E> {
+    #[error("Stage `{stage}` timed out after {timeout:?}")]
+    Elapsed { stage: String, timeout: Duration },
+
+    #[error("{0}")]
+    Failed(E),
+}
+
+/// Adapter that retries a fallible op according to a [RetryPolicy]. See [TryOp::with_retry].
+pub struct Retry<T: TryOp> {
+    op: T,
+    policy: RetryPolicy,
+    stage: String,
+    retry_if: Option<Box<dyn Fn(&T::Error) -> bool + Send + Sync>>,
+}
+
+impl<T: TryOp> Retry<T> {
+    pub fn new(op: T, policy: RetryPolicy) -> Self {
+        Self {
+            op,
+            policy,
+            stage: std::any::type_name::<T>().to_string(),
+            retry_if: None,
+        }
+    }
+
+    /// Set the name of the stage, reported in [RetryError] and in logs.
+    /// Defaults to the type name of the wrapped op.
+    pub fn named(mut self, stage: &str) -> Self {
+        self.stage = stage.to_string();
+        self
+    }
+
+    /// Only retry the errors for which `predicate` returns true (e.g.: to not retry
+    /// invalid requests). By default, all errors are retried.
+    pub fn retry_if(
+        mut self,
+        predicate: impl Fn(&T::Error) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.retry_if = Some(Box::new(predicate));
+        self
+    }
+}
+
+impl<T> Op for Retry<T>
+where
+    T: TryOp,
+    T::Input: Clone,
+{
+    type Input = T::Input;
+    type Output = Result<T::Output, RetryError<T::Error>>;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let mut retries = 0;
+
+        loop {
+            match self.op.try_call(input.clone()).await {
+                Ok(output) => return Ok(output),
+                Err(error)
+                    if retries < self.policy.max_retries
+                        && self
+                            .retry_if
+                            .as_ref()
+                            .map_or(true, |predicate| predicate(&error)) =>
+                {
+                    let delay = self.policy.delay(retries);
+                    retries += 1;
+
+                    tracing::warn!(target: "rig",
+                        "Stage `{}` failed (attempt {}/{}), retrying in {:?}",
+                        self.stage,
+                        retries,
+                        self.policy.max_retries + 1,
+                        delay
+                    );
+
+                    Delay::new(delay).await;
+                }
+                Err(error) => {
+                    return Err(RetryError {
+                        stage: self.stage.clone(),
+                        attempts: retries + 1,
+                        error,
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Adapter that fails a fallible op if it does not complete within a given duration.
+/// See [TryOp::with_timeout].
+pub struct Timeout<T> {
+    op: T,
+    timeout: Duration,
+    stage: String,
+}
+
+impl<T: TryOp> Timeout<T> {
+    pub fn new(op: T, timeout: Duration) -> Self {
+        Self {
+            op,
+            timeout,
+            stage: std::any::type_name::<T>().to_string(),
+        }
+    }
+
+    /// Set the name of the stage, reported in [TimeoutError].
+    /// Defaults to the type name of the wrapped op.
+    pub fn named(mut self, stage: &str) -> Self {
+        self.stage = stage.to_string();
+        self
+    }
+}
+
+impl<T: TryOp> Op for Timeout<T> {
+    type Input = T::Input;
+    type Output = Result<T::Output, TimeoutError<T::Error>>;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let op = pin!(self.op.try_call(input));
+
+        match future::select(op, Delay::new(self.timeout)).await {
+            Either::Left((result, _)) => result.map_err(TimeoutError::Failed),
+            Either::Right(_) => Err(TimeoutError::Elapsed {
+                stage: self.stage.clone(),
+                timeout: self.timeout,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+    use crate::pipeline::{map, then};
+
+    #[tokio::test]
+    async fn test_retry() {
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let op = map({
+            let calls = calls.clone();
+            move |x: i32| {
+                if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err("transient error")
+                } else {
+                    Ok(x + 1)
+                }
+            }
+        })
+        .with_retry(RetryPolicy::new(2).backoff(Duration::ZERO, Duration::ZERO));
+
+        assert_eq!(op.call(1).await.unwrap(), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_exhausted() {
+        let op = map(|_: i32| Err::<i32, _>("permanent error"))
+            .with_retry(RetryPolicy::new(2).backoff(Duration::ZERO, Duration::ZERO))
+            .named("lookup");
+
+        let error = op.call(1).await.unwrap_err();
+        assert_eq!(error.stage, "lookup");
+        assert_eq!(error.attempts, 3);
+        assert_eq!(error.error, "permanent error");
+    }
+
+    #[tokio::test]
+    async fn test_retry_if() {
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let op = map({
+            let calls = calls.clone();
+            move |_: i32| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err::<i32, _>("invalid request")
+            }
+        })
+        .with_retry(RetryPolicy::new(5).backoff(Duration::ZERO, Duration::ZERO))
+        .retry_if(|error| *error != "invalid request");
+
+        assert_eq!(op.call(1).await.unwrap_err().attempts, 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let op = then(|x: i32| async move {
+            Delay::new(Duration::from_millis(if x > 0 { 500 } else { 0 })).await;
+            Ok::<_, String>(x)
+        })
+        .with_timeout(Duration::from_millis(50))
+        .named("completion");
+
+        assert_eq!(op.call(0).await.unwrap(), 0);
+        assert!(matches!(
+            op.call(1).await,
+            Err(TimeoutError::Elapsed { stage, .. }) if stage == "completion"
+        ));
+    }
+
+    #[test]
+    fn test_retry_policy_delay() {
+        let policy = RetryPolicy::new(10).backoff(Duration::from_secs(1), Duration::from_secs(5));
+
+        assert_eq!(policy.delay(0), Duration::from_secs(1));
+        assert_eq!(policy.delay(2), Duration::from_secs(4));
+        assert_eq!(policy.delay(3), Duration::from_secs(5));
+
+        // The delay is capped instead of overflowing
+        assert_eq!(policy.delay(usize::MAX), Duration::from_secs(5));
+        let policy = policy.multiplier(1e300);
+        assert_eq!(policy.delay(5), Duration::from_secs(5));
+
+        let policy = RetryPolicy {
+            multiplier: f64::NAN,
+            ..policy
+        };
+        assert_eq!(policy.delay(1), Duration::from_secs(5));
+    }
+
+    #[test]
+    #[should_panic(expected = "The retry multiplier must be finite and non-negative")]
+    fn test_retry_policy_negative_multiplier() {
+        let _ = RetryPolicy::new(3).multiplier(-2.0);
+    }
+}
@@ -0,0 +1,352 @@
+//! This module defines the [Traced] adapter, which instruments a pipeline op with a `tracing`
+//! span (op name, input/output sizes, duration), and the [PipelineTracer], which collects the
+//! executions of traced ops so that the executed DAG can be exported to JSON or Graphviz
+//! for debugging. See [Op::traced].
+//!
+//! Model usage (e.g.: token counts) is reported by the spans of the providers, which are
+//! nested in the spans of the traced ops.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::pipeline::{self, trace::PipelineTracer, Op};
+//!
+//! let tracer = PipelineTracer::new();
+//!
+//! let pipeline = pipeline::new()
+//!     .chain(lookup(index, 3).traced("retrieve").tracer(&tracer))
+//!     .map(|docs| format!("Context: {docs:?}"))
+//!     .chain(prompt(agent).traced("answer").tracer(&tracer));
+//!
+//! pipeline.call("What is a flurbo?").await;
+//!
+//! // Render with `dot -Tsvg pipeline.dot > pipeline.svg`
+//! std::fs::write("pipeline.dot", tracer.dag().to_dot())?;
+//! ```
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+use serde::Serialize;
+use tracing::Instrument;
+use web_time::Instant;
+
+use super::op::Op;
+
+/// Execution of a traced op, as recorded by a [PipelineTracer].
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct OpExecution {
+    /// Index of the execution in the tracer
+    pub id: usize,
+    /// Name of the op (see [Op::traced])
+    pub name: String,
+    /// Length of the debug representation of the input of the op
+    pub input_size: usize,
+    /// Length of the debug representation of the output of the op
+    pub output_size: usize,
+    /// Start of the execution, in milliseconds since the creation of the tracer
+    pub start_ms: f64,
+    /// Duration of the execution, in milliseconds
+    pub duration_ms: f64,
+}
+
+impl OpExecution {
+    fn end_ms(&self) -> f64 {
+        self.start_ms + self.duration_ms
+    }
+}
+
+/// Collector of the executions of traced ops. Cloning a tracer returns a handle
+/// to the same collector.
+#[derive(Clone)]
+pub struct PipelineTracer {
+    start: Instant,
+    executions: Arc<Mutex<Vec<OpExecution>>>,
+}
+
+impl Default for PipelineTracer {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            executions: Arc::new(Mutex::new(vec![])),
+        }
+    }
+}
+
+impl PipelineTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Executions recorded so far, in order of completion.
+    pub fn executions(&self) -> Vec<OpExecution> {
+        self.executions
+            .lock()
+            .expect("Tracer lock poisoned")
+            .clone()
+    }
+
+    /// Remove the recorded executions.
+    pub fn clear(&self) {
+        self.executions
+            .lock()
+            .expect("Tracer lock poisoned")
+            .clear();
+    }
+
+    /// Build the DAG of the recorded executions. An execution depends on the executions
+    /// that completed right before it started: executions that overlap in time (i.e.: that ran
+    /// concurrently) are on parallel branches.
+    pub fn dag(&self) -> ExecutionDag {
+        let nodes = self.executions();
+
+        // Adjacency map of the "happened before" relation
+        let before = |a: &OpExecution, b: &OpExecution| a.id < b.id && a.end_ms() <= b.start_ms;
+        let mut successors: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut in_degree: HashMap<usize, usize> = nodes.iter().map(|node| (node.id, 0)).collect();
+        for a in &nodes {
+            for b in nodes.iter().filter(|b| before(a, b)) {
+                successors.entry(a.id).or_default().push(b.id);
+                *in_degree.entry(b.id).or_default() += 1;
+            }
+        }
+
+        // Visit the executions in topological order (Kahn's algorithm), keeping the ancestors
+        // of each one. The closest predecessors of an execution are visited first, so that the
+        // transitive edges (i.e.: from the ancestors of another predecessor) are skipped.
+        let mut queue = nodes
+            .iter()
+            .map(|node| node.id)
+            .filter(|id| in_degree[id] == 0)
+            .collect::<VecDeque<_>>();
+        let mut rank = HashMap::new();
+        let mut predecessors: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut ancestors: HashMap<usize, HashSet<usize>> = HashMap::new();
+        let mut edges = vec![];
+
+        while let Some(id) = queue.pop_front() {
+            let mut closest = predecessors.remove(&id).unwrap_or_default();
+            closest.sort_by_key(|predecessor| Reverse(rank[predecessor]));
+
+            let mut reachable = HashSet::new();
+            for predecessor in closest {
+                if reachable.insert(predecessor) {
+                    edges.push((predecessor, id));
+                }
+                reachable.extend(&ancestors[&predecessor]);
+            }
+            rank.insert(id, rank.len());
+            ancestors.insert(id, reachable);
+
+            for successor in successors.remove(&id).unwrap_or_default() {
+                predecessors.entry(successor).or_default().push(id);
+                let degree = in_degree
+                    .get_mut(&successor)
+                    .expect("Successor should be a node");
+                *degree -= 1;
+                if *degree == 0 {
+                    queue.push_back(successor);
+                }
+            }
+        }
+        edges.sort_unstable();
+
+        ExecutionDag { nodes, edges }
+    }
+
+    fn record(&self, name: &str, input_size: usize, output_size: usize, start: Instant) {
+        let mut executions = self.executions.lock().expect("Tracer lock poisoned");
+        let id = executions.len();
+
+        executions.push(OpExecution {
+            id,
+            name: name.to_string(),
+            input_size,
+            output_size,
+            start_ms: start.saturating_duration_since(self.start).as_secs_f64() * 1000.0,
+            duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+        });
+    }
+}
+
+/// DAG of the executions recorded by a [PipelineTracer]. Edges are pairs of execution ids.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct ExecutionDag {
+    pub nodes: Vec<OpExecution>,
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl ExecutionDag {
+    /// Export the DAG as JSON.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("DAG should be serializable")
+    }
+
+    /// Export the DAG in the Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph pipeline {\n    rankdir=LR;\n    node [shape=box];\n");
+
+        for node in &self.nodes {
+            dot.push_str(&format!(
+                "    n{} [label=\"{}\\n{:.1} ms | in: {} | out: {}\"];\n",
+                node.id,
+                node.name.replace('"', "\\\""),
+                node.duration_ms,
+                node.input_size,
+                node.output_size
+            ));
+        }
+        for (from, to) in &self.edges {
+            dot.push_str(&format!("    n{from} -> n{to};\n"));
+        }
+
+        dot.push('}');
+        dot
+    }
+}
+
+/// Adapter that instruments an op with a `tracing` span and optionally records its executions
+/// in a [PipelineTracer]. See [Op::traced].
+pub struct Traced<T> {
+    op: T,
+    name: String,
+    tracer: Option<PipelineTracer>,
+}
+
+impl<T: Op> Traced<T> {
+    pub fn new(op: T, name: &str) -> Self {
+        Self {
+            op,
+            name: name.to_string(),
+            tracer: None,
+        }
+    }
+
+    /// Record the executions of the op in `tracer`.
+    pub fn tracer(mut self, tracer: &PipelineTracer) -> Self {
+        self.tracer = Some(tracer.clone());
+        self
+    }
+}
+
+impl<T> Op for Traced<T>
+where
+    T: Op,
+    T::Input: Debug,
+    T::Output: Debug,
+{
+    type Input = T::Input;
+    type Output = T::Output;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let span = tracing::info_span!(target: "rig",
+            "pipeline_op",
+            op = %self.name,
+            input_size = tracing::field::Empty,
+            output_size = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+
+        // Sizes are only computed if they are used
+        let measure = self.tracer.is_some() || !span.is_disabled();
+        let input_size = if measure {
+            format!("{input:?}").len()
+        } else {
+            0
+        };
+
+        let start = Instant::now();
+        let output = self.op.call(input).instrument(span.clone()).await;
+        let output_size = if measure {
+            format!("{output:?}").len()
+        } else {
+            0
+        };
+
+        span.record("input_size", input_size);
+        span.record("output_size", output_size);
+        span.record("duration_ms", start.elapsed().as_secs_f64() * 1000.0);
+
+        if let Some(tracer) = &self.tracer {
+            tracer.record(&self.name, input_size, output_size, start);
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parallel,
+        pipeline::{self, map},
+    };
+
+    #[tokio::test]
+    async fn test_traced_dag() {
+        let tracer = PipelineTracer::new();
+
+        let pipeline = pipeline::new()
+            .chain(map(|x: i32| x + 1).traced("add").tracer(&tracer))
+            .chain(parallel!(
+                map(|x: i32| x * 2).traced("double").tracer(&tracer),
+                map(|x: i32| x.to_string()).traced("format").tracer(&tracer)
+            ))
+            .chain(
+                map(|(x, s): (i32, String)| format!("{x} {s}"))
+                    .traced("join")
+                    .tracer(&tracer),
+            );
+
+        let result = pipeline.call(1).await;
+        assert_eq!(result, "4 2");
+
+        let dag = tracer.dag();
+        let names = dag
+            .nodes
+            .iter()
+            .map(|node| node.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names.len(), 4);
+        assert_eq!(names[0], "add");
+        assert_eq!(names[3], "join");
+        assert_eq!(dag.nodes[0].input_size, 1);
+        assert_eq!(dag.nodes[3].output_size, "\"4 2\"".len());
+
+        // Every edge goes forward in time and the last op is reachable from the first one
+        assert!(dag.edges.iter().all(|(from, to)| from < to));
+        assert!(dag.edges.iter().any(|(from, _)| *from == 0));
+        assert!(dag.edges.iter().any(|(_, to)| *to == 3));
+
+        let dot = dag.to_dot();
+        assert!(dot.starts_with("digraph pipeline {"));
+        assert!(dot.contains("n0 [label=\"add"));
+        assert_eq!(dag.to_json()["nodes"][3]["name"], "join");
+    }
+
+    #[test]
+    fn test_dag_edges() {
+        let tracer = PipelineTracer::new();
+        let execution = |id: usize, start_ms: f64, duration_ms: f64| OpExecution {
+            id,
+            name: format!("op{id}"),
+            input_size: 0,
+            output_size: 0,
+            start_ms,
+            duration_ms,
+        };
+        // 0 -> (1 || 2) -> 3, with 2 taking longer than 1
+        *tracer.executions.lock().unwrap() = vec![
+            execution(0, 0.0, 1.0),
+            execution(1, 1.0, 1.0),
+            execution(2, 1.0, 3.0),
+            execution(3, 4.0, 1.0),
+        ];
+
+        // The transitive edges (e.g.: 0 -> 3) are dropped
+        assert_eq!(tracer.dag().edges, vec![(0, 1), (0, 2), (1, 3), (2, 3)]);
+    }
+}
@@ -1,10 +1,14 @@
-use std::future::Future;
+use std::{future::Future, time::Duration};
 
 use futures::stream;
 #[allow(unused_imports)] // Needed since this is used in a macro rule
 use futures::try_join;
 
-use super::op::{self};
+use super::{
+    checkpoint::{StageStore, TryCheckpointed},
+    op::{self},
+    retry::{Retry, RetryPolicy, Timeout},
+};
 
 // ================================================================
 // Core TryOp trait
@@ -186,6 +190,61 @@ pub trait TryOp: Send + Sync {
     {
         TrySequential::new(self, op)
     }
+
+    /// Retry the current op according to the given [RetryPolicy] when it fails.
+    /// The input must be cloneable since it is passed to each attempt.
+    ///
+    /// # Example
+    /// ```rust
+    /// use mcp_rig::pipeline::{self, retry::RetryPolicy, TryOp};
+    ///
+    /// let op = pipeline::new()
+    ///     .lookup::<_, _, Document>(index, 5)
+    ///     .with_retry(RetryPolicy::new(3))
+    ///     .named("retrieval");
+    ///
+    /// // On failure, the error indicates which stage exhausted its retries
+    /// let result = op.try_call("What is a flurbo?".to_string()).await;
+    /// ```
+    fn with_retry(self, policy: RetryPolicy) -> Retry<Self>
+    where
+        Self::Input: Clone,
+        Self: Sized,
+    {
+        Retry::new(self, policy)
+    }
+
+    /// Fail the current op with [TimeoutError::Elapsed](super::retry::TimeoutError::Elapsed)
+    /// if it does not complete within `timeout`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::time::Duration;
+    /// use mcp_rig::pipeline::{self, TryOp};
+    ///
+    /// let op = pipeline::new()
+    ///     .prompt(agent)
+    ///     .with_timeout(Duration::from_secs(30));
+    ///
+    /// let result = op.try_call("What is a flurbo?".to_string()).await;
+    /// ```
+    fn with_timeout(self, timeout: Duration) -> Timeout<Self>
+    where
+        Self: Sized,
+    {
+        Timeout::new(self, timeout)
+    }
+
+    /// Same as [Op::checkpoint](op::Op::checkpoint) but for fallible ops: only successful
+    /// outputs are saved, so a stage that failed is executed again on the next run.
+    fn checkpoint_ok<S>(self, stage: &str, store: S) -> TryCheckpointed<Self, S>
+    where
+        S: StageStore,
+        Self::Output: serde::Serialize + for<'a> serde::Deserialize<'a>,
+        Self: Sized,
+    {
+        TryCheckpointed::new(self, stage, store)
+    }
 }
 
 impl<Op, T, E> TryOp for Op
@@ -0,0 +1,450 @@
+//! This module defines ops verifying the answers of RAG pipelines against their sources, for
+//! the deployments where an unsupported answer is costly (e.g.: legal, medical or financial).
+//!
+//! The verification follows the Chain-of-Verification method:
+//! 1. [plan_verification]: the claims of the answer are turned into yes/no verification
+//!    questions (whose answer is "yes" if the claim is true),
+//! 2. [answer_verification]: each question is answered independently, against the sources
+//!    only (i.e.: without the answer, so the verifier isn't biased by it),
+//! 3. [check_citations]: the citations of the answer (e.g.: `[doc-1]`) which don't match any
+//!    source are flagged.
+//!
+//! The ops pass a [Verification] along, whose [VerificationReport] lists the verdict of each
+//! question. The [verify] function chains the three ops and returns the answer alongside its
+//! report, as [Verified].
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::pipeline::{self, verification::{verify, Verification}, Op};
+//!
+//! let planner = openai_client.agent("gpt-4o-mini").build();
+//! let verifier = openai_client.agent("gpt-4o").build();
+//!
+//! let pipeline = pipeline::new().chain(verify(planner, verifier, 4));
+//!
+//! let verified = pipeline
+//!     .call(
+//!         Verification::new(question, answer)
+//!             .sources(docs.into_iter().map(|(_, id, doc): (f64, String, String)| (id, doc))),
+//!     )
+//!     .await?;
+//! if !verified.report.is_verified() {
+//!     for check in verified.report.unsupported() {
+//!         println!("Unsupported claim: {} ({:?})", check.question, check.verdict);
+//!     }
+//! }
+//! ```
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::completion::{self, PromptError};
+
+use super::Op;
+
+/// Document an answer is grounded on.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Source {
+    pub id: String,
+    pub text: String,
+}
+
+/// Verdict of the sources on a verification question.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// The sources support the claim (i.e.: answer "yes")
+    Supported,
+    /// The sources contradict the claim (i.e.: answer "no")
+    Contradicted,
+    /// The sources don't answer the question
+    NotFound,
+}
+
+/// Verification question of a claim of the answer, answered against the sources.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Check {
+    pub question: String,
+    pub verdict: Verdict,
+    /// IDs of the sources the verdict is based on
+    pub sources: Vec<String>,
+    /// Answer of the verifier (i.e.: its explanation)
+    pub answer: String,
+}
+
+/// Report of the verification of an answer.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct VerificationReport {
+    pub checks: Vec<Check>,
+    /// Citations of the answer which don't match any source
+    pub unknown_citations: Vec<String>,
+}
+
+impl VerificationReport {
+    /// Checks of the claims which aren't supported by the sources
+    pub fn unsupported(&self) -> impl Iterator<Item = &Check> {
+        self.checks
+            .iter()
+            .filter(|check| check.verdict != Verdict::Supported)
+    }
+
+    /// Whether all the claims are supported by the sources, and all the citations are valid
+    pub fn is_verified(&self) -> bool {
+        self.unsupported().next().is_none() && self.unknown_citations.is_empty()
+    }
+}
+
+/// Answer to a question, along with its sources, being verified.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Verification {
+    pub question: String,
+    pub answer: String,
+    pub sources: Vec<Source>,
+    /// Verification questions of the claims of the answer (see [plan_verification])
+    pub questions: Vec<String>,
+    pub report: VerificationReport,
+}
+
+impl Verification {
+    pub fn new(question: impl Into<String>, answer: impl Into<String>) -> Self {
+        Self {
+            question: question.into(),
+            answer: answer.into(),
+            sources: vec![],
+            questions: vec![],
+            report: VerificationReport::default(),
+        }
+    }
+
+    /// Add the source `id` to the sources of the answer
+    pub fn source(mut self, id: impl Into<String>, text: impl Into<String>) -> Self {
+        self.sources.push(Source {
+            id: id.into(),
+            text: text.into(),
+        });
+        self
+    }
+
+    /// Add the sources `(id, text)` to the sources of the answer
+    pub fn sources(mut self, sources: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.sources
+            .extend(sources.into_iter().map(|(id, text)| Source { id, text }));
+        self
+    }
+
+    /// Sources formatted for the prompts
+    fn formatted_sources(&self) -> String {
+        self.sources
+            .iter()
+            .map(|source| format!("<source id=\"{}\">\n{}\n</source>", source.id, source.text))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Answer alongside its verification report (see [verify]).
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Verified {
+    pub answer: String,
+    pub report: VerificationReport,
+}
+
+impl From<Verification> for Verified {
+    fn from(verification: Verification) -> Self {
+        Self {
+            answer: verification.answer,
+            report: verification.report,
+        }
+    }
+}
+
+/// Op turning the claims of the answer into verification questions. See [plan_verification].
+pub struct PlanVerification<P> {
+    planner: P,
+}
+
+impl<P> Op for PlanVerification<P>
+where
+    P: completion::Prompt,
+{
+    type Input = Verification;
+    type Output = Result<Verification, PromptError>;
+
+    async fn call(&self, mut input: Self::Input) -> Self::Output {
+        let prompt = format!(
+            "List the factual claims of the answer below as yes/no verification questions, \
+            phrased so that the answer to each question is \"yes\" if the claim is true. Write \
+            one question per line, and nothing else.\n\n\
+            <question>\n{}\n</question>\n\n<answer>\n{}\n</answer>",
+            input.question, input.answer
+        );
+        let questions = self.planner.prompt(prompt).await?;
+
+        input.questions = questions
+            .lines()
+            .map(|line| {
+                line.trim()
+                    .trim_start_matches(|c: char| c.is_ascii_digit() || "-*.) ".contains(c))
+                    .trim()
+            })
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect();
+        Ok(input)
+    }
+}
+
+/// Create an op turning the claims of the answer into verification questions, by prompting
+/// `planner`.
+pub fn plan_verification<P>(planner: P) -> PlanVerification<P>
+where
+    P: completion::Prompt,
+{
+    PlanVerification { planner }
+}
+
+/// Op answering the verification questions against the sources. See [answer_verification].
+pub struct AnswerVerification<P> {
+    verifier: P,
+    concurrency: usize,
+}
+
+impl<P> AnswerVerification<P>
+where
+    P: completion::Prompt,
+{
+    async fn check(&self, sources: &str, question: &str) -> Result<Check, PromptError> {
+        let prompt = format!(
+            "Answer the yes/no question below using only the following sources, not your own \
+            knowledge.\n\n{sources}\n\n<question>\n{question}\n</question>\n\n\
+            Reply in the following format:\n\
+            VERDICT: YES if the sources say yes, NO if they say no, NOT_FOUND if they don't say\n\
+            SOURCES: comma-separated IDs of the sources of the verdict\n\
+            ANSWER: short explanation"
+        );
+        let reply = self.verifier.prompt(prompt).await?;
+        Ok(parse_check(question, &reply))
+    }
+}
+
+impl<P> Op for AnswerVerification<P>
+where
+    P: completion::Prompt,
+{
+    type Input = Verification;
+    type Output = Result<Verification, PromptError>;
+
+    async fn call(&self, mut input: Self::Input) -> Self::Output {
+        let sources = input.formatted_sources();
+        let checks = stream::iter(input.questions.iter())
+            .map(|question| self.check(&sources, question))
+            .buffered(self.concurrency.max(1))
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        // The verifier may cite sources which don't exist
+        input.report.checks = checks
+            .into_iter()
+            .map(|mut check| {
+                check
+                    .sources
+                    .retain(|id| input.sources.iter().any(|source| &source.id == id));
+                check
+            })
+            .collect();
+        Ok(input)
+    }
+}
+
+/// Create an op answering the verification questions against the sources only, by prompting
+/// `verifier` (at most `concurrency` questions at once). The verdicts are added to the report.
+pub fn answer_verification<P>(verifier: P, concurrency: usize) -> AnswerVerification<P>
+where
+    P: completion::Prompt,
+{
+    AnswerVerification {
+        verifier,
+        concurrency,
+    }
+}
+
+/// Parse the reply of the verifier to `question`. A reply without a verdict is `NotFound`.
+fn parse_check(question: &str, reply: &str) -> Check {
+    let field = |name: &str| {
+        reply.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim().eq_ignore_ascii_case(name)).then(|| value.trim().to_string())
+        })
+    };
+
+    let verdict = match field("VERDICT")
+        .as_deref()
+        .map(str::to_uppercase)
+        .as_deref()
+    {
+        Some(verdict) if verdict.starts_with("YES") => Verdict::Supported,
+        Some(verdict) if verdict.starts_with("NO") && !verdict.starts_with("NOT") => {
+            Verdict::Contradicted
+        }
+        _ => Verdict::NotFound,
+    };
+    let sources = field("SOURCES")
+        .map(|sources| {
+            sources
+                .split(',')
+                .map(|id| id.trim().trim_matches(['[', ']', '"']).to_string())
+                .filter(|id| !id.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Check {
+        question: question.to_string(),
+        verdict,
+        sources,
+        answer: field("ANSWER").unwrap_or_else(|| reply.trim().to_string()),
+    }
+}
+
+/// Op flagging the citations of the answer which don't match any source. See [check_citations].
+pub struct CheckCitations;
+
+impl Op for CheckCitations {
+    type Input = Verification;
+    type Output = Verification;
+
+    async fn call(&self, mut input: Self::Input) -> Self::Output {
+        input.report.unknown_citations = citations(&input.answer)
+            .filter(|citation| !input.sources.iter().any(|source| source.id == *citation))
+            .map(String::from)
+            .fold(vec![], |mut unknown, citation| {
+                if !unknown.contains(&citation) {
+                    unknown.push(citation);
+                }
+                unknown
+            });
+        input
+    }
+}
+
+/// Create an op flagging the citations of the answer (i.e.: the source IDs between square
+/// brackets, e.g.: `[doc-1]` or `[doc-1, doc-2]`) which don't match any source.
+pub fn check_citations() -> CheckCitations {
+    CheckCitations
+}
+
+/// Citations of `text` (i.e.: the comma-separated IDs between square brackets)
+fn citations(text: &str) -> impl Iterator<Item = &str> {
+    text.split('[')
+        .skip(1)
+        .filter_map(|part| part.split_once(']').map(|(citation, _)| citation))
+        .flat_map(|citation| citation.split(','))
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+}
+
+/// Op verifying an answer against its sources. See [verify].
+pub struct Verify<P1, P2> {
+    plan: PlanVerification<P1>,
+    answer: AnswerVerification<P2>,
+}
+
+impl<P1, P2> Op for Verify<P1, P2>
+where
+    P1: completion::Prompt,
+    P2: completion::Prompt,
+{
+    type Input = Verification;
+    type Output = Result<Verified, PromptError>;
+
+    async fn call(&self, input: Self::Input) -> Self::Output {
+        let verification = self.plan.call(input).await?;
+        let verification = self.answer.call(verification).await?;
+        let verification = CheckCitations.call(verification).await;
+
+        if !verification.report.is_verified() {
+            tracing::warn!(target: "rig",
+                "Answer not verified: {} unsupported claims, {} unknown citations",
+                verification.report.unsupported().count(),
+                verification.report.unknown_citations.len()
+            );
+        }
+        Ok(verification.into())
+    }
+}
+
+/// Create a Chain-of-Verification op: the claims of the answer are turned into verification
+/// questions by `planner`, answered against the sources by `verifier` (at most `concurrency`
+/// at once), and the citations of the answer are checked. Returns the answer alongside its
+/// [VerificationReport].
+pub fn verify<P1, P2>(planner: P1, verifier: P2, concurrency: usize) -> Verify<P1, P2>
+where
+    P1: completion::Prompt,
+    P2: completion::Prompt,
+{
+    Verify {
+        plan: plan_verification(planner),
+        answer: answer_verification(verifier, concurrency),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::{self, MockResponse};
+
+    #[tokio::test]
+    async fn test_verify() {
+        let planner = mock::CompletionModel::new().default_response(MockResponse::text(
+            "1. Are flurbos green?\n2. Do glarks eat flurbos?\n",
+        ));
+        let verifier = mock::CompletionModel::new()
+            .when_prompt_contains(
+                "Are flurbos green?",
+                MockResponse::text(
+                    "VERDICT: YES\nSOURCES: doc-1, doc-7\nANSWER: Flurbos are green.",
+                ),
+            )
+            .default_response(MockResponse::text(
+                "VERDICT: NOT_FOUND\nSOURCES:\nANSWER: The sources don't say.",
+            ));
+
+        let op = verify(planner.agent().build(), verifier.agent().build(), 2);
+        let verified = op
+            .call(
+                Verification::new(
+                    "What are flurbos?",
+                    "Flurbos are green [doc-1] and eaten by glarks [doc-9].",
+                )
+                .source("doc-1", "Flurbos are green alien fruits."),
+            )
+            .await
+            .unwrap();
+
+        let report = verified.report;
+        assert!(!report.is_verified());
+        assert_eq!(report.checks.len(), 2);
+        assert_eq!(report.checks[0].verdict, Verdict::Supported);
+        assert_eq!(report.checks[0].sources, vec!["doc-1"]);
+        assert_eq!(
+            report
+                .unsupported()
+                .map(|check| &check.question)
+                .collect::<Vec<_>>(),
+            vec!["Do glarks eat flurbos?"]
+        );
+        assert_eq!(report.unknown_citations, vec!["doc-9"]);
+    }
+
+    #[test]
+    fn test_parse_check() {
+        let check = parse_check(
+            "Q?",
+            "VERDICT: NO\nSOURCES: [doc-2]\nANSWER: They are blue.",
+        );
+        assert_eq!(check.verdict, Verdict::Contradicted);
+        assert_eq!(check.sources, vec!["doc-2"]);
+        assert_eq!(check.answer, "They are blue.");
+
+        assert_eq!(parse_check("Q?", "I don't know").verdict, Verdict::NotFound);
+    }
+}
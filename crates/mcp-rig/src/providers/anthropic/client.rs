@@ -1,6 +1,12 @@
 //! Anthropic client api implementation
 
-use crate::{agent::AgentBuilder, extractor::ExtractorBuilder};
+use crate::{
+    agent::AgentBuilder,
+    extractor::ExtractorBuilder,
+    health::{self, Health, HealthCheck},
+    http_client::{HttpBackend, HttpClient, Transform},
+    key_provider::{KeyPlacement, KeyProvider},
+};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -76,6 +82,7 @@ impl<'a> ClientBuilder<'a> {
 pub struct Client {
     base_url: String,
     http_client: reqwest::Client,
+    pub(super) http: HttpBackend,
 }
 
 impl Client {
@@ -87,29 +94,29 @@ impl Client {
     ///   - This should really never happen.
     /// - If the reqwest client cannot be built (if the TLS backend cannot be initialized).
     pub fn new(api_key: &str, base_url: &str, betas: Option<Vec<&str>>, version: &str) -> Self {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-api-key", api_key.parse().expect("API key should parse"));
+        headers.insert(
+            "anthropic-version",
+            version.parse().expect("Anthropic version should parse"),
+        );
+        if let Some(betas) = betas {
+            headers.insert(
+                "anthropic-beta",
+                betas
+                    .join(",")
+                    .parse()
+                    .expect("Anthropic betas should parse"),
+            );
+        }
+
         Self {
             base_url: base_url.to_string(),
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert("x-api-key", api_key.parse().expect("API key should parse"));
-                    headers.insert(
-                        "anthropic-version",
-                        version.parse().expect("Anthropic version should parse"),
-                    );
-                    if let Some(betas) = betas {
-                        headers.insert(
-                            "anthropic-beta",
-                            betas
-                                .join(",")
-                                .parse()
-                                .expect("Anthropic betas should parse"),
-                        );
-                    }
-                    headers
-                })
+                .default_headers(headers.clone())
                 .build()
                 .expect("Anthropic reqwest client should build"),
+            http: HttpBackend::new(headers),
         }
     }
 
@@ -120,6 +127,44 @@ impl Client {
         ClientBuilder::new(&api_key).build()
     }
 
+    /// Add the header `name` with `value` to every request of the client.
+    /// Panics if the header name or value is invalid.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.http = self.http.with_header(name, value);
+        self
+    }
+
+    /// Get the API key of each request from `keys` (e.g.: a
+    /// [KeyPool](crate::key_provider::KeyPool) spreading the load across several keys) instead
+    /// of using the key given at construction. See [crate::key_provider].
+    pub fn with_key_provider(mut self, keys: impl KeyProvider + 'static) -> Self {
+        self.http = self.http.with_keys(keys, KeyPlacement::Header("x-api-key"));
+        self
+    }
+
+    /// Send the requests of the client with `client`, e.g.: to configure a proxy, custom root
+    /// certificates, mTLS or the connection pool. The authentication headers of the provider
+    /// are added to every request.
+    pub fn with_reqwest_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = client;
+        self
+    }
+
+    /// Send the requests of the client through `client` instead of the default reqwest
+    /// client (e.g.: to use another HTTP library, or a fake in tests). See [crate::http_client].
+    pub fn with_http_client(mut self, client: impl HttpClient + 'static) -> Self {
+        self.http = self.http.with_client(client);
+        self
+    }
+
+    /// Modify the JSON requests and responses of the client with `transform` (e.g.: to add the
+    /// headers of an enterprise gateway, or strip the fields unsupported by a proxy). See
+    /// [crate::http_client::Transform].
+    pub fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.http = self.http.with_transform(transform);
+        self
+    }
+
     pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
         self.http_client.post(url)
@@ -144,7 +189,7 @@ impl Client {
     ///    .build();
     /// ```
     pub fn agent(&self, model: &str) -> AgentBuilder<CompletionModel> {
-        AgentBuilder::new(self.completion_model(model))
+        AgentBuilder::new(self.completion_model(model)).model_name(model)
     }
 
     pub fn extractor<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync>(
@@ -154,3 +199,16 @@ impl Client {
         ExtractorBuilder::new(self.completion_model(model))
     }
 }
+
+impl HealthCheck for Client {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn health(&self) -> Health {
+        health::check(
+            &self.http,
+            self.http_client
+                .get(format!("{}/v1/models", self.base_url))
+                .query(&[("limit", "1")]),
+        )
+        .await
+    }
+}
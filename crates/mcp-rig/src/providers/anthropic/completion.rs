@@ -7,6 +7,7 @@ use crate::{
     json_utils,
     message::{self, MessageError},
     one_or_many::string_or_one_or_many,
+    payload_log::SendLogged,
     OneOrMany,
 };
 
@@ -476,6 +477,55 @@ impl TryFrom<Message> for message::Message {
     }
 }
 
+/// Typed Anthropic-specific options of the message requests, to set as the additional
+/// parameters of a request or an agent (e.g.: `agent.additional_params(ChatOptions { top_k:
+/// Some(40), .. })`).
+///
+/// For more information, see this link: <https://docs.anthropic.com/en/api/messages>
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ChatOptions {
+    /// Number of the most likely tokens considered at each step (top-k sampling)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// Cumulative probability of the tokens considered at each step (nucleus sampling)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    /// Sequences stopping the generation
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+    /// Extended thinking of the model before it answers (only supported by the models with
+    /// extended thinking)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<Thinking>,
+}
+
+/// Metadata of an Anthropic request.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Metadata {
+    /// Opaque id of the end-user, to help Anthropic detect abuse
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+
+/// Extended thinking configuration of an Anthropic request.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Thinking {
+    /// Think with at most `budget_tokens` tokens (at least 1024, and less than `max_tokens`)
+    Enabled {
+        budget_tokens: u64,
+    },
+    Disabled,
+}
+
+impl From<ChatOptions> for serde_json::Value {
+    fn from(options: ChatOptions) -> Self {
+        serde_json::to_value(options).expect("Chat options should serialize")
+    }
+}
+
 #[derive(Clone)]
 pub struct CompletionModel {
     pub(crate) client: Client,
@@ -525,12 +575,44 @@ pub enum ToolChoice {
     Tool {
         name: String,
     },
+    None,
+}
+
+impl From<completion::ToolChoice> for ToolChoice {
+    fn from(tool_choice: completion::ToolChoice) -> Self {
+        match tool_choice {
+            completion::ToolChoice::Auto => ToolChoice::Auto,
+            completion::ToolChoice::None => ToolChoice::None,
+            completion::ToolChoice::Required => ToolChoice::Any,
+            completion::ToolChoice::Tool(name) => ToolChoice::Tool { name },
+        }
+    }
 }
 
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    fn supports_vision(&self) -> bool {
+        // All Claude 3 and later models accept images
+        !self.model.starts_with("claude-2") && !self.model.starts_with("claude-instant")
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "chat",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("chat {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "chat",
+            gen_ai.system = "anthropic",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens,
+            gen_ai.usage.output_tokens
+        )
+    ))]
     async fn completion(
         &self,
         completion_request: completion::CompletionRequest,
@@ -591,7 +673,7 @@ impl completion::CompletionModel for CompletionModel {
                             input_schema: tool.parameters,
                         })
                         .collect::<Vec<_>>(),
-                    "tool_choice": ToolChoice::Auto,
+                    "tool_choice": completion_request.tool_choice.map(ToolChoice::from).unwrap_or_default(),
                 }),
             );
         }
@@ -606,7 +688,7 @@ impl completion::CompletionModel for CompletionModel {
             .client
             .post("/v1/messages")
             .json(&request)
-            .send()
+            .send_logged("anthropic", &self.client.http)
             .await?;
 
         if response.status().is_success() {
@@ -616,6 +698,11 @@ impl completion::CompletionModel for CompletionModel {
                         "Anthropic completion token usage: {}",
                         completion.usage
                     );
+                    crate::telemetry::record_usage(
+                        &self.model,
+                        Some(completion.usage.input_tokens),
+                        Some(completion.usage.output_tokens),
+                    );
                     completion.try_into()
                 }
                 ApiResponse::Error(error) => Err(CompletionError::ProviderError(error.message)),
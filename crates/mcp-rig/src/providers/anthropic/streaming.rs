@@ -122,7 +122,7 @@ impl StreamingCompletionModel for CompletionModel {
                             input_schema: tool.parameters,
                         })
                         .collect::<Vec<_>>(),
-                    "tool_choice": ToolChoice::Auto,
+                    "tool_choice": completion_request.tool_choice.map(ToolChoice::from).unwrap_or_default(),
                 }),
             );
         }
@@ -133,9 +133,8 @@ impl StreamingCompletionModel for CompletionModel {
 
         let response = self
             .client
-            .post("/v1/messages")
-            .json(&request)
-            .send()
+            .http
+            .send(self.client.post("/v1/messages").json(&request))
             .await?;
 
         if !response.status().is_success() {
@@ -10,10 +10,14 @@
 //! ```
 use crate::{
     agent::AgentBuilder,
-    completion::{self, CompletionError, CompletionRequest},
+    completion::{self, CompletionError, CompletionRequest, ToolChoice},
     embeddings::{self, EmbeddingError, EmbeddingsBuilder},
     extractor::ExtractorBuilder,
+    health::{self, Health, HealthCheck},
+    http_client::{HttpBackend, HttpClient, Transform},
     json_utils,
+    key_provider::{KeyPlacement, KeyProvider},
+    payload_log::SendLogged,
     providers::openai,
     Embed,
 };
@@ -30,6 +34,7 @@ pub struct Client {
     api_version: String,
     azure_endpoint: String,
     http_client: reqwest::Client,
+    http: HttpBackend,
 }
 
 impl Client {
@@ -41,17 +46,17 @@ impl Client {
     /// * `api_version` - API version to use (e.g., "2024-10-21" for GA, "2024-10-01-preview" for preview)
     /// * `azure_endpoint` - Azure OpenAI endpoint URL, for example: https://{your-resource-name}.openai.azure.com
     pub fn new(api_key: &str, api_version: &str, azure_endpoint: &str) -> Self {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("api-key", api_key.parse().expect("API key should parse"));
+
         Self {
             api_version: api_version.to_string(),
             azure_endpoint: azure_endpoint.to_string(),
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert("api-key", api_key.parse().expect("API key should parse"));
-                    headers
-                })
+                .default_headers(headers.clone())
                 .build()
                 .expect("Azure OpenAI reqwest client should build"),
+            http: HttpBackend::new(headers),
         }
     }
 
@@ -64,6 +69,44 @@ impl Client {
         Self::new(&api_key, &api_version, &azure_endpoint)
     }
 
+    /// Add the header `name` with `value` to every request of the client.
+    /// Panics if the header name or value is invalid.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.http = self.http.with_header(name, value);
+        self
+    }
+
+    /// Get the API key of each request from `keys` (e.g.: a
+    /// [KeyPool](crate::key_provider::KeyPool) spreading the load across several keys) instead
+    /// of using the key given at construction. See [crate::key_provider].
+    pub fn with_key_provider(mut self, keys: impl KeyProvider + 'static) -> Self {
+        self.http = self.http.with_keys(keys, KeyPlacement::Header("api-key"));
+        self
+    }
+
+    /// Send the requests of the client with `client`, e.g.: to configure a proxy, custom root
+    /// certificates, mTLS or the connection pool. The authentication headers of the provider
+    /// are added to every request.
+    pub fn with_reqwest_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = client;
+        self
+    }
+
+    /// Send the requests of the client through `client` instead of the default reqwest
+    /// client (e.g.: to use another HTTP library, or a fake in tests). See [crate::http_client].
+    pub fn with_http_client(mut self, client: impl HttpClient + 'static) -> Self {
+        self.http = self.http.with_client(client);
+        self
+    }
+
+    /// Modify the JSON requests and responses of the client with `transform` (e.g.: to add the
+    /// headers of an enterprise gateway, or strip the fields unsupported by a proxy). See
+    /// [crate::http_client::Transform].
+    pub fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.http = self.http.with_transform(transform);
+        self
+    }
+
     fn post_embedding(&self, deployment_id: &str) -> reqwest::RequestBuilder {
         let url = format!(
             "{}/openai/deployments/{}/embeddings?api-version={}",
@@ -169,7 +212,7 @@ impl Client {
     ///    .build();
     /// ```
     pub fn agent(&self, model: &str) -> AgentBuilder<CompletionModel> {
-        AgentBuilder::new(self.completion_model(model))
+        AgentBuilder::new(self.completion_model(model)).model_name(model)
     }
 
     /// Create an extractor builder with the given completion model.
@@ -181,6 +224,20 @@ impl Client {
     }
 }
 
+impl HealthCheck for Client {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn health(&self) -> Health {
+        health::check(
+            &self.http,
+            self.http_client.get(format!(
+                "{}/openai/models?api-version={}",
+                self.azure_endpoint, self.api_version
+            )),
+        )
+        .await
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ApiErrorResponse {
     message: String,
@@ -254,6 +311,7 @@ pub struct EmbeddingModel {
     client: Client,
     pub model: String,
     ndims: usize,
+    dimensions: Option<usize>,
 }
 
 impl embeddings::EmbeddingModel for EmbeddingModel {
@@ -263,20 +321,39 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
         self.ndims
     }
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "embeddings",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("embeddings {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "embeddings",
+            gen_ai.system = "az.ai.openai",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens
+        )
+    ))]
     async fn embed_texts(
         &self,
         documents: impl IntoIterator<Item = String>,
     ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
         let documents = documents.into_iter().collect::<Vec<_>>();
 
+        let mut request = json!({
+            "input": documents,
+        });
+        if let Some(dimensions) = self.dimensions {
+            request["dimensions"] = json!(dimensions);
+        }
+
         let response = self
             .client
             .post_embedding(&self.model)
-            .json(&json!({
-                "input": documents,
-            }))
-            .send()
+            .json(&request)
+            .send_logged("azure", &self.client.http)
             .await?;
 
         if response.status().is_success() {
@@ -286,6 +363,11 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
                         "Azure embedding token usage: {}",
                         response.usage
                     );
+                    crate::telemetry::record_usage(
+                        &self.model,
+                        Some(response.usage.prompt_tokens as u64),
+                        None,
+                    );
 
                     if response.data.len() != documents.len() {
                         return Err(EmbeddingError::ResponseError(
@@ -317,8 +399,17 @@ impl EmbeddingModel {
             client,
             model: model.to_string(),
             ndims,
+            dimensions: None,
         }
     }
+
+    /// Request embeddings truncated to `ndims` dimensions from the provider (using the
+    /// `dimensions` parameter). Only supported by `text-embedding-3` and later models.
+    pub fn with_output_dims(mut self, ndims: usize) -> Self {
+        self.ndims = ndims;
+        self.dimensions = Some(ndims);
+        self
+    }
 }
 
 // ================================================================
@@ -370,7 +461,26 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = openai::CompletionResponse;
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    fn supports_vision(&self) -> bool {
+        openai::supports_vision(&self.model)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "chat",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("chat {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "chat",
+            gen_ai.system = "az.ai.openai",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens,
+            gen_ai.usage.output_tokens
+        )
+    ))]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
@@ -410,7 +520,7 @@ impl completion::CompletionModel for CompletionModel {
                 "messages": full_history,
                 "temperature": completion_request.temperature,
                 "tools": completion_request.tools.into_iter().map(openai::ToolDefinition::from).collect::<Vec<_>>(),
-                "tool_choice": "auto",
+                "tool_choice": ToolChoice::openai_or_auto(&completion_request.tool_choice),
             })
         };
 
@@ -424,7 +534,7 @@ impl completion::CompletionModel for CompletionModel {
                     request
                 },
             )
-            .send()
+            .send_logged("azure", &self.client.http)
             .await?;
 
         if response.status().is_success() {
@@ -437,6 +547,13 @@ impl completion::CompletionModel for CompletionModel {
                         "Azure completion token usage: {:?}",
                         response.usage.clone().map(|usage| format!("{usage}")).unwrap_or("N/A".to_string())
                     );
+                    if let Some(usage) = &response.usage {
+                        crate::telemetry::record_usage(
+                            &self.model,
+                            Some(usage.prompt_tokens as u64),
+                            Some(usage.total_tokens.saturating_sub(usage.prompt_tokens) as u64),
+                        );
+                    }
                     response.try_into()
                 }
                 ApiResponse::Err(err) => Err(CompletionError::ProviderError(err.message)),
@@ -486,6 +603,7 @@ mod azure_tests {
                 temperature: Some(0.0),
                 tools: vec![],
                 additional_params: None,
+                tool_choice: None,
             })
             .await
             .unwrap();
@@ -12,10 +12,18 @@ use std::collections::HashMap;
 
 use crate::{
     agent::AgentBuilder,
+    cassette::{Cassette, SendRecorded},
     completion::{self, CompletionError},
-    embeddings::{self, EmbeddingError, EmbeddingsBuilder},
+    embeddings::{self, EmbeddingError, EmbeddingUsage, EmbeddingsBuilder},
     extractor::ExtractorBuilder,
-    json_utils, message, Embed, OneOrMany,
+    finetuning::{FineTuneError, FineTuneJob, FineTuneRequest, FineTuneStatus, FineTuning},
+    health::{self, Health, HealthCheck},
+    http_client::{multipart_body, HttpBackend, HttpClient, Transform},
+    json_utils,
+    key_provider::{KeyPlacement, KeyProvider},
+    message,
+    models::{ListModels, ModelInfo, ModelListError},
+    Embed, OneOrMany,
 };
 
 use schemars::JsonSchema;
@@ -31,6 +39,8 @@ const COHERE_API_BASE_URL: &str = "https://api.cohere.ai";
 pub struct Client {
     base_url: String,
     http_client: reqwest::Client,
+    http: HttpBackend,
+    cassette: Option<Cassette>,
 }
 
 impl Client {
@@ -39,24 +49,32 @@ impl Client {
     }
 
     pub fn from_url(api_key: &str, base_url: &str) -> Self {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            format!("Bearer {}", api_key)
+                .parse()
+                .expect("Bearer token should parse"),
+        );
+
         Self {
             base_url: base_url.to_string(),
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert(
-                        "Authorization",
-                        format!("Bearer {}", api_key)
-                            .parse()
-                            .expect("Bearer token should parse"),
-                    );
-                    headers
-                })
+                .default_headers(headers.clone())
                 .build()
                 .expect("Cohere reqwest client should build"),
+            http: HttpBackend::new(headers),
+            cassette: None,
         }
     }
 
+    /// Record the HTTP interactions of the client to `cassette`, or replay them from it
+    /// (depending on the mode of the cassette). See [crate::cassette].
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
     /// Create a new Cohere client from the `COHERE_API_KEY` environment variable.
     /// Panics if the environment variable is not set.
     pub fn from_env() -> Self {
@@ -64,11 +82,64 @@ impl Client {
         Self::new(&api_key)
     }
 
+    /// Add the header `name` with `value` to every request of the client.
+    /// Panics if the header name or value is invalid.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.http = self.http.with_header(name, value);
+        self
+    }
+
+    /// Set the name of the application sending the requests (`X-Client-Name` header).
+    pub fn with_client_name(self, client_name: &str) -> Self {
+        self.with_header("X-Client-Name", client_name)
+    }
+
+    /// Get the API key of each request from `keys` (e.g.: a
+    /// [KeyPool](crate::key_provider::KeyPool) spreading the load across several keys) instead
+    /// of using the key given at construction. See [crate::key_provider].
+    pub fn with_key_provider(mut self, keys: impl KeyProvider + 'static) -> Self {
+        self.http = self.http.with_keys(keys, KeyPlacement::Bearer);
+        self
+    }
+
+    /// Send the requests of the client with `client`, e.g.: to configure a proxy, custom root
+    /// certificates, mTLS or the connection pool. The authentication headers of the provider
+    /// are added to every request.
+    pub fn with_reqwest_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = client;
+        self
+    }
+
+    /// Send the requests of the client through `client` instead of the default reqwest
+    /// client (e.g.: to use another HTTP library, or a fake in tests). See [crate::http_client].
+    pub fn with_http_client(mut self, client: impl HttpClient + 'static) -> Self {
+        self.http = self.http.with_client(client);
+        self
+    }
+
+    /// Modify the JSON requests and responses of the client with `transform` (e.g.: to add the
+    /// headers of an enterprise gateway, or strip the fields unsupported by a proxy). See
+    /// [crate::http_client::Transform].
+    pub fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.http = self.http.with_transform(transform);
+        self
+    }
+
     pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
         self.http_client.post(url)
     }
 
+    pub fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/{}", self.base_url, path).replace("//", "/");
+        self.http_client.get(url)
+    }
+
+    pub fn delete(&self, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/{}", self.base_url, path).replace("//", "/");
+        self.http_client.delete(url)
+    }
+
     /// Note: default embedding dimension of 0 will be used if model is not known.
     /// If this is the case, it's better to use function `embedding_model_with_ndims`
     pub fn embedding_model(&self, model: &str, input_type: &str) -> EmbeddingModel {
@@ -105,7 +176,7 @@ impl Client {
     }
 
     pub fn agent(&self, model: &str) -> AgentBuilder<CompletionModel> {
-        AgentBuilder::new(self.completion_model(model))
+        AgentBuilder::new(self.completion_model(model)).model_name(model)
     }
 
     pub fn extractor<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync>(
@@ -116,6 +187,17 @@ impl Client {
     }
 }
 
+impl HealthCheck for Client {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn health(&self) -> Health {
+        health::check(
+            &self.http,
+            self.get("/v1/models").query(&[("page_size", "1")]),
+        )
+        .await
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ApiErrorResponse {
     message: String,
@@ -128,6 +210,237 @@ enum ApiResponse<T> {
     Err(ApiErrorResponse),
 }
 
+// ================================================================
+// Cohere Models API
+// ================================================================
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    models: Vec<Model>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Model {
+    name: String,
+    #[serde(default)]
+    endpoints: Vec<String>,
+    context_length: Option<f64>,
+}
+
+impl ListModels for Client {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ModelListError> {
+        let mut models = vec![];
+        let mut page_token = None;
+
+        loop {
+            let mut request = self.get("/v1/models").query(&[("page_size", "1000")]);
+            if let Some(page_token) = &page_token {
+                request = request.query(&[("page_token", page_token)]);
+            }
+            let response = request
+                .send_recorded("cohere", self.cassette.as_ref(), &self.http)
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(ModelListError::ProviderError(response.text().await?));
+            }
+
+            let response = match response.json::<ApiResponse<ModelsResponse>>().await? {
+                ApiResponse::Ok(response) => response,
+                ApiResponse::Err(err) => return Err(ModelListError::ProviderError(err.message)),
+            };
+            models.extend(response.models.into_iter().map(|model| ModelInfo {
+                id: model.name,
+                owned_by: Some("cohere".to_string()),
+                context_length: model.context_length.map(|length| length as u64),
+                endpoints: model.endpoints,
+                ..Default::default()
+            }));
+
+            match response.next_page_token {
+                Some(token) if !token.is_empty() => page_token = Some(token),
+                _ => return Ok(models),
+            }
+        }
+    }
+}
+
+// ================================================================
+// Cohere Fine-tuning API
+// ================================================================
+#[derive(Debug, Deserialize)]
+struct FinetunedModelResponse {
+    finetuned_model: FinetunedModel,
+}
+
+#[derive(Debug, Deserialize)]
+struct FinetunedModelsResponse {
+    #[serde(default)]
+    finetuned_models: Vec<FinetunedModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FinetunedModel {
+    id: String,
+    settings: FinetuneSettings,
+    /// e.g.: `STATUS_QUEUED`, `STATUS_FINETUNING`, `STATUS_READY`, `STATUS_FAILED`
+    status: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FinetuneSettings {
+    base_model: BaseModel,
+}
+
+#[derive(Debug, Deserialize)]
+struct BaseModel {
+    name: Option<String>,
+    base_type: String,
+}
+
+impl From<FinetunedModel> for FineTuneJob {
+    fn from(model: FinetunedModel) -> Self {
+        let status = match model.status.as_str() {
+            "STATUS_FINETUNING" | "STATUS_DEPLOYING_API" => FineTuneStatus::Running,
+            "STATUS_READY" => FineTuneStatus::Succeeded,
+            "STATUS_FAILED" => FineTuneStatus::Failed,
+            "STATUS_DELETED" => FineTuneStatus::Cancelled,
+            _ => FineTuneStatus::Queued,
+        };
+        FineTuneJob {
+            // The fine-tuned models are used in chat requests with the `-ft` suffix
+            fine_tuned_model: (status == FineTuneStatus::Succeeded)
+                .then(|| format!("{}-ft", model.id)),
+            id: model.id,
+            base_model: model
+                .settings
+                .base_model
+                .name
+                .unwrap_or(model.settings.base_model.base_type),
+            status,
+            created_at: None,
+            error: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DatasetResponse {
+    id: String,
+}
+
+impl Client {
+    /// Upload a dataset of fine-tuning conversations (in the
+    /// [Cohere](crate::finetuning::TrainingFormat::Cohere) training format) and return its ID.
+    pub async fn upload_dataset(
+        &self,
+        name: &str,
+        content: impl Into<Vec<u8>>,
+    ) -> Result<String, FineTuneError> {
+        let (content_type, body) =
+            multipart_body(&[], "data", &format!("{name}.jsonl"), content.into());
+
+        let response = self
+            .post("/v1/datasets")
+            .query(&[("name", name), ("type", "chat-finetune-input")])
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body)
+            .send_recorded("cohere", self.cassette.as_ref(), &self.http)
+            .await?;
+        Ok(parse_finetuning::<DatasetResponse>(response).await?.id)
+    }
+}
+
+async fn parse_finetuning<T: for<'a> Deserialize<'a>>(
+    response: reqwest::Response,
+) -> Result<T, FineTuneError> {
+    if !response.status().is_success() {
+        return Err(FineTuneError::ProviderError(response.text().await?));
+    }
+
+    match response.json::<ApiResponse<T>>().await? {
+        ApiResponse::Ok(response) => Ok(response),
+        ApiResponse::Err(err) => Err(FineTuneError::ProviderError(err.message)),
+    }
+}
+
+impl FineTuning for Client {
+    type Model = CompletionModel;
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn create_fine_tune_job(
+        &self,
+        request: FineTuneRequest,
+    ) -> Result<FineTuneJob, FineTuneError> {
+        let mut settings = json!({
+            "base_model": { "base_type": "BASE_TYPE_CHAT", "name": request.base_model },
+            "dataset_id": request.training_file,
+        });
+        if let Some(epochs) = request.epochs {
+            settings["hyperparameters"] = json!({ "train_epochs": epochs });
+        }
+
+        let response = self
+            .post("/v1/finetuning/finetuned-models")
+            .json(&json!({
+                "name": request.suffix.unwrap_or_else(|| "mcp-rig-finetune".to_string()),
+                "settings": settings,
+            }))
+            .send_recorded("cohere", self.cassette.as_ref(), &self.http)
+            .await?;
+        Ok(parse_finetuning::<FinetunedModelResponse>(response)
+            .await?
+            .finetuned_model
+            .into())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn fine_tune_job(&self, job_id: &str) -> Result<FineTuneJob, FineTuneError> {
+        let response = self
+            .get(&format!("/v1/finetuning/finetuned-models/{job_id}"))
+            .send_recorded("cohere", self.cassette.as_ref(), &self.http)
+            .await?;
+        Ok(parse_finetuning::<FinetunedModelResponse>(response)
+            .await?
+            .finetuned_model
+            .into())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn list_fine_tune_jobs(&self) -> Result<Vec<FineTuneJob>, FineTuneError> {
+        let response = self
+            .get("/v1/finetuning/finetuned-models")
+            .send_recorded("cohere", self.cassette.as_ref(), &self.http)
+            .await?;
+        Ok(parse_finetuning::<FinetunedModelsResponse>(response)
+            .await?
+            .finetuned_models
+            .into_iter()
+            .map(FineTuneJob::from)
+            .collect())
+    }
+
+    /// Cohere has no cancellation: the fine-tuned model is deleted (which stops its training).
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn cancel_fine_tune_job(&self, job_id: &str) -> Result<(), FineTuneError> {
+        let response = self
+            .delete(&format!("/v1/finetuning/finetuned-models/{job_id}"))
+            .send_recorded("cohere", self.cassette.as_ref(), &self.http)
+            .await?;
+        parse_finetuning::<serde_json::Value>(response)
+            .await
+            .map(|_| ())
+    }
+
+    fn fine_tuned_model(&self, job: &FineTuneJob) -> Result<CompletionModel, FineTuneError> {
+        match &job.fine_tuned_model {
+            Some(model) => Ok(self.completion_model(model)),
+            None => Err(FineTuneError::ModelNotReady(job.id.clone())),
+        }
+    }
+}
+
 // ================================================================
 // Cohere Embedding API
 // ================================================================
@@ -211,11 +524,33 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
         self.ndims
     }
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
     async fn embed_texts(
         &self,
         documents: impl IntoIterator<Item = String>,
     ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
+        Ok(self.embed_texts_with_usage(documents).await?.0)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "embeddings",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("embeddings {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "embeddings",
+            gen_ai.system = "cohere",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens
+        )
+    ))]
+    async fn embed_texts_with_usage(
+        &self,
+        documents: impl IntoIterator<Item = String>,
+    ) -> Result<(Vec<embeddings::Embedding>, Option<EmbeddingUsage>), EmbeddingError> {
         let documents = documents.into_iter().collect::<Vec<_>>();
 
         let response = self
@@ -226,20 +561,33 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
                 "texts": documents,
                 "input_type": self.input_type,
             }))
-            .send()
+            .send_recorded("cohere", self.client.cassette.as_ref(), &self.client.http)
             .await?;
 
         if response.status().is_success() {
             match response.json::<ApiResponse<EmbeddingResponse>>().await? {
                 ApiResponse::Ok(response) => {
-                    match response.meta {
-                        Some(meta) => tracing::info!(target: "rig",
-                            "Cohere embeddings billed units: {}",
-                            meta.billed_units,
-                        ),
-                        None => tracing::info!(target: "rig",
-                            "Cohere embeddings billed units: n/a",
-                        ),
+                    let usage = match response.meta {
+                        Some(meta) => {
+                            tracing::info!(target: "rig",
+                                "Cohere embeddings billed units: {}",
+                                meta.billed_units,
+                            );
+                            crate::telemetry::record_usage(
+                                &self.model,
+                                Some(meta.billed_units.input_tokens as u64),
+                                None,
+                            );
+                            Some(EmbeddingUsage {
+                                total_tokens: meta.billed_units.input_tokens as usize,
+                            })
+                        }
+                        None => {
+                            tracing::info!(target: "rig",
+                                "Cohere embeddings billed units: n/a",
+                            );
+                            None
+                        }
                     };
 
                     if response.embeddings.len() != documents.len() {
@@ -253,15 +601,18 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
                         ));
                     }
 
-                    Ok(response
-                        .embeddings
-                        .into_iter()
-                        .zip(documents.into_iter())
-                        .map(|(embedding, document)| embeddings::Embedding {
-                            document,
-                            vec: embedding,
-                        })
-                        .collect())
+                    Ok((
+                        response
+                            .embeddings
+                            .into_iter()
+                            .zip(documents.into_iter())
+                            .map(|(embedding, document)| embeddings::Embedding {
+                                document,
+                                vec: embedding,
+                            })
+                            .collect(),
+                        usage,
+                    ))
                 }
                 ApiResponse::Err(error) => Err(EmbeddingError::ProviderError(error.message)),
             }
@@ -286,7 +637,7 @@ impl EmbeddingModel {
 // Cohere Completion API
 // ================================================================
 /// `command-r-plus` completion model
-pub const COMMAND_R_PLUS: &str = "comman-r-plus";
+pub const COMMAND_R_PLUS: &str = "command-r-plus";
 /// `command-r` completion model
 pub const COMMAND_R: &str = "command-r";
 /// `command` completion model
@@ -328,9 +679,10 @@ impl From<CompletionResponse> for completion::CompletionResponse<CompletionRespo
         let model_response = if !tool_calls.is_empty() {
             tool_calls
                 .iter()
-                .map(|tool_call| {
+                .enumerate()
+                .map(|(index, tool_call)| {
                     completion::AssistantContent::tool_call(
-                        tool_call.name.clone(),
+                        tool_call_id(&response.generation_id, index),
                         tool_call.name.clone(),
                         tool_call.parameters.clone(),
                     )
@@ -384,12 +736,19 @@ pub struct Connector {
     pub id: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ToolCall {
     pub name: String,
     pub parameters: serde_json::Value,
 }
 
+/// Id of the `index`-th tool call of the generation `generation_id`. Cohere doesn't identify
+/// the tool calls, so that their results are matched back to them by this id (see
+/// [MessageConverter]).
+fn tool_call_id(generation_id: &str, index: usize) -> String {
+    format!("{generation_id}_{index}")
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ChatHistory {
     pub role: String,
@@ -515,34 +874,235 @@ impl TryFrom<message::Message> for Vec<Message> {
     type Error = message::MessageError;
 
     fn try_from(message: message::Message) -> Result<Self, Self::Error> {
+        MessageConverter::default().convert(message)
+    }
+}
+
+/// Converter of the generic messages of a conversation into Cohere messages. The tool results
+/// of Cohere carry their tool call instead of its id: the calls of the converted assistant
+/// messages are kept to resolve the ids of the later tool results.
+#[derive(Default)]
+pub(crate) struct MessageConverter {
+    calls: HashMap<String, ToolCall>,
+}
+
+impl MessageConverter {
+    pub(crate) fn convert(
+        &mut self,
+        message: message::Message,
+    ) -> Result<Vec<Message>, message::MessageError> {
         match message {
-            message::Message::User { content } => content
-                .into_iter()
-                .map(|content| {
-                    Ok(Message::User {
-                        message: match content {
-                            message::UserContent::Text(message::Text { text }) => text,
-                            _ => {
-                                return Err(message::MessageError::ConversionError(
-                                    "Only text content is supported by Cohere".to_owned(),
-                                ))
+            message::Message::User { content } => {
+                let (tool_results, other_content): (Vec<_>, Vec<_>) = content
+                    .into_iter()
+                    .partition(|content| matches!(content, message::UserContent::ToolResult(_)));
+                let mut messages = other_content
+                    .into_iter()
+                    .map(|content| {
+                        Ok(Message::User {
+                            message: match content {
+                                message::UserContent::Text(message::Text { text }) => text,
+                                _ => {
+                                    return Err(message::MessageError::ConversionError(
+                                        "Only text content is supported by Cohere".to_owned(),
+                                    ))
+                                }
+                            },
+                            tool_calls: vec![],
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if !tool_results.is_empty() {
+                    messages.push(Message::Tool {
+                        tool_results: self.tool_results(tool_results)?,
+                    });
+                }
+                Ok(messages)
+            }
+            message::Message::Assistant { content } => {
+                let mut texts = vec![];
+                let mut tool_calls = vec![];
+                for content in content {
+                    match content {
+                        message::AssistantContent::Text(message::Text { text }) => texts.push(text),
+                        message::AssistantContent::ToolCall(message::ToolCall { id, function }) => {
+                            let call = ToolCall {
+                                name: function.name,
+                                parameters: function.arguments,
+                            };
+                            self.calls.insert(id, call.clone());
+                            tool_calls.push(call);
+                        }
+                    }
+                }
+                Ok(vec![Message::Chatbot {
+                    message: texts.join("\n"),
+                    tool_calls,
+                }])
+            }
+        }
+    }
+
+    /// Convert the tool results `content` (i.e.: [message::UserContent::ToolResult]s), matched
+    /// to the tool calls converted before them
+    fn tool_results(
+        &self,
+        content: Vec<message::UserContent>,
+    ) -> Result<Vec<ToolResult>, message::MessageError> {
+        content
+            .into_iter()
+            .filter_map(|content| match content {
+                message::UserContent::ToolResult(result) => Some(result),
+                _ => None,
+            })
+            .map(|message::ToolResult { id, content }| {
+                let call = self.calls.get(&id).cloned().ok_or_else(|| {
+                    message::MessageError::ConversionError(format!(
+                        "Tool result {id} doesn't match any tool call"
+                    ))
+                })?;
+                let outputs = content
+                    .into_iter()
+                    .map(|content| match content {
+                        message::ToolResultContent::Text(message::Text { text }) => {
+                            // Cohere expects the outputs to be objects
+                            match serde_json::from_str(&text) {
+                                Ok(output @ serde_json::Value::Object(_)) => Ok(output),
+                                _ => Ok(json!({ "result": text })),
                             }
-                        },
-                        tool_calls: vec![],
+                        }
+                        _ => Err(message::MessageError::ConversionError(
+                            "Only text tool results are supported by Cohere".to_owned(),
+                        )),
                     })
-                })
-                .collect::<Result<Vec<_>, _>>(),
-            _ => Err(message::MessageError::ConversionError(
-                "Only user messages are supported by Cohere".to_owned(),
-            )),
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(ToolResult { call, outputs })
+            })
+            .collect()
+    }
+}
+
+/// Typed Cohere-specific options of the chat requests, to set as the additional parameters of
+/// a request or an agent (e.g.: `agent.additional_params(ChatOptions { k: Some(10), .. })`).
+///
+/// For more information, see this link: <https://docs.cohere.com/reference/chat>
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ChatOptions {
+    /// Number of the most likely tokens considered at each step (top-k sampling)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k: Option<u32>,
+    /// Cumulative probability of the tokens considered at each step (nucleus sampling)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    /// Seed of the sampling, for (best effort) deterministic generations
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Sequences stopping the generation
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+    /// Maximum number of input tokens, the prompt being truncated to fit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_input_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_truncation: Option<PromptTruncation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safety_mode: Option<SafetyMode>,
+    /// Connectors the model retrieves documents from (i.e.: grounded generation)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub connectors: Vec<ChatConnector>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub citation_options: Option<CitationOptions>,
+}
+
+/// How Cohere truncates the prompts over the context window of the model.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PromptTruncation {
+    /// Drop the oldest messages and documents
+    Auto,
+    /// Like [PromptTruncation::Auto], keeping the order of the documents
+    AutoPreserveOrder,
+    /// Fail instead of truncating
+    Off,
+}
+
+/// Safety instructions added by Cohere to the prompts.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SafetyMode {
+    /// Guards against harmful content, allowing a wider range of topics (Cohere's default)
+    Contextual,
+    /// Guards against harmful content and avoids the sensitive topics
+    Strict,
+    /// No safety instructions
+    None,
+}
+
+/// Connector of a Cohere chat request, which the model retrieves documents from.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ChatConnector {
+    pub id: String,
+    /// Options of the connector (e.g.: the site filter of the web-search connector)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<serde_json::Value>,
+}
+
+impl ChatConnector {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            options: None,
         }
     }
+
+    /// Cohere's `web-search` connector
+    pub fn web_search() -> Self {
+        Self::new("web-search")
+    }
+
+    /// Restrict the web searches to the site `site` (e.g.: `https://docs.cohere.com`)
+    pub fn site(mut self, site: &str) -> Self {
+        let options = self.options.get_or_insert_with(|| json!({}));
+        options["site"] = json!(site);
+        self
+    }
+}
+
+/// Options of the citations of a Cohere chat response.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CitationOptions {
+    pub mode: CitationMode,
+}
+
+/// How Cohere generates the citations of a response.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CitationMode {
+    /// Citations generated along the response
+    Fast,
+    /// Citations generated after the response, more accurately
+    Accurate,
+    /// No citations
+    Off,
+}
+
+impl From<ChatOptions> for serde_json::Value {
+    fn from(options: ChatOptions) -> Self {
+        serde_json::to_value(options).expect("Chat options should serialize")
+    }
 }
 
 #[derive(Clone)]
 pub struct CompletionModel {
     client: Client,
     pub model: String,
+    safety_mode: Option<SafetyMode>,
+    connectors: Vec<ChatConnector>,
+    citation_options: Option<CitationOptions>,
 }
 
 impl CompletionModel {
@@ -550,6 +1110,38 @@ impl CompletionModel {
         Self {
             client,
             model: model.to_string(),
+            safety_mode: None,
+            connectors: vec![],
+            citation_options: None,
+        }
+    }
+
+    /// Set the safety mode of the requests
+    pub fn with_safety_mode(mut self, safety_mode: SafetyMode) -> Self {
+        self.safety_mode = Some(safety_mode);
+        self
+    }
+
+    /// Ground the responses in the documents retrieved from `connector` (e.g.:
+    /// [ChatConnector::web_search])
+    pub fn with_connector(mut self, connector: ChatConnector) -> Self {
+        self.connectors.push(connector);
+        self
+    }
+
+    /// Set how the citations of the responses are generated
+    pub fn with_citation_options(mut self, citation_options: CitationOptions) -> Self {
+        self.citation_options = Some(citation_options);
+        self
+    }
+
+    /// Options of the model, overridden by the additional parameters of the requests
+    fn options(&self) -> ChatOptions {
+        ChatOptions {
+            safety_mode: self.safety_mode,
+            connectors: self.connectors.clone(),
+            citation_options: self.citation_options,
+            ..Default::default()
         }
     }
 }
@@ -557,38 +1149,61 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "chat",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("chat {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "chat",
+            gen_ai.system = "cohere",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens,
+            gen_ai.usage.output_tokens
+        )
+    ))]
     async fn completion(
         &self,
         completion_request: completion::CompletionRequest,
     ) -> Result<completion::CompletionResponse<CompletionResponse>, CompletionError> {
+        let mut converter = MessageConverter::default();
         let chat_history = completion_request
             .chat_history
             .into_iter()
-            .map(Vec::<Message>::try_from)
+            .map(|message| converter.convert(message))
             .collect::<Result<Vec<Vec<_>>, _>>()?
             .into_iter()
             .flatten()
             .collect::<Vec<_>>();
 
-        let message = match completion_request.prompt {
-            message::Message::User { content } => Ok(content
-                .into_iter()
-                .map(|content| match content {
-                    message::UserContent::Text(message::Text { text }) => Ok(text),
-                    _ => Err(CompletionError::RequestError(
-                        "Only text content is supported by Cohere".into(),
-                    )),
-                })
-                .collect::<Result<Vec<_>, _>>()?
-                .join("\n")),
+        // The results of the tool calls of the last turn are sent along the (empty) message
+        let (message, tool_results) = match completion_request.prompt {
+            message::Message::User { content } => {
+                let (tool_results, other_content): (Vec<_>, Vec<_>) = content
+                    .into_iter()
+                    .partition(|content| matches!(content, message::UserContent::ToolResult(_)));
+                let message = other_content
+                    .into_iter()
+                    .map(|content| match content {
+                        message::UserContent::Text(message::Text { text }) => Ok(text),
+                        _ => Err(CompletionError::RequestError(
+                            "Only text content is supported by Cohere".into(),
+                        )),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join("\n");
+                Ok((message, converter.tool_results(tool_results)?))
+            }
 
             _ => Err(CompletionError::RequestError(
                 "Only user messages are supported by Cohere".into(),
             )),
         }?;
 
-        let request = json!({
+        let mut request = json!({
             "model": self.model,
             "preamble": completion_request.preamble,
             "message": message,
@@ -597,18 +1212,26 @@ impl completion::CompletionModel for CompletionModel {
             "temperature": completion_request.temperature,
             "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
         });
+        if !tool_results.is_empty() {
+            request["tool_results"] = json!(tool_results);
+        }
+        json_utils::merge_inplace(&mut request, self.options().into());
 
         let response = self
             .client
             .post("/v1/chat")
             .json(
                 &if let Some(ref params) = completion_request.additional_params {
-                    json_utils::merge(request.clone(), params.clone())
+                    json_utils::deep_merge(
+                        request.clone(),
+                        params.clone(),
+                        json_utils::ArrayStrategy::Replace,
+                    )
                 } else {
                     request.clone()
                 },
             )
-            .send()
+            .send_recorded("cohere", self.client.cassette.as_ref(), &self.client.http)
             .await?;
 
         if response.status().is_success() {
@@ -621,3 +1244,80 @@ impl completion::CompletionModel for CompletionModel {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_options() {
+        let options = ChatOptions {
+            k: Some(10),
+            stop_sequences: vec!["END".to_string()],
+            prompt_truncation: Some(PromptTruncation::AutoPreserveOrder),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::Value::from(options),
+            json!({"k": 10, "stop_sequences": ["END"], "prompt_truncation": "AUTO_PRESERVE_ORDER"})
+        );
+    }
+
+    #[test]
+    fn test_grounding_options() {
+        let model = Client::new("key")
+            .completion_model(COMMAND_R)
+            .with_safety_mode(SafetyMode::Strict)
+            .with_connector(ChatConnector::web_search().site("https://docs.cohere.com"))
+            .with_citation_options(CitationOptions {
+                mode: CitationMode::Accurate,
+            });
+        assert_eq!(
+            serde_json::Value::from(model.options()),
+            json!({
+                "safety_mode": "STRICT",
+                "connectors": [{"id": "web-search", "options": {"site": "https://docs.cohere.com"}}],
+                "citation_options": {"mode": "ACCURATE"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_tool_call_ids() {
+        let response: CompletionResponse = serde_json::from_value(json!({
+            "text": "",
+            "generation_id": "gen",
+            "finish_reason": "COMPLETE",
+            "tool_calls": [
+                {"name": "get_weather", "parameters": {"city": "Paris"}},
+                {"name": "get_weather", "parameters": {"city": "Rome"}}
+            ]
+        }))
+        .unwrap();
+        let response = completion::CompletionResponse::from(response);
+
+        // Each call of the same tool gets its own id, which its result is matched back to
+        let calls = response.choice.into_iter().collect::<Vec<_>>();
+        let mut converter = MessageConverter::default();
+        converter
+            .convert(message::Message::Assistant {
+                content: OneOrMany::many(calls).unwrap(),
+            })
+            .unwrap();
+        let results = converter
+            .tool_results(vec![message::UserContent::tool_result(
+                "gen_1",
+                OneOrMany::one(message::ToolResultContent::text("Sunny")),
+            )])
+            .unwrap();
+        assert_eq!(results[0].call.parameters, json!({"city": "Rome"}));
+        assert_eq!(results[0].outputs, vec![json!({"result": "Sunny"})]);
+
+        assert!(converter
+            .tool_results(vec![message::UserContent::tool_result(
+                "get_weather",
+                OneOrMany::one(message::ToolResultContent::text("Sunny")),
+            )])
+            .is_err());
+    }
+}
@@ -9,9 +9,13 @@
 //! let deepseek_chat = client.completion_model(deepseek::DEEPSEEK_CHAT);
 //! ```
 use crate::{
-    completion::{self, CompletionError, CompletionModel, CompletionRequest},
+    completion::{self, CompletionError, CompletionModel, CompletionRequest, ToolChoice},
     extractor::ExtractorBuilder,
+    health::{self, Health, HealthCheck},
+    http_client::{self, HttpBackend},
     json_utils,
+    key_provider::{KeyPlacement, KeyProvider},
+    payload_log::SendLogged,
     providers::openai::Message,
     OneOrMany,
 };
@@ -32,6 +36,7 @@ pub struct Client {
     pub base_url: String,
     pub api_key: String,
     http_client: HttpClient,
+    http: HttpBackend,
 }
 
 impl Client {
@@ -41,6 +46,7 @@ impl Client {
             base_url: DEEPSEEK_API_BASE_URL.to_string(),
             api_key: api_key.to_string(),
             http_client: HttpClient::new(),
+            http: HttpBackend::default(),
         }
     }
 
@@ -57,9 +63,48 @@ impl Client {
             base_url: base_url.to_string(),
             api_key: api_key.to_string(),
             http_client: HttpClient::new(),
+            http: HttpBackend::default(),
         }
     }
 
+    /// Add the header `name` with `value` to every request of the client.
+    /// Panics if the header name or value is invalid.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.http = self.http.with_header(name, value);
+        self
+    }
+
+    /// Get the API key of each request from `keys` (e.g.: a
+    /// [KeyPool](crate::key_provider::KeyPool) spreading the load across several keys) instead
+    /// of using the key given at construction. See [crate::key_provider].
+    pub fn with_key_provider(mut self, keys: impl KeyProvider + 'static) -> Self {
+        self.http = self.http.with_keys(keys, KeyPlacement::Bearer);
+        self
+    }
+
+    /// Send the requests of the client with `client`, e.g.: to configure a proxy, custom root
+    /// certificates, mTLS or the connection pool. The authentication headers of the provider
+    /// are added to every request.
+    pub fn with_reqwest_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = client;
+        self
+    }
+
+    /// Send the requests of the client through `client` instead of the default reqwest
+    /// client (e.g.: to use another HTTP library, or a fake in tests). See [crate::http_client].
+    pub fn with_http_client(mut self, client: impl http_client::HttpClient + 'static) -> Self {
+        self.http = self.http.with_client(client);
+        self
+    }
+
+    /// Modify the JSON requests and responses of the client with `transform` (e.g.: to add the
+    /// headers of an enterprise gateway, or strip the fields unsupported by a proxy). See
+    /// [crate::http_client::Transform].
+    pub fn with_transform(mut self, transform: impl http_client::Transform + 'static) -> Self {
+        self.http = self.http.with_transform(transform);
+        self
+    }
+
     fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
         self.http_client.post(url)
@@ -75,7 +120,7 @@ impl Client {
 
     /// Optionally add an agent() convenience:
     pub fn agent(&self, model_name: &str) -> crate::agent::AgentBuilder<DeepSeekCompletionModel> {
-        crate::agent::AgentBuilder::new(self.completion_model(model_name))
+        crate::agent::AgentBuilder::new(self.completion_model(model_name)).model_name(model_name)
     }
 
     /// Create an extractor builder with the given completion model.
@@ -87,6 +132,17 @@ impl Client {
     }
 }
 
+impl HealthCheck for Client {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn health(&self) -> Health {
+        health::check(
+            &self.http,
+            self.http_client.get(format!("{}/models", self.base_url)),
+        )
+        .await
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ApiErrorResponse {
     message: String,
@@ -161,7 +217,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
                         .iter()
                         .map(|call| {
                             completion::AssistantContent::tool_call(
-                                &call.function.name,
+                                &call.id,
                                 &call.function.name,
                                 call.function.arguments.clone(),
                             )
@@ -198,7 +254,22 @@ pub struct DeepSeekCompletionModel {
 impl CompletionModel for DeepSeekCompletionModel {
     type Response = CompletionResponse;
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "chat",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("chat {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "chat",
+            gen_ai.system = "deepseek",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens,
+            gen_ai.usage.output_tokens
+        )
+    ))]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
@@ -241,7 +312,7 @@ impl CompletionModel for DeepSeekCompletionModel {
                 "messages": full_history,
                 "temperature": completion_request.temperature,
                 "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
-                "tool_choice": "auto",
+                "tool_choice": ToolChoice::openai_or_auto(&completion_request.tool_choice),
             })
         };
 
@@ -255,7 +326,7 @@ impl CompletionModel for DeepSeekCompletionModel {
                     request
                 },
             )
-            .send()
+            .send_logged("deepseek", &self.client.http)
             .await?;
 
         if response.status().is_success() {
@@ -2,6 +2,9 @@ use crate::{
     agent::AgentBuilder,
     embeddings::{self},
     extractor::ExtractorBuilder,
+    health::{self, Health, HealthCheck},
+    http_client::{HttpBackend, HttpClient, Transform},
+    key_provider::{KeyPlacement, KeyProvider},
     Embed,
 };
 use schemars::JsonSchema;
@@ -19,6 +22,7 @@ pub struct Client {
     base_url: String,
     api_key: String,
     http_client: reqwest::Client,
+    pub(super) http: HttpBackend,
 }
 
 impl Client {
@@ -26,20 +30,20 @@ impl Client {
         Self::from_url(api_key, GEMINI_API_BASE_URL)
     }
     pub fn from_url(api_key: &str, base_url: &str) -> Self {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            "application/json".parse().unwrap(),
+        );
+
         Self {
             base_url: base_url.to_string(),
             api_key: api_key.to_string(),
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert(
-                        reqwest::header::CONTENT_TYPE,
-                        "application/json".parse().unwrap(),
-                    );
-                    headers
-                })
+                .default_headers(headers.clone())
                 .build()
                 .expect("Gemini reqwest client should build"),
+            http: HttpBackend::new(headers),
         }
     }
 
@@ -50,6 +54,44 @@ impl Client {
         Self::new(&api_key)
     }
 
+    /// Add the header `name` with `value` to every request of the client.
+    /// Panics if the header name or value is invalid.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.http = self.http.with_header(name, value);
+        self
+    }
+
+    /// Get the API key of each request from `keys` (e.g.: a
+    /// [KeyPool](crate::key_provider::KeyPool) spreading the load across several keys) instead
+    /// of using the key given at construction. See [crate::key_provider].
+    pub fn with_key_provider(mut self, keys: impl KeyProvider + 'static) -> Self {
+        self.http = self.http.with_keys(keys, KeyPlacement::Query("key"));
+        self
+    }
+
+    /// Send the requests of the client with `client`, e.g.: to configure a proxy, custom root
+    /// certificates, mTLS or the connection pool. The authentication headers of the provider
+    /// are added to every request.
+    pub fn with_reqwest_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = client;
+        self
+    }
+
+    /// Send the requests of the client through `client` instead of the default reqwest
+    /// client (e.g.: to use another HTTP library, or a fake in tests). See [crate::http_client].
+    pub fn with_http_client(mut self, client: impl HttpClient + 'static) -> Self {
+        self.http = self.http.with_client(client);
+        self
+    }
+
+    /// Modify the JSON requests and responses of the client with `transform` (e.g.: to add the
+    /// headers of an enterprise gateway, or strip the fields unsupported by a proxy). See
+    /// [crate::http_client::Transform].
+    pub fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.http = self.http.with_transform(transform);
+        self
+    }
+
     pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}?key={}", self.base_url, path, self.api_key).replace("//", "/");
 
@@ -135,7 +177,7 @@ impl Client {
     ///    .build();
     /// ```
     pub fn agent(&self, model: &str) -> AgentBuilder<CompletionModel> {
-        AgentBuilder::new(self.completion_model(model))
+        AgentBuilder::new(self.completion_model(model)).model_name(model)
     }
 
     /// Create an extractor builder with the given completion model.
@@ -147,6 +189,20 @@ impl Client {
     }
 }
 
+impl HealthCheck for Client {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn health(&self) -> Health {
+        health::check(
+            &self.http,
+            self.http_client.get(format!(
+                "{}/v1beta/models?key={}&pageSize=1",
+                self.base_url, self.api_key
+            )),
+        )
+        .await
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ApiErrorResponse {
     pub message: String,
@@ -21,6 +21,7 @@ use std::convert::TryFrom;
 
 use crate::{
     completion::{self, CompletionError, CompletionRequest},
+    payload_log::SendLogged,
     OneOrMany,
 };
 
@@ -48,7 +49,26 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = GenerateContentResponse;
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    fn supports_vision(&self) -> bool {
+        true
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "chat",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("chat {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "chat",
+            gen_ai.system = "gemini",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens,
+            gen_ai.usage.output_tokens
+        )
+    ))]
     async fn completion(
         &self,
         mut completion_request: CompletionRequest,
@@ -106,7 +126,7 @@ impl completion::CompletionModel for CompletionModel {
             .client
             .post(&format!("/v1beta/models/{}:generateContent", self.model))
             .json(&request)
-            .send()
+            .send_logged("gemini", &self.client.http)
             .await?
             .error_for_status()?
             .json::<GenerateContentResponse>()
@@ -122,6 +142,14 @@ impl completion::CompletionModel for CompletionModel {
             ),
         }
 
+        if let Some(usage) = &response.usage_metadata {
+            crate::telemetry::record_usage(
+                &self.model,
+                Some(usage.prompt_token_count as u64),
+                Some(usage.candidates_token_count as u64),
+            );
+        }
+
         tracing::debug!("Received response");
 
         completion::CompletionResponse::try_from(response)
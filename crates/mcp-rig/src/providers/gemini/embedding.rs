@@ -5,7 +5,10 @@
 
 use serde_json::json;
 
-use crate::embeddings::{self, EmbeddingError};
+use crate::{
+    embeddings::{self, EmbeddingError},
+    payload_log::SendLogged,
+};
 
 use super::{client::ApiResponse, Client};
 
@@ -41,7 +44,21 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
         }
     }
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "embeddings",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("embeddings {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "embeddings",
+            gen_ai.system = "gemini",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens
+        )
+    ))]
     async fn embed_texts(
         &self,
         documents: impl IntoIterator<Item = String> + Send,
@@ -62,7 +79,7 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
             .client
             .post(&format!("/v1beta/models/{}:embedContent", self.model))
             .json(&request_body)
-            .send()
+            .send_logged("gemini", &self.client.http)
             .await?
             .error_for_status()?
             .json::<ApiResponse<gemini_api_types::EmbeddingResponse>>()
@@ -11,12 +11,19 @@
 
 use crate::{
     agent::AgentBuilder,
+    audio_generation::{self, AudioGenerationError, AudioGenerationRequest},
     completion::{self, CompletionError, CompletionRequest},
     extractor::ExtractorBuilder,
+    health::{self, Health, HealthCheck},
+    http_client::{HttpBackend, HttpClient, Transform},
+    image_generation::{self, ImageGenerationError, ImageGenerationRequest},
     json_utils,
+    key_provider::{KeyPlacement, KeyProvider},
+    payload_log::SendLogged,
     providers::openai::Message,
     OneOrMany,
 };
+use base64::{prelude::BASE64_STANDARD, Engine};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -32,6 +39,7 @@ const HYPERBOLIC_API_BASE_URL: &str = "https://api.hyperbolic.xyz/v1";
 pub struct Client {
     base_url: String,
     http_client: reqwest::Client,
+    http: HttpBackend,
 }
 
 impl Client {
@@ -42,21 +50,21 @@ impl Client {
 
     /// Create a new OpenAI client with the given API key and base API URL.
     pub fn from_url(api_key: &str, base_url: &str) -> Self {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            format!("Bearer {}", api_key)
+                .parse()
+                .expect("Bearer token should parse"),
+        );
+
         Self {
             base_url: base_url.to_string(),
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert(
-                        "Authorization",
-                        format!("Bearer {}", api_key)
-                            .parse()
-                            .expect("Bearer token should parse"),
-                    );
-                    headers
-                })
+                .default_headers(headers.clone())
                 .build()
                 .expect("OpenAI reqwest client should build"),
+            http: HttpBackend::new(headers),
         }
     }
 
@@ -67,6 +75,44 @@ impl Client {
         Self::new(&api_key)
     }
 
+    /// Add the header `name` with `value` to every request of the client.
+    /// Panics if the header name or value is invalid.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.http = self.http.with_header(name, value);
+        self
+    }
+
+    /// Get the API key of each request from `keys` (e.g.: a
+    /// [KeyPool](crate::key_provider::KeyPool) spreading the load across several keys) instead
+    /// of using the key given at construction. See [crate::key_provider].
+    pub fn with_key_provider(mut self, keys: impl KeyProvider + 'static) -> Self {
+        self.http = self.http.with_keys(keys, KeyPlacement::Bearer);
+        self
+    }
+
+    /// Send the requests of the client with `client`, e.g.: to configure a proxy, custom root
+    /// certificates, mTLS or the connection pool. The authentication headers of the provider
+    /// are added to every request.
+    pub fn with_reqwest_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = client;
+        self
+    }
+
+    /// Send the requests of the client through `client` instead of the default reqwest
+    /// client (e.g.: to use another HTTP library, or a fake in tests). See [crate::http_client].
+    pub fn with_http_client(mut self, client: impl HttpClient + 'static) -> Self {
+        self.http = self.http.with_client(client);
+        self
+    }
+
+    /// Modify the JSON requests and responses of the client with `transform` (e.g.: to add the
+    /// headers of an enterprise gateway, or strip the fields unsupported by a proxy). See
+    /// [crate::http_client::Transform].
+    pub fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.http = self.http.with_transform(transform);
+        self
+    }
+
     fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
         self.http_client.post(url)
@@ -102,7 +148,7 @@ impl Client {
     ///    .build();
     /// ```
     pub fn agent(&self, model: &str) -> AgentBuilder<CompletionModel> {
-        AgentBuilder::new(self.completion_model(model))
+        AgentBuilder::new(self.completion_model(model)).model_name(model)
     }
 
     /// Create an extractor builder with the given completion model.
@@ -112,6 +158,37 @@ impl Client {
     ) -> ExtractorBuilder<T, CompletionModel> {
         ExtractorBuilder::new(self.completion_model(model))
     }
+
+    /// Create an image generation model with the given name.
+    ///
+    /// # Example
+    /// ```
+    /// use mcp_rig::providers::hyperbolic::{Client, self};
+    ///
+    /// // Initialize the Hyperbolic client
+    /// let hyperbolic = Client::new("your-hyperbolic-api-key");
+    ///
+    /// let flux = hyperbolic.image_generation_model(hyperbolic::FLUX_1_DEV);
+    /// ```
+    pub fn image_generation_model(&self, model: &str) -> ImageGenerationModel {
+        ImageGenerationModel::new(self.clone(), model)
+    }
+
+    /// Create the text-to-speech model of Hyperbolic.
+    pub fn audio_generation_model(&self) -> AudioGenerationModel {
+        AudioGenerationModel::new(self.clone())
+    }
+}
+
+impl HealthCheck for Client {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn health(&self) -> Health {
+        health::check(
+            &self.http,
+            self.http_client.get(format!("{}/models", self.base_url)),
+        )
+        .await
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -225,7 +302,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
                         .iter()
                         .map(|call| {
                             completion::AssistantContent::tool_call(
-                                &call.function.name,
+                                &call.id,
                                 &call.function.name,
                                 call.function.arguments.clone(),
                             )
@@ -278,7 +355,22 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "chat",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("chat {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "chat",
+            gen_ai.system = "hyperbolic",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens,
+            gen_ai.usage.output_tokens
+        )
+    ))]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
@@ -322,7 +414,7 @@ impl completion::CompletionModel for CompletionModel {
                     request
                 },
             )
-            .send()
+            .send_logged("hyperbolic", &self.client.http)
             .await?;
 
         if response.status().is_success() {
@@ -332,6 +424,13 @@ impl completion::CompletionModel for CompletionModel {
                         "Hyperbolic completion token usage: {:?}",
                         response.usage.clone().map(|usage| format!("{usage}")).unwrap_or("N/A".to_string())
                     );
+                    if let Some(usage) = &response.usage {
+                        crate::telemetry::record_usage(
+                            &self.model,
+                            Some(usage.prompt_tokens as u64),
+                            Some(usage.total_tokens.saturating_sub(usage.prompt_tokens) as u64),
+                        );
+                    }
 
                     response.try_into()
                 }
@@ -342,3 +441,257 @@ impl completion::CompletionModel for CompletionModel {
         }
     }
 }
+
+// ================================================================
+// Hyperbolic Image Generation API
+// ================================================================
+/// `SDXL1.0-base` image generation model
+pub const SDXL_1_0_BASE: &str = "SDXL1.0-base";
+/// `SDXL-turbo` image generation model
+pub const SDXL_TURBO: &str = "SDXL-turbo";
+/// `SD2` image generation model
+pub const SD_2: &str = "SD2";
+/// `SD1.5` image generation model
+pub const SD_1_5: &str = "SD1.5";
+/// `SSD` image generation model
+pub const SSD: &str = "SSD";
+/// `FLUX.1-dev` image generation model
+pub const FLUX_1_DEV: &str = "FLUX.1-dev";
+
+/// A Hyperbolic image generation response.
+///
+/// For more information, see this link: <https://docs.hyperbolic.xyz/docs/rest-api>
+#[derive(Debug, Deserialize)]
+pub struct ImageGenerationResponse {
+    pub images: Vec<GeneratedImage>,
+    pub inference_time: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GeneratedImage {
+    pub index: usize,
+    /// Base64-encoded image
+    pub image: String,
+    pub random_seed: Option<u64>,
+}
+
+impl TryFrom<ImageGenerationResponse>
+    for image_generation::ImageGenerationResponse<ImageGenerationResponse>
+{
+    type Error = ImageGenerationError;
+
+    fn try_from(response: ImageGenerationResponse) -> Result<Self, Self::Error> {
+        let image = response.images.first().ok_or_else(|| {
+            ImageGenerationError::ResponseError("Response contained no images".to_owned())
+        })?;
+        let image = BASE64_STANDARD
+            .decode(&image.image)
+            .map_err(|e| ImageGenerationError::ResponseError(e.to_string()))?;
+
+        Ok(image_generation::ImageGenerationResponse { image, response })
+    }
+}
+
+#[derive(Clone)]
+pub struct ImageGenerationModel {
+    client: Client,
+    /// Name of the model (e.g.: SDXL1.0-base)
+    pub model: String,
+}
+
+impl ImageGenerationModel {
+    pub fn new(client: Client, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+        }
+    }
+}
+
+impl image_generation::ImageGenerationModel for ImageGenerationModel {
+    type Response = ImageGenerationResponse;
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn image_generation(
+        &self,
+        request: ImageGenerationRequest,
+    ) -> Result<image_generation::ImageGenerationResponse<Self::Response>, ImageGenerationError>
+    {
+        let body = json!({
+            "model_name": self.model,
+            "prompt": request.prompt,
+            "width": request.width,
+            "height": request.height,
+        });
+
+        let response = self
+            .client
+            .post("/image/generation")
+            .json(&if let Some(params) = request.additional_params {
+                json_utils::merge(body, params)
+            } else {
+                body
+            })
+            .send_logged("hyperbolic", &self.client.http)
+            .await?;
+
+        if response.status().is_success() {
+            match response
+                .json::<ApiResponse<ImageGenerationResponse>>()
+                .await?
+            {
+                ApiResponse::Ok(response) => response.try_into(),
+                ApiResponse::Err(err) => Err(ImageGenerationError::ProviderError(err.message)),
+            }
+        } else {
+            Err(ImageGenerationError::ProviderError(response.text().await?))
+        }
+    }
+}
+
+// ================================================================
+// Hyperbolic Audio Generation API
+// ================================================================
+/// A Hyperbolic audio generation (text-to-speech) response.
+///
+/// For more information, see this link: <https://docs.hyperbolic.xyz/docs/rest-api>
+#[derive(Debug, Deserialize)]
+pub struct AudioGenerationResponse {
+    /// Base64-encoded MP3 audio
+    pub audio: String,
+}
+
+impl TryFrom<AudioGenerationResponse>
+    for audio_generation::AudioGenerationResponse<AudioGenerationResponse>
+{
+    type Error = AudioGenerationError;
+
+    fn try_from(response: AudioGenerationResponse) -> Result<Self, Self::Error> {
+        let audio = BASE64_STANDARD
+            .decode(&response.audio)
+            .map_err(|e| AudioGenerationError::ResponseError(e.to_string()))?;
+
+        Ok(audio_generation::AudioGenerationResponse { audio, response })
+    }
+}
+
+/// The text-to-speech model of Hyperbolic. The voice of the requests is the `speaker` of the
+/// model (e.g.: `EN-US`, `EN-BR`, `EN-AU`).
+#[derive(Clone)]
+pub struct AudioGenerationModel {
+    client: Client,
+}
+
+impl AudioGenerationModel {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+impl audio_generation::AudioGenerationModel for AudioGenerationModel {
+    type Response = AudioGenerationResponse;
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn audio_generation(
+        &self,
+        request: AudioGenerationRequest,
+    ) -> Result<audio_generation::AudioGenerationResponse<Self::Response>, AudioGenerationError>
+    {
+        let mut body = json!({ "text": request.text });
+        if let Some(voice) = request.voice {
+            body["speaker"] = json!(voice);
+        }
+        if let Some(speed) = request.speed {
+            body["speed"] = json!(speed);
+        }
+
+        let response = self
+            .client
+            .post("/audio/generation")
+            .json(&if let Some(params) = request.additional_params {
+                json_utils::merge(body, params)
+            } else {
+                body
+            })
+            .send_logged("hyperbolic", &self.client.http)
+            .await?;
+
+        if response.status().is_success() {
+            match response
+                .json::<ApiResponse<AudioGenerationResponse>>()
+                .await?
+            {
+                ApiResponse::Ok(response) => response.try_into(),
+                ApiResponse::Err(err) => Err(AudioGenerationError::ProviderError(err.message)),
+            }
+        } else {
+            Err(AudioGenerationError::ProviderError(response.text().await?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audio_generation::AudioGenerationModel as _, image_generation::ImageGenerationModel as _,
+    };
+
+    #[derive(Clone, Default)]
+    struct FakeClient {
+        requests: std::sync::Arc<std::sync::Mutex<Vec<(String, serde_json::Value)>>>,
+    }
+
+    impl HttpClient for FakeClient {
+        async fn send(
+            &self,
+            request: http::Request<bytes::Bytes>,
+        ) -> Result<http::Response<bytes::Bytes>, crate::http_client::HttpClientError> {
+            let path = request.uri().path().to_string();
+            let body = serde_json::from_slice(request.body()).unwrap();
+            self.requests.lock().unwrap().push((path.clone(), body));
+
+            let encoded = BASE64_STANDARD.encode(b"flurbo");
+            let response = if path.ends_with("/image/generation") {
+                json!({"images": [{"index": 0, "image": encoded, "random_seed": 42}], "inference_time": 1.5})
+            } else {
+                json!({"audio": encoded})
+            };
+            Ok(http::Response::new(response.to_string().into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_image_and_audio_generation() {
+        let fake = FakeClient::default();
+        let client = Client::new("sk-123").with_http_client(fake.clone());
+
+        let response = client
+            .image_generation_model(FLUX_1_DEV)
+            .image_generation_request("A flurbo")
+            .width(512)
+            .additional_params(json!({"steps": 20}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.image, b"flurbo");
+        assert_eq!(response.response.images[0].random_seed, Some(42));
+
+        let response = client
+            .audio_generation_model()
+            .audio_generation_request("Hello")
+            .voice("EN-US")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.audio, b"flurbo");
+
+        let requests = fake.requests.lock().unwrap();
+        assert_eq!(
+            requests[0].1,
+            json!({"model_name": FLUX_1_DEV, "prompt": "A flurbo", "width": 512, "height": 1024, "steps": 20})
+        );
+        assert!(requests[1].0.ends_with("/v1/audio/generation"));
+        assert_eq!(requests[1].1, json!({"text": "Hello", "speaker": "EN-US"}));
+    }
+}
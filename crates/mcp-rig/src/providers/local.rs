@@ -0,0 +1,430 @@
+//! Local embedding models running on ONNX Runtime (through [fastembed](https://docs.rs/fastembed)).
+//!
+//! Models are downloaded from Hugging Face on first use and cached locally, after which
+//! embeddings can be generated fully offline and at no cost. The inference runs on a dedicated
+//! worker thread shared by all the models, so that it doesn't block the async executor (of any
+//! runtime).
+//! Requires the `local` feature.
+//!
+//! # Example
+//! ```
+//! use mcp_rig::providers::local;
+//!
+//! let client = local::Client::new().cache_dir("./.fastembed_cache");
+//!
+//! let bge_small = client.embedding_model(local::BGE_SMALL_EN_V1_5)?;
+//! ```
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+
+use fastembed::{InitOptions, SparseInitOptions, SparseTextEmbedding, TextEmbedding};
+use futures::channel::{mpsc, oneshot};
+
+use crate::{
+    embeddings::{self, sparse, EmbeddingError, EmbeddingsBuilder},
+    Embed,
+};
+
+// ================================================================
+// Main Local Client
+// ================================================================
+#[derive(Clone, Default)]
+pub struct Client {
+    cache_dir: Option<PathBuf>,
+    show_download_progress: bool,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the directory in which downloaded models are cached.
+    /// Defaults to fastembed's default cache directory.
+    pub fn cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// Show a progress bar while downloading models.
+    pub fn show_download_progress(mut self, show: bool) -> Self {
+        self.show_download_progress = show;
+        self
+    }
+
+    /// Load the embedding model with the given name, downloading it if necessary.
+    /// Returns an error if the model is unknown or cannot be loaded.
+    ///
+    /// # Example
+    /// ```
+    /// use mcp_rig::providers::local;
+    ///
+    /// let client = local::Client::new();
+    ///
+    /// let embedding_model = client.embedding_model(local::ALL_MINILM_L6_V2)?;
+    /// ```
+    pub fn embedding_model(&self, model: &str) -> Result<EmbeddingModel, EmbeddingError> {
+        let (fastembed_model, ndims) = match model {
+            BGE_SMALL_EN_V1_5 => (fastembed::EmbeddingModel::BGESmallENV15, 384),
+            BGE_BASE_EN_V1_5 => (fastembed::EmbeddingModel::BGEBaseENV15, 768),
+            BGE_LARGE_EN_V1_5 => (fastembed::EmbeddingModel::BGELargeENV15, 1024),
+            GTE_BASE_EN_V1_5 => (fastembed::EmbeddingModel::GTEBaseENV15, 768),
+            GTE_LARGE_EN_V1_5 => (fastembed::EmbeddingModel::GTELargeENV15, 1024),
+            ALL_MINILM_L6_V2 => (fastembed::EmbeddingModel::AllMiniLML6V2, 384),
+            ALL_MINILM_L12_V2 => (fastembed::EmbeddingModel::AllMiniLML12V2, 384),
+            NOMIC_EMBED_TEXT_V1_5 => (fastembed::EmbeddingModel::NomicEmbedTextV15, 768),
+            _ => {
+                return Err(EmbeddingError::ProviderError(format!(
+                    "Unknown local embedding model: {model}"
+                )))
+            }
+        };
+
+        let mut options = InitOptions::new(fastembed_model)
+            .with_show_download_progress(self.show_download_progress);
+        if let Some(cache_dir) = &self.cache_dir {
+            options = options.with_cache_dir(cache_dir.clone());
+        }
+
+        let embedding = TextEmbedding::try_new(options)
+            .map_err(|e| EmbeddingError::ProviderError(e.to_string()))?;
+
+        Ok(EmbeddingModel::new(embedding, model, ndims))
+    }
+
+    /// Load the sparse (SPLADE) embedding model with the given name, downloading it if necessary.
+    /// Returns an error if the model is unknown or cannot be loaded.
+    ///
+    /// # Example
+    /// ```
+    /// use mcp_rig::providers::local;
+    ///
+    /// let client = local::Client::new();
+    ///
+    /// let sparse_model = client.sparse_embedding_model(local::SPLADE_PP_EN_V1)?;
+    /// ```
+    pub fn sparse_embedding_model(
+        &self,
+        model: &str,
+    ) -> Result<SparseEmbeddingModel, EmbeddingError> {
+        let fastembed_model = match model {
+            SPLADE_PP_EN_V1 => fastembed::SparseModel::SPLADEPPV1,
+            _ => {
+                return Err(EmbeddingError::ProviderError(format!(
+                    "Unknown local sparse embedding model: {model}"
+                )))
+            }
+        };
+
+        let mut options = SparseInitOptions::new(fastembed_model)
+            .with_show_download_progress(self.show_download_progress);
+        if let Some(cache_dir) = &self.cache_dir {
+            options = options.with_cache_dir(cache_dir.clone());
+        }
+
+        let embedding = SparseTextEmbedding::try_new(options)
+            .map_err(|e| EmbeddingError::ProviderError(e.to_string()))?;
+
+        Ok(SparseEmbeddingModel::new(embedding, model))
+    }
+
+    /// Create an embedding builder with the given embedding model.
+    ///
+    /// # Example
+    /// ```
+    /// use mcp_rig::providers::local;
+    ///
+    /// let client = local::Client::new();
+    ///
+    /// let embeddings = client.embeddings(local::BGE_SMALL_EN_V1_5)?
+    ///     .simple_document("doc0", "Hello, world!")
+    ///     .simple_document("doc1", "Goodbye, world!")
+    ///     .build()
+    ///     .await
+    ///     .expect("Failed to embed documents");
+    /// ```
+    pub fn embeddings<D: Embed>(
+        &self,
+        model: &str,
+    ) -> Result<EmbeddingsBuilder<EmbeddingModel, D>, EmbeddingError> {
+        Ok(EmbeddingsBuilder::new(self.embedding_model(model)?))
+    }
+}
+
+// ================================================================
+// Local Embedding API
+// ================================================================
+/// `BAAI/bge-small-en-v1.5` embedding model
+pub const BGE_SMALL_EN_V1_5: &str = "BAAI/bge-small-en-v1.5";
+/// `BAAI/bge-base-en-v1.5` embedding model
+pub const BGE_BASE_EN_V1_5: &str = "BAAI/bge-base-en-v1.5";
+/// `BAAI/bge-large-en-v1.5` embedding model
+pub const BGE_LARGE_EN_V1_5: &str = "BAAI/bge-large-en-v1.5";
+/// `Alibaba-NLP/gte-base-en-v1.5` embedding model
+pub const GTE_BASE_EN_V1_5: &str = "Alibaba-NLP/gte-base-en-v1.5";
+/// `Alibaba-NLP/gte-large-en-v1.5` embedding model
+pub const GTE_LARGE_EN_V1_5: &str = "Alibaba-NLP/gte-large-en-v1.5";
+/// `sentence-transformers/all-MiniLM-L6-v2` embedding model
+pub const ALL_MINILM_L6_V2: &str = "sentence-transformers/all-MiniLM-L6-v2";
+/// `sentence-transformers/all-MiniLM-L12-v2` embedding model
+pub const ALL_MINILM_L12_V2: &str = "sentence-transformers/all-MiniLM-L12-v2";
+/// `nomic-ai/nomic-embed-text-v1.5` embedding model
+pub const NOMIC_EMBED_TEXT_V1_5: &str = "nomic-ai/nomic-embed-text-v1.5";
+
+#[derive(Clone)]
+pub struct EmbeddingModel {
+    embedding: Arc<TextEmbedding>,
+    pub model: String,
+    ndims: usize,
+}
+
+impl embeddings::EmbeddingModel for EmbeddingModel {
+    const MAX_DOCUMENTS: usize = 256;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
+
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "embeddings",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("embeddings {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "embeddings",
+            gen_ai.system = "fastembed",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens
+        )
+    ))]
+    async fn embed_texts(
+        &self,
+        documents: impl IntoIterator<Item = String>,
+    ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
+        let documents = documents.into_iter().collect::<Vec<_>>();
+        let embedding = self.embedding.clone();
+
+        let (documents, vectors) = run_blocking(move || {
+            let vectors = embedding.embed(documents.iter().collect::<Vec<_>>(), None);
+            (documents, vectors)
+        })
+        .await?;
+
+        let vectors = vectors.map_err(|e| EmbeddingError::ProviderError(e.to_string()))?;
+        zip_documents(documents, vectors, |document, vec| embeddings::Embedding {
+            document,
+            vec: vec.into_iter().map(|x| x as f64).collect(),
+        })
+    }
+}
+
+impl EmbeddingModel {
+    pub fn new(embedding: TextEmbedding, model: &str, ndims: usize) -> Self {
+        Self {
+            embedding: Arc::new(embedding),
+            model: model.to_string(),
+            ndims,
+        }
+    }
+}
+
+/// `prithivida/Splade_PP_en_v1` sparse embedding model
+pub const SPLADE_PP_EN_V1: &str = "prithivida/Splade_PP_en_v1";
+
+#[derive(Clone)]
+pub struct SparseEmbeddingModel {
+    embedding: Arc<SparseTextEmbedding>,
+    pub model: String,
+}
+
+impl sparse::SparseEmbeddingModel for SparseEmbeddingModel {
+    const MAX_DOCUMENTS: usize = 256;
+
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "embeddings",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("embeddings {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "embeddings",
+            gen_ai.system = "fastembed",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens
+        )
+    ))]
+    async fn embed_texts(
+        &self,
+        documents: impl IntoIterator<Item = String>,
+    ) -> Result<Vec<sparse::SparseEmbedding>, EmbeddingError> {
+        let documents = documents.into_iter().collect::<Vec<_>>();
+        let embedding = self.embedding.clone();
+
+        let (documents, vectors) = run_blocking(move || {
+            let vectors = embedding.embed(documents.iter().collect::<Vec<_>>(), None);
+            (documents, vectors)
+        })
+        .await?;
+
+        let vectors = vectors.map_err(|e| EmbeddingError::ProviderError(e.to_string()))?;
+        zip_documents(documents, vectors, |document, vec| {
+            sparse::SparseEmbedding {
+                document,
+                indices: vec.indices.into_iter().map(|i| i as u32).collect(),
+                values: vec.values,
+            }
+        })
+    }
+}
+
+impl SparseEmbeddingModel {
+    pub fn new(embedding: SparseTextEmbedding, model: &str) -> Self {
+        Self {
+            embedding: Arc::new(embedding),
+            model: model.to_string(),
+        }
+    }
+}
+
+// ================================================================
+// Inference
+// ================================================================
+
+type InferenceJob = Box<dyn FnOnce() + Send>;
+
+/// Run `f` (i.e.: the CPU-bound inference) on the worker thread, rather than blocking the async
+/// executor. The thread is spawned with the standard library on first use, so that it works with
+/// any async runtime (or none), and runs the batches one after the other (ONNX Runtime already
+/// parallelizes each batch).
+async fn run_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, EmbeddingError> {
+    static SENDER: OnceLock<Result<mpsc::UnboundedSender<InferenceJob>, String>> = OnceLock::new();
+
+    let sender = SENDER
+        .get_or_init(|| {
+            let (sender, receiver) = mpsc::unbounded::<InferenceJob>();
+            std::thread::Builder::new()
+                .name("fastembed".to_string())
+                .spawn(move || {
+                    for job in futures::executor::block_on_stream(receiver) {
+                        // A panicking batch fails its own request only
+                        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                    }
+                })
+                .map(|_| sender)
+                .map_err(|e| e.to_string())
+        })
+        .as_ref()
+        .map_err(|e| EmbeddingError::ProviderError(e.clone()))?;
+
+    let (result_sender, result_receiver) = oneshot::channel();
+    sender
+        .unbounded_send(Box::new(move || {
+            let _ = result_sender.send(f());
+        }))
+        .map_err(|e| EmbeddingError::ProviderError(e.to_string()))?;
+    result_receiver
+        .await
+        .map_err(|_| EmbeddingError::ProviderError("The inference panicked".to_string()))
+}
+
+/// Embeddings of `documents`, from their `vectors` in the same order
+fn zip_documents<V, E>(
+    documents: Vec<String>,
+    vectors: Vec<V>,
+    embedding: impl Fn(String, V) -> E,
+) -> Result<Vec<E>, EmbeddingError> {
+    if vectors.len() != documents.len() {
+        return Err(EmbeddingError::ResponseError(
+            "Response data length does not match input length".into(),
+        ));
+    }
+
+    Ok(documents
+        .into_iter()
+        .zip(vectors)
+        .map(|(document, vec)| embedding(document, vec))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local() {
+        // The unknown models are rejected before any download
+        let client = Client::new();
+        assert!(matches!(
+            client.embedding_model("unknown/model"),
+            Err(EmbeddingError::ProviderError(_))
+        ));
+        assert!(matches!(
+            client.sparse_embedding_model("unknown/model"),
+            Err(EmbeddingError::ProviderError(_))
+        ));
+
+        // The inference runs outside of any Tokio runtime
+        let documents = vec!["first".to_string(), "second".to_string()];
+        let (documents, vectors) = futures::executor::block_on(run_blocking(move || {
+            let vectors = documents
+                .iter()
+                .map(|document| vec![document.len() as f32])
+                .collect::<Vec<_>>();
+            (documents, vectors)
+        }))
+        .unwrap();
+
+        // A panicking batch doesn't stop the worker thread
+        assert!(matches!(
+            futures::executor::block_on(run_blocking(|| panic!("inference failed"))),
+            Err::<(), _>(EmbeddingError::ProviderError(_))
+        ));
+        assert_eq!(futures::executor::block_on(run_blocking(|| 1)).unwrap(), 1);
+
+        // The embeddings keep the order of the documents
+        let embeddings = zip_documents(documents.clone(), vectors, |document, vec| {
+            (document, vec[0])
+        })
+        .unwrap();
+        assert_eq!(
+            embeddings,
+            vec![("first".to_string(), 5.0), ("second".to_string(), 6.0)]
+        );
+        assert!(matches!(
+            zip_documents(documents, vec![vec![1.0]], |document, vec| (document, vec)),
+            Err(EmbeddingError::ResponseError(_))
+        ));
+    }
+
+    #[test]
+    #[ignore = "downloads the models from Hugging Face"]
+    fn test_local_models() {
+        use crate::embeddings::{sparse::SparseEmbeddingModel as _, EmbeddingModel as _};
+
+        let dir = assert_fs::TempDir::new().unwrap();
+        let client = Client::new().cache_dir(dir.path());
+        let documents = vec!["Hello, world!".to_string(), "Goodbye, world!".to_string()];
+
+        let model = client.embedding_model(ALL_MINILM_L6_V2).unwrap();
+        let embeddings = futures::executor::block_on(model.embed_texts(documents.clone())).unwrap();
+        assert_eq!(embeddings.len(), 2);
+        assert_eq!(embeddings[1].document, "Goodbye, world!");
+        assert!(embeddings.iter().all(|e| e.vec.len() == model.ndims()));
+
+        let model = client.sparse_embedding_model(SPLADE_PP_EN_V1).unwrap();
+        let embeddings = futures::executor::block_on(model.embed_texts(documents)).unwrap();
+        assert_eq!(embeddings.len(), 2);
+        assert_eq!(embeddings[0].document, "Hello, world!");
+        assert!(embeddings
+            .iter()
+            .all(|e| !e.indices.is_empty() && e.indices.len() == e.values.len()));
+    }
+}
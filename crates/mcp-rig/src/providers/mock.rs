@@ -0,0 +1,551 @@
+//! Mock provider, for testing applications built with Rig deterministically, without network
+//! access or API keys.
+//!
+//! [CompletionModel] is a scriptable completion model which answers with:
+//! 1. the response of the first rule matching the request (see [CompletionModel::when]),
+//! 2. otherwise, the next queued response (see [CompletionModel::push]),
+//! 3. otherwise, the default response (see [CompletionModel::default_response]).
+//!
+//! Responses can be texts, tool calls (which are executed by agents like real ones) or errors.
+//! Every request is recorded, so that tests can assert on what the model was sent. The model
+//! also streams its responses (the texts by chunks of a few characters).
+//!
+//! [EmbeddingModel] returns deterministic embeddings (derived from the hash of each document,
+//! unless fixed ones are provided).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{completion::Prompt, providers::mock::{self, MockResponse}};
+//!
+//! let model = mock::CompletionModel::new()
+//!     .when_prompt_contains("weather", MockResponse::tool_call("get_weather", json!({"city": "Paris"})))
+//!     .default_response(MockResponse::text("I don't know"));
+//!
+//! let agent = model.agent().tool(GetWeather).build();
+//!
+//! assert_eq!(agent.prompt("What is the weather in Paris?").await?, "Sunny, 24°C");
+//! assert_eq!(agent.prompt("Who are you?").await?, "I don't know");
+//! assert_eq!(model.requests().len(), 2);
+//! ```
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures_timer::Delay;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    agent::AgentBuilder,
+    completion::{self, CompletionError, CompletionRequest},
+    embeddings::{self, EmbeddingError},
+    extractor::ExtractorBuilder,
+    message::{AssistantContent, Message},
+    one_or_many::OneOrMany,
+    streaming::{StreamingChoice, StreamingCompletionModel, StreamingResult},
+};
+
+/// Number of characters of the text chunks streamed by a mock [CompletionModel].
+const STREAM_CHUNK_CHARS: usize = 4;
+
+/// Scripted response of a mock [CompletionModel].
+#[derive(Clone, Debug)]
+pub enum MockResponse {
+    /// Answer with a text
+    Text(String),
+    /// Answer with a call to the tool `name`
+    ToolCall {
+        name: String,
+        arguments: serde_json::Value,
+    },
+    /// Fail with a provider error
+    Error(String),
+}
+
+impl MockResponse {
+    pub fn text(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+
+    pub fn tool_call(name: &str, arguments: serde_json::Value) -> Self {
+        Self::ToolCall {
+            name: name.to_string(),
+            arguments,
+        }
+    }
+
+    pub fn error(message: &str) -> Self {
+        Self::Error(message.to_string())
+    }
+}
+
+/// Request received by a mock [CompletionModel].
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    pub prompt: Message,
+    pub preamble: Option<String>,
+    pub chat_history: Vec<Message>,
+    /// Names of the tools sent with the request
+    pub tools: Vec<String>,
+    /// Ids of the documents sent with the request
+    pub documents: Vec<String>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u64>,
+    pub tool_choice: Option<completion::ToolChoice>,
+    pub additional_params: Option<serde_json::Value>,
+}
+
+impl MockRequest {
+    /// Text of the prompt (i.e.: its first text content), if any.
+    pub fn prompt_text(&self) -> Option<String> {
+        self.prompt.rag_text()
+    }
+}
+
+type Matcher = Box<dyn Fn(&CompletionRequest) -> bool + Send + Sync>;
+
+#[derive(Default)]
+struct CompletionState {
+    rules: Vec<(Matcher, MockResponse)>,
+    queue: VecDeque<MockResponse>,
+    default: Option<MockResponse>,
+    latency: Option<Duration>,
+    usage: Option<(u64, u64)>,
+    requests: Vec<MockRequest>,
+}
+
+/// Scriptable mock completion model. Clones of a model share the same script and
+/// recorded requests.
+#[derive(Clone, Default)]
+pub struct CompletionModel {
+    state: Arc<Mutex<CompletionState>>,
+}
+
+impl CompletionModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, CompletionState> {
+        self.state.lock().expect("Mock model lock poisoned")
+    }
+
+    /// Answer with `response` to every request for which `matcher` returns true.
+    /// Rules are checked in the order they were added.
+    pub fn when(
+        self,
+        matcher: impl Fn(&CompletionRequest) -> bool + Send + Sync + 'static,
+        response: MockResponse,
+    ) -> Self {
+        self.state().rules.push((Box::new(matcher), response));
+        self
+    }
+
+    /// Answer with `response` to every request whose prompt contains `text`.
+    pub fn when_prompt_contains(self, text: &str, response: MockResponse) -> Self {
+        let text = text.to_string();
+        self.when(
+            move |request| {
+                request
+                    .prompt
+                    .rag_text()
+                    .is_some_and(|prompt| prompt.contains(&text))
+            },
+            response,
+        )
+    }
+
+    /// Queue a response. Queued responses are returned once each, in order, to the requests
+    /// which do not match any rule.
+    pub fn push(self, response: MockResponse) -> Self {
+        self.state().queue.push_back(response);
+        self
+    }
+
+    /// Set the response to the requests which do not match any rule once the queue is empty.
+    /// Without a default response, such requests fail with a provider error.
+    pub fn default_response(self, response: MockResponse) -> Self {
+        self.state().default = Some(response);
+        self
+    }
+
+    /// Delay every response by `latency`.
+    pub fn latency(self, latency: Duration) -> Self {
+        self.state().latency = Some(latency);
+        self
+    }
+
+    /// Report the given token usage (as the `mock` model) for every successful response,
+    /// e.g.: to test usage accounting.
+    pub fn usage(self, input_tokens: u64, output_tokens: u64) -> Self {
+        self.state().usage = Some((input_tokens, output_tokens));
+        self
+    }
+
+    /// Requests received so far, in order.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state().requests.clone()
+    }
+
+    /// Create an agent builder with this model.
+    pub fn agent(&self) -> AgentBuilder<Self> {
+        AgentBuilder::new(self.clone()).model_name("mock")
+    }
+
+    /// Create an extractor builder with this model.
+    pub fn extractor<T>(&self) -> ExtractorBuilder<T, Self>
+    where
+        T: schemars::JsonSchema + for<'a> serde::Deserialize<'a> + serde::Serialize + Send + Sync,
+    {
+        ExtractorBuilder::new(self.clone())
+    }
+
+    /// Record `request` and pick its response. Returns the index of the request, its response
+    /// and the latency to simulate.
+    fn respond(
+        &self,
+        request: &CompletionRequest,
+    ) -> (usize, Option<MockResponse>, Option<Duration>) {
+        let mut state = self.state();
+        let index = state.requests.len();
+
+        state.requests.push(MockRequest {
+            prompt: request.prompt.clone(),
+            preamble: request.preamble.clone(),
+            chat_history: request.chat_history.clone(),
+            tools: request.tools.iter().map(|tool| tool.name.clone()).collect(),
+            documents: request.documents.iter().map(|doc| doc.id.clone()).collect(),
+            temperature: request.temperature,
+            max_tokens: request.max_tokens,
+            tool_choice: request.tool_choice.clone(),
+            additional_params: request.additional_params.clone(),
+        });
+
+        let rule = state
+            .rules
+            .iter()
+            .find(|(matcher, _)| matcher(request))
+            .map(|(_, response)| response.clone());
+        let response = rule
+            .or_else(|| state.queue.pop_front())
+            .or_else(|| state.default.clone());
+
+        (index, response, state.latency)
+    }
+
+    /// Content of the mock `response` to the `index`-th request, recording its usage
+    fn choice(
+        &self,
+        request: &CompletionRequest,
+        index: usize,
+        response: Option<MockResponse>,
+    ) -> Result<AssistantContent, CompletionError> {
+        let choice = match response {
+            Some(MockResponse::Text(text)) => AssistantContent::text(text),
+            Some(MockResponse::ToolCall { name, arguments }) => {
+                AssistantContent::tool_call(format!("call_{index}"), name, arguments)
+            }
+            Some(MockResponse::Error(message)) => {
+                return Err(CompletionError::ProviderError(message))
+            }
+            None => {
+                return Err(CompletionError::ProviderError(format!(
+                    "No mock response for prompt: {:?}",
+                    request.prompt.rag_text().unwrap_or_default()
+                )))
+            }
+        };
+
+        let usage = self.state().usage;
+        if let Some((input_tokens, output_tokens)) = usage {
+            crate::telemetry::record_usage("mock", Some(input_tokens), Some(output_tokens));
+        }
+        Ok(choice)
+    }
+}
+
+impl completion::CompletionModel for CompletionModel {
+    type Response = ();
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<()>, CompletionError> {
+        let (index, response, latency) = self.respond(&request);
+
+        if let Some(latency) = latency {
+            Delay::new(latency).await;
+        }
+
+        let choice = self.choice(&request, index, response)?;
+        Ok(completion::CompletionResponse {
+            choice: OneOrMany::one(choice),
+            raw_response: (),
+        })
+    }
+}
+
+impl StreamingCompletionModel for CompletionModel {
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamingResult, CompletionError> {
+        let (index, response, latency) = self.respond(&request);
+
+        if let Some(latency) = latency {
+            Delay::new(latency).await;
+        }
+
+        let chunks = match self.choice(&request, index, response)? {
+            AssistantContent::Text(text) => text
+                .text
+                .chars()
+                .collect::<Vec<_>>()
+                .chunks(STREAM_CHUNK_CHARS)
+                .map(|chunk| Ok(StreamingChoice::Message(chunk.iter().collect())))
+                .collect::<Vec<_>>(),
+            AssistantContent::ToolCall(tool_call) => vec![Ok(StreamingChoice::ToolCall(
+                tool_call.function.name,
+                tool_call.id,
+                tool_call.function.arguments,
+            ))],
+        };
+        Ok(Box::pin(futures::stream::iter(chunks)))
+    }
+}
+
+#[derive(Default)]
+struct EmbeddingState {
+    embeddings: HashMap<String, Vec<f64>>,
+    errors: Vec<(String, String)>,
+    latency: Option<Duration>,
+    requests: Vec<Vec<String>>,
+}
+
+/// Mock embedding model returning deterministic embeddings: the embedding of a document is
+/// a normalized vector derived from its hash, unless a fixed one is set with
+/// [EmbeddingModel::embedding]. Clones of a model share the same configuration and
+/// recorded requests.
+#[derive(Clone)]
+pub struct EmbeddingModel {
+    ndims: usize,
+    state: Arc<Mutex<EmbeddingState>>,
+}
+
+impl EmbeddingModel {
+    pub fn new(ndims: usize) -> Self {
+        Self {
+            ndims,
+            state: Default::default(),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, EmbeddingState> {
+        self.state.lock().expect("Mock model lock poisoned")
+    }
+
+    /// Return `vec` as the embedding of `document`.
+    pub fn embedding(self, document: &str, vec: Vec<f64>) -> Self {
+        self.state().embeddings.insert(document.to_string(), vec);
+        self
+    }
+
+    /// Fail with a provider error (with the given message) the requests containing
+    /// a document that contains `text`.
+    pub fn error_on(self, text: &str, message: &str) -> Self {
+        self.state()
+            .errors
+            .push((text.to_string(), message.to_string()));
+        self
+    }
+
+    /// Delay every response by `latency`.
+    pub fn latency(self, latency: Duration) -> Self {
+        self.state().latency = Some(latency);
+        self
+    }
+
+    /// Documents of the requests received so far, in order.
+    pub fn requests(&self) -> Vec<Vec<String>> {
+        self.state().requests.clone()
+    }
+
+    /// Deterministic embedding of `document`: the SHA-256 hashes of the document (with a
+    /// counter) mapped to [-1, 1] and normalized.
+    fn hash_embedding(&self, document: &str) -> Vec<f64> {
+        let vec = (0..)
+            .flat_map(|i: u64| {
+                Sha256::new()
+                    .chain_update(i.to_le_bytes())
+                    .chain_update(document.as_bytes())
+                    .finalize()
+                    .to_vec()
+            })
+            .take(self.ndims)
+            .map(|byte| byte as f64 / 127.5 - 1.0)
+            .collect::<Vec<_>>();
+
+        let norm = vec.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            vec.into_iter().map(|x| x / norm).collect()
+        } else {
+            vec
+        }
+    }
+}
+
+impl embeddings::EmbeddingModel for EmbeddingModel {
+    const MAX_DOCUMENTS: usize = 1024;
+
+    fn ndims(&self) -> usize {
+        self.ndims
+    }
+
+    async fn embed_texts(
+        &self,
+        documents: impl IntoIterator<Item = String> + Send,
+    ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
+        let documents = documents.into_iter().collect::<Vec<_>>();
+
+        let (result, latency) = {
+            let mut state = self.state();
+            state.requests.push(documents.clone());
+
+            let error = state.errors.iter().find(|(text, _)| {
+                documents
+                    .iter()
+                    .any(|document| document.contains(text.as_str()))
+            });
+
+            let result = match error {
+                Some((_, message)) => Err(EmbeddingError::ProviderError(message.clone())),
+                None => Ok(documents
+                    .into_iter()
+                    .map(|document| embeddings::Embedding {
+                        vec: state
+                            .embeddings
+                            .get(&document)
+                            .cloned()
+                            .unwrap_or_else(|| self.hash_embedding(&document)),
+                        document,
+                    })
+                    .collect::<Vec<_>>()),
+            };
+            (result, state.latency)
+        };
+
+        if let Some(latency) = latency {
+            Delay::new(latency).await;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        completion::{Chat, Prompt, ToolDefinition},
+        embeddings::EmbeddingModel as _,
+        tool::Tool,
+    };
+
+    #[derive(serde::Deserialize)]
+    struct WeatherArgs {
+        city: String,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Weather error")]
+    struct WeatherError;
+
+    struct GetWeather;
+
+    impl Tool for GetWeather {
+        const NAME: &'static str = "get_weather";
+
+        type Error = WeatherError;
+        type Args = WeatherArgs;
+        type Output = String;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            ToolDefinition {
+                name: Self::NAME.to_string(),
+                description: "Get the weather in a city".to_string(),
+                parameters: json!({}),
+            }
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            Ok(format!("Sunny in {}", args.city))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_completion_model() {
+        let model = CompletionModel::new()
+            .when_prompt_contains(
+                "weather",
+                MockResponse::tool_call("get_weather", json!({"city": "Paris"})),
+            )
+            .push(MockResponse::text("First"))
+            .push(MockResponse::error("Rate limited"))
+            .default_response(MockResponse::text("Default"));
+
+        let agent = model.agent().preamble("Be nice").tool(GetWeather).build();
+
+        assert_eq!(
+            agent.prompt("What is the weather?").await.unwrap(),
+            "\"Sunny in Paris\""
+        );
+        assert_eq!(agent.prompt("Hello").await.unwrap(), "First");
+        assert!(agent.prompt("Hello").await.is_err());
+        assert_eq!(
+            agent
+                .chat("Hello", vec![Message::user("Hi")])
+                .await
+                .unwrap(),
+            "Default"
+        );
+
+        let requests = model.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(
+            requests[0].prompt_text().as_deref(),
+            Some("What is the weather?")
+        );
+        assert_eq!(requests[0].preamble.as_deref(), Some("Be nice"));
+        assert_eq!(requests[0].tools, vec!["get_weather"]);
+        assert_eq!(requests[3].chat_history.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_mock_completion_model_without_response() {
+        let agent = CompletionModel::new().agent().build();
+        assert!(agent.prompt("Hello").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_embedding_model() {
+        let model = EmbeddingModel::new(8)
+            .embedding("fixed", vec![1.0; 8])
+            .error_on("boom", "Service unavailable");
+
+        let embeddings = model
+            .embed_texts(vec!["hello".to_string(), "fixed".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(embeddings[0].vec.len(), 8);
+        assert_eq!(embeddings[1].vec, vec![1.0; 8]);
+
+        // Embeddings are deterministic and normalized
+        let again = model.embed_texts(vec!["hello".to_string()]).await.unwrap();
+        assert_eq!(embeddings[0].vec, again[0].vec);
+        let norm = again[0].vec.iter().map(|x| x * x).sum::<f64>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-9);
+
+        assert!(model.embed_texts(vec!["kaboom".to_string()]).await.is_err());
+        assert_eq!(model.requests().len(), 3);
+    }
+}
@@ -10,6 +10,8 @@
 //! - EternalAI
 //! - DeepSeek
 //! - Azure OpenAI
+//! - Local ONNX embedding models (requires the `local` feature)
+//! - Mock models, for deterministic tests (see [mock])
 //!
 //! Each provider has its own module, which contains a `Client` implementation that can
 //! be used to initialize completion and embedding models and execute requests to those models.
@@ -51,6 +53,9 @@ pub mod deepseek;
 pub mod galadriel;
 pub mod gemini;
 pub mod hyperbolic;
+#[cfg(feature = "local")]
+pub mod local;
+pub mod mock;
 pub mod moonshot;
 pub mod openai;
 pub mod perplexity;
@@ -11,9 +11,13 @@
 
 use crate::{
     agent::AgentBuilder,
-    completion::{self, CompletionError, CompletionRequest},
+    completion::{self, CompletionError, CompletionRequest, ToolChoice},
     extractor::ExtractorBuilder,
+    health::{self, Health, HealthCheck},
+    http_client::{HttpBackend, HttpClient, Transform},
     json_utils,
+    key_provider::{KeyPlacement, KeyProvider},
+    payload_log::SendLogged,
     providers::openai,
 };
 use schemars::JsonSchema;
@@ -29,6 +33,7 @@ const MOONSHOT_API_BASE_URL: &str = "https://api.moonshot.cn/v1";
 pub struct Client {
     base_url: String,
     http_client: reqwest::Client,
+    http: HttpBackend,
 }
 
 impl Client {
@@ -39,21 +44,21 @@ impl Client {
 
     /// Create a new Moonshot client with the given API key and base API URL.
     pub fn from_url(api_key: &str, base_url: &str) -> Self {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            format!("Bearer {}", api_key)
+                .parse()
+                .expect("Bearer token should parse"),
+        );
+
         Self {
             base_url: base_url.to_string(),
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert(
-                        "Authorization",
-                        format!("Bearer {}", api_key)
-                            .parse()
-                            .expect("Bearer token should parse"),
-                    );
-                    headers
-                })
+                .default_headers(headers.clone())
                 .build()
                 .expect("Moonshot reqwest client should build"),
+            http: HttpBackend::new(headers),
         }
     }
 
@@ -64,6 +69,44 @@ impl Client {
         Self::new(&api_key)
     }
 
+    /// Add the header `name` with `value` to every request of the client.
+    /// Panics if the header name or value is invalid.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.http = self.http.with_header(name, value);
+        self
+    }
+
+    /// Get the API key of each request from `keys` (e.g.: a
+    /// [KeyPool](crate::key_provider::KeyPool) spreading the load across several keys) instead
+    /// of using the key given at construction. See [crate::key_provider].
+    pub fn with_key_provider(mut self, keys: impl KeyProvider + 'static) -> Self {
+        self.http = self.http.with_keys(keys, KeyPlacement::Bearer);
+        self
+    }
+
+    /// Send the requests of the client with `client`, e.g.: to configure a proxy, custom root
+    /// certificates, mTLS or the connection pool. The authentication headers of the provider
+    /// are added to every request.
+    pub fn with_reqwest_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = client;
+        self
+    }
+
+    /// Send the requests of the client through `client` instead of the default reqwest
+    /// client (e.g.: to use another HTTP library, or a fake in tests). See [crate::http_client].
+    pub fn with_http_client(mut self, client: impl HttpClient + 'static) -> Self {
+        self.http = self.http.with_client(client);
+        self
+    }
+
+    /// Modify the JSON requests and responses of the client with `transform` (e.g.: to add the
+    /// headers of an enterprise gateway, or strip the fields unsupported by a proxy). See
+    /// [crate::http_client::Transform].
+    pub fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.http = self.http.with_transform(transform);
+        self
+    }
+
     fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
         self.http_client.post(url)
@@ -86,7 +129,7 @@ impl Client {
 
     /// Create an agent builder with the given completion model.
     pub fn agent(&self, model: &str) -> AgentBuilder<CompletionModel> {
-        AgentBuilder::new(self.completion_model(model))
+        AgentBuilder::new(self.completion_model(model)).model_name(model)
     }
 
     /// Create an extractor builder with the given completion model.
@@ -98,6 +141,17 @@ impl Client {
     }
 }
 
+impl HealthCheck for Client {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn health(&self) -> Health {
+        health::check(
+            &self.http,
+            self.http_client.get(format!("{}/models", self.base_url)),
+        )
+        .await
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ApiErrorResponse {
     error: MoonshotError,
@@ -138,7 +192,22 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = openai::CompletionResponse;
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "chat",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("chat {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "chat",
+            gen_ai.system = "moonshot",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens,
+            gen_ai.usage.output_tokens
+        )
+    ))]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
@@ -178,7 +247,7 @@ impl completion::CompletionModel for CompletionModel {
                 "messages": full_history,
                 "temperature": completion_request.temperature,
                 "tools": completion_request.tools.into_iter().map(openai::ToolDefinition::from).collect::<Vec<_>>(),
-                "tool_choice": "auto",
+                "tool_choice": ToolChoice::openai_or_auto(&completion_request.tool_choice),
             })
         };
 
@@ -192,7 +261,7 @@ impl completion::CompletionModel for CompletionModel {
                     request
                 },
             )
-            .send()
+            .send_logged("moonshot", &self.client.http)
             .await?;
 
         if response.status().is_success() {
@@ -205,6 +274,13 @@ impl completion::CompletionModel for CompletionModel {
                         "Azure completion token usage: {:?}",
                         response.usage.clone().map(|usage| format!("{usage}")).unwrap_or("N/A".to_string())
                     );
+                    if let Some(usage) = &response.usage {
+                        crate::telemetry::record_usage(
+                            &self.model,
+                            Some(usage.prompt_tokens as u64),
+                            Some(usage.total_tokens.saturating_sub(usage.prompt_tokens) as u64),
+                        );
+                    }
                     response.try_into()
                 }
                 ApiResponse::Err(err) => Err(CompletionError::ProviderError(err.error.message)),
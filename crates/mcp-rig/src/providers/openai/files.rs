@@ -0,0 +1,452 @@
+//! OpenAI Files and Vector Stores APIs, and the [FileSearchTool] searching the vector stores
+//! (i.e.: OpenAI-hosted retrieval usable by any agent, alongside its other tools).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::providers::openai::{self, FilePurpose};
+//!
+//! let openai = openai::Client::from_env();
+//!
+//! let file = openai
+//!     .upload_file("flurbo.md", std::fs::read("flurbo.md")?, FilePurpose::Assistants)
+//!     .await?;
+//! let vector_store = openai.create_vector_store("Glossary", &[&file.id]).await?;
+//!
+//! let agent = openai
+//!     .agent(openai::GPT_4O)
+//!     .preamble("Answer the questions using the glossary.")
+//!     .tool(openai.file_search_tool(&[&vector_store.id]))
+//!     .build();
+//! ```
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::{ApiResponse, Client};
+use crate::{
+    cassette::SendRecorded,
+    completion::ToolDefinition,
+    http_client::{multipart_body, HttpClientError},
+    tool::{SyncFuture, Tool},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum FileError {
+    /// Http error (e.g.: connection error, timeout, etc.)
+    #[error("HttpError: {0}")]
+    HttpError(#[from] reqwest::Error),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error returned by OpenAI
+    #[error("ProviderError: {0}")]
+    ProviderError(String),
+}
+
+impl From<HttpClientError> for FileError {
+    fn from(error: HttpClientError) -> Self {
+        match error {
+            HttpClientError::HttpError(error) => FileError::HttpError(error),
+            error => FileError::ProviderError(error.to_string()),
+        }
+    }
+}
+
+/// Intended use of an uploaded file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilePurpose {
+    /// File searchable through the vector stores (e.g.: with the [FileSearchTool])
+    Assistants,
+    Batch,
+    #[serde(rename = "fine-tune")]
+    FineTune,
+    Vision,
+    UserData,
+}
+
+impl FilePurpose {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FilePurpose::Assistants => "assistants",
+            FilePurpose::Batch => "batch",
+            FilePurpose::FineTune => "fine-tune",
+            FilePurpose::Vision => "vision",
+            FilePurpose::UserData => "user_data",
+        }
+    }
+}
+
+/// A file uploaded to OpenAI.
+///
+/// For more information, see this link: <https://platform.openai.com/docs/api-reference/files/object>
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FileObject {
+    pub id: String,
+    pub bytes: u64,
+    pub created_at: u64,
+    pub filename: String,
+    pub purpose: String,
+}
+
+/// An OpenAI vector store, indexing files for the [FileSearchTool].
+///
+/// For more information, see this link: <https://platform.openai.com/docs/api-reference/vector-stores/object>
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct VectorStore {
+    pub id: String,
+    pub name: Option<String>,
+    pub created_at: u64,
+    /// `expired`, `in_progress` or `completed`
+    pub status: String,
+    pub usage_bytes: u64,
+    pub file_counts: FileCounts,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FileCounts {
+    pub in_progress: u64,
+    pub completed: u64,
+    pub failed: u64,
+    pub cancelled: u64,
+    pub total: u64,
+}
+
+/// A chunk of a file matching a vector store search.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct VectorStoreSearchResult {
+    pub file_id: String,
+    pub filename: String,
+    pub score: f64,
+    pub content: Vec<VectorStoreSearchContent>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct VectorStoreSearchContent {
+    pub r#type: String,
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListResponse<T> {
+    data: Vec<T>,
+}
+
+impl Client {
+    /// Upload a file to OpenAI (e.g.: to add it to a vector store).
+    pub async fn upload_file(
+        &self,
+        filename: &str,
+        content: impl Into<Vec<u8>>,
+        purpose: FilePurpose,
+    ) -> Result<FileObject, FileError> {
+        let (content_type, body) = multipart_body(
+            &[("purpose", purpose.as_str())],
+            "file",
+            filename,
+            content.into(),
+        );
+
+        let response = self
+            .post("/files")
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body)
+            .send_recorded("openai", self.cassette.as_ref(), &self.http)
+            .await?;
+        parse(response).await
+    }
+
+    /// List the files uploaded to OpenAI.
+    pub async fn list_files(&self) -> Result<Vec<FileObject>, FileError> {
+        let response = self
+            .get("/files")
+            .send_recorded("openai", self.cassette.as_ref(), &self.http)
+            .await?;
+        Ok(parse::<ListResponse<FileObject>>(response).await?.data)
+    }
+
+    /// Delete the uploaded file `file_id`.
+    pub async fn delete_file(&self, file_id: &str) -> Result<(), FileError> {
+        let response = self
+            .delete(&format!("/files/{file_id}"))
+            .send_recorded("openai", self.cassette.as_ref(), &self.http)
+            .await?;
+        parse::<serde_json::Value>(response).await.map(|_| ())
+    }
+
+    /// Create a vector store named `name`, indexing the uploaded files `file_ids`.
+    pub async fn create_vector_store(
+        &self,
+        name: &str,
+        file_ids: &[&str],
+    ) -> Result<VectorStore, FileError> {
+        let response = self
+            .post("/vector_stores")
+            .header("OpenAI-Beta", "assistants=v2")
+            .json(&json!({ "name": name, "file_ids": file_ids }))
+            .send_recorded("openai", self.cassette.as_ref(), &self.http)
+            .await?;
+        parse(response).await
+    }
+
+    /// Get the vector store `vector_store_id` (e.g.: to check that its files are indexed).
+    pub async fn vector_store(&self, vector_store_id: &str) -> Result<VectorStore, FileError> {
+        let response = self
+            .get(&format!("/vector_stores/{vector_store_id}"))
+            .header("OpenAI-Beta", "assistants=v2")
+            .send_recorded("openai", self.cassette.as_ref(), &self.http)
+            .await?;
+        parse(response).await
+    }
+
+    /// Add the uploaded file `file_id` to the vector store `vector_store_id`.
+    pub async fn add_vector_store_file(
+        &self,
+        vector_store_id: &str,
+        file_id: &str,
+    ) -> Result<(), FileError> {
+        let response = self
+            .post(&format!("/vector_stores/{vector_store_id}/files"))
+            .header("OpenAI-Beta", "assistants=v2")
+            .json(&json!({ "file_id": file_id }))
+            .send_recorded("openai", self.cassette.as_ref(), &self.http)
+            .await?;
+        parse::<serde_json::Value>(response).await.map(|_| ())
+    }
+
+    /// Delete the vector store `vector_store_id` (the files themselves are not deleted).
+    pub async fn delete_vector_store(&self, vector_store_id: &str) -> Result<(), FileError> {
+        let response = self
+            .delete(&format!("/vector_stores/{vector_store_id}"))
+            .header("OpenAI-Beta", "assistants=v2")
+            .send_recorded("openai", self.cassette.as_ref(), &self.http)
+            .await?;
+        parse::<serde_json::Value>(response).await.map(|_| ())
+    }
+
+    /// Search the vector store `vector_store_id` for the chunks of files relevant to `query`.
+    pub async fn search_vector_store(
+        &self,
+        vector_store_id: &str,
+        query: &str,
+        max_num_results: usize,
+    ) -> Result<Vec<VectorStoreSearchResult>, FileError> {
+        let response = self
+            .post(&format!("/vector_stores/{vector_store_id}/search"))
+            .header("OpenAI-Beta", "assistants=v2")
+            .json(&json!({ "query": query, "max_num_results": max_num_results }))
+            .send_recorded("openai", self.cassette.as_ref(), &self.http)
+            .await?;
+        Ok(parse::<ListResponse<VectorStoreSearchResult>>(response)
+            .await?
+            .data)
+    }
+
+    /// Create a [FileSearchTool] searching the vector stores `vector_store_ids`.
+    pub fn file_search_tool(&self, vector_store_ids: &[&str]) -> FileSearchTool {
+        FileSearchTool::new(self.clone(), vector_store_ids)
+    }
+}
+
+async fn parse<T: for<'a> Deserialize<'a>>(response: reqwest::Response) -> Result<T, FileError> {
+    if !response.status().is_success() {
+        return Err(FileError::ProviderError(response.text().await?));
+    }
+
+    match response.json::<ApiResponse<T>>().await? {
+        ApiResponse::Ok(response) => Ok(response),
+        ApiResponse::Err(err) => Err(FileError::ProviderError(err.message)),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FileSearchArgs {
+    pub query: String,
+}
+
+/// A chunk of a file returned by the [FileSearchTool].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FileSearchResult {
+    pub filename: String,
+    pub score: f64,
+    pub text: String,
+}
+
+/// Tool searching the files of OpenAI vector stores (i.e.: the `file_search` tool of OpenAI's
+/// assistants, usable by any agent).
+#[derive(Clone)]
+pub struct FileSearchTool {
+    client: Client,
+    vector_store_ids: Vec<String>,
+    max_num_results: usize,
+}
+
+impl FileSearchTool {
+    pub fn new(client: Client, vector_store_ids: &[&str]) -> Self {
+        Self {
+            client,
+            vector_store_ids: vector_store_ids.iter().map(|id| id.to_string()).collect(),
+            max_num_results: 5,
+        }
+    }
+
+    /// Set the maximum number of chunks returned by a search (defaults to 5).
+    pub fn max_num_results(mut self, max_num_results: usize) -> Self {
+        self.max_num_results = max_num_results;
+        self
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn search(&self, query: &str) -> Result<Vec<FileSearchResult>, FileError> {
+        let mut results = vec![];
+        for vector_store_id in &self.vector_store_ids {
+            let matches = self
+                .client
+                .search_vector_store(vector_store_id, query, self.max_num_results)
+                .await?;
+            results.extend(matches.into_iter().map(|result| {
+                FileSearchResult {
+                    filename: result.filename,
+                    score: result.score,
+                    text: result
+                        .content
+                        .into_iter()
+                        .map(|content| content.text)
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                }
+            }));
+        }
+
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        results.truncate(self.max_num_results);
+        Ok(results)
+    }
+}
+
+impl Tool for FileSearchTool {
+    const NAME: &'static str = "file_search";
+
+    type Error = FileError;
+    type Args = FileSearchArgs;
+    type Output = Vec<FileSearchResult>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Search the files of the knowledge base for the passages relevant to \
+                a query."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "The search query"
+                    }
+                },
+                "required": ["query"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        // The requests of the client are only `Send`, but the future of a tool call must be `Sync`
+        SyncFuture::new(self.search(&args.query)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::http_client::HttpClient;
+
+    #[derive(Clone, Default)]
+    struct FakeClient {
+        requests: Arc<Mutex<Vec<http::Request<Bytes>>>>,
+    }
+
+    impl HttpClient for FakeClient {
+        async fn send(
+            &self,
+            request: http::Request<Bytes>,
+        ) -> Result<http::Response<Bytes>, HttpClientError> {
+            let body = match request.uri().path() {
+                "/v1/files" => json!({
+                    "id": "file-1",
+                    "object": "file",
+                    "bytes": 6,
+                    "created_at": 1700000000,
+                    "filename": "flurbo.md",
+                    "purpose": "assistants"
+                }),
+                path => {
+                    let score = if path.contains("vs-1") { 0.5 } else { 0.9 };
+                    json!({
+                        "object": "vector_store.search_results.page",
+                        "data": [{
+                            "file_id": "file-1",
+                            "filename": format!("{path}.md"),
+                            "score": score,
+                            "content": [{"type": "text", "text": "A flurbo is a green alien."}]
+                        }]
+                    })
+                }
+            };
+            self.requests.lock().unwrap().push(request);
+            Ok(http::Response::new(body.to_string().into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_file() {
+        let fake = FakeClient::default();
+        let client = Client::new("sk-123").with_http_client(fake.clone());
+
+        let file = client
+            .upload_file("flurbo.md", "flurbo", FilePurpose::Assistants)
+            .await
+            .unwrap();
+        assert_eq!(file.id, "file-1");
+
+        let requests = fake.requests.lock().unwrap();
+        let content_type = requests[0].headers()["Content-Type"].to_str().unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+        let body = String::from_utf8(requests[0].body().to_vec()).unwrap();
+        assert!(body.starts_with(&format!("--{boundary}\r\n")));
+        assert!(body.contains("name=\"purpose\"\r\n\r\nassistants\r\n"));
+        assert!(body.contains(
+            "filename=\"flurbo.md\"\r\nContent-Type: application/octet-stream\r\n\r\nflurbo\r\n"
+        ));
+        assert!(body.ends_with(&format!("--{boundary}--\r\n")));
+    }
+
+    #[tokio::test]
+    async fn test_file_search_tool() {
+        let fake = FakeClient::default();
+        let client = Client::new("sk-123").with_http_client(fake.clone());
+
+        let results = client
+            .file_search_tool(&["vs-1", "vs-2"])
+            .max_num_results(1)
+            .call(FileSearchArgs {
+                query: "flurbo".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].filename, "/v1/vector_stores/vs-2/search.md");
+        assert_eq!(results[0].text, "A flurbo is a green alien.");
+
+        let requests = fake.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].headers()["OpenAI-Beta"], "assistants=v2");
+    }
+}
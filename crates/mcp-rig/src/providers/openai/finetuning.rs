@@ -0,0 +1,188 @@
+//! OpenAI fine-tuning jobs API (see [crate::finetuning]).
+//!
+//! For more information, see this link: <https://platform.openai.com/docs/api-reference/fine-tuning>
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{ApiResponse, Client, CompletionModel};
+use crate::{
+    cassette::SendRecorded,
+    finetuning::{FineTuneError, FineTuneJob, FineTuneRequest, FineTuneStatus, FineTuning},
+};
+
+#[derive(Debug, Deserialize)]
+struct Job {
+    id: String,
+    model: String,
+    /// `validating_files`, `queued`, `running`, `succeeded`, `failed` or `cancelled`
+    status: String,
+    fine_tuned_model: Option<String>,
+    created_at: Option<u64>,
+    error: Option<JobError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobError {
+    message: Option<String>,
+}
+
+impl From<Job> for FineTuneJob {
+    fn from(job: Job) -> Self {
+        FineTuneJob {
+            id: job.id,
+            base_model: job.model,
+            status: match job.status.as_str() {
+                "running" => FineTuneStatus::Running,
+                "succeeded" => FineTuneStatus::Succeeded,
+                "failed" => FineTuneStatus::Failed,
+                "cancelled" => FineTuneStatus::Cancelled,
+                _ => FineTuneStatus::Queued,
+            },
+            fine_tuned_model: job.fine_tuned_model,
+            created_at: job.created_at,
+            error: job.error.and_then(|error| error.message),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JobsResponse {
+    data: Vec<Job>,
+}
+
+async fn parse<T: for<'a> Deserialize<'a>>(
+    response: reqwest::Response,
+) -> Result<T, FineTuneError> {
+    if !response.status().is_success() {
+        return Err(FineTuneError::ProviderError(response.text().await?));
+    }
+
+    match response.json::<ApiResponse<T>>().await? {
+        ApiResponse::Ok(response) => Ok(response),
+        ApiResponse::Err(err) => Err(FineTuneError::ProviderError(err.message)),
+    }
+}
+
+impl FineTuning for Client {
+    type Model = CompletionModel;
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn create_fine_tune_job(
+        &self,
+        request: FineTuneRequest,
+    ) -> Result<FineTuneJob, FineTuneError> {
+        let mut body = json!({
+            "model": request.base_model,
+            "training_file": request.training_file,
+        });
+        if let Some(validation_file) = request.validation_file {
+            body["validation_file"] = validation_file.into();
+        }
+        if let Some(suffix) = request.suffix {
+            body["suffix"] = suffix.into();
+        }
+        if let Some(epochs) = request.epochs {
+            body["hyperparameters"] = json!({ "n_epochs": epochs });
+        }
+
+        let response = self
+            .post("/fine_tuning/jobs")
+            .json(&body)
+            .send_recorded("openai", self.cassette.as_ref(), &self.http)
+            .await?;
+        Ok(parse::<Job>(response).await?.into())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn fine_tune_job(&self, job_id: &str) -> Result<FineTuneJob, FineTuneError> {
+        let response = self
+            .get(&format!("/fine_tuning/jobs/{job_id}"))
+            .send_recorded("openai", self.cassette.as_ref(), &self.http)
+            .await?;
+        Ok(parse::<Job>(response).await?.into())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn list_fine_tune_jobs(&self) -> Result<Vec<FineTuneJob>, FineTuneError> {
+        let response = self
+            .get("/fine_tuning/jobs")
+            .send_recorded("openai", self.cassette.as_ref(), &self.http)
+            .await?;
+        Ok(parse::<JobsResponse>(response)
+            .await?
+            .data
+            .into_iter()
+            .map(FineTuneJob::from)
+            .collect())
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn cancel_fine_tune_job(&self, job_id: &str) -> Result<(), FineTuneError> {
+        let response = self
+            .post(&format!("/fine_tuning/jobs/{job_id}/cancel"))
+            .send_recorded("openai", self.cassette.as_ref(), &self.http)
+            .await?;
+        parse::<Job>(response).await.map(|_| ())
+    }
+
+    fn fine_tuned_model(&self, job: &FineTuneJob) -> Result<CompletionModel, FineTuneError> {
+        match &job.fine_tuned_model {
+            Some(model) => Ok(self.completion_model(model)),
+            None => Err(FineTuneError::ModelNotReady(job.id.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::http_client::{HttpClient, HttpClientError};
+
+    struct FakeClient;
+
+    impl HttpClient for FakeClient {
+        async fn send(
+            &self,
+            request: http::Request<Bytes>,
+        ) -> Result<http::Response<Bytes>, HttpClientError> {
+            let status = if request.uri().path().ends_with("ftjob-1") {
+                "succeeded"
+            } else {
+                "queued"
+            };
+            let body = json!({
+                "object": "fine_tuning.job",
+                "id": "ftjob-1",
+                "model": "gpt-4o-mini-2024-07-18",
+                "created_at": 1721764800,
+                "fine_tuned_model": "ft:gpt-4o-mini-2024-07-18:org:support:abc123",
+                "status": status,
+                "error": null
+            });
+            Ok(http::Response::new(body.to_string().into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fine_tune_job() {
+        let client = Client::new("sk-123").with_http_client(FakeClient);
+
+        let job = client
+            .create_fine_tune_job(FineTuneRequest::new("gpt-4o-mini", "file-1"))
+            .await
+            .unwrap();
+        assert_eq!(job.status, FineTuneStatus::Queued);
+
+        let job = client
+            .wait_for_fine_tune_job(&job.id, std::time::Duration::from_millis(1))
+            .await
+            .unwrap();
+        assert_eq!(job.status, FineTuneStatus::Succeeded);
+        assert_eq!(
+            client.fine_tuned_model(&job).unwrap().model,
+            "ft:gpt-4o-mini-2024-07-18:org:support:abc123"
+        );
+    }
+}
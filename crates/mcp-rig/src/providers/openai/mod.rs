@@ -8,15 +8,25 @@
 //!
 //! let gpt4o = client.completion_model(openai::GPT_4O);
 //! ```
+mod files;
+mod finetuning;
+
+pub use files::*;
+
 use std::{convert::Infallible, str::FromStr};
 
 use crate::{
     agent::AgentBuilder,
-    completion::{self, CompletionError, CompletionRequest},
-    embeddings::{self, EmbeddingError, EmbeddingsBuilder},
+    cassette::{Cassette, SendRecorded},
+    completion::{self, CompletionError, CompletionRequest, ToolChoice},
+    embeddings::{self, EmbeddingError, EmbeddingUsage, EmbeddingsBuilder},
     extractor::ExtractorBuilder,
+    health::{self, Health, HealthCheck},
+    http_client::{HttpBackend, HttpClient, Transform},
     json_utils,
+    key_provider::{KeyPlacement, KeyProvider},
     message::{self, AudioMediaType, ImageDetail},
+    models::{ListModels, ModelInfo, ModelListError},
     one_or_many::string_or_one_or_many,
     Embed, OneOrMany,
 };
@@ -33,6 +43,8 @@ const OPENAI_API_BASE_URL: &str = "https://api.openai.com/v1";
 pub struct Client {
     base_url: String,
     http_client: reqwest::Client,
+    http: HttpBackend,
+    cassette: Option<Cassette>,
 }
 
 impl Client {
@@ -43,24 +55,32 @@ impl Client {
 
     /// Create a new OpenAI client with the given API key and base API URL.
     pub fn from_url(api_key: &str, base_url: &str) -> Self {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            format!("Bearer {}", api_key)
+                .parse()
+                .expect("Bearer token should parse"),
+        );
+
         Self {
             base_url: base_url.to_string(),
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert(
-                        "Authorization",
-                        format!("Bearer {}", api_key)
-                            .parse()
-                            .expect("Bearer token should parse"),
-                    );
-                    headers
-                })
+                .default_headers(headers.clone())
                 .build()
                 .expect("OpenAI reqwest client should build"),
+            http: HttpBackend::new(headers),
+            cassette: None,
         }
     }
 
+    /// Record the HTTP interactions of the client to `cassette`, or replay them from it
+    /// (depending on the mode of the cassette). See [crate::cassette].
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
     /// Create a new OpenAI client from the `OPENAI_API_KEY` environment variable.
     /// Panics if the environment variable is not set.
     pub fn from_env() -> Self {
@@ -68,11 +88,71 @@ impl Client {
         Self::new(&api_key)
     }
 
+    /// Add the header `name` with `value` to every request of the client.
+    /// Panics if the header name or value is invalid.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.http = self.http.with_header(name, value);
+        self
+    }
+
+    /// Set the organization used for the requests (`OpenAI-Organization` header), e.g.: for
+    /// billing attribution.
+    pub fn with_organization(self, organization: &str) -> Self {
+        self.with_header("OpenAI-Organization", organization)
+    }
+
+    /// Set the project used for the requests (`OpenAI-Project` header), e.g.: for billing
+    /// attribution.
+    pub fn with_project(self, project: &str) -> Self {
+        self.with_header("OpenAI-Project", project)
+    }
+
+    /// Get the API key of each request from `keys` (e.g.: a
+    /// [KeyPool](crate::key_provider::KeyPool) spreading the load across several keys) instead
+    /// of using the key given at construction. See [crate::key_provider].
+    pub fn with_key_provider(mut self, keys: impl KeyProvider + 'static) -> Self {
+        self.http = self.http.with_keys(keys, KeyPlacement::Bearer);
+        self
+    }
+
+    /// Send the requests of the client with `client`, e.g.: to configure a proxy, custom root
+    /// certificates, mTLS or the connection pool. The authentication headers of the provider
+    /// are added to every request.
+    pub fn with_reqwest_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = client;
+        self
+    }
+
+    /// Send the requests of the client through `client` instead of the default reqwest
+    /// client (e.g.: to use another HTTP library, or a fake in tests). See [crate::http_client].
+    pub fn with_http_client(mut self, client: impl HttpClient + 'static) -> Self {
+        self.http = self.http.with_client(client);
+        self
+    }
+
+    /// Modify the JSON requests and responses of the client with `transform` (e.g.: to add the
+    /// headers of an enterprise gateway, or strip the fields unsupported by a proxy). See
+    /// [crate::http_client::Transform].
+    pub fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.http = self.http.with_transform(transform);
+        self
+    }
+
     fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
         self.http_client.post(url)
     }
 
+    fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/{}", self.base_url, path).replace("//", "/");
+        self.http_client.get(url)
+    }
+
+    fn delete(&self, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/{}", self.base_url, path).replace("//", "/");
+        self.http_client.delete(url)
+    }
+
     /// Create an embedding model with the given name.
     /// Note: default embedding dimension of 0 will be used if model is not known.
     /// If this is the case, it's better to use function `embedding_model_with_ndims`
@@ -160,7 +240,7 @@ impl Client {
     ///    .build();
     /// ```
     pub fn agent(&self, model: &str) -> AgentBuilder<CompletionModel> {
-        AgentBuilder::new(self.completion_model(model))
+        AgentBuilder::new(self.completion_model(model)).model_name(model)
     }
 
     /// Create an extractor builder with the given completion model.
@@ -172,6 +252,13 @@ impl Client {
     }
 }
 
+impl HealthCheck for Client {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn health(&self) -> Health {
+        health::check(&self.http, self.get("/models")).await
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ApiErrorResponse {
     message: String,
@@ -184,6 +271,49 @@ enum ApiResponse<T> {
     Err(ApiErrorResponse),
 }
 
+// ================================================================
+// OpenAI Models API
+// ================================================================
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<Model>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Model {
+    id: String,
+    created: Option<u64>,
+    owned_by: Option<String>,
+}
+
+impl ListModels for Client {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ModelListError> {
+        let response = self
+            .get("/models")
+            .send_recorded("openai", self.cassette.as_ref(), &self.http)
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ModelListError::ProviderError(response.text().await?));
+        }
+
+        match response.json::<ApiResponse<ModelsResponse>>().await? {
+            ApiResponse::Ok(response) => Ok(response
+                .data
+                .into_iter()
+                .map(|model| ModelInfo {
+                    id: model.id,
+                    owned_by: model.owned_by,
+                    created: model.created,
+                    ..Default::default()
+                })
+                .collect()),
+            ApiResponse::Err(err) => Err(ModelListError::ProviderError(err.message)),
+        }
+    }
+}
+
 // ================================================================
 // OpenAI Embedding API
 // ================================================================
@@ -245,6 +375,7 @@ pub struct EmbeddingModel {
     client: Client,
     pub model: String,
     ndims: usize,
+    dimensions: Option<usize>,
 }
 
 impl embeddings::EmbeddingModel for EmbeddingModel {
@@ -254,21 +385,48 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
         self.ndims
     }
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
     async fn embed_texts(
         &self,
         documents: impl IntoIterator<Item = String>,
     ) -> Result<Vec<embeddings::Embedding>, EmbeddingError> {
+        Ok(self.embed_texts_with_usage(documents).await?.0)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "embeddings",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("embeddings {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "embeddings",
+            gen_ai.system = "openai",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens
+        )
+    ))]
+    async fn embed_texts_with_usage(
+        &self,
+        documents: impl IntoIterator<Item = String>,
+    ) -> Result<(Vec<embeddings::Embedding>, Option<EmbeddingUsage>), EmbeddingError> {
         let documents = documents.into_iter().collect::<Vec<_>>();
 
+        let mut request = json!({
+            "model": self.model,
+            "input": documents,
+        });
+        if let Some(dimensions) = self.dimensions {
+            request["dimensions"] = json!(dimensions);
+        }
+
         let response = self
             .client
             .post("/embeddings")
-            .json(&json!({
-                "model": self.model,
-                "input": documents,
-            }))
-            .send()
+            .json(&request)
+            .send_recorded("openai", self.client.cassette.as_ref(), &self.client.http)
             .await?;
 
         if response.status().is_success() {
@@ -278,6 +436,11 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
                         "OpenAI embedding token usage: {}",
                         response.usage
                     );
+                    crate::telemetry::record_usage(
+                        &self.model,
+                        Some(response.usage.prompt_tokens as u64),
+                        None,
+                    );
 
                     if response.data.len() != documents.len() {
                         return Err(EmbeddingError::ResponseError(
@@ -285,15 +448,22 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
                         ));
                     }
 
-                    Ok(response
-                        .data
-                        .into_iter()
-                        .zip(documents.into_iter())
-                        .map(|(embedding, document)| embeddings::Embedding {
-                            document,
-                            vec: embedding.embedding,
-                        })
-                        .collect())
+                    let usage = EmbeddingUsage {
+                        total_tokens: response.usage.total_tokens,
+                    };
+
+                    Ok((
+                        response
+                            .data
+                            .into_iter()
+                            .zip(documents.into_iter())
+                            .map(|(embedding, document)| embeddings::Embedding {
+                                document,
+                                vec: embedding.embedding,
+                            })
+                            .collect(),
+                        Some(usage),
+                    ))
                 }
                 ApiResponse::Err(err) => Err(EmbeddingError::ProviderError(err.message)),
             }
@@ -309,8 +479,17 @@ impl EmbeddingModel {
             client,
             model: model.to_string(),
             ndims,
+            dimensions: None,
         }
     }
+
+    /// Request embeddings truncated to `ndims` dimensions from the provider (using the
+    /// `dimensions` parameter). Only supported by `text-embedding-3` and later models.
+    pub fn with_output_dims(mut self, ndims: usize) -> Self {
+        self.ndims = ndims;
+        self.dimensions = Some(ndims);
+        self
+    }
 }
 
 // ================================================================
@@ -330,6 +509,10 @@ pub const GPT_4O: &str = "gpt-4o";
 pub const GPT_4O_MINI: &str = "gpt-4o-mini";
 /// `gpt-4o-2024-05-13` completion model
 pub const GPT_4O_2024_05_13: &str = "gpt-4o-2024-05-13";
+/// `gpt-4o-search-preview` completion model (supports the built-in web search)
+pub const GPT_4O_SEARCH_PREVIEW: &str = "gpt-4o-search-preview";
+/// `gpt-4o-mini-search-preview` completion model (supports the built-in web search)
+pub const GPT_4O_MINI_SEARCH_PREVIEW: &str = "gpt-4o-mini-search-preview";
 /// `gpt-4-turbo` completion model
 pub const GPT_4_TURBO: &str = "gpt-4-turbo";
 /// `gpt-4-turbo-2024-04-09` completion model
@@ -407,7 +590,7 @@ impl TryFrom<CompletionResponse> for completion::CompletionResponse<CompletionRe
                         .iter()
                         .map(|call| {
                             completion::AssistantContent::tool_call(
-                                &call.function.name,
+                                &call.id,
                                 &call.function.name,
                                 call.function.arguments.clone(),
                             )
@@ -598,16 +781,11 @@ impl TryFrom<message::Message> for Vec<Message> {
                     .into_iter()
                     .partition(|content| matches!(content, message::UserContent::ToolResult(_)));
 
-                // If there are messages with both tool results and user content, openai will only
-                //  handle tool results. It's unlikely that there will be both.
-                if !tool_results.is_empty() {
-                    tool_results
-                        .into_iter()
-                        .map(|content| match content {
-                            message::UserContent::ToolResult(message::ToolResult {
-                                id,
-                                content,
-                            }) => Ok::<_, message::MessageError>(Message::ToolResult {
+                let mut messages = tool_results
+                    .into_iter()
+                    .map(|content| match content {
+                        message::UserContent::ToolResult(message::ToolResult { id, content }) => {
+                            Ok::<_, message::MessageError>(Message::ToolResult {
                                 tool_call_id: id,
                                 content: content.try_map(|content| match content {
                                     message::ToolResultContent::Text(message::Text { text }) => {
@@ -617,16 +795,16 @@ impl TryFrom<message::Message> for Vec<Message> {
                                         "Tool result content does not support non-text".into(),
                                     )),
                                 })?,
-                            }),
-                            _ => unreachable!(),
-                        })
-                        .collect::<Result<Vec<_>, _>>()
-                } else {
-                    let other_content = OneOrMany::many(other_content).expect(
-                        "There must be other content here if there were no tool result content",
-                    );
+                            })
+                        }
+                        _ => unreachable!(),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
 
-                    Ok(vec![Message::User {
+                // The other content follows the tool results, in a user message (OpenAI expects
+                //  the tool results right after the tool calls)
+                if let Ok(other_content) = OneOrMany::many(other_content) {
+                    messages.push(Message::User {
                         content: other_content.map(|content| match content {
                             message::UserContent::Text(message::Text { text }) => {
                                 UserContent::Text { text }
@@ -658,8 +836,9 @@ impl TryFrom<message::Message> for Vec<Message> {
                             _ => unreachable!(),
                         }),
                         name: None,
-                    }])
+                    });
                 }
+                Ok(messages)
             }
             message::Message::Assistant { content } => {
                 let (text_content, tool_calls) = content.into_iter().fold(
@@ -855,6 +1034,7 @@ pub struct CompletionModel {
     client: Client,
     /// Name of the model (e.g.: gpt-3.5-turbo-1106)
     pub model: String,
+    web_search: Option<WebSearchOptions>,
 }
 
 impl CompletionModel {
@@ -862,14 +1042,124 @@ impl CompletionModel {
         Self {
             client,
             model: model.to_string(),
+            web_search: None,
         }
     }
+
+    /// Let the model search the web with OpenAI's built-in `web_search` tool before answering
+    /// (only supported by the search models, e.g.: [GPT_4O_SEARCH_PREVIEW]).
+    pub fn with_web_search(mut self, options: WebSearchOptions) -> Self {
+        self.web_search = Some(options);
+        self
+    }
+}
+
+/// Options of OpenAI's built-in `web_search` tool.
+///
+/// For more information, see this link: <https://platform.openai.com/docs/guides/tools-web-search>
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct WebSearchOptions {
+    /// `low`, `medium` or `high`: amount of context retrieved from the web
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_context_size: Option<String>,
+    /// Approximate location of the user, used to refine the search
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_location: Option<serde_json::Value>,
+}
+
+/// Typed OpenAI-specific options of the chat completion requests, to set as the additional
+/// parameters of a request or an agent (e.g.: `agent.additional_params(ChatOptions { seed:
+/// Some(42), .. })`). Also accepted by the OpenAI-compatible providers.
+///
+/// For more information, see this link: <https://platform.openai.com/docs/api-reference/chat/create>
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ChatOptions {
+    /// Cumulative probability of the tokens considered at each step (nucleus sampling)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    /// Seed of the sampling, for (best effort) deterministic completions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Sequences stopping the generation (up to 4)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    /// Bias added to the logits of the tokens, by token id
+    #[serde(skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub logit_bias: std::collections::HashMap<String, i32>,
+    /// Whether to return the log probabilities of the output tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<bool>,
+    /// Number of the most likely tokens returned with their log probability, at each position
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_logprobs: Option<u32>,
+    /// Whether the model may call several tools in a single response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
+    /// Effort spent reasoning (only supported by the reasoning models, e.g.: `o3-mini`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// `auto`, `default` or `flex`: latency tier processing the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<String>,
+    /// Id of the end-user, to help OpenAI detect abuse
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// Effort spent reasoning by the OpenAI reasoning models.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+impl From<ChatOptions> for serde_json::Value {
+    fn from(options: ChatOptions) -> Self {
+        serde_json::to_value(options).expect("Chat options should serialize")
+    }
+}
+
+/// Whether the OpenAI model with the given name accepts image inputs.
+pub(crate) fn supports_vision(model: &str) -> bool {
+    model.starts_with("gpt-4o")
+        || model.starts_with("gpt-4.1")
+        || model.starts_with("gpt-4.5")
+        || model.contains("vision")
+        || (model.starts_with(GPT_4_TURBO) && !model.starts_with(GPT_4_TURBO_PREVIEW))
+        || (model.starts_with("o1")
+            && !model.starts_with(O1_MINI)
+            && !model.starts_with(O1_PREVIEW))
 }
 
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    fn supports_vision(&self) -> bool {
+        supports_vision(&self.model)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "chat",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("chat {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "chat",
+            gen_ai.system = "openai",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens,
+            gen_ai.usage.output_tokens
+        )
+    ))]
     async fn completion(
         &self,
         completion_request: CompletionRequest,
@@ -909,10 +1199,15 @@ impl completion::CompletionModel for CompletionModel {
                 "messages": full_history,
                 "temperature": completion_request.temperature,
                 "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
-                "tool_choice": "auto",
+                "tool_choice": ToolChoice::openai_or_auto(&completion_request.tool_choice),
             })
         };
 
+        let request = match &self.web_search {
+            Some(options) => json_utils::merge(request, json!({ "web_search_options": options })),
+            None => request,
+        };
+
         let response = self
             .client
             .post("/chat/completions")
@@ -923,7 +1218,7 @@ impl completion::CompletionModel for CompletionModel {
                     request
                 },
             )
-            .send()
+            .send_recorded("openai", self.client.cassette.as_ref(), &self.client.http)
             .await?;
 
         if response.status().is_success() {
@@ -936,6 +1231,13 @@ impl completion::CompletionModel for CompletionModel {
                         "OpenAI completion token usage: {:?}",
                         response.usage.clone().map(|usage| format!("{usage}")).unwrap_or("N/A".to_string())
                     );
+                    if let Some(usage) = &response.usage {
+                        crate::telemetry::record_usage(
+                            &self.model,
+                            Some(usage.prompt_tokens as u64),
+                            Some(usage.total_tokens.saturating_sub(usage.prompt_tokens) as u64),
+                        );
+                    }
                     response.try_into()
                 }
                 ApiResponse::Err(err) => Err(CompletionError::ProviderError(err.message)),
@@ -1235,4 +1537,76 @@ mod tests {
         assert_eq!(original_user_message[0], user_message);
         assert_eq!(original_assistant_message[0], assistant_message);
     }
+
+    #[derive(Clone, Default)]
+    struct FakeClient {
+        headers: std::sync::Arc<std::sync::Mutex<Vec<http::HeaderMap>>>,
+    }
+
+    impl HttpClient for FakeClient {
+        async fn send(
+            &self,
+            request: http::Request<bytes::Bytes>,
+        ) -> Result<http::Response<bytes::Bytes>, crate::http_client::HttpClientError> {
+            self.headers.lock().unwrap().push(request.headers().clone());
+            if request.uri().path().ends_with("/models") {
+                let body = json!({
+                    "object": "list",
+                    "data": [
+                        {"id": GPT_4O, "object": "model", "created": 1715367049, "owned_by": "system"},
+                        {"id": TEXT_EMBEDDING_3_SMALL, "object": "model", "created": 1705948997, "owned_by": "system"}
+                    ]
+                });
+                return Ok(http::Response::new(body.to_string().into()));
+            }
+            let body = json!({
+                "object": "list",
+                "data": [{"object": "embedding", "embedding": [0.5, 0.5], "index": 0}],
+                "model": TEXT_EMBEDDING_3_SMALL,
+                "usage": {"prompt_tokens": 2, "total_tokens": 2}
+            });
+            Ok(http::Response::new(body.to_string().into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_headers() {
+        use crate::embeddings::EmbeddingModel as _;
+
+        let fake = FakeClient::default();
+        let client = Client::new("sk-123")
+            .with_organization("org-123")
+            .with_project("proj-123")
+            .with_header("X-Team", "search")
+            .with_http_client(fake.clone());
+
+        let embeddings = client
+            .embedding_model(TEXT_EMBEDDING_3_SMALL)
+            .embed_text("flurbo")
+            .await
+            .unwrap();
+        assert_eq!(embeddings.vec, vec![0.5, 0.5]);
+
+        let headers = fake.headers.lock().unwrap();
+        assert_eq!(headers[0]["Authorization"], "Bearer sk-123");
+        assert_eq!(headers[0]["OpenAI-Organization"], "org-123");
+        assert_eq!(headers[0]["OpenAI-Project"], "proj-123");
+        assert_eq!(headers[0]["X-Team"], "search");
+    }
+
+    #[tokio::test]
+    async fn test_list_models() {
+        let client = Client::new("sk-123").with_http_client(FakeClient::default());
+
+        let models = client.list_models().await.unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].id, GPT_4O);
+        assert_eq!(models[0].owned_by.as_deref(), Some("system"));
+
+        assert_eq!(client.validate_model(GPT_4O).await.unwrap(), models[0]);
+        assert!(matches!(
+            client.validate_model("gpt-4o-typo").await,
+            Err(ModelListError::ModelNotFound(_))
+        ));
+    }
 }
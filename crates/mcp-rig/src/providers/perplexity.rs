@@ -13,7 +13,12 @@ use crate::{
     agent::AgentBuilder,
     completion::{self, message, CompletionError, MessageError},
     extractor::ExtractorBuilder,
-    json_utils, OneOrMany,
+    health::{self, Health, HealthCheck},
+    http_client::{HttpBackend, HttpClient, Transform},
+    json_utils,
+    key_provider::{KeyPlacement, KeyProvider},
+    payload_log::SendLogged,
+    OneOrMany,
 };
 
 use schemars::JsonSchema;
@@ -29,6 +34,7 @@ const PERPLEXITY_API_BASE_URL: &str = "https://api.perplexity.ai";
 pub struct Client {
     base_url: String,
     http_client: reqwest::Client,
+    http: HttpBackend,
 }
 
 impl Client {
@@ -44,24 +50,62 @@ impl Client {
     }
 
     pub fn from_url(api_key: &str, base_url: &str) -> Self {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            format!("Bearer {}", api_key)
+                .parse()
+                .expect("Bearer token should parse"),
+        );
+
         Self {
             base_url: base_url.to_string(),
             http_client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = reqwest::header::HeaderMap::new();
-                    headers.insert(
-                        "Authorization",
-                        format!("Bearer {}", api_key)
-                            .parse()
-                            .expect("Bearer token should parse"),
-                    );
-                    headers
-                })
+                .default_headers(headers.clone())
                 .build()
                 .expect("Perplexity reqwest client should build"),
+            http: HttpBackend::new(headers),
         }
     }
 
+    /// Add the header `name` with `value` to every request of the client.
+    /// Panics if the header name or value is invalid.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.http = self.http.with_header(name, value);
+        self
+    }
+
+    /// Get the API key of each request from `keys` (e.g.: a
+    /// [KeyPool](crate::key_provider::KeyPool) spreading the load across several keys) instead
+    /// of using the key given at construction. See [crate::key_provider].
+    pub fn with_key_provider(mut self, keys: impl KeyProvider + 'static) -> Self {
+        self.http = self.http.with_keys(keys, KeyPlacement::Bearer);
+        self
+    }
+
+    /// Send the requests of the client with `client`, e.g.: to configure a proxy, custom root
+    /// certificates, mTLS or the connection pool. The authentication headers of the provider
+    /// are added to every request.
+    pub fn with_reqwest_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = client;
+        self
+    }
+
+    /// Send the requests of the client through `client` instead of the default reqwest
+    /// client (e.g.: to use another HTTP library, or a fake in tests). See [crate::http_client].
+    pub fn with_http_client(mut self, client: impl HttpClient + 'static) -> Self {
+        self.http = self.http.with_client(client);
+        self
+    }
+
+    /// Modify the JSON requests and responses of the client with `transform` (e.g.: to add the
+    /// headers of an enterprise gateway, or strip the fields unsupported by a proxy). See
+    /// [crate::http_client::Transform].
+    pub fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.http = self.http.with_transform(transform);
+        self
+    }
+
     pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
         let url = format!("{}/{}", self.base_url, path).replace("//", "/");
         self.http_client.post(url)
@@ -72,7 +116,7 @@ impl Client {
     }
 
     pub fn agent(&self, model: &str) -> AgentBuilder<CompletionModel> {
-        AgentBuilder::new(self.completion_model(model))
+        AgentBuilder::new(self.completion_model(model)).model_name(model)
     }
 
     pub fn extractor<T: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync>(
@@ -83,6 +127,22 @@ impl Client {
     }
 }
 
+/// Health check with a 1-token completion of the [SONAR] model.
+impl HealthCheck for Client {
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    async fn health(&self) -> Health {
+        health::check(
+            &self.http,
+            self.post("/chat/completions").json(&json!({
+                "model": SONAR,
+                "messages": [{"role": "user", "content": "ping"}],
+                "max_tokens": 1,
+            })),
+        )
+        .await
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ApiErrorResponse {
     message: String,
@@ -260,7 +320,22 @@ impl From<Message> for message::Message {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "chat",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("chat {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "chat",
+            gen_ai.system = "perplexity",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens,
+            gen_ai.usage.output_tokens
+        )
+    ))]
     async fn completion(
         &self,
         completion_request: completion::CompletionRequest,
@@ -311,7 +386,7 @@ impl completion::CompletionModel for CompletionModel {
                     request.clone()
                 },
             )
-            .send()
+            .send_logged("perplexity", &self.client.http)
             .await?;
 
         if response.status().is_success() {
@@ -321,6 +396,11 @@ impl completion::CompletionModel for CompletionModel {
                         "Perplexity completion token usage: {}",
                         completion.usage
                     );
+                    crate::telemetry::record_usage(
+                        &self.model,
+                        Some(completion.usage.prompt_tokens as u64),
+                        Some(completion.usage.completion_tokens as u64),
+                    );
                     Ok(completion.try_into()?)
                 }
                 ApiResponse::Err(error) => Err(CompletionError::ProviderError(error.message)),
@@ -4,8 +4,9 @@
 // ================================================================
 
 use crate::{
-    completion::{self, CompletionError},
+    completion::{self, CompletionError, ToolChoice},
     json_utils,
+    payload_log::SendLogged,
     providers::openai::Message,
 };
 
@@ -39,7 +40,22 @@ impl CompletionModel {
 impl completion::CompletionModel for CompletionModel {
     type Response = CompletionResponse;
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "chat",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("chat {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "chat",
+            gen_ai.system = "xai",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens,
+            gen_ai.usage.output_tokens
+        )
+    ))]
     async fn completion(
         &self,
         completion_request: completion::CompletionRequest,
@@ -79,7 +95,7 @@ impl completion::CompletionModel for CompletionModel {
                 "messages": full_history,
                 "temperature": completion_request.temperature,
                 "tools": completion_request.tools.into_iter().map(ToolDefinition::from).collect::<Vec<_>>(),
-                "tool_choice": "auto",
+                "tool_choice": ToolChoice::openai_or_auto(&completion_request.tool_choice),
             })
         };
 
@@ -93,12 +109,19 @@ impl completion::CompletionModel for CompletionModel {
             .client
             .post("/v1/chat/completions")
             .json(&request)
-            .send()
+            .send_logged("xai", &self.client.http)
             .await?;
 
         if response.status().is_success() {
             match response.json::<ApiResponse<CompletionResponse>>().await? {
-                ApiResponse::Ok(completion) => completion.try_into(),
+                ApiResponse::Ok(completion) => {
+                    crate::telemetry::record_usage(
+                        &self.model,
+                        Some(completion.usage.prompt_tokens as u64),
+                        Some(completion.usage.completion_tokens as u64),
+                    );
+                    completion.try_into()
+                }
                 ApiResponse::Error(error) => Err(CompletionError::ProviderError(error.message())),
             }
         } else {
@@ -144,7 +167,7 @@ pub mod xai_api_types {
                             .iter()
                             .map(|call| {
                                 completion::AssistantContent::tool_call(
-                                    &call.function.name,
+                                    &call.id,
                                     &call.function.name,
                                     call.function.arguments.clone(),
                                 )
@@ -6,7 +6,10 @@
 use serde::Deserialize;
 use serde_json::json;
 
-use crate::embeddings::{self, EmbeddingError};
+use crate::{
+    embeddings::{self, EmbeddingError},
+    payload_log::SendLogged,
+};
 
 use super::{
     client::xai_api_types::{ApiErrorResponse, ApiResponse},
@@ -69,7 +72,21 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
         self.ndims
     }
 
-    #[cfg_attr(feature = "worker", worker::send)]
+    #[cfg_attr(target_arch = "wasm32", rig_derive::wasm_send)]
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "embeddings",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("embeddings {}", self.model),
+            otel.kind = "client",
+            gen_ai.operation.name = "embeddings",
+            gen_ai.system = "xai",
+            gen_ai.request.model = %self.model,
+            gen_ai.usage.input_tokens
+        )
+    ))]
     async fn embed_texts(
         &self,
         documents: impl IntoIterator<Item = String>,
@@ -83,7 +100,7 @@ impl embeddings::EmbeddingModel for EmbeddingModel {
                 "model": self.model,
                 "input": documents,
             }))
-            .send()
+            .send_logged("xai", &self.client.http)
             .await?;
 
         if response.status().is_success() {
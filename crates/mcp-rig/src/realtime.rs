@@ -0,0 +1,626 @@
+//! Voice agents with the OpenAI Realtime API (requires the `realtime` feature).
+//!
+//! A [RealtimeSession] is a WebSocket connection to the Realtime API: the audio of the user is
+//! streamed with [RealtimeSession::send_audio] (PCM16, 24kHz, mono by default) and the
+//! [ServerEvent]s of the model (audio and transcript deltas, speech detection, function calls,
+//! etc.) are read with [RealtimeSession::next_event]. With server VAD (the default), the model
+//! detects the end of the user's speech and responds on its own.
+//!
+//! A [RealtimeAgent] adds tools to a session: its tools (native or MCP) are declared to the
+//! model, and the function calls of the model are executed with the [ToolSet] of the agent
+//! and their results sent back, so that a voice agent can act through MCP servers.
+//!
+//! For more information, see this link: <https://platform.openai.com/docs/guides/realtime>
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::realtime::{RealtimeAgent, RealtimeClient, ServerEvent};
+//!
+//! let client = RealtimeClient::from_env();
+//!
+//! let mut session = RealtimeAgent::new(client, mcp_rig::realtime::GPT_4O_REALTIME_PREVIEW)
+//!     .instructions("You are a helpful voice assistant.")
+//!     .voice("alloy")
+//!     .tool(weather_tool)
+//!     .connect()
+//!     .await?;
+//!
+//! session.send_audio(&microphone_chunk).await?;
+//!
+//! while let Some(event) = session.next_event().await? {
+//!     match event {
+//!         ServerEvent::ResponseAudioDelta { delta, .. } => speaker.play(&delta)?,
+//!         ServerEvent::InputAudioBufferSpeechStarted { .. } => speaker.stop()?,
+//!         _ => {}
+//!     }
+//! }
+//! ```
+use base64::{prelude::BASE64_STANDARD, Engine};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio_tungstenite::{
+    tungstenite::{self, client::IntoClientRequest, http::HeaderValue, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::{
+    completion::ToolDefinition,
+    tool::{Tool, ToolSet},
+};
+
+const OPENAI_REALTIME_BASE_URL: &str = "wss://api.openai.com/v1/realtime";
+
+/// `gpt-4o-realtime-preview` realtime model
+pub const GPT_4O_REALTIME_PREVIEW: &str = "gpt-4o-realtime-preview";
+/// `gpt-4o-mini-realtime-preview` realtime model
+pub const GPT_4O_MINI_REALTIME_PREVIEW: &str = "gpt-4o-mini-realtime-preview";
+
+#[derive(Debug, thiserror::Error)]
+pub enum RealtimeError {
+    /// WebSocket error (e.g.: connection error, closed connection, etc.)
+    #[error("WebSocketError: {0}")]
+    WebSocketError(#[from] tungstenite::Error),
+
+    /// Json error (e.g.: serialization, deserialization)
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error returned by OpenAI (i.e.: an `error` event)
+    #[error("ProviderError: {0}")]
+    ProviderError(String),
+}
+
+/// Client connecting [RealtimeSession]s to the OpenAI Realtime API.
+#[derive(Clone)]
+pub struct RealtimeClient {
+    api_key: String,
+    base_url: String,
+}
+
+impl RealtimeClient {
+    /// Create a new realtime client with the given API key.
+    pub fn new(api_key: &str) -> Self {
+        Self::from_url(api_key, OPENAI_REALTIME_BASE_URL)
+    }
+
+    /// Create a new realtime client with the given API key and base WebSocket URL.
+    pub fn from_url(api_key: &str, base_url: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            base_url: base_url.to_string(),
+        }
+    }
+
+    /// Create a new realtime client from the `OPENAI_API_KEY` environment variable.
+    /// Panics if the environment variable is not set.
+    pub fn from_env() -> Self {
+        let api_key = std::env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY not set");
+        Self::new(&api_key)
+    }
+
+    /// Open a session with the realtime model `model`, configured with `config`.
+    pub async fn connect(
+        &self,
+        model: &str,
+        config: SessionConfig,
+    ) -> Result<RealtimeSession, RealtimeError> {
+        let mut request = format!("{}?model={}", self.base_url, model).into_client_request()?;
+        let headers = request.headers_mut();
+        headers.insert(
+            "Authorization",
+            HeaderValue::from_str(&format!("Bearer {}", self.api_key))
+                .map_err(|e| RealtimeError::ProviderError(e.to_string()))?,
+        );
+        headers.insert("OpenAI-Beta", HeaderValue::from_static("realtime=v1"));
+
+        let (socket, _) = tokio_tungstenite::connect_async(request).await?;
+        let mut session = RealtimeSession { socket };
+        session
+            .send(ClientEvent::SessionUpdate { session: config })
+            .await?;
+        Ok(session)
+    }
+}
+
+/// Configuration of a realtime session.
+///
+/// For more information, see this link: <https://platform.openai.com/docs/api-reference/realtime-client-events/session/update>
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SessionConfig {
+    /// System prompt of the model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    /// Voice of the model (e.g.: `alloy`, `echo`, `shimmer`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    /// `["text"]` or `["audio", "text"]` (the default)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modalities: Option<Vec<String>>,
+    /// `pcm16` (the default), `g711_ulaw` or `g711_alaw`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_audio_format: Option<String>,
+    /// `pcm16` (the default), `g711_ulaw` or `g711_alaw`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_audio_format: Option<String>,
+    /// Transcription of the audio of the user (e.g.: `{"model": "whisper-1"}`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_audio_transcription: Option<serde_json::Value>,
+    /// Detection of the end of the user's speech. Server VAD is used when unset, and
+    /// `Some(TurnDetection::None)` disables it (i.e.: the audio is committed manually).
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_turn_detection"
+    )]
+    pub turn_detection: Option<TurnDetection>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub tools: Vec<RealtimeTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+}
+
+/// Turn detection of a realtime session.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TurnDetection {
+    /// Voice activity detection by the server
+    ServerVad {
+        /// Activation threshold of the VAD, between 0 and 1 (defaults to 0.5)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        threshold: Option<f64>,
+        /// Audio included before the detected speech, in ms (defaults to 300)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        prefix_padding_ms: Option<u64>,
+        /// Silence ending the speech, in ms (defaults to 500)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        silence_duration_ms: Option<u64>,
+    },
+    /// No turn detection: the audio is committed with [RealtimeSession::commit_audio]
+    None,
+}
+
+fn serialize_turn_detection<S: serde::Serializer>(
+    turn_detection: &Option<TurnDetection>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match turn_detection {
+        Some(TurnDetection::None) | None => serializer.serialize_none(),
+        Some(turn_detection) => turn_detection.serialize(serializer),
+    }
+}
+
+/// Definition of a function callable by the realtime model.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RealtimeTool {
+    pub r#type: String,
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+}
+
+impl From<ToolDefinition> for RealtimeTool {
+    fn from(tool: ToolDefinition) -> Self {
+        Self {
+            r#type: "function".into(),
+            name: tool.name,
+            description: tool.description,
+            parameters: tool.parameters,
+        }
+    }
+}
+
+/// Events sent to the Realtime API.
+///
+/// For more information, see this link: <https://platform.openai.com/docs/api-reference/realtime-client-events>
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type")]
+pub enum ClientEvent {
+    #[serde(rename = "session.update")]
+    SessionUpdate { session: SessionConfig },
+    /// Audio of the user, base64 encoded
+    #[serde(rename = "input_audio_buffer.append")]
+    InputAudioBufferAppend { audio: String },
+    #[serde(rename = "input_audio_buffer.commit")]
+    InputAudioBufferCommit,
+    #[serde(rename = "input_audio_buffer.clear")]
+    InputAudioBufferClear,
+    /// Item added to the conversation (e.g.: a text message, or the output of a function call)
+    #[serde(rename = "conversation.item.create")]
+    ConversationItemCreate { item: serde_json::Value },
+    #[serde(rename = "response.create")]
+    ResponseCreate,
+    #[serde(rename = "response.cancel")]
+    ResponseCancel,
+}
+
+/// Events received from the Realtime API. Events not listed here are [ServerEvent::Other].
+///
+/// For more information, see this link: <https://platform.openai.com/docs/api-reference/realtime-server-events>
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum ServerEvent {
+    #[serde(rename = "error")]
+    Error { error: RealtimeApiError },
+    #[serde(rename = "session.created")]
+    SessionCreated { session: serde_json::Value },
+    #[serde(rename = "session.updated")]
+    SessionUpdated { session: serde_json::Value },
+    /// The user started speaking (e.g.: to interrupt the audio of the model)
+    #[serde(rename = "input_audio_buffer.speech_started")]
+    InputAudioBufferSpeechStarted { item_id: String },
+    #[serde(rename = "input_audio_buffer.speech_stopped")]
+    InputAudioBufferSpeechStopped { item_id: String },
+    /// Transcript of the audio of the user
+    #[serde(rename = "conversation.item.input_audio_transcription.completed")]
+    InputAudioTranscriptionCompleted { item_id: String, transcript: String },
+    /// Chunk of the audio of the model, decoded
+    #[serde(rename = "response.audio.delta")]
+    ResponseAudioDelta {
+        response_id: String,
+        item_id: String,
+        #[serde(deserialize_with = "deserialize_base64")]
+        delta: Vec<u8>,
+    },
+    #[serde(rename = "response.audio.done")]
+    ResponseAudioDone {
+        response_id: String,
+        item_id: String,
+    },
+    /// Chunk of the transcript of the audio of the model
+    #[serde(rename = "response.audio_transcript.delta")]
+    ResponseAudioTranscriptDelta {
+        response_id: String,
+        item_id: String,
+        delta: String,
+    },
+    #[serde(rename = "response.text.delta")]
+    ResponseTextDelta {
+        response_id: String,
+        item_id: String,
+        delta: String,
+    },
+    /// Function call of the model, with its complete arguments
+    #[serde(rename = "response.function_call_arguments.done")]
+    FunctionCallArgumentsDone {
+        response_id: String,
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+    #[serde(rename = "response.done")]
+    ResponseDone { response: serde_json::Value },
+    #[serde(other)]
+    Other,
+}
+
+/// Error of an `error` event.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RealtimeApiError {
+    pub r#type: String,
+    #[serde(default)]
+    pub code: Option<String>,
+    pub message: String,
+}
+
+fn deserialize_base64<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<u8>, D::Error> {
+    let encoded = String::deserialize(deserializer)?;
+    BASE64_STANDARD
+        .decode(encoded)
+        .map_err(serde::de::Error::custom)
+}
+
+/// A WebSocket session with the OpenAI Realtime API.
+pub struct RealtimeSession {
+    socket: WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>,
+}
+
+impl RealtimeSession {
+    /// Send a raw [ClientEvent].
+    pub async fn send(&mut self, event: ClientEvent) -> Result<(), RealtimeError> {
+        let event = serde_json::to_string(&event)?;
+        self.socket.send(Message::Text(event)).await?;
+        Ok(())
+    }
+
+    /// Stream a chunk of the audio of the user (in the input audio format of the session).
+    pub async fn send_audio(&mut self, audio: &[u8]) -> Result<(), RealtimeError> {
+        self.send(ClientEvent::InputAudioBufferAppend {
+            audio: BASE64_STANDARD.encode(audio),
+        })
+        .await
+    }
+
+    /// Commit the audio of the user (only needed without turn detection), and request a response.
+    pub async fn commit_audio(&mut self) -> Result<(), RealtimeError> {
+        self.send(ClientEvent::InputAudioBufferCommit).await?;
+        self.send(ClientEvent::ResponseCreate).await
+    }
+
+    /// Send a text message of the user, and request a response.
+    pub async fn send_text(&mut self, text: &str) -> Result<(), RealtimeError> {
+        self.send(ClientEvent::ConversationItemCreate {
+            item: json!({
+                "type": "message",
+                "role": "user",
+                "content": [{ "type": "input_text", "text": text }]
+            }),
+        })
+        .await?;
+        self.send(ClientEvent::ResponseCreate).await
+    }
+
+    /// Send the output of the function call `call_id`, and request a response.
+    pub async fn send_function_output(
+        &mut self,
+        call_id: &str,
+        output: &str,
+    ) -> Result<(), RealtimeError> {
+        self.send(ClientEvent::ConversationItemCreate {
+            item: json!({
+                "type": "function_call_output",
+                "call_id": call_id,
+                "output": output
+            }),
+        })
+        .await?;
+        self.send(ClientEvent::ResponseCreate).await
+    }
+
+    /// Cancel the response in progress (e.g.: when the user interrupts the model).
+    pub async fn cancel_response(&mut self) -> Result<(), RealtimeError> {
+        self.send(ClientEvent::ResponseCancel).await
+    }
+
+    /// Wait for the next event of the model, or `None` once the session is closed.
+    /// `error` events are returned as [ServerEvent::Error] (the session stays open).
+    pub async fn next_event(&mut self) -> Result<Option<ServerEvent>, RealtimeError> {
+        while let Some(message) = self.socket.next().await {
+            match message? {
+                Message::Text(text) => return Ok(Some(serde_json::from_str(&text)?)),
+                Message::Close(_) => return Ok(None),
+                _ => continue,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Close the session.
+    pub async fn close(mut self) -> Result<(), RealtimeError> {
+        self.socket.close(None).await?;
+        Ok(())
+    }
+}
+
+/// Builder of realtime sessions whose function calls are executed with a [ToolSet]
+/// (i.e.: native and MCP tools, like a text [Agent](crate::agent::Agent)).
+pub struct RealtimeAgent {
+    client: RealtimeClient,
+    model: String,
+    config: SessionConfig,
+    tools: ToolSet,
+}
+
+impl RealtimeAgent {
+    pub fn new(client: RealtimeClient, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+            config: SessionConfig::default(),
+            tools: ToolSet::default(),
+        }
+    }
+
+    /// Set the system prompt
+    pub fn instructions(mut self, instructions: &str) -> Self {
+        self.config.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Set the voice of the model (e.g.: `alloy`)
+    pub fn voice(mut self, voice: &str) -> Self {
+        self.config.voice = Some(voice.into());
+        self
+    }
+
+    /// Set the turn detection of the session (server VAD by default)
+    pub fn turn_detection(mut self, turn_detection: TurnDetection) -> Self {
+        self.config.turn_detection = Some(turn_detection);
+        self
+    }
+
+    /// Set the temperature of the model
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.config.temperature = Some(temperature);
+        self
+    }
+
+    /// Set the whole configuration of the session (the tools are added to it on connection)
+    pub fn config(mut self, config: SessionConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Add a tool to the agent
+    pub fn tool(mut self, tool: impl Tool + 'static) -> Self {
+        self.tools.add_tool(tool);
+        self
+    }
+
+    /// Add a tool of an MCP server to the agent
+    pub fn mcp_tool<T: mcp_core::transport::Transport>(
+        mut self,
+        tool: mcp_core::types::Tool,
+        client: std::sync::Arc<mcp_core::client::Client<T>>,
+    ) -> Self {
+        self.tools
+            .add_tool(crate::tool::McpTool::from_mcp_server(tool, client));
+        self
+    }
+
+    /// Add the tools of `toolset` to the agent
+    pub fn tools(mut self, toolset: ToolSet) -> Self {
+        self.tools.add_tools(toolset);
+        self
+    }
+
+    /// Open the session, declaring the tools of the agent to the model.
+    pub async fn connect(mut self) -> Result<RealtimeAgentSession, RealtimeError> {
+        for tool in self.tools.tools.values() {
+            let definition = tool.definition(String::new()).await;
+            self.config.tools.push(definition.into());
+        }
+
+        let session = self.client.connect(&self.model, self.config).await?;
+        Ok(RealtimeAgentSession {
+            session,
+            tools: self.tools,
+        })
+    }
+}
+
+/// A [RealtimeSession] executing the function calls of the model with the tools of its agent.
+pub struct RealtimeAgentSession {
+    session: RealtimeSession,
+    tools: ToolSet,
+}
+
+impl RealtimeAgentSession {
+    /// Wait for the next event of the model, or `None` once the session is closed.
+    ///
+    /// When the event is a function call, the tool is called before the event is returned,
+    /// and its result sent back to the model (which then continues its response). Tool errors
+    /// are sent to the model as `{"error": "..."}` so that it can tell the user.
+    pub async fn next_event(&mut self) -> Result<Option<ServerEvent>, RealtimeError> {
+        let event = self.session.next_event().await?;
+        if let Some(ServerEvent::FunctionCallArgumentsDone {
+            call_id,
+            name,
+            arguments,
+            ..
+        }) = &event
+        {
+            let output = match self.tools.call(name, arguments.clone()).await {
+                Ok(output) => output,
+                Err(e) => {
+                    tracing::warn!(target: "rig", "Realtime tool call {} failed: {}", name, e);
+                    json!({ "error": e.to_string() }).to_string()
+                }
+            };
+            self.session.send_function_output(call_id, &output).await?;
+        }
+        Ok(event)
+    }
+
+    /// The underlying session (e.g.: to stream audio, or send text messages).
+    pub fn session(&mut self) -> &mut RealtimeSession {
+        &mut self.session
+    }
+
+    /// Stream a chunk of the audio of the user (in the input audio format of the session).
+    pub async fn send_audio(&mut self, audio: &[u8]) -> Result<(), RealtimeError> {
+        self.session.send_audio(audio).await
+    }
+
+    /// Send a text message of the user, and request a response.
+    pub async fn send_text(&mut self, text: &str) -> Result<(), RealtimeError> {
+        self.session.send_text(text).await
+    }
+
+    /// Close the session.
+    pub async fn close(self) -> Result<(), RealtimeError> {
+        self.session.close().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_session_update() {
+        let event = ClientEvent::SessionUpdate {
+            session: SessionConfig {
+                instructions: Some("Be brief.".into()),
+                turn_detection: Some(TurnDetection::ServerVad {
+                    threshold: Some(0.6),
+                    prefix_padding_ms: None,
+                    silence_duration_ms: None,
+                }),
+                tools: vec![ToolDefinition {
+                    name: "add".into(),
+                    description: "Add x and y".into(),
+                    parameters: json!({"type": "object"}),
+                }
+                .into()],
+                ..Default::default()
+            },
+        };
+
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({
+                "type": "session.update",
+                "session": {
+                    "instructions": "Be brief.",
+                    "turn_detection": {"type": "server_vad", "threshold": 0.6},
+                    "tools": [{
+                        "type": "function",
+                        "name": "add",
+                        "description": "Add x and y",
+                        "parameters": {"type": "object"}
+                    }]
+                }
+            })
+        );
+
+        let config = SessionConfig {
+            turn_detection: Some(TurnDetection::None),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&config).unwrap(),
+            json!({"turn_detection": null})
+        );
+    }
+
+    #[test]
+    fn test_deserialize_server_events() {
+        let event: ServerEvent = serde_json::from_value(json!({
+            "type": "response.audio.delta",
+            "event_id": "event_1",
+            "response_id": "resp_1",
+            "item_id": "item_1",
+            "output_index": 0,
+            "content_index": 0,
+            "delta": "AAEC"
+        }))
+        .unwrap();
+        let ServerEvent::ResponseAudioDelta { delta, .. } = event else {
+            panic!("Expected an audio delta");
+        };
+        assert_eq!(delta, vec![0, 1, 2]);
+
+        let event: ServerEvent = serde_json::from_value(json!({
+            "type": "response.function_call_arguments.done",
+            "event_id": "event_2",
+            "response_id": "resp_1",
+            "item_id": "item_2",
+            "output_index": 0,
+            "call_id": "call_1",
+            "name": "add",
+            "arguments": "{\"x\": 1, \"y\": 2}"
+        }))
+        .unwrap();
+        assert!(matches!(
+            event,
+            ServerEvent::FunctionCallArgumentsDone { ref name, .. } if name == "add"
+        ));
+
+        let event: ServerEvent =
+            serde_json::from_value(json!({"type": "rate_limits.updated", "rate_limits": []}))
+                .unwrap();
+        assert!(matches!(event, ServerEvent::Other));
+    }
+}
@@ -0,0 +1,437 @@
+//! Detection and redaction of personally identifiable information (PII) and secrets.
+//!
+//! A [Redactor] replaces the email addresses, phone numbers, payment card numbers, IP
+//! addresses and API keys of a text (plus custom patterns, and the entities found by an
+//! optional NER model, e.g.: the names of people) with tokens such as `<EMAIL_1>`. The
+//! tokenization is reversible: the original values are kept in a [Vault], which re-hydrates
+//! the texts containing the tokens (e.g.: the answer of an agent, for the end user).
+//!
+//! An agent with a redactor (see [AgentBuilder::redaction](crate::agent::AgentBuilder::redaction))
+//! redacts its prompts, chat history and tool results before they are sent to the model,
+//! re-hydrates the arguments of the tool calls before the tools are called, and re-hydrates
+//! its answers (streamed or not). A redactor is also a [RedactionRule] of the
+//! [PayloadLogger](crate::payload_log::PayloadLogger), to scrub the logs.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{providers::openai, redaction::{Entities, Redactor, Vault}};
+//!
+//! let openai = openai::Client::from_env();
+//! let ner = openai.extractor::<Entities>(openai::GPT_4O_MINI).build();
+//! let redactor = Redactor::new()
+//!     .pattern("CUSTOMER_ID", r"\bCUS-\d{6}\b")?
+//!     .ner(ner);
+//!
+//! let mut vault = Vault::default();
+//! let redacted = redactor.redact("Email John Doe at john@doe.com", &mut vault).await?;
+//! assert_eq!(redacted, "Email <PERSON_1> at <EMAIL_1>");
+//! assert_eq!(vault.rehydrate(&redacted), "Email John Doe at john@doe.com");
+//!
+//! // Redact the prompts of an agent
+//! let agent = openai.agent(openai::GPT_4O).redaction(redactor).build();
+//! ```
+use std::sync::Arc;
+
+use async_stream::stream;
+use futures::{future::BoxFuture, StreamExt};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    completion::{AssistantContent, CompletionModel, Message},
+    extractor::{ExtractionError, Extractor},
+    message::{ToolResultContent, UserContent},
+    payload_log::{RedactionRule, PII_PATTERNS},
+    streaming::{StreamingChoice, StreamingResult},
+};
+
+/// Pattern of the API keys redacted by a [Redactor], before the
+/// [PII_PATTERNS](crate::payload_log::PII_PATTERNS).
+const KEY_PATTERN: &str = r"\b(?:sk-|xai-|ghp_|github_pat_|AIza|AKIA)[A-Za-z0-9_-]{12,}";
+
+/// Entities found by the NER model of a [Redactor].
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct Entities {
+    /// The personal information found in the text: names of people, postal addresses,
+    /// organizations, dates of birth, identity document numbers, etc.
+    pub entities: Vec<Entity>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct Entity {
+    /// Kind of the entity, in uppercase (e.g.: PERSON, ADDRESS, ORGANIZATION)
+    pub kind: String,
+    /// Text of the entity, exactly as it appears in the text
+    pub text: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RedactionError {
+    #[error("NerError: {0}")]
+    NerError(#[from] ExtractionError),
+}
+
+type Ner =
+    Arc<dyn Fn(String) -> BoxFuture<'static, Result<Entities, ExtractionError>> + Send + Sync>;
+
+/// Original values of the tokens of the redacted texts, to re-hydrate them.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Vault {
+    /// Tokens and their values, in order of creation
+    entries: Vec<(String, String)>,
+}
+
+impl Vault {
+    /// Token of `value` (of the given `kind`), created if `value` has none yet
+    pub fn token(&mut self, kind: &str, value: &str) -> String {
+        if let Some((token, _)) = self.entries.iter().find(|(_, v)| v == value) {
+            return token.clone();
+        }
+        let prefix = format!("<{kind}_");
+        let index = self
+            .entries
+            .iter()
+            .filter(|(token, _)| token.starts_with(&prefix))
+            .count()
+            + 1;
+        let token = format!("{prefix}{index}>");
+        self.entries.push((token.clone(), value.to_string()));
+        token
+    }
+
+    /// Original value of `token`
+    pub fn get(&self, token: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(t, _)| t == token)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Replace the tokens of `text` with their original values
+    pub fn rehydrate(&self, text: &str) -> String {
+        self.entries
+            .iter()
+            .fold(text.to_string(), |text, (token, value)| {
+                text.replace(token, value)
+            })
+    }
+
+    /// Replace the tokens of the strings of `value` (e.g.: the arguments of a tool call) with
+    /// their original values
+    pub fn rehydrate_json(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => object
+                .values_mut()
+                .for_each(|value| self.rehydrate_json(value)),
+            Value::Array(values) => values
+                .iter_mut()
+                .for_each(|value| self.rehydrate_json(value)),
+            Value::String(string) => *string = self.rehydrate(string),
+            _ => (),
+        }
+    }
+
+    /// Replace the tokens of the chunks of `stream` (e.g.: the streamed answer of an agent) with
+    /// their original values. The text that could be the start of a token split across chunks
+    /// is held back until the token is complete.
+    pub fn rehydrate_stream(self, mut stream: StreamingResult) -> StreamingResult {
+        let longest = self
+            .entries
+            .iter()
+            .map(|(token, _)| token.len())
+            .max()
+            .unwrap_or_default();
+
+        Box::pin(stream! {
+            // Text received and not sent yet
+            let mut pending = String::new();
+
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(StreamingChoice::Message(chunk)) => {
+                        pending.push_str(&chunk);
+                        let end = match pending.rfind('<') {
+                            Some(start)
+                                if !pending[start..].contains('>')
+                                    && pending.len() - start < longest =>
+                            {
+                                start
+                            }
+                            _ => pending.len(),
+                        };
+                        if end > 0 {
+                            yield Ok(StreamingChoice::Message(self.rehydrate(&pending[..end])));
+                            pending.drain(..end);
+                        }
+                    }
+                    Ok(StreamingChoice::ToolCall(name, id, mut arguments)) => {
+                        if !pending.is_empty() {
+                            yield Ok(StreamingChoice::Message(self.rehydrate(&pending)));
+                            pending.clear();
+                        }
+                        self.rehydrate_json(&mut arguments);
+                        yield Ok(StreamingChoice::ToolCall(name, id, arguments));
+                    }
+                    Err(e) => {
+                        if !pending.is_empty() {
+                            yield Ok(StreamingChoice::Message(self.rehydrate(&pending)));
+                            pending.clear();
+                        }
+                        yield Err(e);
+                    }
+                }
+            }
+
+            if !pending.is_empty() {
+                yield Ok(StreamingChoice::Message(self.rehydrate(&pending)));
+            }
+        })
+    }
+}
+
+/// Redactor of the PII and secrets of texts (see the [module](self) docs).
+#[derive(Clone)]
+pub struct Redactor {
+    /// Kinds and patterns of the redacted values, applied in order
+    rules: Vec<(String, Regex)>,
+    ner: Option<Ner>,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self {
+            rules: std::iter::once(&("KEY", KEY_PATTERN))
+                .chain(PII_PATTERNS)
+                .map(|(kind, pattern)| {
+                    let pattern = Regex::new(pattern).expect("PII pattern should be valid");
+                    (kind.to_string(), pattern)
+                })
+                .collect(),
+            ner: None,
+        }
+    }
+}
+
+impl Redactor {
+    /// Redactor of the email addresses, API keys, payment card numbers, phone numbers and IP
+    /// addresses
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also redact the values matching the regex `pattern` (e.g.: customer IDs), with tokens
+    /// of the given `kind` (e.g.: `CUSTOMER_ID`)
+    pub fn pattern(mut self, kind: &str, pattern: &str) -> Result<Self, regex::Error> {
+        self.rules.push((kind.to_uppercase(), Regex::new(pattern)?));
+        Ok(self)
+    }
+
+    /// Also redact the entities found by the NER model `extractor` (e.g.: the names of people
+    /// and their addresses), after the patterns
+    pub fn ner<M: CompletionModel + 'static>(mut self, extractor: Extractor<M, Entities>) -> Self {
+        let extractor = Arc::new(extractor);
+        self.ner = Some(Arc::new(move |text| {
+            let extractor = extractor.clone();
+            Box::pin(async move { extractor.extract(&text).await })
+        }));
+        self
+    }
+
+    /// Redact the values matching the patterns of `text`, with the tokens of `vault`
+    pub fn redact_patterns(&self, text: &str, vault: &mut Vault) -> String {
+        self.rules
+            .iter()
+            .fold(text.to_string(), |text, (kind, pattern)| {
+                pattern
+                    .replace_all(&text, |captures: &regex::Captures| {
+                        vault.token(kind, &captures[0])
+                    })
+                    .into_owned()
+            })
+    }
+
+    /// Redact the values matching the patterns of `text` and the entities found by the NER
+    /// model (if any), with the tokens of `vault`
+    pub async fn redact(&self, text: &str, vault: &mut Vault) -> Result<String, RedactionError> {
+        let mut text = self.redact_patterns(text, vault);
+        let Some(ner) = &self.ner else {
+            return Ok(text);
+        };
+
+        let entities = ner(text.clone()).await?;
+        for entity in entities.entities {
+            if entity.text.trim().is_empty() || !text.contains(&entity.text) {
+                continue;
+            }
+            let token = vault.token(&entity.kind.to_uppercase(), &entity.text);
+            text = text.replace(&entity.text, &token);
+        }
+        Ok(text)
+    }
+
+    /// Redact the texts of `message` (including the texts of its tool results, and the strings
+    /// of the arguments of its tool calls)
+    pub async fn redact_message(
+        &self,
+        mut message: Message,
+        vault: &mut Vault,
+    ) -> Result<Message, RedactionError> {
+        let texts: Vec<&mut String> = match &mut message {
+            Message::User { content } => content
+                .iter_mut()
+                .flat_map(|content| match content {
+                    UserContent::Text(text) => vec![&mut text.text],
+                    UserContent::ToolResult(result) => result
+                        .content
+                        .iter_mut()
+                        .filter_map(|content| match content {
+                            ToolResultContent::Text(text) => Some(&mut text.text),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => vec![],
+                })
+                .collect(),
+            Message::Assistant { content } => content
+                .iter_mut()
+                .flat_map(|content| match content {
+                    AssistantContent::Text(text) => vec![&mut text.text],
+                    AssistantContent::ToolCall(call) => json_strings(&mut call.function.arguments),
+                })
+                .collect(),
+        };
+        for text in texts {
+            *text = self.redact(text, vault).await?;
+        }
+        Ok(message)
+    }
+
+    /// Irreversibly replace the values matching the patterns of `text` with their kind (e.g.:
+    /// `<EMAIL>`), e.g.: for logs
+    pub fn scrub(&self, text: &str) -> String {
+        self.rules
+            .iter()
+            .fold(text.to_string(), |text, (kind, pattern)| {
+                pattern.replace_all(&text, format!("<{kind}>")).into_owned()
+            })
+    }
+
+    fn scrub_value(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => object
+                .values_mut()
+                .for_each(|value| self.scrub_value(value)),
+            Value::Array(values) => values.iter_mut().for_each(|value| self.scrub_value(value)),
+            Value::String(string) => *string = self.scrub(string),
+            _ => (),
+        }
+    }
+}
+
+/// Strings of `value` (e.g.: the arguments of a tool call)
+fn json_strings(value: &mut Value) -> Vec<&mut String> {
+    match value {
+        Value::Object(object) => object.values_mut().flat_map(json_strings).collect(),
+        Value::Array(values) => values.iter_mut().flat_map(json_strings).collect(),
+        Value::String(string) => vec![string],
+        _ => vec![],
+    }
+}
+
+impl RedactionRule for Redactor {
+    fn redact(&self, entry: &mut Value) {
+        self.scrub_value(entry)
+    }
+}
+
+impl std::fmt::Debug for Redactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Redactor")
+            .field("rules", &self.rules)
+            .field("ner", &self.ner.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        providers::mock::{self, MockResponse},
+        OneOrMany,
+    };
+
+    #[tokio::test]
+    async fn test_redact() {
+        let redactor = Redactor::new()
+            .pattern("customer_id", r"\bCUS-\d{6}\b")
+            .unwrap();
+        let mut vault = Vault::default();
+
+        let text = "Mail a@b.com or c@d.org (not a@b.com), call +1 555-123-4567, \
+            customer CUS-123456";
+        let redacted = redactor.redact(text, &mut vault).await.unwrap();
+        assert_eq!(
+            redacted,
+            "Mail <EMAIL_1> or <EMAIL_2> (not <EMAIL_1>), call <PHONE_1>, \
+            customer <CUSTOMER_ID_1>"
+        );
+        assert_eq!(vault.rehydrate(&redacted), text);
+
+        let mut arguments = json!({"to": ["<EMAIL_2>"]});
+        vault.rehydrate_json(&mut arguments);
+        assert_eq!(arguments, json!({"to": ["c@d.org"]}));
+
+        assert_eq!(redactor.scrub("Key: sk-abcdef1234567890"), "Key: <KEY>");
+
+        // The arguments of the tool calls of the chat history are redacted too
+        let message = Message::Assistant {
+            content: OneOrMany::one(AssistantContent::tool_call(
+                "call_1",
+                "send",
+                json!({"to": ["a@b.com", "e@f.net"], "copies": 2}),
+            )),
+        };
+        let Message::Assistant { content } =
+            redactor.redact_message(message, &mut vault).await.unwrap()
+        else {
+            panic!("Expected an assistant message");
+        };
+        let AssistantContent::ToolCall(call) = content.first() else {
+            panic!("Expected a tool call");
+        };
+        assert_eq!(
+            call.function.arguments,
+            json!({"to": ["<EMAIL_1>", "<EMAIL_3>"], "copies": 2})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ner() {
+        let model = mock::CompletionModel::new().push(MockResponse::tool_call(
+            "submit",
+            json!({"entities": [{"kind": "person", "text": "John Doe"}]}),
+        ));
+        let redactor = Redactor::new().ner(model.extractor::<Entities>().build());
+        let mut vault = Vault::default();
+
+        let redacted = redactor
+            .redact("John Doe (john@doe.com)", &mut vault)
+            .await
+            .unwrap();
+        assert_eq!(redacted, "<PERSON_1> (<EMAIL_1>)");
+        assert_eq!(vault.get("<PERSON_1>"), Some("John Doe"));
+    }
+}
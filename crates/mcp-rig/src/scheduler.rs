@@ -0,0 +1,538 @@
+//! Scheduling of background agent tasks, e.g.: posting a daily summary to Discord with an agent
+//! and the tools of an MCP server.
+//!
+//! A [Scheduler] runs [Task]s (i.e.: a prompt of an agent, or any job such as a pipeline run)
+//! on a [Schedule]:
+//! - [Schedule::cron]: on a cron expression (in UTC), e.g.: `0 9 * * 1-5` for 9:00 on weekdays,
+//! - [Schedule::every]: at a fixed interval,
+//! - [Schedule::after]: once, after a delay.
+//!
+//! A task due while its previous run is still running is skipped, unless it allows overlapping
+//! runs (see [Task::allow_overlap]). The outcome of each run (or skipped run) is passed to the
+//! callback of the task (see [Task::on_result]).
+//!
+//! The scheduler runs the tasks concurrently, in the future returned by [Scheduler::run] (e.g.:
+//! spawned on the runtime of the service), which completes once no task is scheduled anymore.
+//!
+//! # Example
+//! ```rust
+//! use std::sync::Arc;
+//! use mcp_rig::{
+//!     providers::openai,
+//!     scheduler::{Schedule, Scheduler, Task, TaskOutcome},
+//! };
+//!
+//! let agent = Arc::new(
+//!     openai.agent(openai::GPT_4O)
+//!         .preamble("You post a summary of the day's news to the #general channel of Discord.")
+//!         .mcp_tool(send_message, discord_client)
+//!         .build(),
+//! );
+//!
+//! let scheduler = Scheduler::new().task(
+//!     Task::prompt("daily-summary", Schedule::cron("0 18 * * *")?, agent, "Post the summary")
+//!         .on_result(|run| {
+//!             if let TaskOutcome::Failed { error } = &run.outcome {
+//!                 eprintln!("{} failed: {error}", run.task);
+//!             }
+//!         }),
+//! );
+//! tokio::spawn(scheduler.run());
+//! ```
+use std::{
+    fmt::Display,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::{
+    future::{self, BoxFuture, Either},
+    stream::FuturesUnordered,
+    StreamExt,
+};
+use futures_timer::Delay;
+use serde::{Deserialize, Serialize};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::{completion::Prompt, observability::utc};
+
+/// Number of days searched for the next run of a cron expression (e.g.: `0 0 29 2 *` runs once
+/// every 4 years).
+const CRON_SEARCH_DAYS: u64 = 8 * 366;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CronError {
+    /// The cron expression doesn't have 5 fields
+    #[error("Invalid cron expression {0:?}: expected 5 fields")]
+    FieldCount(String),
+    /// A field of the cron expression is invalid
+    #[error("Invalid cron field {field:?}: {reason}")]
+    InvalidField { field: String, reason: String },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduleError {
+    /// The interval of a [Schedule::Every] is zero
+    #[error("The interval of a schedule must not be zero")]
+    ZeroInterval,
+}
+
+/// Cron expression: minute, hour, day of the month, month and day of the week (`0` or `7` for
+/// Sunday), in UTC. Each field is `*`, a value, a range (e.g.: `1-5`), a step (e.g.: `*/15` or
+/// `0-30/10`) or a list of these (e.g.: `0,30`). The `@hourly`, `@daily` and `@weekly` aliases
+/// are supported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cron {
+    /// Bitsets of the allowed values of each field
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day of the month and the day of the week are restricted (in which case a
+    /// day matching either of them matches, as in crontab)
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl Cron {
+    pub fn parse(expression: &str) -> Result<Self, CronError> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            expression => expression,
+        };
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(CronError::FieldCount(expression.to_string()));
+        };
+
+        let weekdays_set = parse_field(weekdays, 0, 7)?;
+        Ok(Self {
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            // Sunday is both 0 and 7
+            weekdays: (weekdays_set | weekdays_set >> 7) & 0x7f,
+            // As in crontab, a field starting with `*` (e.g.: `*/2`) is not a restriction
+            days_restricted: !days.starts_with('*'),
+            weekdays_restricted: !weekdays.starts_with('*'),
+        })
+    }
+
+    /// First time strictly after `time` matching the expression (to the minute)
+    pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let start = (secs / 60 + 1) * 60;
+        let first_day = start / 86400;
+
+        (first_day..first_day + CRON_SEARCH_DAYS)
+            .filter(|day| self.matches_day(*day))
+            .find_map(|day| {
+                (0..24u64)
+                    .filter(|hour| self.hours & 1 << hour != 0)
+                    .flat_map(|hour| {
+                        (0..60u64)
+                            .filter(|minute| self.minutes & 1 << minute != 0)
+                            .map(move |minute| day * 86400 + hour * 3600 + minute * 60)
+                    })
+                    .find(|secs| *secs >= start)
+            })
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Whether the day `day` (since the Unix epoch) matches the expression
+    fn matches_day(&self, day: u64) -> bool {
+        let (_, month, day_of_month, ..) = utc(UNIX_EPOCH + Duration::from_secs(day * 86400));
+        // The Unix epoch was a Thursday
+        let weekday = (day + 4) % 7;
+
+        let day_matches = self.days & 1 << day_of_month != 0;
+        let weekday_matches = self.weekdays & 1 << weekday != 0;
+        let days_match = if self.days_restricted && self.weekdays_restricted {
+            day_matches || weekday_matches
+        } else {
+            day_matches && weekday_matches
+        };
+        self.months & 1 << month != 0 && days_match
+    }
+}
+
+/// Bitset of the values of the cron field `field`, between `min` and `max`
+fn parse_field(field: &str, min: u64, max: u64) -> Result<u64, CronError> {
+    let invalid = |reason: &str| CronError::InvalidField {
+        field: field.to_string(),
+        reason: reason.to_string(),
+    };
+    let number = |value: &str| value.parse::<u64>().map_err(|_| invalid("not a number"));
+
+    let mut set = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, number(step)?),
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (number(start)?, number(end)?),
+            // A single value with a step runs from the value to the maximum
+            None if part.contains('/') => (number(range)?, max),
+            None => (number(range)?, number(range)?),
+        };
+        if step == 0 {
+            return Err(invalid("step of 0"));
+        }
+        if start < min || end > max || start > end {
+            return Err(invalid(&format!("out of the range {min}-{max}")));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+/// When a [Task] runs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    /// On a cron expression
+    Cron(Cron),
+    /// At a fixed interval, the first run being after the interval. A zero interval never runs
+    /// (see [Schedule::every]).
+    Every(Duration),
+    /// Once, after a delay
+    After(Duration),
+}
+
+impl Schedule {
+    /// Run on the cron expression `expression` (see [Cron])
+    pub fn cron(expression: &str) -> Result<Self, CronError> {
+        Ok(Schedule::Cron(Cron::parse(expression)?))
+    }
+
+    /// Run every `interval`, which must not be zero
+    pub fn every(interval: Duration) -> Result<Self, ScheduleError> {
+        if interval.is_zero() {
+            return Err(ScheduleError::ZeroInterval);
+        }
+        Ok(Schedule::Every(interval))
+    }
+
+    /// Run once, after `delay`
+    pub fn after(delay: Duration) -> Self {
+        Schedule::After(delay)
+    }
+
+    /// Time of the run after the run of `time` (or after the start of the scheduler, if
+    /// `first`), if any
+    fn next(&self, time: SystemTime, first: bool) -> Option<SystemTime> {
+        match self {
+            Schedule::Cron(cron) => cron.next_after(time),
+            // A zero interval would run the task in a busy loop
+            Schedule::Every(interval) if interval.is_zero() => None,
+            Schedule::Every(interval) => Some(time + *interval),
+            Schedule::After(delay) => first.then_some(time + *delay),
+        }
+    }
+}
+
+/// Outcome of a run of a [Task].
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TaskOutcome {
+    Succeeded {
+        output: String,
+    },
+    Failed {
+        error: String,
+    },
+    /// The run was skipped, since the previous run of the task was still running
+    Skipped,
+}
+
+/// Run of a [Task].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaskRun {
+    /// Name of the task
+    pub task: String,
+    /// Time the run was scheduled at
+    pub scheduled_at: SystemTime,
+    pub duration: Duration,
+    pub outcome: TaskOutcome,
+}
+
+type Job = Arc<dyn Fn() -> BoxFuture<'static, Result<String, String>> + Send + Sync>;
+
+type Callback = Arc<dyn Fn(&TaskRun) + Send + Sync>;
+
+/// Task of a [Scheduler] (see the [module](self) docs).
+pub struct Task {
+    name: String,
+    schedule: Schedule,
+    job: Job,
+    on_result: Option<Callback>,
+    allow_overlap: bool,
+}
+
+impl Task {
+    /// Run `job` (e.g.: a pipeline) on `schedule`
+    pub fn new<F, Fut, E>(name: &str, schedule: Schedule, job: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, E>> + Send + 'static,
+        E: Display,
+    {
+        Self {
+            name: name.to_string(),
+            schedule,
+            job: Arc::new(move || {
+                let run = job();
+                Box::pin(async move { run.await.map_err(|e| e.to_string()) })
+            }),
+            on_result: None,
+            allow_overlap: false,
+        }
+    }
+
+    /// Prompt `agent` with `prompt` on `schedule`
+    pub fn prompt<P: Prompt + 'static>(
+        name: &str,
+        schedule: Schedule,
+        agent: Arc<P>,
+        prompt: &str,
+    ) -> Self {
+        let prompt = prompt.to_string();
+        Self::new(name, schedule, move || {
+            let agent = agent.clone();
+            let prompt = prompt.clone();
+            async move { agent.prompt(prompt).await }
+        })
+    }
+
+    /// Call `callback` with each run of the task (including the skipped runs)
+    pub fn on_result(mut self, callback: impl Fn(&TaskRun) + Send + Sync + 'static) -> Self {
+        self.on_result = Some(Arc::new(callback));
+        self
+    }
+
+    /// Run the task even if its previous run is still running
+    pub fn allow_overlap(mut self) -> Self {
+        self.allow_overlap = true;
+        self
+    }
+
+    fn report(&self, run: TaskRun) {
+        match &run.outcome {
+            TaskOutcome::Failed { error } => {
+                tracing::warn!(target: "rig", "Task {} failed: {}", run.task, error)
+            }
+            TaskOutcome::Skipped => tracing::warn!(target: "rig",
+                "Task {} skipped, its previous run is still running", run.task
+            ),
+            TaskOutcome::Succeeded { .. } => {
+                tracing::info!(target: "rig", "Task {} ran in {:?}", run.task, run.duration)
+            }
+        }
+        if let Some(callback) = &self.on_result {
+            callback(&run);
+        }
+    }
+}
+
+/// Scheduler of background [Task]s (see the [module](self) docs).
+#[derive(Default)]
+pub struct Scheduler {
+    tasks: Vec<Task>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `task` to the scheduler
+    pub fn task(mut self, task: Task) -> Self {
+        self.tasks.push(task);
+        self
+    }
+
+    /// Run the tasks on their schedules, until no task is scheduled anymore
+    pub async fn run(self) {
+        let now = SystemTime::now();
+        let mut next = self
+            .tasks
+            .iter()
+            .map(|task| task.schedule.next(now, true))
+            .collect::<Vec<_>>();
+        let running = self
+            .tasks
+            .iter()
+            .map(|_| Arc::new(AtomicBool::new(false)))
+            .collect::<Vec<_>>();
+        let mut runs = FuturesUnordered::new();
+
+        loop {
+            let Some(due) = next.iter().flatten().min().copied() else {
+                // Only the runs in flight are left
+                while let Some((index, run)) = runs.next().await {
+                    self.tasks[index].report(run);
+                }
+                return;
+            };
+
+            let delay = due.duration_since(SystemTime::now()).unwrap_or_default();
+            let finished = if runs.is_empty() {
+                Delay::new(delay).await;
+                None
+            } else {
+                match future::select(Delay::new(delay), runs.next()).await {
+                    Either::Left(_) => None,
+                    Either::Right((finished, _)) => finished,
+                }
+            };
+            if let Some((index, run)) = finished {
+                self.tasks[index].report(run);
+                continue;
+            }
+
+            let now = SystemTime::now();
+            for (index, task) in self.tasks.iter().enumerate() {
+                let Some(scheduled_at) = next[index].filter(|time| *time <= now) else {
+                    continue;
+                };
+                next[index] = task.schedule.next(now, false);
+
+                if running[index].swap(true, Ordering::SeqCst) && !task.allow_overlap {
+                    task.report(TaskRun {
+                        task: task.name.clone(),
+                        scheduled_at,
+                        duration: Duration::ZERO,
+                        outcome: TaskOutcome::Skipped,
+                    });
+                    continue;
+                }
+
+                let job = (task.job)();
+                let running = running[index].clone();
+                let name = task.name.clone();
+                runs.push(async move {
+                    let start = web_time::Instant::now();
+                    let result = job.await;
+                    running.store(false, Ordering::SeqCst);
+                    let run = TaskRun {
+                        task: name,
+                        scheduled_at,
+                        duration: start.elapsed(),
+                        outcome: match result {
+                            Ok(output) => TaskOutcome::Succeeded { output },
+                            Err(error) => TaskOutcome::Failed { error },
+                        },
+                    };
+                    (index, run)
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::providers::mock::{self, MockResponse};
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[tokio::test]
+    async fn test_scheduler() {
+        // Saturday 2024-06-08 12:07 UTC
+        let now = at(1717848420);
+        let weekdays = Cron::parse("30 9 * * 1-5").unwrap();
+        assert_eq!(weekdays.next_after(now), Some(at(1718011800)));
+        let quarters = Cron::parse("*/15 * * * *").unwrap();
+        assert_eq!(quarters.next_after(now), Some(at(1717848900)));
+        // February has no 31st
+        let end_of_month = Cron::parse("0 0 31 * *").unwrap();
+        assert_eq!(
+            end_of_month.next_after(at(1740700800)),
+            Some(at(1743379200))
+        );
+        // A step of the day of the month doesn't restrict the days: Mondays on an odd day
+        let odd_mondays = Cron::parse("0 0 */2 * 1").unwrap();
+        assert_eq!(odd_mondays.next_after(now), Some(at(1718582400)));
+        assert!(Cron::parse("61 * * * *").is_err());
+        assert!(Cron::parse("* * *").is_err());
+        assert!(Schedule::every(Duration::ZERO).is_err());
+        assert_eq!(Schedule::Every(Duration::ZERO).next(now, true), None);
+
+        let runs = Arc::new(Mutex::new(vec![]));
+        let record = |runs: &Arc<Mutex<Vec<TaskRun>>>| {
+            let runs = runs.clone();
+            move |run: &TaskRun| runs.lock().unwrap().push(run.clone())
+        };
+        let agent = Arc::new(
+            mock::CompletionModel::new()
+                .default_response(MockResponse::text("Posted"))
+                .agent()
+                .build(),
+        );
+        let scheduler = Scheduler::new()
+            .task(
+                Task::prompt(
+                    "summary",
+                    Schedule::after(Duration::from_millis(10)),
+                    agent,
+                    "Post the summary",
+                )
+                .on_result(record(&runs)),
+            )
+            // Slower than its interval: the runs due while it runs are skipped
+            .task(
+                Task::new(
+                    "slow",
+                    Schedule::every(Duration::from_millis(20)).unwrap(),
+                    || async {
+                        Delay::new(Duration::from_millis(50)).await;
+                        Err::<String, _>("Timed out")
+                    },
+                )
+                .on_result(record(&runs)),
+            );
+        future::select(
+            Box::pin(scheduler.run()),
+            Delay::new(Duration::from_millis(150)),
+        )
+        .await;
+
+        let runs = runs.lock().unwrap();
+        let outcomes = |name: &str| {
+            runs.iter()
+                .filter(|run| run.task == name)
+                .map(|run| run.outcome.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            outcomes("summary"),
+            vec![TaskOutcome::Succeeded {
+                output: "Posted".to_string()
+            }]
+        );
+        let slow = outcomes("slow");
+        assert!(slow.contains(&TaskOutcome::Skipped));
+        assert!(slow.contains(&TaskOutcome::Failed {
+            error: "Timed out".to_string()
+        }));
+    }
+}
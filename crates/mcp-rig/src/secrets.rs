@@ -0,0 +1,307 @@
+//! Secure values of the MCP servers (e.g.: their API keys), scoped per server.
+//!
+//! Each MCP server gets its secure values from a named scope (e.g.: `twitter`) of a
+//! [SecretsProvider], instead of from a hard-coded list of environment variables:
+//! - [EnvSecrets]: the environment variables named after the scope and the value (e.g.:
+//!   `TWITTER_API_KEY` for the value `api_key` of the scope `twitter`).
+//! - [FileSecrets]: a JSON file mapping the scopes to their values.
+//! - [InMemorySecrets]: values set in code (e.g.: in tests).
+//! - [SecretsCallback]: the values returned by a callback, e.g.: fetched from a vault.
+//! - [SecretsChain]: the first of several providers holding the value (e.g.: a file, then the
+//!   environment).
+//!
+//! The resolved values are given to the MCP clients with [secure_values] (or
+//! [with_scoped_secrets] on a client builder). A server only ever sees the values of its own
+//! scope.
+//!
+//! # Example
+//! ```rust
+//! use mcp_core::client::Client;
+//! use mcp_rig::secrets::{self, EnvSecrets, FileSecrets, SecretsChain};
+//!
+//! // secrets.json: { "twitter": { "api_key": "...", "api_secret": "..." } }
+//! let secrets = SecretsChain::new()
+//!     .provider(FileSecrets::load("secrets.json")?)
+//!     .provider(EnvSecrets::new());
+//!
+//! let builder = Client::builder(transport);
+//! let client = secrets::with_scoped_secrets(builder, &secrets, "twitter", &["api_key", "api_secret"])
+//!     .await?
+//!     .build();
+//! ```
+use std::{collections::HashMap, future::Future, path::Path};
+
+use futures::future::BoxFuture;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SecretsError {
+    /// The secure value is in none of the providers
+    #[error("Secret {name} not found in scope {scope}")]
+    NotFound { scope: String, name: String },
+
+    /// Error returned by a provider (e.g.: the vault is unreachable)
+    #[error("ProviderError: {0}")]
+    ProviderError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// Trait for the providers of the secure values of the MCP servers.
+pub trait SecretsProvider: Send + Sync {
+    /// Secure value `name` of the scope `scope` (e.g.: the name of an MCP server), or `None` if
+    /// the provider doesn't hold it.
+    fn secret(
+        &self,
+        scope: &str,
+        name: &str,
+    ) -> impl Future<Output = Result<Option<String>, SecretsError>> + Send;
+}
+
+/// Dyn-compatible version of the [SecretsProvider] trait.
+pub(crate) trait SecretsProviderDyn: Send + Sync {
+    fn secret<'a>(
+        &'a self,
+        scope: &'a str,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<Option<String>, SecretsError>>;
+}
+
+impl<T: SecretsProvider> SecretsProviderDyn for T {
+    fn secret<'a>(
+        &'a self,
+        scope: &'a str,
+        name: &'a str,
+    ) -> BoxFuture<'a, Result<Option<String>, SecretsError>> {
+        Box::pin(SecretsProvider::secret(self, scope, name))
+    }
+}
+
+/// Secrets provider reading the environment variable `<PREFIX>_<SCOPE>_<NAME>` (in uppercase,
+/// with the characters other than letters and digits replaced by `_`). By default, without
+/// prefix: the value `api_key` of the scope `twitter` is read from `TWITTER_API_KEY`.
+#[derive(Clone, Debug, Default)]
+pub struct EnvSecrets {
+    prefix: Option<String>,
+}
+
+impl EnvSecrets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prefix the environment variables with `prefix` (e.g.: `MCP`)
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Name of the environment variable of the value `name` of the scope `scope`
+    pub fn var(&self, scope: &str, name: &str) -> String {
+        self.prefix
+            .iter()
+            .map(String::as_str)
+            .chain([scope, name])
+            .collect::<Vec<_>>()
+            .join("_")
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+}
+
+impl SecretsProvider for EnvSecrets {
+    async fn secret(&self, scope: &str, name: &str) -> Result<Option<String>, SecretsError> {
+        Ok(std::env::var(self.var(scope, name)).ok())
+    }
+}
+
+/// Secrets provider holding the values set in code, by scope.
+#[derive(Clone, Debug, Default)]
+pub struct InMemorySecrets {
+    scopes: HashMap<String, HashMap<String, String>>,
+}
+
+impl InMemorySecrets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the value `name` of the scope `scope`
+    pub fn with_secret(mut self, scope: &str, name: &str, value: &str) -> Self {
+        self.scopes
+            .entry(scope.to_string())
+            .or_default()
+            .insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+impl SecretsProvider for InMemorySecrets {
+    async fn secret(&self, scope: &str, name: &str) -> Result<Option<String>, SecretsError> {
+        Ok(self
+            .scopes
+            .get(scope)
+            .and_then(|values| values.get(name))
+            .cloned())
+    }
+}
+
+/// Secrets provider reading the values from a JSON file mapping the scopes to their values
+/// (e.g.: `{ "twitter": { "api_key": "..." } }`). The file is read once, when loaded.
+#[derive(Clone, Debug, Default)]
+pub struct FileSecrets(InMemorySecrets);
+
+impl FileSecrets {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SecretsError> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(Self(InMemorySecrets {
+            scopes: serde_json::from_str(&content)?,
+        }))
+    }
+}
+
+impl SecretsProvider for FileSecrets {
+    async fn secret(&self, scope: &str, name: &str) -> Result<Option<String>, SecretsError> {
+        SecretsProvider::secret(&self.0, scope, name).await
+    }
+}
+
+/// Secrets provider returning the values returned by a callback (e.g.: fetched from a vault),
+/// called with the scope and the name of each value.
+pub struct SecretsCallback<F> {
+    callback: F,
+}
+
+impl<F, Fut> SecretsCallback<F>
+where
+    F: Fn(String, String) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Option<String>, SecretsError>> + Send,
+{
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F, Fut> SecretsProvider for SecretsCallback<F>
+where
+    F: Fn(String, String) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Option<String>, SecretsError>> + Send,
+{
+    async fn secret(&self, scope: &str, name: &str) -> Result<Option<String>, SecretsError> {
+        (self.callback)(scope.to_string(), name.to_string()).await
+    }
+}
+
+/// Secrets provider returning the value of the first of its providers holding it.
+#[derive(Default)]
+pub struct SecretsChain {
+    providers: Vec<Box<dyn SecretsProviderDyn>>,
+}
+
+impl SecretsChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `provider` to the chain, after the previous providers
+    pub fn provider(mut self, provider: impl SecretsProvider + 'static) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+}
+
+impl SecretsProvider for SecretsChain {
+    async fn secret(&self, scope: &str, name: &str) -> Result<Option<String>, SecretsError> {
+        for provider in &self.providers {
+            if let Some(value) = provider.secret(scope, name).await? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Secure values `names` of the scope `scope`, by name. Fails if one of them is missing.
+pub async fn secure_values(
+    provider: &impl SecretsProvider,
+    scope: &str,
+    names: &[impl AsRef<str>],
+) -> Result<HashMap<String, String>, SecretsError> {
+    let mut values = HashMap::new();
+    for name in names {
+        let name = name.as_ref();
+        let value = provider
+            .secret(scope, name)
+            .await?
+            .ok_or_else(|| SecretsError::NotFound {
+                scope: scope.to_string(),
+                name: name.to_string(),
+            })?;
+        values.insert(name.to_string(), value);
+    }
+    Ok(values)
+}
+
+/// Give the secure values `names` of the scope `scope` to the MCP client built by `builder`.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn with_scoped_secrets<T: mcp_core::transport::Transport>(
+    builder: mcp_core::client::ClientBuilder<T>,
+    provider: &impl SecretsProvider,
+    scope: &str,
+    names: &[impl AsRef<str>],
+) -> Result<mcp_core::client::ClientBuilder<T>, SecretsError> {
+    let values = secure_values(provider, scope, names).await?;
+    tracing::info!(target: "rig", "Resolved {} secure values of scope {}", values.len(), scope);
+    Ok(values.into_iter().fold(builder, |builder, (name, value)| {
+        builder.with_secure_value(name, mcp_core::client::SecureValue::Static(value))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_var() {
+        assert_eq!(
+            EnvSecrets::new().var("twitter", "api_key"),
+            "TWITTER_API_KEY"
+        );
+        assert_eq!(
+            EnvSecrets::new().prefix("mcp").var("my-server", "token"),
+            "MCP_MY_SERVER_TOKEN"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_secrets_chain() {
+        let secrets = SecretsChain::new()
+            .provider(InMemorySecrets::new().with_secret("twitter", "api_key", "key"))
+            .provider(SecretsCallback::new(|scope, name| async move {
+                Ok::<_, SecretsError>((scope == "vault").then(|| format!("{name}-from-vault")))
+            }));
+
+        let values = secure_values(&secrets, "twitter", &["api_key"])
+            .await
+            .unwrap();
+        assert_eq!(values["api_key"], "key");
+        let values = secure_values(&secrets, "vault", &["token"]).await.unwrap();
+        assert_eq!(values["token"], "token-from-vault");
+
+        // The values of a scope are not visible from the other scopes
+        assert!(matches!(
+            secure_values(&secrets, "discord", &["api_key"]).await,
+            Err(SecretsError::NotFound { scope, name }) if scope == "discord" && name == "api_key"
+        ));
+    }
+}
@@ -0,0 +1,519 @@
+//! Serving agents over HTTP with [axum](https://docs.rs/axum) (requires the `serve` feature).
+//!
+//! An [AgentServer] mounts an agent behind the following routes:
+//! - `POST /chat`: prompt the agent, with a JSON body `{"message": "...", "session_id": "..."}`
+//!   (the session ID is optional). Responds with `{"session_id": "...", "response": "..."}`.
+//! - `POST /chat/stream` (streaming agents only, see [AgentServer::streaming_router]): same
+//!   request, but the response is streamed as server-sent events:
+//!   - `session`: the ID of the session,
+//!   - `message`: a chunk of the response,
+//!   - `tool_call` and `tool_result`: a tool call of the agent (JSON) and its result,
+//!   - `error`: the error that interrupted the response,
+//!   - `done`: the end of the response.
+//! - `GET /sessions/:id`: the messages of a session.
+//! - `DELETE /sessions/:id`: delete a session.
+//!
+//! Sessions hold the chat history of a conversation, in memory. When a request has no session
+//! ID, a new session is created. The history of long conversations can be truncated with
+//! [AgentServer::max_history]. Sessions expire after [AgentServer::session_ttl] without
+//! requests (1 hour by default), and at most [AgentServer::max_sessions] sessions are kept
+//! (10,000 by default), the least recently used ones being evicted first.
+//!
+//! Agents can also be served behind an OpenAI-compatible API with an
+//! [OpenAiServer](openai::OpenAiServer), see the [openai] module, and be invoked by webhooks
+//! with a [WebhookServer](webhook::WebhookServer), see the [webhook] module.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{providers::openai, serve::AgentServer};
+//!
+//! let agent = openai::Client::from_env()
+//!     .agent(openai::GPT_4O)
+//!     .preamble("You are a helpful assistant.")
+//!     .build();
+//!
+//! // Serve the agent on port 3000
+//! AgentServer::new(agent)
+//!     .max_history(20)
+//!     .serve("0.0.0.0:3000")
+//!     .await?;
+//!
+//! // Or mount it in an existing application
+//! let app = axum::Router::new().nest("/assistant", AgentServer::new(agent).router());
+//! ```
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::Future,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use futures::{channel::mpsc, future::LocalBoxFuture, FutureExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    agent::Agent,
+    completion::{Chat, CompletionModel, Message, PromptError},
+    observability::new_id,
+    streaming::{StreamingChat, StreamingChoice, StreamingCompletionModel},
+};
+
+pub mod openai;
+pub mod webhook;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ServeError {
+    #[error("Session {0} not found")]
+    SessionNotFound(String),
+
+    #[error("PromptError: {0}")]
+    PromptError(#[from] PromptError),
+}
+
+impl IntoResponse for ServeError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            ServeError::SessionNotFound(_) => StatusCode::NOT_FOUND,
+            ServeError::PromptError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+/// Body of the chat requests.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatRequest {
+    pub message: String,
+    /// ID of the session to continue, or `None` to start a new session
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+/// Body of the chat responses.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatResponse {
+    pub session_id: String,
+    pub response: String,
+}
+
+/// Default time after which a session without requests expires.
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Default maximum number of sessions.
+pub const DEFAULT_MAX_SESSIONS: usize = 10_000;
+
+struct Session {
+    history: Vec<Message>,
+    last_used: Instant,
+}
+
+/// In-memory chat histories, by session ID.
+struct Sessions {
+    sessions: Mutex<HashMap<String, Session>>,
+    max_history: Option<usize>,
+    ttl: Duration,
+    max_sessions: usize,
+}
+
+impl Sessions {
+    /// Lock the sessions, after removing the expired ones.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Session>> {
+        let mut sessions = self.sessions.lock().expect("Sessions lock poisoned");
+        sessions.retain(|_, session| session.last_used.elapsed() < self.ttl);
+        sessions
+    }
+
+    fn history(&self, session_id: &str) -> Vec<Message> {
+        self.lock()
+            .get(session_id)
+            .map(|session| session.history.clone())
+            .unwrap_or_default()
+    }
+
+    /// Append a prompt and its response to the history of a session.
+    fn append(&self, session_id: &str, prompt: Message, response: Message) {
+        let mut sessions = self.lock();
+
+        if !sessions.contains_key(session_id) && sessions.len() >= self.max_sessions {
+            let least_recently_used = sessions
+                .iter()
+                .min_by_key(|(_, session)| session.last_used)
+                .map(|(id, _)| id.clone());
+            if let Some(id) = least_recently_used {
+                sessions.remove(&id);
+            }
+        }
+
+        let session = sessions
+            .entry(session_id.to_string())
+            .or_insert_with(|| Session {
+                history: vec![],
+                last_used: Instant::now(),
+            });
+        session.history.extend([prompt, response]);
+        session.last_used = Instant::now();
+
+        if let Some(max_history) = self.max_history {
+            let excess = session.history.len().saturating_sub(max_history);
+            session.history.drain(..excess);
+        }
+    }
+}
+
+type LocalTask = Box<dyn FnOnce() -> LocalBoxFuture<'static, ()> + Send>;
+
+/// Spawn the future returned by `task`, which does not need to be `Send` (e.g.: streaming
+/// responses), on a `LocalSet` driven by a dedicated thread shared by all the servers.
+pub(crate) fn spawn_local<F, Fut>(task: F)
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + 'static,
+{
+    static SENDER: OnceLock<mpsc::UnboundedSender<LocalTask>> = OnceLock::new();
+
+    let sender = SENDER.get_or_init(|| {
+        let (sender, mut receiver) = mpsc::unbounded::<LocalTask>();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to build the streaming runtime");
+        std::thread::Builder::new()
+            .name("mcp-rig-streams".to_string())
+            .spawn(move || {
+                let local = tokio::task::LocalSet::new();
+                local.block_on(&runtime, async move {
+                    while let Some(task) = receiver.next().await {
+                        tokio::task::spawn_local(task());
+                    }
+                })
+            })
+            .expect("Failed to spawn the streaming thread");
+        sender
+    });
+
+    let _ = sender.unbounded_send(Box::new(move || task().boxed_local()));
+}
+
+struct ServerState<M: CompletionModel> {
+    agent: Agent<M>,
+    sessions: Sessions,
+}
+
+/// HTTP server of an agent. See the [module documentation](self).
+pub struct AgentServer<M: CompletionModel> {
+    agent: Agent<M>,
+    max_history: Option<usize>,
+    session_ttl: Duration,
+    max_sessions: usize,
+}
+
+impl<M: CompletionModel + 'static> AgentServer<M> {
+    pub fn new(agent: Agent<M>) -> Self {
+        Self {
+            agent,
+            max_history: None,
+            session_ttl: DEFAULT_SESSION_TTL,
+            max_sessions: DEFAULT_MAX_SESSIONS,
+        }
+    }
+
+    /// Keep at most the last `max_history` messages of each session.
+    pub fn max_history(mut self, max_history: usize) -> Self {
+        self.max_history = Some(max_history);
+        self
+    }
+
+    /// Expire the sessions after `session_ttl` without requests (default: [DEFAULT_SESSION_TTL]).
+    pub fn session_ttl(mut self, session_ttl: Duration) -> Self {
+        self.session_ttl = session_ttl;
+        self
+    }
+
+    /// Keep at most `max_sessions` sessions, evicting the least recently used ones
+    /// (default: [DEFAULT_MAX_SESSIONS]).
+    pub fn max_sessions(mut self, max_sessions: usize) -> Self {
+        self.max_sessions = max_sessions;
+        self
+    }
+
+    fn state(self) -> Arc<ServerState<M>> {
+        Arc::new(ServerState {
+            agent: self.agent,
+            sessions: Sessions {
+                sessions: Mutex::new(HashMap::new()),
+                max_history: self.max_history,
+                ttl: self.session_ttl,
+                max_sessions: self.max_sessions,
+            },
+        })
+    }
+
+    fn routes() -> Router<Arc<ServerState<M>>> {
+        Router::new().route("/chat", post(chat::<M>)).route(
+            "/sessions/:id",
+            get(session::<M>).delete(delete_session::<M>),
+        )
+    }
+
+    /// Router of the `/chat` and `/sessions/:id` routes.
+    pub fn router(self) -> Router {
+        let state = self.state();
+        Self::routes().with_state(state)
+    }
+
+    /// Serve the agent (see [AgentServer::router]) on `addr` until the server fails.
+    pub async fn serve(self, addr: impl tokio::net::ToSocketAddrs) -> std::io::Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        axum::serve(listener, self.router()).await
+    }
+}
+
+impl<M: StreamingCompletionModel + 'static> AgentServer<M> {
+    /// Router of the `/chat`, `/chat/stream` and `/sessions/:id` routes.
+    pub fn streaming_router(self) -> Router {
+        let state = self.state();
+        Self::routes()
+            .route("/chat/stream", post(chat_stream::<M>))
+            .with_state(state)
+    }
+
+    /// Serve the agent (see [AgentServer::streaming_router]) on `addr` until the server fails.
+    pub async fn serve_streaming(
+        self,
+        addr: impl tokio::net::ToSocketAddrs,
+    ) -> std::io::Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        axum::serve(listener, self.streaming_router()).await
+    }
+}
+
+async fn chat<M: CompletionModel + 'static>(
+    State(state): State<Arc<ServerState<M>>>,
+    Json(request): Json<ChatRequest>,
+) -> Result<Json<ChatResponse>, ServeError> {
+    let session_id = request.session_id.unwrap_or_else(new_id);
+    let history = state.sessions.history(&session_id);
+
+    let response = state.agent.chat(request.message.as_str(), history).await?;
+    state.sessions.append(
+        &session_id,
+        Message::user(request.message),
+        Message::assistant(response.clone()),
+    );
+
+    Ok(Json(ChatResponse {
+        session_id,
+        response,
+    }))
+}
+
+async fn session<M: CompletionModel + 'static>(
+    State(state): State<Arc<ServerState<M>>>,
+    Path(session_id): Path<String>,
+) -> Result<Json<Vec<Message>>, ServeError> {
+    state
+        .sessions
+        .lock()
+        .get(&session_id)
+        .map(|session| Json(session.history.clone()))
+        .ok_or(ServeError::SessionNotFound(session_id))
+}
+
+async fn delete_session<M: CompletionModel + 'static>(
+    State(state): State<Arc<ServerState<M>>>,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, ServeError> {
+    match state.sessions.lock().remove(&session_id) {
+        Some(_) => Ok(StatusCode::NO_CONTENT),
+        None => Err(ServeError::SessionNotFound(session_id)),
+    }
+}
+
+async fn chat_stream<M: StreamingCompletionModel + 'static>(
+    State(state): State<Arc<ServerState<M>>>,
+    Json(request): Json<ChatRequest>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let session_id = request.session_id.unwrap_or_else(new_id);
+    let (sender, receiver) = mpsc::unbounded();
+
+    spawn_local(move || async move {
+        stream_response(&state, session_id, request.message, sender).await
+    });
+
+    Sse::new(receiver.map(Ok)).keep_alive(KeepAlive::default())
+}
+
+/// Stream the response of the agent to `message` as server-sent events.
+async fn stream_response<M: StreamingCompletionModel>(
+    state: &ServerState<M>,
+    session_id: String,
+    message: String,
+    sender: mpsc::UnboundedSender<Event>,
+) {
+    // Sending fails if the client disconnected, in which case the response is still
+    // recorded in the session
+    let send = |event: Event| {
+        let _ = sender.unbounded_send(event);
+    };
+    let error = |e: &dyn std::fmt::Display| Event::default().event("error").data(e.to_string());
+
+    send(Event::default().event("session").data(&session_id));
+
+    let history = state.sessions.history(&session_id);
+    let mut stream = match state.agent.stream_chat(&message, history).await {
+        Ok(stream) => stream,
+        Err(e) => return send(error(&e)),
+    };
+
+    let mut response = String::new();
+    while let Some(chunk) = stream.next().await {
+        match chunk {
+            Ok(StreamingChoice::Message(text)) => {
+                response.push_str(&text);
+                send(Event::default().event("message").data(text));
+            }
+            Ok(StreamingChoice::ToolCall(name, id, arguments)) => {
+                let tool_call = json!({ "id": id, "name": name, "arguments": arguments });
+                send(
+                    Event::default()
+                        .event("tool_call")
+                        .data(tool_call.to_string()),
+                );
+
+                match state.agent.tools.call(&name, arguments.to_string()).await {
+                    Ok(result) => {
+                        response.push_str(&result);
+                        send(Event::default().event("tool_result").data(result));
+                    }
+                    Err(e) => return send(error(&e)),
+                }
+            }
+            Err(e) => return send(error(&e)),
+        }
+    }
+
+    state.sessions.append(
+        &session_id,
+        Message::user(message),
+        Message::assistant(response),
+    );
+    send(Event::default().event("done").data(""));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::{self, MockResponse};
+
+    #[tokio::test]
+    async fn test_agent_server() {
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::text("Hi!"))
+            .push(MockResponse::text("Fine, thanks."));
+        let server = AgentServer::new(model.agent().build()).max_history(2);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, server.router()).await });
+
+        let client = reqwest::Client::new();
+        let response: ChatResponse = client
+            .post(format!("{url}/chat"))
+            .json(&json!({ "message": "Hello" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response.response, "Hi!");
+        let session_id = response.session_id;
+
+        let response: ChatResponse = client
+            .post(format!("{url}/chat"))
+            .json(&json!({ "message": "How are you?", "session_id": session_id }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response.response, "Fine, thanks.");
+        assert_eq!(response.session_id, session_id);
+        // The history of the first prompt was sent to the model
+        assert_eq!(model.requests()[1].chat_history.len(), 2);
+
+        let history: Vec<Message> = client
+            .get(format!("{url}/sessions/{session_id}"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(
+            history,
+            vec![
+                Message::user("How are you?"),
+                Message::assistant("Fine, thanks.")
+            ]
+        );
+
+        let status = client
+            .delete(format!("{url}/sessions/{session_id}"))
+            .send()
+            .await
+            .unwrap()
+            .status();
+        assert_eq!(status, reqwest::StatusCode::NO_CONTENT);
+
+        // The model has no response left
+        let response = client
+            .post(format!("{url}/chat"))
+            .json(&json!({ "message": "Hello" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.status(),
+            reqwest::StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn test_sessions_expiry_and_eviction() {
+        let sessions = Sessions {
+            sessions: Mutex::new(HashMap::new()),
+            max_history: None,
+            ttl: Duration::from_millis(100),
+            max_sessions: 2,
+        };
+        let exchange =
+            |id: &str| sessions.append(id, Message::user("Hi"), Message::assistant("Hi!"));
+
+        exchange("a");
+        exchange("b");
+        exchange("a");
+        // "b" is the least recently used session
+        exchange("c");
+        assert_eq!(sessions.history("a").len(), 4);
+        assert!(sessions.history("b").is_empty());
+        assert_eq!(sessions.history("c").len(), 2);
+
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(sessions.history("a").is_empty());
+        assert!(sessions.lock().is_empty());
+    }
+}
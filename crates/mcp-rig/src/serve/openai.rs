@@ -0,0 +1,566 @@
+//! OpenAI-compatible facade of agents, so that OpenAI clients (SDKs, chat UIs, ...) can talk
+//! to agents as if they were models.
+//!
+//! An [OpenAiServer] mounts one or more agents, each under a model name, behind the following
+//! routes:
+//! - `POST /v1/chat/completions`: prompt the agent of the requested `model` with the last
+//!   message of the request, the previous user and assistant messages being its chat history.
+//!   With `"stream": true`, the response is streamed as `chat.completion.chunk` server-sent
+//!   events, terminated by `data: [DONE]`.
+//! - `GET /v1/models`: the model names of the agents.
+//!
+//! The system messages of the requests are ignored: the preamble of the agent is used instead.
+//! The tools of the agents are called by the agents themselves, so their results are part of
+//! the content of the responses. Sampling parameters (e.g.: `temperature`) are ignored.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{providers::openai, serve::openai::OpenAiServer};
+//!
+//! let client = openai::Client::from_env();
+//! let support = client
+//!     .agent(openai::GPT_4O)
+//!     .preamble("You are a support agent.")
+//!     .build();
+//! let writer = client
+//!     .agent(openai::GPT_4O)
+//!     .preamble("You are a technical writer.")
+//!     .build();
+//!
+//! // Clients use `support-agent` or `writer-agent` as the model, e.g.:
+//! // OpenAI(base_url="http://localhost:3000/v1", api_key="secret")
+//! OpenAiServer::new()
+//!     .streaming_agent("support-agent", support)
+//!     .streaming_agent("writer-agent", writer)
+//!     .api_key("secret")
+//!     .serve("0.0.0.0:3000")
+//!     .await?;
+//! ```
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use futures::{channel::mpsc, future::BoxFuture, stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    agent::Agent,
+    completion::{Chat, CompletionModel, Message, PromptError},
+    observability::new_id,
+    streaming::{StreamingChat, StreamingChoice, StreamingCompletionModel},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum OpenAiServeError {
+    #[error("Invalid API key")]
+    Unauthorized,
+
+    #[error("The model `{0}` does not exist")]
+    ModelNotFound(String),
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("PromptError: {0}")]
+    PromptError(#[from] PromptError),
+}
+
+impl OpenAiServeError {
+    fn kind(&self) -> (StatusCode, &'static str, &'static str) {
+        match self {
+            OpenAiServeError::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                "invalid_request_error",
+                "invalid_api_key",
+            ),
+            OpenAiServeError::ModelNotFound(_) => (
+                StatusCode::NOT_FOUND,
+                "invalid_request_error",
+                "model_not_found",
+            ),
+            OpenAiServeError::InvalidRequest(_) => (
+                StatusCode::BAD_REQUEST,
+                "invalid_request_error",
+                "invalid_request",
+            ),
+            OpenAiServeError::PromptError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "server_error",
+                "server_error",
+            ),
+        }
+    }
+
+    /// Error object, as returned by the OpenAI API.
+    fn body(&self) -> Value {
+        let (_, kind, code) = self.kind();
+        json!({ "error": { "message": self.to_string(), "type": kind, "code": code } })
+    }
+}
+
+impl IntoResponse for OpenAiServeError {
+    fn into_response(self) -> Response {
+        (self.kind().0, Json(self.body())).into_response()
+    }
+}
+
+/// Body of the chat completion requests. Only the fields used by the server are deserialized.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatCompletionMessage>,
+    #[serde(default)]
+    pub stream: bool,
+}
+
+/// Message of a chat completion request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChatCompletionMessage {
+    /// `system`, `user`, `assistant` or `tool`
+    pub role: String,
+    /// Text content, or array of content parts (only the text parts are used)
+    #[serde(default)]
+    pub content: Value,
+}
+
+impl ChatCompletionMessage {
+    fn text(&self) -> String {
+        match &self.content {
+            Value::String(text) => text.clone(),
+            Value::Array(parts) => parts
+                .iter()
+                .filter_map(|part| part.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => String::new(),
+        }
+    }
+}
+
+impl ChatCompletionRequest {
+    /// Split the messages of the request into the prompt (the last message, which must be a
+    /// user message) and the chat history.
+    fn prompt(&self) -> Result<(String, Vec<Message>), OpenAiServeError> {
+        let Some((prompt, history)) = self.messages.split_last() else {
+            return Err(OpenAiServeError::InvalidRequest(
+                "`messages` must not be empty".into(),
+            ));
+        };
+        if prompt.role != "user" {
+            return Err(OpenAiServeError::InvalidRequest(
+                "The last message must be a user message".into(),
+            ));
+        }
+
+        let history = history
+            .iter()
+            .filter_map(|message| match message.role.as_str() {
+                "user" => Some(Message::user(message.text())),
+                "assistant" => Some(Message::assistant(message.text())),
+                _ => None,
+            })
+            .collect();
+
+        Ok((prompt.text(), history))
+    }
+}
+
+/// Agent served under a model name, with its type erased.
+pub(crate) trait ServedAgent: Send + Sync {
+    fn chat(
+        &self,
+        prompt: String,
+        history: Vec<Message>,
+    ) -> BoxFuture<'_, Result<String, PromptError>>;
+
+    /// Stream the response of the agent as chunks of text (or an error interrupting it).
+    fn stream(
+        self: Arc<Self>,
+        prompt: String,
+        history: Vec<Message>,
+    ) -> mpsc::UnboundedReceiver<Result<String, String>>;
+}
+
+impl<M: CompletionModel + 'static> ServedAgent for Agent<M> {
+    fn chat(
+        &self,
+        prompt: String,
+        history: Vec<Message>,
+    ) -> BoxFuture<'_, Result<String, PromptError>> {
+        Box::pin(Chat::chat(self, prompt, history))
+    }
+
+    /// The response of non-streaming agents is sent as a single chunk.
+    fn stream(
+        self: Arc<Self>,
+        prompt: String,
+        history: Vec<Message>,
+    ) -> mpsc::UnboundedReceiver<Result<String, String>> {
+        let (sender, receiver) = mpsc::unbounded();
+        tokio::spawn(async move {
+            let chunk = Chat::chat(&*self, prompt, history)
+                .await
+                .map_err(|e| e.to_string());
+            let _ = sender.unbounded_send(chunk);
+        });
+        receiver
+    }
+}
+
+/// Agent of a streaming model, whose responses are streamed as they are generated.
+struct StreamingAgent<M: StreamingCompletionModel>(Agent<M>);
+
+impl<M: StreamingCompletionModel + 'static> ServedAgent for StreamingAgent<M> {
+    fn chat(
+        &self,
+        prompt: String,
+        history: Vec<Message>,
+    ) -> BoxFuture<'_, Result<String, PromptError>> {
+        Box::pin(Chat::chat(&self.0, prompt, history))
+    }
+
+    fn stream(
+        self: Arc<Self>,
+        prompt: String,
+        history: Vec<Message>,
+    ) -> mpsc::UnboundedReceiver<Result<String, String>> {
+        let (sender, receiver) = mpsc::unbounded();
+
+        super::spawn_local(move || async move {
+            stream_response(&self.0, prompt, history, sender).await
+        });
+
+        receiver
+    }
+}
+
+/// Stream the response of `agent` to `prompt`, calling the tools it requests.
+async fn stream_response<M: StreamingCompletionModel>(
+    agent: &Agent<M>,
+    prompt: String,
+    history: Vec<Message>,
+    sender: mpsc::UnboundedSender<Result<String, String>>,
+) {
+    // Sending fails if the client disconnected
+    let send = |chunk: Result<String, String>| {
+        let _ = sender.unbounded_send(chunk);
+    };
+
+    let mut stream = match agent.stream_chat(&prompt, history).await {
+        Ok(stream) => stream,
+        Err(e) => return send(Err(e.to_string())),
+    };
+
+    while let Some(chunk) = stream.next().await {
+        match chunk {
+            Ok(StreamingChoice::Message(text)) => send(Ok(text)),
+            Ok(StreamingChoice::ToolCall(name, _, arguments)) => {
+                match agent.tools.call(&name, arguments.to_string()).await {
+                    Ok(result) => send(Ok(result)),
+                    Err(e) => return send(Err(e.to_string())),
+                }
+            }
+            Err(e) => return send(Err(e.to_string())),
+        }
+    }
+}
+
+struct ServerState {
+    agents: BTreeMap<String, Arc<dyn ServedAgent>>,
+    api_key: Option<String>,
+    created: u64,
+}
+
+impl ServerState {
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), OpenAiServeError> {
+        let Some(api_key) = &self.api_key else {
+            return Ok(());
+        };
+
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match bearer {
+            Some(bearer) if bool::from(bearer.as_bytes().ct_eq(api_key.as_bytes())) => Ok(()),
+            _ => Err(OpenAiServeError::Unauthorized),
+        }
+    }
+}
+
+/// OpenAI-compatible HTTP server of agents. See the [module documentation](self).
+#[derive(Default)]
+pub struct OpenAiServer {
+    agents: BTreeMap<String, Arc<dyn ServedAgent>>,
+    api_key: Option<String>,
+}
+
+impl OpenAiServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `agent` under the model name `model`. Streamed responses of the agent are sent
+    /// as a single chunk, once complete.
+    pub fn agent<M: CompletionModel + 'static>(mut self, model: &str, agent: Agent<M>) -> Self {
+        self.agents.insert(model.to_string(), Arc::new(agent));
+        self
+    }
+
+    /// Serve `agent` under the model name `model`, streaming its responses as they are
+    /// generated.
+    pub fn streaming_agent<M: StreamingCompletionModel + 'static>(
+        mut self,
+        model: &str,
+        agent: Agent<M>,
+    ) -> Self {
+        self.agents
+            .insert(model.to_string(), Arc::new(StreamingAgent(agent)));
+        self
+    }
+
+    /// Require the requests to be authorized with the bearer token `api_key`.
+    pub fn api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    /// Router of the `/v1/chat/completions` and `/v1/models` routes.
+    pub fn router(self) -> Router {
+        let state = Arc::new(ServerState {
+            agents: self.agents,
+            api_key: self.api_key,
+            created: unix_time(),
+        });
+
+        Router::new()
+            .route("/v1/chat/completions", post(chat_completions))
+            .route("/v1/models", get(models))
+            .with_state(state)
+    }
+
+    /// Serve the agents (see [OpenAiServer::router]) on `addr` until the server fails.
+    pub async fn serve(self, addr: impl tokio::net::ToSocketAddrs) -> std::io::Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        axum::serve(listener, self.router()).await
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+async fn models(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, OpenAiServeError> {
+    state.authorize(&headers)?;
+
+    let models = state
+        .agents
+        .keys()
+        .map(|model| {
+            json!({
+                "id": model,
+                "object": "model",
+                "created": state.created,
+                "owned_by": "rig",
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({ "object": "list", "data": models })))
+}
+
+async fn chat_completions(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    Json(request): Json<ChatCompletionRequest>,
+) -> Result<Response, OpenAiServeError> {
+    state.authorize(&headers)?;
+
+    let agent = state
+        .agents
+        .get(&request.model)
+        .cloned()
+        .ok_or_else(|| OpenAiServeError::ModelNotFound(request.model.clone()))?;
+    let (prompt, history) = request.prompt()?;
+
+    let id = format!("chatcmpl-{}", new_id());
+    let created = unix_time();
+
+    if !request.stream {
+        let response = agent.chat(prompt, history).await?;
+        return Ok(Json(json!({
+            "id": id,
+            "object": "chat.completion",
+            "created": created,
+            "model": request.model,
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": response },
+                "finish_reason": "stop",
+            }],
+            // The usage of the requests of the agent is not tracked per response
+            "usage": { "prompt_tokens": 0, "completion_tokens": 0, "total_tokens": 0 },
+        }))
+        .into_response());
+    }
+
+    let model = request.model;
+    let chunk = move |delta: Value, finish_reason: Option<&str>| {
+        let chunk = json!({
+            "id": id,
+            "object": "chat.completion.chunk",
+            "created": created,
+            "model": model,
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+        });
+        Event::default().data(chunk.to_string())
+    };
+
+    let role = chunk(json!({ "role": "assistant" }), None);
+    let chunks = agent.stream(prompt, history).map({
+        let chunk = chunk.clone();
+        move |content| {
+            match content {
+            Ok(content) => chunk(json!({ "content": content }), None),
+            Err(e) => Event::default().data(
+                json!({ "error": { "message": e, "type": "server_error", "code": "server_error" } })
+                    .to_string(),
+            ),
+        }
+        }
+    });
+    let done = [
+        chunk(json!({}), Some("stop")),
+        Event::default().data("[DONE]"),
+    ];
+
+    let events = stream::iter([role])
+        .chain(chunks)
+        .chain(stream::iter(done))
+        .map(Ok::<_, Infallible>);
+
+    Ok(Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::{self, MockResponse};
+
+    #[tokio::test]
+    async fn test_openai_server() {
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::text("Hi!"))
+            .push(MockResponse::text("Streamed."));
+        let server = OpenAiServer::new()
+            .agent("greeter", model.agent().build())
+            .api_key("secret");
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, server.router()).await });
+
+        let client = reqwest::Client::new();
+        let request = json!({
+            "model": "greeter",
+            "messages": [
+                { "role": "system", "content": "Ignored" },
+                { "role": "user", "content": "Hello" },
+                { "role": "assistant", "content": "Hello! How can I help?" },
+                { "role": "user", "content": [{ "type": "text", "text": "Say hi" }] },
+            ],
+        });
+
+        let status = client
+            .post(format!("{url}/chat/completions"))
+            .json(&request)
+            .send()
+            .await
+            .unwrap()
+            .status();
+        assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+
+        let response: Value = client
+            .post(format!("{url}/chat/completions"))
+            .bearer_auth("secret")
+            .json(&request)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response["object"], "chat.completion");
+        assert_eq!(response["model"], "greeter");
+        assert_eq!(response["choices"][0]["message"]["content"], "Hi!");
+        assert_eq!(model.requests()[0].chat_history.len(), 2);
+
+        let mut request = request;
+        request["stream"] = json!(true);
+        let body = client
+            .post(format!("{url}/chat/completions"))
+            .bearer_auth("secret")
+            .json(&request)
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let chunks = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .collect::<Vec<_>>();
+        assert_eq!(chunks.len(), 4);
+        let content: Value = serde_json::from_str(chunks[1]).unwrap();
+        assert_eq!(content["object"], "chat.completion.chunk");
+        assert_eq!(content["choices"][0]["delta"]["content"], "Streamed.");
+        let last: Value = serde_json::from_str(chunks[2]).unwrap();
+        assert_eq!(last["choices"][0]["finish_reason"], "stop");
+        assert_eq!(chunks[3], "[DONE]");
+
+        request["model"] = json!("unknown");
+        let response = client
+            .post(format!("{url}/chat/completions"))
+            .bearer_auth("secret")
+            .json(&request)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+        let models: Value = client
+            .get(format!("{url}/models"))
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(models["data"][0]["id"], "greeter");
+    }
+}
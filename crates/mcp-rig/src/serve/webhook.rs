@@ -0,0 +1,362 @@
+//! Webhook-triggered agent invocations, so that the events of external systems (e.g.: a new
+//! GitHub issue, a Stripe payment) drive agents without a bespoke glue service.
+//!
+//! A [WebhookServer] mounts named [Webhook]s behind `POST /webhooks/:name`. The JSON payload of
+//! each request is passed to the webhook:
+//! - [Webhook::prompt]: prompts an agent with a template rendered with the payload (see
+//!   [render]), e.g.: `New issue "{{issue.title}}" opened by {{sender.login}}`,
+//! - [Webhook::new]: runs any job with the payload (e.g.: a pipeline).
+//!
+//! By default, the webhook responds with `{"response": "..."}` once the job is done. With
+//! [Webhook::background], it responds `202 Accepted` right away and runs the job in the
+//! background, for the senders with short timeouts.
+//!
+//! The requests of a webhook with a secret (see [Webhook::secret]) must be signed with it: their
+//! `X-Hub-Signature-256` header is the HMAC-SHA256 of their body (i.e.: `sha256=<hex>`, as sent
+//! by GitHub).
+//!
+//! # Example
+//! ```rust
+//! use std::sync::Arc;
+//! use mcp_rig::{providers::openai, serve::webhook::{Webhook, WebhookServer}};
+//!
+//! let triage = Arc::new(
+//!     openai.agent(openai::GPT_4O)
+//!         .preamble("You triage the GitHub issues, and post a summary to Discord.")
+//!         .mcp_tool(send_message, discord_client)
+//!         .build(),
+//! );
+//!
+//! // GitHub posts to http://host:3000/webhooks/github
+//! WebhookServer::new()
+//!     .webhook(
+//!         "github",
+//!         Webhook::prompt(triage, "New issue \"{{issue.title}}\" by {{sender.login}}:\n{{issue.body}}")
+//!             .secret(&std::env::var("GITHUB_WEBHOOK_SECRET")?)
+//!             .background(),
+//!     )
+//!     .serve("0.0.0.0:3000")
+//!     .await?;
+//! ```
+use std::{collections::HashMap, fmt::Display, future::Future, sync::Arc};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use futures::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::completion::Prompt;
+
+/// Header of the signature of the webhook requests.
+const SIGNATURE_HEADER: &str = "x-hub-signature-256";
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("Webhook {0} not found")]
+    NotFound(String),
+
+    #[error("Invalid signature")]
+    InvalidSignature,
+
+    #[error("Invalid payload: {0}")]
+    InvalidPayload(String),
+
+    #[error("Field {0} missing from the payload")]
+    MissingField(String),
+
+    #[error("Webhook failed: {0}")]
+    Failed(String),
+}
+
+impl IntoResponse for WebhookError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            WebhookError::NotFound(_) => StatusCode::NOT_FOUND,
+            WebhookError::InvalidSignature => StatusCode::UNAUTHORIZED,
+            WebhookError::InvalidPayload(_) | WebhookError::MissingField(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            WebhookError::Failed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+/// Render `template` with `payload`: each `{{path}}` is replaced by the value at the dotted
+/// `path` of the payload (e.g.: `{{issue.labels.0.name}}`), the strings being inserted as is
+/// and the other values as JSON. `{{.}}` is replaced by the whole payload.
+pub fn render(template: &str, payload: &Value) -> Result<String, WebhookError> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        let path = rest[start + 2..start + end].trim();
+
+        let value = if path == "." {
+            Some(payload)
+        } else {
+            path.split('.').try_fold(payload, |value, key| match value {
+                Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+                value => value.get(key),
+            })
+        };
+        match value {
+            Some(Value::String(text)) => rendered.push_str(text),
+            Some(value) => rendered.push_str(&value.to_string()),
+            None => return Err(WebhookError::MissingField(path.to_string())),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+type Handler = Arc<dyn Fn(Value) -> BoxFuture<'static, Result<String, String>> + Send + Sync>;
+
+/// Webhook of a [WebhookServer] (see the [module](self) docs).
+#[derive(Clone)]
+pub struct Webhook {
+    handler: Handler,
+    /// Template of the prompts, checked before the handler runs (if prompting an agent)
+    template: Option<String>,
+    secret: Option<String>,
+    background: bool,
+}
+
+impl Webhook {
+    /// Run `handler` (e.g.: a pipeline) with the payload of each request
+    pub fn new<F, Fut, E>(handler: F) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, E>> + Send + 'static,
+        E: Display,
+    {
+        Self {
+            handler: Arc::new(move |payload| {
+                let run = handler(payload);
+                Box::pin(async move { run.await.map_err(|e| e.to_string()) })
+            }),
+            template: None,
+            secret: None,
+            background: false,
+        }
+    }
+
+    /// Prompt `agent` with `template` rendered with the payload of each request (see [render])
+    pub fn prompt<P: Prompt + 'static>(agent: Arc<P>, template: &str) -> Self {
+        let prompt_template = template.to_string();
+        let mut webhook = Self::new(move |payload| {
+            let agent = agent.clone();
+            let prompt = render(&prompt_template, &payload);
+            async move {
+                let prompt = prompt.map_err(|e| e.to_string())?;
+                agent.prompt(prompt).await.map_err(|e| e.to_string())
+            }
+        });
+        webhook.template = Some(template.to_string());
+        webhook
+    }
+
+    /// Only accept the requests signed with `secret` (see the [module](self) docs)
+    pub fn secret(mut self, secret: &str) -> Self {
+        self.secret = Some(secret.to_string());
+        self
+    }
+
+    /// Respond `202 Accepted` right away, and run the webhook in the background
+    pub fn background(mut self) -> Self {
+        self.background = true;
+        self
+    }
+
+    /// Check the signature of the request of body `body`
+    fn verify(&self, headers: &HeaderMap, body: &[u8]) -> Result<(), WebhookError> {
+        let Some(secret) = &self.secret else {
+            return Ok(());
+        };
+        let signature = headers
+            .get(SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("sha256="))
+            .and_then(decode_hex)
+            .ok_or(WebhookError::InvalidSignature)?;
+
+        // `verify_slice` compares in constant time, not to leak the signature through timing
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC should accept keys of any size")
+            .chain_update(body)
+            .verify_slice(&signature)
+            .map_err(|_| WebhookError::InvalidSignature)
+    }
+}
+
+/// Bytes of the hex string `hex`, or None if it is not valid hex
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).ok())
+        .collect()
+}
+
+/// HTTP listener of webhooks. See the [module documentation](self).
+#[derive(Default)]
+pub struct WebhookServer {
+    webhooks: HashMap<String, Webhook>,
+}
+
+impl WebhookServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mount `webhook` under `POST /webhooks/{name}`
+    pub fn webhook(mut self, name: &str, webhook: Webhook) -> Self {
+        self.webhooks.insert(name.to_string(), webhook);
+        self
+    }
+
+    /// Router of the `/webhooks/:name` route.
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/webhooks/:name", post(receive))
+            .with_state(Arc::new(self.webhooks))
+    }
+
+    /// Serve the webhooks (see [WebhookServer::router]) on `addr` until the server fails.
+    pub async fn serve(self, addr: impl tokio::net::ToSocketAddrs) -> std::io::Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        axum::serve(listener, self.router()).await
+    }
+}
+
+async fn receive(
+    State(webhooks): State<Arc<HashMap<String, Webhook>>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, WebhookError> {
+    let webhook = webhooks
+        .get(&name)
+        .ok_or_else(|| WebhookError::NotFound(name.clone()))?;
+    webhook.verify(&headers, &body)?;
+    let payload = serde_json::from_slice::<Value>(&body)
+        .map_err(|e| WebhookError::InvalidPayload(e.to_string()))?;
+    // Fail the request rather than the background job on a payload missing a field
+    if let Some(template) = &webhook.template {
+        render(template, &payload)?;
+    }
+
+    let run = (webhook.handler)(payload);
+    if webhook.background {
+        tokio::spawn(async move {
+            if let Err(error) = run.await {
+                tracing::warn!(target: "rig", "Webhook {} failed: {}", name, error);
+            }
+        });
+        return Ok((StatusCode::ACCEPTED, Json(json!({ "accepted": true }))).into_response());
+    }
+
+    let response = run.await.map_err(WebhookError::Failed)?;
+    Ok(Json(json!({ "response": response })).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::{self, MockResponse};
+
+    #[tokio::test]
+    async fn test_webhook_server() {
+        assert_eq!(decode_hex("00ff1A"), Some(vec![0x00, 0xff, 0x1a]));
+        assert_eq!(decode_hex("+f"), None);
+        assert_eq!(decode_hex("abc"), None);
+
+        let payload = json!({
+            "issue": {"title": "Crash on start", "labels": [{"name": "bug"}]},
+            "sender": {"login": "octocat"}
+        });
+        assert_eq!(
+            render(
+                "{{issue.title}} ({{ issue.labels.0.name }}) by {{sender.login}}",
+                &payload
+            )
+            .unwrap(),
+            "Crash on start (bug) by octocat"
+        );
+        assert!(matches!(
+            render("{{issue.body}}", &payload),
+            Err(WebhookError::MissingField(path)) if path == "issue.body"
+        ));
+
+        let model = mock::CompletionModel::new().default_response(MockResponse::text("Triaged"));
+        let server = WebhookServer::new().webhook(
+            "github",
+            Webhook::prompt(
+                Arc::new(model.agent().build()),
+                "New issue \"{{issue.title}}\" by {{sender.login}}",
+            )
+            .secret("s3cret"),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/webhooks", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, server.router()).await });
+
+        let body = payload.to_string();
+        let signature = Hmac::<Sha256>::new_from_slice(b"s3cret")
+            .unwrap()
+            .chain_update(body.as_bytes())
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        let client = reqwest::Client::new();
+        let response: Value = client
+            .post(format!("{url}/github"))
+            .header(SIGNATURE_HEADER, format!("sha256={signature}"))
+            .body(body.clone())
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response, json!({"response": "Triaged"}));
+        assert_eq!(
+            model.requests()[0].prompt_text().unwrap(),
+            "New issue \"Crash on start\" by octocat"
+        );
+
+        let status = |request: reqwest::RequestBuilder| async move {
+            request.send().await.unwrap().status()
+        };
+        assert_eq!(
+            status(
+                client
+                    .post(format!("{url}/github"))
+                    .header(SIGNATURE_HEADER, "sha256=00")
+                    .body(body.clone())
+            )
+            .await,
+            reqwest::StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(client.post(format!("{url}/gitlab")).body(body)).await,
+            reqwest::StatusCode::NOT_FOUND
+        );
+    }
+}
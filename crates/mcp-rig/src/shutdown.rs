@@ -0,0 +1,310 @@
+//! Graceful shutdown of the infrastructure of the agents (e.g.: on SIGTERM), so that services
+//! embedding the library don't lose the prompts in flight, nor the data of their sinks.
+//!
+//! A [Shutdown] is shared by the components to stop:
+//! - The agents built with [AgentBuilder::shutdown](crate::agent::AgentBuilder::shutdown) count
+//!   their prompts as in flight, and reject the new prompts once the shutdown has started. Any
+//!   other work can be counted with [Shutdown::track].
+//! - The hooks registered with [Shutdown::on_shutdown] run once the prompts in flight are
+//!   drained (or the drain timed out), in the reverse order of their registration: e.g.:
+//!   closing the transports of the MCP servers (see [Shutdown::mcp_transport]), which cancels
+//!   their subscriptions and pending requests, or persisting the
+//!   [UsageLedger](crate::usage::UsageLedger).
+//! - The installed [PayloadLogger](crate::payload_log::PayloadLogger) is flushed last.
+//!
+//! The traces of the agents are exported when their prompt completes, and are thus drained
+//! along with the prompts.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use mcp_rig::{providers::openai, shutdown::Shutdown};
+//!
+//! let shutdown = Shutdown::new();
+//! shutdown.mcp_transport("twitter", transport.clone());
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .mcp_tool(tool, client)
+//!     .shutdown(shutdown.clone())
+//!     .build();
+//!
+//! tokio::signal::ctrl_c().await?;
+//! let report = shutdown.shutdown(Duration::from_secs(30)).await;
+//! if !report.is_clean() {
+//!     eprintln!("Unclean shutdown: {report:?}");
+//! }
+//! ```
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use futures::{
+    channel::oneshot,
+    future::{self, BoxFuture, Either},
+};
+use futures_timer::Delay;
+use serde::{Deserialize, Serialize};
+
+/// Error returned by a shutdown hook.
+pub type HookError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+type Hook = Box<dyn FnOnce() -> BoxFuture<'static, Result<(), HookError>> + Send>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShutdownError {
+    /// The shutdown has started, no new work is accepted
+    #[error("Shutting down")]
+    ShuttingDown,
+}
+
+/// Outcome of a shutdown hook.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HookOutcome {
+    pub name: String,
+    /// Error of the hook, if it failed
+    pub error: Option<String>,
+}
+
+/// Outcome of a [Shutdown].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ShutdownReport {
+    /// Whether the work in flight was drained before the timeout
+    pub drained: bool,
+    /// Number of the units of work still in flight after the timeout
+    pub abandoned: usize,
+    pub hooks: Vec<HookOutcome>,
+}
+
+impl ShutdownReport {
+    /// Whether the work in flight was drained and all the hooks succeeded
+    pub fn is_clean(&self) -> bool {
+        self.drained && self.hooks.iter().all(|hook| hook.error.is_none())
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    shutting_down: AtomicBool,
+    in_flight: AtomicUsize,
+    /// Senders notified when no work is in flight anymore
+    drained: Mutex<Vec<oneshot::Sender<()>>>,
+    hooks: Mutex<Vec<(String, Hook)>>,
+}
+
+/// Coordinator of the graceful shutdown (see the [module](self) docs). Cloning it shares it.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    inner: Arc<Inner>,
+}
+
+/// Unit of work in flight of a [Shutdown], until dropped.
+pub struct InFlight {
+    inner: Arc<Inner>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.inner.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            let mut drained = self.inner.drained.lock().expect("Shutdown lock poisoned");
+            drained.drain(..).for_each(|sender| {
+                let _ = sender.send(());
+            });
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the shutdown has started
+    pub fn is_shutting_down(&self) -> bool {
+        self.inner.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// Number of the units of work in flight
+    pub fn in_flight(&self) -> usize {
+        self.inner.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Count a unit of work as in flight until the returned guard is dropped. Fails once the
+    /// shutdown has started.
+    pub fn enter(&self) -> Result<InFlight, ShutdownError> {
+        self.inner.in_flight.fetch_add(1, Ordering::SeqCst);
+        let in_flight = InFlight {
+            inner: self.inner.clone(),
+        };
+        if self.is_shutting_down() {
+            return Err(ShutdownError::ShuttingDown);
+        }
+        Ok(in_flight)
+    }
+
+    /// Run `future` as a unit of work in flight. Fails without running it once the shutdown has
+    /// started.
+    pub async fn track<F: Future>(&self, future: F) -> Result<F::Output, ShutdownError> {
+        let _in_flight = self.enter()?;
+        Ok(future.await)
+    }
+
+    /// Run `hook` (named `name`, e.g.: in the report) on shutdown, after the work in flight is
+    /// drained
+    pub fn on_shutdown<F, Fut>(&self, name: &str, hook: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), HookError>> + Send + 'static,
+    {
+        let hook: Hook = Box::new(move || Box::pin(hook()));
+        self.inner
+            .hooks
+            .lock()
+            .expect("Shutdown lock poisoned")
+            .push((name.to_string(), hook));
+    }
+
+    /// Close the transport of the MCP server `name` on shutdown, which ends its client and
+    /// cancels its pending requests and subscriptions
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn mcp_transport<T: mcp_core::transport::Transport + 'static>(
+        &self,
+        name: &str,
+        transport: Arc<T>,
+    ) {
+        self.on_shutdown(name, move || async move {
+            transport.close().await.map_err(HookError::from)
+        });
+    }
+
+    /// Stop accepting new work, wait up to `timeout` for the work in flight to drain, then run
+    /// the hooks and flush the installed payload logger. The hooks only run on the first call.
+    pub async fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        self.inner.shutting_down.store(true, Ordering::SeqCst);
+        tracing::info!(target: "rig", "Shutting down ({} in flight)", self.in_flight());
+
+        let drained = {
+            let mut drained = self.inner.drained.lock().expect("Shutdown lock poisoned");
+            if self.in_flight() == 0 {
+                None
+            } else {
+                let (sender, receiver) = oneshot::channel();
+                drained.push(sender);
+                Some(receiver)
+            }
+        };
+        let drained = match drained {
+            None => true,
+            Some(receiver) => match future::select(receiver, Delay::new(timeout)).await {
+                Either::Left(_) => true,
+                Either::Right(_) => false,
+            },
+        };
+        let abandoned = self.in_flight();
+        if !drained {
+            tracing::warn!(target: "rig", "Shutdown timed out, abandoning {} in flight", abandoned);
+        }
+
+        let hooks = std::mem::take(&mut *self.inner.hooks.lock().expect("Shutdown lock poisoned"));
+        let mut outcomes = vec![];
+        for (name, hook) in hooks.into_iter().rev() {
+            let error = hook().await.err().map(|e| e.to_string());
+            if let Some(error) = &error {
+                tracing::warn!(target: "rig", "Shutdown hook {} failed: {}", name, error);
+            }
+            outcomes.push(HookOutcome { name, error });
+        }
+
+        if let Some(logger) = crate::payload_log::logger() {
+            logger.flush();
+        }
+
+        ShutdownReport {
+            drained,
+            abandoned,
+            hooks: outcomes,
+        }
+    }
+}
+
+impl std::fmt::Debug for Shutdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Shutdown")
+            .field("shutting_down", &self.is_shutting_down())
+            .field("in_flight", &self.in_flight())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        completion::{CompletionError, Prompt, PromptError},
+        providers::mock::{self, MockResponse},
+        streaming::StreamingPrompt,
+    };
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let shutdown = Shutdown::new();
+        let closed = Arc::new(Mutex::new(vec![]));
+        for name in ["first", "second"] {
+            let closed = closed.clone();
+            shutdown.on_shutdown(name, move || async move {
+                closed.lock().unwrap().push(name);
+                Ok::<_, HookError>(())
+            });
+        }
+        shutdown.on_shutdown("failing", || async {
+            Err::<(), HookError>("Already closed".into())
+        });
+
+        let model = mock::CompletionModel::new()
+            .latency(Duration::from_millis(50))
+            .default_response(MockResponse::text("Done"));
+        let agent = model.agent().shutdown(shutdown.clone()).build();
+
+        // The prompt in flight completes, the prompts after the start of the shutdown fail
+        let (answer, report) = future::join(agent.prompt("Hello"), async {
+            Delay::new(Duration::from_millis(10)).await;
+            assert_eq!(shutdown.in_flight(), 1);
+            shutdown.shutdown(Duration::from_secs(1)).await
+        })
+        .await;
+        assert_eq!(answer.unwrap(), "Done");
+        assert!(report.drained);
+        assert_eq!(*closed.lock().unwrap(), vec!["second", "first"]);
+        assert_eq!(report.hooks[0].error.as_deref(), Some("Already closed"));
+        assert!(!report.is_clean());
+        assert!(matches!(
+            agent.prompt("Hello").await,
+            Err(PromptError::CompletionError(CompletionError::RequestError(
+                _
+            )))
+        ));
+
+        // The streamed prompts are in flight until their stream is dropped
+        let shutdown = Shutdown::new();
+        let agent = model.agent().shutdown(shutdown.clone()).build();
+        let stream = agent.stream_prompt("Hello").await.unwrap();
+        assert_eq!(shutdown.in_flight(), 1);
+        let chunks = stream.collect::<Vec<_>>().await;
+        assert!(chunks.iter().all(Result::is_ok));
+        assert_eq!(shutdown.in_flight(), 0);
+        shutdown.shutdown(Duration::from_secs(1)).await;
+        assert!(agent.stream_prompt("Hello").await.is_err());
+
+        // The work still in flight after the timeout is abandoned
+        let shutdown = Shutdown::new();
+        let _in_flight = shutdown.enter().unwrap();
+        let report = shutdown.shutdown(Duration::from_millis(10)).await;
+        assert!(!report.drained);
+        assert_eq!(report.abandoned, 1);
+    }
+}
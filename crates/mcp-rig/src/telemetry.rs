@@ -0,0 +1,167 @@
+//! OpenTelemetry instrumentation of completions, embeddings and tool calls.
+//!
+//! When the `otel` feature is enabled, every completion and embedding request made by a provider
+//! and every tool call made by a [ToolSet](crate::tool::ToolSet) (including MCP tools) is wrapped
+//! in a `tracing` span following the OpenTelemetry
+//! [GenAI semantic conventions](https://opentelemetry.io/docs/specs/semconv/gen-ai/gen-ai-spans/):
+//! - `chat {model}` spans, with the `gen_ai.system`, `gen_ai.request.model`,
+//!   `gen_ai.usage.input_tokens` and `gen_ai.usage.output_tokens` attributes;
+//! - `embeddings {model}` spans, with the `gen_ai.system`, `gen_ai.request.model` and
+//!   `gen_ai.usage.input_tokens` attributes;
+//! - `execute_tool {tool}` spans, with the `gen_ai.tool.name` and `gen_ai.tool.type` attributes
+//!   (`extension` for MCP tools, `function` otherwise).
+//!
+//! Spans are emitted on the `rig` target. Latency is the duration of the span. Since agents call
+//! their model and tools from within the caller's span, the spans of a multi-turn agent are nested
+//! in the span of the application.
+//!
+//! The spans are exported by installing the `tracing-opentelemetry` layer, e.g.:
+//! ```rust
+//! use opentelemetry::trace::TracerProvider;
+//! use tracing_subscriber::prelude::*;
+//!
+//! let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+//!     .with_batch_exporter(opentelemetry_otlp::new_exporter().tonic().build_span_exporter()?, runtime::Tokio)
+//!     .build();
+//!
+//! tracing_subscriber::registry()
+//!     .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("my-app")))
+//!     .init();
+//! ```
+
+/// Record the token usage of a completion or embedding request made by `model` on the current
+/// span, and in the installed [usage ledger](crate::usage::UsageLedger) (if any).
+pub(crate) fn record_usage(model: &str, input_tokens: Option<u64>, output_tokens: Option<u64>) {
+    #[cfg(feature = "otel")]
+    {
+        let span = tracing::Span::current();
+        if let Some(input_tokens) = input_tokens {
+            span.record("gen_ai.usage.input_tokens", input_tokens);
+        }
+        if let Some(output_tokens) = output_tokens {
+            span.record("gen_ai.usage.output_tokens", output_tokens);
+        }
+    }
+
+    crate::usage::record(model, input_tokens, output_tokens);
+}
+
+/// Record the type of the tool being called (see the `gen_ai.tool.type` attribute)
+/// on the current span.
+#[allow(unused_variables)]
+pub(crate) fn record_tool_type(tool_type: &'static str) {
+    #[cfg(feature = "otel")]
+    tracing::Span::current().record("gen_ai.tool.type", tool_type);
+}
+
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use bytes::Bytes;
+    use serde_json::json;
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Subscriber,
+    };
+    use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+    use crate::{
+        completion::CompletionModel,
+        http_client::{HttpClient, HttpClientError},
+        providers::openai,
+    };
+
+    /// Layer recording the name and fields of the spans
+    #[derive(Clone, Default)]
+    struct SpanRecorder {
+        spans: Arc<Mutex<Vec<(Id, String, HashMap<String, String>)>>>,
+    }
+
+    impl SpanRecorder {
+        /// Fields of the span `name`
+        fn fields(&self, name: &str) -> HashMap<String, String> {
+            let spans = self.spans.lock().unwrap();
+            let span = spans.iter().find(|(_, span, _)| span == name);
+            span.map(|(_, _, fields)| fields.clone())
+                .unwrap_or_default()
+        }
+    }
+
+    struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+    impl Visit for FieldVisitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{value:?}"));
+        }
+    }
+
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanRecorder {
+        fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, _context: Context<'_, S>) {
+            let mut fields = HashMap::new();
+            attributes.record(&mut FieldVisitor(&mut fields));
+            let name = attributes.metadata().name().to_string();
+            self.spans.lock().unwrap().push((id.clone(), name, fields));
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, _context: Context<'_, S>) {
+            let mut spans = self.spans.lock().unwrap();
+            if let Some((_, _, fields)) = spans.iter_mut().rev().find(|(span, _, _)| span == id) {
+                values.record(&mut FieldVisitor(fields));
+            }
+        }
+    }
+
+    /// Client answering the chat completions
+    struct FakeClient;
+
+    impl HttpClient for FakeClient {
+        async fn send(
+            &self,
+            _request: http::Request<Bytes>,
+        ) -> Result<http::Response<Bytes>, HttpClientError> {
+            let body = json!({
+                "id": "chatcmpl-123",
+                "object": "chat.completion",
+                "created": 1715367049,
+                "model": openai::GPT_4O,
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Hello!"},
+                    "finish_reason": "stop"
+                }],
+                "usage": {"prompt_tokens": 12, "total_tokens": 15}
+            });
+            Ok(http::Response::new(body.to_string().into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_completion_span() {
+        let recorder = SpanRecorder::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+
+        let model = openai::Client::new("sk-123")
+            .with_http_client(FakeClient)
+            .completion_model(openai::GPT_4O);
+        model.completion_request("Hi").send().await.unwrap();
+
+        let fields = recorder.fields("chat");
+        assert_eq!(fields["otel.name"], format!("chat {}", openai::GPT_4O));
+        assert_eq!(fields["gen_ai.operation.name"], "chat");
+        assert_eq!(fields["gen_ai.system"], "openai");
+        assert_eq!(fields["gen_ai.request.model"], openai::GPT_4O);
+        assert_eq!(fields["gen_ai.usage.input_tokens"], "12");
+        assert_eq!(fields["gen_ai.usage.output_tokens"], "3");
+    }
+}
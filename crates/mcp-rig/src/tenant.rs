@@ -0,0 +1,374 @@
+//! Isolation of the agents of multiple tenants (i.e.: customers) hosted by a same service.
+//!
+//! A [Tenant] is attached to the agents built for a customer with
+//! [AgentBuilder::tenant](crate::agent::AgentBuilder::tenant), which threads its id through:
+//! - the [TraceContext] of the agents, and thus their exported traces and sessions,
+//! - the [UsageLedger](crate::usage::UsageLedger), whose records can be filtered and grouped
+//!   by tenant (see [GroupBy::Tenant](crate::usage::GroupBy::Tenant)),
+//! - the rate limiter of the tenant (see [Tenant::rate_limit]), shared by all its agents, which
+//!   rejects the prompts over the limit,
+//! - the [AuditLog] of the tenant (see [Tenant::audit_log]), which records the prompts, the
+//!   rejected prompts and the tool calls of its agents (the last 10,000 entries by default, see
+//!   [AuditLog::capacity]).
+//!
+//! The prompts rejected by the rate limiter fail with
+//! [CompletionError::TenantError](crate::completion::CompletionError::TenantError).
+//!
+//! The names shared by the tenants (e.g.: the namespaces of the vector indexes and embedding
+//! caches, or the ids of the sessions) are scoped to a tenant with [Tenant::namespace].
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use mcp_rig::{
+//!     embeddings::cache::CachedEmbeddingModel,
+//!     observability::TraceContext,
+//!     providers::openai,
+//!     tenant::{AuditLog, Tenant},
+//! };
+//!
+//! let audit_log = AuditLog::new();
+//! let acme = Tenant::new("acme")
+//!     .rate_limit(100, Duration::from_secs(60))
+//!     .audit_log(audit_log.clone());
+//!
+//! let embedding_model = CachedEmbeddingModel::new(embedding_model, store)
+//!     .namespace(&acme.namespace(openai::TEXT_EMBEDDING_3_SMALL));
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .trace_context(TraceContext::new("support").session_id(&acme.namespace("conversation-42")))
+//!     .tenant(acme.clone())
+//!     .build();
+//!
+//! let answer = agent.prompt("Where is my order?").await?;
+//! std::fs::write("audit.jsonl", audit_log.to_jsonl())?;
+//! ```
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::observability::TraceContext;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TenantError {
+    /// The tenant made more requests than its rate limit allows
+    #[error("Tenant {tenant} is rate limited, retry after {retry_after:?}")]
+    RateLimited {
+        tenant: String,
+        retry_after: Duration,
+    },
+}
+
+/// Fixed-window rate limiter. Cloning it shares it.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    requests: u32,
+    per: Duration,
+    /// Start of the current window, and number of the requests made in it
+    window: Arc<Mutex<(Instant, u32)>>,
+}
+
+impl RateLimiter {
+    /// Allow `requests` requests per `per`
+    pub fn new(requests: u32, per: Duration) -> Self {
+        Self {
+            requests,
+            per,
+            window: Arc::new(Mutex::new((Instant::now(), 0))),
+        }
+    }
+
+    /// Count a request, or fail with the time until the next window if the limit is reached
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut window = self.window.lock().expect("Rate limiter lock poisoned");
+        let elapsed = window.0.elapsed();
+        if elapsed >= self.per {
+            *window = (Instant::now(), 0);
+        } else if window.1 >= self.requests {
+            return Err(self.per - elapsed);
+        }
+        window.1 += 1;
+        Ok(())
+    }
+}
+
+/// Event recorded in an [AuditLog].
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// Prompt accepted by the agent
+    Prompt,
+    /// Prompt rejected by the rate limiter of the tenant
+    RateLimited,
+    /// Tool called by the agent
+    ToolCall { name: String, is_error: bool },
+}
+
+/// Entry of an [AuditLog].
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AuditEntry {
+    /// Time of the event, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub tenant_id: String,
+    /// Name of the agent
+    pub agent: String,
+    /// ID of the session (i.e.: conversation) of the event, if any
+    pub session_id: Option<String>,
+    /// ID of the user of the agent, if any
+    pub user_id: Option<String>,
+    pub event: AuditEvent,
+}
+
+/// Default maximum number of entries of an [AuditLog].
+pub const DEFAULT_AUDIT_LOG_CAPACITY: usize = 10_000;
+
+/// Audit log of the agents of one or several tenants, keeping its last entries (see
+/// [AuditLog::capacity]). Cloning it returns a handle to the same entries.
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    entries: Arc<Mutex<VecDeque<AuditEntry>>>,
+    capacity: usize,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(VecDeque::new())),
+            capacity: DEFAULT_AUDIT_LOG_CAPACITY,
+        }
+    }
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep at most the last `capacity` entries, the oldest ones being dropped
+    /// (default: [DEFAULT_AUDIT_LOG_CAPACITY])
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<AuditEntry>> {
+        self.entries.lock().expect("Audit log lock poisoned")
+    }
+
+    /// Record `entry` in the log, dropping the oldest entry if the log is full
+    pub fn record(&self, entry: AuditEntry) {
+        let mut entries = self.lock();
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        if self.capacity > 0 {
+            entries.push_back(entry);
+        }
+    }
+
+    /// Entries of the log, in order
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.lock().iter().cloned().collect()
+    }
+
+    /// Entries of the tenant `tenant_id`, in order
+    pub fn tenant_entries(&self, tenant_id: &str) -> Vec<AuditEntry> {
+        self.lock()
+            .iter()
+            .filter(|entry| entry.tenant_id == tenant_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Entries of the log as JSON lines
+    pub fn to_jsonl(&self) -> String {
+        self.lock()
+            .iter()
+            .filter_map(|entry| serde_json::to_string(entry).ok())
+            .map(|line| line + "\n")
+            .collect()
+    }
+}
+
+/// Tenant of a multi-tenant service (see the [module](self) docs). Cloning it shares its rate
+/// limiter and audit log.
+#[derive(Clone, Debug)]
+pub struct Tenant {
+    id: String,
+    rate_limiter: Option<RateLimiter>,
+    audit_log: Option<AuditLog>,
+}
+
+impl Tenant {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            rate_limiter: None,
+            audit_log: None,
+        }
+    }
+
+    /// Allow the agents of the tenant `requests` prompts per `per` (in total)
+    pub fn rate_limit(mut self, requests: u32, per: Duration) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(requests, per));
+        self
+    }
+
+    /// Record the prompts and tool calls of the agents of the tenant in `audit_log`
+    pub fn audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// `name` scoped to the tenant (e.g.: the namespace of a vector index, or a session id)
+    pub fn namespace(&self, name: &str) -> String {
+        format!("{}/{}", self.id, name)
+    }
+
+    /// Admit a prompt of the agent of `context`, unless the tenant is rate limited
+    pub(crate) fn admit(&self, context: &TraceContext) -> Result<(), TenantError> {
+        let limited = self
+            .rate_limiter
+            .as_ref()
+            .and_then(|rate_limiter| rate_limiter.try_acquire().err());
+        if let Some(retry_after) = limited {
+            tracing::warn!(target: "rig", "Tenant {} is rate limited", self.id);
+            self.audit(context, AuditEvent::RateLimited);
+            return Err(TenantError::RateLimited {
+                tenant: self.id.clone(),
+                retry_after,
+            });
+        }
+        self.audit(context, AuditEvent::Prompt);
+        Ok(())
+    }
+
+    /// Record `event` of the agent of `context` in the audit log of the tenant (if any)
+    pub(crate) fn audit(&self, context: &TraceContext, event: AuditEvent) {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record(AuditEntry {
+                timestamp_ms: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_millis() as u64)
+                    .unwrap_or_default(),
+                tenant_id: self.id.clone(),
+                agent: context.name.clone(),
+                session_id: context.session_id.clone(),
+                user_id: context.user_id.clone(),
+                event,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        completion::{CompletionError, Prompt, PromptError},
+        providers::mock::{self, MockResponse},
+        streaming::{StreamingChat, StreamingPrompt},
+        tool,
+    };
+
+    #[tokio::test]
+    async fn test_tenant() {
+        let audit_log = AuditLog::new();
+        let acme = Tenant::new("acme")
+            .rate_limit(2, Duration::from_secs(3600))
+            .audit_log(audit_log.clone());
+        assert_eq!(acme.namespace("docs"), "acme/docs");
+
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::tool_call("echo", json!({"text": "Hi"})))
+            .default_response(MockResponse::text("Hello!"));
+        let agent =
+            model
+                .agent()
+                .tool(tool::from_fn(
+                    "echo",
+                    "Echo the arguments",
+                    json!({"type": "object"}),
+                    |args: serde_json::Value| async move {
+                        Ok::<_, std::io::Error>(args["text"].clone())
+                    },
+                ))
+                .trace_context(TraceContext::new("support").session_id("conversation-42"))
+                .tenant(acme.clone())
+                .build();
+        // Another agent of the same tenant shares its rate limit
+        let other_agent = model.agent().tenant(acme).build();
+
+        agent.prompt("Say hi").await.unwrap();
+        // The streamed prompts are rate limited and audited too
+        other_agent.stream_prompt("Hello").await.unwrap();
+        assert!(matches!(
+            agent.prompt("Hello").await,
+            Err(PromptError::CompletionError(CompletionError::TenantError(
+                TenantError::RateLimited { .. }
+            )))
+        ));
+        assert!(matches!(
+            agent.stream_chat("Hello", vec![]).await,
+            Err(CompletionError::TenantError(_))
+        ));
+
+        let events = audit_log
+            .tenant_entries("acme")
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                AuditEvent::Prompt,
+                AuditEvent::ToolCall {
+                    name: "echo".to_string(),
+                    is_error: false
+                },
+                AuditEvent::Prompt,
+                AuditEvent::RateLimited,
+                AuditEvent::RateLimited,
+            ]
+        );
+        assert_eq!(
+            audit_log.entries()[0].session_id.as_deref(),
+            Some("conversation-42")
+        );
+    }
+
+    #[test]
+    fn test_audit_log_capacity() {
+        let audit_log = AuditLog::new().capacity(2);
+        let acme = Tenant::new("acme").audit_log(audit_log.clone());
+        let context = TraceContext::new("support");
+
+        acme.audit(&context, AuditEvent::Prompt);
+        acme.audit(&context, AuditEvent::RateLimited);
+        acme.audit(
+            &context,
+            AuditEvent::ToolCall {
+                name: "echo".to_string(),
+                is_error: false,
+            },
+        );
+
+        let events = audit_log
+            .entries()
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0], AuditEvent::RateLimited);
+    }
+}
@@ -8,15 +8,33 @@
 //!
 //! The [ToolSet] struct is a collection of tools that can be used by an [Agent](crate::agent::Agent)
 //! and optionally RAGged.
+//!
+//! Request-scoped state (e.g.: the user ID, tenant or database handle of the request) is passed
+//! to the tools in the [Extensions] of the call, see [Tool::call_with_extensions].
+//!
+//! One-off tools can be defined from async closures with [from_fn] (JSON arguments and schema)
+//! or [from_typed_fn] (typed arguments, whose schema is derived with `schemars`) instead of
+//! implementing the [Tool] trait.
 
-use std::{collections::HashMap, pin::Pin, sync::Arc};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
 
-use futures::Future;
+use futures::{future::BoxFuture, Future};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use web_time::Instant;
 
 use crate::{
-    completion::{self, ToolDefinition},
+    completion::{self, Prompt, PromptError, ToolDefinition},
     embeddings::{embed::EmbedError, tool::ToolSchema},
+    payload_log,
+    tool_stats::ToolStats,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -111,6 +129,88 @@ pub trait Tool: Sized + Send + Sync {
         &self,
         args: Self::Args,
     ) -> impl Future<Output = Result<Self::Output, Self::Error>> + Send + Sync;
+
+    /// Behavior hints of the tool (e.g.: whether it is read-only), used by the
+    /// [ApprovalPolicy] of the toolset. Defaults to no hints.
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::default()
+    }
+
+    /// The tool execution method, with the request-scoped [Extensions] of the call (e.g.: as
+    /// passed to [Agent::prompt_with_extensions](crate::agent::Agent::prompt_with_extensions)).
+    /// Defaults to [Tool::call]: tools needing request-scoped state override this method, and
+    /// implement [Tool::call] for the calls without extensions.
+    fn call_with_extensions(
+        &self,
+        args: Self::Args,
+        extensions: &Extensions,
+    ) -> impl Future<Output = Result<Self::Output, Self::Error>> + Send + Sync {
+        let _ = extensions;
+        Tool::call(self, args)
+    }
+}
+
+/// Typed map of request-scoped values passed to the tools (see [Tool::call_with_extensions]),
+/// holding at most one value per type.
+///
+/// # Example
+/// ```rust
+/// use mcp_rig::tool::Extensions;
+///
+/// #[derive(Clone)]
+/// struct UserId(String);
+///
+/// let extensions = Extensions::new().with(UserId("user-1".to_string()));
+/// assert_eq!(extensions.get::<UserId>().unwrap().0, "user-1");
+/// ```
+#[derive(Clone, Default)]
+pub struct Extensions {
+    values: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `value` to the extensions (replacing the value of the same type, if any)
+    pub fn with<T: Send + Sync + 'static>(mut self, value: T) -> Self {
+        self.insert(value);
+        self
+    }
+
+    /// Insert `value` in the extensions (replacing the value of the same type, if any)
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) {
+        self.values.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    /// Value of type `T`, if any
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| (**value).downcast_ref::<T>())
+    }
+
+    /// Whether the extensions contain a value of type `T`
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.values.len())
+            .finish()
+    }
 }
 
 /// Trait that represents an LLM tool that can be stored in a vector store and RAGged
@@ -153,6 +253,28 @@ pub trait ToolDyn: Send + Sync {
         &self,
         args: String,
     ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>>;
+
+    /// Call the tool with the request-scoped [Extensions] of the call. Defaults to
+    /// [ToolDyn::call] (i.e.: the extensions are ignored).
+    fn call_with_extensions<'a>(
+        &'a self,
+        args: String,
+        extensions: &'a Extensions,
+    ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + 'a>> {
+        let _ = extensions;
+        ToolDyn::call(self, args)
+    }
+
+    /// Behavior hints of the tool (see [ToolAnnotations]). Defaults to no hints.
+    fn annotations(&self) -> ToolAnnotations {
+        ToolAnnotations::default()
+    }
+
+    /// Check that the tool can be called (e.g.: that its MCP server is reachable). Defaults to
+    /// always ready.
+    fn ready(&self) -> Pin<Box<dyn Future<Output = Result<(), ToolError>> + Send + Sync + '_>> {
+        Box::pin(async { Ok(()) })
+    }
 }
 
 impl<T: Tool> ToolDyn for T {
@@ -160,6 +282,10 @@ impl<T: Tool> ToolDyn for T {
         self.name()
     }
 
+    fn annotations(&self) -> ToolAnnotations {
+        <Self as Tool>::annotations(self)
+    }
+
     fn definition(
         &self,
         prompt: String,
@@ -171,41 +297,415 @@ impl<T: Tool> ToolDyn for T {
         &self,
         args: String,
     ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>> {
-        Box::pin(async move {
-            match serde_json::from_str(&args) {
-                Ok(args) => <Self as Tool>::call(self, args)
-                    .await
-                    .map_err(|e| ToolError::ToolCallError(Box::new(e)))
-                    .and_then(|output| {
-                        serde_json::to_string(&output).map_err(ToolError::JsonError)
-                    }),
-                Err(e) => Err(ToolError::JsonError(e)),
-            }
-        })
+        Box::pin(call_tool(self, args, None))
+    }
+
+    fn call_with_extensions<'a>(
+        &'a self,
+        args: String,
+        extensions: &'a Extensions,
+    ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + 'a>> {
+        Box::pin(call_tool(self, args, Some(extensions)))
+    }
+}
+
+/// Call `tool` with its JSON `args` (and `extensions`, if any), and return its JSON output.
+async fn call_tool<T: Tool>(
+    tool: &T,
+    args: String,
+    extensions: Option<&Extensions>,
+) -> Result<String, ToolError> {
+    let args = serde_json::from_str(&args).map_err(ToolError::JsonError)?;
+    let output = match extensions {
+        Some(extensions) => Tool::call_with_extensions(tool, args, extensions).await,
+        None => Tool::call(tool, args).await,
+    }
+    .map_err(|e| ToolError::ToolCallError(Box::new(e)))?;
+    serde_json::to_string(&output).map_err(ToolError::JsonError)
+}
+
+/// Tool defined by an async closure, created with [from_fn] or [from_typed_fn].
+pub struct FnTool<A, F> {
+    name: String,
+    description: String,
+    parameters: serde_json::Value,
+    annotations: ToolAnnotations,
+    f: F,
+    _args: PhantomData<fn(A)>,
+}
+
+impl<A, F> FnTool<A, F> {
+    /// Set the behavior hints of the tool (e.g.: that it is read-only)
+    pub fn with_annotations(mut self, annotations: ToolAnnotations) -> Self {
+        self.annotations = annotations;
+        self
     }
 }
 
+/// Create a tool named `name` calling the async closure `f` with the JSON arguments of the
+/// model, described to the model by `description` and the JSON schema `parameters`.
+///
+/// # Example
+/// ```
+/// use mcp_rig::tool;
+///
+/// let time = tool::from_fn(
+///     "time",
+///     "Get the current time in a timezone",
+///     serde_json::json!({
+///         "type": "object",
+///         "properties": { "timezone": { "type": "string" } }
+///     }),
+///     |args: serde_json::Value| async move {
+///         Ok::<_, std::io::Error>(format!("12:00 in {}", args["timezone"]))
+///     },
+/// );
+///
+/// let agent = openai.agent("gpt-4o").tool(time).build();
+/// ```
+pub fn from_fn<F, Fut, O, E>(
+    name: &str,
+    description: &str,
+    parameters: serde_json::Value,
+    f: F,
+) -> FnTool<serde_json::Value, F>
+where
+    F: Fn(serde_json::Value) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<O, E>> + Send,
+{
+    FnTool {
+        name: name.to_string(),
+        description: description.to_string(),
+        parameters,
+        annotations: ToolAnnotations::default(),
+        f,
+        _args: PhantomData,
+    }
+}
+
+/// Create a tool named `name` calling the async closure `f` with the arguments of the model,
+/// deserialized into `A`. The JSON schema of the arguments is derived from `A` (the doc
+/// comments of its fields become their descriptions).
+///
+/// # Example
+/// ```
+/// use mcp_rig::tool;
+///
+/// #[derive(serde::Deserialize, schemars::JsonSchema)]
+/// struct AddArgs {
+///     /// The first number to add
+///     x: i32,
+///     /// The second number to add
+///     y: i32,
+/// }
+///
+/// let adder = tool::from_typed_fn("add", "Add x and y together", |args: AddArgs| async move {
+///     Ok::<_, std::convert::Infallible>(args.x + args.y)
+/// });
+/// ```
+pub fn from_typed_fn<A, F, Fut, O, E>(name: &str, description: &str, f: F) -> FnTool<A, F>
+where
+    A: JsonSchema + for<'a> Deserialize<'a> + Send + Sync,
+    F: Fn(A) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<O, E>> + Send,
+{
+    FnTool {
+        name: name.to_string(),
+        description: description.to_string(),
+        parameters: parameters_schema::<A>(),
+        annotations: ToolAnnotations::default(),
+        f,
+        _args: PhantomData,
+    }
+}
+
+/// JSON schema of the arguments `A` of a tool, as expected by [ToolDefinition::parameters].
+pub fn parameters_schema<A: JsonSchema>() -> serde_json::Value {
+    let mut parameters = serde_json::to_value(schemars::schema_for!(A)).unwrap_or_default();
+    if let Some(schema) = parameters.as_object_mut() {
+        schema.remove("$schema");
+        schema.remove("title");
+    }
+    parameters
+}
+
+impl<A, F, Fut, O, E> Tool for FnTool<A, F>
+where
+    A: for<'a> Deserialize<'a> + Send + Sync,
+    F: Fn(A) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<O, E>> + Send,
+    O: Serialize,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    // Unused: the name of the tool is given at runtime
+    const NAME: &'static str = "fn_tool";
+
+    type Error = E;
+    type Args = A;
+    type Output = O;
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn annotations(&self) -> ToolAnnotations {
+        self.annotations.clone()
+    }
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            parameters: self.parameters.clone(),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        SyncFuture::new((self.f)(args)).await
+    }
+}
+
+/// Future that is `Sync` whatever the (`Send`) future it wraps, so that tools can await
+/// futures that are only `Send` (e.g.: the requests of a provider client) in [Tool::call].
+pub struct SyncFuture<F>(Mutex<Pin<Box<F>>>);
+
+impl<F: Future + Send> SyncFuture<F> {
+    pub fn new(future: F) -> Self {
+        Self(Mutex::new(Box::pin(future)))
+    }
+}
+
+impl<F: Future + Send> Future for SyncFuture<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // The future is only ever polled through `&mut self`, so the lock is never contended
+        match self.0.get_mut() {
+            Ok(future) => future.as_mut().poll(cx),
+            Err(poisoned) => poisoned.into_inner().as_mut().poll(cx),
+        }
+    }
+}
+
+/// Maximum number of calls in flight to an MCP server, shared by the tools of the server (see
+/// [McpTool::with_limit]), so that many parallel tool calls don't overload a small (e.g.:
+/// local stdio) server. The calls over the limit wait for a previous call to complete.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+pub struct McpCallLimit {
+    max_in_flight: usize,
+    semaphore: Arc<tokio::sync::Semaphore>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl McpCallLimit {
+    /// Limit of `max_in_flight` calls at once (at least 1)
+    pub fn new(max_in_flight: usize) -> Self {
+        let max_in_flight = max_in_flight.max(1);
+        Self {
+            max_in_flight,
+            semaphore: Arc::new(tokio::sync::Semaphore::new(max_in_flight)),
+        }
+    }
+
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    /// Wait until a call may start. The call ends when the returned permit is dropped.
+    async fn acquire(&self) -> tokio::sync::OwnedSemaphorePermit {
+        self.semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("The semaphore of the calls is never closed")
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl std::fmt::Debug for McpCallLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpCallLimit")
+            .field("max_in_flight", &self.max_in_flight)
+            .finish()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 pub struct McpTool<T: mcp_core::transport::Transport> {
     definition: mcp_core::types::Tool,
-    client: Arc<mcp_core::client::Client<T>>,
+    client: std::sync::Arc<mcp_core::client::Client<T>>,
+    /// Limit of the calls in flight to the server, if any
+    limit: Option<McpCallLimit>,
+    annotations: ToolAnnotations,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl<T> McpTool<T>
 where
     T: mcp_core::transport::Transport,
 {
     pub fn from_mcp_server(
         definition: mcp_core::types::Tool,
-        client: Arc<mcp_core::client::Client<T>>,
+        client: std::sync::Arc<mcp_core::client::Client<T>>,
     ) -> Self {
-        Self { definition, client }
+        // The annotations of the definition, if the MCP types carry them
+        let annotations = serde_json::to_value(&definition)
+            .map(|definition| ToolAnnotations::from_mcp(&definition))
+            .unwrap_or_default();
+        Self {
+            definition,
+            client,
+            limit: None,
+            annotations,
+        }
     }
+
+    /// Set the annotations of the tool (e.g.: parsed with [ToolAnnotations::from_mcp] from the
+    /// raw `tools/list` response of the server)
+    pub fn with_annotations(mut self, annotations: ToolAnnotations) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
+    /// Count the calls of the tool towards `limit`, which should be shared by all the tools of
+    /// the server
+    pub fn with_limit(mut self, limit: McpCallLimit) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T> McpTool<T>
+where
+    T: mcp_core::transport::Transport,
+{
+    async fn call_tool(&self, name: &str, args: serde_json::Value) -> Result<String, ToolError> {
+        let _permit = match &self.limit {
+            Some(limit) => Some(SyncFuture::new(limit.acquire()).await),
+            None => None,
+        };
+        call_mcp_tool(&self.client, name, args).await
+    }
+}
+
+/// Call the tool `name` of the MCP server of `client`, and join the texts of its result
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn call_mcp_tool<T: mcp_core::transport::Transport>(
+    client: &mcp_core::client::Client<T>,
+    name: &str,
+    args: serde_json::Value,
+) -> Result<String, ToolError> {
+    Ok(call_mcp_tool_content(client, name, args)
+        .await?
+        .into_iter()
+        .map(|content| match content {
+            completion::message::ToolResultContent::Text(text) => text.text,
+            _ => "".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(""))
 }
 
+/// Call the tool `name` of the MCP server of `client`, and return the contents of its result
+/// (i.e.: its texts and images)
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn call_mcp_tool_content<T: mcp_core::transport::Transport>(
+    client: &mcp_core::client::Client<T>,
+    name: &str,
+    args: serde_json::Value,
+) -> Result<Vec<completion::message::ToolResultContent>, ToolError> {
+    let result = client.call_tool(name, Some(args)).await.map_err(|e| {
+        ToolError::ToolCallError(Box::new(McpToolError(format!(
+            "Tool returned an error: {}",
+            e
+        ))))
+    })?;
+
+    if result.is_error.unwrap_or(false) {
+        if let Some(error) = result.content.first() {
+            match error {
+                mcp_core::types::ToolResponseContent::Text { text } => {
+                    return Err(ToolError::ToolCallError(Box::new(McpToolError(
+                        text.clone(),
+                    ))));
+                }
+                _ => {
+                    return Err(ToolError::ToolCallError(Box::new(McpToolError(
+                        "Unsuppported error type".to_string(),
+                    ))))
+                }
+            }
+        } else {
+            return Err(ToolError::ToolCallError(Box::new(McpToolError(
+                "No error message returned".to_string(),
+            ))));
+        }
+    }
+
+    Ok(result.content.into_iter().filter_map(mcp_content).collect())
+}
+
+/// Content of the result of an MCP tool, if it is a text or an image
+#[cfg(not(target_arch = "wasm32"))]
+fn mcp_content(
+    content: mcp_core::types::ToolResponseContent,
+) -> Option<completion::message::ToolResultContent> {
+    use completion::message::{ContentFormat, ImageMediaType, MimeType, ToolResultContent};
+
+    match content {
+        mcp_core::types::ToolResponseContent::Text { text } => Some(ToolResultContent::text(text)),
+        other => {
+            // Read through JSON, as the fields of the images differ between the MCP versions
+            let value = serde_json::to_value(other).ok()?;
+            if value.get("type")?.as_str()? != "image" {
+                return None;
+            }
+            let mime_type = value
+                .get("mimeType")
+                .or_else(|| value.get("mime_type"))
+                .and_then(|mime_type| mime_type.as_str());
+            Some(ToolResultContent::image(
+                value.get("data")?.as_str()?,
+                Some(ContentFormat::Base64),
+                mime_type.and_then(ImageMediaType::from_mime_type),
+                None,
+            ))
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T> McpTool<T>
+where
+    T: mcp_core::transport::Transport,
+{
+    /// Check that the server is reachable and still lists the tool
+    async fn listed(&self) -> Result<(), ToolError> {
+        let tools = self.client.list_tools(None, None).await.map_err(|e| {
+            ToolError::ToolCallError(Box::new(McpToolError(format!("Server unreachable: {}", e))))
+        })?;
+        if tools
+            .tools
+            .iter()
+            .any(|tool| tool.name == self.definition.name)
+        {
+            Ok(())
+        } else {
+            Err(ToolError::ToolCallError(Box::new(McpToolError(
+                "Tool no longer listed by the server".to_string(),
+            ))))
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug, thiserror::Error)]
 #[error("MCP tool error: {0}")]
 pub struct McpToolError(String);
 
+#[cfg(not(target_arch = "wasm32"))]
 impl<T> ToolDyn for McpTool<T>
 where
     T: mcp_core::transport::Transport,
@@ -214,6 +714,14 @@ where
         self.definition.name.clone()
     }
 
+    fn annotations(&self) -> ToolAnnotations {
+        self.annotations.clone()
+    }
+
+    fn ready(&self) -> Pin<Box<dyn Future<Output = Result<(), ToolError>> + Send + Sync + '_>> {
+        Box::pin(self.listed())
+    }
+
     fn definition(
         &self,
         _prompt: String,
@@ -234,51 +742,16 @@ where
         &self,
         args: String,
     ) -> Pin<Box<dyn Future<Output = Result<String, ToolError>> + Send + Sync + '_>> {
+        crate::telemetry::record_tool_type("extension");
+
         let name = self.definition.name.clone();
         let args_clone = args.clone();
         let args: serde_json::Value = serde_json::from_str(&args_clone).unwrap_or_default();
         Box::pin(async move {
-            let result = self
-                .client
-                .call_tool(&name, Some(args))
-                .await
-                .map_err(|e| {
-                    ToolError::ToolCallError(Box::new(McpToolError(format!(
-                        "Tool returned an error: {}",
-                        e
-                    ))))
-                })?;
-
-            if result.is_error.unwrap_or(false) {
-                if let Some(error) = result.content.first() {
-                    match error {
-                        mcp_core::types::ToolResponseContent::Text { text } => {
-                            return Err(ToolError::ToolCallError(Box::new(McpToolError(
-                                text.clone(),
-                            ))));
-                        }
-                        _ => {
-                            return Err(ToolError::ToolCallError(Box::new(McpToolError(
-                                "Unsuppported error type".to_string(),
-                            ))))
-                        }
-                    }
-                } else {
-                    return Err(ToolError::ToolCallError(Box::new(McpToolError(
-                        "No error message returned".to_string(),
-                    ))));
-                }
-            }
-
-            Ok(result
-                .content
-                .into_iter()
-                .map(|c| match c {
-                    mcp_core::types::ToolResponseContent::Text { text } => text,
-                    _ => "".to_string(),
-                })
-                .collect::<Vec<_>>()
-                .join(""))
+            payload_log::log_tool_call(&name, &args);
+            let result = self.call_tool(&name, args).await;
+            payload_log::log_tool_result(&name, &result);
+            result
         })
     }
 }
@@ -313,6 +786,20 @@ impl ToolType {
         }
     }
 
+    pub fn annotations(&self) -> ToolAnnotations {
+        match self {
+            ToolType::Simple(tool) => tool.annotations(),
+            ToolType::Embedding(tool) => tool.annotations(),
+        }
+    }
+
+    pub async fn ready(&self) -> Result<(), ToolError> {
+        match self {
+            ToolType::Simple(tool) => tool.ready().await,
+            ToolType::Embedding(tool) => tool.ready().await,
+        }
+    }
+
     pub async fn definition(&self, prompt: String) -> ToolDefinition {
         match self {
             ToolType::Simple(tool) => tool.definition(prompt).await,
@@ -320,10 +807,10 @@ impl ToolType {
         }
     }
 
-    pub async fn call(&self, args: String) -> Result<String, ToolError> {
+    pub async fn call(&self, args: String, extensions: &Extensions) -> Result<String, ToolError> {
         match self {
-            ToolType::Simple(tool) => tool.call(args).await,
-            ToolType::Embedding(tool) => tool.call(args).await,
+            ToolType::Simple(tool) => tool.call_with_extensions(args, extensions).await,
+            ToolType::Embedding(tool) => tool.call_with_extensions(args, extensions).await,
         }
     }
 }
@@ -337,15 +824,458 @@ pub enum ToolSetError {
     #[error("ToolNotFoundError: {0}")]
     ToolNotFoundError(String),
 
+    /// The call of the tool was denied by the [ApprovalPolicy] of the toolset
+    #[error("Call of tool {0} denied")]
+    ApprovalDenied(String),
+
     // TODO: Revisit this
     #[error("JsonError: {0}")]
     JsonError(#[from] serde_json::Error),
 }
 
+/// What an agent does when a call of a tool fails (whether the tool is native or MCP).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolErrorPolicy {
+    /// Abort the prompt with the error (the default)
+    #[default]
+    Fail,
+    /// Return the error to the model as the result of the tool call (see [error_result]), so
+    /// that it can recover (e.g.: call the tool with other arguments) or apologize
+    ReturnToModel,
+}
+
+/// Result of a failed tool call, as returned to the model: `{"error": "..."}`.
+pub fn error_result(error: &ToolSetError) -> String {
+    let message = match error {
+        ToolSetError::ToolCallError(ToolError::ToolCallError(error)) => error.to_string(),
+        ToolSetError::ToolCallError(ToolError::JsonError(error)) => {
+            format!("Invalid arguments: {error}")
+        }
+        ToolSetError::ToolNotFoundError(name) => format!("Tool {name} not found"),
+        error => error.to_string(),
+    };
+    serde_json::json!({ "error": message }).to_string()
+}
+
+/// Behavior hints of a tool, as annotated by MCP servers (the `annotations` of their tool
+/// definitions). The hints are not guaranteed to be accurate: they should only be trusted for
+/// trusted servers. The unset hints take the defaults of the MCP specification.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ToolAnnotations {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The tool does not modify its environment
+    #[serde(
+        rename = "readOnlyHint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub read_only: Option<bool>,
+    /// The tool may perform destructive updates (only meaningful if it is not read-only)
+    #[serde(
+        rename = "destructiveHint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub destructive: Option<bool>,
+    /// Calling the tool repeatedly with the same arguments has no additional effect
+    #[serde(
+        rename = "idempotentHint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub idempotent: Option<bool>,
+    /// The tool interacts with external entities (e.g.: the web)
+    #[serde(
+        rename = "openWorldHint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub open_world: Option<bool>,
+}
+
+impl ToolAnnotations {
+    /// Annotations of an MCP tool definition (e.g.: an item of the `tools` of a `tools/list`
+    /// response). Missing or invalid annotations are ignored.
+    pub fn from_mcp(definition: &serde_json::Value) -> Self {
+        definition
+            .get("annotations")
+            .and_then(|annotations| serde_json::from_value(annotations.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether the tool is read-only (false if unset)
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.unwrap_or(false)
+    }
+
+    /// Whether the tool may perform destructive updates (true if unset and not read-only)
+    pub fn is_destructive(&self) -> bool {
+        !self.is_read_only() && self.destructive.unwrap_or(true)
+    }
+
+    /// Whether the tool is idempotent (false if unset)
+    pub fn is_idempotent(&self) -> bool {
+        self.idempotent.unwrap_or(false)
+    }
+
+    /// Whether the tool interacts with external entities (true if unset)
+    pub fn is_open_world(&self) -> bool {
+        self.open_world.unwrap_or(true)
+    }
+}
+
+/// Whether a tool may be called (see [ApprovalPolicy]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Approval {
+    /// Call the tool
+    #[default]
+    Allow,
+    /// Ask the approver of the policy before calling the tool (denied without approver)
+    Ask,
+    /// Never call the tool
+    Deny,
+}
+
+/// Call of a tool submitted to the approver of an [ApprovalPolicy].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApprovalRequest {
+    pub tool: String,
+    /// JSON arguments of the call
+    pub args: String,
+    pub annotations: ToolAnnotations,
+}
+
+pub(crate) type Approver = Arc<dyn Fn(ApprovalRequest) -> BoxFuture<'static, bool> + Send + Sync>;
+
+/// Approval of the tool calls of an agent, from the [ToolAnnotations] of the tools (e.g.:
+/// "allow the read-only tools, ask before calling the destructive ones"). The rules are data
+/// (e.g.: part of an [AgentConfig](crate::agent::AgentConfig)), and the approver (e.g.: a
+/// prompt to the user) is set in code.
+///
+/// The approval of a tool is, in order: its own approval (see [ApprovalPolicy::tool]), the
+/// approval of the read-only tools if it is read-only, of the destructive tools if it is
+/// destructive, of the open-world tools if it is open-world, and the default approval.
+///
+/// # Example
+/// ```rust
+/// use mcp_rig::{providers::openai, tool::{Approval, ApprovalPolicy}};
+///
+/// let policy = ApprovalPolicy::new()
+///     .read_only(Approval::Allow)
+///     .destructive(Approval::Ask)
+///     .tool("delete_account", Approval::Deny)
+///     .approver(|request| async move {
+///         println!("Allow {} with {}? [y/N]", request.tool, request.args);
+///         read_line().await.trim() == "y"
+///     });
+///
+/// let agent = openai.agent(openai::GPT_4O)
+///     .mcp_tool(tool, client)
+///     .tool_approval(policy)
+///     .build();
+/// ```
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct ApprovalPolicy {
+    /// Approval of the read-only tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<Approval>,
+    /// Approval of the destructive tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destructive: Option<Approval>,
+    /// Approval of the tools interacting with external entities
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_world: Option<Approval>,
+    /// Approval of the tools matching no rule
+    #[serde(default)]
+    pub default: Approval,
+    /// Approvals of specific tools (by name), overriding the other rules
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tools: HashMap<String, Approval>,
+    #[serde(skip)]
+    approver: Option<Approver>,
+}
+
+impl ApprovalPolicy {
+    /// Policy allowing all the tool calls (until rules are added)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read_only(mut self, approval: Approval) -> Self {
+        self.read_only = Some(approval);
+        self
+    }
+
+    pub fn destructive(mut self, approval: Approval) -> Self {
+        self.destructive = Some(approval);
+        self
+    }
+
+    pub fn open_world(mut self, approval: Approval) -> Self {
+        self.open_world = Some(approval);
+        self
+    }
+
+    /// Set the approval of the tools matching no rule
+    pub fn default_approval(mut self, approval: Approval) -> Self {
+        self.default = approval;
+        self
+    }
+
+    /// Set the approval of the tool `toolname`, whatever its annotations
+    pub fn tool(mut self, toolname: &str, approval: Approval) -> Self {
+        self.tools.insert(toolname.to_string(), approval);
+        self
+    }
+
+    /// Ask `approver` whether the calls of the tools with [Approval::Ask] may proceed
+    pub fn approver<F, Fut>(mut self, approver: F) -> Self
+    where
+        F: Fn(ApprovalRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.approver = Some(Arc::new(move |request| Box::pin(approver(request))));
+        self
+    }
+
+    pub(crate) fn with_approver(mut self, approver: Approver) -> Self {
+        self.approver = Some(approver);
+        self
+    }
+
+    /// Approval of the tool `toolname`, with annotations `annotations`
+    pub fn approval(&self, toolname: &str, annotations: &ToolAnnotations) -> Approval {
+        self.tools
+            .get(toolname)
+            .copied()
+            .or(self.read_only.filter(|_| annotations.is_read_only()))
+            .or(self.destructive.filter(|_| annotations.is_destructive()))
+            .or(self.open_world.filter(|_| annotations.is_open_world()))
+            .unwrap_or(self.default)
+    }
+
+    /// Check that the call of the tool `toolname` with `args` may proceed
+    pub(crate) async fn check(
+        &self,
+        toolname: &str,
+        args: &str,
+        annotations: ToolAnnotations,
+    ) -> Result<(), ToolSetError> {
+        let approved = match (self.approval(toolname, &annotations), &self.approver) {
+            (Approval::Allow, _) => true,
+            (Approval::Ask, Some(approver)) => {
+                approver(ApprovalRequest {
+                    tool: toolname.to_string(),
+                    args: args.to_string(),
+                    annotations,
+                })
+                .await
+            }
+            (Approval::Ask, None) | (Approval::Deny, _) => false,
+        };
+        if approved {
+            Ok(())
+        } else {
+            tracing::warn!(target: "rig", "Call of tool {toolname} denied");
+            Err(ToolSetError::ApprovalDenied(toolname.to_string()))
+        }
+    }
+}
+
+impl PartialEq for ApprovalPolicy {
+    /// Policies are equal if their rules are (whatever their approvers)
+    fn eq(&self, other: &Self) -> bool {
+        self.read_only == other.read_only
+            && self.destructive == other.destructive
+            && self.open_world == other.open_world
+            && self.default == other.default
+            && self.tools == other.tools
+    }
+}
+
+impl std::fmt::Debug for ApprovalPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApprovalPolicy")
+            .field("read_only", &self.read_only)
+            .field("destructive", &self.destructive)
+            .field("open_world", &self.open_world)
+            .field("default", &self.default)
+            .field("tools", &self.tools)
+            .field("approver", &self.approver.is_some())
+            .finish()
+    }
+}
+
+type Summarizer =
+    Arc<dyn Fn(String) -> BoxFuture<'static, Result<String, PromptError>> + Send + Sync>;
+
+/// How a tool result longer than the [OutputLimit] of the tool is shortened.
+#[derive(Clone)]
+pub enum TruncationStrategy {
+    /// Keep the beginning of the result, followed by a truncation marker
+    Truncate,
+    /// Keep the beginning and the end of the result, with a truncation marker in between
+    HeadTail,
+    /// Have a model (e.g.: an agent using a small model) summarize the result, see
+    /// [OutputLimit::summarize]. Falls back to [TruncationStrategy::HeadTail] if the
+    /// summarization fails or the summary is still too long.
+    Summarize(Summarizer),
+}
+
+impl std::fmt::Debug for TruncationStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TruncationStrategy::Truncate => write!(f, "Truncate"),
+            TruncationStrategy::HeadTail => write!(f, "HeadTail"),
+            TruncationStrategy::Summarize(_) => write!(f, "Summarize"),
+        }
+    }
+}
+
+/// Maximum size of the results of a tool, to keep large results (e.g.: a fetched web page)
+/// from filling the context window when they are returned to the model.
+///
+/// # Example
+/// ```rust
+/// use mcp_rig::{providers::openai, tool::OutputLimit};
+///
+/// let openai = openai::Client::from_env();
+/// let summarizer = openai.agent(openai::GPT_4O_MINI).build();
+///
+/// let agent = openai.agent(openai::GPT_4O)
+///     .mcp_tool(fetch, client)
+///     .tool_output_limit(OutputLimit::head_tail(10_000))
+///     .tool_output_limit_for("fetch", OutputLimit::summarize(4_000, summarizer))
+///     .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct OutputLimit {
+    /// Maximum number of characters of a result (not counting the truncation marker)
+    pub max_chars: usize,
+    pub strategy: TruncationStrategy,
+}
+
+impl OutputLimit {
+    /// Keep the first `max_chars` characters of the results
+    pub fn truncate(max_chars: usize) -> Self {
+        Self {
+            max_chars,
+            strategy: TruncationStrategy::Truncate,
+        }
+    }
+
+    /// Keep the first and last `max_chars / 2` characters of the results
+    pub fn head_tail(max_chars: usize) -> Self {
+        Self {
+            max_chars,
+            strategy: TruncationStrategy::HeadTail,
+        }
+    }
+
+    /// Have `summarizer` summarize the results longer than `max_chars` characters
+    pub fn summarize(max_chars: usize, summarizer: impl Prompt + 'static) -> Self {
+        let summarizer = Arc::new(summarizer);
+        Self {
+            max_chars,
+            strategy: TruncationStrategy::Summarize(Arc::new(move |text| {
+                let summarizer = summarizer.clone();
+                Box::pin(async move { summarizer.prompt(text).await })
+            })),
+        }
+    }
+
+    /// Shorten `output`, the result of the tool `toolname`, if it is over the limit
+    pub async fn apply(&self, toolname: &str, output: String) -> String {
+        let len = output.chars().count();
+        if len <= self.max_chars {
+            return output;
+        }
+        tracing::info!(target: "rig",
+            "Result of tool {toolname} is {len} characters long (limit: {}), shortening it",
+            self.max_chars
+        );
+
+        match &self.strategy {
+            TruncationStrategy::Truncate => format!(
+                "{}{}",
+                head(&output, self.max_chars),
+                truncation_marker(len - self.max_chars)
+            ),
+            TruncationStrategy::HeadTail => head_tail(&output, self.max_chars, len),
+            TruncationStrategy::Summarize(summarizer) => {
+                let prompt = format!(
+                    "Summarize the following result of the tool `{toolname}` in less than {} \
+                    characters. Keep the facts, figures and identifiers, and do not add any \
+                    comment.\n\n{output}",
+                    self.max_chars
+                );
+                match summarizer(prompt).await {
+                    Ok(summary) if summary.chars().count() <= self.max_chars => summary,
+                    Ok(_) => {
+                        tracing::warn!(target: "rig",
+                            "Summary of the result of tool {toolname} is over the limit, truncating it instead"
+                        );
+                        head_tail(&output, self.max_chars, len)
+                    }
+                    Err(error) => {
+                        tracing::warn!(target: "rig",
+                            "Failed to summarize the result of tool {toolname}, truncating it instead: {error}"
+                        );
+                        head_tail(&output, self.max_chars, len)
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn truncation_marker(truncated: usize) -> String {
+    format!("\n[... {truncated} characters truncated ...]\n")
+}
+
+/// First `chars` characters of `text`
+fn head(text: &str, chars: usize) -> &str {
+    match text.char_indices().nth(chars) {
+        Some((i, _)) => &text[..i],
+        None => text,
+    }
+}
+
+/// Keep the first and last `max_chars / 2` characters of `text` (of `len` characters)
+fn head_tail(text: &str, max_chars: usize, len: usize) -> String {
+    let head_chars = max_chars.div_ceil(2);
+    let tail_chars = max_chars - head_chars;
+    let tail = match text.char_indices().nth(len - tail_chars) {
+        Some((i, _)) => &text[i..],
+        None => "",
+    };
+    format!(
+        "{}{}{}",
+        head(text, head_chars),
+        truncation_marker(len - max_chars),
+        tail
+    )
+}
+
 /// A struct that holds a set of tools
 #[derive(Default)]
 pub struct ToolSet {
     pub(crate) tools: HashMap<String, ToolType>,
+    /// Error policy of the tools without their own policy
+    default_error_policy: ToolErrorPolicy,
+    /// Error policies of specific tools (by name)
+    error_policies: HashMap<String, ToolErrorPolicy>,
+    /// Output limit of the tools without their own limit
+    default_output_limit: Option<OutputLimit>,
+    /// Output limits of specific tools (by name)
+    output_limits: HashMap<String, OutputLimit>,
+    /// Approval of the tool calls (all allowed if unset)
+    approval_policy: Option<ApprovalPolicy>,
+    /// Usage statistics of the tools (not recorded if unset)
+    stats: Option<ToolStats>,
 }
 
 impl ToolSet {
@@ -377,25 +1307,173 @@ impl ToolSet {
     /// Merge another toolset into this one
     pub fn add_tools(&mut self, toolset: ToolSet) {
         self.tools.extend(toolset.tools);
+        self.error_policies.extend(toolset.error_policies);
+        self.output_limits.extend(toolset.output_limits);
+        if toolset.approval_policy.is_some() {
+            self.approval_policy = toolset.approval_policy;
+        }
+        if toolset.stats.is_some() {
+            self.stats = toolset.stats;
+        }
+    }
+
+    /// Set the error policy of the tools without their own policy
+    pub fn set_error_policy(&mut self, policy: ToolErrorPolicy) {
+        self.default_error_policy = policy;
+    }
+
+    /// Set the error policy of the tool `toolname`
+    pub fn set_tool_error_policy(&mut self, toolname: &str, policy: ToolErrorPolicy) {
+        self.error_policies.insert(toolname.to_string(), policy);
+    }
+
+    /// Error policy of the tool `toolname`
+    pub fn error_policy(&self, toolname: &str) -> ToolErrorPolicy {
+        self.error_policies
+            .get(toolname)
+            .copied()
+            .unwrap_or(self.default_error_policy)
+    }
+
+    /// Set the output limit of the tools without their own limit
+    pub fn set_output_limit(&mut self, limit: OutputLimit) {
+        self.default_output_limit = Some(limit);
+    }
+
+    /// Set the output limit of the tool `toolname`
+    pub fn set_tool_output_limit(&mut self, toolname: &str, limit: OutputLimit) {
+        self.output_limits.insert(toolname.to_string(), limit);
+    }
+
+    /// Output limit of the tool `toolname`, if any
+    pub fn output_limit(&self, toolname: &str) -> Option<&OutputLimit> {
+        self.output_limits
+            .get(toolname)
+            .or(self.default_output_limit.as_ref())
+    }
+
+    /// Set the approval policy of the tool calls
+    pub fn set_approval_policy(&mut self, policy: ApprovalPolicy) {
+        self.approval_policy = Some(policy);
+    }
+
+    /// Approval policy of the tool calls, if any
+    pub fn approval_policy(&self) -> Option<&ApprovalPolicy> {
+        self.approval_policy.as_ref()
+    }
+
+    /// Record the usage statistics of the tools in `stats`
+    pub fn set_stats(&mut self, stats: ToolStats) {
+        self.stats = Some(stats);
+    }
+
+    /// Usage statistics of the tools, if recorded
+    pub fn stats(&self) -> Option<&ToolStats> {
+        self.stats.as_ref()
+    }
+
+    /// Readiness of the tools (see [ToolDyn::ready]), by name
+    pub async fn readiness(&self) -> Vec<(String, Result<(), ToolError>)> {
+        let mut readiness = futures::future::join_all(
+            self.tools
+                .iter()
+                .map(|(name, tool)| async move { (name.clone(), tool.ready().await) }),
+        )
+        .await;
+        readiness.sort_by(|(a, _), (b, _)| a.cmp(b));
+        readiness
+    }
+
+    /// Annotations of the tool `toolname`, if it exists
+    pub fn annotations(&self, toolname: &str) -> Option<ToolAnnotations> {
+        self.tools.get(toolname).map(ToolType::annotations)
     }
 
     pub(crate) fn get(&self, toolname: &str) -> Option<&ToolType> {
         self.tools.get(toolname)
     }
 
-    /// Call a tool with the given name and arguments
+    /// Call a tool with the given name and arguments. The result is shortened if it is over the
+    /// output limit of the tool (see [OutputLimit]).
     pub async fn call(&self, toolname: &str, args: String) -> Result<String, ToolSetError> {
+        self.call_with_extensions(toolname, args, &Extensions::new())
+            .await
+    }
+
+    /// Call a tool with the given name and arguments, and the request-scoped `extensions`
+    /// (see [Tool::call_with_extensions]).
+    #[cfg_attr(feature = "otel", tracing::instrument(
+        target = "rig",
+        name = "execute_tool",
+        skip_all,
+        err,
+        fields(
+            otel.name = %format!("execute_tool {toolname}"),
+            gen_ai.operation.name = "execute_tool",
+            gen_ai.tool.name = %toolname,
+            gen_ai.tool.type = "function"
+        )
+    ))]
+    pub async fn call_with_extensions(
+        &self,
+        toolname: &str,
+        args: String,
+        extensions: &Extensions,
+    ) -> Result<String, ToolSetError> {
         if let Some(tool) = self.tools.get(toolname) {
-            tracing::info!(target: "rig",
-                "Calling tool {toolname} with args:\n{}",
-                serde_json::to_string_pretty(&args).unwrap_or_else(|_| args.clone())
-            );
-            Ok(tool.call(args).await?)
+            self.call_checked(toolname, args, tool.annotations(), |args| {
+                tool.call(args, extensions)
+            })
+            .await
         } else {
             Err(ToolSetError::ToolNotFoundError(toolname.to_string()))
         }
     }
 
+    /// Call `tool`, which is not part of the toolset (e.g.: a tool of a [ToolRegistry]), with
+    /// the approval policy and output limits of the toolset
+    pub(crate) async fn call_external(
+        &self,
+        tool: &dyn ToolDyn,
+        args: String,
+        extensions: &Extensions,
+    ) -> Result<String, ToolSetError> {
+        self.call_checked(&tool.name(), args, tool.annotations(), |args| {
+            tool.call_with_extensions(args, extensions)
+        })
+        .await
+    }
+
+    /// Call a tool with `call`, once approved, and limit its output
+    async fn call_checked<F>(
+        &self,
+        toolname: &str,
+        args: String,
+        annotations: ToolAnnotations,
+        call: impl FnOnce(String) -> F,
+    ) -> Result<String, ToolSetError>
+    where
+        F: Future<Output = Result<String, ToolError>>,
+    {
+        if let Some(policy) = &self.approval_policy {
+            policy.check(toolname, &args, annotations).await?;
+        }
+        tracing::info!(target: "rig",
+            "Calling tool {toolname} with args:\n{}",
+            serde_json::to_string_pretty(&args).unwrap_or_else(|_| args.clone())
+        );
+        let start = Instant::now();
+        let output = call(args).await;
+        if let Some(stats) = &self.stats {
+            stats.record(toolname, output.is_ok(), start.elapsed());
+        }
+        let output = output?;
+        Ok(match self.output_limit(toolname) {
+            Some(limit) => limit.apply(toolname, output).await,
+            None => output,
+        })
+    }
+
     /// Get the documents of all the tools in the toolset
     pub async fn documents(&self) -> Result<Vec<completion::Document>, ToolSetError> {
         let mut docs = Vec::new();
@@ -453,6 +1531,182 @@ impl ToolSet {
     }
 }
 
+/// Consistent version of the tools of a [ToolRegistry]: the tools registered or unregistered
+/// after the snapshot was taken are not part of it. Cloning a snapshot is cheap.
+#[derive(Clone, Default)]
+pub struct ToolSnapshot {
+    version: u64,
+    tools: Arc<HashMap<String, Arc<dyn ToolDyn>>>,
+}
+
+impl ToolSnapshot {
+    /// Version of the registry the snapshot was taken at (incremented by each change)
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn get(&self, toolname: &str) -> Option<&Arc<dyn ToolDyn>> {
+        self.tools.get(toolname)
+    }
+
+    pub fn contains(&self, toolname: &str) -> bool {
+        self.tools.contains_key(toolname)
+    }
+
+    /// Names of the tools, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names = self.tools.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// Definitions of the tools (sorted by name), for the prompt `prompt`
+    pub async fn definitions(&self, prompt: &str) -> Vec<ToolDefinition> {
+        let mut definitions = vec![];
+        for name in self.names() {
+            definitions.push(self.tools[&name].definition(prompt.to_string()).await);
+        }
+        definitions
+    }
+}
+
+impl std::fmt::Debug for ToolSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolSnapshot")
+            .field("version", &self.version)
+            .field("tools", &self.names())
+            .finish()
+    }
+}
+
+#[derive(Default)]
+struct RegistryState {
+    snapshot: ToolSnapshot,
+    /// Names of the tools of the MCP servers (see [ToolRegistry::set_mcp_tools]), by server
+    servers: HashMap<String, Vec<String>>,
+}
+
+/// Registry of tools shared by many agents (see
+/// [AgentBuilder::tool_registry](crate::agent::AgentBuilder::tool_registry)), whose tools can
+/// be registered and unregistered at runtime (e.g.: when an MCP server notifies that its list
+/// of tools changed). Cloning a registry shares it.
+///
+/// Each prompt of an agent uses a [ToolSnapshot] of the registry taken when the prompt starts,
+/// so that the tools don't change in the middle of the prompt.
+///
+/// # Example
+/// ```rust
+/// use mcp_rig::{providers::openai, tool::ToolRegistry};
+///
+/// let registry = ToolRegistry::new();
+/// registry.set_mcp_tools("twitter", client.list_tools(None, None).await?.tools, client.clone());
+///
+/// let agent = openai.agent(openai::GPT_4O).tool_registry(registry.clone()).build();
+///
+/// // On `notifications/tools/list_changed`
+/// registry.set_mcp_tools("twitter", client.list_tools(None, None).await?.tools, client.clone());
+/// ```
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    state: Arc<std::sync::RwLock<RegistryState>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current snapshot of the tools
+    pub fn snapshot(&self) -> ToolSnapshot {
+        self.state
+            .read()
+            .expect("Tool registry lock poisoned")
+            .snapshot
+            .clone()
+    }
+
+    /// Current version of the registry
+    pub fn version(&self) -> u64 {
+        self.snapshot().version
+    }
+
+    /// Register `tool`, replacing the tool of the same name (if any). Returns the new version
+    /// of the registry.
+    pub fn register(&self, tool: impl ToolDyn + 'static) -> u64 {
+        self.update(|tools, _| {
+            tools.insert(tool.name(), Arc::new(tool));
+        })
+    }
+
+    /// Unregister the tool `toolname`. Returns the new version of the registry, if the tool was
+    /// registered.
+    pub fn unregister(&self, toolname: &str) -> Option<u64> {
+        let mut removed = false;
+        let version = self.update(|tools, _| {
+            removed = tools.remove(toolname).is_some();
+        });
+        removed.then_some(version)
+    }
+
+    /// Replace the tools of the MCP server `server` with `tools` (e.g.: listed again after a
+    /// `notifications/tools/list_changed` notification). Returns the new version of the
+    /// registry.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_mcp_tools<T: mcp_core::transport::Transport>(
+        &self,
+        server: &str,
+        tools: Vec<mcp_core::types::Tool>,
+        client: Arc<mcp_core::client::Client<T>>,
+    ) -> u64 {
+        self.update(|registered, servers| {
+            for name in servers.remove(server).unwrap_or_default() {
+                registered.remove(&name);
+            }
+            let names = tools.iter().map(|tool| tool.name.clone()).collect();
+            for tool in tools {
+                registered.insert(
+                    tool.name.clone(),
+                    Arc::new(McpTool::from_mcp_server(tool, client.clone())),
+                );
+            }
+            servers.insert(server.to_string(), names);
+        })
+    }
+
+    /// Apply `change` to a copy of the tools, and publish it as a new snapshot
+    fn update(
+        &self,
+        change: impl FnOnce(&mut HashMap<String, Arc<dyn ToolDyn>>, &mut HashMap<String, Vec<String>>),
+    ) -> u64 {
+        let mut state = self.state.write().expect("Tool registry lock poisoned");
+        let mut tools = (*state.snapshot.tools).clone();
+        change(&mut tools, &mut state.servers);
+        let version = state.snapshot.version + 1;
+        state.snapshot = ToolSnapshot {
+            version,
+            tools: Arc::new(tools),
+        };
+        tracing::info!(target: "rig", "Tool registry updated to version {}", version);
+        version
+    }
+}
+
+impl std::fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolRegistry")
+            .field("snapshot", &self.snapshot())
+            .finish()
+    }
+}
+
 #[derive(Default)]
 pub struct ToolSetBuilder {
     tools: Vec<ToolType>,
@@ -476,6 +1730,231 @@ impl ToolSetBuilder {
                 .into_iter()
                 .map(|tool| (tool.name(), tool))
                 .collect(),
+            ..Default::default()
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::providers::mock::{self, MockResponse};
+
+    #[derive(Deserialize, JsonSchema)]
+    struct AddArgs {
+        /// The first number to add
+        x: i32,
+        y: i32,
+    }
+
+    #[tokio::test]
+    async fn test_from_fn() {
+        let tool = from_fn(
+            "echo",
+            "Echo the arguments",
+            json!({"type": "object"}),
+            |args: serde_json::Value| async move { Ok::<_, std::io::Error>(args) },
+        );
+        let toolset = ToolSet::from_tools(vec![tool]);
+
+        assert!(toolset.contains("echo"));
+        assert_eq!(
+            toolset.call("echo", r#"{"a":1}"#.into()).await.unwrap(),
+            r#"{"a":1}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_from_typed_fn() {
+        let tool = from_typed_fn("add", "Add x and y", |args: AddArgs| async move {
+            Ok::<_, std::convert::Infallible>(args.x + args.y)
+        });
+
+        let definition = Tool::definition(&tool, String::new()).await;
+        assert_eq!(definition.name, "add");
+        assert_eq!(definition.parameters["required"], json!(["x", "y"]));
+        assert_eq!(
+            definition.parameters["properties"]["x"]["description"],
+            "The first number to add"
+        );
+        assert!(definition.parameters.get("$schema").is_none());
+
+        assert_eq!(Tool::call(&tool, AddArgs { x: 1, y: 2 }).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_output_limit() {
+        let output = "abcdefghij".to_string();
+
+        assert_eq!(
+            OutputLimit::truncate(20).apply("t", output.clone()).await,
+            output
+        );
+        assert_eq!(
+            OutputLimit::truncate(4).apply("t", output.clone()).await,
+            "abcd\n[... 6 characters truncated ...]\n"
+        );
+        assert_eq!(
+            OutputLimit::head_tail(5).apply("t", output.clone()).await,
+            "abc\n[... 5 characters truncated ...]\nij"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_output_limit_summarize() {
+        let summarizer = mock::CompletionModel::new()
+            .when_prompt_contains("`fetch`", MockResponse::text("A page about flurbos."))
+            .default_response(MockResponse::error("Overloaded"));
+
+        let limit = OutputLimit::summarize(30, summarizer.agent().build());
+        let page = "flurbo ".repeat(100);
+        assert_eq!(
+            limit.apply("fetch", page.clone()).await,
+            "A page about flurbos."
+        );
+        assert!(summarizer.requests()[0]
+            .prompt_text()
+            .unwrap()
+            .ends_with(&page));
+
+        // Falls back to head+tail truncation when the summarization fails
+        let shortened = limit.apply("other", page).await;
+        assert!(shortened.starts_with("flurbo flurbo f\n[..."));
+        assert!(shortened.contains("[... 670 characters truncated ...]"));
+    }
+
+    #[tokio::test]
+    async fn test_toolset_output_limit() {
+        let tool = from_fn(
+            "echo",
+            "Echo the arguments",
+            json!({"type": "object"}),
+            |args: serde_json::Value| async move { Ok::<_, std::io::Error>(args) },
+        );
+        let mut toolset = ToolSet::from_tools(vec![tool]);
+        toolset.set_output_limit(OutputLimit::truncate(100));
+        toolset.set_tool_output_limit("echo", OutputLimit::truncate(3));
+
+        assert_eq!(
+            toolset.call("echo", r#"{"a":1}"#.into()).await.unwrap(),
+            "{\"a\n[... 4 characters truncated ...]\n"
+        );
+        assert_eq!(toolset.output_limit("other").unwrap().max_chars, 100);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_mcp_call_limit() {
+        use std::{
+            sync::atomic::{AtomicUsize, Ordering},
+            time::Duration,
+        };
+
+        let limit = McpCallLimit::new(2);
+        let in_flight = AtomicUsize::new(0);
+        let max_seen = AtomicUsize::new(0);
+
+        futures::future::join_all((0..6).map(|_| async {
+            let _permit = limit.acquire().await;
+            let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_seen.fetch_max(current, Ordering::SeqCst);
+            futures_timer::Delay::new(Duration::from_millis(5)).await;
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+        }))
+        .await;
+
+        assert_eq!(max_seen.load(Ordering::SeqCst), 2);
+        assert_eq!(limit.max_in_flight(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_approval_policy() {
+        let annotations = ToolAnnotations::from_mcp(&json!({
+            "name": "search",
+            "annotations": { "readOnlyHint": true, "openWorldHint": false }
+        }));
+        assert!(annotations.is_read_only() && !annotations.is_destructive());
+        assert!(!annotations.is_open_world());
+        // Destructive and open-world by default
+        assert!(ToolAnnotations::default().is_destructive());
+
+        let tool = |name: &str, annotations: ToolAnnotations| {
+            from_fn(name, "", json!({}), |_: serde_json::Value| async move {
+                Ok::<_, std::io::Error>("done".to_string())
+            })
+            .with_annotations(annotations)
+        };
+        let mut toolset = ToolSet::default();
+        toolset.add_tool(tool("search", annotations));
+        toolset.add_tool(tool("delete", ToolAnnotations::default()));
+        toolset.add_tool(tool("forbidden", ToolAnnotations::default()));
+
+        let policy: ApprovalPolicy = serde_json::from_value(json!({
+            "read_only": "allow",
+            "destructive": "ask",
+            "tools": { "forbidden": "deny" }
+        }))
+        .unwrap();
+        assert_eq!(
+            policy,
+            ApprovalPolicy::new()
+                .read_only(Approval::Allow)
+                .destructive(Approval::Ask)
+                .tool("forbidden", Approval::Deny)
+        );
+
+        // Asking without an approver denies
+        toolset.set_approval_policy(policy.clone());
+        assert!(toolset.call("search", "{}".to_string()).await.is_ok());
+        assert!(matches!(
+            toolset.call("delete", "{}".to_string()).await,
+            Err(ToolSetError::ApprovalDenied(name)) if name == "delete"
+        ));
+
+        toolset.set_approval_policy(
+            policy.approver(|request| async move { request.args.contains("confirmed") }),
+        );
+        assert!(toolset.call("delete", "{}".to_string()).await.is_err());
+        assert!(toolset
+            .call("delete", r#"{"confirmed":true}"#.to_string())
+            .await
+            .is_ok());
+        assert!(toolset
+            .call("forbidden", r#"{"confirmed":true}"#.to_string())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tool_registry() {
+        let echo = || {
+            from_fn(
+                "echo",
+                "Echo the arguments",
+                json!({"type": "object"}),
+                |args: serde_json::Value| async move { Ok::<_, std::io::Error>(args["text"].clone()) },
+            )
+        };
+        let registry = ToolRegistry::new();
+        assert_eq!(registry.register(echo()), 1);
+
+        // The snapshots are not affected by the later changes
+        let snapshot = registry.snapshot();
+        assert_eq!(registry.unregister("echo"), Some(2));
+        assert_eq!(registry.unregister("echo"), None);
+        assert_eq!(snapshot.version(), 1);
+        assert_eq!(snapshot.names(), vec!["echo"]);
+        assert!(registry.snapshot().is_empty());
+
+        // The agents see the tools registered after they were built
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::tool_call("echo", json!({"text": "Hi"})))
+            .default_response(MockResponse::text("Hello!"));
+        let agent = model.agent().tool_registry(registry.clone()).build();
+        registry.register(echo());
+        assert_eq!(agent.prompt("Say hi").await.unwrap(), "\"Hi\"");
+        assert_eq!(model.requests()[0].tools, vec!["echo"]);
+    }
+}
@@ -0,0 +1,279 @@
+//! Usage statistics of the tools (i.e.: their success rates and latencies), fed back into the
+//! tools sent to the model, so that the agents learn to prefer the tools that actually work.
+//!
+//! The [ToolStats] of an agent (see
+//! [AgentBuilder::tool_stats](crate::agent::AgentBuilder::tool_stats)) record each call of its
+//! tools. Once a tool has been called [ToolStats::min_calls] times, its statistics can:
+//! - be added as a compact reliability hint to its description (see
+//!   [ToolStats::reliability_hints]), e.g.: `(Reliability: 60% of 10 calls succeeded, 1.2s on
+//!   average)`,
+//! - down-rank it in the dynamic selection of the tools, if flaky (see
+//!   [ToolSelection::stats](crate::agent::ToolSelection::stats)).
+//!
+//! The statistics are shared by the clones of a [ToolStats] (e.g.: between several agents
+//! using the same MCP servers).
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{agent::ToolSelection, providers::openai, tool_stats::ToolStats};
+//!
+//! let stats = ToolStats::new().reliability_hints(true);
+//!
+//! let agent = openai.agent(openai::GPT_4O)
+//!     .mcp_tool(search_code, github_client)
+//!     .mcp_tool(search_web, search_client)
+//!     .tool_stats(stats.clone())
+//!     .tool_selection(ToolSelection::new(embedding_model, 5).stats(stats.clone()))
+//!     .build();
+//!
+//! for (toolname, usage) in stats.snapshot() {
+//!     println!("{toolname}: {:.0}% success, {:?}", usage.success_rate() * 100.0, usage.mean_latency());
+//! }
+//! ```
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::completion::ToolDefinition;
+
+/// Default number of calls of a tool before its statistics are used.
+const DEFAULT_MIN_CALLS: u64 = 5;
+
+/// Usage of a tool.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ToolUsage {
+    /// Number of calls of the tool
+    pub calls: u64,
+    /// Number of failed calls of the tool
+    pub failures: u64,
+    /// Total duration of the calls
+    pub total_latency: Duration,
+    /// Duration of the slowest call
+    pub max_latency: Duration,
+}
+
+impl ToolUsage {
+    /// Share of the calls which succeeded (1 if the tool was never called)
+    pub fn success_rate(&self) -> f64 {
+        if self.calls == 0 {
+            1.0
+        } else {
+            (self.calls - self.failures) as f64 / self.calls as f64
+        }
+    }
+
+    /// Mean duration of the calls
+    pub fn mean_latency(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::ZERO
+        } else {
+            self.total_latency / self.calls as u32
+        }
+    }
+}
+
+/// Usage statistics of the tools (see the [module](self) docs).
+#[derive(Clone, Debug)]
+pub struct ToolStats {
+    usages: Arc<Mutex<HashMap<String, ToolUsage>>>,
+    /// Number of calls of a tool before its statistics are used
+    min_calls: u64,
+    /// Whether to add the reliability hints to the tool descriptions
+    hints: bool,
+}
+
+impl Default for ToolStats {
+    fn default() -> Self {
+        Self {
+            usages: Default::default(),
+            min_calls: DEFAULT_MIN_CALLS,
+            hints: false,
+        }
+    }
+}
+
+impl ToolStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only use the statistics of the tools called at least `min_calls` times (5 by default),
+    /// so that a single failure doesn't discredit a tool
+    pub fn min_calls(mut self, min_calls: u64) -> Self {
+        self.min_calls = min_calls;
+        self
+    }
+
+    /// Add a reliability hint to the descriptions of the tools sent to the model (disabled by
+    /// default)
+    pub fn reliability_hints(mut self, hints: bool) -> Self {
+        self.hints = hints;
+        self
+    }
+
+    /// Record a call of the tool `toolname`, which took `latency`
+    pub fn record(&self, toolname: &str, success: bool, latency: Duration) {
+        let mut usages = self.usages.lock().expect("Tool stats lock poisoned");
+        let usage = usages.entry(toolname.to_string()).or_default();
+        usage.calls += 1;
+        if !success {
+            usage.failures += 1;
+        }
+        usage.total_latency += latency;
+        usage.max_latency = usage.max_latency.max(latency);
+    }
+
+    /// Usage of the tool `toolname`, if it was called
+    pub fn usage(&self, toolname: &str) -> Option<ToolUsage> {
+        self.usages
+            .lock()
+            .expect("Tool stats lock poisoned")
+            .get(toolname)
+            .cloned()
+    }
+
+    /// Usages of all the called tools, by name
+    pub fn snapshot(&self) -> Vec<(String, ToolUsage)> {
+        let mut usages = self
+            .usages
+            .lock()
+            .expect("Tool stats lock poisoned")
+            .iter()
+            .map(|(toolname, usage)| (toolname.clone(), usage.clone()))
+            .collect::<Vec<_>>();
+        usages.sort_by(|(a, _), (b, _)| a.cmp(b));
+        usages
+    }
+
+    /// Forget the usages of the tools (e.g.: after fixing an MCP server)
+    pub fn reset(&self) {
+        self.usages
+            .lock()
+            .expect("Tool stats lock poisoned")
+            .clear();
+    }
+
+    /// Usage of the tool `toolname`, if it was called at least `min_calls` times
+    fn significant_usage(&self, toolname: &str) -> Option<ToolUsage> {
+        self.usage(toolname)
+            .filter(|usage| usage.calls >= self.min_calls)
+    }
+
+    /// Success rate of the tool `toolname`, or 1 if it wasn't called at least `min_calls` times
+    pub fn reliability(&self, toolname: &str) -> f64 {
+        self.significant_usage(toolname)
+            .map(|usage| usage.success_rate())
+            .unwrap_or(1.0)
+    }
+
+    /// Reliability hint of the tool `toolname` (e.g.: `Reliability: 60% of 10 calls succeeded,
+    /// 1.2s on average`), if it was called at least `min_calls` times
+    pub fn hint(&self, toolname: &str) -> Option<String> {
+        self.significant_usage(toolname).map(|usage| {
+            format!(
+                "Reliability: {:.0}% of {} calls succeeded, {:.1}s on average",
+                usage.success_rate() * 100.0,
+                usage.calls,
+                usage.mean_latency().as_secs_f64()
+            )
+        })
+    }
+
+    /// `definitions` with the reliability hints of their tools appended to their descriptions,
+    /// if enabled
+    pub fn annotate(&self, definitions: Vec<ToolDefinition>) -> Vec<ToolDefinition> {
+        if !self.hints {
+            return definitions;
+        }
+        definitions
+            .into_iter()
+            .map(|mut definition| {
+                if let Some(hint) = self.hint(&definition.name) {
+                    definition.description = format!("{} ({hint})", definition.description);
+                }
+                definition
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        agent::ToolSelection,
+        completion::Prompt,
+        providers::mock::{self, MockResponse},
+        tool::{self, ToolErrorPolicy},
+    };
+
+    fn definition(name: &str, description: &str) -> ToolDefinition {
+        ToolDefinition {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters: json!({"type": "object"}),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_stats() {
+        let stats = ToolStats::new().min_calls(2).reliability_hints(true);
+        let flaky = tool::from_fn(
+            "flaky",
+            "Search the web",
+            json!({"type": "object"}),
+            |args: serde_json::Value| async move {
+                if args["retry"].as_bool().unwrap_or(false) {
+                    Ok("Found it".to_string())
+                } else {
+                    Err(std::io::Error::other("Service unavailable"))
+                }
+            },
+        );
+        let model = mock::CompletionModel::new()
+            .push(MockResponse::tool_call("flaky", json!({})))
+            .push(MockResponse::tool_call("flaky", json!({"retry": true})));
+        let agent = model
+            .agent()
+            .tool(flaky)
+            .tool_error_policy(ToolErrorPolicy::ReturnToModel)
+            .tool_stats(stats.clone())
+            .build();
+        agent.prompt("Search the web").await.unwrap();
+
+        let usage = stats.usage("flaky").unwrap();
+        assert_eq!((usage.calls, usage.failures), (2, 1));
+        assert_eq!(stats.reliability("flaky"), 0.5);
+        assert_eq!(stats.reliability("unused"), 1.0);
+        let definitions = stats.annotate(vec![
+            definition("flaky", "Search the web"),
+            definition("unused", "Search the news"),
+        ]);
+        assert!(definitions[0]
+            .description
+            .starts_with("Search the web (Reliability: 50% of 2 calls succeeded, "));
+        assert_eq!(definitions[1].description, "Search the news");
+
+        // The flaky tool is down-ranked in the selection of the tools
+        let embedding_model = mock::EmbeddingModel::new(2)
+            .embedding("flaky: Search the web", vec![1.0, 0.0])
+            .embedding("unused: Search the news", vec![0.8, 0.6])
+            .embedding("Latest news on Rust", vec![1.0, 0.0]);
+        let selection = ToolSelection::new(embedding_model, 1).stats(stats);
+        let selected = selection
+            .select(
+                "Latest news on Rust",
+                vec![
+                    definition("flaky", "Search the web"),
+                    definition("unused", "Search the news"),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(selected[0].name, "unused");
+    }
+}
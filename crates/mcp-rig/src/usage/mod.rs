@@ -0,0 +1,409 @@
+//! Token usage and cost accounting of completion and embedding requests.
+//!
+//! Once a [UsageLedger] is installed, the token usage reported by the providers for every
+//! (non-streaming) completion and embedding request is recorded in the ledger as a
+//! [UsageRecord], along with the model, the agent, the session and the tenant that made the
+//! request (taken from the [TraceContext](crate::observability::TraceContext) of the agent) and
+//! its cost
+//! (computed from the [Pricing] of the ledger).
+//!
+//! The records can then be aggregated into a [CostReport] grouped by model, agent, session,
+//! tenant, experiment variant and/or day with [UsageLedger::query], and exported to CSV or JSON.
+//!
+//! # Example
+//! ```rust
+//! use mcp_rig::{
+//!     completion::Prompt,
+//!     observability::TraceContext,
+//!     usage::{GroupBy, Pricing, UsageLedger},
+//! };
+//!
+//! let ledger = UsageLedger::new(
+//!     Pricing::new()
+//!         .model("gpt-4o", 2.5, 10.0)
+//!         .model("gpt-4o-mini", 0.15, 0.6),
+//! );
+//! ledger.clone().install();
+//!
+//! let agent = openai
+//!     .agent("gpt-4o")
+//!     .trace_context(TraceContext::new("support-agent").session_id("conversation-42"))
+//!     .build();
+//! let response = agent.prompt("What is a flurbo?").await?;
+//!
+//! // Cost per agent and per day, most expensive first
+//! let report = ledger
+//!     .query()
+//!     .group_by(GroupBy::Agent)
+//!     .group_by(GroupBy::Day)
+//!     .report();
+//! std::fs::write("costs.csv", report.to_csv())?;
+//! ```
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, RwLock},
+    task::{Context, Poll},
+};
+
+use serde::{Deserialize, Serialize};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::observability::TraceContext;
+
+mod report;
+
+pub use report::{CostReport, CostRow, GroupBy, UsageQuery};
+
+static LEDGER: RwLock<Option<UsageLedger>> = RwLock::new(None);
+
+thread_local! {
+    /// Agent and session of the request being polled on this thread, if any
+    static SCOPE: RefCell<Option<UsageScope>> = const { RefCell::new(None) };
+}
+
+/// Price of a model, in currency units (e.g.: USD) per million tokens.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    /// Cost of a request with the given token usage.
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input_per_million
+            + output_tokens as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Prices of the models, used to compute the cost of the requests.
+///
+/// A model is priced by its exact name or, failing that, by the longest priced name it starts
+/// with (e.g.: `gpt-4o-2024-08-06` is priced as `gpt-4o`).
+#[derive(Clone, Debug, Default)]
+pub struct Pricing {
+    models: HashMap<String, ModelPrice>,
+}
+
+impl Pricing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the price of `model`, in currency units per million input and output tokens.
+    pub fn model(mut self, model: &str, input_per_million: f64, output_per_million: f64) -> Self {
+        self.models.insert(
+            model.to_string(),
+            ModelPrice {
+                input_per_million,
+                output_per_million,
+            },
+        );
+        self
+    }
+
+    /// Price of `model`, if it is priced.
+    pub fn price(&self, model: &str) -> Option<&ModelPrice> {
+        self.models.get(model).or_else(|| {
+            self.models
+                .iter()
+                .filter(|(name, _)| model.starts_with(name.as_str()))
+                .max_by_key(|(name, _)| name.len())
+                .map(|(_, price)| price)
+        })
+    }
+}
+
+/// Token usage of a completion or embedding request.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Time of the request, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub model: String,
+    /// Name of the agent that made the request, if any
+    pub agent: Option<String>,
+    /// ID of the session (i.e.: conversation) of the request, if any
+    pub session_id: Option<String>,
+    /// ID of the tenant of the request, if any (see [Tenant](crate::tenant::Tenant))
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    /// Name of the experiment the request was made in, if any (see
+    /// [Experiment](crate::agent::Experiment))
+    #[serde(default)]
+    pub experiment: Option<String>,
+    /// Variant of the experiment the request was made with, if any
+    #[serde(default)]
+    pub variant: Option<String>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Cost of the request, or `None` if the model is not priced
+    pub cost: Option<f64>,
+}
+
+/// Ledger of the token usage of the requests. Cloning a ledger returns a handle to the same
+/// records. See the [module documentation](self).
+#[derive(Clone, Default)]
+pub struct UsageLedger {
+    records: Arc<Mutex<Vec<UsageRecord>>>,
+    pricing: Arc<Pricing>,
+}
+
+impl UsageLedger {
+    pub fn new(pricing: Pricing) -> Self {
+        Self {
+            records: Arc::new(Mutex::new(vec![])),
+            pricing: Arc::new(pricing),
+        }
+    }
+
+    /// Install the ledger, replacing the previously installed one (if any).
+    pub fn install(self) {
+        *LEDGER.write().expect("Usage ledger lock poisoned") = Some(self);
+    }
+
+    /// Uninstall the installed ledger (if any).
+    pub fn uninstall() {
+        *LEDGER.write().expect("Usage ledger lock poisoned") = None;
+    }
+
+    pub fn pricing(&self) -> &Pricing {
+        &self.pricing
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<UsageRecord>> {
+        self.records.lock().expect("Usage ledger lock poisoned")
+    }
+
+    /// Record the token usage of a request made by `model`. When called from an agent's
+    /// request, the usage is attributed to the agent and its session.
+    pub fn record(&self, model: &str, input_tokens: u64, output_tokens: u64) {
+        let scope = SCOPE
+            .with(|scope| scope.borrow().clone())
+            .unwrap_or_default();
+
+        self.lock().push(UsageRecord {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or_default(),
+            model: model.to_string(),
+            agent: scope.agent,
+            session_id: scope.session_id,
+            tenant_id: scope.tenant_id,
+            experiment: scope.experiment,
+            variant: scope.variant,
+            input_tokens,
+            output_tokens,
+            cost: self
+                .pricing
+                .price(model)
+                .map(|price| price.cost(input_tokens, output_tokens)),
+        });
+    }
+
+    /// Records of the ledger.
+    pub fn records(&self) -> Vec<UsageRecord> {
+        self.lock().clone()
+    }
+
+    /// Remove every record of the ledger.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Query the records of the ledger, e.g.: to build a [CostReport].
+    pub fn query(&self) -> UsageQuery {
+        UsageQuery::new(self.records())
+    }
+}
+
+/// Record the token usage of a request made by `model` in the installed ledger (if any).
+pub(crate) fn record(model: &str, input_tokens: Option<u64>, output_tokens: Option<u64>) {
+    let ledger = LEDGER
+        .read()
+        .expect("Usage ledger lock poisoned")
+        .as_ref()
+        .cloned();
+
+    if let Some(ledger) = ledger {
+        ledger.record(
+            model,
+            input_tokens.unwrap_or_default(),
+            output_tokens.unwrap_or_default(),
+        );
+    }
+}
+
+/// Agent, session, tenant and experiment variant the usage of the requests is attributed to.
+#[derive(Clone, Debug, Default)]
+struct UsageScope {
+    agent: Option<String>,
+    session_id: Option<String>,
+    tenant_id: Option<String>,
+    experiment: Option<String>,
+    variant: Option<String>,
+}
+
+impl UsageScope {
+    /// Scope nested in `outer`: the attributions missing from this scope are inherited
+    fn within(&self, outer: Option<&UsageScope>) -> UsageScope {
+        let Some(outer) = outer else {
+            return self.clone();
+        };
+        UsageScope {
+            agent: self.agent.clone().or_else(|| outer.agent.clone()),
+            session_id: self.session_id.clone().or_else(|| outer.session_id.clone()),
+            tenant_id: self.tenant_id.clone().or_else(|| outer.tenant_id.clone()),
+            experiment: self.experiment.clone().or_else(|| outer.experiment.clone()),
+            variant: self.variant.clone().or_else(|| outer.variant.clone()),
+        }
+    }
+}
+
+/// Future attributing the usage of the requests it makes to an agent and a session.
+pub(crate) struct Scoped<F> {
+    scope: UsageScope,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        // The scope is set while the inner future is polled, since it may be polled
+        // from a different thread each time
+        let previous = SCOPE.with(|scope| {
+            let nested = this.scope.within(scope.borrow().as_ref());
+            scope.replace(Some(nested))
+        });
+        let result = this.future.as_mut().poll(cx);
+        SCOPE.with(|scope| *scope.borrow_mut() = previous);
+        result
+    }
+}
+
+/// Attribute the usage of the requests made by `future` to the agent, session and tenant of
+/// `context`.
+pub(crate) fn scoped<F: Future>(context: &TraceContext, future: F) -> Scoped<F> {
+    Scoped {
+        scope: UsageScope {
+            agent: Some(context.name.clone()),
+            session_id: context.session_id.clone(),
+            tenant_id: context.tenant_id.clone(),
+            ..Default::default()
+        },
+        future: Box::pin(future),
+    }
+}
+
+/// Attribute the usage of the requests made by `future` to the variant `variant` of the
+/// experiment `experiment`, in the session `session_id`.
+pub(crate) fn experiment_scoped<F: Future>(
+    experiment: &str,
+    variant: &str,
+    session_id: &str,
+    future: F,
+) -> Scoped<F> {
+    Scoped {
+        scope: UsageScope {
+            session_id: Some(session_id.to_string()),
+            experiment: Some(experiment.to_string()),
+            variant: Some(variant.to_string()),
+            ..Default::default()
+        },
+        future: Box::pin(future),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pricing() {
+        let pricing = Pricing::new()
+            .model("gpt-4o", 2.5, 10.0)
+            .model("gpt-4o-mini", 0.15, 0.6);
+
+        assert_eq!(pricing.price("gpt-4o").unwrap().input_per_million, 2.5);
+        assert_eq!(
+            pricing
+                .price("gpt-4o-mini-2024-07-18")
+                .unwrap()
+                .input_per_million,
+            0.15
+        );
+        assert_eq!(
+            pricing
+                .price("gpt-4o-2024-08-06")
+                .unwrap()
+                .input_per_million,
+            2.5
+        );
+        assert!(pricing.price("claude-3-5-sonnet").is_none());
+
+        let cost = pricing.price("gpt-4o").unwrap().cost(1_000, 500);
+        assert!((cost - 0.0075).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn test_agent_usage() {
+        use crate::{
+            completion::Prompt,
+            providers::mock::{self, MockResponse},
+        };
+
+        let ledger = UsageLedger::new(Pricing::new().model("mock", 1.0, 2.0));
+        ledger.clone().install();
+
+        let model = mock::CompletionModel::new()
+            .default_response(MockResponse::text("Hi!"))
+            .usage(1_000, 100);
+        let agent = model
+            .agent()
+            .trace_context(TraceContext::new("greeter").session_id("session-1"))
+            .build();
+        agent.prompt("Hello").await.unwrap();
+        agent.prompt("Hello again").await.unwrap();
+        UsageLedger::uninstall();
+
+        let report = ledger
+            .query()
+            .agent("greeter")
+            .group_by(GroupBy::Session)
+            .report();
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.rows[0].group, vec![Some("session-1".to_string())]);
+        assert_eq!(report.rows[0].requests, 2);
+        assert_eq!(report.rows[0].input_tokens, 2_000);
+        assert!((report.rows[0].cost - 0.0024).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn test_scoped_record() {
+        let ledger = UsageLedger::new(Pricing::new().model("gpt-4o", 2.5, 10.0));
+
+        ledger.record("gpt-4o", 100, 10);
+        scoped(
+            &TraceContext::new("support").session_id("session-1"),
+            async {
+                futures_timer::Delay::new(std::time::Duration::from_millis(1)).await;
+                ledger.record("gpt-4o-mini", 100, 10);
+            },
+        )
+        .await;
+
+        let records = ledger.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].agent, None);
+        assert_eq!(records[0].cost, Some(0.00035));
+        assert_eq!(records[1].agent.as_deref(), Some("support"));
+        assert_eq!(records[1].session_id.as_deref(), Some("session-1"));
+        assert_eq!(records[1].cost, Some(0.00035));
+    }
+}
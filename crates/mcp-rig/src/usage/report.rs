@@ -0,0 +1,363 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use super::UsageRecord;
+use crate::observability::rfc3339;
+
+/// Dimension of a [CostReport].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    Model,
+    Agent,
+    Session,
+    Tenant,
+    /// Day (UTC) of the requests, formatted as `YYYY-MM-DD`
+    Day,
+    Experiment,
+    /// Variant of the experiment of the requests
+    Variant,
+}
+
+impl GroupBy {
+    fn name(&self) -> &'static str {
+        match self {
+            GroupBy::Model => "model",
+            GroupBy::Agent => "agent",
+            GroupBy::Session => "session",
+            GroupBy::Tenant => "tenant",
+            GroupBy::Day => "day",
+            GroupBy::Experiment => "experiment",
+            GroupBy::Variant => "variant",
+        }
+    }
+
+    fn key(&self, record: &UsageRecord) -> Option<String> {
+        match self {
+            GroupBy::Model => Some(record.model.clone()),
+            GroupBy::Agent => record.agent.clone(),
+            GroupBy::Session => record.session_id.clone(),
+            GroupBy::Tenant => record.tenant_id.clone(),
+            GroupBy::Day => Some(day(record.timestamp_ms)),
+            GroupBy::Experiment => record.experiment.clone(),
+            GroupBy::Variant => record.variant.clone(),
+        }
+    }
+}
+
+/// Day (UTC) of a timestamp in milliseconds since the Unix epoch.
+fn day(timestamp_ms: u64) -> String {
+    rfc3339(UNIX_EPOCH + Duration::from_millis(timestamp_ms))[..10].to_string()
+}
+
+/// Query of the records of a [UsageLedger](super::UsageLedger): filters, and dimensions of
+/// the [CostReport].
+pub struct UsageQuery {
+    records: Vec<UsageRecord>,
+    group_by: Vec<GroupBy>,
+}
+
+impl UsageQuery {
+    pub(crate) fn new(records: Vec<UsageRecord>) -> Self {
+        Self {
+            records,
+            group_by: vec![],
+        }
+    }
+
+    fn filter(mut self, predicate: impl Fn(&UsageRecord) -> bool) -> Self {
+        self.records.retain(predicate);
+        self
+    }
+
+    /// Only keep the requests made by `model`.
+    pub fn model(self, model: &str) -> Self {
+        self.filter(|record| record.model == model)
+    }
+
+    /// Only keep the requests made by the agent named `agent`.
+    pub fn agent(self, agent: &str) -> Self {
+        self.filter(|record| record.agent.as_deref() == Some(agent))
+    }
+
+    /// Only keep the requests of the session `session_id`.
+    pub fn session(self, session_id: &str) -> Self {
+        self.filter(|record| record.session_id.as_deref() == Some(session_id))
+    }
+
+    /// Only keep the requests of the tenant `tenant_id`.
+    pub fn tenant(self, tenant_id: &str) -> Self {
+        self.filter(|record| record.tenant_id.as_deref() == Some(tenant_id))
+    }
+
+    /// Only keep the requests made in the experiment `experiment`.
+    pub fn experiment(self, experiment: &str) -> Self {
+        self.filter(|record| record.experiment.as_deref() == Some(experiment))
+    }
+
+    /// Only keep the requests made at or after `time`.
+    pub fn since(self, time: SystemTime) -> Self {
+        let timestamp_ms = timestamp_ms(time);
+        self.filter(|record| record.timestamp_ms >= timestamp_ms)
+    }
+
+    /// Only keep the requests made before `time`.
+    pub fn until(self, time: SystemTime) -> Self {
+        let timestamp_ms = timestamp_ms(time);
+        self.filter(|record| record.timestamp_ms < timestamp_ms)
+    }
+
+    /// Group the report by `group_by` (in addition to the previous dimensions, if any).
+    pub fn group_by(mut self, group_by: GroupBy) -> Self {
+        if !self.group_by.contains(&group_by) {
+            self.group_by.push(group_by);
+        }
+        self
+    }
+
+    /// Records matching the filters of the query.
+    pub fn records(&self) -> &[UsageRecord] {
+        &self.records
+    }
+
+    /// Aggregate the records matching the filters into a report.
+    pub fn report(&self) -> CostReport {
+        let mut rows: BTreeMap<Vec<Option<String>>, CostRow> = BTreeMap::new();
+        for record in &self.records {
+            let group = self
+                .group_by
+                .iter()
+                .map(|group_by| group_by.key(record))
+                .collect::<Vec<_>>();
+            rows.entry(group.clone())
+                .or_insert_with(|| CostRow::new(group))
+                .add(record);
+        }
+
+        let mut rows = rows.into_values().collect::<Vec<_>>();
+        // Most expensive first
+        rows.sort_by(|a, b| b.cost.total_cmp(&a.cost));
+
+        CostReport {
+            group_by: self.group_by.clone(),
+            rows,
+        }
+    }
+}
+
+fn timestamp_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Aggregated usage of a group of requests.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CostRow {
+    /// Values of the dimensions of the report for this group, in the order of
+    /// [CostReport::group_by] (`None` if the requests have no agent, session, tenant or
+    /// experiment)
+    pub group: Vec<Option<String>>,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Total cost of the priced requests
+    pub cost: f64,
+    /// Number of requests made by models without a price (not included in the cost)
+    pub unpriced_requests: u64,
+}
+
+impl CostRow {
+    fn new(group: Vec<Option<String>>) -> Self {
+        Self {
+            group,
+            requests: 0,
+            input_tokens: 0,
+            output_tokens: 0,
+            cost: 0.0,
+            unpriced_requests: 0,
+        }
+    }
+
+    fn add(&mut self, record: &UsageRecord) {
+        self.requests += 1;
+        self.input_tokens += record.input_tokens;
+        self.output_tokens += record.output_tokens;
+        match record.cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced_requests += 1,
+        }
+    }
+}
+
+/// Cost report: usage of the requests aggregated by the dimensions of the report.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CostReport {
+    /// Dimensions of the report
+    pub group_by: Vec<GroupBy>,
+    /// Rows of the report, by decreasing cost
+    pub rows: Vec<CostRow>,
+}
+
+impl CostReport {
+    /// Total usage of the requests of the report.
+    pub fn total(&self) -> CostRow {
+        let mut total = CostRow::new(vec![]);
+        for row in &self.rows {
+            total.requests += row.requests;
+            total.input_tokens += row.input_tokens;
+            total.output_tokens += row.output_tokens;
+            total.cost += row.cost;
+            total.unpriced_requests += row.unpriced_requests;
+        }
+        total
+    }
+
+    /// Export the report as CSV, with one column per dimension followed by the
+    /// `requests`, `input_tokens`, `output_tokens`, `cost` and `unpriced_requests` columns.
+    pub fn to_csv(&self) -> String {
+        let header = self
+            .group_by
+            .iter()
+            .map(|group_by| group_by.name())
+            .chain([
+                "requests",
+                "input_tokens",
+                "output_tokens",
+                "cost",
+                "unpriced_requests",
+            ])
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let mut csv = header + "\n";
+        for row in &self.rows {
+            let line = row
+                .group
+                .iter()
+                .map(|value| csv_field(value.as_deref().unwrap_or_default()))
+                .chain([
+                    row.requests.to_string(),
+                    row.input_tokens.to_string(),
+                    row.output_tokens.to_string(),
+                    row.cost.to_string(),
+                    row.unpriced_requests.to_string(),
+                ])
+                .collect::<Vec<_>>()
+                .join(",");
+            csv.push_str(&line);
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Export the report as JSON: an array of objects with one field per dimension followed
+    /// by the aggregated usage.
+    pub fn to_json(&self) -> serde_json::Value {
+        self.rows
+            .iter()
+            .map(|row| {
+                let mut object = serde_json::Map::new();
+                for (group_by, value) in self.group_by.iter().zip(&row.group) {
+                    object.insert(group_by.name().to_string(), value.clone().into());
+                }
+                object.insert("requests".into(), row.requests.into());
+                object.insert("input_tokens".into(), row.input_tokens.into());
+                object.insert("output_tokens".into(), row.output_tokens.into());
+                object.insert("cost".into(), row.cost.into());
+                object.insert("unpriced_requests".into(), row.unpriced_requests.into());
+                serde_json::Value::Object(object)
+            })
+            .collect()
+    }
+}
+
+/// Quote a CSV field if needed.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn record(model: &str, agent: Option<&str>, day: u64, cost: Option<f64>) -> UsageRecord {
+        UsageRecord {
+            timestamp_ms: day * 86_400_000,
+            model: model.to_string(),
+            agent: agent.map(String::from),
+            session_id: None,
+            tenant_id: None,
+            experiment: None,
+            variant: None,
+            input_tokens: 100,
+            output_tokens: 10,
+            cost,
+        }
+    }
+
+    #[test]
+    fn test_cost_report() {
+        let records = vec![
+            record("gpt-4o", Some("support"), 0, Some(1.0)),
+            record("gpt-4o", Some("support"), 1, Some(2.0)),
+            record("gpt-4o-mini", Some("triage, v2"), 1, Some(0.5)),
+            record("llama-3", None, 1, None),
+        ];
+
+        let report = UsageQuery::new(records.clone())
+            .group_by(GroupBy::Agent)
+            .report();
+        assert_eq!(report.rows.len(), 3);
+        assert_eq!(report.rows[0].group, vec![Some("support".to_string())]);
+        assert_eq!(report.rows[0].requests, 2);
+        assert_eq!(report.rows[0].cost, 3.0);
+        assert_eq!(report.rows[2].group, vec![None]);
+        assert_eq!(report.rows[2].unpriced_requests, 1);
+
+        let total = report.total();
+        assert_eq!(total.requests, 4);
+        assert_eq!(total.input_tokens, 400);
+        assert_eq!(total.cost, 3.5);
+
+        assert_eq!(
+            report.to_csv(),
+            "agent,requests,input_tokens,output_tokens,cost,unpriced_requests\n\
+            support,2,200,20,3,0\n\
+            \"triage, v2\",1,100,10,0.5,0\n\
+            ,1,100,10,0,1\n"
+        );
+        assert_eq!(
+            report.to_json()[0],
+            json!({
+                "agent": "support",
+                "requests": 2,
+                "input_tokens": 200,
+                "output_tokens": 20,
+                "cost": 3.0,
+                "unpriced_requests": 0,
+            })
+        );
+
+        let report = UsageQuery::new(records)
+            .model("gpt-4o")
+            .since(UNIX_EPOCH + Duration::from_secs(86_400))
+            .group_by(GroupBy::Model)
+            .group_by(GroupBy::Day)
+            .report();
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(
+            report.rows[0].group,
+            vec![Some("gpt-4o".to_string()), Some("1970-01-02".to_string())]
+        );
+        assert_eq!(report.rows[0].cost, 2.0);
+    }
+}
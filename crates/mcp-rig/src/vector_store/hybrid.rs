@@ -0,0 +1,92 @@
+//! The module defines the [HybridVectorStoreIndex] trait, implemented by vector store backends
+//! that support hybrid retrieval, i.e.: searching over both dense and sparse embeddings of the
+//! same documents (see [HybridEmbedding](crate::embeddings::sparse::HybridEmbedding)).
+//!
+//! Backends that do not fuse the dense and sparse rankings natively can use the
+//! [reciprocal_rank_fusion] helper, as does the
+//! [InMemoryHybridIndex](super::in_memory_store::InMemoryHybridIndex).
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use super::VectorStoreError;
+use crate::embeddings::sparse::HybridEmbedding;
+
+/// Default value of the `k` constant of [reciprocal_rank_fusion], as suggested in the original paper.
+pub const RRF_K: f64 = 60.0;
+
+/// Trait for vector store indexes that support hybrid (dense + sparse) retrieval
+pub trait HybridVectorStoreIndex: Send + Sync {
+    /// Insert documents along with their dense and sparse embeddings.
+    fn insert_documents<T: serde::Serialize + Send + Sync>(
+        &self,
+        documents: Vec<(String, T, HybridEmbedding)>,
+    ) -> impl std::future::Future<Output = Result<(), VectorStoreError>> + Send;
+
+    /// Get the top n documents based on both the dense and sparse similarity to the given query.
+    /// The result is a list of tuples of the form (score, id, document)
+    fn top_n_hybrid<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> impl std::future::Future<Output = Result<Vec<(f64, String, T)>, VectorStoreError>> + Send;
+
+    /// Same as `top_n_hybrid` but returns the document ids only.
+    fn top_n_ids_hybrid(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> impl std::future::Future<Output = Result<Vec<(f64, String)>, VectorStoreError>> + Send;
+}
+
+/// Fuse multiple rankings of document ids (e.g.: a dense and a sparse ranking) using
+/// reciprocal rank fusion: each document gets a score of `sum(1 / (k + rank))` over the
+/// rankings it appears in (with `rank` starting at 1). The scores of the input rankings are
+/// only used to order them.
+///
+/// Returns the top `n` documents ordered by decreasing fused score.
+pub fn reciprocal_rank_fusion(
+    rankings: impl IntoIterator<Item = Vec<(f64, String)>>,
+    k: f64,
+    n: usize,
+) -> Vec<(f64, String)> {
+    let mut scores: HashMap<String, f64> = HashMap::new();
+
+    for mut ranking in rankings {
+        ranking.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ranking.into_iter().enumerate().for_each(|(rank, (_, id))| {
+            *scores.entry(id).or_default() += 1.0 / (k + rank as f64 + 1.0);
+        });
+    }
+
+    let mut fused = scores
+        .into_iter()
+        .map(|(id, score)| (score, id))
+        .collect::<Vec<_>>();
+    fused.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    fused.truncate(n);
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let dense = vec![
+            (0.9, "doc1".to_string()),
+            (0.8, "doc2".to_string()),
+            (0.1, "doc3".to_string()),
+        ];
+        let sparse = vec![(12.0, "doc3".to_string()), (7.5, "doc2".to_string())];
+
+        let fused = reciprocal_rank_fusion(vec![dense, sparse], RRF_K, 2);
+
+        assert_eq!(
+            fused.iter().map(|(_, id)| id.as_str()).collect::<Vec<_>>(),
+            vec!["doc3", "doc2"]
+        );
+        assert!((fused[0].0 - (1.0 / 63.0 + 1.0 / 61.0)).abs() < 1e-12);
+    }
+}
@@ -1,33 +1,54 @@
-//! In-memory implementation of a vector store.
+//! In-memory implementation of a vector store, and of a hybrid (dense + sparse) index.
 use std::{
     cmp::Reverse,
     collections::{BinaryHeap, HashMap},
+    sync::RwLock,
 };
 
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 
-use super::{VectorStoreError, VectorStoreIndex};
+use super::{
+    hybrid::{reciprocal_rank_fusion, HybridVectorStoreIndex, RRF_K},
+    VectorStoreError, VectorStoreIndex,
+};
 use crate::{
-    embeddings::{distance::VectorDistance, Embedding, EmbeddingModel},
+    embeddings::{
+        distance::VectorDistance,
+        quantization::{Element, EmbeddingVector, QuantizedEmbedding},
+        sparse::{HybridEmbedding, HybridEmbeddingModel, SparseEmbeddingModel},
+        Embedding, EmbeddingModel,
+    },
     OneOrMany,
 };
 
 /// [InMemoryVectorStore] is a simple in-memory vector store that stores embeddings
 /// in-memory using a HashMap.
-#[derive(Clone, Default)]
-pub struct InMemoryVectorStore<D: Serialize> {
+///
+/// By default, embeddings are stored as [Embedding] (i.e.: vectors of `f64`). Use
+/// [InMemoryVectorStore::quantize] to convert the store to a more compact representation
+/// (see [QuantizedEmbedding]).
+#[derive(Clone)]
+pub struct InMemoryVectorStore<D: Serialize, V = Embedding> {
     /// The embeddings are stored in a HashMap.
     /// Hashmap key is the document id.
     /// Hashmap value is a tuple of the serializable document and its corresponding embeddings.
-    embeddings: HashMap<String, (D, OneOrMany<Embedding>)>,
+    embeddings: HashMap<String, (D, OneOrMany<V>)>,
+}
+
+impl<D: Serialize, V> Default for InMemoryVectorStore<D, V> {
+    fn default() -> Self {
+        Self {
+            embeddings: HashMap::new(),
+        }
+    }
 }
 
-impl<D: Serialize + Eq> InMemoryVectorStore<D> {
+impl<D: Serialize + Eq, V: EmbeddingVector> InMemoryVectorStore<D, V> {
     /// Create a new [InMemoryVectorStore] from documents and their corresponding embeddings.
     /// Ids are automatically generated have will have the form `"doc{n}"` where `n`
     /// is the index of the document.
-    pub fn from_documents(documents: impl IntoIterator<Item = (D, OneOrMany<Embedding>)>) -> Self {
+    pub fn from_documents(documents: impl IntoIterator<Item = (D, OneOrMany<V>)>) -> Self {
         let mut store = HashMap::new();
         documents
             .into_iter()
@@ -41,7 +62,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
 
     /// Create a new [InMemoryVectorStore] from documents and and their corresponding embeddings with ids.
     pub fn from_documents_with_ids(
-        documents: impl IntoIterator<Item = (impl ToString, D, OneOrMany<Embedding>)>,
+        documents: impl IntoIterator<Item = (impl ToString, D, OneOrMany<V>)>,
     ) -> Self {
         let mut store = HashMap::new();
         documents.into_iter().for_each(|(i, doc, embeddings)| {
@@ -54,7 +75,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
     /// Create a new [InMemoryVectorStore] from documents and their corresponding embeddings.
     /// Document ids are generated using the provided function.
     pub fn from_documents_with_id_f(
-        documents: impl IntoIterator<Item = (D, OneOrMany<Embedding>)>,
+        documents: impl IntoIterator<Item = (D, OneOrMany<V>)>,
         f: fn(&D) -> String,
     ) -> Self {
         let mut store = HashMap::new();
@@ -77,8 +98,8 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
                 .iter()
                 .map(|embedding| {
                     (
-                        OrderedFloat(embedding.cosine_similarity(prompt_embedding, false)),
-                        &embedding.document,
+                        OrderedFloat(embedding.similarity(prompt_embedding)),
+                        embedding.document(),
                     )
                 })
                 .max_by(|a, b| a.0.cmp(&b.0))
@@ -107,10 +128,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
     /// Add documents and their corresponding embeddings to the store.
     /// Ids are automatically generated have will have the form `"doc{n}"` where `n`
     /// is the index of the document.
-    pub fn add_documents(
-        &mut self,
-        documents: impl IntoIterator<Item = (D, OneOrMany<Embedding>)>,
-    ) {
+    pub fn add_documents(&mut self, documents: impl IntoIterator<Item = (D, OneOrMany<V>)>) {
         let current_index = self.embeddings.len();
         documents
             .into_iter()
@@ -124,7 +142,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
     /// Add documents and their corresponding embeddings to the store with ids.
     pub fn add_documents_with_ids(
         &mut self,
-        documents: impl IntoIterator<Item = (impl ToString, D, OneOrMany<Embedding>)>,
+        documents: impl IntoIterator<Item = (impl ToString, D, OneOrMany<V>)>,
     ) {
         documents.into_iter().for_each(|(id, doc, embeddings)| {
             self.embeddings.insert(id.to_string(), (doc, embeddings));
@@ -135,7 +153,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
     /// Document ids are generated using the provided function.
     pub fn add_documents_with_id_f(
         &mut self,
-        documents: Vec<(D, OneOrMany<Embedding>)>,
+        documents: Vec<(D, OneOrMany<V>)>,
         f: fn(&D) -> String,
     ) {
         for (doc, embeddings) in documents {
@@ -159,7 +177,7 @@ impl<D: Serialize + Eq> InMemoryVectorStore<D> {
 
 /// RankingItem(distance, document_id, serializable document, embeddings document)
 #[derive(Eq, PartialEq)]
-struct RankingItem<'a, D: Serialize>(OrderedFloat<f64>, &'a String, &'a D, &'a String);
+struct RankingItem<'a, D: Serialize>(OrderedFloat<f64>, &'a String, &'a D, &'a str);
 
 impl<D: Serialize + Eq> Ord for RankingItem<'_, D> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
@@ -176,11 +194,27 @@ impl<D: Serialize + Eq> PartialOrd for RankingItem<'_, D> {
 type EmbeddingRanking<'a, D> = BinaryHeap<Reverse<RankingItem<'a, D>>>;
 
 impl<D: Serialize> InMemoryVectorStore<D> {
-    pub fn index<M: EmbeddingModel>(self, model: M) -> InMemoryVectorIndex<M, D> {
+    /// Convert the store to one that keeps its embeddings as [QuantizedEmbedding] with
+    /// `E` as element type (e.g.: `f32`, [f16](crate::embeddings::quantization::f16) or `i8`).
+    pub fn quantize<E: Element>(self) -> InMemoryVectorStore<D, QuantizedEmbedding<E>> {
+        InMemoryVectorStore {
+            embeddings: self
+                .embeddings
+                .into_iter()
+                .map(|(id, (doc, embeddings))| {
+                    (id, (doc, embeddings.map(|embedding| embedding.quantize())))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<D: Serialize, V> InMemoryVectorStore<D, V> {
+    pub fn index<M: EmbeddingModel>(self, model: M) -> InMemoryVectorIndex<M, D, V> {
         InMemoryVectorIndex::new(model, self)
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (&String, &(D, OneOrMany<Embedding>))> {
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &(D, OneOrMany<V>))> {
         self.embeddings.iter()
     }
 
@@ -193,17 +227,17 @@ impl<D: Serialize> InMemoryVectorStore<D> {
     }
 }
 
-pub struct InMemoryVectorIndex<M: EmbeddingModel, D: Serialize> {
+pub struct InMemoryVectorIndex<M: EmbeddingModel, D: Serialize, V = Embedding> {
     model: M,
-    pub store: InMemoryVectorStore<D>,
+    pub store: InMemoryVectorStore<D, V>,
 }
 
-impl<M: EmbeddingModel, D: Serialize> InMemoryVectorIndex<M, D> {
-    pub fn new(model: M, store: InMemoryVectorStore<D>) -> Self {
+impl<M: EmbeddingModel, D: Serialize, V> InMemoryVectorIndex<M, D, V> {
+    pub fn new(model: M, store: InMemoryVectorStore<D, V>) -> Self {
         Self { model, store }
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (&String, &(D, OneOrMany<Embedding>))> {
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &(D, OneOrMany<V>))> {
         self.store.iter()
     }
 
@@ -216,8 +250,8 @@ impl<M: EmbeddingModel, D: Serialize> InMemoryVectorIndex<M, D> {
     }
 }
 
-impl<M: EmbeddingModel + Sync, D: Serialize + Sync + Send + Eq> VectorStoreIndex
-    for InMemoryVectorIndex<M, D>
+impl<M: EmbeddingModel + Sync, D: Serialize + Sync + Send + Eq, V: EmbeddingVector> VectorStoreIndex
+    for InMemoryVectorIndex<M, D, V>
 {
     async fn top_n<T: for<'a> Deserialize<'a>>(
         &self,
@@ -259,13 +293,121 @@ impl<M: EmbeddingModel + Sync, D: Serialize + Sync + Send + Eq> VectorStoreIndex
     }
 }
 
+/// In-memory [HybridVectorStoreIndex]: the documents are ranked by the cosine similarity of their
+/// dense embeddings and by the dot product of their sparse embeddings with the ones of the query,
+/// and both rankings are fused with [reciprocal_rank_fusion] (with `k` = [RRF_K]).
+pub struct InMemoryHybridIndex<M: EmbeddingModel, S: SparseEmbeddingModel> {
+    model: HybridEmbeddingModel<M, S>,
+    documents: RwLock<HashMap<String, (serde_json::Value, HybridEmbedding)>>,
+}
+
+impl<M: EmbeddingModel, S: SparseEmbeddingModel> InMemoryHybridIndex<M, S> {
+    /// Create a new empty index, embedding the queries with `model`.
+    pub fn new(model: HybridEmbeddingModel<M, S>) -> Self {
+        Self {
+            model,
+            documents: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.read().expect("Index lock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Ids of the top `n` documents for the query, with their fused score.
+    async fn hybrid_search(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        let query = self.model.embed_text(query).await?;
+
+        let documents = self.documents.read().expect("Index lock poisoned");
+        let (dense, sparse) = documents
+            .iter()
+            .map(|(id, (_, embedding))| {
+                (
+                    (
+                        embedding.dense.cosine_similarity(&query.dense, false),
+                        id.clone(),
+                    ),
+                    (embedding.sparse.dot_product(&query.sparse), id.clone()),
+                )
+            })
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+
+        Ok(reciprocal_rank_fusion([dense, sparse], RRF_K, n))
+    }
+}
+
+impl<M: EmbeddingModel, S: SparseEmbeddingModel> HybridVectorStoreIndex
+    for InMemoryHybridIndex<M, S>
+{
+    async fn insert_documents<T: Serialize + Send + Sync>(
+        &self,
+        documents: Vec<(String, T, HybridEmbedding)>,
+    ) -> Result<(), VectorStoreError> {
+        let documents = documents
+            .into_iter()
+            .map(|(id, document, embedding)| Ok((id, (serde_json::to_value(document)?, embedding))))
+            .collect::<Result<Vec<_>, VectorStoreError>>()?;
+
+        self.documents
+            .write()
+            .expect("Index lock poisoned")
+            .extend(documents);
+        Ok(())
+    }
+
+    async fn top_n_hybrid<T: for<'a> Deserialize<'a> + Send>(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String, T)>, VectorStoreError> {
+        let ranking = self.hybrid_search(query, n).await?;
+
+        let documents = self.documents.read().expect("Index lock poisoned");
+        ranking
+            .into_iter()
+            .filter_map(|(score, id)| {
+                let (document, _) = documents.get(&id)?;
+                Some(
+                    serde_json::from_value(document.clone())
+                        .map(|document| (score, id, document))
+                        .map_err(VectorStoreError::JsonError),
+                )
+            })
+            .collect()
+    }
+
+    async fn top_n_ids_hybrid(
+        &self,
+        query: &str,
+        n: usize,
+    ) -> Result<Vec<(f64, String)>, VectorStoreError> {
+        self.hybrid_search(query, n).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::cmp::Reverse;
 
-    use crate::{embeddings::embedding::Embedding, OneOrMany};
+    use crate::{
+        embeddings::{
+            embedding::Embedding,
+            sparse::{HybridEmbeddingModel, SparseEmbedding, SparseEmbeddingModel},
+            EmbeddingError, EmbeddingModel,
+        },
+        vector_store::hybrid::{HybridVectorStoreIndex, RRF_K},
+        OneOrMany,
+    };
 
-    use super::{InMemoryVectorStore, RankingItem};
+    use super::{InMemoryHybridIndex, InMemoryVectorStore, RankingItem};
 
     #[test]
     fn test_auto_ids() {
@@ -502,4 +644,128 @@ mod tests {
             )]
         )
     }
+
+    #[test]
+    fn test_quantized_store() {
+        let vector_store = InMemoryVectorStore::from_documents_with_ids(vec![
+            (
+                "doc1",
+                "glarb-garb",
+                OneOrMany::one(Embedding {
+                    document: "glarb-garb".to_string(),
+                    vec: vec![0.1, 0.1, 0.5],
+                }),
+            ),
+            (
+                "doc2",
+                "marble-marble",
+                OneOrMany::one(Embedding {
+                    document: "marble-marble".to_string(),
+                    vec: vec![0.7, -0.3, 0.0],
+                }),
+            ),
+        ])
+        .quantize::<i8>();
+
+        let ranking = vector_store.vector_search(
+            &Embedding {
+                document: "glarby-glarble".to_string(),
+                vec: vec![0.0, 0.1, 0.6],
+            },
+            1,
+        );
+
+        let results = ranking
+            .into_iter()
+            .map(|Reverse(RankingItem(distance, id, _, embed_doc))| {
+                (distance.0, id.clone(), embed_doc.to_string())
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1, "doc1");
+        assert_eq!(results[0].2, "glarb-garb");
+        assert!((results[0].0 - 0.9807965956109156).abs() < 1e-2);
+    }
+
+    #[derive(Clone)]
+    struct Dense;
+
+    impl EmbeddingModel for Dense {
+        const MAX_DOCUMENTS: usize = 5;
+
+        fn ndims(&self) -> usize {
+            2
+        }
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<Embedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|document| Embedding {
+                    vec: vec![1.0, document.len() as f64],
+                    document,
+                })
+                .collect())
+        }
+    }
+
+    #[derive(Clone)]
+    struct Sparse;
+
+    impl SparseEmbeddingModel for Sparse {
+        const MAX_DOCUMENTS: usize = 5;
+
+        async fn embed_texts(
+            &self,
+            texts: impl IntoIterator<Item = String> + Send,
+        ) -> Result<Vec<SparseEmbedding>, EmbeddingError> {
+            Ok(texts
+                .into_iter()
+                .map(|document| SparseEmbedding {
+                    indices: document.bytes().map(u32::from).collect(),
+                    values: vec![1.0; document.len()],
+                    document,
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_hybrid_index() {
+        let model = HybridEmbeddingModel::new(Dense, Sparse);
+        let index = InMemoryHybridIndex::new(model.clone());
+        assert!(index.is_empty());
+
+        let documents = model
+            .embed_texts(vec!["x".to_string(), "abc".to_string(), "zzzz".to_string()])
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|embedding| {
+                (
+                    embedding.dense.document.clone(),
+                    embedding.dense.document.to_uppercase(),
+                    embedding,
+                )
+            })
+            .collect();
+        index.insert_documents(documents).await.unwrap();
+        assert_eq!(index.len(), 3);
+
+        // "abc" is ranked first by both the dense and the sparse similarity to "cab"
+        let results = index.top_n_hybrid::<String>("cab", 2).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].1, "abc");
+        assert_eq!(results[0].2, "ABC");
+        assert!((results[0].0 - 2.0 / (RRF_K + 1.0)).abs() < 1e-12);
+
+        let ids = index.top_n_ids_hybrid("cab", 2).await.unwrap();
+        assert_eq!(
+            ids.into_iter().map(|(_, id)| id).collect::<Vec<_>>(),
+            results.into_iter().map(|(_, id, _)| id).collect::<Vec<_>>()
+        );
+    }
 }
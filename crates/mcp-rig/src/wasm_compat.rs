@@ -0,0 +1,37 @@
+//! Compatibility of the futures of rig with wasm32 targets.
+//!
+//! The async traits of rig (e.g.: [CompletionModel](crate::completion::CompletionModel))
+//! require `Send` futures, but the futures of browser resources (e.g.: `reqwest` requests,
+//! which use `fetch`) are not `Send`. Since wasm32 targets are single-threaded, such futures
+//! can safely be wrapped in a [WasmSendFuture], which is what the `rig_derive::wasm_send`
+//! attribute does for async functions.
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Future that is `Send` (and `Sync`) on wasm32 targets, whatever the future it wraps.
+pub(crate) struct WasmSendFuture<F>(Pin<Box<F>>);
+
+impl<F: Future> WasmSendFuture<F> {
+    pub(crate) fn new(future: F) -> Self {
+        Self(Box::pin(future))
+    }
+}
+
+impl<F: Future> Future for WasmSendFuture<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.as_mut().poll(cx)
+    }
+}
+
+// SAFETY: wasm32 targets are single-threaded, so the future is never sent to (nor shared with)
+// another thread.
+#[cfg(target_arch = "wasm32")]
+unsafe impl<F> Send for WasmSendFuture<F> {}
+
+#[cfg(target_arch = "wasm32")]
+unsafe impl<F> Sync for WasmSendFuture<F> {}
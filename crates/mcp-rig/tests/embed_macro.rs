@@ -215,3 +215,50 @@ fn test_multiple_embed_tags() {
         ]
     );
 }
+
+#[test]
+fn test_weighted_embed() {
+    #[derive(Embed)]
+    struct Article {
+        #[allow(dead_code)]
+        id: String,
+        #[embed(weight = 2)]
+        title: String,
+        #[embed]
+        tags: Vec<String>,
+    }
+
+    let article = Article {
+        id: "doc1".to_string(),
+        title: "Rust".to_string(),
+        tags: vec!["systems".to_string(), "programming".to_string()],
+    };
+
+    assert_eq!(
+        embeddings::to_texts(article).unwrap(),
+        vec!["Rust Rust\nsystems programming".to_string()]
+    );
+}
+
+#[test]
+fn test_templated_embed() {
+    #[derive(Embed)]
+    #[embed(template = "Title: {title}\n{body} ({{draft}})")]
+    struct Article {
+        #[allow(dead_code)]
+        id: String,
+        title: String,
+        body: String,
+    }
+
+    let article = Article {
+        id: "doc1".to_string(),
+        title: "Rust".to_string(),
+        body: "A language empowering everyone.".to_string(),
+    };
+
+    assert_eq!(
+        embeddings::to_texts(article).unwrap(),
+        vec!["Title: Rust\nA language empowering everyone. ({draft})".to_string()]
+    );
+}
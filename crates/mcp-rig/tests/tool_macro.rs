@@ -0,0 +1,63 @@
+use mcp_rig::{
+    tool,
+    tool::{Tool, ToolSet},
+};
+use serde_json::json;
+
+#[derive(Debug, thiserror::Error)]
+#[error("Math error")]
+struct MathError;
+
+/// Add x and y together
+#[tool]
+async fn add(
+    /// The first number to add
+    x: i32,
+    /// The second number to add
+    y: i32,
+) -> Result<i32, MathError> {
+    Ok(x + y)
+}
+
+/// Divide x by y
+#[tool(name = "divide_numbers")]
+async fn divide(x: f64, y: f64) -> Result<f64, MathError> {
+    if y == 0.0 {
+        return Err(MathError);
+    }
+    Ok(x / y)
+}
+
+#[tokio::test]
+async fn test_tool_definition() {
+    let definition = Add.definition(String::new()).await;
+
+    assert_eq!(definition.name, "add");
+    assert_eq!(definition.description, "Add x and y together");
+    assert_eq!(definition.parameters["type"], "object");
+    assert_eq!(definition.parameters["required"], json!(["x", "y"]));
+    assert_eq!(
+        definition.parameters["properties"]["x"]["description"],
+        "The first number to add"
+    );
+
+    assert_eq!(
+        Divide.definition(String::new()).await.name,
+        "divide_numbers"
+    );
+}
+
+#[tokio::test]
+async fn test_tool_call() {
+    let toolset = ToolSet::from_tools(vec![Add]);
+    assert_eq!(
+        toolset
+            .call("add", r#"{"x": 1, "y": 2}"#.into())
+            .await
+            .unwrap(),
+        "3"
+    );
+
+    assert!(Divide.call(DivideArgs { x: 1.0, y: 0.0 }).await.is_err());
+    assert_eq!(add(2, 3).await.unwrap(), 5);
+}